@@ -68,7 +68,7 @@ use crossbeam_channel::{Receiver, Sender};
 use lsp_types::{
     notification::{Exit, Initialized},
     request::{Initialize, Shutdown},
-    InitializeParams, InitializeResult, ServerCapabilities,
+    InitializeParams, ServerCapabilities,
 };
 
 pub type Result<T> = ::std::result::Result<T, failure::Error>;
@@ -88,6 +88,18 @@ pub fn run_server(
     receiver: Receiver<RawMessage>,
     sender: Sender<RawMessage>,
     server: impl FnOnce(InitializeParams, &Receiver<RawMessage>, &Sender<RawMessage>) -> Result<()>,
+) -> Result<()> {
+    run_server_with(|_| serde_json::to_value(caps).unwrap(), receiver, sender, server)
+}
+
+/// Like `run_server`, but the capabilities are computed from the raw params of
+/// the `Initialize` request, for the client and server capabilities which
+/// `lsp_types` doesn't know about.
+pub fn run_server_with(
+    caps: impl FnOnce(&serde_json::Value) -> serde_json::Value,
+    receiver: Receiver<RawMessage>,
+    sender: Sender<RawMessage>,
+    server: impl FnOnce(InitializeParams, &Receiver<RawMessage>, &Sender<RawMessage>) -> Result<()>,
 ) -> Result<()> {
     log::info!("lsp server initializes");
     let params = initialize(&receiver, &sender, caps)?;
@@ -119,16 +131,19 @@ pub fn handle_shutdown(req: RawRequest, sender: &Sender<RawMessage>) -> Option<R
 fn initialize(
     receiver: &Receiver<RawMessage>,
     sender: &Sender<RawMessage>,
-    caps: ServerCapabilities,
+    caps: impl FnOnce(&serde_json::Value) -> serde_json::Value,
 ) -> Result<InitializeParams> {
-    let (id, params) = match receiver.recv() {
-        Ok(RawMessage::Request(req)) => match req.cast::<Initialize>() {
-            Err(req) => bail!("expected initialize request, got {:?}", req),
-            Ok(req) => req,
-        },
+    let req = match receiver.recv() {
+        Ok(RawMessage::Request(req)) => req,
         msg => bail!("expected initialize request, got {:?}", msg),
     };
-    let resp = RawResponse::ok::<Initialize>(id, &InitializeResult { capabilities: caps });
+    let caps = caps(&req.params);
+    let (id, params) = match req.cast::<Initialize>() {
+        Err(req) => bail!("expected initialize request, got {:?}", req),
+        Ok(req) => req,
+    };
+    let resp =
+        RawResponse { id, result: Some(serde_json::json!({ "capabilities": caps })), error: None };
     sender.send(RawMessage::Response(resp)).unwrap();
     match receiver.recv() {
         Ok(RawMessage::Notification(n)) => {
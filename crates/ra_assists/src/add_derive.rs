@@ -10,7 +10,7 @@ use crate::{AssistCtx, Assist, AssistId};
 pub(crate) fn add_derive(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
     let nominal = ctx.node_at_offset::<ast::NominalDef>()?;
     let node_start = derive_insertion_offset(nominal)?;
-    ctx.add_action(AssistId("add_derive"), "add `#[derive]`", |edit| {
+    ctx.add_action(AssistId("add_derive"), "add `#[derive]`", nominal.syntax().range(), |edit| {
         let derive_attr = nominal
             .attrs()
             .filter_map(|x| x.as_call())
@@ -24,7 +24,6 @@ pub(crate) fn add_derive(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist>
             }
             Some(tt) => tt.syntax().range().end() - TextUnit::of_char(')'),
         };
-        edit.target(nominal.syntax().range());
         edit.set_cursor(offset)
     });
 
@@ -35,10 +35,10 @@ pub(crate) fn add_explicit_type(mut ctx: AssistCtx<impl HirDatabase>) -> Option<
     if is_unknown(&ty) {
         return None;
     }
+    let module = hir::source_binder::module_from_child_node(db, ctx.frange.file_id, stmt.syntax())?;
 
-    ctx.add_action(AssistId("add_explicit_type"), "add explicit type", |edit| {
-        edit.target(pat_range);
-        edit.insert(name_range.end(), format!(": {}", ty.display(db)));
+    ctx.add_action(AssistId("add_explicit_type"), "add explicit type", pat_range, |edit| {
+        edit.insert(name_range.end(), format!(": {}", ty.display_source_code(db, module)));
     });
     ctx.build()
 }
@@ -86,4 +86,27 @@ mod tests {
     fn add_explicit_type_not_applicable_if_specified_ty_is_tuple() {
         check_assist_not_applicable(add_explicit_type, "fn f() { let a<|>: (i32, i32) = (3, 4); }");
     }
+
+    #[test]
+    fn add_explicit_type_uses_name_in_scope() {
+        check_assist(
+            add_explicit_type,
+            "mod m { pub struct S; } use m::S; fn f() { let a<|> = S; }",
+            "mod m { pub struct S; } use m::S; fn f() { let a<|>: S = S; }",
+        );
+    }
+
+    #[test]
+    fn add_explicit_type_qualifies_paths() {
+        check_assist(
+            add_explicit_type,
+            "mod m { pub struct S; pub fn s() -> S { S } } fn f() { let a<|> = m::s(); }",
+            "mod m { pub struct S; pub fn s() -> S { S } } fn f() { let a<|>: m::S = m::s(); }",
+        );
+        check_assist(
+            add_explicit_type,
+            "mod m { pub struct S; } mod n { fn f() { let a<|> = &(crate::m::S, 1); } }",
+            "mod m { pub struct S; } mod n { fn f() { let a<|>: &(crate::m::S, i32) = &(crate::m::S, 1); } }",
+        );
+    }
 }
@@ -0,0 +1,186 @@
+use hir::{ModuleDef, Ty, db::HirDatabase};
+use ra_syntax::{
+    AstNode,
+    ast::{self, NameOwner, TypeAscriptionOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId, add_impl::trait_impl_header};
+
+/// Generates a `From` impl constructing an enum variant with a single field
+/// from the value of that field.
+pub(crate) fn add_from_impl_for_enum(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let variant = ctx.node_at_offset::<ast::EnumVariant>()?;
+    let variant_name = variant.name()?;
+    let enum_def = variant.parent_enum();
+    let enum_name = enum_def.name()?;
+    let (field_name, field_type) = match variant.kind() {
+        ast::StructKind::Tuple(fields) => {
+            let mut fields = fields.fields();
+            let field = fields.next()?;
+            if fields.next().is_some() {
+                return None;
+            }
+            (None, field.type_ref()?)
+        }
+        ast::StructKind::Named(fields) => {
+            let mut fields = fields.fields();
+            let field = fields.next()?;
+            if fields.next().is_some() {
+                return None;
+            }
+            (Some(field.name()?), field.ascribed_type()?)
+        }
+        ast::StructKind::Unit => return None,
+    };
+
+    let db = ctx.db;
+    let module =
+        hir::source_binder::module_from_child_node(db, ctx.frange.file_id, enum_def.syntax())?;
+    let hir_enum = hir::source_binder::enum_from_module(db, module, enum_def);
+    let hir_variant = hir_enum
+        .variants(db)
+        .into_iter()
+        .find(|it| it.name(db).map(|it| it.to_string()) == Some(variant_name.text().to_string()))?;
+    let field_ty = hir_variant.fields(db).into_iter().next()?.ty(db);
+    let analyzer = hir::SourceAnalyzer::new(db, ctx.frange.file_id, enum_def.syntax(), None);
+    if has_from_impl(db, &analyzer, module, &hir_enum.ty(db), &field_ty) {
+        return None;
+    }
+
+    let header =
+        trait_impl_header(enum_def.into(), Some(&format!("From<{}>", field_type.syntax().text())))?;
+    ctx.add_action(
+        AssistId("add_from_impl_for_enum"),
+        "add `From` impl",
+        variant.syntax().range(),
+        |edit| {
+            let (param, constructor) = match field_name {
+                Some(name) => (
+                    name.text().to_string(),
+                    format!("{} {{ {} }}", variant_name.text(), name.text()),
+                ),
+                None => ("v".to_string(), format!("{}(v)", variant_name.text())),
+            };
+            let buf = format!(
+                "\n\n{} {{\n    fn from({}: {}) -> Self {{\n        {}::{}\n    }}\n}}",
+                header,
+                param,
+                field_type.syntax().text(),
+                enum_name.text(),
+                constructor,
+            );
+            edit.insert(enum_def.syntax().range().end(), buf);
+        },
+    );
+
+    ctx.build()
+}
+
+/// Whether there's an `impl From<field_ty> for enum_ty` in the crate already.
+fn has_from_impl(
+    db: &impl HirDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    module: hir::Module,
+    enum_ty: &Ty,
+    field_ty: &Ty,
+) -> bool {
+    let from_trait =
+        analyzer.all_names(db).into_iter().find_map(|(name, res)| match res.take_types()? {
+            hir::Resolution::Def(ModuleDef::Trait(it)) if name.to_string() == "From" => Some(it),
+            _ => None,
+        });
+    let (krate, from_trait) = match (module.krate(db), from_trait) {
+        (Some(krate), Some(from_trait)) => (krate, from_trait),
+        _ => return false,
+    };
+    db.impls_in_crate(krate).lookup_impl_blocks_for_trait(&from_trait).any(|impl_block| {
+        impl_block.target_trait_ref(db).map_or(false, |it| {
+            it.self_ty() == enum_ty && it.substs().iter().nth(1) == Some(field_ty)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
+
+    #[test]
+    fn add_from_impl_for_tuple_variant() {
+        check_assist(
+            add_from_impl_for_enum,
+            "
+enum Error { <|>Io(std::io::Error), Other }",
+            "
+enum Error { <|>Io(std::io::Error), Other }
+
+impl From<std::io::Error> for Error {
+    fn from(v: std::io::Error) -> Self {
+        Error::Io(v)
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn add_from_impl_for_named_variant_with_generics() {
+        check_assist(
+            add_from_impl_for_enum,
+            "
+enum E<T> { <|>A { value: T }, B }",
+            "
+enum E<T> { <|>A { value: T }, B }
+
+impl<T> From<T> for E<T> {
+    fn from(value: T) -> Self {
+        E::A { value }
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn add_from_impl_for_enum_not_applicable() {
+        check_assist_not_applicable(add_from_impl_for_enum, "enum E { <|>A, B(u32) }");
+        check_assist_not_applicable(add_from_impl_for_enum, "enum E { <|>A(u32, u32) }");
+        check_assist_not_applicable(
+            add_from_impl_for_enum,
+            "
+trait From<T> { fn from(t: T) -> Self; }
+enum E { <|>A(u32) }
+impl From<u32> for E {
+    fn from(v: u32) -> Self { E::A(v) }
+}",
+        );
+    }
+
+    #[test]
+    fn add_from_impl_for_enum_with_other_from_impl() {
+        check_assist(
+            add_from_impl_for_enum,
+            "
+trait From<T> { fn from(t: T) -> Self; }
+enum E { A(u32), <|>B(bool) }
+impl From<u32> for E {
+    fn from(v: u32) -> Self { E::A(v) }
+}",
+            "
+trait From<T> { fn from(t: T) -> Self; }
+enum E { A(u32), <|>B(bool) }
+
+impl From<bool> for E {
+    fn from(v: bool) -> Self {
+        E::B(v)
+    }
+}
+impl From<u32> for E {
+    fn from(v: u32) -> Self { E::A(v) }
+}",
+        );
+    }
+
+    #[test]
+    fn add_from_impl_for_enum_target() {
+        check_assist_target(add_from_impl_for_enum, "enum E { <|>A(u32) }", "A(u32)");
+    }
+}
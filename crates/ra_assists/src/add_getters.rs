@@ -0,0 +1,83 @@
+use std::fmt::Write;
+
+use hir::db::HirDatabase;
+use ra_syntax::{
+    TextUnit,
+    ast::{self, AstNode, NameOwner, StructKind, TypeAscriptionOwner, VisibilityOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId, add_impl::impl_header};
+
+/// Generates a getter returning a reference for each named field of the
+/// struct, in a new `impl` block after the struct.
+pub(crate) fn add_getters(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let strukt = ctx.node_at_offset::<ast::StructDef>()?;
+    let header = impl_header(ast::NominalDef::cast(strukt.syntax())?)?;
+    let fields = match strukt.kind() {
+        StructKind::Named(fields) => fields
+            .fields()
+            .map(|it| Some((it.name()?.text().to_string(), it.ascribed_type()?)))
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    if fields.is_empty() {
+        return None;
+    }
+
+    ctx.add_action(AssistId("add_getters"), "add getters", strukt.syntax().range(), |edit| {
+        let start_offset = strukt.syntax().range().end();
+        let vis = strukt.visibility().map(|it| format!("{} ", it.syntax().text()));
+        let vis = vis.as_ref().map(String::as_str).unwrap_or("");
+        let mut buf = format!("\n\n{} {{", header);
+        edit.set_cursor(start_offset + TextUnit::of_str(&buf) + TextUnit::of_str("\n    "));
+        for (idx, (name, ty)) in fields.iter().enumerate() {
+            if idx > 0 {
+                buf.push_str("\n");
+            }
+            write!(
+                buf,
+                "\n    {}fn {}(&self) -> &{} {{\n        &self.{}\n    }}",
+                vis,
+                name,
+                ty.syntax().text(),
+                name
+            )
+            .unwrap();
+        }
+        buf.push_str("\n}");
+        edit.insert(start_offset, buf);
+    });
+
+    ctx.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable};
+
+    #[test]
+    fn test_add_getters() {
+        check_assist(
+            add_getters,
+            "pub struct Foo<T> {<|>a: u32, pub b: T}",
+            "pub struct Foo<T> {a: u32, pub b: T}
+
+impl<T> Foo<T> {
+    <|>pub fn a(&self) -> &u32 {
+        &self.a
+    }
+
+    pub fn b(&self) -> &T {
+        &self.b
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn add_getters_not_applicable_to_tuple_structs() {
+        check_assist_not_applicable(add_getters, "struct Foo(u32<|>);");
+        check_assist_not_applicable(add_getters, "struct Foo {<|>}");
+    }
+}
@@ -9,28 +9,10 @@ use crate::{AssistCtx, Assist, AssistId};
 
 pub(crate) fn add_impl(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
     let nominal = ctx.node_at_offset::<ast::NominalDef>()?;
-    let name = nominal.name()?;
-    ctx.add_action(AssistId("add_impl"), "add impl", |edit| {
-        edit.target(nominal.syntax().range());
-        let type_params = nominal.type_param_list();
+    let header = impl_header(nominal)?;
+    ctx.add_action(AssistId("add_impl"), "add impl", nominal.syntax().range(), |edit| {
         let start_offset = nominal.syntax().range().end();
-        let mut buf = String::new();
-        buf.push_str("\n\nimpl");
-        if let Some(type_params) = type_params {
-            type_params.syntax().text().push_to(&mut buf);
-        }
-        buf.push_str(" ");
-        buf.push_str(name.text().as_str());
-        if let Some(type_params) = type_params {
-            let lifetime_params = type_params
-                .lifetime_params()
-                .filter_map(|it| it.lifetime_token())
-                .map(|it| it.text());
-            let type_params =
-                type_params.type_params().filter_map(|it| it.name()).map(|it| it.text());
-            join(lifetime_params.chain(type_params)).surround_with("<", ">").to_buf(&mut buf);
-        }
-        buf.push_str(" {\n");
+        let mut buf = format!("\n\n{} {{\n", header);
         edit.set_cursor(start_offset + TextUnit::of_str(&buf));
         buf.push_str("\n}");
         edit.insert(start_offset, buf);
@@ -39,6 +21,36 @@ pub(crate) fn add_impl(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
     ctx.build()
 }
 
+/// Returns the `impl` header for `nominal`, like `impl<'a, T: Clone> Foo<'a, T>`.
+pub(crate) fn impl_header(nominal: &ast::NominalDef) -> Option<String> {
+    trait_impl_header(nominal, None)
+}
+
+/// Like `impl_header`, but for an impl of `trait_` if it is given, like
+/// `impl<T> Clone for Foo<T>`.
+pub(crate) fn trait_impl_header(nominal: &ast::NominalDef, trait_: Option<&str>) -> Option<String> {
+    let name = nominal.name()?;
+    let type_params = nominal.type_param_list();
+    let mut buf = String::new();
+    buf.push_str("impl");
+    if let Some(type_params) = type_params {
+        type_params.syntax().text().push_to(&mut buf);
+    }
+    buf.push_str(" ");
+    if let Some(trait_) = trait_ {
+        buf.push_str(trait_);
+        buf.push_str(" for ");
+    }
+    buf.push_str(name.text().as_str());
+    if let Some(type_params) = type_params {
+        let lifetime_params =
+            type_params.lifetime_params().filter_map(|it| it.lifetime_token()).map(|it| it.text());
+        let type_params = type_params.type_params().filter_map(|it| it.name()).map(|it| it.text());
+        join(lifetime_params.chain(type_params)).surround_with("<", ">").to_buf(&mut buf);
+    }
+    Some(buf)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
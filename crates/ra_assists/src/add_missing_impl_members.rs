@@ -75,7 +75,7 @@ fn add_missing_impl_members_inner(
         return None;
     }
 
-    ctx.add_action(AssistId(assist_id), label, |edit| {
+    ctx.add_action(AssistId(assist_id), label, impl_node.syntax().range(), |edit| {
         let (parent_indent, indent) = {
             // FIXME: Find a way to get the indent already used in the file.
             // Now, we copy the indent of first item or indent with 4 spaces relative to impl block
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use hir::db::HirDatabase;
+use ra_syntax::{
+    TextUnit,
+    ast::{self, AstNode, NameOwner, StructKind, TypeAscriptionOwner, VisibilityOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId, add_impl::impl_header};
+
+/// Generates a `new` constructor, taking all the fields, in a new `impl` block
+/// after the struct.
+pub(crate) fn add_new(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let strukt = ctx.node_at_offset::<ast::StructDef>()?;
+    let header = impl_header(ast::NominalDef::cast(strukt.syntax())?)?;
+    let (params, ctor) = match strukt.kind() {
+        StructKind::Named(fields) => {
+            let fields = fields
+                .fields()
+                .map(|it| Some((it.name()?.text().to_string(), it.ascribed_type()?)))
+                .collect::<Option<Vec<_>>>()?;
+            let names = fields.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+            (fields_params(&fields), format!("Self {{ {} }}", names.join(", ")))
+        }
+        StructKind::Tuple(fields) => {
+            let fields = fields
+                .fields()
+                .enumerate()
+                .map(|(idx, it)| Some((format!("field{}", idx), it.type_ref()?)))
+                .collect::<Option<Vec<_>>>()?;
+            let names = fields.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+            (fields_params(&fields), format!("Self({})", names.join(", ")))
+        }
+        StructKind::Unit => return None,
+    };
+
+    ctx.add_action(AssistId("add_new"), "add new", strukt.syntax().range(), |edit| {
+        let start_offset = strukt.syntax().range().end();
+        let mut buf = format!("\n\n{} {{\n    ", header);
+        edit.set_cursor(start_offset + TextUnit::of_str(&buf));
+        if let Some(vis) = strukt.visibility() {
+            write!(buf, "{} ", vis.syntax().text()).unwrap();
+        }
+        write!(buf, "fn new({}) -> Self {{\n        {}\n    }}\n}}", params, ctor).unwrap();
+        edit.insert(start_offset, buf);
+    });
+
+    ctx.build()
+}
+
+fn fields_params(fields: &[(String, &ast::TypeRef)]) -> String {
+    fields
+        .iter()
+        .map(|(name, ty)| format!("{}: {}", name, ty.syntax().text()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    #[test]
+    fn test_add_new() {
+        check_assist(
+            add_new,
+            "struct Foo {<|>a: u32, b: String}",
+            "struct Foo {a: u32, b: String}
+
+impl Foo {
+    <|>fn new(a: u32, b: String) -> Self {
+        Self { a, b }
+    }
+}",
+        );
+        check_assist(
+            add_new,
+            "pub struct Foo<'a, T: Clone>(&'a T, u32<|>);",
+            "pub struct Foo<'a, T: Clone>(&'a T, u32);
+
+impl<'a, T: Clone> Foo<'a, T> {
+    <|>pub fn new(field0: &'a T, field1: u32) -> Self {
+        Self(field0, field1)
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn add_new_not_applicable_to_unit_structs() {
+        check_assist_not_applicable(add_new, "struct Foo<|>;");
+    }
+
+    #[test]
+    fn add_new_target() {
+        check_assist_target(add_new, "struct Foo {<|>a: u32}\nstruct Bar;", "struct Foo {a: u32}");
+    }
+}
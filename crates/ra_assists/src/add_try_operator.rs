@@ -0,0 +1,185 @@
+use hir::{
+    AdtDef, HirDisplay, Ty,
+    db::HirDatabase,
+};
+use ra_syntax::{
+    AstNode,
+    SyntaxKind::{ARG_LIST, CONDITION, FN_DEF, LAMBDA_EXPR, METHOD_CALL_EXPR, MATCH_EXPR, TRY_EXPR},
+    ast::{self, TypeAscriptionOwner},
+};
+
+use crate::{
+    AssistCtx, Assist, AssistId,
+    wrap_return_type_in_result::{is_result, returned_exprs, wrap_in_result},
+};
+
+/// Appends `?` to a call returning a `Result` which is not handled, changing
+/// the return type of the function to a `Result` if it isn't one already.
+pub(crate) fn add_try_operator(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let expr = ctx.node_at_offset::<ast::Expr>()?;
+    let expr =
+        expr.syntax().ancestors().filter_map(ast::Expr::cast).find(|it| match it.kind() {
+            ast::ExprKind::CallExpr(_) | ast::ExprKind::MethodCallExpr(_) => true,
+            _ => false,
+        })?;
+    if is_handled(expr) {
+        return None;
+    }
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, expr.syntax(), None);
+    let err_ty = result_err_ty(db, &analyzer.type_of(db, expr)?)?;
+
+    let fn_def = expr
+        .syntax()
+        .ancestors()
+        .find(|it| it.kind() == FN_DEF || it.kind() == LAMBDA_EXPR)
+        .and_then(ast::FnDef::cast)?;
+    let body = fn_def.body()?;
+    let returns_result =
+        fn_def.ret_type().and_then(|it| it.type_ref()).map_or(false, |it| is_result(it));
+    let module = hir::source_binder::module_from_child_node(db, file_id, fn_def.syntax())?;
+
+    // With `?`, the call evaluates to the `Ok` value. If the function already
+    // returns a `Result`, wherever that value ends up being returned needs to
+    // be wrapped in `Ok` again; otherwise `wrap_in_result` does so.
+    let mut wrap_in_ok = Vec::new();
+    if returns_result {
+        let returned = returned_exprs(body);
+        if returned.iter().any(|it| it.syntax() == expr.syntax()) {
+            return None;
+        }
+        if let Some(let_stmt) = expr.syntax().parent().and_then(ast::LetStmt::cast) {
+            if let_stmt.ascribed_type().is_some() {
+                return None;
+            }
+            if let Some(ast::PatKind::BindPat(pat)) = let_stmt.pat().map(|it| it.kind()) {
+                for usage in analyzer.find_all_refs(db, pat) {
+                    let usage = body
+                        .syntax()
+                        .descendants()
+                        .filter_map(ast::PathExpr::cast)
+                        .find(|it| it.syntax().range() == usage.range)?;
+                    let usage = ast::Expr::cast(usage.syntax())?;
+                    if returned.iter().any(|it| it.syntax() == usage.syntax()) {
+                        wrap_in_ok.push(usage);
+                    } else if is_handled(usage) {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    ctx.add_action(AssistId("add_try_operator"), "add `?`", expr.syntax().range(), |edit| {
+        edit.insert(expr.syntax().range().end(), "?");
+        if !returns_result {
+            wrap_in_result(edit, fn_def, &err_ty.display_source_code(db, module).to_string());
+        }
+        for usage in wrap_in_ok {
+            edit.insert(usage.syntax().range().start(), "Ok(");
+            edit.insert(usage.syntax().range().end(), ")");
+        }
+    });
+
+    ctx.build()
+}
+
+/// Whether the `Result` value of `expr` is already taken care of: propagated
+/// with `?`, matched on, or passed on to a method or function.
+fn is_handled(expr: &ast::Expr) -> bool {
+    let parent = match expr.syntax().parent() {
+        Some(it) => it,
+        None => return false,
+    };
+    match parent.kind() {
+        TRY_EXPR | ARG_LIST | MATCH_EXPR | CONDITION => true,
+        METHOD_CALL_EXPR => ast::MethodCallExpr::cast(parent)
+            .and_then(|it| it.expr())
+            .map_or(false, |it| it.syntax() == expr.syntax()),
+        _ => false,
+    }
+}
+
+/// If `ty` is a `Result`, returns its error type.
+fn result_err_ty(db: &impl HirDatabase, ty: &Ty) -> Option<Ty> {
+    let (adt, substs) = ty.as_adt()?;
+    match adt {
+        AdtDef::Enum(it) if it.name(db)?.to_string() == "Result" => substs.iter().nth(1).cloned(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    const RESULT: &str = "enum Result<T, E> { Ok(T), Err(E) }\nstruct Error;\n";
+
+    #[test]
+    fn add_try_operator_in_result_fn() {
+        check_assist(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<u32, Error> {{ let x = pa<|>rse(); x }}", RESULT),
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<u32, Error> {{ let x = pa<|>rse()?; Ok(x) }}", RESULT),
+        );
+        check_assist(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<(), Error> {{ pa<|>rse(); Ok(()) }}", RESULT),
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<(), Error> {{ pa<|>rse()?; Ok(()) }}", RESULT),
+        );
+    }
+
+    #[test]
+    fn add_try_operator_changes_signature() {
+        check_assist(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> u32 {{ pa<|>rse() + 1 }}", RESULT),
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<u32, Error> {{ Ok(pa<|>rse()? + 1) }}", RESULT),
+        );
+        check_assist(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() {{\n    pa<|>rse();\n}}", RESULT),
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<(), Error> {{\n    pa<|>rse()?;\n    Ok(())\n}}", RESULT),
+        );
+        check_assist(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo(x: bool) -> () {{\n    if x {{ return; }}\n    pa<|>rse();\n}}", RESULT),
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo(x: bool) -> Result<(), Error> {{\n    if x {{ return Ok(()); }}\n    pa<|>rse()?;\n    Ok(())\n}}", RESULT),
+        );
+    }
+
+    #[test]
+    fn add_try_operator_not_applicable() {
+        check_assist_not_applicable(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<u32, Error> {{ pa<|>rse()? }}", RESULT),
+        );
+        check_assist_not_applicable(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<u32, Error> {{ pa<|>rse() }}", RESULT),
+        );
+        check_assist_not_applicable(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> Result<u32, Error> {{ let x = pa<|>rse(); x.map_err(|e| e) }}", RESULT),
+        );
+        check_assist_not_applicable(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() -> u32 {{ match pa<|>rse() {{ _ => 0 }} }}", RESULT),
+        );
+        check_assist_not_applicable(
+            add_try_operator,
+            "fn parse() -> u32 {}\nfn foo() -> u32 { pa<|>rse() }",
+        );
+    }
+
+    #[test]
+    fn add_try_operator_target() {
+        check_assist_target(
+            add_try_operator,
+            &format!("{}fn parse() -> Result<u32, Error> {{}}\nfn foo() {{ pa<|>rse(); }}", RESULT),
+            "parse()",
+        );
+    }
+}
@@ -1,13 +1,14 @@
 use hir::db::HirDatabase;
 use ra_text_edit::TextEditBuilder;
-use ra_db::FileRange;
+use ra_db::{FileRange, SourceRootId};
+use relative_path::RelativePathBuf;
 use ra_syntax::{
     SourceFile, TextRange, AstNode, TextUnit, SyntaxNode, SyntaxElement, SyntaxToken,
     algo::{find_token_at_offset, find_node_at_offset, find_covering_element, TokenAtOffset},
 };
 use ra_fmt::{leading_indent, reindent};
 
-use crate::{AssistLabel, AssistAction, AssistId};
+use crate::{AssistLabel, AssistAction, AssistId, AssistNewFile, GroupLabel};
 
 #[derive(Clone, Debug)]
 pub(crate) enum Assist {
@@ -42,9 +43,10 @@ pub(crate) enum Assist {
 /// with `should_compute_edit = false`, and then applying the selected edit
 /// again, with `should_compute_edit = true` this time.
 ///
-/// Note, however, that we don't actually use such two-phase logic at the
-/// moment, because the LSP API is pretty awkward in this place, and it's much
-/// easier to just compute the edit eagerly :-)#[derive(Debug, Clone)]
+/// The LSP server does exactly this: code actions only carry the labels, and
+/// the edit of the action picked by the user is computed by `resolve_assist`.
+/// The labels include the target of the assist, so it must be known without
+/// computing the edit.
 #[derive(Debug)]
 pub(crate) struct AssistCtx<'a, DB> {
     pub(crate) db: &'a DB,
@@ -79,13 +81,40 @@ impl<'a, DB: HirDatabase> AssistCtx<'a, DB> {
         f(ctx)
     }
 
+    /// Adds an action applying to `target`, whose edit is built by `f` if the
+    /// edits are to be computed.
     pub(crate) fn add_action(
         &mut self,
         id: AssistId,
         label: impl Into<String>,
+        target: TextRange,
         f: impl FnOnce(&mut AssistBuilder),
     ) -> &mut Self {
-        let label = AssistLabel { label: label.into(), id };
+        self.add_action_impl(None, id, label.into(), target, f)
+    }
+
+    /// Like `add_action`, for an action which is one of the alternatives in
+    /// `group`.
+    pub(crate) fn add_grouped_action(
+        &mut self,
+        group: &GroupLabel,
+        id: AssistId,
+        label: impl Into<String>,
+        target: TextRange,
+        f: impl FnOnce(&mut AssistBuilder),
+    ) -> &mut Self {
+        self.add_action_impl(Some(group.clone()), id, label.into(), target, f)
+    }
+
+    fn add_action_impl(
+        &mut self,
+        group: Option<GroupLabel>,
+        id: AssistId,
+        label: String,
+        target: TextRange,
+        f: impl FnOnce(&mut AssistBuilder),
+    ) -> &mut Self {
+        let label = AssistLabel { label, id, target, group };
         match &mut self.assist {
             Assist::Unresolved(labels) => labels.push(label),
             Assist::Resolved(labels_actions) => {
@@ -124,7 +153,7 @@ impl<'a, DB: HirDatabase> AssistCtx<'a, DB> {
 pub(crate) struct AssistBuilder {
     edit: TextEditBuilder,
     cursor_position: Option<TextUnit>,
-    target: Option<TextRange>,
+    new_files: Vec<AssistNewFile>,
 }
 
 impl AssistBuilder {
@@ -144,7 +173,6 @@ impl AssistBuilder {
         self.replace(node.range(), replace_with)
     }
 
-    #[allow(unused)]
     pub(crate) fn delete(&mut self, range: TextRange) {
         self.edit.delete(range)
     }
@@ -157,15 +185,20 @@ impl AssistBuilder {
         self.cursor_position = Some(offset)
     }
 
-    pub(crate) fn target(&mut self, target: TextRange) {
-        self.target = Some(target)
+    pub(crate) fn create_file(
+        &mut self,
+        source_root: SourceRootId,
+        path: RelativePathBuf,
+        text: impl Into<String>,
+    ) {
+        self.new_files.push(AssistNewFile { source_root, path, text: text.into() })
     }
 
     fn build(self) -> AssistAction {
         AssistAction {
             edit: self.edit.finish(),
             cursor_position: self.cursor_position,
-            target: self.target,
+            new_files: self.new_files,
         }
     }
 }
@@ -62,7 +62,7 @@ fn fmt_segments_raw(segments: &[&ast::PathSegment], buf: &mut String) {
             Some(ast::PathSegmentKind::SelfKw) => buf.push_str("self"),
             Some(ast::PathSegmentKind::SuperKw) => buf.push_str("super"),
             Some(ast::PathSegmentKind::CrateKw) => buf.push_str("crate"),
-            None => {}
+            Some(ast::PathSegmentKind::Type { .. }) | None => {}
         }
         first = false;
     }
@@ -530,6 +530,7 @@ pub(crate) fn auto_import(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist
             ctx.add_action(
                 AssistId("auto_import"),
                 format!("import {} in mod {}", fmt_segments(&segments), name.text()),
+                path.syntax().range(),
                 |edit| {
                     apply_auto_import(item_list.syntax(), path, &segments, edit);
                 },
@@ -540,6 +541,7 @@ pub(crate) fn auto_import(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist
         ctx.add_action(
             AssistId("auto_import"),
             format!("import {} in the current file", fmt_segments(&segments)),
+            path.syntax().range(),
             |edit| {
                 apply_auto_import(current_file.syntax(), path, &segments, edit);
             },
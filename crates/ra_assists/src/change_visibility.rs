@@ -1,11 +1,11 @@
 use hir::db::HirDatabase;
 use ra_syntax::{
-    AstNode, SyntaxNode, TextUnit,
+    AstNode, SyntaxNode, TextUnit, TextRange,
     ast::{self, VisibilityOwner, NameOwner},
     SyntaxKind::{VISIBILITY, FN_KW, MOD_KW, STRUCT_KW, ENUM_KW, TRAIT_KW, FN_DEF, MODULE, STRUCT_DEF, ENUM_DEF, TRAIT_DEF, IDENT, WHITESPACE, COMMENT, ATTR},
 };
 
-use crate::{AssistCtx, Assist, AssistId};
+use crate::{AssistCtx, Assist, AssistId, GroupLabel};
 
 pub(crate) fn change_visibility(ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
     if let Some(vis) = ctx.node_at_offset::<ast::Visibility>() {
@@ -41,9 +41,19 @@ fn add_vis(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
         (vis_offset(field.syntax()), ident.range())
     };
 
-    ctx.add_action(AssistId("change_visibility"), "make pub(crate)", |edit| {
-        edit.target(target);
-        edit.insert(offset, "pub(crate) ");
+    let group = GroupLabel("change visibility".to_string());
+    ctx.add_grouped_action(
+        &group,
+        AssistId("change_visibility"),
+        "make pub(crate)",
+        target,
+        |edit| {
+            edit.insert(offset, "pub(crate) ");
+            edit.set_cursor(offset);
+        },
+    );
+    ctx.add_grouped_action(&group, AssistId("change_visibility"), "make pub", target, |edit| {
+        edit.insert(offset, "pub ");
         edit.set_cursor(offset);
     });
 
@@ -62,31 +72,50 @@ fn vis_offset(node: &SyntaxNode) -> TextUnit {
 }
 
 fn change_vis(mut ctx: AssistCtx<impl HirDatabase>, vis: &ast::Visibility) -> Option<Assist> {
-    if vis.syntax().text() == "pub" {
-        ctx.add_action(AssistId("change_visibility"), "change to pub(crate)", |edit| {
-            edit.target(vis.syntax().range());
-            edit.replace(vis.syntax().range(), "pub(crate)");
-            edit.set_cursor(vis.syntax().range().start())
-        });
-
-        return ctx.build();
-    }
-    if vis.syntax().text() == "pub(crate)" {
-        ctx.add_action(AssistId("change_visibility"), "change to pub", |edit| {
-            edit.target(vis.syntax().range());
-            edit.replace(vis.syntax().range(), "pub");
+    let new_vis = if vis.syntax().text() == "pub" {
+        "pub(crate)"
+    } else if vis.syntax().text() == "pub(crate)" {
+        "pub"
+    } else {
+        return None;
+    };
+    let group = GroupLabel("change visibility".to_string());
+    ctx.add_grouped_action(
+        &group,
+        AssistId("change_visibility"),
+        format!("change to {}", new_vis),
+        vis.syntax().range(),
+        |edit| {
+            edit.replace(vis.syntax().range(), new_vis);
             edit.set_cursor(vis.syntax().range().start());
-        });
+        },
+    );
+    ctx.add_grouped_action(
+        &group,
+        AssistId("change_visibility"),
+        "make private",
+        vis.syntax().range(),
+        |edit| {
+            // Remove the whitespace after the visibility as well, so that we
+            // don't leave a leading space before the item keyword.
+            let end = vis
+                .syntax()
+                .next_sibling_or_token()
+                .filter(|it| it.kind() == WHITESPACE)
+                .map(|it| it.range().end())
+                .unwrap_or(vis.syntax().range().end());
+            edit.delete(TextRange::from_to(vis.syntax().range().start(), end));
+            edit.set_cursor(vis.syntax().range().start());
+        },
+    );
 
-        return ctx.build();
-    }
-    None
+    ctx.build()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::helpers::{check_assist, check_assist_target};
+    use crate::helpers::{check_assist, check_assist_target, check_assist_nth_action};
 
     #[test]
     fn change_visibility_adds_pub_crate_to_items() {
@@ -122,6 +151,28 @@ mod tests {
         check_assist(change_visibility, "<|>pub(crate) fn foo() {}", "<|>pub fn foo() {}")
     }
 
+    #[test]
+    fn change_visibility_private_to_pub() {
+        check_assist_nth_action(change_visibility, "<|>fn foo() {}", "<|>pub fn foo() {}", 1);
+        check_assist_nth_action(
+            change_visibility,
+            "struct S { <|>field: u32 }",
+            "struct S { <|>pub field: u32 }",
+            1,
+        )
+    }
+
+    #[test]
+    fn change_visibility_to_private() {
+        check_assist_nth_action(change_visibility, "<|>pub fn foo() {}", "<|>fn foo() {}", 1);
+        check_assist_nth_action(
+            change_visibility,
+            "struct S { <|>pub(crate) field: u32 }",
+            "struct S { <|>field: u32 }",
+            1,
+        )
+    }
+
     #[test]
     fn change_visibility_handles_comment_attrs() {
         check_assist(
@@ -0,0 +1,311 @@
+use hir::{
+    AdtDef, ModuleDef, PathResolution, Struct,
+    db::HirDatabase,
+};
+use ra_syntax::{
+    AstNode, SyntaxNode, TextRange,
+    SyntaxKind::{DOTDOT, INT_NUMBER, L_PAREN, R_PAREN, SEMI, WHITESPACE},
+    ast::{self, ArgListOwner, TypeParamsOwner, VisibilityOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId, assist_ctx::AssistBuilder};
+
+/// Converts a tuple struct, like `struct S(u32);`, into a struct with named
+/// fields, `struct S { field0: u32 }`, and updates the constructor calls,
+/// patterns and field accesses. Assists edit a single file, so this is only
+/// offered for the structs which can't be used outside of the current file.
+pub(crate) fn convert_tuple_struct_to_named_struct(
+    mut ctx: AssistCtx<impl HirDatabase>,
+) -> Option<Assist> {
+    let strukt = ctx.node_at_offset::<ast::StructDef>()?;
+    let fields = match strukt.kind() {
+        ast::StructKind::Tuple(it) => it,
+        _ => return None,
+    };
+    let db = ctx.db;
+    let module =
+        hir::source_binder::module_from_child_node(db, ctx.frange.file_id, strukt.syntax())?;
+    if strukt.visibility().is_some() || has_children_in_other_files(db, module) {
+        return None;
+    }
+    let hir_struct = hir::source_binder::struct_from_module(db, module, strukt);
+    let file = strukt.syntax().ancestors().last()?;
+    let file_id = ctx.frange.file_id;
+
+    ctx.add_action(
+        AssistId("convert_tuple_struct_to_named_struct"),
+        "convert to named fields",
+        strukt.syntax().range(),
+        |edit| {
+            edit_definition(edit, strukt, fields);
+            for node in file.descendants() {
+                let analyzer = || hir::SourceAnalyzer::new(db, file_id, node, None);
+                if let Some(call) = ast::CallExpr::cast(node) {
+                    let path = match call.expr().map(|it| it.kind()) {
+                        Some(ast::ExprKind::PathExpr(it)) => it.path(),
+                        _ => None,
+                    };
+                    let is_ctor = path.map_or(false, |path| {
+                        resolves_to_struct(db, &analyzer(), path, hir_struct)
+                    });
+                    if let (true, Some(arg_list)) = (is_ctor, call.arg_list()) {
+                        let args: Vec<_> = arg_list.args().map(|it| it.syntax()).collect();
+                        edit_list(edit, arg_list.syntax(), &args);
+                    }
+                } else if let Some(pat) = ast::TupleStructPat::cast(node) {
+                    let is_struct = pat.path().map_or(false, |path| {
+                        resolves_to_struct(db, &analyzer(), path, hir_struct)
+                    });
+                    if is_struct {
+                        edit_pattern(edit, pat);
+                    }
+                } else if let Some(field_expr) = ast::FieldExpr::cast(node) {
+                    let field = match analyzer().resolve_field(field_expr) {
+                        Some(it) => it,
+                        None => continue,
+                    };
+                    if !hir_struct.fields(db).contains(&field) {
+                        continue;
+                    }
+                    let index = field_expr
+                        .syntax()
+                        .children_with_tokens()
+                        .find(|it| it.kind() == INT_NUMBER);
+                    if let Some(index) = index {
+                        edit.replace(index.range(), format!("field{}", index));
+                    }
+                }
+            }
+        },
+    );
+
+    ctx.build()
+}
+
+fn edit_definition(
+    edit: &mut AssistBuilder,
+    strukt: &ast::StructDef,
+    fields: &ast::PosFieldDefList,
+) {
+    let semi = strukt.syntax().children_with_tokens().find(|it| it.kind() == SEMI);
+    match (strukt.where_clause(), semi) {
+        // The fields need to move after the `where` clause.
+        (Some(_), Some(semi)) => {
+            let named = fields
+                .fields()
+                .enumerate()
+                .map(|(idx, field)| named_field(idx, field))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut start = fields.syntax().range().start();
+            if let Some(ws) = fields.syntax().prev_sibling_or_token() {
+                if ws.kind() == WHITESPACE {
+                    start = ws.range().start();
+                }
+            }
+            edit.delete(TextRange::from_to(start, fields.syntax().range().end()));
+            edit.replace(semi.range(), format!(" {{ {} }}", named));
+        }
+        _ => {
+            let types: Vec<&SyntaxNode> = fields
+                .fields()
+                .map(|it| it.type_ref().map_or(it.syntax(), |it| it.syntax()))
+                .collect();
+            edit_list(edit, fields.syntax(), &types);
+            if let Some(semi) = semi {
+                edit.delete(semi.range());
+            }
+        }
+    }
+}
+
+fn named_field(idx: usize, field: &ast::PosFieldDef) -> String {
+    let text = field.syntax().text().to_string();
+    match field.type_ref() {
+        Some(ty) => {
+            let offset = ty.syntax().range().start() - field.syntax().range().start();
+            let (prefix, ty) = text.split_at(offset.to_usize());
+            format!("{}field{}: {}", prefix, idx, ty)
+        }
+        None => text,
+    }
+}
+
+/// Turns a parenthesized list, like `(a, b)`, into a braced one with named
+/// fields, `{ field0: a, field1: b }`, editing only around the elements.
+fn edit_list(edit: &mut AssistBuilder, list: &SyntaxNode, elements: &[&SyntaxNode]) {
+    let (l_paren, r_paren) = match parens(list) {
+        Some(it) => it,
+        None => return,
+    };
+    if elements.is_empty() {
+        edit.replace(TextRange::from_to(l_paren.start(), r_paren.end()), " {}".to_string());
+        return;
+    }
+    edit.replace(l_paren, " { ".to_string());
+    for (idx, element) in elements.iter().enumerate() {
+        edit.insert(element.range().start(), format!("field{}: ", idx));
+    }
+    edit.replace(r_paren, " }".to_string());
+}
+
+fn edit_pattern(edit: &mut AssistBuilder, pat: &ast::TupleStructPat) {
+    let args: Vec<&SyntaxNode> = pat.args().map(|it| it.syntax()).collect();
+    // Elements after `..` are counted from the end, which needs the number of
+    // fields. This is rare enough to not bother and leave the pattern alone.
+    if let Some(rest) = pat.syntax().children_with_tokens().find(|it| it.kind() == DOTDOT) {
+        if args.iter().any(|it| it.range().start() > rest.range().start()) {
+            return;
+        }
+        if args.is_empty() {
+            if let Some((l_paren, r_paren)) = parens(pat.syntax()) {
+                let range = TextRange::from_to(l_paren.start(), r_paren.end());
+                edit.replace(range, " { .. }".to_string());
+            }
+            return;
+        }
+    }
+    edit_list(edit, pat.syntax(), &args);
+}
+
+fn parens(node: &SyntaxNode) -> Option<(TextRange, TextRange)> {
+    let l_paren = node.children_with_tokens().find(|it| it.kind() == L_PAREN)?.range();
+    let r_paren = node.children_with_tokens().find(|it| it.kind() == R_PAREN)?.range();
+    Some((l_paren, r_paren))
+}
+
+/// Whether some descendant of `module`, where its private items are visible,
+/// is defined in another file.
+fn has_children_in_other_files(db: &impl HirDatabase, module: hir::Module) -> bool {
+    module.children(db).any(|child| match child.definition_source(db).1 {
+        hir::ModuleSource::SourceFile(_) => true,
+        hir::ModuleSource::Module(_) => has_children_in_other_files(db, child),
+    })
+}
+
+fn resolves_to_struct(
+    db: &impl HirDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    path: &ast::Path,
+    strukt: Struct,
+) -> bool {
+    match analyzer.resolve_path(db, path) {
+        Some(PathResolution::Def(ModuleDef::Struct(it))) => it == strukt,
+        Some(PathResolution::SelfType(impl_block)) => {
+            impl_block.target_ty(db).as_adt().map(|(it, _)| it) == Some(AdtDef::Struct(strukt))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hir::mock::MockDatabase;
+    use ra_db::FileRange;
+
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    #[test]
+    fn convert_tuple_struct_updates_uses() {
+        check_assist(
+            convert_tuple_struct_to_named_struct,
+            r#"
+struct <|>S(pub u32, String);
+
+impl S {
+    fn new() -> S {
+        Self(0, String::new())
+    }
+    fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+fn f(s: S) {
+    let S(a, b) = S(1, s.1);
+    match s {
+        S(0, ..) => (),
+        S(..) => (),
+    }
+}
+"#,
+            r#"
+struct <|>S { pub field0: u32, field1: String }
+
+impl S {
+    fn new() -> S {
+        Self { field0: 0, field1: String::new() }
+    }
+    fn get(&self) -> u32 {
+        self.field0
+    }
+}
+
+fn f(s: S) {
+    let S { field0: a, field1: b } = S { field0: 1, field1: s.field1 };
+    match s {
+        S { field0: 0, .. } => (),
+        S { .. } => (),
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_tuple_struct_with_where_clause() {
+        check_assist(
+            convert_tuple_struct_to_named_struct,
+            "struct S<|><T>(T) where T: Clone;",
+            "struct S<|><T> where T: Clone { field0: T }",
+        );
+    }
+
+    #[test]
+    fn convert_tuple_struct_ignores_other_structs() {
+        check_assist(
+            convert_tuple_struct_to_named_struct,
+            "struct <|>S(u32);\nstruct T(u32);\nfn f(t: T) { T(t.0); }",
+            "struct <|>S { field0: u32 }\nstruct T(u32);\nfn f(t: T) { T(t.0); }",
+        );
+    }
+
+    #[test]
+    fn convert_tuple_struct_not_applicable_to_named_structs() {
+        check_assist_not_applicable(convert_tuple_struct_to_named_struct, "struct <|>S { a: u32 }");
+    }
+
+    #[test]
+    fn convert_tuple_struct_not_applicable_to_structs_visible_in_other_files() {
+        check_assist_not_applicable(
+            convert_tuple_struct_to_named_struct,
+            "pub(crate) struct <|>S(u32);",
+        );
+        let (db, position) = MockDatabase::with_position(
+            "
+//- /lib.rs
+struct <|>S(u32);
+mod foo;
+
+//- /foo.rs
+fn f(s: super::S) -> u32 { s.0 }
+",
+        );
+        let frange = FileRange {
+            file_id: position.file_id,
+            range: TextRange::offset_len(position.offset, 0.into()),
+        };
+        let assist = AssistCtx::with_ctx(&db, frange, true, convert_tuple_struct_to_named_struct);
+        assert!(assist.is_none());
+    }
+
+    #[test]
+    fn convert_tuple_struct_target() {
+        check_assist_target(
+            convert_tuple_struct_to_named_struct,
+            "struct <|>S(u32);\nfn f() {}",
+            "struct S(u32);",
+        );
+    }
+}
@@ -1,24 +1,19 @@
 use std::fmt::Write;
 
 use hir::{
-    AdtDef, FieldSource,
+    AdtDef, FieldSource, EnumVariant, ModuleDef, PathResolution,
     db::HirDatabase,
 };
-use ra_syntax::ast::{self, AstNode};
+use ra_syntax::{
+    SyntaxKind::COMMA,
+    ast::{self, AstNode},
+};
+use ra_fmt::leading_indent;
 
 use crate::{AssistCtx, Assist, AssistId};
 
 pub(crate) fn fill_match_arms(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
     let match_expr = ctx.node_at_offset::<ast::MatchExpr>()?;
-
-    // We already have some match arms, so we don't provide any assists.
-    match match_expr.match_arm_list() {
-        Some(arm_list) if arm_list.arms().count() > 0 => {
-            return None;
-        }
-        _ => {}
-    }
-
     let expr = match_expr.expr()?;
     let analyzer = hir::SourceAnalyzer::new(ctx.db, ctx.frange.file_id, expr.syntax(), None);
     let match_expr_ty = analyzer.type_of(ctx.db, expr)?;
@@ -29,49 +24,156 @@ pub(crate) fn fill_match_arms(mut ctx: AssistCtx<impl HirDatabase>) -> Option<As
     let enum_name = enum_def.name(ctx.db)?;
     let db = ctx.db;
 
-    ctx.add_action(AssistId("fill_match_arms"), "fill match arms", |edit| {
-        let mut buf = format!("match {} {{\n", expr.syntax().text().to_string());
-        let variants = enum_def.variants(db);
-        for variant in variants {
-            let name = match variant.name(db) {
-                Some(it) => it,
-                None => continue,
-            };
-            write!(&mut buf, "    {}::{}", enum_name, name.to_string()).unwrap();
-
-            let pat = variant
-                .fields(db)
-                .into_iter()
-                .map(|field| {
-                    let name = field.name(db).to_string();
-                    let (_, source) = field.source(db);
-                    match source {
-                        FieldSource::Named(_) => name,
-                        FieldSource::Pos(_) => "_".to_string(),
+    let arms: Vec<&ast::MatchArm> =
+        match_expr.match_arm_list().into_iter().flat_map(|it| it.arms()).collect();
+    let covered: Vec<EnumVariant> = arms
+        .iter()
+        .filter(|arm| arm.guard().is_none())
+        .flat_map(|arm| arm.pats())
+        .filter_map(|pat| covered_variant(db, &analyzer, pat))
+        .collect();
+    let missing: Vec<String> = enum_def
+        .variants(db)
+        .into_iter()
+        .filter(|variant| !covered.contains(variant))
+        .filter_map(|variant| {
+            let name = variant.name(db)?;
+            Some(format!("{}::{}{}", enum_name, name, variant_fields_pat(db, variant)))
+        })
+        .collect();
+    if missing.is_empty() {
+        return None;
+    }
+
+    ctx.add_action(
+        AssistId("fill_match_arms"),
+        "fill match arms",
+        match_expr.syntax().range(),
+        |edit| {
+            if arms.is_empty() {
+                let mut buf = format!("match {} {{\n", expr.syntax().text().to_string());
+                for pat in missing {
+                    writeln!(&mut buf, "    {} => todo!(),", pat).unwrap();
+                }
+                buf.push_str("}");
+                edit.set_cursor(expr.syntax().range().start());
+                edit.replace_node_and_indent(match_expr.syntax(), buf);
+                return;
+            }
+
+            let indent = leading_indent(arms[0].syntax()).unwrap_or("");
+            // New arms go before the first catch-all arm, so that they are reachable.
+            let catch_all = arms
+                .iter()
+                .find(|arm| arm.guard().is_none() && arm.pats().any(|pat| is_irrefutable(pat)));
+            match catch_all {
+                Some(catch_all) => {
+                    let mut buf = String::new();
+                    for pat in missing {
+                        write!(&mut buf, "{} => todo!(),\n{}", pat, indent).unwrap();
+                    }
+                    let offset = catch_all.syntax().range().start();
+                    edit.insert(offset, buf);
+                    edit.set_cursor(offset);
+                }
+                None => {
+                    let last = arms[arms.len() - 1];
+                    let mut buf = String::new();
+                    let comma =
+                        last.syntax().next_sibling_or_token().filter(|it| it.kind() == COMMA);
+                    let offset = match comma {
+                        Some(comma) => comma.range().end(),
+                        None => {
+                            let is_block = last.expr().map_or(false, |it| match it.kind() {
+                                ast::ExprKind::BlockExpr(_) => true,
+                                _ => false,
+                            });
+                            if !is_block {
+                                buf.push(',');
+                            }
+                            last.syntax().range().end()
+                        }
+                    };
+                    for pat in missing {
+                        write!(&mut buf, "\n{}{} => todo!(),", indent, pat).unwrap();
                     }
-                })
-                .collect::<Vec<_>>();
+                    edit.insert(offset, buf);
+                    edit.set_cursor(offset);
+                }
+            }
+        },
+    );
 
-            match pat.first().map(|s| s.as_str()) {
-                Some("_") => write!(&mut buf, "({})", pat.join(", ")).unwrap(),
-                Some(_) => write!(&mut buf, "{{{}}}", pat.join(", ")).unwrap(),
-                None => (),
-            };
+    ctx.build()
+}
 
-            buf.push_str(" => (),\n");
+/// Returns the pattern for the fields of `variant`, like `(_, _)` or `{x, y}`.
+fn variant_fields_pat(db: &impl HirDatabase, variant: EnumVariant) -> String {
+    let pat = variant
+        .fields(db)
+        .into_iter()
+        .map(|field| {
+            let name = field.name(db).to_string();
+            let (_, source) = field.source(db);
+            match source {
+                FieldSource::Named(_) => name,
+                FieldSource::Pos(_) => "_".to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    match pat.first().map(|s| s.as_str()) {
+        Some("_") => format!("({})", pat.join(", ")),
+        Some(_) => format!("{{{}}}", pat.join(", ")),
+        None => String::new(),
+    }
+}
+
+/// If `pat` matches every value of some enum variant, returns that variant.
+fn covered_variant(
+    db: &impl HirDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    pat: &ast::Pat,
+) -> Option<EnumVariant> {
+    let path = match pat.kind() {
+        ast::PatKind::RefPat(it) => return covered_variant(db, analyzer, it.pat()?),
+        ast::PatKind::PathPat(it) => it.path()?,
+        ast::PatKind::TupleStructPat(it) => {
+            if !it.args().all(is_irrefutable) {
+                return None;
+            }
+            it.path()?
         }
-        buf.push_str("}");
-        edit.target(match_expr.syntax().range());
-        edit.set_cursor(expr.syntax().range().start());
-        edit.replace_node_and_indent(match_expr.syntax(), buf);
-    });
+        ast::PatKind::StructPat(it) => {
+            let fields = it.field_pat_list()?;
+            if !fields.field_pats().filter_map(|it| it.pat()).all(is_irrefutable) {
+                return None;
+            }
+            it.path()?
+        }
+        _ => return None,
+    };
+    match analyzer.resolve_path(db, path)? {
+        PathResolution::Def(ModuleDef::EnumVariant(it)) => Some(it),
+        _ => None,
+    }
+}
 
-    ctx.build()
+// FIXME: a lone identifier may also be a unit variant brought into scope with
+// a `use`, in which case it is not a catch-all.
+fn is_irrefutable(pat: &ast::Pat) -> bool {
+    match pat.kind() {
+        ast::PatKind::PlaceholderPat(_) => true,
+        ast::PatKind::BindPat(it) => it.pat().map_or(true, is_irrefutable),
+        ast::PatKind::RefPat(it) => it.pat().map_or(false, is_irrefutable),
+        ast::PatKind::TuplePat(it) => it.args().all(is_irrefutable),
+        _ => false,
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::helpers::{check_assist, check_assist_target};
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
 
     use super::fill_match_arms;
 
@@ -105,11 +207,11 @@ mod tests {
             fn main() {
                 let a = A::As;
                 match <|>a {
-                    A::As => (),
-                    A::Bs => (),
-                    A::Cs(_) => (),
-                    A::Ds(_, _) => (),
-                    A::Es{x, y} => (),
+                    A::As => todo!(),
+                    A::Bs => todo!(),
+                    A::Cs(_) => todo!(),
+                    A::Ds(_, _) => todo!(),
+                    A::Es{x, y} => todo!(),
                 }
             }
             "#,
@@ -137,7 +239,7 @@ mod tests {
 
             fn foo(a: &A) {
                 match <|>a {
-                    A::As => (),
+                    A::As => todo!(),
                 }
             }
             "#,
@@ -162,7 +264,7 @@ mod tests {
 
             fn foo(a: &mut A) {
                 match <|>a {
-                    A::Es{x, y} => (),
+                    A::Es{x, y} => todo!(),
                 }
             }
             "#,
@@ -182,8 +284,8 @@ mod tests {
 
             fn main() {
                 match <|>&E::X {
-                    E::X => (),
-                    E::Y => (),
+                    E::X => todo!(),
+                    E::Y => todo!(),
                 }
             }
             "#,
@@ -206,8 +308,8 @@ mod tests {
 
             fn main() {
                 match <|>E::X {
-                    E::X => (),
-                    E::Y => (),
+                    E::X => todo!(),
+                    E::Y => todo!(),
                 }
             }
             "#,
@@ -228,4 +330,89 @@ mod tests {
             "match E::X {}",
         );
     }
+
+    #[test]
+    fn fill_match_arms_partial() {
+        check_assist(
+            fill_match_arms,
+            r#"
+            enum A {
+                As,
+                Bs(u32),
+                Cs{x: usize},
+            }
+
+            fn main() {
+                match A::As<|> {
+                    A::As => 1,
+                    A::Bs(0) => 2,
+                    A::Cs{..} => {}
+                }
+            }
+            "#,
+            r#"
+            enum A {
+                As,
+                Bs(u32),
+                Cs{x: usize},
+            }
+
+            fn main() {
+                match A::As {
+                    A::As => 1,
+                    A::Bs(0) => 2,
+                    A::Cs{..} => {}<|>
+                    A::Bs(_) => todo!(),
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn fill_match_arms_before_catch_all() {
+        check_assist(
+            fill_match_arms,
+            r#"
+            enum A { As, Bs, Cs }
+
+            fn main() {
+                match A::As<|> {
+                    A::As => (),
+                    A::Bs if true => (),
+                    _ => (),
+                }
+            }
+            "#,
+            r#"
+            enum A { As, Bs, Cs }
+
+            fn main() {
+                match A::As {
+                    A::As => (),
+                    A::Bs if true => (),
+                    <|>A::Bs => todo!(),
+                    A::Cs => todo!(),
+                    _ => (),
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn fill_match_arms_all_covered() {
+        check_assist_not_applicable(
+            fill_match_arms,
+            r#"
+            enum A { As, Bs(u32) }
+
+            fn main() {
+                match A::As<|> {
+                    A::As | A::Bs(_) => (),
+                }
+            }
+            "#,
+        );
+    }
 }
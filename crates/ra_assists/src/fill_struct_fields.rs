@@ -38,11 +38,15 @@ where
         let named_field_list = self.named_field_list;
         let struct_fields_string = self.struct_fields_string()?;
         let struct_lit = self.struct_lit;
-        self.ctx.add_action(AssistId("fill_struct_fields"), "fill struct fields", |edit| {
-            edit.target(struct_lit.syntax().range());
-            edit.set_cursor(struct_lit.syntax().range().start());
-            edit.replace_node_and_indent(named_field_list.syntax(), struct_fields_string);
-        });
+        self.ctx.add_action(
+            AssistId("fill_struct_fields"),
+            "fill struct fields",
+            struct_lit.syntax().range(),
+            |edit| {
+                edit.set_cursor(struct_lit.syntax().range().start());
+                edit.replace_node_and_indent(named_field_list.syntax(), struct_fields_string);
+            },
+        );
         Some(())
     }
 
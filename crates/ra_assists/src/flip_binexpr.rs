@@ -20,8 +20,7 @@ pub(crate) fn flip_binexpr(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assis
         return None;
     }
 
-    ctx.add_action(AssistId("flip_binexpr"), "flip binary expression", |edit| {
-        edit.target(op_range);
+    ctx.add_action(AssistId("flip_binexpr"), "flip binary expression", op_range, |edit| {
         if let FlipAction::FlipAndReplaceOp(new_op) = action {
             edit.replace(op_range, new_op);
         }
@@ -55,6 +54,14 @@ impl From<BinOp> for FlipAction {
             BinOp::BitOrAssign => FlipAction::DontFlip,
             BinOp::BitAndAssign => FlipAction::DontFlip,
             BinOp::BitXorAssign => FlipAction::DontFlip,
+            // These are not commutative, flipping them changes the meaning.
+            BinOp::Subtraction => FlipAction::DontFlip,
+            BinOp::Division => FlipAction::DontFlip,
+            BinOp::Remainder => FlipAction::DontFlip,
+            BinOp::LeftShift => FlipAction::DontFlip,
+            BinOp::RightShift => FlipAction::DontFlip,
+            BinOp::RangeRightOpen => FlipAction::DontFlip,
+            BinOp::RangeRightClosed => FlipAction::DontFlip,
             BinOp::GreaterTest => FlipAction::FlipAndReplaceOp("<"),
             BinOp::GreaterEqualTest => FlipAction::FlipAndReplaceOp("<="),
             BinOp::LesserTest => FlipAction::FlipAndReplaceOp(">"),
@@ -80,6 +87,13 @@ mod tests {
         check_assist_not_applicable(flip_binexpr, "fn f() { let mut _x = 1; _x +=<|> 2 }")
     }
 
+    #[test]
+    fn flip_binexpr_not_applicable_for_non_commutative_ops() {
+        check_assist_not_applicable(flip_binexpr, "fn f() { let res = 1 -<|> 2; }");
+        check_assist_not_applicable(flip_binexpr, "fn f() { let res = 1 <<<|> 2; }");
+        check_assist_not_applicable(flip_binexpr, "fn f() { let res = 1..<|>2; }");
+    }
+
     #[test]
     fn flip_binexpr_works_for_eq() {
         check_assist(
@@ -1,7 +1,7 @@
 use hir::db::HirDatabase;
 use ra_syntax::{
     Direction,
-    SyntaxKind::COMMA,
+    SyntaxKind::{COMMA, R_PAREN, R_BRACK, R_CURLY, R_ANGLE},
     algo::non_trivia_sibling,
 };
 
@@ -11,8 +11,12 @@ pub(crate) fn flip_comma(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist>
     let comma = ctx.token_at_offset().find(|leaf| leaf.kind() == COMMA)?;
     let prev = non_trivia_sibling(comma.into(), Direction::Prev)?;
     let next = non_trivia_sibling(comma.into(), Direction::Next)?;
-    ctx.add_action(AssistId("flip_comma"), "flip comma", |edit| {
-        edit.target(comma.range());
+    // A trailing comma has nothing to swap with.
+    match next.kind() {
+        R_PAREN | R_BRACK | R_CURLY | R_ANGLE => return None,
+        _ => (),
+    }
+    ctx.add_action(AssistId("flip_comma"), "flip comma", comma.range(), |edit| {
         edit.replace(prev.range(), next.to_string());
         edit.replace(next.range(), prev.to_string());
     });
@@ -24,7 +28,7 @@ pub(crate) fn flip_comma(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist>
 mod tests {
     use super::*;
 
-    use crate::helpers::{check_assist, check_assist_target};
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
 
     #[test]
     fn flip_comma_works_for_function_parameters() {
@@ -35,6 +39,16 @@ mod tests {
         )
     }
 
+    #[test]
+    fn flip_comma_works_for_call_arguments() {
+        check_assist(flip_comma, "fn f() { foo(1,<|> bar(2)); }", "fn f() { foo(bar(2),<|> 1); }")
+    }
+
+    #[test]
+    fn flip_comma_not_applicable_for_trailing_comma() {
+        check_assist_not_applicable(flip_comma, "fn f() { foo(1, 2,<|>); }");
+    }
+
     #[test]
     fn flip_comma_target() {
         check_assist_target(flip_comma, "fn foo(x: i32,<|> y: Result<(), ()>) {}", ",")
@@ -23,56 +23,60 @@ pub(crate) fn introduce_variable(mut ctx: AssistCtx<impl HirDatabase>) -> Option
     if indent.kind() != WHITESPACE {
         return None;
     }
-    ctx.add_action(AssistId("introduce_variable"), "introduce variable", move |edit| {
-        let mut buf = String::new();
-
-        let cursor_offset = if wrap_in_block {
-            buf.push_str("{ let var_name = ");
-            TextUnit::of_str("{ let ")
-        } else {
-            buf.push_str("let var_name = ");
-            TextUnit::of_str("let ")
-        };
-
-        expr.syntax().text().push_to(&mut buf);
-        let full_stmt = ast::ExprStmt::cast(anchor_stmt);
-        let is_full_stmt = if let Some(expr_stmt) = full_stmt {
-            Some(expr.syntax()) == expr_stmt.expr().map(|e| e.syntax())
-        } else {
-            false
-        };
-        if is_full_stmt {
-            tested_by!(test_introduce_var_expr_stmt);
-            if !full_stmt.unwrap().has_semi() {
-                buf.push_str(";");
-            }
-            edit.replace(expr.syntax().range(), buf);
-        } else {
-            buf.push_str(";");
-
-            // We want to maintain the indent level,
-            // but we do not want to duplicate possible
-            // extra newlines in the indent block
-            let text = indent.text();
-            if text.starts_with("\r\n") {
-                buf.push_str("\r\n");
-                buf.push_str(text.trim_start_matches("\r\n"));
-            } else if text.starts_with("\n") {
-                buf.push_str("\n");
-                buf.push_str(text.trim_start_matches("\n"));
+    ctx.add_action(
+        AssistId("introduce_variable"),
+        "introduce variable",
+        expr.syntax().range(),
+        move |edit| {
+            let mut buf = String::new();
+
+            let cursor_offset = if wrap_in_block {
+                buf.push_str("{ let var_name = ");
+                TextUnit::of_str("{ let ")
             } else {
-                buf.push_str(text);
-            }
+                buf.push_str("let var_name = ");
+                TextUnit::of_str("let ")
+            };
+
+            expr.syntax().text().push_to(&mut buf);
+            let full_stmt = ast::ExprStmt::cast(anchor_stmt);
+            let is_full_stmt = if let Some(expr_stmt) = full_stmt {
+                Some(expr.syntax()) == expr_stmt.expr().map(|e| e.syntax())
+            } else {
+                false
+            };
+            if is_full_stmt {
+                tested_by!(test_introduce_var_expr_stmt);
+                if !full_stmt.unwrap().has_semi() {
+                    buf.push_str(";");
+                }
+                edit.replace(expr.syntax().range(), buf);
+            } else {
+                buf.push_str(";");
 
-            edit.target(expr.syntax().range());
-            edit.replace(expr.syntax().range(), "var_name".to_string());
-            edit.insert(anchor_stmt.range().start(), buf);
-            if wrap_in_block {
-                edit.insert(anchor_stmt.range().end(), " }");
+                // We want to maintain the indent level,
+                // but we do not want to duplicate possible
+                // extra newlines in the indent block
+                let text = indent.text();
+                if text.starts_with("\r\n") {
+                    buf.push_str("\r\n");
+                    buf.push_str(text.trim_start_matches("\r\n"));
+                } else if text.starts_with("\n") {
+                    buf.push_str("\n");
+                    buf.push_str(text.trim_start_matches("\n"));
+                } else {
+                    buf.push_str(text);
+                }
+
+                edit.replace(expr.syntax().range(), "var_name".to_string());
+                edit.insert(anchor_stmt.range().start(), buf);
+                if wrap_in_block {
+                    edit.insert(anchor_stmt.range().end(), " }");
+                }
             }
-        }
-        edit.set_cursor(anchor_stmt.range().start() + cursor_offset);
-    });
+            edit.set_cursor(anchor_stmt.range().start() + cursor_offset);
+        },
+    );
 
     ctx.build()
 }
@@ -12,7 +12,8 @@ use itertools::Itertools;
 
 use ra_text_edit::TextEdit;
 use ra_syntax::{TextRange, TextUnit};
-use ra_db::FileRange;
+use ra_db::{FileRange, SourceRootId};
+use relative_path::RelativePathBuf;
 use hir::db::HirDatabase;
 
 pub(crate) use crate::assist_ctx::{AssistCtx, Assist};
@@ -22,37 +23,57 @@ pub(crate) use crate::assist_ctx::{AssistCtx, Assist};
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AssistId(pub &'static str);
 
+/// Label of a group of alternative assists, like the different visibilities
+/// an item can be given. Editors may show the whole group as a single entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupLabel(pub String);
+
 #[derive(Debug, Clone)]
 pub struct AssistLabel {
     /// Short description of the assist, as shown in the UI.
     pub label: String,
     pub id: AssistId,
+    /// The syntax the assist applies to. Assists with smaller targets are
+    /// more relevant and come first.
+    pub target: TextRange,
+    pub group: Option<GroupLabel>,
 }
 
 #[derive(Debug, Clone)]
 pub struct AssistAction {
     pub edit: TextEdit,
     pub cursor_position: Option<TextUnit>,
-    pub target: Option<TextRange>,
+    /// Files which should be created alongside the `edit`.
+    pub new_files: Vec<AssistNewFile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssistNewFile {
+    pub source_root: SourceRootId,
+    pub path: RelativePathBuf,
+    pub text: String,
 }
 
 /// Return all the assists applicable at the given position.
 ///
 /// Assists are returned in the "unresolved" state, that is only labels are
-/// returned, without actual edits.
+/// returned, without actual edits. Use `resolve_assist` to compute the edit of
+/// the one picked by the user.
 pub fn applicable_assists<H>(db: &H, range: FileRange) -> Vec<AssistLabel>
 where
     H: HirDatabase + 'static,
 {
     AssistCtx::with_ctx(db, range, false, |ctx| {
-        all_assists()
+        let mut a = all_assists()
             .iter()
             .filter_map(|f| f(ctx.clone()))
             .map(|a| match a {
                 Assist::Unresolved(labels) => labels,
                 Assist::Resolved(..) => unreachable!(),
             })
-            .concat()
+            .concat();
+        a.sort_by_key(|it| it.target.len());
+        a
     })
 }
 
@@ -64,8 +85,6 @@ pub fn assists<H>(db: &H, range: FileRange) -> Vec<(AssistLabel, AssistAction)>
 where
     H: HirDatabase + 'static,
 {
-    use std::cmp::Ordering;
-
     AssistCtx::with_ctx(db, range, true, |ctx| {
         let mut a = all_assists()
             .iter()
@@ -75,50 +94,105 @@ where
                 Assist::Unresolved(..) => unreachable!(),
             })
             .concat();
-        a.sort_by(|a, b| match (a.1.target, b.1.target) {
-            (Some(a), Some(b)) => a.len().cmp(&b.len()),
-            (Some(_), None) => Ordering::Less,
-            (None, Some(_)) => Ordering::Greater,
-            (None, None) => Ordering::Equal,
-        });
+        a.sort_by_key(|(label, _)| label.target.len());
         a
     })
 }
 
+/// Computes the edit of the assist with the given `id` and `label` applicable
+/// at the given position, if it's still applicable.
+///
+/// Only the assist providing the action is resolved, the edits of all the
+/// others are never computed.
+pub fn resolve_assist<H>(
+    db: &H,
+    range: FileRange,
+    id: &str,
+    label: &str,
+) -> Option<(AssistLabel, AssistAction)>
+where
+    H: HirDatabase + 'static,
+{
+    let is_wanted = |it: &AssistLabel| it.id.0 == id && it.label == label;
+    let assist = all_assists().iter().find(|f| {
+        match AssistCtx::with_ctx(db, range, false, |ctx| f(ctx)) {
+            Some(Assist::Unresolved(labels)) => labels.iter().any(is_wanted),
+            _ => false,
+        }
+    })?;
+    match AssistCtx::with_ctx(db, range, true, |ctx| assist(ctx))? {
+        Assist::Resolved(labels_actions) => {
+            labels_actions.into_iter().find(|(label, _)| is_wanted(label))
+        }
+        Assist::Unresolved(..) => unreachable!(),
+    }
+}
+
 mod add_derive;
+mod sort_derives;
 mod add_explicit_type;
 mod add_impl;
+mod add_new;
+mod add_getters;
+mod add_from_impl_for_enum;
 mod flip_comma;
 mod flip_binexpr;
+mod move_bounds;
+mod move_module_to_file;
 mod change_visibility;
+mod convert_tuple_struct;
 mod fill_match_arms;
 mod fill_struct_fields;
 mod introduce_variable;
 mod inline_local_variable;
 mod replace_if_let_with_match;
+mod replace_match_with_if_let;
 mod split_import;
+mod merge_imports;
+mod unmerge_use;
+mod replace_glob_import;
 mod remove_dbg;
 mod auto_import;
+mod qualify_path;
 mod add_missing_impl_members;
+mod wrap_return_type_in_result;
+mod add_try_operator;
+mod turbofish;
 
 fn all_assists<DB: HirDatabase>() -> &'static [fn(AssistCtx<DB>) -> Option<Assist>] {
     &[
         add_derive::add_derive,
+        sort_derives::sort_derives,
         add_explicit_type::add_explicit_type,
         add_impl::add_impl,
+        add_new::add_new,
+        add_getters::add_getters,
+        add_from_impl_for_enum::add_from_impl_for_enum,
         change_visibility::change_visibility,
+        convert_tuple_struct::convert_tuple_struct_to_named_struct,
         fill_match_arms::fill_match_arms,
         fill_struct_fields::fill_struct_fields,
         flip_comma::flip_comma,
         flip_binexpr::flip_binexpr,
+        move_bounds::move_bounds_to_where_clause,
+        move_module_to_file::move_module_to_file,
         introduce_variable::introduce_variable,
         replace_if_let_with_match::replace_if_let_with_match,
+        replace_match_with_if_let::replace_match_with_if_let,
         split_import::split_import,
+        merge_imports::merge_imports,
+        unmerge_use::unmerge_use,
+        replace_glob_import::replace_glob_import,
         remove_dbg::remove_dbg,
         auto_import::auto_import,
+        qualify_path::qualify_path,
         add_missing_impl_members::add_missing_impl_members,
         add_missing_impl_members::add_missing_default_members,
         inline_local_variable::inline_local_varialbe,
+        wrap_return_type_in_result::wrap_return_type_in_result,
+        add_try_operator::add_try_operator,
+        turbofish::add_turbofish,
+        turbofish::remove_turbofish,
     ]
 }
 
@@ -234,8 +308,8 @@ mod helpers {
             Assist::Resolved(labels_actions) => labels_actions,
         };
 
-        let (_, action) = labels_actions.get(index).expect("expect assist action at index");
-        let range = action.target.expect("expected target on action");
+        let (label, _) = labels_actions.get(index).expect("expect assist action at index");
+        let range = label.target;
         assert_eq_text!(&before[range.start().to_usize()..range.end().to_usize()], target);
     }
 
@@ -255,8 +329,8 @@ mod helpers {
             Assist::Resolved(labels_actions) => labels_actions,
         };
 
-        let (_, action) = labels_actions.get(index).expect("expect assist action at index");
-        let range = action.target.expect("expected target on action");
+        let (label, _) = labels_actions.get(index).expect("expect assist action at index");
+        let range = label.target;
         assert_eq_text!(&before[range.start().to_usize()..range.end().to_usize()], target);
     }
 
@@ -302,6 +376,7 @@ mod tests {
         let mut assists = assists.iter();
 
         assert_eq!(assists.next().expect("expected assist").0.label, "make pub(crate)");
+        assert_eq!(assists.next().expect("expected assist").0.label, "make pub");
         assert_eq!(assists.next().expect("expected assist").0.label, "add `#[derive]`");
     }
 
@@ -325,4 +400,25 @@ mod tests {
         assert_eq!(assists.next().expect("expected assist").0.label, "replace with match");
     }
 
+    #[test]
+    fn resolve_assist_by_label() {
+        let before = "struct Foo { <|>bar: u32 }";
+        let (before_cursor_pos, before) = extract_offset(before);
+        let (db, _source_root, file_id) = MockDatabase::with_single_file(&before);
+        let frange =
+            FileRange { file_id, range: TextRange::offset_len(before_cursor_pos, 0.into()) };
+        let labels = super::applicable_assists(&db, frange);
+        let group = labels[0].group.clone().expect("expected a group");
+        assert_eq!(labels[1].group, Some(group));
+
+        let (label, action) = super::resolve_assist(&db, frange, "change_visibility", "make pub")
+            .expect("expected assist");
+        assert_eq!(label.label, "make pub");
+        let actual = action.edit.apply(&before);
+        assert_eq!(actual, "struct Foo { pub bar: u32 }");
+
+        assert!(
+            super::resolve_assist(&db, frange, "change_visibility", "make pub(super)").is_none()
+        );
+    }
 }
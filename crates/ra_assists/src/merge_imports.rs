@@ -0,0 +1,114 @@
+use hir::db::HirDatabase;
+use ra_syntax::{
+    AstNode, TextRange,
+    SyntaxKind::WHITESPACE,
+    ast::{self, AttrsOwner, VisibilityOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId};
+
+/// Merges the `use` item under the cursor with the next one, if both import
+/// from the same path, like `use foo::a; use foo::b;` into `use foo::{a, b};`.
+pub(crate) fn merge_imports(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let use_item = ctx.node_at_offset::<ast::UseItem>()?;
+    let next = use_item.syntax().next_sibling().and_then(ast::UseItem::cast)?;
+    let vis = use_item.visibility().map(|it| it.syntax().text().to_string());
+    let next_vis = next.visibility().map(|it| it.syntax().text().to_string());
+    if vis != next_vis {
+        return None;
+    }
+    // Merging `#[cfg(test)] use foo::b;` into an unconditional import would
+    // change what it means.
+    if attrs_text(use_item) != attrs_text(next) {
+        return None;
+    }
+    let tree = use_item.use_tree()?;
+    let (prefix, mut trees) = split_use_tree(tree)?;
+    let (next_prefix, next_trees) = split_use_tree(next.use_tree()?)?;
+    if prefix != next_prefix {
+        return None;
+    }
+    trees.extend(next_trees);
+
+    ctx.add_action(AssistId("merge_imports"), "merge imports", use_item.syntax().range(), |edit| {
+        edit.replace(
+            tree.syntax().range(),
+            format!("{}::{{{}}}", prefix, trees.join(", ")),
+        );
+        // Only the second item goes away, comments in between stay.
+        let start = match next.syntax().prev_sibling_or_token() {
+            Some(it) if it.kind() == WHITESPACE => it.range().start(),
+            _ => next.syntax().range().start(),
+        };
+        edit.delete(TextRange::from_to(start, next.syntax().range().end()));
+        edit.set_cursor(use_item.syntax().range().start());
+    });
+
+    ctx.build()
+}
+
+fn attrs_text(item: &ast::UseItem) -> Vec<String> {
+    item.attrs().map(|it| it.syntax().text().to_string()).collect()
+}
+
+/// Splits `foo::bar::{a, b}` into `foo::bar` and the list of imported trees.
+/// A tree without a list, like `foo::bar::a`, is split at the last segment.
+fn split_use_tree(tree: &ast::UseTree) -> Option<(String, Vec<String>)> {
+    let path = tree.path()?;
+    if let Some(list) = tree.use_tree_list() {
+        let trees = list.use_trees().map(|it| it.syntax().text().to_string()).collect();
+        return Some((path.syntax().text().to_string(), trees));
+    }
+    if tree.has_star() {
+        return Some((path.syntax().text().to_string(), vec!["*".to_string()]));
+    }
+    let mut last = path.segment()?.syntax().text().to_string();
+    if let Some(alias) = tree.alias() {
+        last.push_str(" ");
+        last.push_str(&alias.syntax().text().to_string());
+    }
+    Some((path.qualifier()?.syntax().text().to_string(), vec![last]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    #[test]
+    fn test_merge_imports() {
+        check_assist(
+            merge_imports,
+            "use foo::<|>a;\nuse foo::b as c;\nfn main() {}",
+            "<|>use foo::{a, b as c};\nfn main() {}",
+        );
+        check_assist(
+            merge_imports,
+            "pub use foo::bar::{a, b}<|>;\npub use foo::bar::*;",
+            "<|>pub use foo::bar::{a, b, *};",
+        );
+        check_assist(
+            merge_imports,
+            "use foo::<|>a;\n// keep me\nuse foo::b;",
+            "<|>use foo::{a, b};\n// keep me",
+        );
+        check_assist(
+            merge_imports,
+            "#[cfg(test)]\nuse foo::<|>a;\n#[cfg(test)]\nuse foo::b;",
+            "<|>#[cfg(test)]\nuse foo::{a, b};",
+        );
+    }
+
+    #[test]
+    fn merge_imports_not_applicable() {
+        check_assist_not_applicable(merge_imports, "use foo::<|>a;\nuse bar::b;");
+        check_assist_not_applicable(merge_imports, "use foo::<|>a;\npub use foo::b;");
+        check_assist_not_applicable(merge_imports, "use foo::<|>a;\nfn main() {}\nuse foo::b;");
+        check_assist_not_applicable(merge_imports, "use foo::<|>a;\n#[cfg(test)]\nuse foo::b;");
+    }
+
+    #[test]
+    fn merge_imports_target() {
+        check_assist_target(merge_imports, "use foo::<|>a;\nuse foo::b;", "use foo::a;");
+    }
+}
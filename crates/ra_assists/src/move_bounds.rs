@@ -0,0 +1,149 @@
+use hir::db::HirDatabase;
+use ra_syntax::{
+    AstNode, SyntaxElement, SyntaxNode, TextRange,
+    SyntaxKind::{COMMA, SEMI, WHITESPACE},
+    ast::{self, NameOwner, TypeBoundsOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId};
+
+/// Moves the bounds of the type parameters into a `where` clause, like
+/// `fn foo<T: Clone>()` into `fn foo<T>() where T: Clone`.
+pub(crate) fn move_bounds_to_where_clause(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let type_param_list = ctx.node_at_offset::<ast::TypeParamList>()?;
+    let parent = type_param_list.syntax().parent()?;
+    let anchor = where_clause_anchor(parent)?;
+    let where_clause = where_clause(parent);
+
+    let params: Vec<(&ast::TypeParam, &ast::TypeBoundList)> =
+        type_param_list.type_params().filter_map(|it| Some((it, it.type_bound_list()?))).collect();
+    if params.is_empty() {
+        return None;
+    }
+    let predicates = params
+        .iter()
+        .map(|(param, bounds)| {
+            Some(format!("{}: {}", param.name()?.text(), bounds.syntax().text()))
+        })
+        .collect::<Option<Vec<_>>>()?
+        .join(", ");
+
+    ctx.add_action(
+        AssistId("move_bounds_to_where_clause"),
+        "move bounds to where clause",
+        type_param_list.syntax().range(),
+        |edit| {
+            for (param, bounds) in params.iter() {
+                // Both are present, we checked for the name above.
+                let name_end = param.name().unwrap().syntax().range().end();
+                edit.delete(TextRange::from_to(name_end, bounds.syntax().range().end()));
+            }
+            match where_clause {
+                Some(where_clause) => {
+                    let last = where_clause.syntax().last_child_or_token();
+                    match last.filter(|it| it.kind() == COMMA) {
+                        Some(comma) => edit.insert(comma.range().end(), format!(" {}", predicates)),
+                        None => edit.insert(
+                            where_clause.syntax().range().end(),
+                            format!(", {}", predicates),
+                        ),
+                    }
+                }
+                None => {
+                    let has_space_before =
+                        anchor.prev_sibling_or_token().map(|it| it.kind()) == Some(WHITESPACE);
+                    let text = match (anchor.kind(), has_space_before) {
+                        (SEMI, _) => format!(" where {}", predicates),
+                        (_, true) => format!("where {} ", predicates),
+                        (_, false) => format!(" where {} ", predicates),
+                    };
+                    edit.insert(anchor.range().start(), text);
+                }
+            }
+        },
+    );
+
+    ctx.build()
+}
+
+fn where_clause(item: &SyntaxNode) -> Option<&ast::WhereClause> {
+    item.children().find_map(ast::WhereClause::cast)
+}
+
+/// The element before which a new `where` clause for `item` should go: the
+/// body, or the `;` of bodyless items.
+fn where_clause_anchor(item: &SyntaxNode) -> Option<SyntaxElement> {
+    let body: Option<&SyntaxNode> = if let Some(it) = ast::FnDef::cast(item) {
+        it.body().map(|it| it.syntax())
+    } else if let Some(it) = ast::ImplBlock::cast(item) {
+        it.item_list().map(|it| it.syntax())
+    } else if let Some(it) = ast::TraitDef::cast(item) {
+        it.item_list().map(|it| it.syntax())
+    } else if let Some(it) = ast::EnumDef::cast(item) {
+        it.variant_list().map(|it| it.syntax())
+    } else if let Some(it) = ast::StructDef::cast(item) {
+        match it.kind() {
+            ast::StructKind::Named(it) => Some(it.syntax()),
+            _ => None,
+        }
+    } else {
+        return None;
+    };
+    match body {
+        Some(it) => Some(it.into()),
+        None => item.children_with_tokens().find(|it| it.kind() == SEMI),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    #[test]
+    fn move_bounds_to_where_clause_fn() {
+        check_assist(
+            move_bounds_to_where_clause,
+            "fn foo<T: Clone + Debug, <|>U, V: Copy>(t: T) -> T {}",
+            "fn foo<T, <|>U, V>(t: T) -> T where T: Clone + Debug, V: Copy {}",
+        );
+        check_assist(
+            move_bounds_to_where_clause,
+            "trait Foo { fn foo<<|>T: Clone>(); }",
+            "trait Foo { fn foo<<|>T>() where T: Clone; }",
+        );
+    }
+
+    #[test]
+    fn move_bounds_to_existing_where_clause() {
+        check_assist(
+            move_bounds_to_where_clause,
+            "impl<<|>T: Clone, U> Foo<T, U> for S where U: Copy {}",
+            "impl<<|>T, U> Foo<T, U> for S where U: Copy, T: Clone {}",
+        );
+        check_assist(
+            move_bounds_to_where_clause,
+            "struct S<<|>T: Clone>\nwhere\n    T: Copy,\n{\n    t: T,\n}",
+            "struct S<<|>T>\nwhere\n    T: Copy, T: Clone\n{\n    t: T,\n}",
+        );
+    }
+
+    #[test]
+    fn move_bounds_to_where_clause_tuple_struct() {
+        check_assist(
+            move_bounds_to_where_clause,
+            "struct S<<|>T: Clone>(T);",
+            "struct S<<|>T>(T) where T: Clone;",
+        );
+    }
+
+    #[test]
+    fn move_bounds_to_where_clause_not_applicable_without_bounds() {
+        check_assist_not_applicable(move_bounds_to_where_clause, "fn foo<<|>T, U>() {}");
+    }
+
+    #[test]
+    fn move_bounds_to_where_clause_target() {
+        check_assist_target(move_bounds_to_where_clause, "fn foo<<|>T: Clone>() {}", "<T: Clone>");
+    }
+}
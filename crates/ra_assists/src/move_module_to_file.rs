@@ -0,0 +1,184 @@
+use hir::db::HirDatabase;
+use ra_fmt::{leading_indent, unindent};
+use ra_syntax::{
+    AstNode, TextRange,
+    SyntaxKind::SOURCE_FILE,
+    ast::{self, NameOwner},
+};
+use relative_path::{RelativePath, RelativePathBuf};
+
+use crate::{AssistCtx, Assist, AssistId};
+
+/// Moves the items of an inline `mod foo { ... }` into a new file, leaving
+/// `mod foo;` behind.
+pub(crate) fn move_module_to_file(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let module = ctx.node_at_offset::<ast::Module>()?;
+    let item_list = module.item_list()?;
+    // Don't offer this in the middle of the module's items.
+    if ctx.frange.range.start() > item_list.syntax().range().start() {
+        return None;
+    }
+    // FIXME: submodules of inline modules are not resolved to files yet, so
+    // there's no point in creating a file for them.
+    if module.syntax().parent().map(|it| it.kind()) != Some(SOURCE_FILE) {
+        return None;
+    }
+    let name = module.name()?;
+
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let is_root = db.crate_graph().crate_id_for_crate_root(file_id).is_some();
+    let path = module_file_path(&db.file_relative_path(file_id), &name.text(), is_root);
+    let source_root = db.file_source_root(file_id);
+    if db.source_root(source_root).files.contains_key(&path) {
+        return None;
+    }
+
+    let text = item_list.syntax().text().to_string();
+    let items = &text[1..text.len() - 1];
+    let indent = format!("{}    ", leading_indent(module.syntax()).unwrap_or(""));
+    let mut items = unindent(items, &indent).trim().to_string();
+    if !items.is_empty() {
+        items.push('\n');
+    }
+
+    ctx.add_action(
+        AssistId("move_module_to_file"),
+        "move module to file",
+        module.syntax().range(),
+        |edit| {
+            edit.replace(
+                TextRange::from_to(name.syntax().range().end(), item_list.syntax().range().end()),
+                ";",
+            );
+            edit.create_file(source_root, path, items);
+        },
+    );
+
+    ctx.build()
+}
+
+/// The path of the file for the module `name` declared in the file at `path`.
+/// This mirrors `resolve_submodule` in hir.
+fn module_file_path(path: &RelativePath, name: &str, is_root: bool) -> RelativePathBuf {
+    let dir_path = path.parent().unwrap_or_else(|| RelativePath::new(""));
+    let mod_name = path.file_stem().unwrap_or("unknown");
+    if is_root || mod_name == "mod" {
+        dir_path.join(format!("{}.rs", name))
+    } else {
+        dir_path.join(format!("{}/{}.rs", mod_name, name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hir::mock::MockDatabase;
+    use ra_db::FileRange;
+    use ra_syntax::TextRange;
+    use test_utils::assert_eq_text;
+
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
+
+    fn check_new_file(fixture: &str, path: &str, text: &str) {
+        let (db, position) = MockDatabase::with_position(fixture);
+        let frange = FileRange {
+            file_id: position.file_id,
+            range: TextRange::offset_len(position.offset, 0.into()),
+        };
+        let (_, action) = crate::assists(&db, frange)
+            .into_iter()
+            .find(|(label, _)| label.id == AssistId("move_module_to_file"))
+            .expect("code action is not applicable");
+        assert_eq!(action.new_files.len(), 1);
+        assert_eq!(action.new_files[0].path, path);
+        assert_eq_text!(text, &action.new_files[0].text);
+    }
+
+    #[test]
+    fn move_module_to_file_leaves_declaration() {
+        check_assist(
+            move_module_to_file,
+            "
+fn main() {}
+mod <|>foo {
+    fn bar() {
+        baz()
+    }
+}
+",
+            "
+fn main() {}
+mod <|>foo;
+",
+        );
+    }
+
+    #[test]
+    fn move_module_to_file_creates_file() {
+        check_new_file(
+            "
+//- /lib.rs
+mod <|>foo {
+    #![allow(unused)]
+
+    fn bar() {
+        baz()
+    }
+}
+",
+            "foo.rs",
+            "#![allow(unused)]
+
+fn bar() {
+    baz()
+}
+",
+        );
+        check_new_file(
+            "
+//- /lib.rs
+mod bar;
+//- /bar.rs
+#[cfg(test)]
+mod <|>tests { fn foo() {} }
+",
+            "bar/tests.rs",
+            "fn foo() {}\n",
+        );
+    }
+
+    #[test]
+    fn move_module_to_file_not_applicable() {
+        check_assist_not_applicable(move_module_to_file, "mod foo;<|>");
+        check_assist_not_applicable(move_module_to_file, "mod foo { fn <|>bar() {} }");
+        check_assist_not_applicable(move_module_to_file, "mod foo { mod <|>bar {} }");
+        check_new_file_not_applicable(
+            "
+//- /lib.rs
+mod <|>foo {}
+//- /foo.rs
+fn foo() {}
+",
+        );
+    }
+
+    fn check_new_file_not_applicable(fixture: &str) {
+        let (db, position) = MockDatabase::with_position(fixture);
+        let frange = FileRange {
+            file_id: position.file_id,
+            range: TextRange::offset_len(position.offset, 0.into()),
+        };
+        let assists = crate::assists(&db, frange);
+        assert!(assists.iter().all(|(label, _)| label.id != AssistId("move_module_to_file")));
+    }
+
+    #[test]
+    fn move_module_to_file_target() {
+        check_assist_target(
+            move_module_to_file,
+            "mod <|>foo { fn bar() {} }",
+            "mod foo { fn bar() {} }",
+        );
+    }
+}
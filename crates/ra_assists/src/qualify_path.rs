@@ -0,0 +1,190 @@
+use hir::db::HirDatabase;
+use ra_syntax::{
+    AstNode, SyntaxNode, TextRange,
+    SyntaxKind::WHITESPACE,
+    ast::{self, NameOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId, unmerge_use::range_with_separator};
+
+/// Replaces a name brought into scope by a `use` with the full path it was
+/// imported from, removing the import if it's not used anymore. This is the
+/// inverse of `auto_import`.
+pub(crate) fn qualify_path(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let mut path = ctx.node_at_offset::<ast::Path>()?;
+    while let Some(qualifier) = path.qualifier() {
+        path = qualifier;
+    }
+    if path.syntax().ancestors().find_map(ast::UseItem::cast).is_some() {
+        return None;
+    }
+    let name_ref = path.segment()?.name_ref()?;
+    let name = name_ref.text().as_str();
+    let container = path
+        .syntax()
+        .ancestors()
+        .find(|it| ast::ItemList::cast(it).is_some() || ast::SourceFile::cast(it).is_some())?;
+    let (tree, full_path) = container
+        .children()
+        .filter_map(ast::UseItem::cast)
+        .filter_map(|it| it.use_tree())
+        .find_map(|it| find_import(it, "", name))?;
+
+    let other_uses = container
+        .descendants()
+        .filter_map(ast::NameRef::cast)
+        .filter(|it| it.syntax() != name_ref.syntax() && it.text() == name)
+        .any(|it| it.syntax().ancestors().find_map(ast::UseItem::cast).is_none());
+
+    ctx.add_action(
+        AssistId("qualify_path"),
+        format!("qualify as `{}`", full_path),
+        path.syntax().range(),
+        |edit| {
+            edit.replace(path.syntax().range(), full_path.clone());
+            if !other_uses {
+                edit.delete(import_range(tree));
+            }
+        },
+    );
+
+    ctx.build()
+}
+
+/// Finds the use tree in `tree` importing `name`, with the path it imports.
+fn find_import<'a>(
+    tree: &'a ast::UseTree,
+    prefix: &str,
+    name: &str,
+) -> Option<(&'a ast::UseTree, String)> {
+    let path = match tree.path() {
+        Some(path) if path.syntax().text() == "self" => prefix.to_string(),
+        Some(path) if prefix.is_empty() => path.syntax().text().to_string(),
+        Some(path) => format!("{}::{}", prefix, path.syntax().text()),
+        None => prefix.to_string(),
+    };
+    if let Some(list) = tree.use_tree_list() {
+        return list.use_trees().find_map(|it| find_import(it, &path, name));
+    }
+    if tree.has_star() {
+        return None;
+    }
+    let imported = match tree.alias().and_then(|it| it.name()) {
+        Some(alias) => alias.text().to_string(),
+        None => path.rsplit("::").next()?.to_string(),
+    };
+    if imported == name {
+        Some((tree, path))
+    } else {
+        None
+    }
+}
+
+/// The range to delete to remove `tree` from its `use` item, or the whole
+/// item if `tree` is all it imports.
+fn import_range(mut tree: &ast::UseTree) -> TextRange {
+    loop {
+        let list = match tree.syntax().parent().and_then(ast::UseTreeList::cast) {
+            Some(it) => it,
+            None => break,
+        };
+        if list.use_trees().count() > 1 {
+            return range_with_separator(tree);
+        }
+        tree = list.parent_use_tree();
+    }
+    match tree.syntax().parent() {
+        Some(use_item) => range_with_trailing_whitespace(use_item),
+        None => tree.syntax().range(),
+    }
+}
+
+fn range_with_trailing_whitespace(node: &SyntaxNode) -> TextRange {
+    match node.next_sibling_or_token() {
+        Some(ws) if ws.kind() == WHITESPACE => {
+            TextRange::from_to(node.range().start(), ws.range().end())
+        }
+        _ => node.range(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
+
+    #[test]
+    fn qualify_path_removes_import() {
+        check_assist(
+            qualify_path,
+            "
+use std::fmt::Debug;
+
+impl Debug<|> for Foo {}",
+            "
+impl std::fmt::Debug<|> for Foo {}",
+        );
+    }
+
+    #[test]
+    fn qualify_path_keeps_used_import() {
+        check_assist(
+            qualify_path,
+            "
+use std::collections::HashMap;
+
+fn foo() -> HashMap<u32, u32> {
+    HashMap<|>::new()
+}",
+            "
+use std::collections::HashMap;
+
+fn foo() -> HashMap<u32, u32> {
+    std::collections::HashMap<|>::new()
+}",
+        );
+    }
+
+    #[test]
+    fn qualify_path_in_tree_list() {
+        check_assist(
+            qualify_path,
+            "
+use foo::{bar::{self, Baz}, Quux as Q};
+fn f(_: Q, _: bar::X) -> <|>Baz {}",
+            "
+use foo::{bar::{self}, Quux as Q};
+fn f(_: Q, _: bar::X) -> <|>foo::bar::Baz {}",
+        );
+        check_assist(
+            qualify_path,
+            "
+use foo::{bar::{self, Baz}, Quux as Q};
+fn f(_: Q<|>, _: bar::X) -> Baz {}",
+            "
+use foo::{bar::{self, Baz}};
+fn f(_: foo::Quux<|>, _: bar::X) -> Baz {}",
+        );
+        check_assist(
+            qualify_path,
+            "
+use foo::{bar::{self, Baz}, Quux as Q};
+fn f(_: Q, _: bar<|>::X) -> Baz {}",
+            "
+use foo::{bar::{Baz}, Quux as Q};
+fn f(_: Q, _: foo::bar<|>::X) -> Baz {}",
+        );
+    }
+
+    #[test]
+    fn qualify_path_not_applicable() {
+        check_assist_not_applicable(qualify_path, "use std::fmt::<|>Debug;");
+        check_assist_not_applicable(qualify_path, "use std::fmt::*;\nimpl Debug<|> for Foo {}");
+        check_assist_not_applicable(qualify_path, "fn foo() -> Foo<|> {}");
+    }
+
+    #[test]
+    fn qualify_path_target() {
+        check_assist_target(qualify_path, "use foo::Bar;\nfn f() { Bar::new<|>() }", "Bar");
+    }
+}
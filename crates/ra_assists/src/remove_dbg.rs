@@ -46,8 +46,7 @@ pub(crate) fn remove_dbg(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist>
         macro_args.text().slice(start..end).to_string()
     };
 
-    ctx.add_action(AssistId("remove_dbg"), "remove dbg!()", |edit| {
-        edit.target(macro_call.syntax().range());
+    ctx.add_action(AssistId("remove_dbg"), "remove dbg!()", macro_call.syntax().range(), |edit| {
         edit.replace(macro_range, macro_content);
         edit.set_cursor(cursor_pos);
     });
@@ -0,0 +1,166 @@
+use hir::{
+    ModuleDef, Name, PathResolution,
+    db::HirDatabase,
+};
+use ra_syntax::{
+    AstNode,
+    SyntaxKind::STAR,
+    ast::{self, NameOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId};
+
+/// Replaces `use foo::*;` with the list of names from `foo` which are actually
+/// used in the file.
+pub(crate) fn replace_glob_import(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let star = ctx.token_at_offset().find(|it| it.kind() == STAR)?;
+    let tree = ast::UseTree::cast(star.parent())?;
+    let path = tree.path()?;
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, tree.syntax(), None);
+    let candidates: Vec<(Name, ModuleDef)> = match analyzer.resolve_path(db, path)? {
+        PathResolution::Def(ModuleDef::Module(module)) => module
+            .scope(db)
+            .entries()
+            .flat_map(|(name, res)| {
+                let defs =
+                    res.def.as_ref().take_types().into_iter().chain(res.def.as_ref().take_values());
+                defs.map(move |def| (name.clone(), *def))
+            })
+            .collect(),
+        PathResolution::Def(ModuleDef::Enum(e)) => e
+            .variants(db)
+            .into_iter()
+            .filter_map(|it| Some((it.name(db)?, ModuleDef::EnumVariant(it))))
+            .collect(),
+        _ => return None,
+    };
+
+    let file = tree.syntax().ancestors().last()?;
+    let paths = file
+        .descendants()
+        .filter_map(ast::Path::cast)
+        .filter(|it| it.qualifier().is_none())
+        .filter(|it| !it.syntax().ancestors().any(|it| ast::UseItem::cast(it).is_some()))
+        .filter_map(|path| {
+            let name_ref = path.segment()?.name_ref()?;
+            let def =
+                candidates.iter().find(|(name, _)| name.to_string() == name_ref.text().as_str())?;
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, path.syntax(), None);
+            match analyzer.resolve_path(db, path)? {
+                PathResolution::Def(it) if it == def.1 => Some(name_ref.text().to_string()),
+                _ => None,
+            }
+        });
+    // An identifier pattern refers to a unit variant or a constant if there's
+    // one with the same name in scope.
+    let bind_pats = file
+        .descendants()
+        .filter_map(ast::BindPat::cast)
+        .filter(|it| it.pat().is_none())
+        .filter_map(|it| it.name())
+        .filter(|name| {
+            candidates.iter().any(|(candidate, def)| {
+                candidate.to_string() == name.text().as_str()
+                    && match def {
+                        ModuleDef::EnumVariant(_) | ModuleDef::Const(_) | ModuleDef::Static(_) => {
+                            true
+                        }
+                        _ => false,
+                    }
+            })
+        })
+        .map(|it| it.text().to_string());
+    let mut used: Vec<String> = paths.chain(bind_pats).collect();
+    used.sort();
+    used.dedup();
+    if used.is_empty() {
+        return None;
+    }
+
+    ctx.add_action(AssistId("replace_glob_import"), "replace glob import", star.range(), |edit| {
+        let replacement =
+            if used.len() == 1 { used[0].clone() } else { format!("{{{}}}", used.join(", ")) };
+        edit.replace(star.range(), replacement);
+        edit.set_cursor(star.range().start());
+    });
+
+    ctx.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    #[test]
+    fn test_replace_glob_import() {
+        check_assist(
+            replace_glob_import,
+            "
+mod foo {
+    pub struct A;
+    pub struct B;
+    pub fn c() {}
+    pub mod d { pub fn e() {} }
+}
+use foo::<|>*;
+fn main() {
+    let _: A = A;
+    c();
+    d::e();
+}",
+            "
+mod foo {
+    pub struct A;
+    pub struct B;
+    pub fn c() {}
+    pub mod d { pub fn e() {} }
+}
+use foo::<|>{A, c, d};
+fn main() {
+    let _: A = A;
+    c();
+    d::e();
+}",
+        );
+    }
+
+    #[test]
+    fn replace_glob_import_of_enum_variants() {
+        check_assist(
+            replace_glob_import,
+            "
+enum E { X, Y }
+use E::*<|>;
+fn f(e: E) { match e { X => (), _ => () } let y = Y; }",
+            "
+enum E { X, Y }
+use E::<|>{X, Y};
+fn f(e: E) { match e { X => (), _ => () } let y = Y; }",
+        );
+    }
+
+    #[test]
+    fn replace_glob_import_ignores_shadowed_names() {
+        check_assist_not_applicable(
+            replace_glob_import,
+            "
+mod foo { pub fn a() {} }
+use foo::<|>*;
+fn a() {}
+fn main() { a(); }",
+        );
+    }
+
+    #[test]
+    fn replace_glob_import_target() {
+        check_assist_target(
+            replace_glob_import,
+            "mod foo { pub fn a() {} }\nuse foo::<|>*;\nfn main() { a(); }",
+            "*",
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use ra_syntax::{AstNode, ast};
-use ra_fmt::extract_trivial_expression;
+use ra_fmt::{extract_trivial_expression, leading_indent, reindent, unindent};
 use hir::db::HirDatabase;
 
 use crate::{AssistCtx, Assist, AssistId};
@@ -10,17 +10,19 @@ pub(crate) fn replace_if_let_with_match(mut ctx: AssistCtx<impl HirDatabase>) ->
     let pat = cond.pat()?;
     let expr = cond.expr()?;
     let then_block = if_expr.then_branch()?;
-    let else_block = match if_expr.else_branch()? {
-        ast::ElseBranch::Block(it) => it,
-        ast::ElseBranch::IfExpr(_) => return None,
-    };
+    let else_branch = if_expr.else_branch()?;
 
-    ctx.add_action(AssistId("replace_if_let_with_match"), "replace with match", |edit| {
-        let match_expr = build_match_expr(expr, pat, then_block, else_block);
-        edit.target(if_expr.syntax().range());
-        edit.replace_node_and_indent(if_expr.syntax(), match_expr);
-        edit.set_cursor(if_expr.syntax().range().start())
-    });
+    ctx.add_action(
+        AssistId("replace_if_let_with_match"),
+        "replace with match",
+        if_expr.syntax().range(),
+        |edit| {
+            let indent = leading_indent(if_expr.syntax()).unwrap_or("");
+            let match_expr = build_match_expr(expr, pat, then_block, else_branch, indent);
+            edit.replace_node_and_indent(if_expr.syntax(), match_expr);
+            edit.set_cursor(if_expr.syntax().range().start())
+        },
+    );
 
     ctx.build()
 }
@@ -29,23 +31,35 @@ fn build_match_expr(
     expr: &ast::Expr,
     pat1: &ast::Pat,
     arm1: &ast::Block,
-    arm2: &ast::Block,
+    arm2: ast::ElseBranch,
+    indent: &str,
 ) -> String {
+    let arm2 = match arm2 {
+        ast::ElseBranch::Block(it) => format_arm(it, indent),
+        // An `else if` chain becomes the body of the catch-all arm.
+        ast::ElseBranch::IfExpr(it) => nest(it.syntax().text().to_string(), indent),
+    };
     let mut buf = String::new();
     buf.push_str(&format!("match {} {{\n", expr.syntax().text()));
-    buf.push_str(&format!("    {} => {}\n", pat1.syntax().text(), format_arm(arm1)));
-    buf.push_str(&format!("    _ => {}\n", format_arm(arm2)));
+    buf.push_str(&format!("    {} => {}\n", pat1.syntax().text(), format_arm(arm1, indent)));
+    buf.push_str(&format!("    _ => {}\n", arm2));
     buf.push_str("}");
     buf
 }
 
-fn format_arm(block: &ast::Block) -> String {
+fn format_arm(block: &ast::Block, indent: &str) -> String {
     match extract_trivial_expression(block) {
-        None => block.syntax().text().to_string(),
+        None => nest(block.syntax().text().to_string(), indent),
         Some(e) => format!("{},", e.syntax().text()),
     }
 }
 
+/// Moves `text`, which was indented with `indent`, one level deeper, to
+/// become an arm of the match.
+fn nest(text: String, indent: &str) -> String {
+    reindent(&unindent(&text, indent), "    ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,4 +112,32 @@ impl VariantData {
         }",
         );
     }
+
+    #[test]
+    fn test_replace_if_let_with_match_else_if_chain() {
+        check_assist(
+            replace_if_let_with_match,
+            "
+fn foo(x: Option<u32>) {
+    if <|>let Some(x) = x {
+        // Got one
+        bar(x);
+    } else if baz() {
+        quux()
+    }
+}           ",
+            "
+fn foo(x: Option<u32>) {
+    <|>match x {
+        Some(x) => {
+            // Got one
+            bar(x);
+        }
+        _ => if baz() {
+            quux()
+        }
+    }
+}           ",
+        )
+    }
 }
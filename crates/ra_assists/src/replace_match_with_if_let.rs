@@ -0,0 +1,156 @@
+use ra_syntax::{AstNode, ast};
+use ra_fmt::{leading_indent, reindent, unindent};
+use hir::db::HirDatabase;
+
+use crate::{AssistCtx, Assist, AssistId};
+
+/// The inverse of `replace_if_let_with_match`, for a match with a single arm
+/// followed by a catch-all one.
+pub(crate) fn replace_match_with_if_let(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let match_expr: &ast::MatchExpr = ctx.node_at_offset()?;
+    let expr = match_expr.expr()?;
+    let mut arms = match_expr.match_arm_list()?.arms();
+    let (arm1, arm2) = (arms.next()?, arms.next()?);
+    if arms.next().is_some() || arm1.guard().is_some() || arm2.guard().is_some() {
+        return None;
+    }
+    let mut pats = arm1.pats();
+    let pat = pats.next()?;
+    if pats.next().is_some() {
+        return None;
+    }
+    match arm2.pats().collect::<Vec<_>>().as_slice() {
+        [pat] => match pat.kind() {
+            ast::PatKind::PlaceholderPat(_) => (),
+            _ => return None,
+        },
+        _ => return None,
+    }
+    let then_expr = arm1.expr()?;
+    let else_expr = arm2.expr()?;
+
+    ctx.add_action(
+        AssistId("replace_match_with_if_let"),
+        "replace with if let",
+        match_expr.syntax().range(),
+        |edit| {
+            let arm_indent = leading_indent(arm1.syntax()).unwrap_or("");
+            let then_branch = format_branch(then_expr, arm_indent);
+            let else_branch = match else_expr.kind() {
+                // Keep the chain flat, instead of nesting the `if` into a block.
+                ast::ExprKind::IfExpr(_) => {
+                    unindent(&else_expr.syntax().text().to_string(), arm_indent)
+                }
+                _ => format_branch(else_expr, arm_indent),
+            };
+            let if_expr = format!(
+                "if let {} = {} {} else {}",
+                pat.syntax().text(),
+                expr.syntax().text(),
+                then_branch,
+                else_branch
+            );
+            edit.replace_node_and_indent(match_expr.syntax(), if_expr);
+            edit.set_cursor(match_expr.syntax().range().start())
+        },
+    );
+
+    ctx.build()
+}
+
+fn format_branch(expr: &ast::Expr, arm_indent: &str) -> String {
+    let text = unindent(&expr.syntax().text().to_string(), arm_indent);
+    match expr.kind() {
+        ast::ExprKind::BlockExpr(_) => text,
+        _ => format!("{{\n    {}\n}}", reindent(&text, "    ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    #[test]
+    fn test_replace_match_with_if_let() {
+        check_assist(
+            replace_match_with_if_let,
+            "
+impl VariantData {
+    pub fn is_struct(&self) -> bool {
+        <|>match *self {
+            VariantData::Struct(..) => {
+                // Yes
+                true
+            }
+            _ => false,
+        }
+    }
+}           ",
+            "
+impl VariantData {
+    pub fn is_struct(&self) -> bool {
+        <|>if let VariantData::Struct(..) = *self {
+            // Yes
+            true
+        } else {
+            false
+        }
+    }
+}           ",
+        )
+    }
+
+    #[test]
+    fn test_replace_match_with_if_let_else_if_chain() {
+        check_assist(
+            replace_match_with_if_let,
+            "
+fn foo(x: Option<u32>) {
+    <|>match x {
+        Some(x) => bar(x),
+        _ => if baz() {
+            quux()
+        } else {
+            ()
+        },
+    }
+}           ",
+            "
+fn foo(x: Option<u32>) {
+    <|>if let Some(x) = x {
+        bar(x)
+    } else if baz() {
+        quux()
+    } else {
+        ()
+    }
+}           ",
+        )
+    }
+
+    #[test]
+    fn replace_match_with_if_let_not_applicable() {
+        check_assist_not_applicable(
+            replace_match_with_if_let,
+            "fn foo(x: Option<u32>) { <|>match x { Some(1) => (), Some(_) => (), None => () } }",
+        );
+        check_assist_not_applicable(
+            replace_match_with_if_let,
+            "fn foo(x: Option<u32>) { <|>match x { Some(x) if x > 1 => (), _ => () } }",
+        );
+        check_assist_not_applicable(
+            replace_match_with_if_let,
+            "fn foo(x: Option<u32>) { <|>match x { Some(x) => (), None => () } }",
+        );
+    }
+
+    #[test]
+    fn replace_match_with_if_let_target() {
+        check_assist_target(
+            replace_match_with_if_let,
+            "fn foo(x: Option<u32>) { <|>match x { Some(x) => (), _ => () } }",
+            "match x { Some(x) => (), _ => () }",
+        );
+    }
+}
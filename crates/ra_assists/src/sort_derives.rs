@@ -0,0 +1,105 @@
+use hir::db::HirDatabase;
+use ra_syntax::{
+    AstNode, TextRange, TextUnit,
+    SyntaxKind::WHITESPACE,
+    ast,
+};
+
+use crate::{AssistCtx, Assist, AssistId};
+
+/// Sorts the traits in a `#[derive]` attribute, merging all the `#[derive]`s
+/// of the item into the first one.
+pub(crate) fn sort_derives(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let attr = ctx.node_at_offset::<ast::Attr>()?;
+    if attr.is_inner() || attr.as_path_list()?.0 != "derive" {
+        return None;
+    }
+    let derives: Vec<(&ast::Attr, Vec<String>)> = attr
+        .syntax()
+        .parent()?
+        .children()
+        .filter_map(ast::Attr::cast)
+        .filter(|it| !it.is_inner())
+        .filter_map(|it| {
+            let (name, paths) = it.as_path_list()?;
+            if name == "derive" {
+                Some((it, paths))
+            } else {
+                None
+            }
+        })
+        .collect();
+    let paths: Vec<String> = derives.iter().flat_map(|(_, paths)| paths.iter().cloned()).collect();
+    let mut sorted = paths.clone();
+    sorted.sort();
+    sorted.dedup();
+    if derives.len() == 1 && sorted == paths {
+        return None;
+    }
+
+    let (first, _) = derives[0];
+    let (last, _) = derives[derives.len() - 1];
+    let target = TextRange::from_to(first.syntax().range().start(), last.syntax().range().end());
+    ctx.add_action(AssistId("sort_derives"), "sort derives", target, |edit| {
+        edit.replace(first.syntax().range(), format!("#[derive({})]", sorted.join(", ")));
+        edit.set_cursor(first.syntax().range().start() + TextUnit::of_str("#[derive("));
+        for (attr, _) in derives[1..].iter() {
+            let end = match attr.syntax().next_sibling_or_token() {
+                Some(ws) if ws.kind() == WHITESPACE => ws.range().end(),
+                _ => attr.syntax().range().end(),
+            };
+            edit.delete(TextRange::from_to(attr.syntax().range().start(), end));
+        }
+    });
+
+    ctx.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
+
+    #[test]
+    fn sort_derives_in_one_attr() {
+        check_assist(
+            sort_derives,
+            "#[derive(PartialEq, Debug, <|>serde::Serialize, Clone)]\nstruct Foo;",
+            "#[derive(<|>Clone, Debug, PartialEq, serde::Serialize)]\nstruct Foo;",
+        );
+    }
+
+    #[test]
+    fn sort_derives_merges_attrs() {
+        check_assist(
+            sort_derives,
+            "
+/// Docs.
+#[derive(Debug, <|>Clone)]
+#[cfg_attr(test, derive(Default))]
+#[derive(PartialEq, Clone)]
+enum Foo {}",
+            "
+/// Docs.
+#[derive(<|>Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(Default))]
+enum Foo {}",
+        );
+    }
+
+    #[test]
+    fn sort_derives_not_applicable() {
+        check_assist_not_applicable(sort_derives, "#[derive(Clone, <|>Debug)]\nstruct Foo;");
+        check_assist_not_applicable(sort_derives, "#[cfg(<|>test)]\nstruct Foo;");
+        check_assist_not_applicable(sort_derives, "#[derive(Debug)]\nstruct <|>Foo;");
+    }
+
+    #[test]
+    fn sort_derives_target() {
+        check_assist_target(
+            sort_derives,
+            "#[derive(<|>Debug)]\n#[inline]\n#[derive(Clone)]\nstruct Foo;",
+            "#[derive(Debug)]\n#[inline]\n#[derive(Clone)]",
+        );
+    }
+}
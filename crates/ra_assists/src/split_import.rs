@@ -24,8 +24,7 @@ pub(crate) fn split_import(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assis
         None => top_path.syntax().range().end(),
     };
 
-    ctx.add_action(AssistId("split_import"), "split import", |edit| {
-        edit.target(colon_colon.range());
+    ctx.add_action(AssistId("split_import"), "split import", colon_colon.range(), |edit| {
         edit.insert(l_curly, "{");
         edit.insert(r_curly, "}");
         edit.set_cursor(l_curly + TextUnit::of_str("{"));
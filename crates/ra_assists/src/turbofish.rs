@@ -0,0 +1,283 @@
+use hir::{ModuleDef, PathResolution, Ty, db::HirDatabase};
+use ra_syntax::{
+    AstNode, TextUnit,
+    SyntaxKind::IMPL_TRAIT_TYPE,
+    ast::{self, ArgListOwner, NameOwner, TypeAscriptionOwner, TypeParamsOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId};
+
+/// Adds `::<_, _>` to a call of a generic function whose generic arguments
+/// can't be inferred.
+pub(crate) fn add_turbofish(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let call = generic_call(&ctx)?;
+    if call.type_args.is_some() || !has_unknown(&call.analyzer.type_of(ctx.db, call.expr)?) {
+        return None;
+    }
+
+    ctx.add_action(AssistId("add_turbofish"), "add `::<>`", call.expr.syntax().range(), |edit| {
+        let offset = call.name_ref.syntax().range().end();
+        let placeholders = vec!["_"; call.type_params.len()];
+        edit.insert(offset, format!("::<{}>", placeholders.join(", ")));
+        edit.set_cursor(offset + TextUnit::of_str("::<"));
+    });
+
+    ctx.build()
+}
+
+/// Removes the `::<...>` from a call of a generic function when all of its
+/// generic parameters are determined by the arguments.
+pub(crate) fn remove_turbofish(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let call = generic_call(&ctx)?;
+    let type_args = call.type_args?;
+    let (_, fn_def) = call.function.source(ctx.db);
+    let param_types: Vec<_> = fn_def
+        .param_list()?
+        .params()
+        .filter_map(|it| it.ascribed_type())
+        .flat_map(|it| it.syntax().descendants())
+        .filter_map(ast::NameRef::cast)
+        .map(|it| it.text().clone())
+        .collect();
+    if !call.type_params.iter().all(|it| param_types.contains(it)) {
+        return None;
+    }
+    // The arguments need to have types of their own, literals or calls like
+    // `Default::default()` could get theirs from the turbofish instead.
+    // FIXME: the type of a local might also have been inferred from the call.
+    let args_known = call.args.into_iter().flat_map(|it| it.args()).all(|arg| {
+        has_own_type(arg)
+            && call.analyzer.type_of(ctx.db, arg).map_or(false, |ty| !has_unknown(&ty))
+    });
+    if !args_known {
+        return None;
+    }
+
+    ctx.add_action(
+        AssistId("remove_turbofish"),
+        "remove `::<>`",
+        type_args.syntax().range(),
+        |edit| {
+            edit.delete(type_args.syntax().range());
+        },
+    );
+
+    ctx.build()
+}
+
+struct GenericCall<'a> {
+    expr: &'a ast::Expr,
+    name_ref: &'a ast::NameRef,
+    type_args: Option<&'a ast::TypeArgList>,
+    args: Option<&'a ast::ArgList>,
+    function: hir::Function,
+    type_params: Vec<ra_syntax::SmolStr>,
+    analyzer: hir::SourceAnalyzer,
+}
+
+/// Finds the call of a generic function under the cursor, not counting the
+/// arguments of the call.
+fn generic_call<'a>(ctx: &AssistCtx<'a, impl HirDatabase>) -> Option<GenericCall<'a>> {
+    let expr = ctx.node_at_offset::<ast::Expr>()?;
+    let expr =
+        expr.syntax().ancestors().filter_map(ast::Expr::cast).find(|it| match it.kind() {
+            ast::ExprKind::CallExpr(_) | ast::ExprKind::MethodCallExpr(_) => true,
+            _ => false,
+        })?;
+    let analyzer = hir::SourceAnalyzer::new(ctx.db, ctx.frange.file_id, expr.syntax(), None);
+    let (name_ref, type_args, args, function) = match expr.kind() {
+        ast::ExprKind::CallExpr(call) => {
+            let path = match call.expr()?.kind() {
+                ast::ExprKind::PathExpr(it) => it.path()?,
+                _ => return None,
+            };
+            let function = match analyzer.resolve_path(ctx.db, path)? {
+                PathResolution::Def(ModuleDef::Function(it)) => it,
+                _ => return None,
+            };
+            let segment = path.segment()?;
+            (segment.name_ref()?, segment.type_arg_list(), call.arg_list(), function)
+        }
+        ast::ExprKind::MethodCallExpr(call) => {
+            let function = analyzer.resolve_method_call(call)?;
+            (call.name_ref()?, call.type_arg_list(), call.arg_list(), function)
+        }
+        _ => return None,
+    };
+    if args.map_or(false, |it| it.syntax().range().start() < ctx.frange.range.start()) {
+        return None;
+    }
+
+    let (_, fn_def) = function.source(ctx.db);
+    // Functions with `impl Trait` arguments can't be called with a turbofish.
+    if fn_def.syntax().descendants().any(|it| it.kind() == IMPL_TRAIT_TYPE) {
+        return None;
+    }
+    let type_params: Vec<_> = fn_def
+        .type_param_list()?
+        .type_params()
+        .filter_map(|it| it.name())
+        .map(|it| it.text().clone())
+        .collect();
+    if type_params.is_empty() {
+        return None;
+    }
+    Some(GenericCall { expr, name_ref, type_args, args, function, type_params, analyzer })
+}
+
+fn has_own_type(expr: &ast::Expr) -> bool {
+    match expr.kind() {
+        ast::ExprKind::PathExpr(_) | ast::ExprKind::FieldExpr(_) => true,
+        ast::ExprKind::RefExpr(it) => it.expr().map_or(false, has_own_type),
+        ast::ExprKind::ParenExpr(it) => it.expr().map_or(false, has_own_type),
+        _ => false,
+    }
+}
+
+fn has_unknown(ty: &Ty) -> bool {
+    let mut res = false;
+    ty.walk(&mut |it| {
+        if *it == Ty::Unknown {
+            res = true;
+        }
+    });
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
+
+    #[test]
+    fn add_turbofish_to_call() {
+        check_assist(
+            add_turbofish,
+            "
+fn make<T, U>() -> (T, U) {}
+fn main() {
+    let x = ma<|>ke();
+}",
+            "
+fn make<T, U>() -> (T, U) {}
+fn main() {
+    let x = make::<<|>_, _>();
+}",
+        );
+    }
+
+    #[test]
+    fn add_turbofish_to_method_call() {
+        check_assist(
+            add_turbofish,
+            "
+struct S;
+impl S { fn make<T>(&self) -> T {} }
+fn main() {
+    let x = S.<|>make();
+}",
+            "
+struct S;
+impl S { fn make<T>(&self) -> T {} }
+fn main() {
+    let x = S.make::<<|>_>();
+}",
+        );
+    }
+
+    #[test]
+    fn add_turbofish_not_applicable() {
+        check_assist_not_applicable(
+            add_turbofish,
+            "
+struct S;
+fn id<T>(t: T) -> T { t }
+fn main() {
+    let x = i<|>d(S);
+}",
+        );
+        check_assist_not_applicable(
+            add_turbofish,
+            "
+fn make<T>() -> T {}
+fn main() {
+    let x = ma<|>ke::<u32>();
+}",
+        );
+        check_assist_not_applicable(
+            add_turbofish,
+            "
+fn make() -> u32 {}
+fn main() {
+    let x = ma<|>ke();
+}",
+        );
+    }
+
+    #[test]
+    fn add_turbofish_target() {
+        check_assist_target(
+            add_turbofish,
+            "
+fn make<T>() -> T {}
+fn main() {
+    let x = ma<|>ke();
+}",
+            "make()",
+        );
+    }
+
+    #[test]
+    fn remove_redundant_turbofish() {
+        check_assist(
+            remove_turbofish,
+            "
+struct S;
+fn id<T>(t: T) -> T { t }
+fn main() {
+    let x = i<|>d::<S>(S);
+}",
+            "
+struct S;
+fn id<T>(t: T) -> T { t }
+fn main() {
+    let x = i<|>d(S);
+}",
+        );
+        check_assist(
+            remove_turbofish,
+            "
+struct S;
+impl S { fn id<T>(&self, t: &T) -> T {} }
+fn main(s: S, x: u32) {
+    let x = s.<|>id::<u32>(&x);
+}",
+            "
+struct S;
+impl S { fn id<T>(&self, t: &T) -> T {} }
+fn main(s: S, x: u32) {
+    let x = s.<|>id(&x);
+}",
+        );
+    }
+
+    #[test]
+    fn remove_turbofish_not_applicable() {
+        check_assist_not_applicable(
+            remove_turbofish,
+            "
+fn make<T>() -> T {}
+fn main() {
+    let x = ma<|>ke::<u32>();
+}",
+        );
+        check_assist_not_applicable(
+            remove_turbofish,
+            "
+fn id<T>(t: T) -> T { t }
+fn main() {
+    let x = i<|>d::<u32>(1);
+}",
+        );
+    }
+}
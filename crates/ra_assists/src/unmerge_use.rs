@@ -0,0 +1,96 @@
+use hir::db::HirDatabase;
+use ra_fmt::leading_indent;
+use ra_syntax::{
+    AstNode, TextRange, SyntaxElement,
+    SyntaxKind::{COMMA, WHITESPACE},
+    ast::{self, VisibilityOwner},
+};
+
+use crate::{AssistCtx, Assist, AssistId};
+
+/// Moves the use tree under the cursor out of its `{}` list into a `use` item
+/// of its own, the inverse of `merge_imports`.
+pub(crate) fn unmerge_use(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let tree = ctx
+        .token_at_offset()
+        .filter_map(|token| {
+            token
+                .parent()
+                .ancestors()
+                .filter_map(ast::UseTree::cast)
+                .find(|it| it.syntax().parent().and_then(ast::UseTreeList::cast).is_some())
+        })
+        .min_by_key(|it| it.syntax().range().len())?;
+    let list = tree.syntax().parent().and_then(ast::UseTreeList::cast)?;
+    let use_item = list.syntax().ancestors().find_map(ast::UseItem::cast)?;
+
+    let mut prefix = list
+        .syntax()
+        .ancestors()
+        .filter_map(ast::UseTree::cast)
+        .map(|it| it.path().map(|path| path.syntax().text().to_string()))
+        .collect::<Option<Vec<_>>>()?;
+    prefix.reverse();
+
+    ctx.add_action(AssistId("unmerge_use"), "unmerge use", tree.syntax().range(), |edit| {
+        let mut buf = String::from("\n");
+        buf.push_str(leading_indent(use_item.syntax()).unwrap_or(""));
+        if let Some(vis) = use_item.visibility() {
+            buf.push_str(&format!("{} ", vis.syntax().text()));
+        }
+        buf.push_str(&format!("use {}::{};", prefix.join("::"), tree.syntax().text()));
+        edit.delete(range_with_separator(tree));
+        edit.insert(use_item.syntax().range().end(), buf);
+        edit.set_cursor(use_item.syntax().range().start());
+    });
+
+    ctx.build()
+}
+
+/// The range of `tree` together with the comma separating it from its
+/// neighbour in the list, and the whitespace after that comma.
+pub(crate) fn range_with_separator(tree: &ast::UseTree) -> TextRange {
+    let range = tree.syntax().range();
+    let next = tree.syntax().next_sibling_or_token();
+    if let Some(comma) = next.filter(|it| it.kind() == COMMA) {
+        let end = match comma.next_sibling_or_token().filter(|it| it.kind() == WHITESPACE) {
+            Some(ws) => ws.range().end(),
+            None => comma.range().end(),
+        };
+        return TextRange::from_to(range.start(), end);
+    }
+    let mut prev = tree.syntax().prev_sibling_or_token();
+    if let Some(ws) = prev.filter(|it| it.kind() == WHITESPACE) {
+        prev = ws.prev_sibling_or_token();
+    }
+    match prev.filter(|it: &SyntaxElement| it.kind() == COMMA) {
+        Some(comma) => TextRange::from_to(comma.range().start(), range.end()),
+        None => range,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    #[test]
+    fn test_unmerge_use() {
+        check_assist(unmerge_use, "use foo::{<|>a, b};", "<|>use foo::{b};\nuse foo::a;");
+        check_assist(
+            unmerge_use,
+            "mod m {\n    pub use foo::{a, bar::{b, <|>c as d}};\n}",
+            "mod m {\n    <|>pub use foo::{a, bar::{b}};\n    pub use foo::bar::c as d;\n}",
+        );
+    }
+
+    #[test]
+    fn unmerge_use_not_applicable_outside_of_list() {
+        check_assist_not_applicable(unmerge_use, "use foo::<|>a;");
+    }
+
+    #[test]
+    fn unmerge_use_target() {
+        check_assist_target(unmerge_use, "use foo::{a, b::<|>c};", "b::c");
+    }
+}
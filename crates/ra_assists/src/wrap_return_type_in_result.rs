@@ -0,0 +1,214 @@
+use hir::db::HirDatabase;
+use ra_fmt::leading_indent;
+use ra_syntax::{
+    AstNode, TextUnit,
+    SyntaxKind::{FN_DEF, LAMBDA_EXPR, R_CURLY},
+    ast,
+};
+
+use crate::{AssistCtx, Assist, AssistId, assist_ctx::AssistBuilder};
+
+/// Changes the return type `T` of a function into `Result<T, ()>`, wrapping
+/// the returned values in `Ok`.
+pub(crate) fn wrap_return_type_in_result(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let ret_type = ctx.node_at_offset::<ast::RetType>()?;
+    let fn_def = ret_type.syntax().parent().and_then(ast::FnDef::cast)?;
+    let type_ref = ret_type.type_ref()?;
+    if is_result(type_ref) {
+        return None;
+    }
+    fn_def.body()?;
+
+    ctx.add_action(
+        AssistId("wrap_return_type_in_result"),
+        "wrap return type in Result",
+        type_ref.syntax().range(),
+        |edit| {
+            wrap_in_result(edit, fn_def, "()");
+            let err_offset = type_ref.syntax().range().start()
+                + TextUnit::of_str(&format!("Result<{}, ", type_ref.syntax().text()));
+            edit.set_cursor(err_offset);
+        },
+    );
+
+    ctx.build()
+}
+
+/// Whether `type_ref` is already spelled as some `Result`.
+pub(crate) fn is_result(type_ref: &ast::TypeRef) -> bool {
+    let path = match type_ref.kind() {
+        ast::TypeRefKind::PathType(it) => it.path(),
+        _ => None,
+    };
+    path.and_then(|it| it.segment())
+        .and_then(|it| it.name_ref())
+        .map_or(false, |it| it.text() == "Result")
+}
+
+/// Changes the return type of `fn_def` to a `Result` with the error type `err`
+/// and wraps all the values returned from it in `Ok`.
+pub(crate) fn wrap_in_result(edit: &mut AssistBuilder, fn_def: &ast::FnDef, err: &str) {
+    let body = match fn_def.body() {
+        Some(it) => it,
+        None => return,
+    };
+    let type_ref = fn_def.ret_type().and_then(|it| it.type_ref());
+    match type_ref {
+        Some(type_ref) => {
+            edit.replace(
+                type_ref.syntax().range(),
+                format!("Result<{}, {}>", type_ref.syntax().text(), err),
+            );
+        }
+        None => {
+            let offset = match fn_def.param_list() {
+                Some(it) => it.syntax().range().end(),
+                None => return,
+            };
+            edit.insert(offset, format!(" -> Result<(), {}>", err));
+        }
+    }
+
+    if body.expr().is_none() && type_ref.map_or(true, is_unit) {
+        if let Some(r_curly) = body.syntax().children_with_tokens().find(|it| it.kind() == R_CURLY)
+        {
+            let indent = leading_indent(fn_def.syntax()).unwrap_or("");
+            edit.insert(r_curly.range().start(), format!("    Ok(())\n{}", indent));
+        }
+    }
+    for expr in returned_exprs(body) {
+        edit.insert(expr.syntax().range().start(), "Ok(");
+        edit.insert(expr.syntax().range().end(), ")");
+    }
+    // A bare `return;` returns `()`.
+    for it in own_returns(body).filter(|it| it.expr().is_none()) {
+        edit.insert(it.syntax().range().end(), " Ok(())");
+    }
+}
+
+/// The expressions whose values are returned from `body`: its tail
+/// expressions and the values of its `return`s.
+pub(crate) fn returned_exprs(body: &ast::Block) -> Vec<&ast::Expr> {
+    let mut acc = Vec::new();
+    if let Some(expr) = body.expr() {
+        collect_tail_exprs(expr, &mut acc);
+    }
+    acc.extend(own_returns(body).filter_map(|it| it.expr()));
+    acc
+}
+
+/// The `return`s in `body`, except the ones in nested closures and functions,
+/// which don't return from us.
+fn own_returns(body: &ast::Block) -> impl Iterator<Item = &ast::ReturnExpr> {
+    body.syntax().descendants().filter_map(ast::ReturnExpr::cast).filter(move |it| {
+        it.syntax()
+            .ancestors()
+            .take_while(|it| it != &body.syntax())
+            .all(|it| it.kind() != LAMBDA_EXPR && it.kind() != FN_DEF)
+    })
+}
+
+fn is_unit(type_ref: &ast::TypeRef) -> bool {
+    match type_ref.kind() {
+        ast::TypeRefKind::TupleType(it) => it.fields().next().is_none(),
+        _ => false,
+    }
+}
+
+/// Collects the expressions which produce the value of `expr`, looking through
+/// blocks, `if`s and `match`es.
+fn collect_tail_exprs<'a>(expr: &'a ast::Expr, acc: &mut Vec<&'a ast::Expr>) {
+    match expr.kind() {
+        ast::ExprKind::BlockExpr(it) => {
+            if let Some(expr) = it.block().and_then(|it| it.expr()) {
+                collect_tail_exprs(expr, acc);
+            }
+        }
+        ast::ExprKind::IfExpr(it) => {
+            if let Some(expr) = it.then_branch().and_then(|it| it.expr()) {
+                collect_tail_exprs(expr, acc);
+            }
+            match it.else_branch() {
+                Some(ast::ElseBranch::Block(block)) => {
+                    if let Some(expr) = block.expr() {
+                        collect_tail_exprs(expr, acc);
+                    }
+                }
+                Some(ast::ElseBranch::IfExpr(if_expr)) => {
+                    if let Some(expr) = ast::Expr::cast(if_expr.syntax()) {
+                        collect_tail_exprs(expr, acc);
+                    }
+                }
+                None => (),
+            }
+        }
+        ast::ExprKind::MatchExpr(it) => {
+            let arms = it.match_arm_list().into_iter().flat_map(|it| it.arms());
+            for expr in arms.filter_map(|it| it.expr()) {
+                collect_tail_exprs(expr, acc);
+            }
+        }
+        // Already handled as a `return`.
+        ast::ExprKind::ReturnExpr(_) => (),
+        _ => acc.push(expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_target, check_assist_not_applicable};
+
+    #[test]
+    fn wrap_return_type_in_result_simple() {
+        check_assist(
+            wrap_return_type_in_result,
+            "fn foo() -> i3<|>2 { let x = 1; x }",
+            "fn foo() -> Result<i32, <|>()> { let x = 1; Ok(x) }",
+        );
+    }
+
+    #[test]
+    fn wrap_return_type_in_result_wraps_all_returns() {
+        check_assist(
+            wrap_return_type_in_result,
+            r#"
+fn foo(x: u32) -> <|>u32 {
+    if x == 0 {
+        return 1;
+    }
+    let f = || { return 2; };
+    match x {
+        1 => 2,
+        _ => if x > 5 { 3 } else { x },
+    }
+}
+"#,
+            r#"
+fn foo(x: u32) -> Result<u32, <|>()> {
+    if x == 0 {
+        return Ok(1);
+    }
+    let f = || { return 2; };
+    match x {
+        1 => Ok(2),
+        _ => if x > 5 { Ok(3) } else { Ok(x) },
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn wrap_return_type_in_result_not_applicable_to_result() {
+        check_assist_not_applicable(
+            wrap_return_type_in_result,
+            "fn foo() -> Result<i32<|>, ()> { Ok(1) }",
+        );
+    }
+
+    #[test]
+    fn wrap_return_type_in_result_target() {
+        check_assist_target(wrap_return_type_in_result, "fn foo() -> <|>i32 { 1 }", "i32");
+    }
+}
@@ -5,12 +5,13 @@ use std::path::Path;
 use std::collections::HashSet;
 
 use rustc_hash::FxHashMap;
+use relative_path::RelativePathBuf;
 
 use ra_db::{
-    CrateGraph, FileId, SourceRoot, SourceRootId, SourceDatabase, salsa,
+    CrateGraph, Edition, FileId, SourceRoot, SourceRootId, SourceDatabase, salsa,
 };
 use ra_hir::db;
-use ra_project_model::ProjectWorkspace;
+use ra_project_model::{get_rustc_cfg_options, CargoConfig, ProjectWorkspace};
 use ra_vfs::{Vfs, VfsChange};
 use vfs_filter::IncludeRustFiles;
 
@@ -85,9 +86,28 @@ impl BatchDatabase {
         db
     }
 
+    /// Creates a database with a single crate, whose root is a single file,
+    /// without any dependencies or stdlib.
+    pub fn from_single_file(text: String) -> (BatchDatabase, FileId) {
+        let mut db = BatchDatabase { runtime: salsa::Runtime::default() };
+        let source_root_id = SourceRootId(0);
+        let file_id = FileId(0);
+        let path = RelativePathBuf::from("main.rs");
+        db.set_file_text(file_id, Arc::new(text));
+        db.set_file_relative_path(file_id, path.clone());
+        db.set_file_source_root(file_id, source_root_id);
+        let mut source_root = SourceRoot::default();
+        source_root.files.insert(path, file_id);
+        db.set_source_root(source_root_id, Arc::new(source_root));
+        let mut crate_graph = CrateGraph::default();
+        crate_graph.add_crate_root(file_id, Edition::Edition2018);
+        db.set_crate_graph(Arc::new(crate_graph));
+        (db, file_id)
+    }
+
     pub fn load_cargo(root: impl AsRef<Path>) -> Result<(BatchDatabase, Vec<SourceRootId>)> {
         let root = std::env::current_dir()?.join(root);
-        let ws = ProjectWorkspace::discover(root.as_ref())?;
+        let ws = ProjectWorkspace::discover(root.as_ref(), &CargoConfig::default())?;
         let mut roots = Vec::new();
         roots.push(IncludeRustFiles::member(root.clone()));
         roots.extend(IncludeRustFiles::from_roots(ws.to_roots()));
@@ -97,7 +117,8 @@ impl BatchDatabase {
             log::debug!("vfs file {:?} -> {:?}", path, vfs_file);
             vfs_file.map(vfs_file_to_id)
         };
-        let crate_graph = ws.to_crate_graph(&mut load);
+        let default_cfg_options = get_rustc_cfg_options();
+        let crate_graph = ws.to_crate_graph(&default_cfg_options, &mut load);
         log::debug!("crate graph: {:?}", crate_graph);
 
         let local_roots = roots
@@ -1,15 +1,16 @@
 use std::{collections::HashSet, time::Instant};
 
-use ra_db::SourceDatabase;
+use ra_db::{SourceDatabase, salsa::{Database, SweepStrategy}};
 use ra_batch::BatchDatabase;
-use ra_hir::{Crate, ModuleDef, Ty, ImplItem};
+use ra_hir::{Crate, ModuleDef, Ty, ImplItem, db};
+use ra_prof::{allocated_bytes, Bytes};
 use ra_syntax::AstNode;
 
 use crate::Result;
 
-pub fn run(verbose: bool) -> Result<()> {
+pub fn run(verbose: bool, memory_usage: bool) -> Result<()> {
     let db_load_time = Instant::now();
-    let (db, roots) = BatchDatabase::load_cargo(".")?;
+    let (mut db, roots) = BatchDatabase::load_cargo(".")?;
     println!("Database loaded, {} roots, {:?}", roots.len(), db_load_time.elapsed());
     let analysis_time = Instant::now();
     let mut num_crates = 0;
@@ -99,5 +100,51 @@ pub fn run(verbose: bool) -> Result<()> {
         (num_exprs_partially_unknown * 100 / num_exprs)
     );
     println!("Analysis: {:?}", analysis_time.elapsed());
+    if memory_usage {
+        println!("Memory usage of queries:");
+        for (name, bytes) in per_query_memory_usage(&mut db) {
+            println!("{} {}", bytes, name);
+        }
+    }
     Ok(())
 }
+
+/// The memory retained by the values of each query, measured as the memory
+/// freed when they are discarded.
+fn per_query_memory_usage(db: &mut BatchDatabase) -> Vec<(String, Bytes)> {
+    let mut acc = Vec::new();
+    let sweep = SweepStrategy::default().discard_values().sweep_all_revisions();
+    macro_rules! sweep_each_query {
+        ($($q:path)*) => {$(
+            let before = allocated_bytes();
+            db.query($q).sweep(sweep);
+            let after = allocated_bytes();
+            let q: $q = Default::default();
+            let name = format!("{:?}", q);
+            acc.push((name, before - after));
+        )*}
+    }
+    sweep_each_query![
+        ra_db::ParseQuery
+        db::HirParseQuery
+        db::AstIdMapQuery
+        db::AstIdToNodeQuery
+        db::RawItemsWithSourceMapQuery
+        db::RawItemsQuery
+        db::CrateDefMapQuery
+        db::ImplsInModuleWithSourceMapQuery
+        db::ImplsInModuleQuery
+        db::GenericParamsQuery
+        db::FnSignatureQuery
+        db::BodyWithSourceMapQuery
+        db::BodyHirQuery
+        db::ExprScopesQuery
+        db::InferQuery
+        db::TypeForDefQuery
+        db::FieldTypesQuery
+        db::CallableItemSignatureQuery
+        db::ImplsInCrateQuery
+    ];
+    acc.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+    acc
+}
@@ -0,0 +1,194 @@
+//! Dumps the syntax tree of a file, or the lowered bodies of its functions
+//! with their inferred types, for tools which don't speak LSP.
+use std::{fmt::Write, fs, path::Path};
+
+use serde_json::{json, Value};
+use ra_arena::ArenaId;
+use ra_batch::BatchDatabase;
+use ra_db::{FileId, SourceDatabase};
+use ra_hir::{Crate, DefWithBody, Function, HirDisplay, ImplItem, Module, ModuleDef};
+use ra_syntax::{AstNode, SourceFile, SyntaxElement, SyntaxKind, TextRange};
+
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+pub fn run(path: &Path, format: Format, hir: bool) -> Result<()> {
+    let text = fs::read_to_string(path)?;
+    let output = if hir {
+        let (db, file_id) = BatchDatabase::from_single_file(text.clone());
+        let bodies: Vec<_> =
+            functions(&db, file_id).into_iter().map(|f| lower_body(&db, &text, f)).collect();
+        match format {
+            Format::Text => bodies.iter().map(FunctionBody::to_text).collect::<Vec<_>>().join("\n"),
+            Format::Json => {
+                let bodies = bodies.iter().map(FunctionBody::to_json).collect();
+                serde_json::to_string_pretty(&Value::Array(bodies))?
+            }
+        }
+    } else {
+        let file = SourceFile::parse(&text);
+        match format {
+            Format::Text => file.syntax().debug_dump(),
+            Format::Json => serde_json::to_string_pretty(&syntax_to_json(file.syntax().into()))?,
+        }
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+fn syntax_to_json(element: SyntaxElement) -> Value {
+    let range = range_to_json(element.range());
+    match element {
+        SyntaxElement::Node(node) => {
+            let children: Vec<_> = node.children_with_tokens().map(syntax_to_json).collect();
+            json!({ "kind": format!("{:?}", node.kind()), "range": range, "children": children })
+        }
+        SyntaxElement::Token(token) => {
+            json!({ "kind": format!("{:?}", token.kind()), "range": range, "text": token.text().as_str() })
+        }
+    }
+}
+
+fn range_to_json(range: TextRange) -> Value {
+    json!([range.start().to_usize(), range.end().to_usize()])
+}
+
+/// The free functions and the methods of the impl blocks in the file, in the
+/// order of their declarations.
+fn functions(db: &BatchDatabase, file_id: FileId) -> Vec<Function> {
+    let source_root = db.file_source_root(file_id);
+    let mut modules: Vec<Module> = Crate::source_root_crates(db, source_root)
+        .into_iter()
+        .filter_map(|krate| krate.root_module(db))
+        .collect();
+    let mut res = Vec::new();
+    while let Some(module) = modules.pop() {
+        modules.extend(module.children(db));
+        for decl in module.declarations(db) {
+            if let ModuleDef::Function(f) = decl {
+                res.push(f);
+            }
+        }
+        for impl_block in module.impl_blocks(db) {
+            for item in impl_block.items(db) {
+                if let ImplItem::Method(f) = item {
+                    res.push(f);
+                }
+            }
+        }
+    }
+    res.sort_by_key(|f| f.source(db).1.syntax().range().start());
+    res
+}
+
+/// The lowered body of a function, with expressions and patterns numbered
+/// by their HIR ids.
+struct FunctionBody {
+    name: String,
+    range: TextRange,
+    params: Vec<u32>,
+    body_expr: u32,
+    exprs: Vec<BodyNode>,
+    pats: Vec<BodyNode>,
+}
+
+/// An expression or a pattern of a body. Desugared nodes have no source.
+struct BodyNode {
+    id: u32,
+    source: Option<(SyntaxKind, TextRange)>,
+    text: Option<String>,
+    ty: String,
+    children: Vec<u32>,
+}
+
+fn lower_body(db: &BatchDatabase, text: &str, f: Function) -> FunctionBody {
+    let body = f.body(db);
+    let source_map = DefWithBody::from(f).body_source_map(db);
+    let infer = f.infer(db);
+    let source_text = |range: TextRange| text[range].to_string();
+    let exprs = body
+        .exprs()
+        .map(|(id, expr)| {
+            let source = source_map.expr_syntax(id).map(|ptr| (ptr.kind(), ptr.range()));
+            let mut children = Vec::new();
+            expr.walk_child_exprs(|child| children.push(child.into_raw().into()));
+            BodyNode {
+                id: id.into_raw().into(),
+                source,
+                text: source.map(|(_, range)| source_text(range)),
+                ty: infer[id].display(db).to_string(),
+                children,
+            }
+        })
+        .collect();
+    let pats = body
+        .pats()
+        .map(|(id, _)| {
+            let source = source_map.pat_syntax(id).map(|ptr| {
+                let ptr = ptr.either(|it| it.syntax_node_ptr(), |it| it.syntax_node_ptr());
+                (ptr.kind(), ptr.range())
+            });
+            BodyNode {
+                id: id.into_raw().into(),
+                source,
+                text: source.map(|(_, range)| source_text(range)),
+                ty: infer[id].display(db).to_string(),
+                children: Vec::new(),
+            }
+        })
+        .collect();
+    FunctionBody {
+        name: f.name(db).to_string(),
+        range: f.source(db).1.syntax().range(),
+        params: body.params().iter().map(|&it| it.into_raw().into()).collect(),
+        body_expr: body.body_expr().into_raw().into(),
+        exprs,
+        pats,
+    }
+}
+
+impl FunctionBody {
+    fn to_text(&self) -> String {
+        let mut buf = String::new();
+        writeln!(buf, "fn {} {}", self.name, self.range).unwrap();
+        for (prefix, nodes) in &[("expr", &self.exprs), ("pat", &self.pats)] {
+            for node in nodes.iter() {
+                let source = match node.source {
+                    Some((kind, range)) => format!("{} {:?}", range, kind),
+                    None => "<desugared>".to_string(),
+                };
+                writeln!(buf, "    {} {} {}: {}", prefix, node.id, source, node.ty).unwrap();
+            }
+        }
+        buf
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "range": range_to_json(self.range),
+            "params": self.params,
+            "body": self.body_expr,
+            "exprs": self.exprs.iter().map(BodyNode::to_json).collect::<Vec<_>>(),
+            "pats": self.pats.iter().map(BodyNode::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl BodyNode {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "kind": self.source.map(|(kind, _)| format!("{:?}", kind)),
+            "range": self.source.map(|(_, range)| range_to_json(range)),
+            "text": self.text,
+            "ty": self.ty,
+            "children": self.children,
+        })
+    }
+}
@@ -1,4 +1,6 @@
 mod analysis_stats;
+mod dump_ast;
+mod unused_pub;
 
 use std::{fs, io::Read, path::Path};
 
@@ -11,6 +13,9 @@ use ra_prof::profile;
 
 type Result<T> = ::std::result::Result<T, failure::Error>;
 
+#[global_allocator]
+static ALLOC: ra_prof::CountingAlloc = ra_prof::CountingAlloc;
+
 fn main() -> Result<()> {
     Logger::with_env().start()?;
     let matches = App::new("ra-cli")
@@ -23,8 +28,23 @@ fn main() -> Result<()> {
         .subcommand(SubCommand::with_name("parse").arg(Arg::with_name("no-dump").long("--no-dump")))
         .subcommand(SubCommand::with_name("symbols"))
         .subcommand(
-            SubCommand::with_name("analysis-stats").arg(Arg::with_name("verbose").short("v")),
+            SubCommand::with_name("dump-ast")
+                .arg(
+                    Arg::with_name("format")
+                        .long("--format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text"),
+                )
+                .arg(Arg::with_name("hir").long("--hir"))
+                .arg(Arg::with_name("file").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("analysis-stats")
+                .arg(Arg::with_name("verbose").short("v"))
+                .arg(Arg::with_name("memory-usage").long("--memory-usage")),
         )
+        .subcommand(SubCommand::with_name("unused-pub"))
         .get_matches();
     match matches.subcommand() {
         ("parse", Some(matches)) => {
@@ -41,6 +61,14 @@ fn main() -> Result<()> {
                 println!("{:?}", s);
             }
         }
+        ("dump-ast", Some(matches)) => {
+            let format = match matches.value_of("format").unwrap() {
+                "json" => dump_ast::Format::Json,
+                _ => dump_ast::Format::Text,
+            };
+            let file = Path::new(matches.value_of("file").unwrap());
+            dump_ast::run(file, format, matches.is_present("hir"))?;
+        }
         ("render-test", Some(matches)) => {
             let file = matches.value_of("file").unwrap();
             let file = Path::new(file);
@@ -51,8 +79,10 @@ fn main() -> Result<()> {
         }
         ("analysis-stats", Some(matches)) => {
             let verbose = matches.is_present("verbose");
-            analysis_stats::run(verbose)?;
+            let memory_usage = matches.is_present("memory-usage");
+            analysis_stats::run(verbose, memory_usage)?;
         }
+        ("unused-pub", _) => unused_pub::run()?,
         _ => unreachable!(),
     }
     Ok(())
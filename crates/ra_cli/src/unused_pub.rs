@@ -0,0 +1,198 @@
+//! Lists the public items of the workspace crates which are not referenced by
+//! the other workspace crates, to find the parts of a library API nobody
+//! uses.
+//!
+//! The candidates are the items declared `pub` in a crate, including the
+//! methods of inherent impls. Their references are the paths and method calls
+//! of the files of the other crates which resolve to them. Only the names of
+//! the candidates are resolved, and names in macro calls, which are not
+//! expanded, are assumed to refer to the candidates with the same name.
+
+use std::collections::{HashMap, HashSet};
+
+use ra_batch::BatchDatabase;
+use ra_db::{FileId, SourceDatabase};
+use ra_hir::{
+    Crate, ImplItem, Module, ModuleDef, ModuleSource, PathResolution, SourceAnalyzer,
+};
+use ra_syntax::{
+    AstNode, SmolStr, SyntaxKind::IDENT, SyntaxNode,
+    ast::{self, NameOwner, VisibilityOwner},
+};
+
+use crate::Result;
+
+struct Candidate {
+    def: ModuleDef,
+    krate: Crate,
+    name: SmolStr,
+    kind: &'static str,
+    file_id: FileId,
+}
+
+pub fn run() -> Result<()> {
+    let (db, roots) = BatchDatabase::load_cargo(".")?;
+    let crates: Vec<Crate> =
+        roots.into_iter().flat_map(|root| Crate::source_root_crates(&db, root)).collect();
+
+    let mut candidates = Vec::new();
+    let mut files = Vec::new();
+    for &krate in crates.iter() {
+        for module in crate_modules(&db, krate) {
+            if let (file, ModuleSource::SourceFile(_)) = module.definition_source(&db) {
+                files.push((krate, file.original_file(&db)));
+            }
+            for def in module.declarations(&db) {
+                candidates.extend(pub_item(&db, krate, def));
+            }
+            for impl_block in module.impl_blocks(&db) {
+                if impl_block.target_trait(&db).is_some() {
+                    continue;
+                }
+                for item in impl_block.items(&db) {
+                    let def = match item {
+                        ImplItem::Method(it) => it.into(),
+                        ImplItem::Const(it) => it.into(),
+                        ImplItem::TypeAlias(it) => it.into(),
+                    };
+                    candidates.extend(pub_item(&db, krate, def));
+                }
+            }
+        }
+    }
+    // Unit and tuple structs are declared in both namespaces.
+    let mut seen = HashSet::new();
+    candidates.retain(|it: &Candidate| seen.insert(it.def));
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, candidate) in candidates.iter().enumerate() {
+        by_name.entry(candidate.name.as_str()).or_default().push(idx);
+    }
+
+    let mut used: HashSet<ModuleDef> = HashSet::new();
+    for &(krate, file_id) in files.iter() {
+        let source_file = db.parse(file_id);
+        // Whether the use is from another crate than the one of the candidate.
+        let is_foreign = |idx: &usize| candidates[*idx].krate != krate;
+        for name_ref in source_file.syntax().descendants().filter_map(ast::NameRef::cast) {
+            let indices = match by_name.get(name_ref.text().as_str()) {
+                Some(it) => it,
+                None => continue,
+            };
+            if !indices.iter().any(is_foreign) {
+                continue;
+            }
+            used.extend(resolve_name_ref(&db, file_id, name_ref));
+        }
+        for name in names_in_token_trees(source_file.syntax()) {
+            if let Some(indices) = by_name.get(name.as_str()) {
+                used.extend(
+                    indices.iter().filter(|it| is_foreign(it)).map(|&it| candidates[it].def),
+                );
+            }
+        }
+    }
+
+    let mut unused: Vec<String> = candidates
+        .iter()
+        .filter(|it| !used.contains(&it.def))
+        .map(|it| {
+            format!("{}: {} {}", db.file_relative_path(it.file_id).as_str(), it.kind, it.name)
+        })
+        .collect();
+    unused.sort();
+    for line in unused.iter() {
+        println!("{}", line);
+    }
+    println!("Unused public items: {} of {}", unused.len(), candidates.len());
+    Ok(())
+}
+
+fn crate_modules(db: &BatchDatabase, krate: Crate) -> Vec<Module> {
+    let mut res = Vec::new();
+    let mut queue: Vec<Module> = krate.root_module(db).into_iter().collect();
+    while let Some(module) = queue.pop() {
+        queue.extend(module.children(db));
+        res.push(module);
+    }
+    res
+}
+
+/// The candidate for `def`, if it is declared `pub`.
+fn pub_item(db: &BatchDatabase, krate: Crate, def: ModuleDef) -> Option<Candidate> {
+    fn candidate<N: NameOwner + VisibilityOwner>(
+        db: &BatchDatabase,
+        krate: Crate,
+        def: ModuleDef,
+        kind: &'static str,
+        (file_id, node): (ra_hir::HirFileId, &N),
+    ) -> Option<Candidate> {
+        let is_pub = node.visibility()?.syntax().text() == "pub";
+        if !is_pub {
+            return None;
+        }
+        let name = node.name()?.text().clone();
+        Some(Candidate { def, krate, name, kind, file_id: file_id.original_file(db) })
+    }
+    match def {
+        ModuleDef::Function(it) => {
+            let (file_id, node) = it.source(db);
+            candidate(db, krate, def, "function", (file_id, &*node))
+        }
+        ModuleDef::Struct(it) => {
+            let (file_id, node) = it.source(db);
+            candidate(db, krate, def, "struct", (file_id, &*node))
+        }
+        ModuleDef::Enum(it) => {
+            let (file_id, node) = it.source(db);
+            candidate(db, krate, def, "enum", (file_id, &*node))
+        }
+        ModuleDef::Const(it) => {
+            let (file_id, node) = it.source(db);
+            candidate(db, krate, def, "constant", (file_id, &*node))
+        }
+        ModuleDef::Static(it) => {
+            let (file_id, node) = it.source(db);
+            candidate(db, krate, def, "static", (file_id, &*node))
+        }
+        ModuleDef::Trait(it) => {
+            let (file_id, node) = it.source(db);
+            candidate(db, krate, def, "trait", (file_id, &*node))
+        }
+        ModuleDef::TypeAlias(it) => {
+            let (file_id, node) = it.source(db);
+            candidate(db, krate, def, "type alias", (file_id, &*node))
+        }
+        ModuleDef::Module(_) | ModuleDef::EnumVariant(_) => None,
+    }
+}
+
+/// The item `name_ref` refers to, either as the last segment of a path or as
+/// the name of a method call.
+fn resolve_name_ref(
+    db: &BatchDatabase,
+    file_id: FileId,
+    name_ref: &ast::NameRef,
+) -> Option<ModuleDef> {
+    let analyzer = SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+    if let Some(method_call) = name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast) {
+        return analyzer.resolve_method_call(method_call).map(ModuleDef::from);
+    }
+    let path = name_ref.syntax().ancestors().find_map(ast::Path::cast)?;
+    match analyzer.resolve_path(db, path)? {
+        PathResolution::Def(def) => Some(def),
+        PathResolution::AssocItem(ImplItem::Method(it)) => Some(it.into()),
+        PathResolution::AssocItem(ImplItem::Const(it)) => Some(it.into()),
+        PathResolution::AssocItem(ImplItem::TypeAlias(it)) => Some(it.into()),
+        _ => None,
+    }
+}
+
+fn names_in_token_trees(node: &SyntaxNode) -> impl Iterator<Item = SmolStr> + '_ {
+    node.descendants()
+        .filter_map(ast::TokenTree::cast)
+        .filter(|it| it.syntax().parent().and_then(ast::TokenTree::cast).is_none())
+        .flat_map(|it| it.syntax().descendants_with_tokens())
+        .filter_map(|it| it.as_token())
+        .filter(|it| it.kind() == IDENT)
+        .map(|it| it.text().clone())
+}
@@ -5,10 +5,15 @@
 /// Note that neither this module, nor any other part of the analyzer's core do
 /// actual IO. See `vfs` and `project_model` in the `ra_lsp_server` crate for how
 /// actual IO is done and lowered to input.
-use relative_path::RelativePathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use relative_path::{RelativePath, RelativePathBuf};
 use rustc_hash::FxHashMap;
 
-use ra_syntax::SmolStr;
+use ra_syntax::{SmolStr, Edition};
 use rustc_hash::FxHashSet;
 
 /// `FileId` is an integer which uniquely identifies a file. File paths are
@@ -36,7 +41,7 @@ pub struct SourceRoot {
 
 /// `CrateGraph` is a bit of information which turns a set of text files into a
 /// number of Rust crates. Each crate is defined by the `FileId` of its root module,
-/// the set of cfg flags (not yet implemented) and the set of dependencies. Note
+/// the set of cfg flags and the set of dependencies. Note
 /// that, due to cfg's, there might be several crates for a single `FileId`! As
 /// in the rust-lang proper, a crate does not have a name. Instead, names are
 /// specified on dependency edges. That is, a crate might be known under
@@ -47,7 +52,8 @@ pub struct SourceRoot {
 /// `CrateGraph` by lowering `cargo metadata` output.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CrateGraph {
-    arena: FxHashMap<CrateId, CrateData>,
+    /// Ordered by id, which is the order the crates were added in.
+    arena: BTreeMap<CrateId, CrateData>,
 }
 
 #[derive(Debug)]
@@ -56,18 +62,45 @@ pub struct CyclicDependencies;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CrateId(pub u32);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Edition {
-    Edition2018,
-    Edition2015,
+/// The cfg flags a crate is compiled with, like `unix` or `feature = "std"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CfgOptions {
+    atoms: FxHashSet<SmolStr>,
+    key_values: FxHashSet<(SmolStr, SmolStr)>,
 }
 
-impl Edition {
-    pub fn from_string(s: &str) -> Edition {
-        match s {
-            "2015" => Edition::Edition2015,
-            "2018" | _ => Edition::Edition2018,
-        }
+impl CfgOptions {
+    pub fn insert_atom(&mut self, atom: SmolStr) {
+        self.atoms.insert(atom);
+    }
+
+    pub fn insert_key_value(&mut self, key: SmolStr, value: SmolStr) {
+        self.key_values.insert((key, value));
+    }
+
+    pub fn is_atom_enabled(&self, atom: &str) -> bool {
+        self.atoms.iter().any(|it| it == atom)
+    }
+
+    pub fn is_key_value_enabled(&self, key: &str, value: &str) -> bool {
+        self.key_values.iter().any(|(k, v)| k == key && v == value)
+    }
+}
+
+/// The environment variables the build system sets when compiling a crate,
+/// which are available through `env!`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Env {
+    entries: FxHashMap<String, String>,
+}
+
+impl Env {
+    pub fn set(&mut self, key: &str, value: String) {
+        self.entries.insert(key.to_string(), value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(|it| it.as_str())
     }
 }
 
@@ -75,12 +108,29 @@ impl Edition {
 struct CrateData {
     file_id: FileId,
     edition: Edition,
+    /// Version of the package the crate comes from, if the build system knows it.
+    version: Option<SmolStr>,
+    cfg_options: CfgOptions,
+    env: Env,
+    /// The compiled library of a proc macro crate, if it was built.
+    proc_macro_dylib: Option<PathBuf>,
+    /// The files generated by the build script, relative to `OUT_DIR`.
+    out_dir_files: FxHashMap<RelativePathBuf, FileId>,
     dependencies: Vec<Dependency>,
 }
 
 impl CrateData {
     fn new(file_id: FileId, edition: Edition) -> CrateData {
-        CrateData { file_id, edition, dependencies: Vec::new() }
+        CrateData {
+            file_id,
+            edition,
+            version: None,
+            cfg_options: CfgOptions::default(),
+            env: Env::default(),
+            proc_macro_dylib: None,
+            out_dir_files: FxHashMap::default(),
+            dependencies: Vec::new(),
+        }
     }
 
     fn add_dep(&mut self, name: SmolStr, crate_id: CrateId) {
@@ -120,12 +170,35 @@ impl CrateGraph {
         Ok(self.arena.get_mut(&from).unwrap().add_dep(name, to))
     }
 
+    pub fn set_version(&mut self, crate_id: CrateId, version: SmolStr) {
+        self.arena.get_mut(&crate_id).unwrap().version = Some(version);
+    }
+
+    pub fn set_cfg_options(&mut self, crate_id: CrateId, cfg_options: CfgOptions) {
+        self.arena.get_mut(&crate_id).unwrap().cfg_options = cfg_options;
+    }
+
+    pub fn set_env(&mut self, crate_id: CrateId, env: Env) {
+        self.arena.get_mut(&crate_id).unwrap().env = env;
+    }
+
+    pub fn set_proc_macro_dylib(&mut self, crate_id: CrateId, dylib: PathBuf) {
+        self.arena.get_mut(&crate_id).unwrap().proc_macro_dylib = Some(dylib);
+    }
+
+    /// Records `file_id` as the file at `path` in the `OUT_DIR` of the crate,
+    /// which `include!(concat!(env!("OUT_DIR"), ..))` can refer to.
+    pub fn add_out_dir_file(&mut self, crate_id: CrateId, path: RelativePathBuf, file_id: FileId) {
+        self.arena.get_mut(&crate_id).unwrap().out_dir_files.insert(path, file_id);
+    }
+
     pub fn is_empty(&self) -> bool {
         self.arena.is_empty()
     }
 
+    /// The ids of the crates, in the order they were added.
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = CrateId> + 'a {
-        self.arena.keys().map(|it| *it)
+        self.arena.keys().cloned()
     }
 
     pub fn crate_root(&self, crate_id: CrateId) -> FileId {
@@ -136,10 +209,29 @@ impl CrateGraph {
         self.arena[&crate_id].edition
     }
 
+    pub fn version(&self, crate_id: CrateId) -> Option<&SmolStr> {
+        self.arena[&crate_id].version.as_ref()
+    }
+
+    pub fn cfg_options(&self, crate_id: CrateId) -> &CfgOptions {
+        &self.arena[&crate_id].cfg_options
+    }
+
+    pub fn env(&self, crate_id: CrateId) -> &Env {
+        &self.arena[&crate_id].env
+    }
+
+    pub fn proc_macro_dylib(&self, crate_id: CrateId) -> Option<&Path> {
+        self.arena[&crate_id].proc_macro_dylib.as_ref().map(|it| it.as_path())
+    }
+
+    pub fn out_dir_file(&self, crate_id: CrateId, path: &RelativePath) -> Option<FileId> {
+        self.arena[&crate_id].out_dir_files.get(path).cloned()
+    }
+
     // FIXME: this only finds one crate with the given root; we could have multiple
     pub fn crate_id_for_crate_root(&self, file_id: FileId) -> Option<CrateId> {
-        let (&crate_id, _) = self.arena.iter().find(|(_crate_id, data)| data.file_id == file_id)?;
-        Some(crate_id)
+        self.iter().find(|it| self.arena[it].file_id == file_id)
     }
 
     pub fn dependencies<'a>(
@@ -149,17 +241,52 @@ impl CrateGraph {
         self.arena[&crate_id].dependencies.iter()
     }
 
-    /// Extends this crate graph by adding a complete disjoint second crate
-    /// graph.
-    pub fn extend(&mut self, other: CrateGraph) {
-        let start = self.arena.len() as u32;
-        self.arena.extend(other.arena.into_iter().map(|(id, mut data)| {
-            let new_id = CrateId(id.0 + start);
-            for dep in &mut data.dependencies {
-                dep.crate_id = CrateId(dep.crate_id.0 + start);
+    /// Extends this crate graph by adding a second crate graph.
+    ///
+    /// The crates of `other` which are identical to a crate of this graph,
+    /// with the same root, cfg flags and dependencies, like the sysroot of two
+    /// workspaces, are merged with it rather than added twice. Returns the
+    /// ids the crates of `other` have in this graph.
+    pub fn extend(&mut self, other: CrateGraph) -> FxHashMap<CrateId, CrateId> {
+        let mut by_root: FxHashMap<FileId, Vec<CrateId>> = FxHashMap::default();
+        for (&id, data) in self.arena.iter() {
+            by_root.entry(data.file_id).or_default().push(id);
+        }
+        let mut id_map = FxHashMap::default();
+        for id in other.iter() {
+            other.extend_crate(id, self, &mut by_root, &mut id_map);
+        }
+        id_map
+    }
+
+    /// Adds crate `id` of this graph to `target` after its dependencies, as
+    /// the ids of the dependencies are part of the data being compared.
+    fn extend_crate(
+        &self,
+        id: CrateId,
+        target: &mut CrateGraph,
+        by_root: &mut FxHashMap<FileId, Vec<CrateId>>,
+        id_map: &mut FxHashMap<CrateId, CrateId>,
+    ) -> CrateId {
+        if let Some(&new_id) = id_map.get(&id) {
+            return new_id;
+        }
+        let mut data = self.arena[&id].clone();
+        for dep in data.dependencies.iter_mut() {
+            dep.crate_id = self.extend_crate(dep.crate_id, target, by_root, id_map);
+        }
+        let same_root = by_root.entry(data.file_id).or_default();
+        let new_id = match same_root.iter().find(|it| target.arena[it] == data) {
+            Some(&existing) => existing,
+            None => {
+                let new_id = CrateId(target.arena.len() as u32);
+                target.arena.insert(new_id, data);
+                same_root.push(new_id);
+                new_id
             }
-            (new_id, data)
-        }));
+        };
+        id_map.insert(id, new_id);
+        new_id
     }
 
     fn dfs_find(&self, target: CrateId, from: CrateId, visited: &mut FxHashSet<CrateId>) -> bool {
@@ -183,7 +310,7 @@ impl CrateGraph {
 
 #[cfg(test)]
 mod tests {
-    use super::{CrateGraph, FileId, SmolStr, Edition::Edition2018};
+    use super::{CfgOptions, CrateGraph, CrateId, Env, FileId, SmolStr, Edition::Edition2018};
 
     #[test]
     fn it_should_panic_because_of_cycle_dependencies() {
@@ -205,4 +332,70 @@ mod tests {
         assert!(graph.add_dep(crate1, SmolStr::new("crate2"), crate2).is_ok());
         assert!(graph.add_dep(crate2, SmolStr::new("crate3"), crate3).is_ok());
     }
+
+    #[test]
+    fn it_remembers_versions() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(FileId(1u32), Edition2018);
+        let crate2 = graph.add_crate_root(FileId(2u32), Edition2018);
+        graph.set_version(crate2, SmolStr::new("0.1.0"));
+        assert_eq!(graph.version(crate1), None);
+        assert_eq!(graph.version(crate2).map(|it| it.as_str()), Some("0.1.0"));
+    }
+
+    #[test]
+    fn it_remembers_cfg_options_and_env() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(FileId(1u32), Edition2018);
+        let mut cfg_options = CfgOptions::default();
+        cfg_options.insert_atom("unix".into());
+        cfg_options.insert_key_value("feature".into(), "std".into());
+        graph.set_cfg_options(crate1, cfg_options);
+        let mut env = Env::default();
+        env.set("OUT_DIR", "/target/out".to_string());
+        graph.set_env(crate1, env);
+
+        let cfg_options = graph.cfg_options(crate1);
+        assert!(cfg_options.is_atom_enabled("unix"));
+        assert!(!cfg_options.is_atom_enabled("windows"));
+        assert!(cfg_options.is_key_value_enabled("feature", "std"));
+        assert!(!cfg_options.is_key_value_enabled("feature", "alloc"));
+        assert_eq!(graph.env(crate1).get("OUT_DIR"), Some("/target/out"));
+    }
+
+    #[test]
+    fn it_iterates_in_insertion_order() {
+        let mut graph = CrateGraph::default();
+        let crates: Vec<CrateId> =
+            (0..20).map(|it| graph.add_crate_root(FileId(it), Edition2018)).collect();
+        assert_eq!(graph.iter().collect::<Vec<_>>(), crates);
+    }
+
+    #[test]
+    fn it_deduplicates_identical_crates_on_extend() {
+        let mut graph = CrateGraph::default();
+        let core = graph.add_crate_root(FileId(1u32), Edition2018);
+        let foo = graph.add_crate_root(FileId(2u32), Edition2018);
+        graph.add_dep(foo, SmolStr::new("core"), core).unwrap();
+
+        let mut other = CrateGraph::default();
+        let bar = other.add_crate_root(FileId(3u32), Edition2018);
+        let other_foo = other.add_crate_root(FileId(2u32), Edition2018);
+        let other_core = other.add_crate_root(FileId(1u32), Edition2018);
+        other.add_dep(other_foo, SmolStr::new("core"), other_core).unwrap();
+        other.add_dep(bar, SmolStr::new("foo"), other_foo).unwrap();
+        // Same root, but different cfg flags, so a different crate.
+        let test_foo = other.add_crate_root(FileId(2u32), Edition2018);
+        let mut cfg_options = CfgOptions::default();
+        cfg_options.insert_atom("test".into());
+        other.set_cfg_options(test_foo, cfg_options);
+
+        let id_map = graph.extend(other);
+        assert_eq!(graph.iter().count(), 4);
+        assert_eq!(id_map[&other_core], core);
+        assert_eq!(id_map[&other_foo], foo);
+        assert_ne!(id_map[&test_foo], foo);
+        let bar_deps: Vec<_> = graph.dependencies(id_map[&bar]).map(|it| it.crate_id()).collect();
+        assert_eq!(bar_deps, vec![foo]);
+    }
 }
@@ -4,6 +4,7 @@ mod input;
 
 use std::{panic, sync::Arc};
 
+use ra_prof::profile;
 use ra_syntax::{TextUnit, TextRange, SourceFile, TreeArc};
 use relative_path::RelativePathBuf;
 
@@ -11,9 +12,10 @@ pub use ::salsa as salsa;
 pub use crate::{
     cancellation::Canceled,
     input::{
-        FileId, CrateId, SourceRoot, SourceRootId, CrateGraph, Dependency, Edition,
+        FileId, CrateId, SourceRoot, SourceRootId, CrateGraph, Dependency, CfgOptions, Env,
     },
 };
+pub use ra_syntax::Edition;
 
 pub trait CheckCanceled: panic::RefUnwindSafe {
     /// Aborts current query if there are pending changes.
@@ -72,6 +74,9 @@ pub trait SourceDatabase: CheckCanceled + std::fmt::Debug {
     fn file_text(&self, file_id: FileId) -> Arc<String>;
     // Parses the file into the syntax tree.
     fn parse(&self, file_id: FileId) -> TreeArc<SourceFile>;
+    /// The edition the file is parsed with, which is the edition of the crate
+    /// it belongs to.
+    fn file_edition(&self, file_id: FileId) -> Edition;
     /// Path to a file, relative to the root of its source root.
     #[salsa::input]
     fn file_relative_path(&self, file_id: FileId) -> RelativePathBuf;
@@ -96,6 +101,15 @@ fn source_root_crates(db: &impl SourceDatabase, id: SourceRootId) -> Arc<Vec<Cra
 }
 
 fn parse(db: &impl SourceDatabase, file_id: FileId) -> TreeArc<SourceFile> {
+    let _p = profile("parse_query");
     let text = db.file_text(file_id);
-    SourceFile::parse(&*text)
+    SourceFile::parse_with_edition(&*text, db.file_edition(file_id))
+}
+
+fn file_edition(db: &impl SourceDatabase, file_id: FileId) -> Edition {
+    // A source root usually holds a single package, so its crates share the
+    // edition. Files outside of any crate are parsed as 2018 code.
+    let crates = db.source_root_crates(db.file_source_root(file_id));
+    let graph = db.crate_graph();
+    crates.first().map_or(Edition::Edition2018, |&krate| graph.edition(krate))
 }
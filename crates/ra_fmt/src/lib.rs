@@ -12,6 +12,16 @@ pub fn reindent(text: &str, indent: &str) -> String {
     text.lines().intersperse(&indent).collect()
 }
 
+/// Removes `indent` from the start of every line of `text` but the first, the
+/// inverse of `reindent`.
+pub fn unindent(text: &str, indent: &str) -> String {
+    let mut lines = text.lines();
+    let first = lines.next().unwrap_or("");
+    let rest =
+        lines.map(|line| if line.starts_with(indent) { &line[indent.len()..] } else { line });
+    std::iter::once(first).chain(rest).intersperse("\n").collect()
+}
+
 /// If the node is on the beginning of the line, calculate indent.
 pub fn leading_indent(node: &SyntaxNode) -> Option<&str> {
     for token in prev_tokens(node.first_token()?) {
@@ -112,7 +112,7 @@ pub(crate) struct EnumVariantData {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub(crate) struct StructFieldId(RawId);
+pub struct StructFieldId(RawId);
 impl_arena_id!(StructFieldId);
 
 /// A single field of an enum variant or struct
@@ -0,0 +1,94 @@
+use ra_syntax::{
+    ast::{self, AstNode},
+    SmolStr,
+    SyntaxKind::{EQ, IDENT, STRING},
+};
+
+use crate::HirDatabase;
+
+/// The `#[deprecated]` attribute of an item.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deprecation {
+    since: Option<SmolStr>,
+    note: Option<SmolStr>,
+}
+
+impl Deprecation {
+    /// The deprecation of `node`, if it has a `#[deprecated]` attribute.
+    pub fn from_attrs(node: &impl ast::AttrsOwner) -> Option<Deprecation> {
+        node.attrs().find_map(|attr| {
+            if attr.as_named()? != "deprecated" {
+                return None;
+            }
+            if let Some((_, note)) = attr.as_key_value() {
+                return Some(Deprecation { since: None, note: Some(note) });
+            }
+            let mut res = Deprecation::default();
+            if let Some((_, args)) = attr.as_call() {
+                // The arguments are a list of `key = "value"` pairs.
+                let tokens: Vec<_> = args
+                    .syntax()
+                    .children_with_tokens()
+                    .filter_map(|it| it.as_token())
+                    .filter(|it| !it.kind().is_trivia())
+                    .collect();
+                for pair in tokens.windows(3) {
+                    if pair[0].kind() != IDENT || pair[1].kind() != EQ || pair[2].kind() != STRING {
+                        continue;
+                    }
+                    let value = Some(pair[2].text().trim_matches('"').into());
+                    match pair[0].text().as_str() {
+                        "since" => res.since = value,
+                        "note" => res.note = value,
+                        _ => (),
+                    }
+                }
+            }
+            Some(res)
+        })
+    }
+
+    /// The version since which the item is deprecated, like `1.2.0` in
+    /// `#[deprecated(since = "1.2.0")]`.
+    pub fn since(&self) -> Option<&str> {
+        self.since.as_ref().map(|it| it.as_str())
+    }
+
+    /// Why the item is deprecated, like the note of
+    /// `#[deprecated = "use `bar` instead"]`.
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_ref().map(|it| it.as_str())
+    }
+}
+
+/// The attributes of items known to the IDE.
+pub trait Attrs {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation>;
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::SourceFile;
+
+    use super::*;
+
+    fn deprecation_of(item: &str) -> Option<Deprecation> {
+        let file = SourceFile::parse(item);
+        let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        Deprecation::from_attrs(fn_def)
+    }
+
+    #[test]
+    fn parses_deprecated_attributes() {
+        assert_eq!(deprecation_of("#[inline] fn foo() {}"), None);
+        assert_eq!(deprecation_of("#[deprecated] fn foo() {}"), Some(Deprecation::default()));
+        assert_eq!(
+            deprecation_of(r#"#[deprecated = "use bar"] fn foo() {}"#),
+            Some(Deprecation { since: None, note: Some("use bar".into()) })
+        );
+        assert_eq!(
+            deprecation_of(r#"#[deprecated(since = "1.2.0", note = "use bar")] fn foo() {}"#),
+            Some(Deprecation { since: Some("1.2.0".into()), note: Some("use bar".into()) })
+        );
+    }
+}
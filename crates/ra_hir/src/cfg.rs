@@ -0,0 +1,180 @@
+//! Conditional compilation: the `#[cfg(...)]` attributes of the items, which
+//! are evaluated with the cfg flags of the crate, like `unix` or
+//! `feature = "std"`.
+
+use ra_db::CfgOptions;
+use ra_syntax::{
+    AstNode, SmolStr, SyntaxElement, SyntaxNode,
+    SyntaxKind::{COMMA, EQ, IDENT, STRING, TOKEN_TREE},
+    ast,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CfgExpr {
+    /// A predicate we can't parse, which is considered enabled to not hide
+    /// code rustc would compile.
+    Invalid,
+    Atom(SmolStr),
+    KeyValue {
+        key: SmolStr,
+        value: SmolStr,
+    },
+    All(Vec<CfgExpr>),
+    Any(Vec<CfgExpr>),
+    Not(Box<CfgExpr>),
+}
+
+impl CfgExpr {
+    /// The predicate of the `#[cfg(...)]` attributes of the item `node`,
+    /// which must all hold, if it has any.
+    pub(crate) fn from_attrs(node: &SyntaxNode) -> Option<CfgExpr> {
+        let mut preds: Vec<CfgExpr> = node
+            .children()
+            .filter_map(ast::Attr::cast)
+            .filter_map(|attr| attr.as_call())
+            .filter(|(name, _args)| name == "cfg")
+            .map(|(_name, args)| parse_cfg(args))
+            .collect();
+        match preds.len() {
+            0 => None,
+            1 => preds.pop(),
+            _ => Some(CfgExpr::All(preds)),
+        }
+    }
+
+    pub(crate) fn is_enabled(&self, cfg_options: &CfgOptions) -> bool {
+        match self {
+            CfgExpr::Invalid => true,
+            CfgExpr::Atom(atom) => cfg_options.is_atom_enabled(atom),
+            CfgExpr::KeyValue { key, value } => cfg_options.is_key_value_enabled(key, value),
+            CfgExpr::All(preds) => preds.iter().all(|it| it.is_enabled(cfg_options)),
+            CfgExpr::Any(preds) => preds.iter().any(|it| it.is_enabled(cfg_options)),
+            CfgExpr::Not(pred) => !pred.is_enabled(cfg_options),
+        }
+    }
+}
+
+/// Parses the arguments of a `cfg` attribute, `(unix)` in `#[cfg(unix)]`.
+fn parse_cfg(tt: &ast::TokenTree) -> CfgExpr {
+    let elements = tt_elements(tt.syntax());
+    let mut input = elements.as_slice();
+    let res = next_cfg_expr(&mut input).unwrap_or(CfgExpr::Invalid);
+    if input.is_empty() {
+        res
+    } else {
+        CfgExpr::Invalid
+    }
+}
+
+/// The elements of a token tree, without its delimiters and the trivia.
+fn tt_elements(tt: &SyntaxNode) -> Vec<SyntaxElement> {
+    let mut res: Vec<SyntaxElement> =
+        tt.children_with_tokens().filter(|it| !it.kind().is_trivia()).collect();
+    if res.len() < 2 {
+        return Vec::new();
+    }
+    res.pop();
+    res.remove(0);
+    res
+}
+
+fn next_cfg_expr(input: &mut &[SyntaxElement]) -> Option<CfgExpr> {
+    let name = match input.first()?.as_token() {
+        Some(token) if token.kind() == IDENT => token.text().clone(),
+        _ => return Some(skip_pred(input)),
+    };
+    *input = &input[1..];
+    let res = match input.first().map(|it| it.kind()) {
+        Some(EQ) => {
+            let value = match input.get(1).and_then(|it| it.as_token()) {
+                Some(token) if token.kind() == STRING => token.text().trim_matches('"').into(),
+                _ => return Some(skip_pred(input)),
+            };
+            *input = &input[2..];
+            CfgExpr::KeyValue { key: name, value }
+        }
+        Some(TOKEN_TREE) => {
+            let elements = tt_elements(input[0].as_node()?);
+            *input = &input[1..];
+            let mut args = elements.as_slice();
+            let mut preds = Vec::new();
+            while let Some(pred) = next_cfg_expr(&mut args) {
+                preds.push(pred);
+            }
+            match name.as_str() {
+                "all" => CfgExpr::All(preds),
+                "any" => CfgExpr::Any(preds),
+                "not" if preds.len() == 1 => CfgExpr::Not(Box::new(preds.pop().unwrap())),
+                _ => CfgExpr::Invalid,
+            }
+        }
+        _ => CfgExpr::Atom(name),
+    };
+    match input.first().map(|it| it.kind()) {
+        Some(COMMA) => *input = &input[1..],
+        None => (),
+        Some(_) => return Some(skip_pred(input)),
+    }
+    Some(res)
+}
+
+/// Skips the rest of a predicate we can't parse, up to the next comma.
+fn skip_pred(input: &mut &[SyntaxElement]) -> CfgExpr {
+    let end = input.iter().position(|it| it.kind() == COMMA).map_or(input.len(), |it| it + 1);
+    *input = &input[end..];
+    CfgExpr::Invalid
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::SourceFile;
+
+    use super::*;
+
+    fn cfg_of(item: &str) -> Option<CfgExpr> {
+        let file = SourceFile::parse(item);
+        let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        CfgExpr::from_attrs(fn_def.syntax())
+    }
+
+    #[test]
+    fn parses_cfg_predicates() {
+        assert_eq!(cfg_of("fn foo() {}"), None);
+        assert_eq!(cfg_of("#[cfg(test)] fn foo() {}"), Some(CfgExpr::Atom("test".into())));
+        assert_eq!(
+            cfg_of(r#"#[cfg(feature = "std")] fn foo() {}"#),
+            Some(CfgExpr::KeyValue { key: "feature".into(), value: "std".into() })
+        );
+        assert_eq!(
+            cfg_of(r#"#[cfg(all(unix, not(feature = "std")))] fn foo() {}"#),
+            Some(CfgExpr::All(vec![
+                CfgExpr::Atom("unix".into()),
+                CfgExpr::Not(Box::new(CfgExpr::KeyValue {
+                    key: "feature".into(),
+                    value: "std".into()
+                })),
+            ]))
+        );
+        assert_eq!(
+            cfg_of("#[cfg(unix)] #[cfg(test)] fn foo() {}"),
+            Some(CfgExpr::All(vec![CfgExpr::Atom("unix".into()), CfgExpr::Atom("test".into())]))
+        );
+        assert_eq!(cfg_of("#[cfg(not(a, b))] fn foo() {}"), Some(CfgExpr::Invalid));
+        assert_eq!(cfg_of("#[cfg(unix test)] fn foo() {}"), Some(CfgExpr::Invalid));
+    }
+
+    #[test]
+    fn evaluates_cfg_predicates() {
+        let mut cfg_options = CfgOptions::default();
+        cfg_options.insert_atom("unix".into());
+        cfg_options.insert_key_value("feature".into(), "std".into());
+        let enabled = |item: &str| cfg_of(item).unwrap().is_enabled(&cfg_options);
+
+        assert!(enabled("#[cfg(unix)] fn foo() {}"));
+        assert!(!enabled("#[cfg(test)] fn foo() {}"));
+        assert!(enabled(r#"#[cfg(any(windows, feature = "std"))] fn foo() {}"#));
+        assert!(!enabled(r#"#[cfg(all(unix, not(feature = "std")))] fn foo() {}"#));
+        assert!(enabled("#[cfg(not(test))] fn foo() {}"));
+        assert!(enabled("#[cfg(unix test)] fn foo() {}"));
+    }
+}
@@ -1,18 +1,20 @@
 use std::sync::Arc;
 
 use ra_db::{CrateId, SourceRootId, Edition};
-use ra_syntax::{ast::self, TreeArc};
+use ra_syntax::{SmolStr, SyntaxNode};
+use ra_syntax::{ast::{self, AstNode, NameOwner}, TreeArc};
 
 use crate::{
-    Name, Ty, HirFileId, Either,
+    Name, AsName, Ty, HirFileId, Either,
     HirDatabase, DefDatabase,
     type_ref::TypeRef,
     nameres::{ModuleScope, Namespace, ImportId, CrateModuleId},
-    expr::{Body, BodySourceMap},
-    ty::InferenceResult,
+    expr::{Body, BodySourceMap, UnsafeOperation},
+    ty::{InferenceResult, ObjectSafetyViolation, object_safety, unresolved_types},
     adt::{EnumVariantId, StructFieldId, VariantDef},
     generics::HasGenericParams,
     docs::{Documentation, Docs, docs_from_ast},
+    attrs::{Attrs, Deprecation},
     ids::{FunctionId, StructId, EnumId, AstItemDef, ConstId, StaticId, TraitId, TypeAliasId},
     impl_block::ImplBlock,
     resolve::Resolver,
@@ -52,6 +54,11 @@ impl Crate {
         crate_graph.edition(self.crate_id)
     }
 
+    pub fn version(&self, db: &impl DefDatabase) -> Option<SmolStr> {
+        let crate_graph = db.crate_graph();
+        crate_graph.version(self.crate_id).cloned()
+    }
+
     // FIXME: should this be in source_binder?
     pub fn source_root_crates(db: &impl DefDatabase, source_root: SourceRootId) -> Vec<Crate> {
         let crate_ids = db.source_root_crates(source_root);
@@ -91,6 +98,22 @@ impl_froms!(
     TypeAlias
 );
 
+impl Attrs for ModuleDef {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        match self {
+            ModuleDef::Module(it) => it.deprecation(db),
+            ModuleDef::Function(it) => it.deprecation(db),
+            ModuleDef::Struct(it) => it.deprecation(db),
+            ModuleDef::Enum(it) => it.deprecation(db),
+            ModuleDef::EnumVariant(it) => it.deprecation(db),
+            ModuleDef::Const(it) => it.deprecation(db),
+            ModuleDef::Static(it) => it.deprecation(db),
+            ModuleDef::Trait(it) => it.deprecation(db),
+            ModuleDef::TypeAlias(it) => it.deprecation(db),
+        }
+    }
+}
+
 pub enum ModuleSource {
     SourceFile(TreeArc<ast::SourceFile>),
     Module(TreeArc<ast::Module>),
@@ -171,21 +194,31 @@ impl Module {
 
     pub fn diagnostics(&self, db: &impl HirDatabase, sink: &mut DiagnosticSink) {
         db.crate_def_map(self.krate).add_diagnostics(db, self.module_id, sink);
-        for decl in self.declarations(db) {
+        let mut declarations = self.declarations(db);
+        // structs are declared in both namespaces
+        declarations.dedup();
+        for decl in declarations {
             match decl {
                 crate::ModuleDef::Function(f) => f.diagnostics(db, sink),
                 crate::ModuleDef::Module(f) => f.diagnostics(db, sink),
+                crate::ModuleDef::Struct(s) => {
+                    let (file_id, source) = s.source(db);
+                    add_item_diagnostics(db, file_id, source.syntax(), &s.resolver(db), sink);
+                }
+                crate::ModuleDef::Enum(e) => {
+                    let (file_id, source) = e.source(db);
+                    add_item_diagnostics(db, file_id, source.syntax(), &e.resolver(db), sink);
+                }
+                crate::ModuleDef::TypeAlias(t) => {
+                    let (file_id, source) = t.source(db);
+                    add_item_diagnostics(db, file_id, source.syntax(), &t.resolver(db), sink);
+                }
                 _ => (),
             }
         }
 
         for impl_block in self.impl_blocks(db) {
-            for item in impl_block.items(db) {
-                match item {
-                    crate::ImplItem::Method(f) => f.diagnostics(db, sink),
-                    _ => (),
-                }
-            }
+            impl_block.diagnostics(db, sink);
         }
     }
 
@@ -216,12 +249,31 @@ impl Module {
     }
 }
 
+/// Reports the problems in the syntax `node` of an item, like the types which
+/// don't resolve in the scope of `resolver`.
+fn add_item_diagnostics(
+    db: &impl HirDatabase,
+    file_id: HirFileId,
+    node: &SyntaxNode,
+    resolver: &Resolver,
+    sink: &mut DiagnosticSink,
+) {
+    object_safety::add_diagnostics(db, file_id, node, resolver, sink);
+    unresolved_types::add_diagnostics(db, file_id, node, resolver, sink);
+}
+
 impl Docs for Module {
     fn docs(&self, db: &impl HirDatabase) -> Option<Documentation> {
         self.declaration_source(db).and_then(|it| docs_from_ast(&*it.1))
     }
 }
 
+impl Attrs for Module {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        self.declaration_source(db).and_then(|it| Deprecation::from_attrs(&*it.1))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct StructField {
     pub(crate) parent: VariantDef,
@@ -244,7 +296,7 @@ impl StructField {
     }
 
     pub fn ty(&self, db: &impl HirDatabase) -> Ty {
-        db.type_for_field(*self)
+        db.field_types(self.parent)[self.id].clone()
     }
 
     pub fn parent_def(&self, _db: &impl HirDatabase) -> VariantDef {
@@ -261,6 +313,15 @@ impl Docs for StructField {
     }
 }
 
+impl Attrs for StructField {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        match self.source(db).1 {
+            FieldSource::Named(named) => Deprecation::from_attrs(&*named),
+            FieldSource::Pos(pos) => Deprecation::from_attrs(&*pos),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Struct {
     pub(crate) id: StructId,
@@ -325,6 +386,12 @@ impl Docs for Struct {
     }
 }
 
+impl Attrs for Struct {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        Deprecation::from_attrs(&*self.source(db).1)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Enum {
     pub(crate) id: EnumId,
@@ -381,6 +448,12 @@ impl Docs for Enum {
     }
 }
 
+impl Attrs for Enum {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        Deprecation::from_attrs(&*self.source(db).1)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct EnumVariant {
     pub(crate) parent: Enum,
@@ -427,6 +500,12 @@ impl Docs for EnumVariant {
     }
 }
 
+impl Attrs for EnumVariant {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        Deprecation::from_attrs(&*self.source(db).1)
+    }
+}
+
 /// The defs which have a body.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DefWithBody {
@@ -450,6 +529,11 @@ impl DefWithBody {
         db.body_with_source_map(*self).1
     }
 
+    /// The operations in the body which are only allowed in unsafe contexts.
+    pub fn unsafe_operations(&self, db: &impl HirDatabase) -> Vec<UnsafeOperation> {
+        crate::expr::unsafety::unsafe_operations(db, *self)
+    }
+
     /// Builds a resolver for code inside this item.
     pub(crate) fn resolver(&self, db: &impl HirDatabase) -> Resolver {
         match *self {
@@ -474,6 +558,8 @@ pub struct FnSignature {
     /// True if the first param is `self`. This is relevant to decide whether this
     /// can be called as a method.
     pub(crate) has_self_param: bool,
+    pub(crate) is_unsafe: bool,
+    pub(crate) is_async: bool,
 }
 
 impl FnSignature {
@@ -485,6 +571,7 @@ impl FnSignature {
         &self.params
     }
 
+    /// The declared return type; `async` functions return a future of it.
     pub fn ret_type(&self) -> &TypeRef {
         &self.ret_type
     }
@@ -494,6 +581,14 @@ impl FnSignature {
     pub fn has_self_param(&self) -> bool {
         self.has_self_param
     }
+
+    pub fn is_unsafe(&self) -> bool {
+        self.is_unsafe
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.is_async
+    }
 }
 
 impl Function {
@@ -562,7 +657,13 @@ impl Function {
     }
 
     pub fn diagnostics(&self, db: &impl HirDatabase, sink: &mut DiagnosticSink) {
+        self.body_source_map(db).add_diagnostics(db, self.source(db).0, sink);
         self.infer(db).add_diagnostics(db, *self, sink);
+        crate::expr::unsafety::add_diagnostics(db, *self, sink);
+        crate::expr::usages::add_diagnostics(db, *self, sink);
+        crate::expr::must_use::add_diagnostics(db, *self, sink);
+        let (file_id, source) = self.source(db);
+        add_item_diagnostics(db, file_id, source.syntax(), &self.resolver(db), sink);
     }
 }
 
@@ -572,6 +673,12 @@ impl Docs for Function {
     }
 }
 
+impl Attrs for Function {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        Deprecation::from_attrs(&*self.source(db).1)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Const {
     pub(crate) id: ConstId,
@@ -586,6 +693,10 @@ impl Const {
         self.id.module(db)
     }
 
+    pub fn ty(&self, db: &impl HirDatabase) -> Ty {
+        db.type_for_def((*self).into(), Namespace::Values)
+    }
+
     pub fn signature(&self, db: &impl HirDatabase) -> Arc<ConstSignature> {
         db.const_signature(*self)
     }
@@ -618,6 +729,12 @@ impl Docs for Const {
     }
 }
 
+impl Attrs for Const {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        Deprecation::from_attrs(&*self.source(db).1)
+    }
+}
+
 /// The declared signature of a const.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConstSignature {
@@ -649,10 +766,18 @@ impl Static {
         self.id.module(db)
     }
 
+    pub fn ty(&self, db: &impl HirDatabase) -> Ty {
+        db.type_for_def((*self).into(), Namespace::Values)
+    }
+
     pub fn signature(&self, db: &impl HirDatabase) -> Arc<ConstSignature> {
         db.static_signature(*self)
     }
 
+    pub fn is_mut(&self, db: &impl DefDatabase) -> bool {
+        self.source(db).1.is_mut()
+    }
+
     /// Builds a resolver for code inside this item.
     pub(crate) fn resolver(&self, db: &impl HirDatabase) -> Resolver {
         // take the outer scope...
@@ -670,6 +795,12 @@ impl Docs for Static {
     }
 }
 
+impl Attrs for Static {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        Deprecation::from_attrs(&*self.source(db).1)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Trait {
     pub(crate) id: TraitId,
@@ -692,6 +823,12 @@ impl Trait {
         self.trait_data(db).items().to_vec()
     }
 
+    /// The reasons why this trait can't be used as `dyn Trait`; empty if the
+    /// trait is object safe.
+    pub fn object_safety_violations(self, db: &impl HirDatabase) -> Vec<ObjectSafetyViolation> {
+        object_safety::object_safety_violations(db, self)
+    }
+
     pub(crate) fn trait_data(self, db: &impl DefDatabase) -> Arc<TraitData> {
         db.trait_data(self)
     }
@@ -711,6 +848,12 @@ impl Docs for Trait {
     }
 }
 
+impl Attrs for Trait {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        Deprecation::from_attrs(&*self.source(db).1)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TypeAlias {
     pub(crate) id: TypeAliasId,
@@ -750,6 +893,10 @@ impl TypeAlias {
         db.type_alias_ref(self)
     }
 
+    pub fn name(self, db: &impl DefDatabase) -> Option<Name> {
+        self.source(db).1.name().map(|it| it.as_name())
+    }
+
     /// Builds a resolver for the type references in this type alias.
     pub(crate) fn resolver(&self, db: &impl HirDatabase) -> Resolver {
         // take the outer scope...
@@ -770,6 +917,12 @@ impl Docs for TypeAlias {
     }
 }
 
+impl Attrs for TypeAlias {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        Deprecation::from_attrs(&*self.source(db).1)
+    }
+}
+
 pub enum Container {
     Trait(Trait),
     ImplBlock(ImplBlock),
@@ -44,7 +44,9 @@ impl FnSignature {
             TypeRef::unit()
         };
 
-        let sig = FnSignature { name, params, ret_type, has_self_param };
+        let is_unsafe = node.is_unsafe();
+        let is_async = node.is_async();
+        let sig = FnSignature { name, params, ret_type, has_self_param, is_unsafe, is_async };
         Arc::new(sig)
     }
 }
@@ -2,20 +2,21 @@ use std::sync::Arc;
 
 use ra_syntax::{SyntaxNode, TreeArc, SourceFile, ast};
 use ra_db::{SourceDatabase, salsa};
+use ra_arena::map::ArenaMap;
 
 use crate::{
     HirFileId, MacroDefId, AstIdMap, ErasedFileAstId, Crate, Module, MacroCallLoc,
     Function, FnSignature, ExprScopes, TypeAlias,
-    Struct, Enum, StructField,
+    Struct, Enum,
     Const, ConstSignature, Static,
     DefWithBody,
     nameres::{Namespace, ImportSourceMap, RawItems, CrateDefMap},
     ty::{InferenceResult, Ty, method_resolution::CrateImplBlocks, TypableDef, CallableDef, FnSig},
-    adt::{StructData, EnumData},
+    adt::{StructData, EnumData, StructFieldId, VariantDef},
     impl_block::{ModuleImplBlocks, ImplSourceMap},
     generics::{GenericParams, GenericDef},
     type_ref::TypeRef,
-    traits::TraitData, Trait, ty::TraitRef,
+    traits::{TraitData, TraitItem}, Trait, ty::TraitRef, ImplItem,
     ids
 };
 
@@ -41,6 +42,9 @@ pub trait DefDatabase: SourceDatabase {
     #[salsa::invoke(crate::ids::macro_def_query)]
     fn macro_def(&self, macro_id: MacroDefId) -> Option<Arc<mbe::MacroRules>>;
 
+    #[salsa::invoke(crate::ids::macro_expansion_size_query)]
+    fn macro_expansion_size(&self, macro_call: ids::MacroCallId) -> usize;
+
     #[salsa::invoke(HirFileId::hir_parse_query)]
     fn hir_parse(&self, file_id: HirFileId) -> TreeArc<SourceFile>;
 
@@ -71,6 +75,9 @@ pub trait DefDatabase: SourceDatabase {
         file_id: HirFileId,
     ) -> (Arc<RawItems>, Arc<ImportSourceMap>);
 
+    #[salsa::invoke(CrateDefMap::recursion_limit_query)]
+    fn recursion_limit(&self, krate: Crate) -> u32;
+
     #[salsa::invoke(CrateDefMap::crate_def_map_query)]
     fn crate_def_map(&self, krate: Crate) -> Arc<CrateDefMap>;
 
@@ -113,8 +120,8 @@ pub trait HirDatabase: DefDatabase {
     #[salsa::invoke(crate::ty::type_for_def)]
     fn type_for_def(&self, def: TypableDef, ns: Namespace) -> Ty;
 
-    #[salsa::invoke(crate::ty::type_for_field)]
-    fn type_for_field(&self, field: StructField) -> Ty;
+    #[salsa::invoke(crate::ty::field_types_query)]
+    fn field_types(&self, variant: VariantDef) -> Arc<ArenaMap<StructFieldId, Ty>>;
 
     #[salsa::invoke(crate::ty::callable_item_sig)]
     fn callable_item_signature(&self, def: CallableDef) -> FnSig;
@@ -133,6 +140,9 @@ pub trait HirDatabase: DefDatabase {
 
     #[salsa::invoke(crate::ty::traits::implements)]
     fn implements(&self, trait_ref: TraitRef) -> Option<crate::ty::traits::Solution>;
+
+    #[salsa::invoke(crate::impl_block::impl_item_trait_item_query)]
+    fn impl_item_trait_item(&self, item: ImplItem) -> Option<TraitItem>;
 }
 
 #[test]
@@ -3,7 +3,7 @@ use std::{fmt, any::Any};
 use ra_syntax::{SyntaxNodePtr, TreeArc, AstPtr, TextRange, ast, SyntaxNode};
 use relative_path::RelativePathBuf;
 
-use crate::{HirFileId, HirDatabase};
+use crate::{HirFileId, HirDatabase, Name, AsName, ObjectSafetyViolation, Trait};
 
 /// Diagnostic defines hir API for errors and warnings.
 ///
@@ -19,6 +19,9 @@ use crate::{HirFileId, HirDatabase};
 /// instance of `Diagnostic` on demand.
 pub trait Diagnostic: Any + Send + Sync + fmt::Debug + 'static {
     fn message(&self) -> String;
+    /// The name of this kind of diagnostic, which can be used in lint
+    /// attributes like `#[allow(unresolved_import)]`.
+    fn code(&self) -> &'static str;
     fn file(&self) -> HirFileId;
     fn syntax_node_ptr(&self) -> SyntaxNodePtr;
     fn highlight_range(&self) -> TextRange {
@@ -81,6 +84,9 @@ impl Diagnostic for NoSuchField {
     fn message(&self) -> String {
         "no such field".to_string()
     }
+    fn code(&self) -> &'static str {
+        "no_such_field"
+    }
     fn file(&self) -> HirFileId {
         self.file
     }
@@ -103,6 +109,9 @@ impl Diagnostic for UnresolvedModule {
     fn message(&self) -> String {
         "unresolved module".to_string()
     }
+    fn code(&self) -> &'static str {
+        "unresolved_module"
+    }
     fn file(&self) -> HirFileId {
         self.file
     }
@@ -113,3 +122,441 @@ impl Diagnostic for UnresolvedModule {
         self
     }
 }
+
+#[derive(Debug)]
+pub struct MissingFields {
+    pub file: HirFileId,
+    pub field_list: AstPtr<ast::NamedFieldList>,
+    pub missed_fields: Vec<Name>,
+}
+
+impl Diagnostic for MissingFields {
+    fn message(&self) -> String {
+        let mut message = String::from("missing structure fields:");
+        for field in &self.missed_fields {
+            message += &format!("\n- {}", field);
+        }
+        message
+    }
+    fn code(&self) -> &'static str {
+        "missing_structure_fields"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.field_list.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnresolvedImport {
+    pub file: HirFileId,
+    pub use_tree: AstPtr<ast::UseTree>,
+}
+
+impl Diagnostic for UnresolvedImport {
+    fn message(&self) -> String {
+        "unresolved import".to_string()
+    }
+    fn code(&self) -> &'static str {
+        "unresolved_import"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.use_tree.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MacroRecursionLimit {
+    pub file: HirFileId,
+    /// The outermost macro call, whose expansion reached the limit.
+    pub call: AstPtr<ast::MacroCall>,
+    pub name: Name,
+}
+
+impl MacroRecursionLimit {
+    pub(crate) fn new(file: HirFileId, call: &ast::MacroCall) -> MacroRecursionLimit {
+        let name = call
+            .path()
+            .and_then(|it| it.segment())
+            .and_then(|it| it.name_ref())
+            .map(|it| it.as_name())
+            .unwrap_or_else(Name::missing);
+        MacroRecursionLimit { file, call: AstPtr::new(call), name }
+    }
+}
+
+impl Diagnostic for MacroRecursionLimit {
+    fn message(&self) -> String {
+        format!("recursion limit reached while expanding `{}!`", self.name)
+    }
+    fn code(&self) -> &'static str {
+        "recursion_limit"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.call.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnresolvedValue {
+    pub file: HirFileId,
+    pub expr: AstPtr<ast::PathExpr>,
+    pub name: Name,
+}
+
+impl Diagnostic for UnresolvedValue {
+    fn message(&self) -> String {
+        format!("cannot find value `{}` in this scope", self.name)
+    }
+    fn code(&self) -> &'static str {
+        "unresolved_value"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.expr.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnresolvedType {
+    pub file: HirFileId,
+    pub path_type: AstPtr<ast::PathType>,
+    pub name: Name,
+}
+
+impl Diagnostic for UnresolvedType {
+    fn message(&self) -> String {
+        format!("cannot find type `{}` in this scope", self.name)
+    }
+    fn code(&self) -> &'static str {
+        "unresolved_type"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.path_type.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnresolvedMacroCall {
+    pub file: HirFileId,
+    pub call: AstPtr<ast::MacroCall>,
+    pub name: Name,
+}
+
+impl Diagnostic for UnresolvedMacroCall {
+    fn message(&self) -> String {
+        format!("cannot find macro `{}!` in this scope", self.name)
+    }
+    fn code(&self) -> &'static str {
+        "unresolved_macro_call"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.call.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MismatchedTypes {
+    pub file: HirFileId,
+    pub expr: AstPtr<ast::Expr>,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Diagnostic for MismatchedTypes {
+    fn message(&self) -> String {
+        format!("mismatched types: expected `{}`, found `{}`", self.expected, self.actual)
+    }
+    fn code(&self) -> &'static str {
+        "mismatched_types"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.expr.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MissingUnsafe {
+    pub file: HirFileId,
+    pub expr: AstPtr<ast::Expr>,
+}
+
+impl Diagnostic for MissingUnsafe {
+    fn message(&self) -> String {
+        "this operation is unsafe and requires an unsafe function or block".to_string()
+    }
+    fn code(&self) -> &'static str {
+        "missing_unsafe"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.expr.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnusedVariable {
+    pub file: HirFileId,
+    pub pat: AstPtr<ast::BindPat>,
+    pub name: Name,
+}
+
+impl Diagnostic for UnusedVariable {
+    fn message(&self) -> String {
+        format!("unused variable: `{}`", self.name)
+    }
+    fn code(&self) -> &'static str {
+        "unused_variables"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.pat.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+/// A statement which discards a value that must be used.
+#[derive(Debug)]
+pub struct UnusedMustUse {
+    pub file: HirFileId,
+    pub expr: AstPtr<ast::Expr>,
+    /// What must be used, like ``return value of `foo` ``.
+    pub description: String,
+    /// The message of the `#[must_use]` attribute, if any.
+    pub note: Option<String>,
+    /// Whether the value is a `Result`.
+    pub is_result: bool,
+    /// Whether the function of the statement returns a `Result`, so the
+    /// error of the value can be propagated with `?`.
+    pub returns_result: bool,
+}
+
+impl Diagnostic for UnusedMustUse {
+    fn message(&self) -> String {
+        match &self.note {
+            Some(note) => format!("unused {} that must be used: {}", self.description, note),
+            None => format!("unused {} that must be used", self.description),
+        }
+    }
+    fn code(&self) -> &'static str {
+        "unused_must_use"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.expr.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnresolvedMethod {
+    pub file: HirFileId,
+    pub name_ref: AstPtr<ast::NameRef>,
+    pub receiver_ty: String,
+    pub method_name: Name,
+}
+
+impl Diagnostic for UnresolvedMethod {
+    fn message(&self) -> String {
+        format!(
+            "no method named `{}` found for type `{}` in the current scope",
+            self.method_name, self.receiver_ty
+        )
+    }
+    fn code(&self) -> &'static str {
+        "unresolved_method"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.name_ref.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+/// A method call which doesn't resolve because the traits providing the
+/// method are not in scope.
+#[derive(Debug)]
+pub struct TraitNotInScope {
+    pub file: HirFileId,
+    pub name_ref: AstPtr<ast::NameRef>,
+    pub receiver_ty: String,
+    pub method_name: Name,
+    pub traits: Vec<(Trait, Name)>,
+}
+
+impl Diagnostic for TraitNotInScope {
+    fn message(&self) -> String {
+        let traits: Vec<String> = self.traits.iter().map(|(_, t)| format!("`{}`", t)).collect();
+        let provided_by = if traits.len() == 1 {
+            format!("the trait {} which provides it is", traits[0])
+        } else {
+            format!("the traits {} which provide it are", traits.join(", "))
+        };
+        format!(
+            "no method named `{}` found for type `{}` in the current scope, {} not in scope",
+            self.method_name, self.receiver_ty, provided_by
+        )
+    }
+    fn code(&self) -> &'static str {
+        "trait_not_in_scope"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.name_ref.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct AmbiguousMethod {
+    pub file: HirFileId,
+    pub name_ref: AstPtr<ast::NameRef>,
+    pub method_name: Name,
+    pub traits: Vec<Name>,
+}
+
+impl Diagnostic for AmbiguousMethod {
+    fn message(&self) -> String {
+        let mut traits: Vec<String> = self.traits.iter().map(|t| format!("`{}`", t)).collect();
+        traits.sort();
+        format!(
+            "multiple applicable methods named `{}` in scope, from the traits {}",
+            self.method_name,
+            traits.join(", ")
+        )
+    }
+    fn code(&self) -> &'static str {
+        "ambiguous_method"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.name_ref.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MissingImplMembers {
+    pub file: HirFileId,
+    pub impl_block: AstPtr<ast::ImplBlock>,
+    /// The range of the header of the impl block, up to its target type.
+    pub header_range: TextRange,
+    pub missed_members: Vec<Name>,
+}
+
+impl Diagnostic for MissingImplMembers {
+    fn message(&self) -> String {
+        let members: Vec<String> = self.missed_members.iter().map(|m| format!("`{}`", m)).collect();
+        format!("not all trait items implemented, missing: {}", members.join(", "))
+    }
+    fn code(&self) -> &'static str {
+        "missing_impl_members"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.impl_block.into()
+    }
+    fn highlight_range(&self) -> TextRange {
+        self.header_range
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct NotObjectSafe {
+    pub file: HirFileId,
+    pub dyn_type: AstPtr<ast::DynTraitType>,
+    pub trait_name: Name,
+    pub violations: Vec<ObjectSafetyViolation>,
+}
+
+impl Diagnostic for NotObjectSafe {
+    fn message(&self) -> String {
+        let mut res = format!("the trait `{}` cannot be made into an object", self.trait_name);
+        for violation in &self.violations {
+            res.push_str(&format!("\n- {}", violation));
+        }
+        res
+    }
+    fn code(&self) -> &'static str {
+        "not_object_safe"
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.dyn_type.into()
+    }
+    fn as_any(&self) -> &(Any + Send + 'static) {
+        self
+    }
+}
@@ -5,20 +5,34 @@ use rustc_hash::FxHashMap;
 
 use ra_arena::{Arena, RawId, impl_arena_id, map::ArenaMap};
 use ra_syntax::{
-    SyntaxNodePtr, AstPtr, AstNode,
+    SyntaxNodePtr, AstPtr, AstNode, TreeArc, TextRange,
     ast::{self, LoopBodyOwner, ArgListOwner, NameOwner, LiteralKind,ArrayExprKind, TypeAscriptionOwner}
 };
 
 use crate::{
-    Path, Name, HirDatabase, Resolver,DefWithBody, Either,
+    Path, Name, HirDatabase, Resolver,DefWithBody, Either, ModuleDef, Module, HirFileId, Const,
+    Static,
+    ids::{LocationCtx, MacroDefId},
+    diagnostics::{DiagnosticSink, MacroRecursionLimit},
     name::AsName,
     type_ref::{Mutability, TypeRef},
 };
 use crate::{path::GenericArgs, ty::primitive::{IntTy, UncertainIntTy, FloatTy, UncertainFloatTy}};
 
-pub use self::scope::ExprScopes;
+pub use self::{
+    scope::ExprScopes,
+    unsafety::UnsafeOperation,
+    captures::{ClosureCapture, CaptureKind},
+};
+
+use self::hygiene::{Expansion, SyntaxContext, TokenMapping};
 
+pub(crate) mod captures;
+pub(crate) mod hygiene;
+pub(crate) mod must_use;
 pub(crate) mod scope;
+pub(crate) mod unsafety;
+pub(crate) mod usages;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExprId(RawId);
@@ -40,6 +54,12 @@ pub struct Body {
     params: Vec<PatId>,
     /// The `ExprId` of the actual body expression.
     body_expr: ExprId,
+    /// The constants and statics declared inside of blocks, by block.
+    block_items: FxHashMap<ExprId, Vec<(Name, ModuleDef)>>,
+    /// The syntax contexts of the path expressions and bindings which come
+    /// from macro expansions. Everything else is in the root context.
+    expr_contexts: FxHashMap<ExprId, SyntaxContext>,
+    pat_contexts: FxHashMap<PatId, SyntaxContext>,
 }
 
 /// An item body together with the mapping from syntax nodes to HIR expression
@@ -54,6 +74,11 @@ pub struct BodySourceMap {
     pat_map: FxHashMap<PatPtr, PatId>,
     pat_map_back: ArenaMap<PatId, PatPtr>,
     field_map: FxHashMap<(ExprId, usize), AstPtr<ast::NamedField>>,
+    /// The expressions of macro expansions which are written in the arguments
+    /// of the macro calls, by their range in the file.
+    macro_arg_map: FxHashMap<TextRange, ExprId>,
+    /// The outermost macro calls whose expansion reached the recursion limit.
+    recursion_limit_calls: Vec<AstPtr<ast::MacroCall>>,
 }
 
 type PatPtr = Either<AstPtr<ast::Pat>, AstPtr<ast::SelfParam>>;
@@ -78,6 +103,18 @@ impl Body {
     pub fn pats(&self) -> impl Iterator<Item = (PatId, &Pat)> {
         self.pats.iter()
     }
+
+    pub(crate) fn block_items(&self, block: ExprId) -> &[(Name, ModuleDef)] {
+        self.block_items.get(&block).map_or(&[], |items| items.as_slice())
+    }
+
+    pub(crate) fn expr_syntax_context(&self, expr: ExprId) -> SyntaxContext {
+        self.expr_contexts.get(&expr).cloned().unwrap_or(SyntaxContext::ROOT)
+    }
+
+    pub(crate) fn pat_syntax_context(&self, pat: PatId) -> SyntaxContext {
+        self.pat_contexts.get(&pat).cloned().unwrap_or(SyntaxContext::ROOT)
+    }
 }
 
 // needs arbitrary_self_types to be a method... or maybe move to the def?
@@ -87,19 +124,30 @@ pub(crate) fn resolver_for_expr(
     expr_id: ExprId,
 ) -> Resolver {
     let scopes = db.expr_scopes(body.owner);
-    resolver_for_scope(body, db, scopes.scope_for(expr_id))
+    let ctx = body.expr_syntax_context(expr_id);
+    resolver_for_scope_in_context(body, db, scopes.scope_for(expr_id), ctx)
 }
 
 pub(crate) fn resolver_for_scope(
     body: Arc<Body>,
     db: &impl HirDatabase,
     scope_id: Option<scope::ScopeId>,
+) -> Resolver {
+    resolver_for_scope_in_context(body, db, scope_id, SyntaxContext::ROOT)
+}
+
+/// The resolver for the names of syntax context `ctx` in the scope `scope_id`.
+fn resolver_for_scope_in_context(
+    body: Arc<Body>,
+    db: &impl HirDatabase,
+    scope_id: Option<scope::ScopeId>,
+    ctx: SyntaxContext,
 ) -> Resolver {
     let mut r = body.owner.resolver(db);
     let scopes = db.expr_scopes(body.owner);
     let scope_chain = scopes.scope_chain(scope_id).collect::<Vec<_>>();
     for scope in scope_chain.into_iter().rev() {
-        r = r.push_expr_scope(Arc::clone(&scopes), scope);
+        r = r.push_expr_scope(Arc::clone(&scopes), scope, ctx);
     }
     r
 }
@@ -121,7 +169,7 @@ impl Index<PatId> for Body {
 }
 
 impl BodySourceMap {
-    pub(crate) fn expr_syntax(&self, expr: ExprId) -> Option<SyntaxNodePtr> {
+    pub fn expr_syntax(&self, expr: ExprId) -> Option<SyntaxNodePtr> {
         self.expr_map_back.get(expr).cloned()
     }
 
@@ -133,16 +181,40 @@ impl BodySourceMap {
         self.expr_map.get(&SyntaxNodePtr::new(node.syntax())).cloned()
     }
 
-    pub(crate) fn pat_syntax(&self, pat: PatId) -> Option<PatPtr> {
+    pub fn pat_syntax(&self, pat: PatId) -> Option<PatPtr> {
         self.pat_map_back.get(pat).cloned()
     }
 
+    pub(crate) fn syntax_pat(&self, ptr: PatPtr) -> Option<PatId> {
+        self.pat_map.get(&ptr).cloned()
+    }
+
     pub(crate) fn node_pat(&self, node: &ast::Pat) -> Option<PatId> {
         self.pat_map.get(&Either::A(AstPtr::new(node))).cloned()
     }
 
-    pub(crate) fn field_syntax(&self, expr: ExprId, field: usize) -> AstPtr<ast::NamedField> {
-        self.field_map[&(expr, field)].clone()
+    pub(crate) fn field_syntax(
+        &self,
+        expr: ExprId,
+        field: usize,
+    ) -> Option<AstPtr<ast::NamedField>> {
+        self.field_map.get(&(expr, field)).cloned()
+    }
+
+    pub(crate) fn macro_arg_expr(&self, range: TextRange) -> Option<ExprId> {
+        self.macro_arg_map.get(&range).cloned()
+    }
+
+    pub(crate) fn add_diagnostics(
+        &self,
+        db: &impl HirDatabase,
+        file: HirFileId,
+        sink: &mut DiagnosticSink,
+    ) {
+        let source_file = db.hir_parse(file);
+        for call in &self.recursion_limit_calls {
+            sink.push(MacroRecursionLimit::new(file, call.to_node(&source_file)));
+        }
     }
 }
 
@@ -170,6 +242,12 @@ pub enum Expr {
         statements: Vec<Statement>,
         tail: Option<ExprId>,
     },
+    UnsafeBlock {
+        body: ExprId,
+    },
+    AsyncBlock {
+        body: ExprId,
+    },
     Loop {
         body: ExprId,
     },
@@ -215,6 +293,9 @@ pub enum Expr {
     Try {
         expr: ExprId,
     },
+    Await {
+        expr: ExprId,
+    },
     Cast {
         expr: ExprId,
         type_ref: TypeRef,
@@ -232,10 +313,20 @@ pub enum Expr {
         rhs: ExprId,
         op: Option<BinaryOp>,
     },
+    Index {
+        base: ExprId,
+        index: ExprId,
+    },
+    Range {
+        start: Option<ExprId>,
+        end: Option<ExprId>,
+        inclusive: bool,
+    },
     Lambda {
         args: Vec<PatId>,
         arg_types: Vec<Option<TypeRef>>,
         body: ExprId,
+        is_move: bool,
     },
     Tuple {
         exprs: Vec<ExprId>,
@@ -298,7 +389,9 @@ impl Expr {
                     f(*expr);
                 }
             }
-            Expr::Loop { body } => f(*body),
+            Expr::UnsafeBlock { body } | Expr::AsyncBlock { body } | Expr::Loop { body } => {
+                f(*body)
+            }
             Expr::While { condition, body } => {
                 f(*condition);
                 f(*body);
@@ -346,8 +439,18 @@ impl Expr {
                 f(*lhs);
                 f(*rhs);
             }
+            Expr::Index { base, index } => {
+                f(*base);
+                f(*index);
+            }
+            Expr::Range { start, end, .. } => {
+                for expr in start.iter().chain(end.iter()) {
+                    f(*expr);
+                }
+            }
             Expr::Field { expr, .. }
             | Expr::Try { expr }
+            | Expr::Await { expr }
             | Expr::Cast { expr, .. }
             | Expr::Ref { expr, .. }
             | Expr::UnaryOp { expr, .. } => {
@@ -478,47 +581,149 @@ impl Pat {
 
 // Queries
 
-pub(crate) struct ExprCollector {
+pub(crate) struct ExprCollector<DB> {
+    db: DB,
+    ctx: LocationCtx<DB>,
     owner: DefWithBody,
+    module: Module,
     exprs: Arena<ExprId, Expr>,
     pats: Arena<PatId, Pat>,
     source_map: BodySourceMap,
     params: Vec<PatId>,
     body_expr: Option<ExprId>,
+    block_items: FxHashMap<ExprId, Vec<(Name, ModuleDef)>>,
+    /// The macro expansions being lowered, innermost last. The syntax of
+    /// expansions is not part of the file, so it is not in the source map.
+    expansions: Vec<Expansion>,
+    expansion_count: u32,
+    /// The outermost macro call being expanded, and the number of tokens its
+    /// expansions produced so far.
+    outermost_call: Option<AstPtr<ast::MacroCall>>,
+    expanded_tokens: usize,
+    expr_contexts: FxHashMap<ExprId, SyntaxContext>,
+    pat_contexts: FxHashMap<PatId, SyntaxContext>,
 }
 
-impl ExprCollector {
-    fn new(owner: DefWithBody) -> Self {
+impl<'a, DB: HirDatabase> ExprCollector<&'a DB> {
+    fn new(db: &'a DB, owner: DefWithBody, module: Module, file_id: HirFileId) -> Self {
         ExprCollector {
+            db,
+            ctx: LocationCtx::new(db, module, file_id),
             owner,
+            module,
             exprs: Arena::default(),
             pats: Arena::default(),
             source_map: BodySourceMap::default(),
             params: Vec::new(),
             body_expr: None,
+            block_items: FxHashMap::default(),
+            expansions: Vec::new(),
+            expansion_count: 0,
+            outermost_call: None,
+            expanded_tokens: 0,
+            expr_contexts: FxHashMap::default(),
+            pat_contexts: FxHashMap::default(),
         }
     }
 
+    fn in_expansion(&self) -> bool {
+        !self.expansions.is_empty()
+    }
+
     fn alloc_expr(&mut self, expr: Expr, syntax_ptr: SyntaxNodePtr) -> ExprId {
         let id = self.exprs.alloc(expr);
-        self.source_map.expr_map.insert(syntax_ptr, id);
-        self.source_map.expr_map_back.insert(id, syntax_ptr);
+        if !self.in_expansion() {
+            self.source_map.expr_map.insert(syntax_ptr, id);
+            self.source_map.expr_map_back.insert(id, syntax_ptr);
+        }
         id
     }
 
     fn alloc_pat(&mut self, pat: Pat, ptr: PatPtr) -> PatId {
         let id = self.pats.alloc(pat);
-        self.source_map.pat_map.insert(ptr, id);
-        self.source_map.pat_map_back.insert(id, ptr);
+        if !self.in_expansion() {
+            self.source_map.pat_map.insert(ptr, id);
+            self.source_map.pat_map_back.insert(id, ptr);
+        }
         id
     }
 
+    /// Records the syntax context of the identifier `name` of the expression
+    /// `expr`, if it comes from a macro expansion.
+    fn mark_expr(&mut self, expr: ExprId, name: &ast::NameRef) {
+        if self.in_expansion() {
+            let ctx = hygiene::syntax_context(&self.expansions, name.syntax().range());
+            self.expr_contexts.insert(expr, ctx);
+        }
+    }
+
+    fn mark_pat(&mut self, pat: PatId, name: &ast::Name) {
+        if self.in_expansion() {
+            let ctx = hygiene::syntax_context(&self.expansions, name.syntax().range());
+            self.pat_contexts.insert(pat, ctx);
+        }
+    }
+
+    /// Expands a macro call in expression position, returning the expanded
+    /// expression and the expansion to lower it in.
+    fn expand_macro_call(
+        &mut self,
+        call: &ast::MacroCall,
+    ) -> Option<(TreeArc<ast::Expr>, Expansion)> {
+        if !self.in_expansion() {
+            self.outermost_call = Some(AstPtr::new(call));
+            self.expanded_tokens = 0;
+        }
+        let path = call.path().and_then(Path::from_ast)?;
+        let krate = self.module.krate(self.db)?;
+        let def_map = self.db.crate_def_map(krate);
+        let macro_def = def_map.resolve_macro(self.db, &path)?;
+        if def_map.expansion_limit_reached(self.expansions.len(), self.expanded_tokens) {
+            let outermost_call = self.outermost_call.expect("set for the outermost call");
+            if !self.source_map.recursion_limit_calls.contains(&outermost_call) {
+                self.source_map.recursion_limit_calls.push(outermost_call);
+            }
+            return None;
+        }
+        let (expr, mapping) = expand_expr_macro(self.db, macro_def, call)?;
+        self.expanded_tokens +=
+            expr.syntax().descendants_with_tokens().filter(|it| it.as_token().is_some()).count();
+        self.expansion_count += 1;
+        let expansion = Expansion { ctx: SyntaxContext(self.expansion_count), mapping };
+        Some((expr, expansion))
+    }
+
+    /// Records `expr` of a macro expansion by its range in the file, if it is
+    /// written in the arguments of the macro calls.
+    fn map_macro_arg(&mut self, expr: &ast::Expr, id: ExprId) {
+        let (first, last) = match (expr.syntax().first_token(), expr.syntax().last_token()) {
+            (Some(first), Some(last)) => (first.range(), last.range()),
+            _ => return,
+        };
+        let first = hygiene::original_range(&self.expansions, first);
+        let last = hygiene::original_range(&self.expansions, last);
+        if let (Some(first), Some(last)) = (first, last) {
+            if first.start() <= last.end() {
+                let range = TextRange::from_to(first.start(), last.end());
+                self.source_map.macro_arg_map.entry(range).or_insert(id);
+            }
+        }
+    }
+
     fn empty_block(&mut self) -> ExprId {
         let block = Expr::Block { statements: Vec::new(), tail: None };
         self.exprs.alloc(block)
     }
 
     fn collect_expr(&mut self, expr: &ast::Expr) -> ExprId {
+        let id = self.lower_expr(expr);
+        if self.in_expansion() {
+            self.map_macro_arg(expr, id);
+        }
+        id
+    }
+
+    fn lower_expr(&mut self, expr: &ast::Expr) -> ExprId {
         let syntax_ptr = SyntaxNodePtr::new(expr.syntax());
         match expr.kind() {
             ast::ExprKind::IfExpr(e) => {
@@ -557,22 +762,39 @@ impl ExprCollector {
                     self.alloc_expr(Expr::If { condition, then_branch, else_branch }, syntax_ptr)
                 }
             }
-            ast::ExprKind::BlockExpr(e) => self.collect_block_opt(e.block()),
+            ast::ExprKind::BlockExpr(e) => {
+                let body = self.collect_block_opt(e.block());
+                if e.is_unsafe() {
+                    self.alloc_expr(Expr::UnsafeBlock { body }, syntax_ptr)
+                } else if e.is_async() {
+                    self.alloc_expr(Expr::AsyncBlock { body }, syntax_ptr)
+                } else {
+                    body
+                }
+            }
             ast::ExprKind::LoopExpr(e) => {
                 let body = self.collect_block_opt(e.loop_body());
                 self.alloc_expr(Expr::Loop { body }, syntax_ptr)
             }
             ast::ExprKind::WhileExpr(e) => {
-                let condition = if let Some(condition) = e.condition() {
-                    if condition.pat().is_none() {
-                        self.collect_expr_opt(condition.expr())
-                    } else {
-                        // FIXME handle while let
-                        return self.alloc_expr(Expr::Missing, syntax_ptr);
-                    }
-                } else {
-                    self.exprs.alloc(Expr::Missing)
-                };
+                let condition = e.condition();
+                if let Some(pat) = condition.and_then(|c| c.pat()) {
+                    // while let -- desugar to a loop over a match, which breaks
+                    // when the pattern doesn't match
+                    let pat = self.collect_pat(pat);
+                    let match_expr = self.collect_expr_opt(condition.and_then(|c| c.expr()));
+                    let body = self.collect_block_opt(e.loop_body());
+                    let placeholder_pat = self.pats.alloc(Pat::Missing);
+                    let break_expr = self.exprs.alloc(Expr::Break { expr: None });
+                    let arms = vec![
+                        MatchArm { pats: vec![pat], expr: body, guard: None },
+                        MatchArm { pats: vec![placeholder_pat], expr: break_expr, guard: None },
+                    ];
+                    let body = self.exprs.alloc(Expr::Match { expr: match_expr, arms });
+                    let condition = self.exprs.alloc(Expr::Missing);
+                    return self.alloc_expr(Expr::While { condition, body }, syntax_ptr);
+                }
+                let condition = self.collect_expr_opt(condition.and_then(|c| c.expr()));
                 let body = self.collect_block_opt(e.loop_body());
                 self.alloc_expr(Expr::While { condition, body }, syntax_ptr)
             }
@@ -627,7 +849,12 @@ impl ExprCollector {
             ast::ExprKind::PathExpr(e) => {
                 let path =
                     e.path().and_then(Path::from_ast).map(Expr::Path).unwrap_or(Expr::Missing);
-                self.alloc_expr(path, syntax_ptr)
+                let id = self.alloc_expr(path, syntax_ptr);
+                // The first name of the path is the one resolved in the body.
+                if let Some(name_ref) = e.syntax().descendants().find_map(ast::NameRef::cast) {
+                    self.mark_expr(id, name_ref);
+                }
+                id
             }
             ast::ExprKind::ContinueExpr(_e) => {
                 // FIXME: labels
@@ -640,7 +867,9 @@ impl ExprCollector {
             ast::ExprKind::ParenExpr(e) => {
                 let inner = self.collect_expr_opt(e.expr());
                 // make the paren expr point to the inner expression as well
-                self.source_map.expr_map.insert(syntax_ptr, inner);
+                if !self.in_expansion() {
+                    self.source_map.expr_map.insert(syntax_ptr, inner);
+                }
                 inner
             }
             ast::ExprKind::ReturnExpr(e) => {
@@ -662,13 +891,11 @@ impl ExprCollector {
                                 self.collect_expr(e)
                             } else if let Some(nr) = field.name_ref() {
                                 // field shorthand
-                                let id = self.exprs.alloc(Expr::Path(Path::from_name_ref(nr)));
-                                self.source_map
-                                    .expr_map
-                                    .insert(SyntaxNodePtr::new(nr.syntax()), id);
-                                self.source_map
-                                    .expr_map_back
-                                    .insert(id, SyntaxNodePtr::new(nr.syntax()));
+                                let id = self.alloc_expr(
+                                    Expr::Path(Path::from_name_ref(nr)),
+                                    SyntaxNodePtr::new(nr.syntax()),
+                                );
+                                self.mark_expr(id, nr);
                                 id
                             } else {
                                 self.exprs.alloc(Expr::Missing)
@@ -678,15 +905,21 @@ impl ExprCollector {
                 } else {
                     Vec::new()
                 };
-                let spread = e.spread().map(|s| self.collect_expr(s));
+                let spread =
+                    e.named_field_list().and_then(|it| it.spread()).map(|s| self.collect_expr(s));
                 let res = self.alloc_expr(Expr::StructLit { path, fields, spread }, syntax_ptr);
-                for (i, ptr) in field_ptrs.into_iter().enumerate() {
-                    self.source_map.field_map.insert((res, i), ptr);
+                if !self.in_expansion() {
+                    for (i, ptr) in field_ptrs.into_iter().enumerate() {
+                        self.source_map.field_map.insert((res, i), ptr);
+                    }
                 }
                 res
             }
             ast::ExprKind::FieldExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
+                if e.name_ref().map_or(false, |it| it.text() == "await") {
+                    return self.alloc_expr(Expr::Await { expr }, syntax_ptr);
+                }
                 let name = match e.field_access() {
                     Some(kind) => kind.as_name(),
                     _ => Name::missing(),
@@ -727,7 +960,8 @@ impl ExprCollector {
                     }
                 }
                 let body = self.collect_expr_opt(e.body());
-                self.alloc_expr(Expr::Lambda { args, arg_types, body }, syntax_ptr)
+                let is_move = e.is_move();
+                self.alloc_expr(Expr::Lambda { args, arg_types, body, is_move }, syntax_ptr)
             }
             ast::ExprKind::BinExpr(e) => {
                 let lhs = self.collect_expr_opt(e.lhs());
@@ -792,9 +1026,30 @@ impl ExprCollector {
 
             // FIXME implement HIR for these:
             ast::ExprKind::Label(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::ExprKind::IndexExpr(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::ExprKind::RangeExpr(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::ExprKind::MacroCall(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
+            ast::ExprKind::IndexExpr(e) => {
+                let base = self.collect_expr_opt(e.base());
+                let index = self.collect_expr_opt(e.index());
+                self.alloc_expr(Expr::Index { base, index }, syntax_ptr)
+            }
+            ast::ExprKind::RangeExpr(e) => {
+                let start = e.start().map(|it| self.collect_expr(it));
+                let end = e.end().map(|it| self.collect_expr(it));
+                let inclusive = e.is_inclusive();
+                self.alloc_expr(Expr::Range { start, end, inclusive }, syntax_ptr)
+            }
+            ast::ExprKind::MacroCall(e) => match self.expand_macro_call(e) {
+                Some((expr, expansion)) => {
+                    self.expansions.push(expansion);
+                    let id = self.collect_expr(&expr);
+                    self.expansions.pop();
+                    // make the macro call point to its expansion
+                    if !self.in_expansion() {
+                        self.source_map.expr_map.insert(syntax_ptr, id);
+                    }
+                    id
+                }
+                None => self.alloc_expr(Expr::Missing, syntax_ptr),
+            },
         }
     }
 
@@ -822,7 +1077,35 @@ impl ExprCollector {
             })
             .collect();
         let tail = block.expr().map(|e| self.collect_expr(e));
-        self.alloc_expr(Expr::Block { statements, tail }, SyntaxNodePtr::new(block.syntax()))
+        let items = self.collect_block_items(block);
+        let expr =
+            self.alloc_expr(Expr::Block { statements, tail }, SyntaxNodePtr::new(block.syntax()));
+        if !items.is_empty() {
+            self.block_items.insert(expr, items);
+        }
+        expr
+    }
+
+    fn collect_block_items(&mut self, block: &ast::Block) -> Vec<(Name, ModuleDef)> {
+        // FIXME: items defined by macro expansions have no location to get an
+        // id from
+        if self.in_expansion() {
+            return Vec::new();
+        }
+        block
+            .items()
+            .filter_map(|item| match item.kind() {
+                ast::ModuleItemKind::ConstDef(it) => {
+                    let def = Const { id: self.ctx.to_def(it) };
+                    Some((it.name()?.as_name(), def.into()))
+                }
+                ast::ModuleItemKind::StaticDef(it) => {
+                    let def = Static { id: self.ctx.to_def(it) };
+                    Some((it.name()?.as_name(), def.into()))
+                }
+                _ => None,
+            })
+            .collect()
     }
 
     fn collect_block_opt(&mut self, block: Option<&ast::Block>) -> ExprId {
@@ -889,7 +1172,11 @@ impl ExprCollector {
             ast::PatKind::SlicePat(_) | ast::PatKind::RangePat(_) => Pat::Missing,
         };
         let ptr = AstPtr::new(pat);
-        self.alloc_pat(pattern, Either::A(ptr))
+        let id = self.alloc_pat(pattern, Either::A(ptr));
+        if let Some(name) = ast::BindPat::cast(pat.syntax()).and_then(|it| it.name()) {
+            self.mark_pat(id, name);
+        }
+        id
     }
 
     fn collect_pat_opt(&mut self, pat: Option<&ast::Pat>) -> PatId {
@@ -947,24 +1234,53 @@ impl ExprCollector {
             pats: self.pats,
             params: self.params,
             body_expr: self.body_expr.expect("A body should have been collected"),
+            block_items: self.block_items,
+            expr_contexts: self.expr_contexts,
+            pat_contexts: self.pat_contexts,
         };
         (body, self.source_map)
     }
 }
 
+/// Expands the call of `macro_def` in expression position. The call doesn't
+/// need to be part of a body.
+pub(crate) fn expand_expr_macro(
+    db: &impl HirDatabase,
+    macro_def: MacroDefId,
+    call: &ast::MacroCall,
+) -> Option<(TreeArc<ast::Expr>, TokenMapping)> {
+    let rules = db.macro_def(macro_def)?;
+    let token_tree = call.token_tree()?;
+    let (arg, token_map) = mbe::ast_to_token_tree(token_tree)?;
+    let expanded = rules.expand(&arg).ok()?;
+    let (expr, rev_token_map) = mbe::token_tree_to_expr(&expanded).ok()?;
+    Some((expr, TokenMapping::new(token_tree, token_map, rev_token_map)))
+}
+
 pub(crate) fn body_with_source_map_query(
     db: &impl HirDatabase,
     def: DefWithBody,
 ) -> (Arc<Body>, Arc<BodySourceMap>) {
-    let mut collector = ExprCollector::new(def);
-
-    match def {
-        DefWithBody::Const(ref c) => collector.collect_const_body(&c.source(db).1),
-        DefWithBody::Function(ref f) => collector.collect_fn_body(&f.source(db).1),
-        DefWithBody::Static(ref s) => collector.collect_static_body(&s.source(db).1),
-    }
-
-    let (body, source_map) = collector.finish();
+    let (body, source_map) = match def {
+        DefWithBody::Const(ref c) => {
+            let (file_id, node) = c.source(db);
+            let mut collector = ExprCollector::new(db, def, c.module(db), file_id);
+            collector.collect_const_body(&node);
+            collector.finish()
+        }
+        DefWithBody::Function(ref f) => {
+            let (file_id, node) = f.source(db);
+            let mut collector = ExprCollector::new(db, def, f.module(db), file_id);
+            collector.collect_fn_body(&node);
+            collector.finish()
+        }
+        DefWithBody::Static(ref s) => {
+            let (file_id, node) = s.source(db);
+            let mut collector = ExprCollector::new(db, def, s.module(db), file_id);
+            collector.collect_static_body(&node);
+            collector.finish()
+        }
+    };
     (Arc::new(body), Arc::new(source_map))
 }
 
@@ -0,0 +1,180 @@
+//! Finds the local bindings a closure captures from its environment, and
+//! whether it borrows them, mutably or not, or moves them. This approximates
+//! the upvar analysis of rustc, capturing whole variables.
+use std::sync::Arc;
+
+use rustc_hash::FxHashMap;
+use ra_syntax::{AstPtr, TextUnit, ast};
+
+use crate::{
+    HirDatabase, Crate, DefWithBody, Either, Name, Ty,
+    expr::{Body, Expr, ExprId, Pat, PatId, BinaryOp, UnaryOp, resolver_for_expr},
+    path::PathKind,
+    resolve::Resolution,
+    type_ref::{Mutability, TypeRef},
+    ty::InferenceResult,
+};
+
+/// How a closure captures a binding. The variants are ordered from the least
+/// to the most restrictive, and a binding is captured by the most restrictive
+/// mode any of its uses in the closure needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CaptureKind {
+    ByRef,
+    ByMutRef,
+    ByValue,
+}
+
+/// A local binding captured by a closure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosureCapture {
+    pub name: Name,
+    pub binding: Either<AstPtr<ast::Pat>, AstPtr<ast::SelfParam>>,
+    pub kind: CaptureKind,
+    pub ty: Ty,
+}
+
+/// The captures of the closure `closure` in the body of `def`, in the order
+/// of their first uses.
+pub(crate) fn closure_captures(
+    db: &impl HirDatabase,
+    def: DefWithBody,
+    closure: ExprId,
+) -> Vec<ClosureCapture> {
+    let body = def.body(db);
+    let source_map = def.body_source_map(db);
+    let infer = def.infer(db);
+    let (closure_body, is_move) = match &body[closure] {
+        Expr::Lambda { body, is_move, .. } => (*body, *is_move),
+        _ => return Vec::new(),
+    };
+    let closure_range = match source_map.expr_syntax(closure) {
+        Some(it) => it.range(),
+        None => return Vec::new(),
+    };
+    let mut parents = FxHashMap::default();
+    for (expr_id, expr) in body.exprs() {
+        expr.walk_child_exprs(|child| {
+            parents.insert(child, expr_id);
+        });
+    }
+    let krate = def.resolver(db).krate();
+    let cx = UseContext { db, krate, body: &body, infer: &infer, parents: &parents };
+
+    // the captured bindings, with their capture modes and first uses
+    let mut captured: Vec<(PatId, CaptureKind, TextUnit)> = Vec::new();
+    let mut stack = vec![closure_body];
+    while let Some(expr_id) = stack.pop() {
+        body[expr_id].walk_child_exprs(|child| stack.push(child));
+        let path = match &body[expr_id] {
+            Expr::Path(path) if path.kind == PathKind::Plain && path.segments.len() == 1 => path,
+            _ => continue,
+        };
+        let resolver = resolver_for_expr(Arc::clone(&body), db, expr_id);
+        let pat = match resolver.resolve_path(db, path).take_values() {
+            Some(Resolution::LocalBinding(pat)) => pat,
+            _ => continue,
+        };
+        let is_outside = source_map
+            .pat_syntax(pat)
+            .map(|it| it.either(|it| it.syntax_node_ptr(), |it| it.syntax_node_ptr()).range())
+            .map_or(false, |range| !range.is_subrange(&closure_range));
+        if !is_outside {
+            continue;
+        }
+        let kind = if is_move { CaptureKind::ByValue } else { cx.capture_kind(expr_id) };
+        let offset = match source_map.expr_syntax(expr_id) {
+            Some(it) => it.range().start(),
+            None => continue,
+        };
+        match captured.iter_mut().find(|(it, _, _)| *it == pat) {
+            Some((_, prev_kind, first_use)) => {
+                *prev_kind = (*prev_kind).max(kind);
+                *first_use = (*first_use).min(offset);
+            }
+            None => captured.push((pat, kind, offset)),
+        }
+    }
+    captured.sort_by_key(|(_, _, first_use)| *first_use);
+
+    captured
+        .into_iter()
+        .filter_map(|(pat, kind, _)| {
+            let name = match &body[pat] {
+                Pat::Bind { name, .. } => name.clone(),
+                _ => return None,
+            };
+            Some(ClosureCapture {
+                name,
+                binding: source_map.pat_syntax(pat)?,
+                kind,
+                ty: infer[pat].clone(),
+            })
+        })
+        .collect()
+}
+
+struct UseContext<'a, DB> {
+    db: &'a DB,
+    krate: Option<Crate>,
+    body: &'a Body,
+    infer: &'a InferenceResult,
+    parents: &'a FxHashMap<ExprId, ExprId>,
+}
+
+impl<'a, DB: HirDatabase> UseContext<'a, DB> {
+    /// The capture mode needed by the use `expr` of a binding, looking through
+    /// the field accesses, indexing and derefs of the place it's part of.
+    fn capture_kind(&self, expr: ExprId) -> CaptureKind {
+        let mut place = expr;
+        while let Some(&parent) = self.parents.get(&place) {
+            match &self.body[parent] {
+                Expr::Field { .. } | Expr::UnaryOp { op: UnaryOp::Deref, .. } => place = parent,
+                Expr::Index { base, .. } if *base == place => place = parent,
+                Expr::Ref { mutability: Mutability::Mut, .. } => return CaptureKind::ByMutRef,
+                Expr::Ref { .. } => return CaptureKind::ByRef,
+                Expr::BinaryOp { lhs, op: Some(op), .. } if *lhs == place && is_assignment(*op) => {
+                    return CaptureKind::ByMutRef;
+                }
+                Expr::MethodCall { receiver, .. } if *receiver == place => {
+                    let sig = self.infer.method_resolution(parent).map(|it| it.signature(self.db));
+                    return match sig.as_ref().and_then(|it| it.params().first()) {
+                        Some(TypeRef::Reference(_, Mutability::Mut)) => CaptureKind::ByMutRef,
+                        Some(TypeRef::Reference(_, Mutability::Shared)) => CaptureKind::ByRef,
+                        _ => self.value_use(place),
+                    };
+                }
+                Expr::Call { callee, .. } if *callee == place => return CaptureKind::ByRef,
+                Expr::Match { expr, .. } if *expr == place => return CaptureKind::ByRef,
+                _ => return self.value_use(place),
+            }
+        }
+        self.value_use(place)
+    }
+
+    /// Using a place by value moves it, unless its type is `Copy`.
+    fn value_use(&self, place: ExprId) -> CaptureKind {
+        if self.infer[place].is_copy(self.db, self.krate) {
+            CaptureKind::ByRef
+        } else {
+            CaptureKind::ByValue
+        }
+    }
+}
+
+fn is_assignment(op: BinaryOp) -> bool {
+    match op {
+        BinaryOp::Assignment
+        | BinaryOp::AddAssign
+        | BinaryOp::DivAssign
+        | BinaryOp::MulAssign
+        | BinaryOp::RemAssign
+        | BinaryOp::ShrAssign
+        | BinaryOp::ShlAssign
+        | BinaryOp::SubAssign
+        | BinaryOp::BitOrAssign
+        | BinaryOp::BitAndAssign
+        | BinaryOp::BitXorAssign => true,
+        _ => false,
+    }
+}
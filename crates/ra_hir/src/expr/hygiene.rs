@@ -0,0 +1,82 @@
+//! Hygiene of the local bindings of bodies.
+//!
+//! `macro_rules` are hygienic for local variables: a binding introduced by a
+//! macro definition is invisible to the code of the call site, and a name
+//! written in a macro definition never refers to a local of the call site.
+//!
+//! Each macro expansion in a body gets its own `SyntaxContext`. Identifiers of
+//! the expansion which come from the macro definition are marked with it, and
+//! those which come from the macro arguments with the context of the call site.
+//! Names then only resolve to the local bindings of the same context.
+use ra_syntax::{AstNode, TextRange, TextUnit, ast};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct SyntaxContext(pub(super) u32);
+
+impl SyntaxContext {
+    /// The context of the code written directly in the body.
+    pub(crate) const ROOT: SyntaxContext = SyntaxContext(0);
+}
+
+/// Maps the tokens of a macro expansion to the tokens of the macro call they
+/// come from, and back.
+pub(crate) struct TokenMapping {
+    /// The start of the token tree of the macro call, in the tree of the call.
+    call_site_offset: TextUnit,
+    token_map: mbe::TokenMap,
+    rev_token_map: mbe::RevTokenMap,
+}
+
+impl TokenMapping {
+    pub(crate) fn new(
+        token_tree: &ast::TokenTree,
+        token_map: mbe::TokenMap,
+        rev_token_map: mbe::RevTokenMap,
+    ) -> TokenMapping {
+        TokenMapping {
+            call_site_offset: token_tree.syntax().range().start(),
+            token_map,
+            rev_token_map,
+        }
+    }
+
+    /// The range in the tree of the call of the token at `range` in the
+    /// expansion, if it comes from the arguments of the call.
+    pub(crate) fn map_up(&self, range: TextRange) -> Option<TextRange> {
+        let id = self.rev_token_map.token_id_of(range)?;
+        Some(self.token_map.relative_range_of(id)? + self.call_site_offset)
+    }
+
+    /// The range in the expansion of the first use of the token at `range` in
+    /// the arguments of the call.
+    pub(crate) fn map_down(&self, range: TextRange) -> Option<TextRange> {
+        let id = self.token_map.token_id_of(range.checked_sub(self.call_site_offset)?)?;
+        self.rev_token_map.ranges_of(id).next()
+    }
+}
+
+/// A macro expansion which is being lowered, with what is needed to find out
+/// where its tokens come from.
+pub(super) struct Expansion {
+    pub(super) ctx: SyntaxContext,
+    pub(super) mapping: TokenMapping,
+}
+
+/// The context of the token at `range` in the innermost of the nested
+/// `expansions`.
+pub(super) fn syntax_context(expansions: &[Expansion], mut range: TextRange) -> SyntaxContext {
+    for expansion in expansions.iter().rev() {
+        match expansion.mapping.map_up(range) {
+            Some(it) => range = it,
+            None => return expansion.ctx,
+        }
+    }
+    SyntaxContext::ROOT
+}
+
+/// The range in the file of the body of the token at `range` in the innermost
+/// of the nested `expansions`, if it is written in the arguments of the macro
+/// calls.
+pub(super) fn original_range(expansions: &[Expansion], range: TextRange) -> Option<TextRange> {
+    expansions.iter().rev().try_fold(range, |range, expansion| expansion.mapping.map_up(range))
+}
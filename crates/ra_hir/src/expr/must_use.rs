@@ -0,0 +1,98 @@
+//! Finds the statements of a body which discard a value that must be used: the
+//! value of a call of a `#[must_use]` function, or a value of a `#[must_use]`
+//! type, like `Result`.
+use ra_syntax::{AstNode, AstPtr, SmolStr, ast};
+
+use crate::{
+    HirDatabase, Function, AdtDef, CallableDef,
+    diagnostics::{DiagnosticSink, UnusedMustUse},
+    expr::{Body, Expr, ExprId, Statement},
+    ty::{Ty, InferenceResult},
+};
+
+pub(crate) fn add_diagnostics(db: &impl HirDatabase, owner: Function, sink: &mut DiagnosticSink) {
+    let (file, _) = owner.source(db);
+    let source_file = db.hir_parse(file);
+    let body = owner.body(db);
+    let source_map = owner.body_source_map(db);
+    let infer = owner.infer(db);
+    let returns_result =
+        owner.ty(db).callable_sig(db).map_or(false, |sig| is_result(db, sig.ret()));
+    for (_, expr) in body.exprs() {
+        let statements = match expr {
+            Expr::Block { statements, .. } => statements,
+            _ => continue,
+        };
+        for statement in statements {
+            let expr_id = match statement {
+                Statement::Expr(it) => *it,
+                Statement::Let { .. } => continue,
+            };
+            let (description, note) = match must_use_value(db, &body, &infer, expr_id) {
+                Some(it) => it,
+                None => continue,
+            };
+            let expr = source_map
+                .expr_syntax(expr_id)
+                .and_then(|ptr| ast::Expr::cast(ptr.to_node(&source_file)));
+            if let Some(expr) = expr {
+                sink.push(UnusedMustUse {
+                    file,
+                    expr: AstPtr::new(expr),
+                    description,
+                    note: note.map(|it| it.to_string()),
+                    is_result: is_result(db, &infer[expr_id]),
+                    returns_result,
+                });
+            }
+        }
+    }
+}
+
+/// If the value of `expr` must be used, describes it, with the message of the
+/// `#[must_use]` attribute which requires it.
+fn must_use_value(
+    db: &impl HirDatabase,
+    body: &Body,
+    infer: &InferenceResult,
+    expr: ExprId,
+) -> Option<(String, Option<SmolStr>)> {
+    let func = match &body[expr] {
+        Expr::Call { callee, .. } => match infer[*callee].as_callable() {
+            Some((CallableDef::Function(func), _)) => Some(func),
+            _ => None,
+        },
+        Expr::MethodCall { .. } => infer.method_resolution(expr),
+        _ => None,
+    };
+    if let Some(func) = func {
+        if let Some(note) = must_use_attr(&*func.source(db).1) {
+            return Some((format!("return value of `{}`", func.name(db)), note));
+        }
+    }
+    let (adt, _) = infer[expr].as_adt()?;
+    let (name, note) = match adt {
+        AdtDef::Struct(it) => (it.name(db)?, must_use_attr(&*it.source(db).1)?),
+        AdtDef::Enum(it) => (it.name(db)?, must_use_attr(&*it.source(db).1)?),
+    };
+    Some((format!("`{}`", name), note))
+}
+
+/// The `#[must_use]` attribute of `node`, with its message, like the one of
+/// `#[must_use = "message"]`.
+fn must_use_attr(node: &impl ast::AttrsOwner) -> Option<Option<SmolStr>> {
+    node.attrs().find_map(|attr| {
+        if attr.as_named()? != "must_use" {
+            return None;
+        }
+        Some(attr.as_key_value().map(|(_, value)| value))
+    })
+}
+
+// FIXME recognize `Result` as a lang item instead of by name
+fn is_result(db: &impl HirDatabase, ty: &Ty) -> bool {
+    match ty.as_adt() {
+        Some((AdtDef::Enum(it), _)) => it.name(db).map_or(false, |it| it.to_string() == "Result"),
+        _ => false,
+    }
+}
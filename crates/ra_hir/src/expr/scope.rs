@@ -4,7 +4,7 @@ use rustc_hash::FxHashMap;
 use ra_arena::{Arena, RawId, impl_arena_id};
 
 use crate::{
-    Name, DefWithBody,
+    Name, DefWithBody, ModuleDef,
     expr::{PatId, ExprId, Pat, Expr, Body, Statement},
     HirDatabase,
 };
@@ -18,6 +18,9 @@ pub struct ExprScopes {
     body: Arc<Body>,
     scopes: Arena<ScopeId, ScopeData>,
     scope_by_expr: FxHashMap<ExprId, ScopeId>,
+    /// For each block, the scopes after each of its statements, starting with
+    /// the scope of the block itself.
+    statement_scopes: FxHashMap<ExprId, Vec<ScopeId>>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -39,6 +42,8 @@ impl ScopeEntry {
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) struct ScopeData {
     parent: Option<ScopeId>,
+    /// The block whose items this scope brings into scope, if any.
+    block: Option<ExprId>,
     entries: Vec<ScopeEntry>,
 }
 
@@ -54,6 +59,7 @@ impl ExprScopes {
             body: body.clone(),
             scopes: Arena::default(),
             scope_by_expr: FxHashMap::default(),
+            statement_scopes: FxHashMap::default(),
         };
         let root = scopes.root_scope();
         scopes.add_params_bindings(root, body.params());
@@ -61,10 +67,22 @@ impl ExprScopes {
         scopes
     }
 
+    pub(crate) fn body(&self) -> &Body {
+        &self.body
+    }
+
     pub(crate) fn entries(&self, scope: ScopeId) -> &[ScopeEntry] {
         &self.scopes[scope].entries
     }
 
+    /// The constants and statics declared in the block `scope` was created for.
+    pub(crate) fn items(&self, scope: ScopeId) -> &[(Name, ModuleDef)] {
+        match self.scopes[scope].block {
+            Some(block) => self.body.block_items(block),
+            None => &[],
+        }
+    }
+
     pub(crate) fn scope_chain<'a>(
         &'a self,
         scope: Option<ScopeId>,
@@ -80,12 +98,21 @@ impl ExprScopes {
         &self.scope_by_expr
     }
 
+    /// The scope after the first `n` statements of `block`.
+    pub(crate) fn scope_after_statements(&self, block: ExprId, n: usize) -> Option<ScopeId> {
+        self.statement_scopes.get(&block).and_then(|scopes| scopes.get(n)).cloned()
+    }
+
     fn root_scope(&mut self) -> ScopeId {
-        self.scopes.alloc(ScopeData { parent: None, entries: vec![] })
+        self.scopes.alloc(ScopeData { parent: None, block: None, entries: vec![] })
     }
 
     fn new_scope(&mut self, parent: ScopeId) -> ScopeId {
-        self.scopes.alloc(ScopeData { parent: Some(parent), entries: vec![] })
+        self.scopes.alloc(ScopeData { parent: Some(parent), block: None, entries: vec![] })
+    }
+
+    fn new_block_scope(&mut self, parent: ScopeId, block: ExprId) -> ScopeId {
+        self.scopes.alloc(ScopeData { parent: Some(parent), block: Some(block), entries: vec![] })
     }
 
     fn add_bindings(&mut self, body: &Body, scope: ScopeId, pat: PatId) {
@@ -111,12 +138,17 @@ impl ExprScopes {
 }
 
 fn compute_block_scopes(
+    block: ExprId,
     statements: &[Statement],
     tail: Option<ExprId>,
     body: &Body,
     scopes: &mut ExprScopes,
     mut scope: ScopeId,
 ) {
+    if !body.block_items(block).is_empty() {
+        scope = scopes.new_block_scope(scope, block);
+    }
+    let mut statement_scopes = vec![scope];
     for stmt in statements {
         match stmt {
             Statement::Let { pat, initializer, .. } => {
@@ -132,7 +164,9 @@ fn compute_block_scopes(
                 compute_expr_scopes(*expr, body, scopes, scope);
             }
         }
+        statement_scopes.push(scope);
     }
+    scopes.statement_scopes.insert(block, statement_scopes);
     if let Some(expr) = tail {
         compute_expr_scopes(expr, body, scopes, scope);
     }
@@ -142,7 +176,7 @@ fn compute_expr_scopes(expr: ExprId, body: &Body, scopes: &mut ExprScopes, scope
     scopes.set_scope(expr, scope);
     match &body[expr] {
         Expr::Block { statements, tail } => {
-            compute_block_scopes(&statements, *tail, body, scopes, scope);
+            compute_block_scopes(expr, &statements, *tail, body, scopes, scope);
         }
         Expr::For { iterable, pat, body: body_expr } => {
             compute_expr_scopes(*iterable, body, scopes, scope);
@@ -258,6 +292,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_while_let_scope() {
+        do_check(
+            r"
+            fn quux(it: Foo) {
+                while let Some(x) = it.next() {
+                    <|>
+                }
+            }",
+            &["x", "it"],
+        );
+    }
+
     #[test]
     fn test_match() {
         do_check(
@@ -284,6 +331,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scope_at() {
+        let (off, code) = extract_offset(
+            r"
+            fn foo(x: i32, y: i32) {
+                let x = x + 1;
+                let z = || { let w = 1; };
+                <|>
+            }",
+        );
+        let (db, _source_root, file_id) = MockDatabase::with_single_file(&code);
+        let file = db.parse(file_id);
+        let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+        let analyzer = SourceAnalyzer::new(&db, file_id, fn_def.syntax(), None);
+
+        let names: Vec<_> = analyzer.scope_at(off).iter().map(|it| it.name().to_string()).collect();
+        assert_eq!(names, ["z", "x", "y"]);
+    }
+
     fn do_check_local_name(code: &str, expected_offset: u32) {
         let (off, code) = extract_offset(code);
 
@@ -0,0 +1,113 @@
+//! Finds the operations in a body which are only allowed in unsafe contexts:
+//! calls of unsafe functions, dereferences of raw pointers and uses of mutable
+//! statics.
+use std::sync::Arc;
+
+use ra_syntax::{AstPtr, AstNode, SyntaxNodePtr, ast};
+
+use crate::{
+    HirDatabase, DefWithBody, Function, ModuleDef, CallableDef,
+    diagnostics::{DiagnosticSink, MissingUnsafe},
+    expr::{Body, Expr, ExprId, UnaryOp, resolver_for_expr},
+    resolve::Resolution,
+    ty::{Ty, ApplicationTy, TypeCtor, InferenceResult},
+};
+
+/// An operation which is only allowed in an `unsafe` block or function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsafeOperation {
+    pub expr: SyntaxNodePtr,
+    /// Whether the operation is in an `unsafe` block or function, where it is
+    /// allowed.
+    pub inside_unsafe_block: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct UnsafeExpr {
+    pub(crate) expr: ExprId,
+    pub(crate) inside_unsafe_block: bool,
+}
+
+pub(crate) fn unsafe_expressions(db: &impl HirDatabase, def: DefWithBody) -> Vec<UnsafeExpr> {
+    let body = db.body_hir(def);
+    let infer = db.infer(def);
+    let is_unsafe_fn = match def {
+        DefWithBody::Function(f) => f.signature(db).is_unsafe(),
+        DefWithBody::Const(_) | DefWithBody::Static(_) => false,
+    };
+    let mut res = Vec::new();
+    walk_unsafe(&mut res, db, &infer, &body, body.body_expr(), is_unsafe_fn);
+    res
+}
+
+pub(crate) fn unsafe_operations(db: &impl HirDatabase, def: DefWithBody) -> Vec<UnsafeOperation> {
+    let source_map = db.body_with_source_map(def).1;
+    unsafe_expressions(db, def)
+        .into_iter()
+        .filter_map(|it| {
+            let expr = source_map.expr_syntax(it.expr)?;
+            Some(UnsafeOperation { expr, inside_unsafe_block: it.inside_unsafe_block })
+        })
+        .collect()
+}
+
+pub(crate) fn add_diagnostics(db: &impl HirDatabase, owner: Function, sink: &mut DiagnosticSink) {
+    let (file, _) = owner.source(db);
+    let source_file = db.hir_parse(file);
+    let source_map = owner.body_source_map(db);
+    for unsafe_expr in unsafe_expressions(db, owner.into()) {
+        if unsafe_expr.inside_unsafe_block {
+            continue;
+        }
+        let expr = source_map
+            .expr_syntax(unsafe_expr.expr)
+            .and_then(|ptr| ast::Expr::cast(ptr.to_node(&source_file)));
+        if let Some(expr) = expr {
+            sink.push(MissingUnsafe { file, expr: AstPtr::new(expr) })
+        }
+    }
+}
+
+fn walk_unsafe(
+    res: &mut Vec<UnsafeExpr>,
+    db: &impl HirDatabase,
+    infer: &InferenceResult,
+    body: &Arc<Body>,
+    current: ExprId,
+    inside_unsafe_block: bool,
+) {
+    let expr = &body[current];
+    let is_unsafe = match expr {
+        Expr::Call { callee, .. } => match infer[*callee].as_callable() {
+            Some((CallableDef::Function(func), _)) => func.signature(db).is_unsafe(),
+            _ => false,
+        },
+        Expr::MethodCall { .. } => {
+            infer.method_resolution(current).map_or(false, |func| func.signature(db).is_unsafe())
+        }
+        Expr::UnaryOp { expr, op: UnaryOp::Deref } => match &infer[*expr] {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::RawPtr(..), .. }) => true,
+            _ => false,
+        },
+        Expr::Path(path) => {
+            let resolver = resolver_for_expr(Arc::clone(body), db, current);
+            match resolver.resolve_path(db, path).take_values() {
+                Some(Resolution::Def(ModuleDef::Static(it))) => it.is_mut(db),
+                _ => false,
+            }
+        }
+        // FIXME: accesses of union fields, once unions are supported
+        _ => false,
+    };
+    if is_unsafe {
+        res.push(UnsafeExpr { expr: current, inside_unsafe_block });
+    }
+
+    let inside_unsafe_block = match expr {
+        Expr::UnsafeBlock { .. } => true,
+        _ => inside_unsafe_block,
+    };
+    expr.walk_child_exprs(|child| {
+        walk_unsafe(res, db, infer, body, child, inside_unsafe_block);
+    });
+}
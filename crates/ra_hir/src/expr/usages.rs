@@ -0,0 +1,114 @@
+//! Finds the uses of the local bindings of a body. This is shared by find
+//! references and the unused variable diagnostic.
+use std::sync::Arc;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use ra_syntax::{AstPtr, AstNode, SyntaxKind::{IDENT, STRING}, ast};
+
+use crate::{
+    HirDatabase, DefWithBody, Function, ModuleDef, Either,
+    diagnostics::{DiagnosticSink, UnusedVariable},
+    expr::{Body, Expr, ExprId, Pat, PatId, resolver_for_expr},
+    path::PathKind,
+    resolve::Resolution,
+};
+
+/// The path expressions referring to each local binding of `def`.
+pub(crate) fn local_usages(
+    db: &impl HirDatabase,
+    def: DefWithBody,
+) -> FxHashMap<PatId, Vec<ExprId>> {
+    let body = db.body_hir(def);
+    let mut res: FxHashMap<PatId, Vec<ExprId>> = FxHashMap::default();
+    for (expr_id, expr) in body.exprs() {
+        let path = match expr {
+            Expr::Path(path) if path.kind == PathKind::Plain && path.segments.len() == 1 => path,
+            _ => continue,
+        };
+        let resolver = resolver_for_expr(Arc::clone(&body), db, expr_id);
+        if let Some(Resolution::LocalBinding(pat)) = resolver.resolve_path(db, path).take_values() {
+            res.entry(pat).or_default().push(expr_id);
+        }
+    }
+    res
+}
+
+pub(crate) fn add_diagnostics(db: &impl HirDatabase, owner: Function, sink: &mut DiagnosticSink) {
+    let (file, fn_def) = owner.source(db);
+    let source_file = db.hir_parse(file);
+    let body = owner.body(db);
+    let source_map = owner.body_source_map(db);
+    let usages = local_usages(db, owner.into());
+    // Macro calls are not expanded in bodies yet, so any identifier in their
+    // arguments might be a use, even in strings like the inline arguments of
+    // format strings.
+    let names_in_macro_calls = names_in_macro_calls(&fn_def);
+    let module_resolver = owner.resolver(db);
+    for (pat_id, pat) in body.pats() {
+        let name = match pat {
+            Pat::Bind { name, .. } => name,
+            _ => continue,
+        };
+        let text = name.to_string();
+        if usages.contains_key(&pat_id)
+            || text.starts_with('_')
+            || *name == crate::Name::missing()
+            || names_in_macro_calls.contains(&text)
+            || is_path_pattern(db, &body, &module_resolver, pat_id)
+        {
+            continue;
+        }
+        let bind_pat = match source_map.pat_syntax(pat_id) {
+            Some(Either::A(ptr)) => ast::BindPat::cast(ptr.to_node(&source_file).syntax()),
+            _ => None,
+        };
+        if let Some(bind_pat) = bind_pat {
+            sink.push(UnusedVariable { file, pat: AstPtr::new(bind_pat), name: name.clone() });
+        }
+    }
+}
+
+/// Whether the binding pattern `pat` actually refers to an enum variant, a
+/// unit struct or a constant, like `None` in `Some(x) | None`.
+fn is_path_pattern(
+    db: &impl HirDatabase,
+    body: &Body,
+    resolver: &crate::Resolver,
+    pat: PatId,
+) -> bool {
+    let name = match &body[pat] {
+        Pat::Bind { name, subpat: None, .. } => name,
+        _ => return false,
+    };
+    match resolver.resolve_name(db, name).take_values() {
+        Some(Resolution::Def(def)) => match def {
+            ModuleDef::EnumVariant(_)
+            | ModuleDef::Struct(_)
+            | ModuleDef::Const(_)
+            | ModuleDef::Static(_) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn names_in_macro_calls(fn_def: &ast::FnDef) -> FxHashSet<String> {
+    fn_def
+        .syntax()
+        .descendants()
+        .filter_map(ast::MacroCall::cast)
+        .filter_map(|it| it.token_tree())
+        .flat_map(|it| it.syntax().descendants_with_tokens())
+        .filter_map(|it| it.as_token())
+        .flat_map(|it| match it.kind() {
+            IDENT => vec![it.text().to_string()],
+            STRING => it
+                .text()
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .filter(|it| !it.is_empty())
+                .map(|it| it.to_string())
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
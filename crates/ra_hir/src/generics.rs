@@ -5,10 +5,14 @@
 
 use std::sync::Arc;
 
-use ra_syntax::ast::{self, NameOwner, TypeParamsOwner};
+use ra_syntax::{
+    SyntaxKind::QUESTION,
+    ast::{self, AstNode, NameOwner, TypeBoundsOwner, TypeParamsOwner},
+};
 
 use crate::{
     db::DefDatabase,
+    type_ref::TypeRef,
     Name, AsName, Function, Struct, Enum, Trait, TypeAlias, ImplBlock, Container
 };
 
@@ -25,6 +29,15 @@ pub struct GenericParam {
 pub struct GenericParams {
     pub(crate) parent_params: Option<Arc<GenericParams>>,
     pub(crate) params: Vec<GenericParam>,
+    pub(crate) where_predicates: Vec<WherePredicate>,
+}
+
+/// A single predicate from a where clause, i.e. `where Type: Trait`. Bounds on
+/// type parameters, like `<T: Trait>`, are desugared to such predicates too.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WherePredicate {
+    pub(crate) type_ref: TypeRef,
+    pub(crate) trait_ref: TypeRef,
 }
 
 // FIXME: consts can have type parameters from their parents (i.e. associated consts of traits)
@@ -73,16 +86,48 @@ impl GenericParams {
         if let Some(params) = node.type_param_list() {
             self.fill_params(params, start)
         }
+        if let Some(where_clause) = node.where_clause() {
+            self.fill_where_predicates(where_clause);
+        }
     }
 
     fn fill_params(&mut self, params: &ast::TypeParamList, start: u32) {
         for (idx, type_param) in params.type_params().enumerate() {
             let name = type_param.name().map(AsName::as_name).unwrap_or_else(Name::missing);
             let param = GenericParam { idx: idx as u32 + start, name };
+            if let Some(bounds) = type_param.type_bound_list() {
+                let type_ref = TypeRef::Path(param.name.clone().into());
+                self.add_where_predicates(type_ref, bounds);
+            }
             self.params.push(param);
         }
     }
 
+    fn fill_where_predicates(&mut self, where_clause: &ast::WhereClause) {
+        for pred in where_clause.predicates() {
+            let (type_ref, bounds) = match (pred.type_ref(), pred.type_bound_list()) {
+                (Some(type_ref), Some(bounds)) => (TypeRef::from_ast(type_ref), bounds),
+                _ => continue,
+            };
+            self.add_where_predicates(type_ref, bounds);
+        }
+    }
+
+    fn add_where_predicates(&mut self, type_ref: TypeRef, bounds: &ast::TypeBoundList) {
+        for bound in bounds.bounds() {
+            // `?Sized` relaxes a bound instead of adding one
+            if bound.syntax().first_child_or_token().map(|it| it.kind()) == Some(QUESTION) {
+                continue;
+            }
+            // lifetime bounds have no type ref
+            let trait_ref = match bound.type_ref() {
+                Some(it) => TypeRef::from_ast(it),
+                None => continue,
+            };
+            self.where_predicates.push(WherePredicate { type_ref: type_ref.clone(), trait_ref });
+        }
+    }
+
     pub(crate) fn find_by_name(&self, name: &Name) -> Option<&GenericParam> {
         self.params.iter().find(|p| &p.name == name)
     }
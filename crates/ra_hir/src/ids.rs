@@ -4,7 +4,7 @@ use std::{
 };
 
 use ra_db::{FileId, salsa};
-use ra_syntax::{TreeArc, SourceFile, AstNode, ast};
+use ra_syntax::{TreeArc, SourceFile, AstNode, TextRange, ast};
 use mbe::MacroRules;
 
 use crate::{
@@ -46,6 +46,19 @@ impl HirFileId {
         }
     }
 
+    /// Maps `range` in this file to a range in the original source file. As we
+    /// don't know where the tokens of an expansion come from, ranges in macro
+    /// expansions are mapped to the whole outermost macro call.
+    pub fn original_range(self, db: &impl DefDatabase, range: TextRange) -> TextRange {
+        let mut range = range;
+        let mut file_id = self;
+        while let Some(call) = file_id.macro_call(db) {
+            range = call.to_node(db).syntax().range();
+            file_id = call.file_id();
+        }
+        range
+    }
+
     /// XXX: this is a temporary function, which should go away when we implement the
     /// nameresolution+macro expansion combo. Prefer using `original_file` if
     /// possible.
@@ -56,6 +69,15 @@ impl HirFileId {
         }
     }
 
+    /// For macro-expansion files, returns the macro call the expansion comes
+    /// from.
+    pub(crate) fn macro_call(self, db: &impl DefDatabase) -> Option<AstId<ast::MacroCall>> {
+        match self.0 {
+            HirFileIdRepr::File(_) => None,
+            HirFileIdRepr::Macro(macro_call_id) => Some(macro_call_id.loc(db).ast_id),
+        }
+    }
+
     pub(crate) fn hir_parse_query(
         db: &impl DefDatabase,
         file_id: HirFileId,
@@ -80,6 +102,16 @@ fn parse_macro(db: &impl DefDatabase, macro_call_id: MacroCallId) -> Option<Tree
     Some(mbe::token_tree_to_ast_item_list(&tt))
 }
 
+/// The number of tokens of the expansion of a macro call, which is stable
+/// while editing the call unless tokens are added or removed.
+pub(crate) fn macro_expansion_size_query(
+    db: &impl DefDatabase,
+    macro_call_id: MacroCallId,
+) -> usize {
+    let source_file = db.hir_parse(macro_call_id.into());
+    source_file.syntax().descendants_with_tokens().filter(|it| it.as_token().is_some()).count()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum HirFileIdRepr {
     File(FileId),
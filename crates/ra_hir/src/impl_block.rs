@@ -3,13 +3,15 @@ use rustc_hash::FxHashMap;
 
 use ra_arena::{Arena, RawId, impl_arena_id, map::ArenaMap};
 use ra_syntax::{
-    AstPtr, SourceFile, TreeArc,
+    AstPtr, SourceFile, TreeArc, TextRange,
     ast::{self, AstNode}
 };
 
 use crate::{
-    Const, TypeAlias, Function, HirFileId,
-    HirDatabase, DefDatabase, TraitRef,
+    Const, TypeAlias, Function, HirFileId, Name,
+    HirDatabase, DefDatabase, TraitRef, Attrs, Deprecation,
+    traits::TraitItem,
+    diagnostics::{DiagnosticSink, MissingImplMembers},
     type_ref::TypeRef,
     ids::LocationCtx,
     resolve::Resolver,
@@ -93,6 +95,48 @@ impl ImplBlock {
         db.impls_in_module(self.module).impls[self.impl_id].items().to_vec()
     }
 
+    pub fn diagnostics(&self, db: &impl HirDatabase, sink: &mut DiagnosticSink) {
+        for item in self.items(db) {
+            match item {
+                ImplItem::Method(f) => f.diagnostics(db, sink),
+                _ => (),
+            }
+        }
+        self.add_missing_members_diagnostic(db, sink);
+    }
+
+    /// Reports the items of the implemented trait which have no default and
+    /// are missing from this impl block.
+    fn add_missing_members_diagnostic(&self, db: &impl HirDatabase, sink: &mut DiagnosticSink) {
+        let trait_ = match self.target_trait_ref(db) {
+            Some(it) => it.trait_(),
+            None => return,
+        };
+        let implemented: Vec<TraitItem> =
+            self.items(db).into_iter().filter_map(|it| it.trait_item(db)).collect();
+        let missed_members: Vec<Name> = trait_
+            .items(db)
+            .into_iter()
+            .filter(|it| !it.has_default(db) && !implemented.contains(it))
+            .filter_map(|it| it.name(db))
+            .collect();
+        if missed_members.is_empty() {
+            return;
+        }
+        let (file, source) = self.source(db);
+        let start = source.syntax().range().start();
+        let end = match source.target_type() {
+            Some(it) => it.syntax().range().end(),
+            None => source.syntax().range().end(),
+        };
+        sink.push(MissingImplMembers {
+            file,
+            impl_block: AstPtr::new(&*source),
+            header_range: TextRange::from_to(start, end),
+            missed_members,
+        });
+    }
+
     pub(crate) fn resolver(&self, db: &impl DefDatabase) -> Resolver {
         let r = self.module().resolver(db);
         // add generic params, if present
@@ -164,6 +208,58 @@ impl From<Function> for ImplItem {
     }
 }
 
+impl ImplItem {
+    /// The containing impl block.
+    pub fn impl_block(self, db: &impl DefDatabase) -> Option<ImplBlock> {
+        match self {
+            ImplItem::Method(it) => it.impl_block(db),
+            ImplItem::Const(it) => it.impl_block(db),
+            ImplItem::TypeAlias(it) => it.impl_block(db),
+        }
+    }
+
+    pub fn name(self, db: &impl DefDatabase) -> Option<Name> {
+        match self {
+            ImplItem::Method(it) => Some(db.fn_signature(it).name().clone()),
+            ImplItem::Const(it) => Some(db.const_signature(it).name().clone()),
+            ImplItem::TypeAlias(it) => it.name(db),
+        }
+    }
+
+    /// The item of the trait which this item implements, if it's in the impl
+    /// block of a trait.
+    pub fn trait_item(self, db: &impl HirDatabase) -> Option<TraitItem> {
+        db.impl_item_trait_item(self)
+    }
+}
+
+pub(crate) fn impl_item_trait_item_query(
+    db: &impl HirDatabase,
+    item: ImplItem,
+) -> Option<TraitItem> {
+    let trait_ = item.impl_block(db)?.target_trait_ref(db)?.trait_();
+    let name = item.name(db)?;
+    trait_.items(db).into_iter().find(|&trait_item| {
+        let same_kind = match (item, trait_item) {
+            (ImplItem::Method(_), TraitItem::Function(_))
+            | (ImplItem::Const(_), TraitItem::Const(_))
+            | (ImplItem::TypeAlias(_), TraitItem::TypeAlias(_)) => true,
+            _ => false,
+        };
+        same_kind && trait_item.name(db).as_ref() == Some(&name)
+    })
+}
+
+impl Attrs for ImplItem {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        match self {
+            ImplItem::Method(it) => it.deprecation(db),
+            ImplItem::Const(it) => it.deprecation(db),
+            ImplItem::TypeAlias(it) => it.deprecation(db),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ImplId(pub RawId);
 impl_arena_id!(ImplId);
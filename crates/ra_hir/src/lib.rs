@@ -18,12 +18,14 @@ macro_rules! impl_froms {
 }
 
 mod either;
+mod cfg;
 
 pub mod db;
 #[macro_use]
 pub mod mock;
 mod path;
 pub mod source_binder;
+mod semantics;
 
 mod source_id;
 mod ids;
@@ -38,6 +40,7 @@ mod impl_block;
 mod expr;
 mod generics;
 mod docs;
+mod attrs;
 mod resolve;
 pub mod diagnostics;
 
@@ -61,14 +64,20 @@ pub use self::{
     source_id::{AstIdMap, ErasedFileAstId},
     ids::{HirFileId, MacroDefId, MacroCallId, MacroCallLoc},
     nameres::{PerNs, Namespace, ImportId},
-    ty::{Ty, ApplicationTy, TypeCtor, TraitRef, Substs, display::HirDisplay, CallableDef},
+    ty::{
+        Ty, ApplicationTy, TypeCtor, TraitRef, Substs, FnSig, display::HirDisplay, CallableDef,
+        ObjectSafetyViolation, MethodViolation,
+    },
     impl_block::{ImplBlock, ImplItem},
+    traits::TraitItem,
     docs::{Docs, Documentation},
+    attrs::{Attrs, Deprecation},
     adt::AdtDef,
-    expr::ExprScopes,
+    expr::{ExprScopes, UnsafeOperation, ClosureCapture, CaptureKind},
     resolve::Resolution,
     generics::{GenericParams, GenericParam, HasGenericParams},
     source_binder::{SourceAnalyzer, PathResolution, ScopeEntryWithSyntax},
+    semantics::{Semantics, Definition},
 };
 
 pub use self::code_model_api::{
@@ -98,6 +98,7 @@ impl MockDatabase {
         let mut source_root = SourceRoot::default();
         let mut source_root_id = WORKSPACE;
         let mut source_root_prefix = "/".to_string();
+        let mut crates = CrateGraphFixture::default();
         for entry in parse_fixture(fixture) {
             if entry.meta.starts_with("root") {
                 self.set_source_root(source_root_id, Arc::new(source_root));
@@ -107,13 +108,18 @@ impl MockDatabase {
                 source_root_prefix = entry.meta["root".len()..].trim().to_string();
                 continue;
             }
+            if let Some(crate_name) = entry.crate_name() {
+                let edition = entry.edition().map_or(Edition::Edition2018, Edition::from_string);
+                let deps = entry.deps().into_iter().map(String::from).collect();
+                crates.0.insert(crate_name.to_string(), (entry.path().to_string(), edition, deps));
+            }
             if entry.text.contains(CURSOR_MARKER) {
                 assert!(position.is_none(), "only one marker (<|>) per fixture is allowed");
                 position = Some(self.add_file_with_position(
                     source_root_id,
                     &source_root_prefix,
                     &mut source_root,
-                    &entry.meta,
+                    entry.path(),
                     &entry.text,
                 ));
             } else {
@@ -121,12 +127,17 @@ impl MockDatabase {
                     source_root_id,
                     &source_root_prefix,
                     &mut source_root,
-                    &entry.meta,
+                    entry.path(),
                     &entry.text,
                 );
             }
         }
         self.set_source_root(source_root_id, Arc::new(source_root));
+        // the crates declared with `crate:name` in the metas replace the
+        // default crate of `lib.rs` or `main.rs`
+        if !crates.0.is_empty() {
+            self.set_crate_graph_from_fixture(crates);
+        }
         position
     }
 
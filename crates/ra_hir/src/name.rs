@@ -42,6 +42,11 @@ impl Name {
         Name::new("Self".into())
     }
 
+    /// The `Output` associated type of the `Fn*` traits.
+    pub(crate) fn output_type() -> Name {
+        Name::new("Output".into())
+    }
+
     pub(crate) fn tuple_field_name(idx: usize) -> Name {
         Name::new(idx.to_string().into())
     }
@@ -68,6 +73,9 @@ impl Name {
             "Self" => KnownName::SelfType,
             "self" => KnownName::SelfParam,
             "macro_rules" => KnownName::MacroRules,
+            "Fn" => KnownName::Fn,
+            "FnMut" => KnownName::FnMut,
+            "FnOnce" => KnownName::FnOnce,
             _ => return None,
         };
         Some(name)
@@ -99,6 +107,12 @@ impl<'a> AsName for ast::FieldKind<'a> {
     }
 }
 
+impl AsName for SmolStr {
+    fn as_name(&self) -> Name {
+        Name::new(self.clone())
+    }
+}
+
 impl AsName for ra_db::Dependency {
     fn as_name(&self) -> Name {
         Name::new(self.name.clone())
@@ -137,4 +151,8 @@ pub(crate) enum KnownName {
     SelfParam,
 
     MacroRules,
+
+    Fn,
+    FnMut,
+    FnOnce,
 }
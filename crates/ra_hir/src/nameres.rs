@@ -50,6 +50,7 @@
 mod per_ns;
 mod raw;
 mod collector;
+mod include;
 #[cfg(test)]
 mod tests;
 
@@ -59,13 +60,13 @@ use rustc_hash::FxHashMap;
 use ra_arena::{Arena, RawId, impl_arena_id};
 use ra_db::{FileId, Edition};
 use test_utils::tested_by;
-use ra_syntax::ast;
+use ra_syntax::ast::{self, AttrsOwner};
 use ra_prof::profile;
 
 use crate::{
     ModuleDef, Name, Crate, Module,
     DefDatabase, Path, PathKind, HirFileId, Trait,
-    ids::MacroDefId,
+    ids::{MacroDefId, MacroCallId},
     diagnostics::DiagnosticSink,
     nameres::diagnostics::DefDiagnostic,
     AstId,
@@ -78,6 +79,14 @@ pub use self::{
     raw::ImportId,
 };
 
+/// The recursion limit of crates without a `#![recursion_limit]` attribute.
+const DEFAULT_RECURSION_LIMIT: u32 = 64;
+
+/// How many tokens the expansion of a macro call may produce, counting the
+/// expansions of the macro calls nested in it. This stops macros which don't
+/// nest deeply but grow exponentially.
+const EXPANSION_TOKEN_LIMIT: usize = 1 << 18;
+
 /// Contans all top-level defs from a macro-expanded crate
 #[derive(Debug, PartialEq, Eq)]
 pub struct CrateDefMap {
@@ -91,6 +100,17 @@ pub struct CrateDefMap {
     root: CrateModuleId,
     modules: Arena<CrateModuleId, ModuleData>,
     public_macros: FxHashMap<Name, MacroDefId>,
+    /// All the `macro_rules` of the crate, which share a crate-global
+    /// namespace.
+    macros: FxHashMap<Name, MacroDefId>,
+    /// The macro calls in item position which were expanded.
+    macro_calls: FxHashMap<AstId<ast::MacroCall>, MacroCallId>,
+    /// Whether some imports or macro calls of the crate were not resolved,
+    /// in which case names missing from the scopes could come from them.
+    incomplete: bool,
+    /// How deeply macro calls may be nested in expansions, from the
+    /// `#![recursion_limit]` attribute of the crate root.
+    recursion_limit: u32,
     diagnostics: Vec<DefDiagnostic>,
 }
 
@@ -195,6 +215,10 @@ impl CrateDefMap {
                 root,
                 modules,
                 public_macros: FxHashMap::default(),
+                macros: FxHashMap::default(),
+                macro_calls: FxHashMap::default(),
+                incomplete: false,
+                recursion_limit: db.recursion_limit(krate),
                 diagnostics: Vec::new(),
             }
         };
@@ -202,6 +226,22 @@ impl CrateDefMap {
         Arc::new(def_map)
     }
 
+    /// The value of the `#![recursion_limit = "N"]` attribute of the crate
+    /// root. This is a query of its own so that editing the root file doesn't
+    /// invalidate the def map.
+    pub(crate) fn recursion_limit_query(db: &impl DefDatabase, krate: Crate) -> u32 {
+        let file_id = db.crate_graph().crate_root(krate.crate_id());
+        let source_file = db.parse(file_id);
+        source_file
+            .attrs()
+            .filter(|it| it.is_inner())
+            .find_map(|attr| match attr.as_key_value() {
+                Some((ref key, ref value)) if key == "recursion_limit" => value.parse().ok(),
+                _ => None,
+            })
+            .unwrap_or(DEFAULT_RECURSION_LIMIT)
+    }
+
     pub(crate) fn root(&self) -> CrateModuleId {
         self.root
     }
@@ -210,6 +250,32 @@ impl CrateDefMap {
         Module { krate: self.krate, module_id }
     }
 
+    pub(crate) fn macro_call(&self, ast_id: AstId<ast::MacroCall>) -> Option<MacroCallId> {
+        self.macro_calls.get(&ast_id).cloned()
+    }
+
+    /// Resolves the path of a macro call outside of item position: either a
+    /// `macro_rules` of this crate or an exported macro of another crate.
+    pub(crate) fn resolve_macro(&self, db: &impl DefDatabase, path: &Path) -> Option<MacroDefId> {
+        if let Some(name) = path.as_ident() {
+            return self.macros.get(name).cloned();
+        }
+        if path.kind != PathKind::Plain || path.segments.len() != 2 {
+            return None;
+        }
+        let krate = match self.resolve_name_in_extern_prelude(&path.segments[0].name).take_types() {
+            Some(ModuleDef::Module(m)) => m.krate(db)?,
+            _ => return None,
+        };
+        db.crate_def_map(krate).public_macros.get(&path.segments[1].name).cloned()
+    }
+
+    /// Whether a macro call nested `depth` expansions deep, in expansions which
+    /// produced `tokens` tokens so far, must not be expanded anymore.
+    pub(crate) fn expansion_limit_reached(&self, depth: usize, tokens: usize) -> bool {
+        depth >= self.recursion_limit as usize || tokens > EXPANSION_TOKEN_LIMIT
+    }
+
     pub(crate) fn prelude(&self) -> Option<Module> {
         self.prelude
     }
@@ -218,6 +284,12 @@ impl CrateDefMap {
         &self.extern_prelude
     }
 
+    /// Whether all the names in the crate are known, so that a name which
+    /// can't be resolved is an error.
+    pub(crate) fn is_complete(&self) -> bool {
+        !self.incomplete && self.prelude.is_some()
+    }
+
     pub(crate) fn add_diagnostics(
         &self,
         db: &impl DefDatabase,
@@ -437,9 +509,12 @@ mod diagnostics {
     use ra_syntax::{AstPtr, ast};
 
     use crate::{
-        AstId, DefDatabase,
-        nameres::CrateModuleId,
-        diagnostics::{DiagnosticSink, UnresolvedModule},
+        AstId, DefDatabase, HirFileId, Name,
+        nameres::{CrateModuleId, raw},
+        diagnostics::{
+            DiagnosticSink, UnresolvedModule, UnresolvedImport, MacroRecursionLimit,
+            UnresolvedMacroCall,
+        },
 };
 
     #[derive(Debug, PartialEq, Eq)]
@@ -449,6 +524,22 @@ mod diagnostics {
             declaration: AstId<ast::Module>,
             candidate: RelativePathBuf,
         },
+        UnresolvedImport {
+            module: CrateModuleId,
+            file_id: HirFileId,
+            import: raw::ImportId,
+        },
+        /// The expansion of the outermost macro `call` went over the recursion
+        /// limit or the token budget of the crate.
+        MacroRecursionLimit {
+            module: CrateModuleId,
+            call: AstId<ast::MacroCall>,
+        },
+        UnresolvedMacroCall {
+            module: CrateModuleId,
+            call: AstId<ast::MacroCall>,
+            name: Name,
+        },
     }
 
     impl DefDiagnostic {
@@ -470,6 +561,33 @@ mod diagnostics {
                         candidate: candidate.clone(),
                     })
                 }
+                DefDiagnostic::UnresolvedImport { module, file_id, import } => {
+                    if *module != target_module {
+                        return;
+                    }
+                    let (_, source_map) = db.raw_items_with_source_map(*file_id);
+                    if let Some(use_tree) = source_map.use_tree_ptr(*import) {
+                        sink.push(UnresolvedImport { file: *file_id, use_tree })
+                    }
+                }
+                DefDiagnostic::MacroRecursionLimit { module, call } => {
+                    if *module != target_module {
+                        return;
+                    }
+                    let node = call.to_node(db);
+                    sink.push(MacroRecursionLimit::new(call.file_id(), &node))
+                }
+                DefDiagnostic::UnresolvedMacroCall { module, call, name } => {
+                    if *module != target_module {
+                        return;
+                    }
+                    let node = call.to_node(db);
+                    sink.push(UnresolvedMacroCall {
+                        file: call.file_id(),
+                        call: AstPtr::new(&node),
+                        name: name.clone(),
+                    })
+                }
             }
         }
     }
@@ -7,12 +7,13 @@ use ra_syntax::ast;
 
 use crate::{
     Function, Module, Struct, Enum, Const, Static, Trait, TypeAlias,
-    DefDatabase, HirFileId, Name, Path,
+    DefDatabase, HirFileId, Name, Path, PathKind,
     KnownName,
     nameres::{
         Resolution, PerNs, ModuleDef, ReachedFixedPoint, ResolveMode,
         CrateDefMap, CrateModuleId, ModuleData,
         diagnostics::DefDiagnostic,
+        include,
         raw,
     },
     ids::{AstItemDef, LocationCtx, MacroCallLoc, MacroCallId, MacroDefId},
@@ -41,7 +42,8 @@ pub(super) fn collect_defs(db: &impl DefDatabase, mut def_map: CrateDefMap) -> C
         glob_imports: FxHashMap::default(),
         unresolved_imports: Vec::new(),
         unexpanded_macros: Vec::new(),
-        global_macro_scope: FxHashMap::default(),
+        expanded_tokens: FxHashMap::default(),
+        include_depth: 0,
     };
     collector.collect();
     collector.finish()
@@ -52,9 +54,13 @@ struct DefCollector<DB> {
     db: DB,
     def_map: CrateDefMap,
     glob_imports: FxHashMap<CrateModuleId, Vec<(CrateModuleId, raw::ImportId)>>,
-    unresolved_imports: Vec<(CrateModuleId, raw::ImportId, raw::ImportData)>,
+    unresolved_imports: Vec<(CrateModuleId, HirFileId, raw::ImportId, raw::ImportData)>,
     unexpanded_macros: Vec<(CrateModuleId, AstId<ast::MacroCall>, Path)>,
-    global_macro_scope: FxHashMap<Name, MacroDefId>,
+    /// The number of tokens produced by the expansion of each outermost macro
+    /// call so far.
+    expanded_tokens: FxHashMap<AstId<ast::MacroCall>, usize>,
+    /// The number of nested `include!`s whose items are being collected.
+    include_depth: usize,
 }
 
 impl<'a, DB> DefCollector<&'a DB>
@@ -90,25 +96,106 @@ where
 
         let unresolved_imports = std::mem::replace(&mut self.unresolved_imports, Vec::new());
         // show unresolved imports in completion, etc
-        for (module_id, import, import_data) in unresolved_imports {
+        for (module_id, file_id, import, import_data) in unresolved_imports {
+            self.report_unresolved_import(module_id, file_id, import, &import_data);
             self.record_resolved_import(module_id, PerNs::none(), import, &import_data)
         }
+        let unexpanded_macros = std::mem::replace(&mut self.unexpanded_macros, Vec::new());
+        for (module_id, call, path) in unexpanded_macros {
+            self.def_map.incomplete = true;
+            self.report_unresolved_macro(module_id, call, &path);
+        }
+    }
+
+    /// Reports a call of a macro which isn't defined anywhere we know of. Only
+    /// macros of a single name are checked, longer paths might point into
+    /// crates which aren't loaded.
+    fn report_unresolved_macro(
+        &mut self,
+        module_id: CrateModuleId,
+        call: AstId<ast::MacroCall>,
+        path: &Path,
+    ) {
+        let name = match path.as_ident() {
+            Some(it) => it,
+            None => return,
+        };
+        if self.def_map.prelude.is_none() || BUILTIN_MACROS.contains(&name.to_string().as_str()) {
+            return;
+        }
+        // `#[macro_use] extern crate` isn't supported yet, so any macro exported
+        // by a dependency might be in scope.
+        let db = self.db;
+        let exported_by_dep = self
+            .def_map
+            .krate
+            .dependencies(db)
+            .into_iter()
+            .any(|dep| db.crate_def_map(dep.krate).public_macros.contains_key(name));
+        if !exported_by_dep {
+            self.def_map.diagnostics.push(DefDiagnostic::UnresolvedMacroCall {
+                module: module_id,
+                call,
+                name: name.clone(),
+            });
+        }
+    }
+
+    /// Reports an import which couldn't be resolved, unless it might come from
+    /// something we don't know about, like a crate which isn't loaded.
+    fn report_unresolved_import(
+        &mut self,
+        module_id: CrateModuleId,
+        file_id: HirFileId,
+        import_id: raw::ImportId,
+        import: &raw::ImportData,
+    ) {
+        self.def_map.incomplete = true;
+        if import.is_extern_crate {
+            return;
+        }
+        let first_segment_resolved = match import.path.kind {
+            PathKind::Crate | PathKind::Self_ | PathKind::Super => true,
+            PathKind::Plain | PathKind::Abs => {
+                let path = Path {
+                    kind: import.path.kind,
+                    segments: import.path.segments[..1].iter().cloned().collect(),
+                };
+                let res =
+                    self.def_map.resolve_path_fp(self.db, ResolveMode::Import, module_id, &path);
+                !res.resolved_def.is_none()
+            }
+        };
+        // Macros are not in `PerNs`, so imports of macros don't resolve.
+        let is_macro = import.path.segments.last().map_or(false, |it| {
+            self.def_map.macros.contains_key(&it.name)
+                || self.def_map.krate.dependencies(self.db).into_iter().any(|dep| {
+                    self.db.crate_def_map(dep.krate).public_macros.contains_key(&it.name)
+                })
+        });
+        if first_segment_resolved && !is_macro {
+            self.def_map.diagnostics.push(DefDiagnostic::UnresolvedImport {
+                module: module_id,
+                file_id,
+                import: import_id,
+            });
+        }
     }
 
     fn define_macro(&mut self, name: Name, macro_id: MacroDefId, export: bool) {
         if export {
             self.def_map.public_macros.insert(name.clone(), macro_id);
         }
-        self.global_macro_scope.insert(name, macro_id);
+        self.def_map.macros.insert(name, macro_id);
     }
 
     fn resolve_imports(&mut self) -> ReachedFixedPoint {
         let mut imports = std::mem::replace(&mut self.unresolved_imports, Vec::new());
         let mut resolved = Vec::new();
-        imports.retain(|(module_id, import, import_data)| {
+        imports.retain(|(module_id, file_id, import, import_data)| {
             let (def, fp) = self.resolve_import(*module_id, import_data);
             if fp == ReachedFixedPoint::Yes {
-                resolved.push((*module_id, def, *import, import_data.clone()))
+                resolved.push((*module_id, *file_id, def, *import, import_data.clone()))
             }
             fp == ReachedFixedPoint::No
         });
@@ -116,7 +203,10 @@ where
         // Resolves imports, filling-in module scopes
         let result =
             if resolved.is_empty() { ReachedFixedPoint::Yes } else { ReachedFixedPoint::No };
-        for (module_id, def, import, import_data) in resolved {
+        for (module_id, file_id, def, import, import_data) in resolved {
+            if def.is_none() {
+                self.report_unresolved_import(module_id, file_id, import, &import_data);
+            }
             self.record_resolved_import(module_id, def, import, &import_data)
         }
         result
@@ -317,6 +407,8 @@ where
             false
         });
 
+        self.unexpanded_macros.extend(macros);
+
         for (module_id, macro_call_id) in resolved {
             self.collect_macro_expansion(module_id, macro_call_id);
         }
@@ -324,8 +416,31 @@ where
     }
 
     fn collect_macro_expansion(&mut self, module_id: CrateModuleId, macro_call_id: MacroCallId) {
+        let ast_id = macro_call_id.loc(self.db).ast_id;
+        let (mut outermost, mut depth) = (ast_id, 0);
+        while let Some(call) = outermost.file_id().macro_call(self.db) {
+            outermost = call;
+            depth += 1;
+        }
+        let tokens = self.expanded_tokens.get(&outermost).cloned().unwrap_or(0);
+        if self.def_map.expansion_limit_reached(depth, tokens) {
+            self.def_map.incomplete = true;
+            let diagnostic =
+                DefDiagnostic::MacroRecursionLimit { module: module_id, call: outermost };
+            if !self.def_map.diagnostics.contains(&diagnostic) {
+                self.def_map.diagnostics.push(diagnostic);
+            }
+            return;
+        }
+        self.def_map.macro_calls.insert(ast_id, macro_call_id);
         let file_id: HirFileId = macro_call_id.into();
+        *self.expanded_tokens.entry(outermost).or_default() +=
+            self.db.macro_expansion_size(macro_call_id);
         let raw_items = self.db.raw_items(file_id);
+        // The expansion failed, so we don't know which items it would define.
+        if raw_items.items().is_empty() {
+            self.def_map.incomplete = true;
+        }
         ModCollector { def_collector: &mut *self, file_id, module_id, raw_items: &raw_items }
             .collect(raw_items.items())
     }
@@ -348,11 +463,15 @@ where
     DB: DefDatabase,
 {
     fn collect(&mut self, items: &[raw::RawItem]) {
-        for item in items {
-            match *item {
+        for &item in items {
+            if !self.is_cfg_enabled(item) {
+                continue;
+            }
+            match item {
                 raw::RawItem::Module(m) => self.collect_module(&self.raw_items[m]),
                 raw::RawItem::Import(import) => self.def_collector.unresolved_imports.push((
                     self.module_id,
+                    self.file_id,
                     import,
                     self.raw_items[import].clone(),
                 )),
@@ -362,6 +481,16 @@ where
         }
     }
 
+    /// Whether the `#[cfg(...)]` of `item`, if any, holds in the crate.
+    fn is_cfg_enabled(&self, item: raw::RawItem) -> bool {
+        let cfg = match self.raw_items.cfg(item) {
+            Some(it) => it,
+            None => return true,
+        };
+        let crate_graph = self.def_collector.db.crate_graph();
+        cfg.is_enabled(crate_graph.cfg_options(self.def_collector.def_map.krate.crate_id()))
+    }
+
     fn collect_module(&mut self, module: &raw::ModuleData) {
         match module {
             // inline module, just recurse
@@ -464,10 +593,36 @@ where
 
         let ast_id = mac.ast_id.with_file_id(self.file_id);
 
-        // Case 2: try to expand macro_rules from this crate, triggering
+        // Case 2: `include!` of a file we know about, whose items are
+        // collected into the current module.
+        if is_include(&mac.path) {
+            let db = self.def_collector.db;
+            let krate = self.def_collector.def_map.krate;
+            let call = ast_id.to_node(db);
+            if let Some(file_id) = include::resolve_include(db, krate, self.file_id, &call) {
+                let def_collector = &mut *self.def_collector;
+                if def_collector.def_map.expansion_limit_reached(def_collector.include_depth, 0) {
+                    def_collector.def_map.incomplete = true;
+                    return;
+                }
+                let raw_items = db.raw_items(file_id.into());
+                def_collector.include_depth += 1;
+                ModCollector {
+                    def_collector: &mut *def_collector,
+                    module_id: self.module_id,
+                    file_id: file_id.into(),
+                    raw_items: &raw_items,
+                }
+                .collect(raw_items.items());
+                def_collector.include_depth -= 1;
+                return;
+            }
+        }
+
+        // Case 3: try to expand macro_rules from this crate, triggering
         // recursive item collection.
         if let Some(&macro_id) =
-            mac.path.as_ident().and_then(|name| self.def_collector.global_macro_scope.get(name))
+            mac.path.as_ident().and_then(|name| self.def_collector.def_map.macros.get(name))
         {
             let macro_call_id = MacroCallLoc { def: macro_id, ast_id }.id(self.def_collector.db);
 
@@ -475,11 +630,38 @@ where
             return;
         }
 
-        // Case 3: path to a macro from another crate, expand during name resolution
+        // Case 4: path to a macro from another crate, expand during name resolution
         self.def_collector.unexpanded_macros.push((self.module_id, ast_id, mac.path.clone()))
     }
 }
 
+/// The macros built into the compiler, which aren't defined by any crate.
+const BUILTIN_MACROS: &[&str] = &[
+    "asm",
+    "cfg",
+    "column",
+    "compile_error",
+    "concat",
+    "concat_idents",
+    "env",
+    "file",
+    "format_args",
+    "global_asm",
+    "include",
+    "include_bytes",
+    "include_str",
+    "line",
+    "log_syntax",
+    "module_path",
+    "option_env",
+    "stringify",
+    "trace_macros",
+];
+
+fn is_include(path: &Path) -> bool {
+    path.as_ident().map_or(false, |it| it.to_string() == "include")
+}
+
 fn is_macro_rules(path: &Path) -> bool {
     path.as_ident().and_then(Name::as_known_name) == Some(KnownName::MacroRules)
 }
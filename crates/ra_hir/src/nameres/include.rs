@@ -0,0 +1,89 @@
+//! Finds the file an `include!` in item position refers to. The argument is
+//! evaluated as far as we can: string literals, `concat!` and `env!` with the
+//! environment of the crate.
+use ra_db::{Env, FileId};
+use ra_syntax::{
+    SyntaxElement,
+    SyntaxKind::{COMMA, EXCL, IDENT, R_BRACK, R_CURLY, R_PAREN, STRING},
+    ast::{self, AstNode},
+};
+use relative_path::RelativePathBuf;
+
+use crate::{Crate, DefDatabase, HirFileId};
+
+pub(super) fn resolve_include(
+    db: &impl DefDatabase,
+    krate: Crate,
+    file_id: HirFileId,
+    call: &ast::MacroCall,
+) -> Option<FileId> {
+    let crate_graph = db.crate_graph();
+    let env = crate_graph.env(krate.crate_id());
+    let path = eval_str_args(call.token_tree()?, env)?;
+
+    // The files generated by the build script are in their own source root,
+    // so they are looked up in the crate graph.
+    if let Some(out_dir) = env.get("OUT_DIR") {
+        if path.starts_with(out_dir) {
+            let rest = path[out_dir.len()..].trim_start_matches(|c| c == '/' || c == '\\');
+            let rest = RelativePathBuf::from(rest.replace('\\', "/")).normalize();
+            return crate_graph.out_dir_file(krate.crate_id(), &rest);
+        }
+    }
+    if std::path::Path::new(&path).is_absolute() {
+        return None;
+    }
+    // Other paths are relative to the file with the call.
+    let file_id = file_id.original_file(db);
+    let file_path = db.file_relative_path(file_id);
+    let path = match file_path.parent() {
+        Some(dir) => dir.join(&path).normalize(),
+        None => RelativePathBuf::from(path).normalize(),
+    };
+    db.source_root(db.file_source_root(file_id)).files.get(&path).cloned()
+}
+
+/// Evaluates the comma separated arguments of a macro call, like `concat!`,
+/// to strings, and concatenates them.
+fn eval_str_args(tt: &ast::TokenTree, env: &Env) -> Option<String> {
+    let mut elements =
+        tt.syntax().children_with_tokens().filter(|it| !it.kind().is_trivia()).skip(1);
+    let mut res = String::new();
+    loop {
+        let element = elements.next()?;
+        match element.kind() {
+            STRING => res.push_str(&unquote(element)?),
+            IDENT => {
+                let name = element.as_token()?.text().clone();
+                if elements.next()?.kind() != EXCL {
+                    return None;
+                }
+                let args = elements.next()?.as_node().and_then(ast::TokenTree::cast)?;
+                let value = eval_str_args(args, env)?;
+                match name.as_str() {
+                    "concat" => res.push_str(&value),
+                    "env" => res.push_str(env.get(&value)?),
+                    _ => return None,
+                }
+            }
+            // The closing delimiter, after a trailing comma or without
+            // arguments.
+            R_PAREN | R_BRACK | R_CURLY => return Some(res),
+            _ => return None,
+        }
+        match elements.next()?.kind() {
+            COMMA => (),
+            R_PAREN | R_BRACK | R_CURLY => return Some(res),
+            _ => return None,
+        }
+    }
+}
+
+/// The value of a string literal without escapes.
+fn unquote(element: SyntaxElement) -> Option<String> {
+    let text = element.as_token()?.text();
+    if text.len() < 2 || !text.ends_with('"') || text.contains('\\') {
+        return None;
+    }
+    Some(text[1..text.len() - 1].to_string())
+}
@@ -9,10 +9,12 @@ use ra_syntax::{
     AstNode, SourceFile, AstPtr, TreeArc,
     ast::{self, NameOwner, AttrsOwner},
 };
+use rustc_hash::FxHashMap;
 
 use crate::{
     DefDatabase, Name, AsName, Path, HirFileId, ModuleSource,
     AstIdMap, FileAstId, Either,
+    cfg::CfgExpr,
 };
 
 /// `RawItems` is a set of top-level items in a file (except for impls).
@@ -27,6 +29,9 @@ pub struct RawItems {
     macros: Arena<Macro, MacroData>,
     /// items for top-level module
     items: Vec<RawItem>,
+    /// The `#[cfg(...)]` of the items which have one, which depends on the
+    /// crate the file is part of.
+    cfgs: FxHashMap<RawItem, CfgExpr>,
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -56,6 +61,13 @@ impl ImportSourceMap {
 
         self.map[import].to_node(file)
     }
+
+    pub(crate) fn use_tree_ptr(&self, import: ImportId) -> Option<AstPtr<ast::UseTree>> {
+        match self.map[import] {
+            Either::A(it) => Some(it),
+            Either::B(_) => None,
+        }
+    }
 }
 
 impl RawItems {
@@ -80,6 +92,10 @@ impl RawItems {
     pub(super) fn items(&self) -> &[RawItem] {
         &self.items
     }
+
+    pub(super) fn cfg(&self, item: RawItem) -> Option<&CfgExpr> {
+        self.cfgs.get(&item)
+    }
 }
 
 impl Index<Module> for RawItems {
@@ -110,7 +126,7 @@ impl Index<Macro> for RawItems {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub(super) enum RawItem {
     Module(Module),
     Import(ImportId),
@@ -183,9 +199,23 @@ struct RawItemsCollector {
 impl RawItemsCollector {
     fn process_module(&mut self, current_module: Option<Module>, body: &impl ast::ModuleItemOwner) {
         for item_or_macro in body.items_with_macros() {
-            match item_or_macro {
-                ast::ItemOrMacro::Macro(m) => self.add_macro(current_module, m),
-                ast::ItemOrMacro::Item(item) => self.add_item(current_module, item),
+            let start = self.items_mut(current_module).len();
+            let cfg = match item_or_macro {
+                ast::ItemOrMacro::Macro(m) => {
+                    self.add_macro(current_module, m);
+                    CfgExpr::from_attrs(m.syntax())
+                }
+                ast::ItemOrMacro::Item(item) => {
+                    self.add_item(current_module, item);
+                    CfgExpr::from_attrs(item.syntax())
+                }
+            };
+            if let Some(cfg) = cfg {
+                // A `use` item might have added several imports.
+                let added = self.items_mut(current_module)[start..].to_vec();
+                for item in added {
+                    self.raw_items.cfgs.insert(item, cfg.clone());
+                }
             }
         }
     }
@@ -316,6 +346,10 @@ impl RawItemsCollector {
     }
 
     fn push_item(&mut self, current_module: Option<Module>, item: RawItem) {
+        self.items_mut(current_module).push(item)
+    }
+
+    fn items_mut(&mut self, current_module: Option<Module>) -> &mut Vec<RawItem> {
         match current_module {
             Some(module) => match &mut self.raw_items.modules[module] {
                 ModuleData::Definition { items, .. } => items,
@@ -323,6 +357,5 @@ impl RawItemsCollector {
             },
             None => &mut self.raw_items.items,
         }
-        .push(item)
     }
 }
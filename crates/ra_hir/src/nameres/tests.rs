@@ -4,7 +4,7 @@ mod incremental;
 
 use std::sync::Arc;
 
-use ra_db::SourceDatabase;
+use ra_db::{SourceDatabase, CrateGraph, Edition, Env};
 use test_utils::covers;
 use insta::assert_snapshot_matches;
 
@@ -261,6 +261,29 @@ E: t
     );
 }
 
+#[test]
+fn crates_declared_in_fixture_metas() {
+    let map = def_map(
+        "
+        //- /main.rs crate:main deps:other_crate edition:2015
+        mod foo;
+
+        //- /foo.rs
+        use other_crate::FromLib;
+
+        //- /lib.rs crate:other_crate
+        struct FromLib;
+        ",
+    );
+    assert_snapshot_matches!(map, @r###"
+crate
+foo: t
+
+crate::foo
+FromLib: t v
+"###);
+}
+
 #[test]
 fn edition_2015_imports() {
     let map = def_map_with_crate_graph(
@@ -301,6 +324,53 @@ Bar: t v
     );
 }
 
+#[test]
+fn mixed_edition_imports() {
+    let map = def_map_with_crate_graph(
+        "
+        //- /main.rs
+        mod foo;
+        use other_crate::dyn::Dyn;
+
+        //- /foo.rs
+        mod bar;
+        use bar::Bar;
+
+        //- /foo/bar.rs
+        pub struct Bar;
+
+        //- /lib.rs
+        pub mod dyn;
+        mod async;
+
+        //- /dyn.rs
+        pub use async::Async as Dyn;
+
+        //- /async.rs
+        pub struct Async;
+        ",
+        crate_graph! {
+            "main": ("/main.rs", "2018", ["other_crate"]),
+            "other_crate": ("/lib.rs", "2015", []),
+        },
+    );
+
+    assert_snapshot_matches!(map,
+        @r###"
+crate
+Dyn: t v
+foo: t
+
+crate::foo
+bar: t
+Bar: t v
+
+crate::foo::bar
+Bar: t v
+"###
+    );
+}
+
 #[test]
 fn module_resolution_works_for_non_standard_filenames() {
     let map = def_map_with_crate_graph(
@@ -571,3 +641,113 @@ fn unresolved_module_diagnostics() {
 "###
     );
 }
+
+#[test]
+fn unresolved_import_diagnostics() {
+    let mut db = MockDatabase::with_files(
+        r"
+        //- /main.rs
+        use foo::{Baz, Qux};
+        use self::bar::Baz;
+        use dep::{Foo, Nope, some_macro};
+        use not_loaded::Foo;
+        mod foo {
+            pub struct Baz;
+        }
+
+        //- /lib.rs
+        pub struct Foo;
+        #[macro_export]
+        macro_rules! some_macro { () => {} }
+        ",
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["dep"]),
+        "dep": ("/lib.rs", []),
+    });
+
+    assert_snapshot_matches!(db.diagnostics(), @r###"
+"Nope": unresolved import
+"Qux": unresolved import
+"self::bar::Baz": unresolved import
+"###
+    );
+}
+
+#[test]
+fn cfg_attrs_are_evaluated_with_the_cfg_flags_of_the_crate() {
+    let fixture = r#"
+        //- /main.rs
+        #[cfg(test)]
+        mod tests;
+        #[cfg(not(test))]
+        struct NotTest;
+        #[cfg(all(unix, feature = "std"))]
+        use self::tests::Foo;
+        #[cfg(windows)]
+        fn windows() {}
+
+        //- /tests.rs
+        pub struct Foo;
+    "#;
+    let def_map_with_cfg = |cfg_options: ra_db::CfgOptions| {
+        let mut db = MockDatabase::with_files(fixture);
+        let mut crate_graph = ra_db::CrateGraph::default();
+        let crate_id =
+            crate_graph.add_crate_root(db.file_id_of("/main.rs"), ra_db::Edition::Edition2018);
+        crate_graph.set_cfg_options(crate_id, cfg_options);
+        db.set_crate_graph(Arc::new(crate_graph));
+        render_crate_def_map(&db.crate_def_map(Crate { crate_id }))
+    };
+
+    assert_snapshot_matches!(def_map_with_cfg(Default::default()), @r###"
+crate
+NotTest: t v
+"###);
+
+    let mut cfg_options = ra_db::CfgOptions::default();
+    cfg_options.insert_atom("test".into());
+    cfg_options.insert_atom("unix".into());
+    cfg_options.insert_key_value("feature".into(), "std".into());
+    assert_snapshot_matches!(def_map_with_cfg(cfg_options), @r###"
+crate
+tests: t
+Foo: t v
+
+crate::tests
+Foo: t v
+"###);
+}
+
+#[test]
+fn unresolved_macro_diagnostics() {
+    let mut db = MockDatabase::with_files(
+        r#"
+        //- /main.rs
+        macro_rules! local { () => {} }
+        local!();
+        from_dep!();
+        include!(concat!(env!("OUT_DIR"), "/gen.rs"));
+        nope!();
+        not_loaded::nope!();
+
+        //- /std.rs
+        #[prelude_import]
+        use prelude::*;
+        mod prelude {
+            pub enum Option<T> { Some(T), None }
+        }
+        #[macro_export]
+        macro_rules! from_dep { () => {} }
+        "#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+
+    assert_snapshot_matches!(db.diagnostics(), @r###"
+"nope!();": cannot find macro `nope!` in this scope
+"###
+    );
+}
@@ -92,3 +92,62 @@ Foo: t v
 Bar: t v
 "###);
 }
+
+#[test]
+fn recursion_limit_attribute() {
+    let map = def_map(
+        "
+        //- /lib.rs
+        #![recursion_limit = \"2\"]
+        macro_rules! nest {
+            ($name:ident x $($rest:ident)*) => { nest!($name $($rest)*); };
+            ($name:ident) => { struct $name; };
+        }
+        nest!(Shallow x);
+        nest!(Deep x x);
+        ",
+    );
+    assert_snapshot_matches!(map, @r###"
+crate
+Shallow: t v
+"###);
+}
+
+#[test]
+fn include_collects_items_into_the_module() {
+    let mut db = MockDatabase::with_files(
+        r#"
+        //- /lib.rs
+        include!("inc.rs");
+        include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+        mod m {
+            include!(concat!(env!("OUT_DIR"), "/", "missing.rs"));
+        }
+
+        //- /inc.rs
+        struct Included;
+
+        //- /out/generated.rs
+        pub fn generated() {}
+        "#,
+    );
+    let root = db.file_id_of("/lib.rs");
+    let generated = db.file_id_of("/out/generated.rs");
+    let mut crate_graph = CrateGraph::default();
+    let crate_id = crate_graph.add_crate_root(root, Edition::Edition2018);
+    let mut env = Env::default();
+    env.set("OUT_DIR", "/home/user/target/debug/build/foo-123/out".to_string());
+    crate_graph.set_env(crate_id, env);
+    crate_graph.add_out_dir_file(crate_id, "generated.rs".into(), generated);
+    db.set_crate_graph(Arc::new(crate_graph));
+
+    let map = db.crate_def_map(Crate { crate_id });
+    assert_snapshot_matches!(render_crate_def_map(&map), @r###"
+crate
+generated: v
+Included: t v
+m: t
+
+crate::m
+"###);
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use ra_syntax::{ast::{self, NameOwner}, AstNode};
+use ra_syntax::{ast::{self, NameOwner, TypeAscriptionOwner}, AstNode};
 
 use crate::{Name, AsName, type_ref::TypeRef};
 
@@ -16,13 +16,17 @@ pub struct PathSegment {
     pub args_and_bindings: Option<Arc<GenericArgs>>,
 }
 
-/// Generic arguments to a path segment (e.g. the `i32` in `Option<i32>`). This
-/// can (in the future) also include bindings of associated types, like in
-/// `Iterator<Item = Foo>`.
+/// Generic arguments to a path segment (e.g. the `i32` in `Option<i32>`),
+/// including bindings of associated types, like in `Iterator<Item = Foo>`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct GenericArgs {
     pub args: Vec<GenericArg>,
-    // someday also bindings
+    /// Bindings of associated types. Only the `Output` of the `Fn(A) -> B`
+    /// sugar is recorded so far.
+    pub bindings: Vec<(Name, TypeRef)>,
+    /// Whether the first argument is the `Self` type of a trait, as in
+    /// `<T as Trait>::Item`, which is represented as `Trait<T>::Item`.
+    pub has_self_type: bool,
 }
 
 /// A single generic argument.
@@ -66,8 +70,16 @@ impl Path {
 
             match segment.kind()? {
                 ast::PathSegmentKind::Name(name) => {
-                    let args =
-                        segment.type_arg_list().and_then(GenericArgs::from_ast).map(Arc::new);
+                    let args = segment
+                        .type_arg_list()
+                        .and_then(GenericArgs::from_ast)
+                        .or_else(|| {
+                            GenericArgs::from_fn_like_path_ast(
+                                segment.param_list()?,
+                                segment.ret_type(),
+                            )
+                        })
+                        .map(Arc::new);
                     let segment = PathSegment { name: name.as_name(), args_and_bindings: args };
                     segments.push(segment);
                 }
@@ -83,6 +95,26 @@ impl Path {
                     kind = PathKind::Super;
                     break;
                 }
+                ast::PathSegmentKind::Type { type_ref, trait_ref } => {
+                    // FIXME: `<T>::Item` paths without a trait
+                    let mut prefix = Path::from_ast(trait_ref?.path()?)?;
+                    let self_type = GenericArg::Type(TypeRef::from_ast_opt(type_ref));
+                    let last = prefix.segments.last_mut()?;
+                    let mut args = match &last.args_and_bindings {
+                        Some(args) => (**args).clone(),
+                        None => GenericArgs {
+                            args: Vec::new(),
+                            bindings: Vec::new(),
+                            has_self_type: false,
+                        },
+                    };
+                    args.args.insert(0, self_type);
+                    args.has_self_type = true;
+                    last.args_and_bindings = Some(Arc::new(args));
+                    segments.reverse();
+                    prefix.segments.extend(segments);
+                    return Some(prefix);
+                }
             }
             path = match qualifier(path) {
                 Some(it) => it,
@@ -137,11 +169,29 @@ impl GenericArgs {
         }
         // lifetimes and assoc type args ignored for now
         if args.len() > 0 {
-            Some(GenericArgs { args })
+            Some(GenericArgs { args, bindings: Vec::new(), has_self_type: false })
         } else {
             None
         }
     }
+
+    /// Desugars the arguments of `Fn(A, B) -> C` to `Fn<(A, B), Output = C>`.
+    pub(crate) fn from_fn_like_path_ast(
+        params: &ast::ParamList,
+        ret_type: Option<&ast::RetType>,
+    ) -> Option<GenericArgs> {
+        let params =
+            params.params().map(|param| TypeRef::from_ast_opt(param.ascribed_type())).collect();
+        let ret_type = match ret_type.and_then(|it| it.type_ref()) {
+            Some(it) => TypeRef::from_ast(it),
+            None => TypeRef::unit(),
+        };
+        Some(GenericArgs {
+            args: vec![GenericArg::Type(TypeRef::Tuple(params))],
+            bindings: vec![(Name::output_type(), ret_type)],
+            has_self_type: false,
+        })
+    }
 }
 
 impl From<Name> for Path {
@@ -229,6 +279,10 @@ fn convert_path(prefix: Option<Path>, path: &ast::Path) -> Option<Path> {
             }
             Path { kind: PathKind::Super, segments: Vec::new() }
         }
+        ast::PathSegmentKind::Type { .. } => {
+            // not allowed in imports
+            return None;
+        }
     };
     Some(res)
 }
@@ -2,16 +2,18 @@
 use std::sync::Arc;
 
 use rustc_hash::FxHashMap;
+use ra_syntax::SmolStr;
 
 use crate::{
     ModuleDef,
     db::HirDatabase,
-    name::{Name, KnownName},
+    name::{Name, KnownName, AsName},
     nameres::{PerNs, CrateDefMap, CrateModuleId},
-    generics::GenericParams,
-    expr::{scope::{ExprScopes, ScopeId}, PatId},
+    generics::{GenericParams, WherePredicate},
+    expr::{scope::{ExprScopes, ScopeEntry, ScopeId}, hygiene::SyntaxContext, PatId},
     impl_block::ImplBlock,
-    path::Path, Trait
+    path::{Path, PathKind, PathSegment},
+    Trait, Crate,
 };
 
 #[derive(Debug, Clone, Default)]
@@ -30,6 +32,8 @@ pub(crate) struct ModuleItemMap {
 pub(crate) struct ExprScope {
     expr_scopes: Arc<ExprScopes>,
     scope_id: ScopeId,
+    /// Only the bindings of this context are visible.
+    ctx: SyntaxContext,
 }
 
 #[derive(Debug, Clone)]
@@ -135,6 +139,14 @@ impl Resolver {
             PathResult::from_resolution(self.resolve_name(db, name))
         } else if path.is_self() {
             PathResult::from_resolution(self.resolve_name(db, &Name::self_param()))
+        } else if path.kind == PathKind::Plain
+            && path.segments[0].name.as_known_name() == Some(KnownName::SelfType)
+        {
+            // `Self::Item` starts from the self type of the enclosing impl
+            match self.resolve_name(db, &path.segments[0].name).types {
+                Some(res) => PathResult::from_resolution_with_index(PerNs::types(res), 1),
+                None => PathResult::empty(),
+            }
         } else {
             let (item_map, module) = match self.module() {
                 Some(m) => m,
@@ -159,6 +171,29 @@ impl Resolver {
         self.resolve_path_segments(db, path).into_fully_resolved()
     }
 
+    /// Resolves the trait at the absolute path `segments`, like
+    /// `["core", "default", "Default"]`, which starts from the extern prelude.
+    pub(crate) fn resolve_known_trait(
+        &self,
+        db: &impl HirDatabase,
+        segments: &[&str],
+    ) -> Option<Trait> {
+        let path = Path {
+            kind: PathKind::Abs,
+            segments: segments
+                .iter()
+                .map(|it| PathSegment {
+                    name: SmolStr::new(*it).as_name(),
+                    args_and_bindings: None,
+                })
+                .collect(),
+        };
+        match self.resolve_path(db, &path).take_types()? {
+            Resolution::Def(ModuleDef::Trait(it)) => Some(it),
+            _ => None,
+        }
+    }
+
     pub(crate) fn all_names(&self, db: &impl HirDatabase) -> FxHashMap<Name, PerNs<Resolution>> {
         let mut names = FxHashMap::default();
         for scope in self.scopes.iter().rev() {
@@ -190,6 +225,39 @@ impl Resolver {
             .flatten()
     }
 
+    /// The where clauses and bounds of all generic parameters in scope.
+    pub(crate) fn where_predicates_in_scope<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = &'a WherePredicate> + 'a {
+        self.scopes
+            .iter()
+            .filter_map(|scope| match scope {
+                Scope::GenericParams(params) => Some(params),
+                _ => None,
+            })
+            .flat_map(|params| params.where_predicates.iter())
+    }
+
+    /// Whether names which can't be resolved in this scope are known to be
+    /// errors, see `CrateDefMap::is_complete`.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.module().map_or(false, |(def_map, _)| def_map.is_complete())
+    }
+
+    pub(crate) fn krate(&self) -> Option<Crate> {
+        self.module().map(|(def_map, module_id)| def_map.mk_module(module_id).krate)
+    }
+
+    /// The traits of the prelude, which are in scope everywhere.
+    pub(crate) fn prelude_traits(&self, db: &impl HirDatabase) -> Vec<Trait> {
+        match self.module().and_then(|(def_map, _)| def_map.prelude()) {
+            Some(prelude) => {
+                db.crate_def_map(prelude.krate)[prelude.module_id].scope.traits().collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
     fn module(&self) -> Option<(&CrateDefMap, CrateModuleId)> {
         self.scopes.iter().rev().find_map(|scope| match scope {
             Scope::ModuleScope(m) => Some((&*m.crate_def_map, m.module_id)),
@@ -225,8 +293,20 @@ impl Resolver {
         self,
         expr_scopes: Arc<ExprScopes>,
         scope_id: ScopeId,
+        ctx: SyntaxContext,
     ) -> Resolver {
-        self.push_scope(Scope::ExprScope(ExprScope { expr_scopes, scope_id }))
+        self.push_scope(Scope::ExprScope(ExprScope { expr_scopes, scope_id, ctx }))
+    }
+}
+
+impl ExprScope {
+    /// The local bindings of the scope which are visible from `self.ctx`.
+    fn entries(&self) -> impl Iterator<Item = &ScopeEntry> {
+        let body = self.expr_scopes.body();
+        self.expr_scopes
+            .entries(self.scope_id)
+            .iter()
+            .filter(move |entry| body.pat_syntax_context(entry.pat()) == self.ctx)
     }
 }
 
@@ -254,10 +334,13 @@ impl Scope {
                 }
             }
             Scope::ExprScope(e) => {
-                let entry =
-                    e.expr_scopes.entries(e.scope_id).iter().find(|entry| entry.name() == name);
-                match entry {
-                    Some(e) => PerNs::values(Resolution::LocalBinding(e.pat())),
+                let entry = e.entries().find(|entry| entry.name() == name);
+                if let Some(entry) = entry {
+                    return PerNs::values(Resolution::LocalBinding(entry.pat()));
+                }
+                let item = e.expr_scopes.items(e.scope_id).iter().find(|(n, _)| n == name);
+                match item {
+                    Some((_, def)) => PerNs::values(Resolution::Def(*def)),
                     None => PerNs::none(),
                 }
             }
@@ -296,9 +379,12 @@ impl Scope {
                 f(Name::self_type(), PerNs::types(Resolution::SelfType(i.clone())));
             }
             Scope::ExprScope(e) => {
-                e.expr_scopes.entries(e.scope_id).iter().for_each(|e| {
+                e.entries().for_each(|e| {
                     f(e.name().clone(), PerNs::values(Resolution::LocalBinding(e.pat())));
                 });
+                e.expr_scopes.items(e.scope_id).iter().for_each(|(name, def)| {
+                    f(name.clone(), PerNs::values(Resolution::Def(*def)));
+                });
             }
         }
     }
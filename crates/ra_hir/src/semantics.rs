@@ -0,0 +1,359 @@
+//! `Semantics` is the entry point of the IDE into the HIR: it maps syntax
+//! nodes to their HIR counterparts, looking through macro expansions.
+//!
+//! Every tree handed out by `Semantics` remembers the file it comes from,
+//! which is either a real file or a macro expansion, so nodes of expansions can
+//! be mapped back to the original source. Expansions of macro calls in
+//! expression position are not files: they remember their call instead, and
+//! how their tokens map to the tokens of the call.
+use std::cell::RefCell;
+
+use ra_db::{FileId, FileRange};
+use ra_syntax::{
+    AstPtr, SourceFile, SyntaxNode, TreeArc, TextRange,
+    ast::{self, AstNode},
+};
+
+use crate::{
+    HirDatabase, HirFileId, Crate, Either, ImplBlock, ImplItem, ModuleDef, PathResolution,
+    SourceAnalyzer, StructField, Ty, Attrs, Deprecation,
+    expr::{ExprId, hygiene::TokenMapping},
+};
+
+/// The definition a name refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Definition {
+    /// An item, including methods.
+    Def(ModuleDef),
+    Field(StructField),
+    AssocItem(ImplItem),
+    /// A local binding, in the original file of the reference.
+    Local(Either<AstPtr<ast::Pat>, AstPtr<ast::SelfParam>>),
+    GenericParam(u32),
+    SelfType(ImplBlock),
+}
+
+impl Attrs for Definition {
+    fn deprecation(&self, db: &impl HirDatabase) -> Option<Deprecation> {
+        match self {
+            Definition::Def(it) => it.deprecation(db),
+            Definition::Field(it) => it.deprecation(db),
+            Definition::AssocItem(it) => it.deprecation(db),
+            Definition::Local(_) | Definition::GenericParam(_) | Definition::SelfType(_) => None,
+        }
+    }
+}
+
+pub struct Semantics<'db, DB> {
+    db: &'db DB,
+    roots: RefCell<Vec<(TreeArc<SourceFile>, HirFileId)>>,
+    expr_expansions: RefCell<Vec<ExprExpansion>>,
+}
+
+struct ExprExpansion {
+    expr: TreeArc<ast::Expr>,
+    /// The macro call, in a tree known to `Semantics`.
+    call: TreeArc<ast::MacroCall>,
+    mapping: TokenMapping,
+}
+
+impl<'db, DB: HirDatabase> Semantics<'db, DB> {
+    pub fn new(db: &'db DB) -> Semantics<'db, DB> {
+        Semantics { db, roots: RefCell::new(Vec::new()), expr_expansions: RefCell::new(Vec::new()) }
+    }
+
+    pub fn parse(&self, file_id: FileId) -> TreeArc<SourceFile> {
+        let file = self.db.parse(file_id);
+        self.cache(&file, file_id.into());
+        file
+    }
+
+    /// Expands the macro call in item position, returning the items it
+    /// produces.
+    pub fn expand(&self, macro_call: &ast::MacroCall) -> Option<TreeArc<SourceFile>> {
+        let file_id = self.find_file(macro_call.syntax())?;
+        let ast_id = self.db.ast_id_map(file_id).ast_id(macro_call).with_file_id(file_id);
+        let source_root = self.db.file_source_root(file_id.original_file(self.db));
+        let macro_call_id =
+            self.db.source_root_crates(source_root).iter().find_map(|&crate_id| {
+                self.db.crate_def_map(Crate { crate_id }).macro_call(ast_id)
+            })?;
+        let file_id = macro_call_id.into();
+        let file = self.db.hir_parse(file_id);
+        self.cache(&file, file_id);
+        Some(file)
+    }
+
+    /// Expands the macro call in expression position, returning the expanded
+    /// expression.
+    pub fn expand_expr(&self, macro_call: &ast::MacroCall) -> Option<TreeArc<ast::Expr>> {
+        self.expand_expr_speculative(macro_call, macro_call)
+    }
+
+    /// Expands `speculative_call`, a copy of the macro call in expression
+    /// position `macro_call` with edits in its arguments, like the identifier
+    /// completion inserts at the cursor. The expansion is analyzed as if it
+    /// came from `macro_call`, and its tokens map to the tokens of the copy.
+    pub fn expand_expr_speculative(
+        &self,
+        macro_call: &ast::MacroCall,
+        speculative_call: &ast::MacroCall,
+    ) -> Option<TreeArc<ast::Expr>> {
+        let analyzer = self.analyze(macro_call.syntax())?;
+        let (expr, mapping) = analyzer.expand_expr_macro(self.db, speculative_call)?;
+        let call = macro_call.to_owned();
+        self.expr_expansions.borrow_mut().push(ExprExpansion { expr: expr.clone(), call, mapping });
+        Some(expr)
+    }
+
+    /// The range in the expression macro expansion `expansion` of the token at
+    /// `range` in the arguments of the call.
+    pub fn map_token_down(&self, expansion: &ast::Expr, range: TextRange) -> Option<TextRange> {
+        self.with_expr_expansion(expansion.syntax(), |it| it.mapping.map_down(range))?
+    }
+
+    /// The node of a real file `node` comes from: the node itself or the
+    /// outermost macro call which expanded to it.
+    pub fn original_node(&self, node: &SyntaxNode) -> Option<(FileId, TreeArc<SyntaxNode>)> {
+        let mut node = node.to_owned();
+        while let Some(call) = self.with_expr_expansion(&node, |it| it.call.clone()) {
+            node = call.syntax().to_owned();
+        }
+        let mut file_id = self.find_file(&node)?;
+        while let Some(macro_call) = file_id.macro_call(self.db) {
+            node = macro_call.to_node(self.db).syntax().to_owned();
+            file_id = macro_call.file_id();
+        }
+        Some((file_id.as_original_file(), node))
+    }
+
+    pub fn original_range(&self, node: &SyntaxNode) -> Option<FileRange> {
+        let (file_id, node) = self.original_node(node)?;
+        Some(FileRange { file_id, range: node.range() })
+    }
+
+    /// Analyzes the code around `node`. Inside of macro expansions, names are
+    /// resolved in the scope of the macro call.
+    pub fn analyze(&self, node: &SyntaxNode) -> Option<SourceAnalyzer> {
+        let (file_id, node) = self.original_node(node)?;
+        Some(SourceAnalyzer::new(self.db, file_id, &node, None))
+    }
+
+    pub fn type_of_expr(&self, expr: &ast::Expr) -> Option<Ty> {
+        let analyzer = self.analyze(expr.syntax())?;
+        analyzer.type_of_expr_id(self.expr_id(&analyzer, expr)?)
+    }
+
+    pub fn resolve_name_ref(&self, name_ref: &ast::NameRef) -> Option<Definition> {
+        let analyzer = self.analyze(name_ref.syntax())?;
+        let parent = name_ref.syntax().parent()?;
+
+        if let Some(method_call) = ast::MethodCallExpr::cast(parent) {
+            let expr = self.expr_id(&analyzer, method_call.into());
+            if let Some(func) = expr.and_then(|it| analyzer.method_resolution(it)) {
+                return Some(Definition::Def(func.into()));
+            }
+        }
+        if let Some(field_expr) = ast::FieldExpr::cast(parent) {
+            let expr = self.expr_id(&analyzer, field_expr.into());
+            if let Some(field) = expr.and_then(|it| analyzer.field_resolution(it)) {
+                return Some(Definition::Field(field));
+            }
+        }
+        if let Some(field) = ast::NamedField::cast(parent) {
+            let expr = match field.expr() {
+                Some(expr) => self.expr_id(&analyzer, expr),
+                None => analyzer.shorthand_field_expr(name_ref),
+            };
+            if let Some(field) = expr.and_then(|it| analyzer.record_field_resolution(it)) {
+                return Some(Definition::Field(field));
+            }
+        }
+
+        let path = name_ref.syntax().ancestors().find_map(ast::Path::cast)?;
+        let res = match analyzer.resolve_path(self.db, path)? {
+            PathResolution::Def(def) => Definition::Def(def),
+            PathResolution::LocalBinding(pat) => Definition::Local(pat),
+            PathResolution::GenericParam(idx) => Definition::GenericParam(idx),
+            PathResolution::SelfType(impl_block) => Definition::SelfType(impl_block),
+            PathResolution::AssocItem(item) => Definition::AssocItem(item),
+        };
+        Some(res)
+    }
+
+    /// The field matched by the field pattern named `name`, either
+    /// `S { field: pat }` or the shorthand `S { field }`.
+    pub fn resolve_field_pat(&self, name: &ast::Name) -> Option<StructField> {
+        let parent = name.syntax().parent()?;
+        let pat = if let Some(field_pat) = ast::FieldPat::cast(parent) {
+            field_pat.pat()?
+        } else {
+            let bind_pat = ast::BindPat::cast(parent)?;
+            ast::FieldPatList::cast(bind_pat.syntax().parent()?)?;
+            bind_pat.into()
+        };
+        self.analyze(name.syntax())?.record_pat_field_resolution(pat)
+    }
+
+    /// The expression of the body `expr` is lowered to. Expressions of
+    /// expression macro expansions are only known if they are written in the
+    /// arguments of the calls.
+    fn expr_id(&self, analyzer: &SourceAnalyzer, expr: &ast::Expr) -> Option<ExprId> {
+        if self.with_expr_expansion(expr.syntax(), |_| ()).is_none() {
+            return analyzer.expr_id(expr);
+        }
+        let first = self.map_token_up(expr.syntax(), expr.syntax().first_token()?.range())?;
+        let last = self.map_token_up(expr.syntax(), expr.syntax().last_token()?.range())?;
+        if first.start() > last.end() {
+            return None;
+        }
+        analyzer.macro_arg_expr(TextRange::from_to(first.start(), last.end()))
+    }
+
+    /// The range in the file of the token at `range` in the tree of `node`,
+    /// through the expression macro expansions the tree comes from.
+    fn map_token_up(&self, node: &SyntaxNode, mut range: TextRange) -> Option<TextRange> {
+        let mut node = node.to_owned();
+        while let Some((call, mapped)) =
+            self.with_expr_expansion(&node, |it| (it.call.clone(), it.mapping.map_up(range)))
+        {
+            range = mapped?;
+            node = call.syntax().to_owned();
+        }
+        Some(range)
+    }
+
+    fn with_expr_expansion<T>(
+        &self,
+        node: &SyntaxNode,
+        f: impl FnOnce(&ExprExpansion) -> T,
+    ) -> Option<T> {
+        let root = node.ancestors().last()?;
+        let expansions = self.expr_expansions.borrow();
+        expansions.iter().find(|it| std::ptr::eq(it.expr.syntax(), root)).map(f)
+    }
+
+    fn cache(&self, file: &SourceFile, file_id: HirFileId) {
+        let mut roots = self.roots.borrow_mut();
+        if !roots.iter().any(|(it, _)| std::ptr::eq(&**it, file)) {
+            roots.push((file.to_owned(), file_id));
+        }
+    }
+
+    fn find_file(&self, node: &SyntaxNode) -> Option<HirFileId> {
+        let root = node.ancestors().last()?;
+        let roots = self.roots.borrow();
+        roots.iter().find(|(it, _)| std::ptr::eq(it.syntax(), root)).map(|&(_, file_id)| file_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::algo::{find_covering_element, find_node_at_offset, find_token_at_offset};
+
+    use crate::{mock::MockDatabase, HirDisplay};
+
+    use super::*;
+
+    #[test]
+    fn resolves_names_in_macro_expansions() {
+        let (db, pos) = MockDatabase::with_position(
+            r"
+            //- /lib.rs
+            macro_rules! foo {
+                ($name:ident) => { fn $name() -> Bar { Bar } }
+            }
+            struct Bar;
+            foo!(<|>baz);
+            ",
+        );
+        let sema = Semantics::new(&db);
+        let file = sema.parse(pos.file_id);
+        let macro_call = find_node_at_offset::<ast::MacroCall>(file.syntax(), pos.offset).unwrap();
+        let expansion = sema.expand(macro_call).unwrap();
+        let name_ref = expansion
+            .syntax()
+            .descendants()
+            .filter_map(ast::NameRef::cast)
+            .find(|it| it.text() == "Bar")
+            .unwrap();
+
+        let original = sema.original_range(name_ref.syntax()).unwrap();
+        assert_eq!(original.file_id, pos.file_id);
+        assert_eq!(original.range, macro_call.syntax().range());
+        match sema.resolve_name_ref(name_ref) {
+            Some(Definition::Def(ModuleDef::Struct(s))) => {
+                assert_eq!(s.name(&db).unwrap().to_string(), "Bar")
+            }
+            res => panic!("unexpected resolution: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn analyzes_arguments_of_expression_macros() {
+        let (db, pos) = MockDatabase::with_position(
+            r"
+            //- /lib.rs
+            macro_rules! id {
+                ($e:expr) => { $e }
+            }
+            struct S { x: u32 }
+            fn foo(s: S) -> u32 {
+                id!(s<|>.x)
+            }
+            ",
+        );
+        let sema = Semantics::new(&db);
+        let file = sema.parse(pos.file_id);
+        let macro_call = find_node_at_offset::<ast::MacroCall>(file.syntax(), pos.offset).unwrap();
+        let expansion = sema.expand_expr(macro_call).unwrap();
+        let token = find_token_at_offset(file.syntax(), pos.offset).left_biased().unwrap();
+        let range = sema.map_token_down(&expansion, token.range()).unwrap();
+        let local = find_covering_element(expansion.syntax(), range)
+            .ancestors()
+            .find_map(ast::NameRef::cast)
+            .unwrap();
+        match sema.resolve_name_ref(local) {
+            Some(Definition::Local(_)) => (),
+            res => panic!("unexpected resolution: {:?}", res),
+        }
+        let field = expansion.syntax().descendants().filter_map(ast::NameRef::cast).last().unwrap();
+        match sema.resolve_name_ref(field) {
+            Some(Definition::Field(f)) => assert_eq!(f.name(&db).to_string(), "x"),
+            res => panic!("unexpected resolution: {:?}", res),
+        }
+        let ty = sema.type_of_expr(&expansion).unwrap();
+        assert_eq!(ty.display(&db).to_string(), "u32");
+    }
+
+    #[test]
+    fn resolves_fields_and_locals() {
+        let (db, pos) = MockDatabase::with_position(
+            r"
+            //- /lib.rs
+            struct S { x: u32 }
+            fn foo(s: S) -> u32 {
+                let y = s.x;
+                y<|>
+            }
+            ",
+        );
+        let sema = Semantics::new(&db);
+        let file = sema.parse(pos.file_id);
+        let name_refs: Vec<_> =
+            file.syntax().descendants().filter_map(ast::NameRef::cast).collect();
+        let field = name_refs.iter().find(|it| it.text() == "x").unwrap();
+        match sema.resolve_name_ref(field) {
+            Some(Definition::Field(f)) => assert_eq!(f.name(&db).to_string(), "x"),
+            res => panic!("unexpected resolution: {:?}", res),
+        }
+        let local = find_node_at_offset::<ast::NameRef>(file.syntax(), pos.offset).unwrap();
+        match sema.resolve_name_ref(local) {
+            Some(Definition::Local(_)) => (),
+            res => panic!("unexpected resolution: {:?}", res),
+        }
+        let tail = file.syntax().descendants().filter_map(ast::Block::cast).last().unwrap();
+        let ty = sema.type_of_expr(tail.expr().unwrap()).unwrap();
+        assert_eq!(ty.display(&db).to_string(), "u32");
+    }
+}
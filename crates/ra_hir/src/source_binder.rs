@@ -10,18 +10,24 @@ use std::sync::Arc;
 use rustc_hash::{FxHashSet, FxHashMap};
 use ra_db::{FileId, FilePosition};
 use ra_syntax::{
-    SyntaxNode, AstPtr, TextUnit, SyntaxNodePtr, TextRange,
+    SyntaxNode, AstPtr, TextUnit, SyntaxNodePtr, TextRange, TreeArc, SmolStr,
     ast::{self, AstNode, NameOwner},
     algo::find_node_at_offset,
     SyntaxKind::*,
 };
 
 use crate::{
-    HirDatabase, Function, Struct, Enum, Const, Static, Either, DefWithBody, PerNs, Name,
-    AsName, Module, HirFileId, Crate, Trait, Resolver, Ty,
-    expr::{BodySourceMap, scope::{ScopeId, ExprScopes}},
+    HirDatabase, Function, Struct, Enum, Const, Static, Either, DefWithBody, PerNs, Name, AsName,
+    Module, HirFileId, Crate, Trait, Resolver, Ty, TypeAlias, ImplItem, TraitItem,
+    expr::{
+        BodySourceMap, ClosureCapture, ExprId, PatId,
+        hygiene::TokenMapping,
+        scope::{ScopeId, ExprScopes},
+    },
     ids::LocationCtx,
-    expr, AstId
+    ty::TraitRef,
+    type_ref::TypeRef,
+    expr, AstId,
 };
 
 /// Locates the module by `FileId`. Picks topmost module in the file.
@@ -121,6 +127,34 @@ pub fn trait_from_module(
     Trait { id: ctx.to_def(trait_def) }
 }
 
+/// The item of an impl block for its syntax, within `module`.
+pub fn impl_item_from_module(
+    db: &impl HirDatabase,
+    module: Module,
+    impl_item: &ast::ImplItem,
+) -> ImplItem {
+    let (file_id, _) = module.definition_source(db);
+    let ctx = LocationCtx::new(db, module, file_id);
+    match impl_item.kind() {
+        ast::ImplItemKind::FnDef(it) => Function { id: ctx.to_def(it) }.into(),
+        ast::ImplItemKind::ConstDef(it) => Const { id: ctx.to_def(it) }.into(),
+        ast::ImplItemKind::TypeAliasDef(it) => TypeAlias { id: ctx.to_def(it) }.into(),
+    }
+}
+
+/// The item of a trait for its syntax, within `module`.
+pub fn trait_item_from_module(
+    db: &impl HirDatabase,
+    module: Module,
+    trait_item: &ast::ImplItem,
+) -> TraitItem {
+    match impl_item_from_module(db, module, trait_item) {
+        ImplItem::Method(it) => it.into(),
+        ImplItem::Const(it) => it.into(),
+        ImplItem::TypeAlias(it) => it.into(),
+    }
+}
+
 fn try_get_resolver_for_node(
     db: &impl HirDatabase,
     file_id: FileId,
@@ -144,7 +178,7 @@ fn try_get_resolver_for_node(
     }
 }
 
-fn def_with_body_from_child_node(
+pub fn def_with_body_from_child_node(
     db: &impl HirDatabase,
     file_id: FileId,
     node: &SyntaxNode,
@@ -170,6 +204,7 @@ fn def_with_body_from_child_node(
 #[derive(Debug)]
 pub struct SourceAnalyzer {
     resolver: Resolver,
+    body_owner: Option<DefWithBody>,
     body_source_map: Option<Arc<BodySourceMap>>,
     infer: Option<Arc<crate::ty::InferenceResult>>,
     scopes: Option<Arc<crate::expr::ExprScopes>>,
@@ -227,6 +262,7 @@ impl SourceAnalyzer {
             let resolver = expr::resolver_for_scope(def.body(db), db, scope);
             SourceAnalyzer {
                 resolver,
+                body_owner: Some(def),
                 body_source_map: Some(source_map),
                 infer: Some(def.infer(db)),
                 scopes: Some(scopes),
@@ -237,6 +273,7 @@ impl SourceAnalyzer {
                     .ancestors()
                     .find_map(|node| try_get_resolver_for_node(db, file_id, node))
                     .unwrap_or_default(),
+                body_owner: None,
                 body_source_map: None,
                 infer: None,
                 scopes: None,
@@ -245,8 +282,17 @@ impl SourceAnalyzer {
     }
 
     pub fn type_of(&self, _db: &impl HirDatabase, expr: &ast::Expr) -> Option<crate::Ty> {
-        let expr_id = self.body_source_map.as_ref()?.node_expr(expr)?;
-        Some(self.infer.as_ref()?[expr_id].clone())
+        self.type_of_expr_id(self.expr_id(expr)?)
+    }
+
+    /// The type of the local binding named by the field shorthand `name_ref`
+    /// of a struct literal.
+    pub fn type_of_field_shorthand(
+        &self,
+        _db: &impl HirDatabase,
+        name_ref: &ast::NameRef,
+    ) -> Option<crate::Ty> {
+        self.type_of_expr_id(self.shorthand_field_expr(name_ref)?)
     }
 
     pub fn type_of_pat(&self, _db: &impl HirDatabase, pat: &ast::Pat) -> Option<crate::Ty> {
@@ -254,14 +300,87 @@ impl SourceAnalyzer {
         Some(self.infer.as_ref()?[pat_id].clone())
     }
 
+    /// The type of the local binding `pat`, resolved by this analyzer.
+    pub fn type_of_binding(&self, pat: PatId) -> Option<crate::Ty> {
+        Some(self.infer.as_ref()?[pat].clone())
+    }
+
+    /// Whether `ty` implements the trait `bound`, like `Into<String>`, which
+    /// is resolved in the scope of this analyzer. Returns `None` if `bound`
+    /// isn't a trait.
+    pub fn implements(
+        &self,
+        db: &impl HirDatabase,
+        ty: crate::Ty,
+        bound: &ast::TypeRef,
+    ) -> Option<bool> {
+        let type_ref = TypeRef::from_ast(bound);
+        let trait_ref = TraitRef::from_hir(db, &self.resolver, &type_ref, Some(ty.clone()))?;
+        Some(ty != Ty::Unknown && db.implements(trait_ref).is_some())
+    }
+
+    /// Whether `ty` implements `Default`, as resolved in the scope of this
+    /// analyzer.
+    pub fn is_default(&self, db: &impl HirDatabase, ty: &crate::Ty) -> bool {
+        ty.is_default(db, &self.resolver)
+    }
+
     pub fn resolve_method_call(&self, call: &ast::MethodCallExpr) -> Option<Function> {
-        let expr_id = self.body_source_map.as_ref()?.node_expr(call.into())?;
-        self.infer.as_ref()?.method_resolution(expr_id)
+        self.method_resolution(self.expr_id(call.into())?)
     }
 
     pub fn resolve_field(&self, field: &ast::FieldExpr) -> Option<crate::StructField> {
-        let expr_id = self.body_source_map.as_ref()?.node_expr(field.into())?;
-        self.infer.as_ref()?.field_resolution(expr_id)
+        self.field_resolution(self.expr_id(field.into())?)
+    }
+
+    pub(crate) fn expr_id(&self, expr: &ast::Expr) -> Option<ExprId> {
+        self.body_source_map.as_ref()?.node_expr(expr)
+    }
+
+    /// The expression of the body written at `range` in the arguments of an
+    /// expression macro call.
+    pub(crate) fn macro_arg_expr(&self, range: TextRange) -> Option<ExprId> {
+        self.body_source_map.as_ref()?.macro_arg_expr(range)
+    }
+
+    pub(crate) fn type_of_expr_id(&self, expr: ExprId) -> Option<crate::Ty> {
+        Some(self.infer.as_ref()?[expr].clone())
+    }
+
+    pub(crate) fn method_resolution(&self, expr: ExprId) -> Option<Function> {
+        self.infer.as_ref()?.method_resolution(expr)
+    }
+
+    pub(crate) fn field_resolution(&self, expr: ExprId) -> Option<crate::StructField> {
+        self.infer.as_ref()?.field_resolution(expr)
+    }
+
+    pub(crate) fn record_field_resolution(&self, expr: ExprId) -> Option<crate::StructField> {
+        self.infer.as_ref()?.record_field_resolution_for_expr(expr)
+    }
+
+    pub(crate) fn record_pat_field_resolution(&self, pat: &ast::Pat) -> Option<crate::StructField> {
+        let pat_id = self.body_source_map.as_ref()?.node_pat(pat)?;
+        self.infer.as_ref()?.record_field_resolution_for_pat(pat_id)
+    }
+
+    /// The expression a field shorthand of a struct literal is lowered to.
+    pub(crate) fn shorthand_field_expr(&self, name_ref: &ast::NameRef) -> Option<ExprId> {
+        self.body_source_map.as_ref()?.syntax_expr(SyntaxNodePtr::new(name_ref.syntax()))
+    }
+
+    /// Expands the macro `call` in expression position, in the scope of this
+    /// analyzer. The call may be a copy of a call of the body with edits in
+    /// its arguments.
+    pub(crate) fn expand_expr_macro(
+        &self,
+        db: &impl HirDatabase,
+        call: &ast::MacroCall,
+    ) -> Option<(TreeArc<ast::Expr>, TokenMapping)> {
+        let path = crate::Path::from_ast(call.path()?)?;
+        let krate = self.resolver.krate()?;
+        let macro_def = db.crate_def_map(krate).resolve_macro(db, &path)?;
+        expr::expand_expr_macro(db, macro_def, call)
     }
 
     pub fn resolve_hir_path(
@@ -273,9 +392,14 @@ impl SourceAnalyzer {
     }
 
     pub fn resolve_path(&self, db: &impl HirDatabase, path: &ast::Path) -> Option<PathResolution> {
+        // Paths of macro expansions are not in the source map, but can still
+        // be resolved by name.
         if let Some(path_expr) = path.syntax().parent().and_then(ast::PathExpr::cast) {
-            let expr_id = self.body_source_map.as_ref()?.node_expr(path_expr.into())?;
-            if let Some(assoc) = self.infer.as_ref()?.assoc_resolutions_for_expr(expr_id) {
+            let expr_id =
+                self.body_source_map.as_ref().and_then(|it| it.node_expr(path_expr.into()));
+            if let Some(assoc) =
+                expr_id.and_then(|it| self.infer.as_ref()?.assoc_resolutions_for_expr(it))
+            {
                 return Some(PathResolution::AssocItem(assoc));
             }
         }
@@ -300,47 +424,96 @@ impl SourceAnalyzer {
     }
 
     pub fn resolve_local_name(&self, name_ref: &ast::NameRef) -> Option<ScopeEntryWithSyntax> {
-        let mut shadowed = FxHashSet::default();
         let name = name_ref.as_name();
         let source_map = self.body_source_map.as_ref()?;
         let scopes = self.scopes.as_ref()?;
         let scope = scope_for(scopes, source_map, name_ref.syntax());
-        let ret = scopes
-            .scope_chain(scope)
-            .flat_map(|scope| scopes.entries(scope).iter())
-            .filter(|entry| shadowed.insert(entry.name()))
-            .filter(|entry| entry.name() == &name)
-            .nth(0);
-        ret.and_then(|entry| {
-            Some(ScopeEntryWithSyntax {
-                name: entry.name().clone(),
-                ptr: source_map.pat_syntax(entry.pat())?,
-            })
-        })
+        visible_locals(scopes, source_map, scope).into_iter().find(|entry| entry.name == name)
+    }
+
+    /// Whether names which can't be resolved with this analyzer are known to
+    /// be errors, see `CrateDefMap::is_complete`.
+    pub fn is_complete(&self) -> bool {
+        self.resolver.is_complete()
+    }
+
+    /// Resolves `name` in the value namespace of the scope at `offset`, like
+    /// the names of the implicit arguments of format strings.
+    pub fn resolve_value_name(
+        &self,
+        db: &impl HirDatabase,
+        name: &SmolStr,
+        offset: TextUnit,
+    ) -> Option<PathResolution> {
+        let name = name.as_name();
+        if let Some(local) = self.scope_at(offset).into_iter().find(|it| it.name == name) {
+            return Some(PathResolution::LocalBinding(local.ptr));
+        }
+        match self.resolver.resolve_name(db, &name).take_values()? {
+            crate::Resolution::Def(it) => Some(PathResolution::Def(it)),
+            _ => None,
+        }
+    }
+
+    /// The local bindings visible at `offset`, from the innermost to the
+    /// outermost, without the shadowed ones.
+    pub fn scope_at(&self, offset: TextUnit) -> Vec<ScopeEntryWithSyntax> {
+        let (source_map, scopes) = match (&self.body_source_map, &self.scopes) {
+            (Some(source_map), Some(scopes)) => (source_map, scopes),
+            _ => return Vec::new(),
+        };
+        let scope = scope_for_offset(scopes, source_map, offset);
+        visible_locals(scopes, source_map, scope)
+    }
+
+    /// The binding of `local`, as returned by `scope_at`.
+    pub fn local_binding(&self, local: &ScopeEntryWithSyntax) -> Option<PatId> {
+        self.body_source_map.as_ref()?.syntax_pat(local.ptr)
     }
 
     pub fn all_names(&self, db: &impl HirDatabase) -> FxHashMap<Name, PerNs<crate::Resolution>> {
         self.resolver.all_names(db)
     }
 
-    pub fn find_all_refs(&self, pat: &ast::BindPat) -> Vec<ReferenceDescriptor> {
-        // FIXME: at least, this should work with any DefWithBody, but ideally
-        // this should be hir-based altogether
-        let fn_def = pat.syntax().ancestors().find_map(ast::FnDef::cast).unwrap();
-        let ptr = Either::A(AstPtr::new(pat.into()));
-        fn_def
-            .syntax()
-            .descendants()
-            .filter_map(ast::NameRef::cast)
-            .filter(|name_ref| match self.resolve_local_name(*name_ref) {
-                None => false,
-                Some(entry) => entry.ptr() == ptr,
-            })
-            .map(|name_ref| ReferenceDescriptor {
-                name: name_ref.text().to_string(),
-                range: name_ref.syntax().range(),
-            })
-            .collect()
+    pub fn find_all_refs(
+        &self,
+        db: &impl HirDatabase,
+        pat: &ast::BindPat,
+    ) -> Vec<ReferenceDescriptor> {
+        let (def, source_map) = match (self.body_owner, &self.body_source_map) {
+            (Some(def), Some(source_map)) => (def, source_map),
+            _ => return Vec::new(),
+        };
+        let name = match pat.name() {
+            Some(it) => it.text().to_string(),
+            None => return Vec::new(),
+        };
+        let pat_id = match source_map.node_pat(pat.into()) {
+            Some(it) => it,
+            None => return Vec::new(),
+        };
+        let mut res: Vec<_> = expr::usages::local_usages(db, def)
+            .remove(&pat_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|expr| source_map.expr_syntax(expr))
+            .map(|ptr| ReferenceDescriptor { name: name.clone(), range: ptr.range() })
+            .collect();
+        res.sort_by_key(|it| it.range.start());
+        res
+    }
+
+    /// The local bindings the closure `closure` captures, and how.
+    pub fn closure_captures(
+        &self,
+        db: &impl HirDatabase,
+        closure: &ast::LambdaExpr,
+    ) -> Vec<ClosureCapture> {
+        let (def, expr_id) = match (self.body_owner, self.expr_id(closure.into())) {
+            (Some(def), Some(expr_id)) => (def, expr_id),
+            _ => return Vec::new(),
+        };
+        expr::captures::closure_captures(db, def, expr_id)
     }
 
     pub fn iterate_method_candidates<T>(
@@ -369,6 +542,25 @@ impl SourceAnalyzer {
     }
 }
 
+fn visible_locals(
+    scopes: &ExprScopes,
+    source_map: &BodySourceMap,
+    scope: Option<ScopeId>,
+) -> Vec<ScopeEntryWithSyntax> {
+    let mut shadowed = FxHashSet::default();
+    scopes
+        .scope_chain(scope)
+        .flat_map(|scope| scopes.entries(scope).iter())
+        .filter(|entry| shadowed.insert(entry.name()))
+        .filter_map(|entry| {
+            Some(ScopeEntryWithSyntax {
+                name: entry.name().clone(),
+                ptr: source_map.pat_syntax(entry.pat())?,
+            })
+        })
+        .collect()
+}
+
 fn scope_for(
     scopes: &ExprScopes,
     source_map: &BodySourceMap,
@@ -393,7 +585,50 @@ fn scope_for_offset(
         .min_by_key(|(ptr, _scope)| {
             (!(ptr.range().start() <= offset && offset <= ptr.range().end()), ptr.range().len())
         })
-        .map(|(ptr, scope)| adjust(scopes, source_map, ptr, offset).unwrap_or(*scope))
+        .map(|(ptr, scope)| {
+            scope_in_block(scopes, source_map, ptr, offset)
+                .or_else(|| adjust(scopes, source_map, ptr, offset))
+                .unwrap_or(*scope)
+        })
+}
+
+/// If `ptr` is a block, and `offset` is between its statements, the scope
+/// which includes the bindings of the statements before `offset`.
+fn scope_in_block(
+    scopes: &ExprScopes,
+    source_map: &BodySourceMap,
+    ptr: SyntaxNodePtr,
+    offset: TextUnit,
+) -> Option<ScopeId> {
+    let block = source_map.syntax_expr(ptr)?;
+    let (statements, tail) = match &scopes.body()[block] {
+        expr::Expr::Block { statements, tail } => (statements, tail),
+        _ => return None,
+    };
+    let expr_range = |expr: ExprId| source_map.expr_syntax(expr).map(|it| it.range());
+    let start = |statement: Option<&expr::Statement>| match statement {
+        Some(expr::Statement::Let { pat, .. }) => source_map.pat_syntax(*pat).map(|it| {
+            it.either(|it| it.syntax_node_ptr(), |it| it.syntax_node_ptr()).range().start()
+        }),
+        Some(expr::Statement::Expr(expr)) => expr_range(*expr).map(|it| it.start()),
+        None => tail.and_then(expr_range).map(|it| it.start()),
+    };
+    let mut n = 0;
+    for (i, statement) in statements.iter().enumerate() {
+        let end = match statement {
+            expr::Statement::Let { initializer: Some(expr), .. } | expr::Statement::Expr(expr) => {
+                expr_range(*expr).map(|it| it.end())
+            }
+            // The end of a `let` without an initializer is not known, but it's
+            // over when the next statement starts.
+            expr::Statement::Let { initializer: None, .. } => start(statements.get(i + 1)),
+        };
+        if end.map_or(true, |end| end > offset) {
+            break;
+        }
+        n += 1;
+    }
+    scopes.scope_after_statements(block, n)
 }
 
 // XXX: during completion, cursor might be outside of any particular
@@ -56,6 +56,36 @@ pub enum TraitItem {
 // casting them.
 impl_froms!(TraitItem: Function, Const, TypeAlias);
 
+impl TraitItem {
+    pub fn name(self, db: &impl DefDatabase) -> Option<Name> {
+        match self {
+            TraitItem::Function(it) => Some(db.fn_signature(it).name().clone()),
+            TraitItem::Const(it) => Some(db.const_signature(it).name().clone()),
+            TraitItem::TypeAlias(it) => it.name(db),
+        }
+    }
+
+    /// Whether the trait gives a default for this item, which the impls of
+    /// the trait may leave out.
+    pub fn has_default(self, db: &impl DefDatabase) -> bool {
+        match self {
+            TraitItem::Function(it) => it.source(db).1.body().is_some(),
+            TraitItem::Const(it) => it.source(db).1.body().is_some(),
+            TraitItem::TypeAlias(it) => it.source(db).1.type_ref().is_some(),
+        }
+    }
+
+    /// The trait which declares this item.
+    pub fn parent_trait(self, db: &impl DefDatabase) -> Option<Trait> {
+        let module = match self {
+            TraitItem::Function(it) => it.module(db),
+            TraitItem::Const(it) => it.module(db),
+            TraitItem::TypeAlias(it) => it.module(db),
+        };
+        db.trait_items_index(module).get_parent_trait(self)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TraitItemsIndex {
     traits_by_def: FxHashMap<TraitItem, Trait>,
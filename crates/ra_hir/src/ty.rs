@@ -10,17 +10,23 @@ pub(crate) mod method_resolution;
 mod op;
 mod lower;
 mod infer;
+pub(crate) mod object_safety;
+pub(crate) mod unresolved_types;
 pub(crate) mod display;
 
 use std::sync::Arc;
 use std::{fmt, mem};
 
-use crate::{Name, AdtDef, type_ref::Mutability, db::HirDatabase, Trait};
+use crate::{
+    Name, AdtDef, ModuleDef, type_ref::Mutability, db::HirDatabase, Trait, StructField, EnumVariant,
+    DefWithBody, expr::ExprId,
+};
 use display::{HirDisplay, HirFormatter};
 
-pub(crate) use lower::{TypableDef, type_for_def, type_for_field, callable_item_sig};
+pub(crate) use lower::{TypableDef, type_for_def, field_types_query, callable_item_sig};
 pub(crate) use infer::{infer, InferenceResult, InferTy};
 pub use lower::CallableDef;
+pub use object_safety::{ObjectSafetyViolation, MethodViolation};
 
 /// A type constructor or type name: this might be something like the primitive
 /// type `bool`, a struct like `Vec`, or things like function pointers or
@@ -83,9 +89,21 @@ pub enum TypeCtor {
     /// ```
     FnPtr,
 
+    /// The type of a closure. Its only parameter is a function pointer type
+    /// with the signature of the closure.
+    Closure { def: DefWithBody, expr: ExprId },
+
     /// The never type `!`.
     Never,
 
+    /// A trait object, written `dyn Trait`. Its parameters are those of the
+    /// trait, without `Self`.
+    Dyn(Trait),
+
+    /// The future of an `async` function or block, `impl Future<Output = T>`.
+    /// Its parameter is the type of its output `T`.
+    Future,
+
     /// A tuple type.  For example, `(i32, bool)`.
     Tuple,
 }
@@ -194,6 +212,22 @@ impl TraitRef {
     pub fn self_ty(&self) -> &Ty {
         &self.substs.0[0]
     }
+
+    pub fn trait_(&self) -> Trait {
+        self.trait_
+    }
+
+    /// The parameters of the trait, including `Self` as the first one.
+    pub fn substs(&self) -> &Substs {
+        &self.substs
+    }
+
+    /// Replaces type parameters in the `Self` type and the parameters of the
+    /// trait, see `Ty::subst`.
+    pub fn subst(&self, substs: &Substs) -> TraitRef {
+        let params: Vec<_> = self.substs.0.iter().map(|ty| ty.clone().subst(substs)).collect();
+        TraitRef { trait_: self.trait_, substs: Substs(params.into()) }
+    }
 }
 
 /// A function signature as seen by type inference: Several parameter types and
@@ -252,6 +286,12 @@ impl Ty {
     pub fn unit() -> Self {
         Ty::apply(TypeCtor::Tuple, Substs::empty())
     }
+    pub fn is_never(&self) -> bool {
+        match self {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::Never, .. }) => true,
+            _ => false,
+        }
+    }
 
     pub fn walk(&self, f: &mut impl FnMut(&Ty)) {
         match self {
@@ -301,6 +341,30 @@ impl Ty {
         }
     }
 
+    /// The fields of the struct `self` is an instance of, with the type
+    /// arguments of `self` substituted into their types.
+    pub fn struct_fields(&self, db: &impl HirDatabase) -> Vec<(StructField, Ty)> {
+        match self.as_adt() {
+            Some((AdtDef::Struct(s), substs)) => fields_with_types(db, s.fields(db), substs),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Like `struct_fields`, for `variant` of the enum `self` is an instance
+    /// of.
+    pub fn variant_fields(
+        &self,
+        db: &impl HirDatabase,
+        variant: EnumVariant,
+    ) -> Vec<(StructField, Ty)> {
+        match self.as_adt() {
+            Some((AdtDef::Enum(e), substs)) if e == variant.parent_enum(db) => {
+                fields_with_types(db, variant.fields(db), substs)
+            }
+            _ => Vec::new(),
+        }
+    }
+
     pub fn as_tuple(&self) -> Option<&Substs> {
         match self {
             Ty::Apply(ApplicationTy { ctor: TypeCtor::Tuple, parameters }) => Some(parameters),
@@ -328,7 +392,9 @@ impl Ty {
         }
     }
 
-    fn callable_sig(&self, db: &impl HirDatabase) -> Option<FnSig> {
+    /// The signature of the values of this type when they are called, like
+    /// functions and closures.
+    pub fn callable_sig(&self, db: &impl HirDatabase) -> Option<FnSig> {
         match self {
             Ty::Apply(a_ty) => match a_ty.ctor {
                 TypeCtor::FnPtr => Some(FnSig::from_fn_ptr_substs(&a_ty.parameters)),
@@ -336,6 +402,7 @@ impl Ty {
                     let sig = db.callable_item_signature(def);
                     Some(sig.subst(&a_ty.parameters))
                 }
+                TypeCtor::Closure { .. } => a_ty.parameters.as_single().callable_sig(db),
                 _ => None,
             },
             _ => None,
@@ -382,6 +449,20 @@ impl Ty {
     }
 }
 
+fn fields_with_types(
+    db: &impl HirDatabase,
+    fields: Vec<StructField>,
+    substs: &Substs,
+) -> Vec<(StructField, Ty)> {
+    fields
+        .into_iter()
+        .map(|field| {
+            let ty = field.ty(db).subst(substs);
+            (field, ty)
+        })
+        .collect()
+}
+
 impl HirDisplay for &Ty {
     fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
         HirDisplay::hir_fmt(*self, f)
@@ -398,36 +479,56 @@ impl HirDisplay for ApplicationTy {
             TypeCtor::Str => write!(f, "str")?,
             TypeCtor::Slice => {
                 let t = self.parameters.as_single();
-                write!(f, "[{}]", t.display(f.db))?;
+                write!(f, "[")?;
+                t.hir_fmt(f)?;
+                write!(f, "]")?;
             }
             TypeCtor::Array => {
                 let t = self.parameters.as_single();
-                write!(f, "[{};_]", t.display(f.db))?;
+                write!(f, "[")?;
+                t.hir_fmt(f)?;
+                write!(f, ";_]")?;
             }
             TypeCtor::RawPtr(m) => {
                 let t = self.parameters.as_single();
-                write!(f, "*{}{}", m.as_keyword_for_ptr(), t.display(f.db))?;
+                write!(f, "*{}", m.as_keyword_for_ptr())?;
+                t.hir_fmt(f)?;
             }
             TypeCtor::Ref(m) => {
                 let t = self.parameters.as_single();
-                write!(f, "&{}{}", m.as_keyword_for_ref(), t.display(f.db))?;
+                write!(f, "&{}", m.as_keyword_for_ref())?;
+                t.hir_fmt(f)?;
             }
             TypeCtor::Never => write!(f, "!")?,
+            TypeCtor::Future => {
+                write!(f, "impl Future<Output = ")?;
+                self.parameters.as_single().hir_fmt(f)?;
+                write!(f, ">")?;
+            }
             TypeCtor::Tuple => {
                 let ts = &self.parameters;
                 if ts.0.len() == 1 {
-                    write!(f, "({},)", ts.0[0].display(f.db))?;
+                    write!(f, "(")?;
+                    ts.0[0].hir_fmt(f)?;
+                    write!(f, ",)")?;
                 } else {
                     write!(f, "(")?;
                     f.write_joined(&*ts.0, ", ")?;
                     write!(f, ")")?;
                 }
             }
-            TypeCtor::FnPtr => {
-                let sig = FnSig::from_fn_ptr_substs(&self.parameters);
-                write!(f, "fn(")?;
-                f.write_joined(sig.params(), ", ")?;
-                write!(f, ") -> {}", sig.ret().display(f.db))?;
+            TypeCtor::FnPtr => FnSig::from_fn_ptr_substs(&self.parameters).hir_fmt(f)?,
+            TypeCtor::Closure { .. } => {
+                let sig = self.parameters.as_single().callable_sig(f.db);
+                match sig {
+                    Some(sig) => {
+                        write!(f, "|")?;
+                        f.write_joined(sig.params(), ", ")?;
+                        write!(f, "| -> ")?;
+                        sig.ret().hir_fmt(f)?;
+                    }
+                    None => write!(f, "{{closure}}")?,
+                }
             }
             TypeCtor::FnDef(def) => {
                 let sig = f.db.callable_item_signature(def);
@@ -447,15 +548,41 @@ impl HirDisplay for ApplicationTy {
                 }
                 write!(f, "(")?;
                 f.write_joined(sig.params(), ", ")?;
-                write!(f, ") -> {}", sig.ret().display(f.db))?;
+                write!(f, ") -> ")?;
+                sig.ret().hir_fmt(f)?;
+                if f.show_where_clauses() {
+                    let generic_def = match def {
+                        CallableDef::Function(it) => it.into(),
+                        CallableDef::Struct(it) => it.into(),
+                        CallableDef::EnumVariant(it) => it.parent_enum(f.db).into(),
+                    };
+                    let predicates: Vec<_> = lower::generic_predicates(f.db, generic_def)
+                        .iter()
+                        .map(|pred| pred.subst(&self.parameters))
+                        .collect();
+                    if !predicates.is_empty() {
+                        write!(f, " where ")?;
+                        f.write_joined(&predicates, ", ")?;
+                    }
+                }
             }
-            TypeCtor::Adt(def_id) => {
-                let name = match def_id {
-                    AdtDef::Struct(s) => s.name(f.db),
-                    AdtDef::Enum(e) => e.name(f.db),
+            TypeCtor::Dyn(tr) => {
+                let name = tr.name(f.db).unwrap_or_else(Name::missing);
+                write!(f, "dyn ")?;
+                f.write_def_path(ModuleDef::Trait(tr), tr.module(f.db), &name)?;
+                if self.parameters.0.len() > 0 {
+                    write!(f, "<")?;
+                    f.write_joined(&*self.parameters.0, ", ")?;
+                    write!(f, ">")?;
                 }
-                .unwrap_or_else(Name::missing);
-                write!(f, "{}", name)?;
+            }
+            TypeCtor::Adt(def_id) => {
+                let (def, name, module) = match def_id {
+                    AdtDef::Struct(s) => (ModuleDef::Struct(s), s.name(f.db), s.module(f.db)),
+                    AdtDef::Enum(e) => (ModuleDef::Enum(e), e.name(f.db), e.module(f.db)),
+                };
+                let name = name.unwrap_or_else(Name::missing);
+                f.write_def_path(def, module, &name)?;
                 if self.parameters.0.len() > 0 {
                     write!(f, "<")?;
                     f.write_joined(&*self.parameters.0, ", ")?;
@@ -469,6 +596,9 @@ impl HirDisplay for ApplicationTy {
 
 impl HirDisplay for Ty {
     fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        if f.should_truncate() {
+            return write!(f, "…");
+        }
         match self {
             Ty::Apply(a_ty) => a_ty.hir_fmt(f)?,
             Ty::Param { name, .. } => write!(f, "{}", name)?,
@@ -478,3 +608,33 @@ impl HirDisplay for Ty {
         Ok(())
     }
 }
+
+impl HirDisplay for &TraitRef {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        HirDisplay::hir_fmt(*self, f)
+    }
+}
+
+impl HirDisplay for TraitRef {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        self.self_ty().hir_fmt(f)?;
+        write!(f, ": ")?;
+        let name = self.trait_.name(f.db).unwrap_or_else(Name::missing);
+        f.write_def_path(ModuleDef::Trait(self.trait_), self.trait_.module(f.db), &name)?;
+        if self.substs.0.len() > 1 {
+            write!(f, "<")?;
+            f.write_joined(&self.substs.0[1..], ", ")?;
+            write!(f, ">")?;
+        }
+        Ok(())
+    }
+}
+
+impl HirDisplay for FnSig {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        write!(f, "fn(")?;
+        f.write_joined(self.params(), ", ")?;
+        write!(f, ") -> ")?;
+        self.ret().hir_fmt(f)
+    }
+}
@@ -1,10 +1,25 @@
 use std::fmt;
 
-use crate::db::HirDatabase;
+use crate::{
+    Module, ModuleDef, Name,
+    db::HirDatabase,
+    resolve::Resolution,
+};
 
 pub struct HirFormatter<'a, 'b, DB> {
     pub db: &'a DB,
     fmt: &'a mut fmt::Formatter<'b>,
+    buf: String,
+    curr_size: usize,
+    /// If set, the rest of the type is elided once this many characters have
+    /// been written.
+    max_size: Option<usize>,
+    where_clauses: bool,
+    /// If set, items are printed with paths that are valid in this module.
+    from_module: Option<Module>,
+    /// Whether the paths to items are written from the crate roots, even for
+    /// the items in scope.
+    full_paths: bool,
 }
 
 pub trait HirDisplay {
@@ -13,7 +28,90 @@ pub trait HirDisplay {
     where
         Self: Sized,
     {
-        HirDisplayWrapper(db, self)
+        HirDisplayWrapper {
+            db,
+            t: self,
+            max_size: None,
+            where_clauses: false,
+            from_module: None,
+            full_paths: false,
+        }
+    }
+
+    /// Displays `self` along with the where clauses of the function types in
+    /// it.
+    fn display_with_where_clauses<'a, DB>(&'a self, db: &'a DB) -> HirDisplayWrapper<'a, DB, Self>
+    where
+        Self: Sized,
+    {
+        HirDisplayWrapper {
+            db,
+            t: self,
+            max_size: None,
+            where_clauses: true,
+            from_module: None,
+            full_paths: false,
+        }
+    }
+
+    /// Displays a short form of `self`: once `max_size` characters have been
+    /// written, the remaining type arguments are replaced by `…`.
+    fn display_truncated<'a, DB>(
+        &'a self,
+        db: &'a DB,
+        max_size: usize,
+    ) -> HirDisplayWrapper<'a, DB, Self>
+    where
+        Self: Sized,
+    {
+        HirDisplayWrapper {
+            db,
+            t: self,
+            max_size: Some(max_size),
+            where_clauses: false,
+            from_module: None,
+            full_paths: false,
+        }
+    }
+
+    /// Displays `self` as it would be written in source code of `module`, with
+    /// paths to items qualified as little as possible.
+    fn display_source_code<'a, DB>(
+        &'a self,
+        db: &'a DB,
+        module: Module,
+    ) -> HirDisplayWrapper<'a, DB, Self>
+    where
+        Self: Sized,
+    {
+        HirDisplayWrapper {
+            db,
+            t: self,
+            max_size: None,
+            where_clauses: false,
+            from_module: Some(module),
+            full_paths: false,
+        }
+    }
+
+    /// Displays `self` with the full paths to items, from the root of their
+    /// crate, which is named as in `module`.
+    fn display_qualified<'a, DB>(
+        &'a self,
+        db: &'a DB,
+        module: Module,
+    ) -> HirDisplayWrapper<'a, DB, Self>
+    where
+        Self: Sized,
+    {
+        HirDisplayWrapper {
+            db,
+            t: self,
+            max_size: None,
+            where_clauses: false,
+            from_module: Some(module),
+            full_paths: true,
+        }
     }
 }
 
@@ -32,18 +130,87 @@ where
                 write!(self, "{}", sep)?;
             }
             first = false;
+            if self.should_truncate() {
+                return write!(self, "…");
+            }
             e.hir_fmt(self)?;
         }
         Ok(())
     }
 
+    /// Whether the short form has reached its size, so the rest should be
+    /// elided.
+    pub fn should_truncate(&self) -> bool {
+        self.max_size.map_or(false, |max_size| self.curr_size >= max_size)
+    }
+
+    pub fn show_where_clauses(&self) -> bool {
+        self.where_clauses
+    }
+
+    /// Writes the path to `def`, which is called `name` and is defined in
+    /// `module`. Without a module to display relative to, this is just the name.
+    pub fn write_def_path(&mut self, def: ModuleDef, module: Module, name: &Name) -> fmt::Result {
+        let from = match self.from_module {
+            Some(it) => it,
+            None => return write!(self, "{}", name),
+        };
+        let resolver = from.resolver(self.db);
+        if !self.full_paths
+            && resolver.resolve_name(self.db, name).take_types() == Some(Resolution::Def(def))
+        {
+            return write!(self, "{}", name);
+        }
+        let mut modules = module.path_to_root(self.db);
+        modules.reverse();
+        let mut segments = Vec::new();
+        if from.krate == module.krate && self.full_paths {
+            segments.push("crate".to_string());
+            modules.drain(..1);
+        } else if from.krate == module.krate {
+            match modules.iter().position(|it| *it == from) {
+                // `module` is `from` or one of its descendants.
+                Some(idx) => modules.drain(..=idx),
+                None => {
+                    segments.push("crate".to_string());
+                    modules.drain(..1)
+                }
+            };
+        } else {
+            let dep =
+                from.krate.dependencies(self.db).into_iter().find(|dep| dep.krate == module.krate);
+            match dep {
+                Some(dep) => segments.push(dep.name.to_string()),
+                // FIXME: the crate is not a direct dependency, find a re-export
+                // of the item instead.
+                None => return write!(self, "{}", name),
+            }
+            modules.drain(..1);
+        }
+        segments.extend(modules.iter().filter_map(|it| it.name(self.db)).map(|it| it.to_string()));
+        for segment in segments {
+            write!(self, "{}::", segment)?;
+        }
+        write!(self, "{}", name)
+    }
+
     /// This allows using the `write!` macro directly with a `HirFormatter`.
     pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
-        fmt::write(self.fmt, args)
+        self.buf.clear();
+        fmt::write(&mut self.buf, args)?;
+        self.curr_size += self.buf.chars().count();
+        self.fmt.write_str(&self.buf)
     }
 }
 
-pub struct HirDisplayWrapper<'a, DB, T>(&'a DB, &'a T);
+pub struct HirDisplayWrapper<'a, DB, T> {
+    db: &'a DB,
+    t: &'a T,
+    max_size: Option<usize>,
+    where_clauses: bool,
+    from_module: Option<Module>,
+    full_paths: bool,
+}
 
 impl<'a, DB, T> fmt::Display for HirDisplayWrapper<'a, DB, T>
 where
@@ -51,6 +218,15 @@ where
     T: HirDisplay,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.1.hir_fmt(&mut HirFormatter { db: self.0, fmt: f })
+        self.t.hir_fmt(&mut HirFormatter {
+            db: self.db,
+            fmt: f,
+            buf: String::with_capacity(20),
+            curr_size: 0,
+            max_size: self.max_size,
+            where_clauses: self.where_clauses,
+            from_module: self.from_module,
+            full_paths: self.full_paths,
+        })
     }
 }
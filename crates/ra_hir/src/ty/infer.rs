@@ -23,6 +23,7 @@ use ena::unify::{InPlaceUnificationTable, UnifyKey, UnifyValue, NoError};
 use rustc_hash::FxHashMap;
 
 use ra_arena::map::ArenaMap;
+use ra_prof::profile;
 use test_utils::tested_by;
 
 use crate::{
@@ -30,7 +31,8 @@ use crate::{
     FnSignature, AdtDef,ConstSignature,
     HirDatabase,
     DefWithBody,
-    ImplItem,
+    ImplItem, KnownName, ModuleDef, Trait,
+    traits::TraitItem,
     type_ref::{TypeRef, Mutability},
     expr::{Body, Expr, BindingAnnotation, Literal, ExprId, Pat, PatId, UnaryOp, BinaryOp, Statement, FieldPat,Array, self},
     generics::{GenericParams, HasGenericParams},
@@ -41,13 +43,16 @@ use crate::{
     diagnostics::DiagnosticSink,
 };
 use super::{
-    Ty, TypableDef, Substs, primitive, op, ApplicationTy, TypeCtor, CallableDef, TraitRef,
+    Ty, TypableDef, Substs, primitive, op, ApplicationTy, TypeCtor, CallableDef, TraitRef, FnSig,
+    lower::substs_from_path_segment,
+    method_resolution::MethodLookup,
     traits::{ Solution, Obligation, Guidance},
 };
 use self::diagnostics::InferenceDiagnostic;
 
 /// The entry point of type inference.
 pub fn infer(db: &impl HirDatabase, def: DefWithBody) -> Arc<InferenceResult> {
+    let _p = profile("infer_query");
     db.check_canceled();
     let body = def.body(db);
     let resolver = def.resolver(db);
@@ -103,9 +108,13 @@ pub struct InferenceResult {
     method_resolutions: FxHashMap<ExprId, Function>,
     /// For each field access expr, records the field it resolves to.
     field_resolutions: FxHashMap<ExprId, StructField>,
+    /// For each field of a struct literal or pattern, records the field it
+    /// resolves to, by the expression of its value or by its pattern.
+    record_field_resolutions: FxHashMap<ExprOrPatId, StructField>,
     /// For each associated item record what it resolves to
     assoc_resolutions: FxHashMap<ExprOrPatId, ImplItem>,
     diagnostics: Vec<InferenceDiagnostic>,
+    type_mismatches: ArenaMap<ExprId, TypeMismatch>,
     pub(super) type_of_expr: ArenaMap<ExprId, Ty>,
     pub(super) type_of_pat: ArenaMap<PatId, Ty>,
 }
@@ -117,19 +126,76 @@ impl InferenceResult {
     pub fn field_resolution(&self, expr: ExprId) -> Option<StructField> {
         self.field_resolutions.get(&expr).map(|it| *it)
     }
+    pub fn record_field_resolution_for_expr(&self, id: ExprId) -> Option<StructField> {
+        self.record_field_resolutions.get(&id.into()).map(|it| *it)
+    }
+    pub fn record_field_resolution_for_pat(&self, id: PatId) -> Option<StructField> {
+        self.record_field_resolutions.get(&id.into()).map(|it| *it)
+    }
     pub fn assoc_resolutions_for_expr(&self, id: ExprId) -> Option<ImplItem> {
         self.assoc_resolutions.get(&id.into()).map(|it| *it)
     }
     pub fn assoc_resolutions_for_pat(&self, id: PatId) -> Option<ImplItem> {
         self.assoc_resolutions.get(&id.into()).map(|it| *it)
     }
+    pub fn type_mismatch_for_expr(&self, expr: ExprId) -> Option<&TypeMismatch> {
+        self.type_mismatches.get(expr)
+    }
     pub(crate) fn add_diagnostics(
         &self,
         db: &impl HirDatabase,
         owner: Function,
         sink: &mut DiagnosticSink,
     ) {
-        self.diagnostics.iter().for_each(|it| it.add_to(db, owner, sink))
+        self.diagnostics.iter().for_each(|it| it.add_to(db, owner, sink));
+        for (expr, mismatch) in self.type_mismatches.iter() {
+            diagnostics::add_type_mismatch(db, owner, expr, mismatch, sink);
+        }
+    }
+}
+
+/// An expression whose type is not the one expected by its context, like the
+/// initializer of a `let` with a type annotation.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TypeMismatch {
+    pub expected: Ty,
+    pub actual: Ty,
+}
+
+/// The maximum number of derefs tried when indexing.
+const AUTODEREF_LIMIT: usize = 10;
+
+/// The type of an `if` or a `match` whose branches have the types
+/// `branch_tys`, which were unified with `expected`: `!` if they all diverge.
+fn merge_branch_tys(expected: Ty, branch_tys: &[Ty]) -> Ty {
+    if branch_tys.iter().all(Ty::is_never) {
+        Ty::simple(TypeCtor::Never)
+    } else {
+        expected
+    }
+}
+
+impl TypeMismatch {
+    /// Whether the mismatch could be an artifact of the missing parts of the
+    /// inference, like coercions and unknown types.
+    fn is_uncertain(&self) -> bool {
+        fn is_uncertain(ty: &Ty) -> bool {
+            let mut res = false;
+            ty.walk(&mut |ty| match ty {
+                Ty::Apply(a_ty) => match a_ty.ctor {
+                    TypeCtor::Ref(_)
+                    | TypeCtor::RawPtr(_)
+                    | TypeCtor::Never
+                    | TypeCtor::FnDef(_)
+                    | TypeCtor::FnPtr
+                    | TypeCtor::Closure { .. } => res = true,
+                    _ => (),
+                },
+                Ty::Param { .. } | Ty::Infer(_) | Ty::Unknown => res = true,
+            });
+            res
+        }
+        is_uncertain(&self.expected) || is_uncertain(&self.actual)
     }
 }
 
@@ -159,10 +225,12 @@ struct InferenceContext<'a, D: HirDatabase> {
     obligations: Vec<Obligation>,
     method_resolutions: FxHashMap<ExprId, Function>,
     field_resolutions: FxHashMap<ExprId, StructField>,
+    record_field_resolutions: FxHashMap<ExprOrPatId, StructField>,
     assoc_resolutions: FxHashMap<ExprOrPatId, ImplItem>,
     type_of_expr: ArenaMap<ExprId, Ty>,
     type_of_pat: ArenaMap<PatId, Ty>,
     diagnostics: Vec<InferenceDiagnostic>,
+    type_mismatches: ArenaMap<ExprId, TypeMismatch>,
     /// The return type of the function being inferred.
     return_ty: Ty,
 }
@@ -172,10 +240,12 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         InferenceContext {
             method_resolutions: FxHashMap::default(),
             field_resolutions: FxHashMap::default(),
+            record_field_resolutions: FxHashMap::default(),
             assoc_resolutions: FxHashMap::default(),
             type_of_expr: ArenaMap::default(),
             type_of_pat: ArenaMap::default(),
             diagnostics: Vec::default(),
+            type_mismatches: ArenaMap::default(),
             var_unification_table: InPlaceUnificationTable::new(),
             obligations: Vec::default(),
             return_ty: Ty::Unknown, // set in collect_fn_signature
@@ -198,13 +268,26 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             let resolved = self.resolve_ty_completely(&mut tv_stack, mem::replace(ty, Ty::Unknown));
             *ty = resolved;
         }
+        let mut type_mismatches = ArenaMap::default();
+        for (expr, mismatch) in mem::replace(&mut self.type_mismatches, ArenaMap::default()).iter()
+        {
+            let mismatch = TypeMismatch {
+                expected: self.resolve_ty_completely(&mut tv_stack, mismatch.expected.clone()),
+                actual: self.resolve_ty_completely(&mut tv_stack, mismatch.actual.clone()),
+            };
+            if !mismatch.is_uncertain() && mismatch.expected != mismatch.actual {
+                type_mismatches.insert(expr, mismatch);
+            }
+        }
         InferenceResult {
             method_resolutions: self.method_resolutions,
             field_resolutions: self.field_resolutions,
+            record_field_resolutions: self.record_field_resolutions,
             assoc_resolutions: self.assoc_resolutions,
             type_of_expr: expr_types,
             type_of_pat: pat_types,
             diagnostics: self.diagnostics,
+            type_mismatches,
         }
     }
 
@@ -220,6 +303,10 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         self.field_resolutions.insert(expr, field);
     }
 
+    fn write_record_field_resolution(&mut self, id: ExprOrPatId, field: StructField) {
+        self.record_field_resolutions.insert(id, field);
+    }
+
     fn write_assoc_resolution(&mut self, id: ExprOrPatId, item: ImplItem) {
         self.assoc_resolutions.insert(id, item);
     }
@@ -239,6 +326,45 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         ty
     }
 
+    /// The signature of a value of type `ty` given by an `Fn`, `FnMut` or
+    /// `FnOnce` bound on it, like `F: Fn(u32) -> u64` for a parameter `f: F`.
+    fn callable_sig_from_fn_trait_bounds(&mut self, ty: &Ty) -> Option<FnSig> {
+        let resolver = self.resolver.clone();
+        for pred in resolver.where_predicates_in_scope() {
+            // FIXME check that the trait is the `Fn*` lang item instead
+            let segment = match &pred.trait_ref {
+                TypeRef::Path(path) => match path.segments.last() {
+                    Some(it) => it,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            match segment.name.as_known_name() {
+                Some(KnownName::Fn) | Some(KnownName::FnMut) | Some(KnownName::FnOnce) => (),
+                _ => continue,
+            }
+            if self.make_ty(&pred.type_ref) != *ty {
+                continue;
+            }
+            let args = match &segment.args_and_bindings {
+                Some(it) => it,
+                None => continue,
+            };
+            let params = match args.args.first() {
+                Some(GenericArg::Type(TypeRef::Tuple(params))) => {
+                    params.iter().map(|it| self.make_ty(it)).collect()
+                }
+                _ => continue,
+            };
+            let ret = match args.bindings.iter().find(|(name, _)| *name == Name::output_type()) {
+                Some((_, type_ref)) => self.make_ty(type_ref),
+                None => Ty::unit(),
+            };
+            return Some(FnSig::from_params_and_return(params, ret));
+        }
+        None
+    }
+
     fn unify_substs(&mut self, substs1: &Substs, substs2: &Substs, depth: usize) -> bool {
         substs1.0.iter().zip(substs2.0.iter()).all(|(t1, t2)| self.unify_inner(t1, t2, depth))
     }
@@ -459,9 +585,30 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             if remaining_index.is_none() { def.take_values()? } else { def.take_types()? };
 
         let remaining_index = remaining_index.unwrap_or(path.segments.len());
+        // the substitutions of the trait in paths like `Trait::method` or
+        // `<T as Trait>::method`
+        let mut trait_substs = None;
 
         // resolve intermediate segments
-        for segment in &path.segments[remaining_index..] {
+        for (i, segment) in path.segments.iter().enumerate().skip(remaining_index) {
+            if let Resolution::Def(ModuleDef::Trait(tr)) = resolved {
+                let item = self.find_trait_item(tr, &segment.name)?;
+                self.write_assoc_resolution(id, item);
+                let trait_segment = &path.segments[i - 1];
+                trait_substs = Some(substs_from_path_segment(
+                    self.db,
+                    &self.resolver,
+                    trait_segment,
+                    &tr.generic_params(self.db),
+                    true,
+                ));
+                resolved = Resolution::Def(match item {
+                    ImplItem::Method(f) => f.into(),
+                    ImplItem::Const(c) => c.into(),
+                    ImplItem::TypeAlias(t) => t.into(),
+                });
+                continue;
+            }
             let ty = match resolved {
                 Resolution::Def(def) => {
                     // FIXME resolve associated items from traits as well
@@ -480,10 +627,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                     // FIXME associated item of generic param
                     return None;
                 }
-                Resolution::SelfType(_) => {
-                    // FIXME associated item of self type
-                    return None;
-                }
+                Resolution::SelfType(impl_block) => impl_block.target_ty(self.db),
             };
 
             // Attempt to find an impl_item for the type which has a name matching
@@ -528,9 +672,19 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             Resolution::Def(def) => {
                 let typable: Option<TypableDef> = def.into();
                 let typable = typable?;
-                let substs = Ty::substs_from_path(self.db, &self.resolver, path, typable);
+                let mut substs = Ty::substs_from_path(self.db, &self.resolver, path, typable);
+                if let Some(trait_substs) = &trait_substs {
+                    // the parameters of the trait come before the item's own
+                    let own_substs = substs.iter().skip(trait_substs.len());
+                    substs = Substs(
+                        trait_substs.iter().chain(own_substs).cloned().collect::<Vec<_>>().into(),
+                    );
+                }
                 let ty = self.db.type_for_def(typable, Namespace::Values).subst(&substs);
                 let ty = self.insert_type_vars(ty);
+                if trait_substs.is_some() {
+                    self.register_obligations_for_call(&ty);
+                }
                 Some(ty)
             }
             Resolution::LocalBinding(pat) => {
@@ -549,6 +703,17 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         }
     }
 
+    fn find_trait_item(&self, tr: Trait, name: &Name) -> Option<ImplItem> {
+        tr.trait_data(self.db).items().iter().find_map(|item| match *item {
+            TraitItem::Function(f) if f.signature(self.db).name() == name => {
+                Some(ImplItem::Method(f))
+            }
+            TraitItem::Const(c) if c.signature(self.db).name() == name => Some(ImplItem::Const(c)),
+            // FIXME: Resolve associated types
+            _ => None,
+        })
+    }
+
     fn resolve_variant(&mut self, path: Option<&Path>) -> (Ty, Option<VariantDef>) {
         let path = match path {
             Some(path) => path,
@@ -566,9 +731,12 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 // generic params can't be used in struct literals
                 return (Ty::Unknown, None);
             }
-            Some(Resolution::SelfType(..)) => {
-                // FIXME this is allowed in an impl for a struct, handle this
-                return (Ty::Unknown, None);
+            Some(Resolution::SelfType(impl_block)) => {
+                let ty = impl_block.target_ty(self.db);
+                return match ty.as_adt() {
+                    Some((AdtDef::Struct(s), _)) => (ty, Some(s.into())),
+                    _ => (Ty::Unknown, None),
+                };
             }
             None => return (Ty::Unknown, None),
         };
@@ -636,6 +804,9 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
 
         for subpat in subpats {
             let matching_field = def.and_then(|it| it.field(self.db, &subpat.name));
+            if let Some(field) = matching_field {
+                self.write_record_field_resolution(subpat.pat.into(), field);
+            }
             let expected_ty =
                 matching_field.map_or(Ty::Unknown, |field| field.ty(self.db)).subst(&substs);
             self.infer_pat(subpat.pat, &expected_ty, default_bm);
@@ -816,6 +987,91 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         }
     }
 
+    /// Reports a path of a single name which doesn't resolve to anything.
+    /// Longer paths might point into crates we don't know about.
+    fn check_unresolved_path(&mut self, resolver: &Resolver, path: &Path, tgt_expr: ExprId) {
+        let name = match path.as_ident() {
+            Some(name) => name,
+            None => return,
+        };
+        // FIXME: `Self` can be the constructor of a unit or tuple struct.
+        if name.as_known_name() == Some(KnownName::SelfType) || !resolver.is_complete() {
+            return;
+        }
+        if resolver.resolve_name(self.db, name).is_none() {
+            self.diagnostics
+                .push(InferenceDiagnostic::UnresolvedValue { expr: tgt_expr, name: name.clone() });
+        }
+    }
+
+    /// Reports a method call which doesn't resolve, if the receiver is a type
+    /// of the current crate and we know about all the methods it could have.
+    fn check_unresolved_method(&mut self, tgt_expr: ExprId, receiver_ty: &Ty, method_name: &Name) {
+        if !self.resolver.is_complete() {
+            return;
+        }
+        let receiver_ty = self.resolve_ty_shallow(receiver_ty).into_owned();
+        let receiver_ty = match receiver_ty.autoderef(self.db).last() {
+            Some(it) => it,
+            None => return,
+        };
+        let adt = match receiver_ty.as_adt() {
+            Some((adt, _)) => adt,
+            None => return,
+        };
+        let krate = self.resolver.krate();
+        if krate.is_none() || adt.krate(self.db) != krate {
+            return;
+        }
+        // Methods of prelude traits, like the ones of derived traits, are not
+        // resolved yet.
+        let db = self.db;
+        let is_trait_method =
+            self.resolver.traits_in_scope().chain(self.resolver.prelude_traits(db)).any(|t| {
+                t.items(db).iter().any(|item| match item {
+                    TraitItem::Function(f) => f.signature(db).name() == method_name,
+                    _ => false,
+                })
+            });
+        // `Deref` impls are not used by autoderef yet.
+        let is_deref = krate.map_or(false, |krate| {
+            db.impls_in_crate(krate).trait_impl_blocks().any(|impl_block| {
+                let is_deref_impl = impl_block
+                    .target_trait_ref(db)
+                    .and_then(|it| it.trait_().name(db))
+                    .map_or(false, |name| name.to_string() == "Deref");
+                is_deref_impl && impl_block.target_ty(db).as_adt().map(|(it, _)| it) == Some(adt)
+            })
+        });
+        if is_trait_method || is_deref {
+            return;
+        }
+        // The trait impls for a type of the current crate are in the crate.
+        let mut traits = Vec::new();
+        if let Some(krate) = krate {
+            for tr in db.impls_in_crate(krate).traits_with_method(db, &receiver_ty, method_name) {
+                if !traits.contains(&tr) {
+                    traits.push(tr);
+                }
+            }
+        }
+        let diagnostic = if traits.is_empty() {
+            InferenceDiagnostic::UnresolvedMethod {
+                expr: tgt_expr,
+                receiver_ty,
+                method_name: method_name.clone(),
+            }
+        } else {
+            InferenceDiagnostic::TraitNotInScope {
+                expr: tgt_expr,
+                receiver_ty,
+                method_name: method_name.clone(),
+                traits,
+            }
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
     fn infer_method_call(
         &mut self,
         tgt_expr: ExprId,
@@ -827,7 +1083,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         let receiver_ty = self.infer_expr(receiver, &Expectation::none());
         let resolved = receiver_ty.clone().lookup_method(self.db, method_name, &self.resolver);
         let (derefed_receiver_ty, method_ty, def_generics) = match resolved {
-            Some((ty, func)) => {
+            Some(MethodLookup::Resolved(ty, func)) => {
                 self.write_method_resolution(tgt_expr, func);
                 (
                     ty,
@@ -835,7 +1091,18 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                     Some(func.generic_params(self.db)),
                 )
             }
-            None => (receiver_ty, Ty::Unknown, None),
+            Some(MethodLookup::Ambiguous(traits)) => {
+                self.diagnostics.push(InferenceDiagnostic::AmbiguousMethod {
+                    expr: tgt_expr,
+                    method_name: method_name.clone(),
+                    traits,
+                });
+                (receiver_ty, Ty::Unknown, None)
+            }
+            None => {
+                self.check_unresolved_method(tgt_expr, &receiver_ty, method_name);
+                (receiver_ty, Ty::Unknown, None)
+            }
         };
         let substs =
             self.substs_for_method_call(def_generics.clone(), generic_args, &derefed_receiver_ty);
@@ -874,19 +1141,30 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             Expr::If { condition, then_branch, else_branch } => {
                 // if let is desugared to match, so this is always simple if
                 self.infer_expr(*condition, &Expectation::has_type(Ty::simple(TypeCtor::Bool)));
-                let then_ty = self.infer_expr(*then_branch, expected);
                 match else_branch {
                     Some(else_branch) => {
-                        self.infer_expr(*else_branch, expected);
+                        let expected = self.expectation_for_branches(expected);
+                        let then_ty = self.infer_expr(*then_branch, &expected);
+                        let else_ty = self.infer_expr(*else_branch, &expected);
+                        merge_branch_tys(expected.ty, &[then_ty, else_ty])
                     }
                     None => {
                         // no else branch -> unit
-                        self.unify(&then_ty, &Ty::unit()); // actually coerce
+                        self.infer_expr(*then_branch, &Expectation::has_type(Ty::unit()));
+                        Ty::unit()
                     }
-                };
-                then_ty
+                }
             }
             Expr::Block { statements, tail } => self.infer_block(statements, *tail, expected),
+            Expr::UnsafeBlock { body } => self.infer_expr(*body, expected),
+            Expr::AsyncBlock { body } => {
+                // `return` in the body returns from the block
+                let output_ty = self.new_type_var();
+                let prev_return_ty = mem::replace(&mut self.return_ty, output_ty.clone());
+                self.infer_expr(*body, &Expectation::has_type(output_ty.clone()));
+                self.return_ty = prev_return_ty;
+                Ty::apply_one(TypeCtor::Future, output_ty)
+            }
             Expr::Loop { body } => {
                 self.infer_expr(*body, &Expectation::has_type(Ty::unit()));
                 // FIXME handle break with value
@@ -899,31 +1177,51 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 Ty::unit()
             }
             Expr::For { iterable, body, pat } => {
-                let _iterable_ty = self.infer_expr(*iterable, &Expectation::none());
-                self.infer_pat(*pat, &Ty::Unknown, BindingMode::default());
+                let iterable_ty = self.infer_expr(*iterable, &Expectation::none());
+                let item_ty = self.iterator_item_ty(iterable_ty);
+                self.infer_pat(*pat, &item_ty, BindingMode::default());
                 self.infer_expr(*body, &Expectation::has_type(Ty::unit()));
                 Ty::unit()
             }
-            Expr::Lambda { body, args, arg_types } => {
+            Expr::Lambda { body, args, arg_types, .. } => {
                 assert_eq!(args.len(), arg_types.len());
 
+                let mut sig_tys = Vec::with_capacity(args.len() + 1);
                 for (arg_pat, arg_type) in args.iter().zip(arg_types.iter()) {
-                    let expected = if let Some(type_ref) = arg_type {
-                        let ty = self.make_ty(type_ref);
-                        ty
-                    } else {
-                        Ty::Unknown
+                    let expected = match arg_type {
+                        Some(type_ref) => self.make_ty(type_ref),
+                        None => self.new_type_var(),
                     };
-                    self.infer_pat(*arg_pat, &expected, BindingMode::default());
+                    let arg_ty = self.infer_pat(*arg_pat, &expected, BindingMode::default());
+                    sig_tys.push(arg_ty);
                 }
 
-                // FIXME: infer lambda type etc.
-                let _body_ty = self.infer_expr(*body, &Expectation::none());
-                Ty::Unknown
+                let ret_ty = self.new_type_var();
+                sig_tys.push(ret_ty.clone());
+                let sig_ty = Ty::apply(TypeCtor::FnPtr, Substs(sig_tys.into()));
+                // a closure passed where a function pointer is expected gets its
+                // signature from the expected type
+                if let Ty::Apply(ApplicationTy { ctor: TypeCtor::FnPtr, .. }) =
+                    &*self.resolve_ty_shallow(&expected.ty)
+                {
+                    self.unify(&sig_ty, &expected.ty);
+                }
+
+                // `return` in the body returns from the closure
+                let prev_return_ty = mem::replace(&mut self.return_ty, ret_ty.clone());
+                self.infer_expr(*body, &Expectation::has_type(ret_ty));
+                self.return_ty = prev_return_ty;
+
+                let ctor = TypeCtor::Closure { def: self.body.owner(), expr: tgt_expr };
+                Ty::apply_one(ctor, sig_ty)
             }
             Expr::Call { callee, args } => {
                 let callee_ty = self.infer_expr(*callee, &Expectation::none());
-                let (param_tys, ret_ty) = match callee_ty.callable_sig(self.db) {
+                let callee_ty = self.resolve_ty_shallow(&callee_ty).into_owned();
+                let sig = callee_ty
+                    .callable_sig(self.db)
+                    .or_else(|| self.callable_sig_from_fn_trait_bounds(&callee_ty));
+                let (param_tys, ret_ty) = match sig {
                     Some(sig) => (sig.params().to_vec(), sig.ret().clone()),
                     None => {
                         // Not callable
@@ -931,9 +1229,11 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                         (Vec::new(), Ty::Unknown)
                     }
                 };
+                self.unify_with_expected_output(&ret_ty, expected);
                 // FIXME register obligations from where clauses from the function
                 let param_iter = param_tys.into_iter().chain(repeat(Ty::Unknown));
                 for (arg, param) in args.iter().zip(param_iter) {
+                    let param = self.resolve_ty_as_possible(&mut vec![], param);
                     self.infer_expr(*arg, &Expectation::has_type(param));
                 }
                 ret_ty
@@ -941,12 +1241,9 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             Expr::MethodCall { receiver, args, method_name, generic_args } => self
                 .infer_method_call(tgt_expr, *receiver, &args, &method_name, generic_args.as_ref()),
             Expr::Match { expr, arms } => {
-                let expected = if expected.ty == Ty::Unknown {
-                    Expectation::has_type(self.new_type_var())
-                } else {
-                    expected.clone()
-                };
+                let expected = self.expectation_for_branches(expected);
                 let input_ty = self.infer_expr(*expr, &Expectation::none());
+                let mut arm_tys = Vec::with_capacity(arms.len());
 
                 for arm in arms {
                     for &pat in &arm.pats {
@@ -958,15 +1255,19 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                             &Expectation::has_type(Ty::simple(TypeCtor::Bool)),
                         );
                     }
-                    self.infer_expr(arm.expr, &expected);
+                    arm_tys.push(self.infer_expr(arm.expr, &expected));
                 }
 
-                expected.ty
+                merge_branch_tys(expected.ty, &arm_tys)
             }
             Expr::Path(p) => {
                 // FIXME this could be more efficient...
                 let resolver = expr::resolver_for_expr(self.body.clone(), self.db, tgt_expr);
-                self.infer_path_expr(&resolver, p, tgt_expr.into()).unwrap_or(Ty::Unknown)
+                let ty = self.infer_path_expr(&resolver, p, tgt_expr.into());
+                if ty.is_none() {
+                    self.check_unresolved_path(&resolver, p, tgt_expr);
+                }
+                ty.unwrap_or(Ty::Unknown)
             }
             Expr::Continue => Ty::simple(TypeCtor::Never),
             Expr::Break { expr } => {
@@ -984,21 +1285,41 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             }
             Expr::StructLit { path, fields, spread } => {
                 let (ty, def_id) = self.resolve_variant(path.as_ref());
+                self.unify_with_expected_output(&ty, expected);
+                let ty = self.resolve_ty_as_possible(&mut vec![], ty);
                 let substs = ty.substs().unwrap_or_else(Substs::empty);
+                if let (Some(def_id), None) = (def_id, spread) {
+                    let missed_fields: Vec<Name> = def_id
+                        .variant_data(self.db)
+                        .fields()
+                        .into_iter()
+                        .flat_map(|it| it.iter())
+                        .map(|(_, it)| it.name.clone())
+                        .filter(|name| fields.iter().all(|it| it.name != *name))
+                        .collect();
+                    if !missed_fields.is_empty() {
+                        self.diagnostics.push(InferenceDiagnostic::MissingFields {
+                            expr: tgt_expr,
+                            missed_fields,
+                        });
+                    }
+                }
                 for (field_idx, field) in fields.into_iter().enumerate() {
-                    let field_ty = def_id
-                        .and_then(|it| match it.field(self.db, &field.name) {
-                            Some(field) => Some(field),
-                            None => {
-                                self.diagnostics.push(InferenceDiagnostic::NoSuchField {
-                                    expr: tgt_expr,
-                                    field: field_idx,
-                                });
-                                None
-                            }
-                        })
-                        .map_or(Ty::Unknown, |field| field.ty(self.db))
-                        .subst(&substs);
+                    let field_def = def_id.and_then(|it| match it.field(self.db, &field.name) {
+                        Some(field) => Some(field),
+                        None => {
+                            self.diagnostics.push(InferenceDiagnostic::NoSuchField {
+                                expr: tgt_expr,
+                                field: field_idx,
+                            });
+                            None
+                        }
+                    });
+                    if let Some(field_def) = field_def {
+                        self.write_record_field_resolution(field.expr.into(), field_def);
+                    }
+                    let field_ty =
+                        field_def.map_or(Ty::Unknown, |field| field.ty(self.db)).subst(&substs);
                     self.infer_expr(field.expr, &Expectation::has_type(field_ty));
                 }
                 if let Some(expr) = spread {
@@ -1033,6 +1354,10 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 let _inner_ty = self.infer_expr(*expr, &Expectation::none());
                 Ty::Unknown
             }
+            Expr::Await { expr } => {
+                let future_ty = self.infer_expr(*expr, &Expectation::none());
+                self.future_output_ty(future_ty)
+            }
             Expr::Cast { expr, type_ref } => {
                 let _inner_ty = self.infer_expr(*expr, &Expectation::none());
                 let cast_ty = self.make_ty(type_ref);
@@ -1117,6 +1442,25 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 }
                 _ => Ty::Unknown,
             },
+            Expr::Index { base, index } => {
+                let base_ty = self.infer_expr(*base, &Expectation::none());
+                let index_ty = self.infer_expr(*index, &Expectation::none());
+                let is_range = match &body[*index] {
+                    Expr::Range { .. } => true,
+                    _ => false,
+                };
+                self.index_output_ty(base_ty, index_ty, is_range)
+            }
+            Expr::Range { start, end, .. } => {
+                let start_ty = start.map(|it| self.infer_expr(it, &Expectation::none()));
+                let end_expectation =
+                    start_ty.map_or_else(Expectation::none, Expectation::has_type);
+                if let Some(end) = end {
+                    self.infer_expr(*end, &end_expectation);
+                }
+                // FIXME: the `Range*` structs of the standard library
+                Ty::Unknown
+            }
             Expr::Tuple { exprs } => {
                 let mut ty_vec = Vec::with_capacity(exprs.len());
                 for arg in exprs.iter() {
@@ -1174,7 +1518,22 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         };
         // use a new type variable if we got Ty::Unknown here
         let ty = self.insert_type_vars_shallow(ty);
-        self.unify(&ty, &expected.ty);
+        // `!` coerces to any type, so diverging expressions don't constrain
+        // the expectation
+        let could_unify = ty.is_never() || self.unify(&ty, &expected.ty);
+        // Blocks and conditionals pass the expectation down to their parts,
+        // where the mismatch is reported.
+        let forwards_expectation = match &body[tgt_expr] {
+            Expr::Block { .. }
+            | Expr::UnsafeBlock { .. }
+            | Expr::If { .. }
+            | Expr::Match { .. } => true,
+            _ => false,
+        };
+        if !could_unify && !forwards_expectation {
+            let mismatch = TypeMismatch { expected: expected.ty.clone(), actual: ty.clone() };
+            self.type_mismatches.insert(tgt_expr, mismatch);
+        }
         let ty = self.resolve_ty_as_possible(&mut vec![], ty);
         self.write_expr_ty(tgt_expr, ty.clone());
         ty
@@ -1186,28 +1545,138 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         tail: Option<ExprId>,
         expected: &Expectation,
     ) -> Ty {
+        let mut diverges = false;
         for stmt in statements {
             match stmt {
                 Statement::Let { pat, type_ref, initializer } => {
                     let decl_ty =
                         type_ref.as_ref().map(|tr| self.make_ty(tr)).unwrap_or(Ty::Unknown);
                     let decl_ty = self.insert_type_vars(decl_ty);
-                    let ty = if let Some(expr) = initializer {
-                        let expr_ty = self.infer_expr(*expr, &Expectation::has_type(decl_ty));
-                        expr_ty
-                    } else {
-                        decl_ty
+                    let ty = match initializer {
+                        // the initializer may be coerced to the declared type,
+                        // e.g. a closure to a function pointer
+                        Some(expr) if type_ref.is_some() => {
+                            self.infer_expr(*expr, &Expectation::has_type(decl_ty.clone()));
+                            decl_ty
+                        }
+                        Some(expr) => self.infer_expr(*expr, &Expectation::has_type(decl_ty)),
+                        None => decl_ty,
                     };
 
                     self.infer_pat(*pat, &ty, BindingMode::default());
                 }
                 Statement::Expr(expr) => {
-                    self.infer_expr(*expr, &Expectation::none());
+                    if self.infer_expr(*expr, &Expectation::none()).is_never() {
+                        diverges = true;
+                    }
                 }
             }
         }
-        let ty = if let Some(expr) = tail { self.infer_expr(expr, expected) } else { Ty::unit() };
-        ty
+        match tail {
+            Some(expr) => self.infer_expr(expr, expected),
+            // a block whose statements diverge, like `{ return; }`, can have
+            // any type
+            None if diverges => Ty::simple(TypeCtor::Never),
+            None => Ty::unit(),
+        }
+    }
+
+    /// The type of indexing a value of type `base_ty` with `index_ty`: the
+    /// elements of arrays, slices and strings, and the `Output` of `Index`
+    /// impls otherwise, after autoderef (including through `Deref` impls, so
+    /// that a `Vec<T>` is indexed as a `[T]`).
+    fn index_output_ty(&mut self, base_ty: Ty, index_ty: Ty, is_range: bool) -> Ty {
+        let krate = self.resolver.krate();
+        let usize_ty =
+            Ty::simple(TypeCtor::Int(primitive::UncertainIntTy::Known(primitive::IntTy::usize())));
+        let mut ty = self.resolve_ty_as_possible(&mut vec![], base_ty);
+        // bound the derefs, in case of a cycle of `Deref` impls
+        for _ in 0..AUTODEREF_LIMIT {
+            if let Ty::Apply(a_ty) = &ty {
+                match a_ty.ctor {
+                    TypeCtor::Slice | TypeCtor::Array if is_range => {
+                        return Ty::apply_one(TypeCtor::Slice, a_ty.parameters.as_single().clone());
+                    }
+                    TypeCtor::Slice | TypeCtor::Array => {
+                        self.unify(&index_ty, &usize_ty);
+                        return a_ty.parameters.as_single().clone();
+                    }
+                    TypeCtor::Str if is_range => return Ty::simple(TypeCtor::Str),
+                    _ => (),
+                }
+            }
+            if let Some(output) = ty.trait_impl_assoc_type(self.db, krate, "Index", "Output") {
+                return output;
+            }
+            ty = match ty.builtin_deref() {
+                Some(it) => it,
+                None => match ty.trait_impl_assoc_type(self.db, krate, "Deref", "Target") {
+                    Some(it) => it,
+                    None => break,
+                },
+            };
+        }
+        Ty::Unknown
+    }
+
+    /// The type of the value an `.await` on a value of type `future_ty` gives:
+    /// the output of `async` functions and blocks, and the `Output` of
+    /// `Future` impls otherwise.
+    fn future_output_ty(&mut self, future_ty: Ty) -> Ty {
+        let krate = self.resolver.krate();
+        let ty = self.resolve_ty_as_possible(&mut vec![], future_ty);
+        if let Ty::Apply(ApplicationTy { ctor: TypeCtor::Future, parameters }) = &ty {
+            return parameters.as_single().clone();
+        }
+        ty.trait_impl_assoc_type(self.db, krate, "Future", "Output").unwrap_or(Ty::Unknown)
+    }
+
+    /// The type of the items a `for` loop gets from a value of type
+    /// `iterable_ty`: the element references of slices and arrays references,
+    /// and the `Item` of `IntoIterator` or `Iterator` impls otherwise.
+    fn iterator_item_ty(&mut self, iterable_ty: Ty) -> Ty {
+        let krate = self.resolver.krate();
+        let ty = self.resolve_ty_as_possible(&mut vec![], iterable_ty);
+        if let Ty::Apply(ApplicationTy { ctor: TypeCtor::Ref(mutability), parameters }) = &ty {
+            if let Ty::Apply(a_ty) = parameters.as_single() {
+                match a_ty.ctor {
+                    TypeCtor::Slice | TypeCtor::Array => {
+                        let elem_ty = a_ty.parameters.as_single().clone();
+                        return Ty::apply_one(TypeCtor::Ref(*mutability), elem_ty);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        ty.trait_impl_assoc_type(self.db, krate, "IntoIterator", "Item")
+            .or_else(|| ty.trait_impl_assoc_type(self.db, krate, "Iterator", "Item"))
+            .unwrap_or(Ty::Unknown)
+    }
+
+    /// Unifies the type of a call or of a struct literal with the expected
+    /// type before its arguments are inferred, so that they get the expected
+    /// types of the generic parameters, like `Vec<u32>` for `Some(Vec::new())`
+    /// or the parameter types of a closure. If they don't unify, this is
+    /// undone, for the mismatch to be reported on the whole expression.
+    fn unify_with_expected_output(&mut self, output_ty: &Ty, expected: &Expectation) {
+        let snapshot = self.var_unification_table.snapshot();
+        if self.unify(output_ty, &expected.ty) {
+            self.var_unification_table.commit(snapshot);
+        } else {
+            self.var_unification_table.rollback_to(snapshot);
+        }
+    }
+
+    /// The expectation of the branches of an `if` or the arms of a `match`:
+    /// without an expected type, they are unified with a new type variable,
+    /// so that a mismatch is reported on the branches which disagree with
+    /// the first one.
+    fn expectation_for_branches(&mut self, expected: &Expectation) -> Expectation {
+        if expected.ty == Ty::Unknown {
+            Expectation::has_type(self.new_type_var())
+        } else {
+            expected.clone()
+        }
     }
 
     fn collect_const_signature(&mut self, signature: &ConstSignature) {
@@ -1342,11 +1811,50 @@ impl Expectation {
 }
 
 mod diagnostics {
-    use crate::{expr::ExprId, diagnostics::{DiagnosticSink, NoSuchField}, HirDatabase, Function};
+    use ra_syntax::{AstPtr, AstNode, SyntaxKind::MACRO_CALL, ast};
+
+    use crate::{
+        expr::ExprId,
+        diagnostics::{
+            DiagnosticSink, NoSuchField, MissingFields, UnresolvedValue, UnresolvedMethod,
+            TraitNotInScope, AmbiguousMethod, MismatchedTypes,
+        },
+        HirDatabase, Function, Name, Trait, ty::{Ty, display::HirDisplay},
+};
+
+    use super::TypeMismatch;
+
+    pub(super) fn add_type_mismatch(
+        db: &impl HirDatabase,
+        owner: Function,
+        expr: ExprId,
+        mismatch: &TypeMismatch,
+        sink: &mut DiagnosticSink,
+    ) {
+        let (file, _) = owner.source(db);
+        let source_file = db.hir_parse(file);
+        let expr = owner
+            .body_source_map(db)
+            .expr_syntax(expr)
+            .and_then(|ptr| ast::Expr::cast(ptr.to_node(&source_file)));
+        if let Some(expr) = expr {
+            sink.push(MismatchedTypes {
+                file,
+                expr: AstPtr::new(expr),
+                expected: mismatch.expected.display(db).to_string(),
+                actual: mismatch.actual.display(db).to_string(),
+            })
+        }
+    }
 
     #[derive(Debug, PartialEq, Eq, Clone)]
     pub(super) enum InferenceDiagnostic {
         NoSuchField { expr: ExprId, field: usize },
+        MissingFields { expr: ExprId, missed_fields: Vec<Name> },
+        UnresolvedValue { expr: ExprId, name: Name },
+        UnresolvedMethod { expr: ExprId, receiver_ty: Ty, method_name: Name },
+        TraitNotInScope { expr: ExprId, receiver_ty: Ty, method_name: Name, traits: Vec<Trait> },
+        AmbiguousMethod { expr: ExprId, method_name: Name, traits: Vec<Trait> },
     }
 
     impl InferenceDiagnostic {
@@ -1359,8 +1867,102 @@ mod diagnostics {
             match self {
                 InferenceDiagnostic::NoSuchField { expr, field } => {
                     let (file, _) = owner.source(db);
-                    let field = owner.body_source_map(db).field_syntax(*expr, *field);
-                    sink.push(NoSuchField { file, field })
+                    if let Some(field) = owner.body_source_map(db).field_syntax(*expr, *field) {
+                        sink.push(NoSuchField { file, field })
+                    }
+                }
+                InferenceDiagnostic::MissingFields { expr, missed_fields } => {
+                    let (file, _) = owner.source(db);
+                    let source_file = db.hir_parse(file);
+                    let field_list = owner
+                        .body_source_map(db)
+                        .expr_syntax(*expr)
+                        .and_then(|ptr| ast::StructLit::cast(ptr.to_node(&source_file)))
+                        .and_then(|it| it.named_field_list());
+                    if let Some(field_list) = field_list {
+                        sink.push(MissingFields {
+                            file,
+                            field_list: AstPtr::new(field_list),
+                            missed_fields: missed_fields.clone(),
+                        })
+                    }
+                }
+                InferenceDiagnostic::UnresolvedValue { expr, name } => {
+                    let (file, fn_def) = owner.source(db);
+                    // Items and macros in the body are not resolved yet, they
+                    // might define the name.
+                    let has_local_defs = fn_def.body().map_or(false, |body| {
+                        body.syntax().descendants().any(|it| {
+                            it.kind() == MACRO_CALL || ast::ModuleItem::cast(it).is_some()
+                        })
+                    });
+                    if has_local_defs {
+                        return;
+                    }
+                    let source_file = db.hir_parse(file);
+                    let path_expr = owner
+                        .body_source_map(db)
+                        .expr_syntax(*expr)
+                        .and_then(|ptr| ast::PathExpr::cast(ptr.to_node(&source_file)));
+                    if let Some(path_expr) = path_expr {
+                        sink.push(UnresolvedValue {
+                            file,
+                            expr: AstPtr::new(path_expr),
+                            name: name.clone(),
+                        })
+                    }
+                }
+                InferenceDiagnostic::UnresolvedMethod { expr, receiver_ty, method_name } => {
+                    let (file, _) = owner.source(db);
+                    let source_file = db.hir_parse(file);
+                    let name_ref = owner
+                        .body_source_map(db)
+                        .expr_syntax(*expr)
+                        .and_then(|ptr| ast::MethodCallExpr::cast(ptr.to_node(&source_file)))
+                        .and_then(|it| it.name_ref());
+                    if let Some(name_ref) = name_ref {
+                        sink.push(UnresolvedMethod {
+                            file,
+                            name_ref: AstPtr::new(name_ref),
+                            receiver_ty: receiver_ty.display(db).to_string(),
+                            method_name: method_name.clone(),
+                        })
+                    }
+                }
+                InferenceDiagnostic::TraitNotInScope { expr, receiver_ty, method_name, traits } => {
+                    let (file, _) = owner.source(db);
+                    let source_file = db.hir_parse(file);
+                    let name_ref = owner
+                        .body_source_map(db)
+                        .expr_syntax(*expr)
+                        .and_then(|ptr| ast::MethodCallExpr::cast(ptr.to_node(&source_file)))
+                        .and_then(|it| it.name_ref());
+                    if let Some(name_ref) = name_ref {
+                        sink.push(TraitNotInScope {
+                            file,
+                            name_ref: AstPtr::new(name_ref),
+                            receiver_ty: receiver_ty.display(db).to_string(),
+                            method_name: method_name.clone(),
+                            traits: traits.iter().filter_map(|t| Some((*t, t.name(db)?))).collect(),
+                        })
+                    }
+                }
+                InferenceDiagnostic::AmbiguousMethod { expr, method_name, traits } => {
+                    let (file, _) = owner.source(db);
+                    let source_file = db.hir_parse(file);
+                    let name_ref = owner
+                        .body_source_map(db)
+                        .expr_syntax(*expr)
+                        .and_then(|ptr| ast::MethodCallExpr::cast(ptr.to_node(&source_file)))
+                        .and_then(|it| it.name_ref());
+                    if let Some(name_ref) = name_ref {
+                        sink.push(AmbiguousMethod {
+                            file,
+                            name_ref: AstPtr::new(name_ref),
+                            method_name: method_name.clone(),
+                            traits: traits.iter().filter_map(|t| t.name(db)).collect(),
+                        })
+                    }
                 }
             }
         }
@@ -5,10 +5,12 @@
 //!  - Building the type for an item: This happens through the `type_for_def` query.
 //!
 //! This usually involves resolving names, collecting generic arguments etc.
-use std::iter;
+use std::{iter, sync::Arc};
+
+use ra_arena::map::ArenaMap;
 
 use crate::{
-    Function, Struct, StructField, Enum, EnumVariant, Path,
+    Function, Struct, Enum, EnumVariant, Path,
     ModuleDef, TypeAlias,
     Const, Static,
     HirDatabase,
@@ -17,8 +19,8 @@ use crate::{
     nameres::Namespace,
     resolve::{Resolver, Resolution},
     path::{PathSegment, GenericArg},
-    generics::{GenericParams, HasGenericParams},
-    adt::VariantDef, Trait
+    generics::{GenericDef, GenericParams, HasGenericParams},
+    adt::{StructFieldId, VariantDef}, Trait
 };
 use super::{Ty, primitive, FnSig, Substs, TypeCtor, TraitRef};
 
@@ -55,6 +57,19 @@ impl Ty {
                 let sig = Substs(inner_tys.into());
                 Ty::apply(TypeCtor::FnPtr, sig)
             }
+            TypeRef::DynTrait(bounds) => {
+                // Only the principal trait is kept, not the auto traits.
+                let trait_ref = bounds
+                    .iter()
+                    .find_map(|it| TraitRef::from_hir(db, resolver, it, Some(Ty::Unknown)));
+                match trait_ref {
+                    Some(trait_ref) => Ty::apply(
+                        TypeCtor::Dyn(trait_ref.trait_),
+                        Substs(trait_ref.substs.0[1..].to_vec().into()),
+                    ),
+                    None => Ty::Unknown,
+                }
+            }
             TypeRef::Error => Ty::Unknown,
         }
     }
@@ -174,11 +189,12 @@ pub(super) fn substs_from_path_segment(
     let mut substs = Vec::new();
     let parent_param_count = def_generics.count_parent_params();
     substs.extend(iter::repeat(Ty::Unknown).take(parent_param_count));
+    let has_self_type = segment.args_and_bindings.as_ref().map_or(false, |args| args.has_self_type);
+    let add_self_param = add_self_param && !has_self_type;
     if add_self_param {
         // FIXME this add_self_param argument is kind of a hack: Traits have the
-        // Self type as an implicit first type parameter, but it can't be
-        // actually provided in the type arguments
-        // (well, actually sometimes it can, in the form of type-relative paths: `<Foo as Default>::default()`)
+        // Self type as an implicit first type parameter, which can only be
+        // provided in the form of type-relative paths: `<Foo as Default>::default()`
         substs.push(Ty::Unknown);
     }
     if let Some(generic_args) = &segment.args_and_bindings {
@@ -274,16 +290,42 @@ pub(crate) fn callable_item_sig(db: &impl HirDatabase, def: CallableDef) -> FnSi
     }
 }
 
-/// Build the type of a specific field of a struct or enum variant.
-pub(crate) fn type_for_field(db: &impl HirDatabase, field: StructField) -> Ty {
-    let parent_def = field.parent_def(db);
-    let resolver = match parent_def {
+/// The trait bounds of `def`, from both the bounds on its type parameters and
+/// its where clause. Bounds of the parent item are not included.
+pub(crate) fn generic_predicates(db: &impl HirDatabase, def: GenericDef) -> Vec<TraitRef> {
+    let resolver = match def {
+        GenericDef::Function(it) => it.resolver(db),
+        GenericDef::Struct(it) => it.resolver(db),
+        GenericDef::Enum(it) => it.resolver(db),
+        GenericDef::Trait(it) => it.resolver(db),
+        GenericDef::TypeAlias(it) => it.resolver(db),
+        GenericDef::ImplBlock(it) => it.resolver(db),
+    };
+    db.generic_params(def)
+        .where_predicates
+        .iter()
+        .filter_map(|pred| {
+            let self_ty = Ty::from_hir(db, &resolver, &pred.type_ref);
+            TraitRef::from_hir(db, &resolver, &pred.trait_ref, Some(self_ty))
+        })
+        .collect()
+}
+
+/// Build the types of all fields of a struct or enum variant.
+pub(crate) fn field_types_query(
+    db: &impl HirDatabase,
+    variant: VariantDef,
+) -> Arc<ArenaMap<StructFieldId, Ty>> {
+    let resolver = match variant {
         VariantDef::Struct(it) => it.resolver(db),
         VariantDef::EnumVariant(it) => it.parent_enum(db).resolver(db),
     };
-    let var_data = parent_def.variant_data(db);
-    let type_ref = &var_data.fields().unwrap()[field.id].type_ref;
-    Ty::from_hir(db, &resolver, type_ref)
+    let var_data = variant.variant_data(db);
+    let mut res = ArenaMap::default();
+    for (id, field) in var_data.fields().into_iter().flat_map(|it| it.iter()) {
+        res.insert(id, Ty::from_hir(db, &resolver, &field.type_ref));
+    }
+    Arc::new(res)
 }
 
 fn fn_sig_for_fn(db: &impl HirDatabase, def: Function) -> FnSig {
@@ -292,6 +334,7 @@ fn fn_sig_for_fn(db: &impl HirDatabase, def: Function) -> FnSig {
     let params =
         signature.params().iter().map(|tr| Ty::from_hir(db, &resolver, tr)).collect::<Vec<_>>();
     let ret = Ty::from_hir(db, &resolver, signature.ret_type());
+    let ret = if signature.is_async() { Ty::apply_one(TypeCtor::Future, ret) } else { ret };
     FnSig::from_params_and_return(params, ret)
 }
 
@@ -5,17 +5,18 @@
 use std::sync::Arc;
 
 use rustc_hash::FxHashMap;
+use ra_syntax::{AstNode, SyntaxKind::IDENT, ast::AttrsOwner};
 
 use crate::{
-    HirDatabase, Module, Crate, Name, Function, Trait,
+    HirDatabase, Module, Crate, Name, Function, Trait, AdtDef,
     impl_block::{ImplId, ImplBlock, ImplItem},
-    ty::{Ty, TypeCtor},
-    nameres::CrateModuleId,
+    ty::{Ty, ApplicationTy, TypeCtor, Mutability},
+    nameres::{CrateModuleId, Namespace},
     resolve::Resolver,
     traits::TraitItem,
     generics::HasGenericParams,
 };
-use super::{TraitRef, Substs};
+use super::{TraitRef, Substs, object_safety};
 
 /// This is used as a key for indexing impls.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -41,6 +42,7 @@ pub struct CrateImplBlocks {
     krate: Crate,
     impls: FxHashMap<TyFingerprint, Vec<(CrateModuleId, ImplId)>>,
     impls_by_trait: FxHashMap<Trait, Vec<(CrateModuleId, ImplId)>>,
+    trait_impls_by_ty: FxHashMap<TyFingerprint, Vec<(CrateModuleId, ImplId)>>,
 }
 
 impl CrateImplBlocks {
@@ -54,6 +56,22 @@ impl CrateImplBlocks {
         )
     }
 
+    /// The impl blocks of traits for `ty`.
+    pub(crate) fn lookup_trait_impl_blocks<'a>(
+        &'a self,
+        ty: &Ty,
+    ) -> impl Iterator<Item = ImplBlock> + 'a {
+        let fingerprint = TyFingerprint::for_impl(ty);
+        fingerprint
+            .and_then(|f| self.trait_impls_by_ty.get(&f))
+            .into_iter()
+            .flat_map(|i| i.iter())
+            .map(move |(module_id, impl_id)| {
+                let module = Module { krate: self.krate, module_id: *module_id };
+                ImplBlock::from_id(module, *impl_id)
+            })
+    }
+
     pub fn lookup_impl_blocks_for_trait<'a>(
         &'a self,
         tr: &Trait,
@@ -66,6 +84,33 @@ impl CrateImplBlocks {
         )
     }
 
+    /// The traits implemented for `ty` in the crate which have a method named
+    /// `name`, whether they are in scope or not.
+    pub(crate) fn traits_with_method<'a>(
+        &'a self,
+        db: &'a impl HirDatabase,
+        ty: &Ty,
+        name: &'a Name,
+    ) -> impl Iterator<Item = Trait> + 'a {
+        self.lookup_trait_impl_blocks(ty)
+            .filter_map(move |impl_block| impl_block.target_trait_ref(db))
+            .map(|trait_ref| trait_ref.trait_)
+            .filter(move |tr| {
+                tr.items(db).into_iter().any(|item| match item {
+                    TraitItem::Function(f) => f.name(db) == *name,
+                    _ => false,
+                })
+            })
+    }
+
+    /// All the impl blocks of traits in the crate.
+    pub(crate) fn trait_impl_blocks<'a>(&'a self) -> impl Iterator<Item = ImplBlock> + 'a {
+        self.impls_by_trait.values().flat_map(|i| i.iter()).map(move |(module_id, impl_id)| {
+            let module = Module { krate: self.krate, module_id: *module_id };
+            ImplBlock::from_id(module, *impl_id)
+        })
+    }
+
     fn collect_recursive(&mut self, db: &impl HirDatabase, module: &Module) {
         let module_impl_blocks = db.impls_in_module(module.clone());
 
@@ -79,6 +124,12 @@ impl CrateImplBlocks {
                     .entry(tr.trait_)
                     .or_insert_with(Vec::new)
                     .push((module.module_id, impl_id));
+                if let Some(target_ty_fp) = TyFingerprint::for_impl(&target_ty) {
+                    self.trait_impls_by_ty
+                        .entry(target_ty_fp)
+                        .or_insert_with(Vec::new)
+                        .push((module.module_id, impl_id));
+                }
             } else {
                 if let Some(target_ty_fp) = TyFingerprint::for_impl(&target_ty) {
                     self.impls
@@ -102,6 +153,7 @@ impl CrateImplBlocks {
             krate,
             impls: FxHashMap::default(),
             impls_by_trait: FxHashMap::default(),
+            trait_impls_by_ty: FxHashMap::default(),
         };
         if let Some(module) = krate.root_module(db) {
             crate_impl_blocks.collect_recursive(db, &module);
@@ -110,26 +162,135 @@ impl CrateImplBlocks {
     }
 }
 
-fn def_crate(db: &impl HirDatabase, ty: &Ty) -> Option<Crate> {
-    match ty {
-        Ty::Apply(a_ty) => match a_ty.ctor {
-            TypeCtor::Adt(def_id) => def_id.krate(db),
-            _ => None,
-        },
-        _ => None,
+/// The crates which may contain inherent impls for `ty`. For an ADT, this is
+/// the crate defining it. Impls for builtin types like slices, raw pointers or
+/// primitives live in `core` and friends, so `krate`, the crate the lookup
+/// happens in, and all of its dependencies are searched for them.
+fn def_crates(db: &impl HirDatabase, krate: Option<Crate>, ty: &Ty) -> Vec<Crate> {
+    let a_ty = match ty {
+        Ty::Apply(a_ty) => a_ty,
+        _ => return Vec::new(),
+    };
+    match a_ty.ctor {
+        TypeCtor::Adt(def_id) => def_id.krate(db).into_iter().collect(),
+        TypeCtor::Bool
+        | TypeCtor::Char
+        | TypeCtor::Int(_)
+        | TypeCtor::Float(_)
+        | TypeCtor::Str
+        | TypeCtor::Slice
+        | TypeCtor::Array
+        | TypeCtor::RawPtr(_)
+        | TypeCtor::Never => crate_and_deps(db, krate),
+        TypeCtor::Dyn(tr) => tr.module(db).krate(db).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `krate` and all the crates it depends on, directly or not.
+fn crate_and_deps(db: &impl HirDatabase, krate: Option<Crate>) -> Vec<Crate> {
+    let mut crates: Vec<Crate> = krate.into_iter().collect();
+    let mut idx = 0;
+    while idx < crates.len() {
+        for dep in crates[idx].dependencies(db) {
+            if !crates.contains(&dep.krate) {
+                crates.push(dep.krate);
+            }
+        }
+        idx += 1;
     }
+    crates
+}
+
+/// Whether the definition of the type `def` derives the trait `trait_name`.
+fn derives(db: &impl HirDatabase, def: AdtDef, trait_name: &str) -> bool {
+    fn has_derive(node: &impl AttrsOwner, trait_name: &str) -> bool {
+        node.attrs().filter_map(|it| it.as_call()).any(|(name, args)| {
+            name == "derive"
+                && args
+                    .syntax()
+                    .children_with_tokens()
+                    .filter_map(|it| it.as_token())
+                    .any(|it| it.kind() == IDENT && it.text() == trait_name)
+        })
+    }
+    match def {
+        AdtDef::Struct(it) => has_derive(&*it.source(db).1, trait_name),
+        AdtDef::Enum(it) => has_derive(&*it.source(db).1, trait_name),
+    }
+}
+
+/// Matches the self type of an impl, `pattern`, with `ty`, recording the types
+/// of the generic parameters of the impl in `substs`.
+pub(super) fn match_impl_ty(pattern: &Ty, ty: &Ty, substs: &mut Vec<Ty>) -> bool {
+    match (pattern, ty) {
+        (Ty::Param { idx, .. }, _) => {
+            let idx = *idx as usize;
+            if substs.len() <= idx {
+                substs.resize_with(idx + 1, || Ty::Unknown);
+            }
+            substs[idx] = ty.clone();
+            true
+        }
+        (_, Ty::Unknown) | (_, Ty::Infer(_)) => true,
+        (Ty::Apply(a_pattern), Ty::Apply(a_ty)) => {
+            a_pattern.ctor == a_ty.ctor
+                && a_pattern.parameters.len() == a_ty.parameters.len()
+                && a_pattern
+                    .parameters
+                    .iter()
+                    .zip(a_ty.parameters.iter())
+                    .all(|(p, t)| match_impl_ty(p, t, substs))
+        }
+        _ => pattern == ty,
+    }
+}
+
+/// The result of looking up a method by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MethodLookup {
+    /// The method, with the actual autoderefed receiver type (but without
+    /// autoref applied yet).
+    Resolved(Ty, Function),
+    /// The receiver has no inherent method of the name, but several traits in
+    /// scope have one for it, at the same autoderef step.
+    Ambiguous(Vec<Trait>),
 }
 
 impl Ty {
-    /// Look up the method with the given name, returning the actual autoderefed
-    /// receiver type (but without autoref applied yet).
+    /// Look up the method with the given name. Like in rustc, at each autoderef
+    /// step the inherent methods take precedence over the trait methods, which
+    /// are ambiguous if they come from several traits.
     pub(crate) fn lookup_method(
         self,
         db: &impl HirDatabase,
         name: &Name,
         resolver: &Resolver,
-    ) -> Option<(Ty, Function)> {
-        self.iterate_method_candidates(db, resolver, Some(name), |ty, f| Some((ty.clone(), f)))
+    ) -> Option<MethodLookup> {
+        let krate = resolver.krate();
+        for derefed_ty in self.autoderef(db) {
+            if let Some(f) =
+                derefed_ty.iterate_inherent_methods(db, krate, Some(name), |_ty, f| Some(f))
+            {
+                return Some(MethodLookup::Resolved(derefed_ty, f));
+            }
+            let mut candidates = Vec::new();
+            derefed_ty.iterate_trait_method_candidates(db, resolver, Some(name), |_ty, f| {
+                if !candidates.contains(&f) {
+                    candidates.push(f);
+                }
+                None::<()>
+            });
+            match candidates.len() {
+                0 => (),
+                1 => return Some(MethodLookup::Resolved(derefed_ty, candidates[0])),
+                _ => {
+                    let traits = candidates.iter().filter_map(|f| f.parent_trait(db)).collect();
+                    return Some(MethodLookup::Ambiguous(traits));
+                }
+            }
+        }
+        None
     }
 
     // This would be nicer if it just returned an iterator, but that runs into
@@ -150,8 +311,11 @@ impl Ty {
         // find in the end takes &self, we still do the autoderef step (just as
         // rustc does an autoderef and then autoref again).
 
+        let krate = resolver.krate();
         for derefed_ty in self.autoderef(db) {
-            if let Some(result) = derefed_ty.iterate_inherent_methods(db, name, &mut callback) {
+            if let Some(result) =
+                derefed_ty.iterate_inherent_methods(db, krate, name, &mut callback)
+            {
                 return Some(result);
             }
             if let Some(result) =
@@ -170,7 +334,16 @@ impl Ty {
         name: Option<&Name>,
         mut callback: impl FnMut(&Ty, Function) -> Option<T>,
     ) -> Option<T> {
-        'traits: for t in resolver.traits_in_scope() {
+        // the methods of a trait object's trait can be called without the trait
+        // being in scope, but only those that are object safe
+        let dyn_trait = match self {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::Dyn(t), .. }) => Some(*t),
+            _ => None,
+        };
+        let traits = dyn_trait
+            .into_iter()
+            .chain(resolver.traits_in_scope().into_iter().filter(|t| Some(*t) != dyn_trait));
+        'traits: for t in traits {
             let data = t.trait_data(db);
             // we'll be lazy about checking whether the type implements the
             // trait, but if we find out it doesn't, we'll skip the rest of the
@@ -181,6 +354,9 @@ impl Ty {
                     &TraitItem::Function(m) => {
                         let sig = m.signature(db);
                         if name.map_or(true, |name| sig.name() == name) && sig.has_self_param() {
+                            if Some(t) == dyn_trait && !object_safety::is_dispatchable(db, m) {
+                                continue;
+                            }
                             if !known_implemented {
                                 let trait_ref = TraitRef {
                                     trait_: t,
@@ -207,33 +383,130 @@ impl Ty {
     fn iterate_inherent_methods<T>(
         &self,
         db: &impl HirDatabase,
+        krate: Option<Crate>,
         name: Option<&Name>,
         mut callback: impl FnMut(&Ty, Function) -> Option<T>,
     ) -> Option<T> {
-        let krate = match def_crate(db, self) {
-            Some(krate) => krate,
-            None => return None,
-        };
-        let impls = db.impls_in_crate(krate);
+        for krate in def_crates(db, krate, self) {
+            let impls = db.impls_in_crate(krate);
 
-        for impl_block in impls.lookup_impl_blocks(self) {
-            for item in impl_block.items(db) {
-                match item {
-                    ImplItem::Method(f) => {
-                        let sig = f.signature(db);
-                        if name.map_or(true, |name| sig.name() == name) && sig.has_self_param() {
-                            if let Some(result) = callback(self, f) {
-                                return Some(result);
+            for impl_block in impls.lookup_impl_blocks(self) {
+                for item in impl_block.items(db) {
+                    match item {
+                        ImplItem::Method(f) => {
+                            let sig = f.signature(db);
+                            if name.map_or(true, |name| sig.name() == name) && sig.has_self_param()
+                            {
+                                if let Some(result) = callback(self, f) {
+                                    return Some(result);
+                                }
                             }
                         }
+                        _ => {}
                     }
-                    _ => {}
                 }
             }
         }
         None
     }
 
+    /// The associated type `assoc_name` of the impl of the trait `trait_name`
+    /// for this type, like the `Output` of its `Index` impl, looked up in
+    /// `krate` and its dependencies. `None` if there's no such impl, or if the
+    /// associated type is unknown.
+    pub(crate) fn trait_impl_assoc_type(
+        &self,
+        db: &impl HirDatabase,
+        krate: Option<Crate>,
+        trait_name: &str,
+        assoc_name: &str,
+    ) -> Option<Ty> {
+        let (impl_block, substs) = self.trait_impl(db, krate, trait_name)?;
+        let alias = impl_block.items(db).into_iter().find_map(|item| match item {
+            ImplItem::TypeAlias(alias)
+                if alias.name(db).map_or(false, |name| name.to_string() == assoc_name) =>
+            {
+                Some(alias)
+            }
+            _ => None,
+        })?;
+        let ty = db.type_for_def(alias.into(), Namespace::Types).subst(&substs.into());
+        if ty == Ty::Unknown {
+            None
+        } else {
+            Some(ty)
+        }
+    }
+
+    /// The impl of the trait `trait_name` for this type, looked up in `krate`
+    /// and its dependencies, with the types of its generic parameters.
+    fn trait_impl(
+        &self,
+        db: &impl HirDatabase,
+        krate: Option<Crate>,
+        trait_name: &str,
+    ) -> Option<(ImplBlock, Vec<Ty>)> {
+        // FIXME recognize the traits as lang items instead of by name
+        for krate in crate_and_deps(db, krate) {
+            let impls = db.impls_in_crate(krate);
+            for impl_block in impls.lookup_trait_impl_blocks(self) {
+                let is_trait_impl = impl_block
+                    .target_trait_ref(db)
+                    .and_then(|it| it.trait_.name(db))
+                    .map_or(false, |name| name.to_string() == trait_name);
+                if !is_trait_impl {
+                    continue;
+                }
+                let mut substs = Vec::new();
+                if match_impl_ty(&impl_block.target_ty(db), self, &mut substs) {
+                    return Some((impl_block, substs));
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether the values of this type are copied rather than moved, with the
+    /// `Copy` impls looked up in `krate` and its dependencies. The types which
+    /// are not known, like type parameters, are assumed to be `Copy`.
+    pub fn is_copy(&self, db: &impl HirDatabase, krate: Option<Crate>) -> bool {
+        let a_ty = match self {
+            Ty::Apply(a_ty) => a_ty,
+            _ => return true,
+        };
+        match a_ty.ctor {
+            TypeCtor::Ref(Mutability::Mut) | TypeCtor::Str | TypeCtor::Slice | TypeCtor::Future => {
+                false
+            }
+            TypeCtor::Tuple | TypeCtor::Array => {
+                a_ty.parameters.iter().all(|it| it.is_copy(db, krate))
+            }
+            TypeCtor::Adt(def) => {
+                derives(db, def, "Copy") || self.trait_impl(db, krate, "Copy").is_some()
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether the type implements `core::default::Default`, resolved in the
+    /// scope of `resolver`, either derived or with an impl.
+    pub(crate) fn is_default(&self, db: &impl HirDatabase, resolver: &Resolver) -> bool {
+        let trait_ = match resolver
+            .resolve_known_trait(db, &["core", "default", "Default"])
+            .or_else(|| resolver.resolve_known_trait(db, &["std", "default", "Default"]))
+        {
+            Some(it) => it,
+            None => return false,
+        };
+        if let Some((def, _)) = self.as_adt() {
+            if derives(db, def, "Default") {
+                return true;
+            }
+        }
+        let trait_ref = TraitRef { trait_, substs: Substs::single(self.clone()) };
+        db.implements(trait_ref).is_some()
+    }
+
     // This would be nicer if it just returned an iterator, but that runs into
     // lifetime problems, because we need to borrow temp `CrateImplBlocks`.
     pub fn iterate_impl_items<T>(
@@ -241,13 +514,30 @@ impl Ty {
         db: &impl HirDatabase,
         mut callback: impl FnMut(ImplItem) -> Option<T>,
     ) -> Option<T> {
-        let krate = def_crate(db, &self)?;
-        let impls = db.impls_in_crate(krate);
+        // FIXME: find the impls of builtin types too, this needs the crate
+        // the lookup happens in
+        let krates = def_crates(db, None, &self);
+        for krate in &krates {
+            let impls = db.impls_in_crate(*krate);
 
-        for impl_block in impls.lookup_impl_blocks(&self) {
-            for item in impl_block.items(db) {
-                if let Some(result) = callback(item) {
-                    return Some(result);
+            for impl_block in impls.lookup_impl_blocks(&self) {
+                for item in impl_block.items(db) {
+                    if let Some(result) = callback(item) {
+                        return Some(result);
+                    }
+                }
+            }
+        }
+        // inherent items take precedence over the items of trait impls
+        // FIXME: trait impls may also be in crates depending on `krates`
+        for krate in krates {
+            let impls = db.impls_in_crate(krate);
+
+            for impl_block in impls.lookup_trait_impl_blocks(&self) {
+                for item in impl_block.items(db) {
+                    if let Some(result) = callback(item) {
+                        return Some(result);
+                    }
                 }
             }
         }
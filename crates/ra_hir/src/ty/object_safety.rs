@@ -0,0 +1,207 @@
+//! Object safety: which traits can be the type of trait objects, `dyn Trait`,
+//! and which of their methods can be called on trait objects. See the
+//! `object_safety` module of rustc.
+use std::fmt;
+
+use ra_syntax::{
+    AstPtr, SyntaxNode,
+    ast::{self, AstNode, TypeBoundsOwner},
+};
+
+use crate::{
+    Function, HirDatabase, HirFileId, Name, Trait,
+    diagnostics::{DiagnosticSink, NotObjectSafe},
+    generics::{GenericParams, HasGenericParams},
+    path::GenericArg,
+    resolve::Resolver,
+    traits::TraitItem,
+    type_ref::TypeRef,
+};
+use super::{Ty, TypeCtor};
+
+/// Why a trait can't be the type of trait objects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectSafetyViolation {
+    /// The trait requires `Self: Sized`, as a supertrait or in a where clause.
+    SizedSelf,
+    /// The trait has an associated const.
+    AssocConst(Name),
+    /// A method which isn't excluded from trait objects by `where Self: Sized`
+    /// can't be called on them.
+    Method(Name, MethodViolation),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodViolation {
+    /// An associated function without a `self` parameter.
+    StaticMethod,
+    /// A method with type parameters.
+    Generic,
+    /// A method with `Self` in the types of its parameters, other than the
+    /// receiver, or in its return type.
+    ReferencesSelf,
+}
+
+impl fmt::Display for ObjectSafetyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjectSafetyViolation::SizedSelf => write!(f, "it requires `Self: Sized`"),
+            ObjectSafetyViolation::AssocConst(name) => {
+                write!(f, "it contains the associated const `{}`", name)
+            }
+            ObjectSafetyViolation::Method(name, MethodViolation::StaticMethod) => {
+                write!(f, "associated function `{}` has no `self` parameter", name)
+            }
+            ObjectSafetyViolation::Method(name, MethodViolation::Generic) => {
+                write!(f, "method `{}` has generic type parameters", name)
+            }
+            ObjectSafetyViolation::Method(name, MethodViolation::ReferencesSelf) => write!(
+                f,
+                "method `{}` references the `Self` type in its parameters or return type",
+                name
+            ),
+        }
+    }
+}
+
+pub(crate) fn object_safety_violations(
+    db: &impl HirDatabase,
+    tr: Trait,
+) -> Vec<ObjectSafetyViolation> {
+    let mut res = Vec::new();
+    let supertraits_sized = tr
+        .source(db)
+        .1
+        .type_bound_list()
+        .into_iter()
+        .flat_map(|it| it.bounds())
+        .filter_map(|it| it.type_ref())
+        .any(|it| is_sized(&TypeRef::from_ast(it)));
+    if supertraits_sized || requires_sized_self(&tr.generic_params(db)) {
+        res.push(ObjectSafetyViolation::SizedSelf);
+    }
+    for item in tr.items(db) {
+        match item {
+            TraitItem::Const(it) => {
+                res.push(ObjectSafetyViolation::AssocConst(it.signature(db).name().clone()))
+            }
+            TraitItem::Function(it) => {
+                if requires_sized_self(&it.generic_params(db)) {
+                    continue;
+                }
+                if let Some(violation) = method_violation(db, it) {
+                    res.push(ObjectSafetyViolation::Method(it.name(db), violation));
+                }
+            }
+            TraitItem::TypeAlias(_) => {}
+        }
+    }
+    res
+}
+
+/// Whether the trait method `func` can be called on trait objects.
+pub(crate) fn is_dispatchable(db: &impl HirDatabase, func: Function) -> bool {
+    !requires_sized_self(&func.generic_params(db)) && method_violation(db, func).is_none()
+}
+
+fn method_violation(db: &impl HirDatabase, func: Function) -> Option<MethodViolation> {
+    let sig = func.signature(db);
+    if !sig.has_self_param() {
+        return Some(MethodViolation::StaticMethod);
+    }
+    if !func.generic_params(db).params.is_empty() {
+        return Some(MethodViolation::Generic);
+    }
+    let mut types = sig.params()[1..].iter().chain(std::iter::once(sig.ret_type()));
+    if types.any(mentions_self) {
+        return Some(MethodViolation::ReferencesSelf);
+    }
+    None
+}
+
+/// Whether the own where clauses of an item require `Self: Sized`.
+fn requires_sized_self(generics: &GenericParams) -> bool {
+    generics
+        .where_predicates
+        .iter()
+        .any(|pred| is_self(&pred.type_ref) && is_sized(&pred.trait_ref))
+}
+
+fn is_self(type_ref: &TypeRef) -> bool {
+    match type_ref {
+        TypeRef::Path(path) => path.as_ident() == Some(&Name::self_type()),
+        _ => false,
+    }
+}
+
+fn is_sized(type_ref: &TypeRef) -> bool {
+    match type_ref {
+        TypeRef::Path(path) => {
+            path.segments.last().map_or(false, |it| it.name.to_string() == "Sized")
+        }
+        _ => false,
+    }
+}
+
+/// Whether `type_ref` contains the `Self` type, but not as the self type of
+/// an associated type, like `Self::Item`.
+fn mentions_self(type_ref: &TypeRef) -> bool {
+    match type_ref {
+        TypeRef::Path(path) => {
+            is_self(type_ref)
+                || path
+                    .segments
+                    .iter()
+                    .filter_map(|it| it.args_and_bindings.as_ref())
+                    .flat_map(|it| {
+                        it.args
+                            .iter()
+                            .map(|GenericArg::Type(it)| it)
+                            .chain(it.bindings.iter().map(|(_, it)| it))
+                    })
+                    .any(mentions_self)
+        }
+        TypeRef::Tuple(inner) | TypeRef::Fn(inner) | TypeRef::DynTrait(inner) => {
+            inner.iter().any(mentions_self)
+        }
+        TypeRef::RawPtr(inner, _)
+        | TypeRef::Reference(inner, _)
+        | TypeRef::Array(inner)
+        | TypeRef::Slice(inner) => mentions_self(inner),
+        TypeRef::Never | TypeRef::Placeholder | TypeRef::Error => false,
+    }
+}
+
+/// Reports the trait object types, in the syntax of an item, whose traits
+/// aren't object safe.
+pub(crate) fn add_diagnostics(
+    db: &impl HirDatabase,
+    file: HirFileId,
+    node: &SyntaxNode,
+    resolver: &Resolver,
+    sink: &mut DiagnosticSink,
+) {
+    for dyn_type in node.descendants().filter_map(ast::DynTraitType::cast) {
+        let type_ref = match ast::TypeRef::cast(dyn_type.syntax()) {
+            Some(it) => TypeRef::from_ast(it),
+            None => continue,
+        };
+        let tr = match Ty::from_hir(db, resolver, &type_ref) {
+            Ty::Apply(a_ty) => match a_ty.ctor {
+                TypeCtor::Dyn(tr) => tr,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let violations = object_safety_violations(db, tr);
+        if violations.is_empty() {
+            continue;
+        }
+        sink.push(NotObjectSafe {
+            file,
+            dyn_type: AstPtr::new(dyn_type),
+            trait_name: tr.name(db).unwrap_or_else(Name::missing),
+            violations,
+        });
+    }
+}
@@ -38,7 +38,7 @@ fn test(a: u32, b: isize, c: !, d: &str) {
 [17; 18) 'b': isize
 [27; 28) 'c': !
 [33; 34) 'd': &str
-[42; 121) '{     ...f32; }': ()
+[42; 121) '{     ...f32; }': !
 [48; 49) 'a': u32
 [55; 56) 'b': isize
 [62; 63) 'c': !
@@ -574,7 +574,7 @@ fn main(foo: Foo) {
 [51; 107) 'if tru...     }': ()
 [54; 58) 'true': bool
 [59; 67) '{      }': ()
-[73; 107) 'if fal...     }': i32
+[73; 107) 'if fal...     }': ()
 [76; 81) 'false': bool
 [82; 107) '{     ...     }': i32
 [92; 95) 'foo': Foo
@@ -582,6 +582,56 @@ fn main(foo: Foo) {
     )
 }
 
+#[test]
+fn infer_if_match_branches_unified() {
+    assert_snapshot_matches!(
+        infer(r#"
+enum Option<T> { Some(T), None }
+fn test(x: Option<u32>, c: bool) {
+    let a = match x {
+        Option::None => return,
+        Option::Some(v) => v,
+    };
+    let b = if c { return; } else { 1u64 };
+    let d = if c { 1 } else { 2i8 };
+    let e = match c { _ => loop {} };
+}
+"#),
+        @r###"
+[42; 43) 'x': Option<u32>
+[58; 59) 'c': bool
+[67; 280) '{     ...} }; }': ()
+[77; 78) 'a': u32
+[81; 158) 'match ...     }': u32
+[87; 88) 'x': Option<u32>
+[99; 111) 'Option::None': Option<u32>
+[115; 121) 'return': !
+[131; 146) 'Option::Some(v)': Option<u32>
+[144; 145) 'v': u32
+[150; 151) 'v': u32
+[168; 169) 'b': u64
+[172; 202) 'if c {...1u64 }': u64
+[175; 176) 'c': bool
+[177; 188) '{ return; }': !
+[179; 185) 'return': !
+[194; 202) '{ 1u64 }': u64
+[196; 200) '1u64': u64
+[212; 213) 'd': i8
+[216; 239) 'if c {... 2i8 }': i8
+[219; 220) 'c': bool
+[221; 226) '{ 1 }': i8
+[223; 224) '1': i8
+[232; 239) '{ 2i8 }': i8
+[234; 237) '2i8': i8
+[249; 250) 'e': !
+[253; 277) 'match ...p {} }': !
+[259; 260) 'c': bool
+[263; 264) '_': bool
+[268; 275) 'loop {}': !
+[273; 275) '{}': ()"###
+    )
+}
+
 #[test]
 fn infer_inherent_method() {
     assert_snapshot_matches!(
@@ -746,7 +796,7 @@ fn test(x: &str, y: isize) {
 [260; 263) '"b"': &str
 [275; 276) 'x': [u8;_]
 [288; 290) '[]': [u8;_]
-[300; 301) 'z': &[u8;_]
+[300; 301) 'z': &[u8]
 [311; 321) '&[1, 2, 3]': &[u8;_]
 [312; 321) '[1, 2, 3]': [u8;_]
 [313; 314) '1': u8
@@ -811,8 +861,8 @@ fn test(x: &i32) {
 [177; 205) '{     ...     }': ()
 [191; 192) 'h': {unknown}
 [195; 198) 'val': {unknown}
-[215; 221) 'lambda': {unknown}
-[224; 256) '|a: u6...b; c }': {unknown}
+[215; 221) 'lambda': |u64, u64, i32| -> i32
+[224; 256) '|a: u6...b; c }': |u64, u64, i32| -> i32
 [225; 226) 'a': u64
 [233; 234) 'b': u64
 [236; 237) 'c': i32
@@ -1277,8 +1327,8 @@ fn test() {
 [241; 252) 'Struct::FOO': u32
 [262; 263) 'y': u32
 [266; 275) 'Enum::BAR': u32
-[285; 286) 'z': {unknown}
-[289; 302) 'TraitTest::ID': {unknown}"###
+[285; 286) 'z': u32
+[289; 302) 'TraitTest::ID': u32"###
     );
 }
 
@@ -1426,6 +1476,106 @@ fn test() {
     );
 }
 
+#[test]
+fn infer_self_in_impl_body() {
+    assert_snapshot_matches!(
+        infer(r#"
+struct Gen<T> {
+    val: T
+}
+
+impl<T> Gen<T> {
+    const ZERO: u32 = 0;
+
+    fn make(val: T) -> Self {
+        Self { val }
+    }
+
+    fn copy(&self) -> Gen<T> {
+        let zero = Self::ZERO;
+        let Self { val } = self;
+        Self::make(self.val)
+    }
+}
+"#),
+        @r###"
+[70; 71) '0': u32
+[86; 89) 'val': T
+[102; 130) '{     ...     }': Gen<T>
+[112; 124) 'Self { val }': Gen<T>
+[119; 122) 'val': T
+[145; 149) 'self': &Gen<T>
+[161; 261) '{     ...     }': Gen<T>
+[175; 179) 'zero': u32
+[182; 192) 'Self::ZERO': u32
+[206; 218) 'Self { val }': Gen<T>
+[213; 216) 'val': &T
+[221; 225) 'self': &Gen<T>
+[235; 245) 'Self::make': fn make<T>(T) -> Gen<T>
+[235; 255) 'Self::...f.val)': Gen<T>
+[246; 250) 'self': &Gen<T>
+[246; 254) 'self.val': T"###
+    );
+}
+
+#[test]
+fn infer_trait_assoc_item_paths() {
+    assert_snapshot_matches!(
+        infer(r#"
+trait Trait<T> {
+    const ID: u32;
+    fn make(t: T) -> Self;
+    fn get(&self) -> T;
+}
+struct S;
+impl Trait<u8> for S {
+    const ID: u32 = 1;
+    fn make(t: u8) -> S { S }
+    fn get(&self) -> u8 { 0 }
+}
+fn test(s: S) {
+    let a = S::get(&s);
+    let b = Trait::get(&s);
+    let c = <S as Trait<u64>>::make(1);
+    let d: S = Trait::make(1u8);
+    let e = <S as Trait<u8>>::ID;
+}
+"#),
+        @r###"
+[49; 50) 't': T
+[76; 80) 'self': &Self
+[143; 144) '1': u32
+[158; 159) 't': u8
+[170; 175) '{ S }': S
+[172; 173) 'S': S
+[188; 192) 'self': &S
+[200; 205) '{ 0 }': u8
+[202; 203) '0': u8
+[216; 217) 's': S
+[222; 384) '{     ...:ID; }': ()
+[232; 233) 'a': u8
+[236; 242) 'S::get': fn get(&S) -> u8
+[236; 246) 'S::get(&s)': u8
+[243; 245) '&s': &S
+[244; 245) 's': S
+[256; 257) 'b': u8
+[260; 270) 'Trait::get': fn get<S, u8>(&Self) -> T
+[260; 274) 'Trait::get(&s)': u8
+[271; 273) '&s': &S
+[272; 273) 's': S
+[284; 285) 'c': S
+[288; 311) '<S as ...::make': fn make<S, u64>(T) -> Self
+[288; 314) '<S as ...ake(1)': S
+[312; 313) '1': u64
+[324; 325) 'd': S
+[331; 342) 'Trait::make': fn make<S, u8>(T) -> Self
+[331; 347) 'Trait:...e(1u8)': S
+[343; 346) '1u8': u8
+[357; 358) 'e': u32
+[361; 381) '<S as ...>>::ID': u32"###
+    );
+}
+
 #[test]
 fn infer_type_alias() {
     assert_snapshot_matches!(
@@ -1863,8 +2013,8 @@ fn test() {
 [49; 50) '0': u32
 [80; 83) '101': u32
 [95; 213) '{     ...NST; }': ()
-[138; 139) 'x': {unknown}
-[142; 153) 'LOCAL_CONST': {unknown}
+[138; 139) 'x': u32
+[142; 153) 'LOCAL_CONST': u32
 [163; 164) 'z': u32
 [167; 179) 'GLOBAL_CONST': u32
 [189; 191) 'id': u32
@@ -1892,10 +2042,10 @@ fn test() {
 [29; 32) '101': u32
 [70; 73) '101': u32
 [85; 280) '{     ...MUT; }': ()
-[173; 174) 'x': {unknown}
-[177; 189) 'LOCAL_STATIC': {unknown}
-[199; 200) 'y': {unknown}
-[203; 219) 'LOCAL_...IC_MUT': {unknown}
+[173; 174) 'x': u32
+[177; 189) 'LOCAL_STATIC': u32
+[199; 200) 'y': u32
+[203; 219) 'LOCAL_...IC_MUT': u32
 [229; 230) 'z': u32
 [233; 246) 'GLOBAL_STATIC': u32
 [256; 257) 'w': u32
@@ -1905,6 +2055,45 @@ fn test() {
     );
 }
 
+#[test]
+fn infer_value_paths() {
+    assert_snapshot_matches!(
+        infer(r#"
+enum E { A, B(u32, u64) }
+fn test() {
+    let a = E::A;
+    let b = E::B;
+    let c = E::B(1, 2);
+    let d = {
+        let x = LATER;
+        static LATER: u8 = 0;
+        x
+    };
+    let e = LATER;
+}
+"#),
+        @r###"
+[37; 203) '{     ...TER; }': ()
+[47; 48) 'a': E
+[51; 55) 'E::A': E
+[65; 66) 'b': B(u32, u64) -> E
+[69; 73) 'E::B': B(u32, u64) -> E
+[83; 84) 'c': E
+[87; 91) 'E::B': B(u32, u64) -> E
+[87; 97) 'E::B(1, 2)': E
+[92; 93) '1': u32
+[95; 96) '2': u64
+[107; 108) 'd': u8
+[111; 181) '{     ...     }': u8
+[125; 126) 'x': u8
+[129; 134) 'LATER': u8
+[174; 175) 'x': u8
+[191; 192) 'e': {unknown}
+[195; 200) 'LATER': {unknown}
+[163; 164) '0': u8"###
+    );
+}
+
 #[test]
 fn infer_trait_method_simple() {
     // the trait implementation is intentionally incomplete -- it shouldn't matter
@@ -2082,14 +2271,14 @@ fn test() {
         @r###"
 [87; 193) '{     ...t(); }': ()
 [97; 99) 's1': S
-[105; 121) 'Defaul...efault': {unknown}
+[105; 121) 'Defaul...efault': fn default<S>() -> Self
 [105; 123) 'Defaul...ault()': S
 [133; 135) 's2': {unknown}
 [138; 148) 'S::default': {unknown}
 [138; 150) 'S::default()': {unknown}
-[160; 162) 's3': {unknown}
-[165; 188) '<S as ...efault': {unknown}
-[165; 190) '<S as ...ault()': {unknown}"###
+[160; 162) 's3': S
+[165; 188) '<S as ...efault': fn default<S>() -> Self
+[165; 190) '<S as ...ault()': S"###
     );
 }
 
@@ -2211,9 +2400,9 @@ fn test() {
 [144; 145) 'y': u64
 [153; 154) 'S': S
 [153; 161) 'S.into()': u64
-[171; 172) 'z': {unknown}
-[175; 192) 'Into::...::into': {unknown}
-[175; 195) 'Into::...nto(S)': {unknown}
+[171; 172) 'z': u64
+[175; 192) 'Into::...::into': fn into<S, u64>(Self) -> T
+[175; 195) 'Into::...nto(S)': u64
 [193; 194) 'S': S"###
     );
 }
@@ -2303,6 +2492,54 @@ fn test() -> u64 {
     );
 }
 
+#[test]
+fn infer_calls_of_callable_values() {
+    assert_snapshot_matches!(
+        infer(r#"
+fn apply<F: FnOnce(u8) -> u16, G>(f: F, g: G, h: fn(u32) -> u64) where G: Fn(&u8) {
+    f(1);
+    g(&2);
+    h(3);
+}
+fn test() {
+    let closure = |x: u8| x as i64;
+    closure(1);
+    let ptr: fn() -> bool = || true;
+    ptr();
+}
+"#),
+        @r###"
+[35; 36) 'f': F
+[41; 42) 'g': G
+[47; 48) 'h': fn(u32) -> u64
+[83; 117) '{     ...(3); }': ()
+[89; 90) 'f': F
+[89; 93) 'f(1)': u16
+[91; 92) '1': u8
+[99; 100) 'g': G
+[99; 104) 'g(&2)': ()
+[101; 103) '&2': &u8
+[102; 103) '2': u8
+[110; 111) 'h': fn(u32) -> u64
+[110; 114) 'h(3)': u64
+[112; 113) '3': u32
+[128; 231) '{     ...r(); }': ()
+[138; 145) 'closure': |u8| -> i64
+[148; 164) '|x: u8...as i64': |u8| -> i64
+[149; 150) 'x': u8
+[156; 157) 'x': u8
+[156; 164) 'x as i64': i64
+[170; 177) 'closure': |u8| -> i64
+[170; 180) 'closure(1)': i64
+[178; 179) '1': u8
+[190; 193) 'ptr': fn() -> bool
+[210; 217) '|| true': || -> bool
+[213; 217) 'true': bool
+[223; 226) 'ptr': fn() -> bool
+[223; 228) 'ptr()': bool"###
+    );
+}
+
 #[test]
 fn tuple_struct_with_fn() {
     assert_snapshot_matches!(
@@ -2319,11 +2556,11 @@ fn test() -> u64 {
 [54; 55) 'a': S
 [58; 59) 'S': S(fn(u32) -> u64) -> S
 [58; 68) 'S(|i| 2*i)': S
-[60; 67) '|i| 2*i': fn(u32) -> u64
-[61; 62) 'i': i32
-[64; 65) '2': i32
-[64; 67) '2*i': i32
-[66; 67) 'i': i32
+[60; 67) '|i| 2*i': |u32| -> u64
+[61; 62) 'i': u32
+[64; 65) '2': u32
+[64; 67) '2*i': u32
+[66; 67) 'i': u32
 [78; 79) 'b': u64
 [82; 83) 'a': S
 [82; 85) 'a.0': fn(u32) -> u64
@@ -2396,6 +2633,376 @@ fn test() { (&S).foo()<|>; }
     assert_eq!(t, "u128");
 }
 
+#[test]
+fn method_resolution_impl_before_ambiguous_traits() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Trait1 { fn foo(self) -> u128; }
+trait Trait2 { fn foo(self) -> u64; }
+struct S;
+impl S { fn foo(self) -> i8 { 0 } }
+impl Trait1 for S { fn foo(self) -> u128 { 0 } }
+impl Trait2 for S { fn foo(self) -> u64 { 0 } }
+fn test() { S.foo()<|>; }
+"#,
+    );
+    assert_eq!(t, "i8");
+}
+
+#[test]
+fn method_resolution_ambiguous_traits() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Trait1 { fn foo(self) -> u128; }
+trait Trait2 { fn foo(self) -> u64; }
+trait Trait3 { fn foo(self) -> u32; }
+struct S;
+impl Trait1 for S { fn foo(self) -> u128 { 0 } }
+impl Trait2 for S { fn foo(self) -> u64 { 0 } }
+fn test() { S.foo()<|>; }
+"#,
+    );
+    assert_eq!(t, "{unknown}");
+}
+
+#[test]
+fn method_resolution_on_builtin_types() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+fn test(s: &[u8], p: *const u32) {
+    (s.len(), p.is_null(), p.read())<|>;
+}
+
+//- /core.rs
+impl<T> [T] {
+    pub fn len(&self) -> usize { 0 }
+}
+impl<T> *const T {
+    pub fn is_null(self) -> bool { false }
+    pub fn read(self) -> T { loop {} }
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["core"]),
+        "core": ("/core.rs", []),
+    });
+    assert_eq!("(usize, bool, u32)", type_at_pos(&db, pos));
+}
+
+#[test]
+fn infer_index_expressions() {
+    assert_snapshot_matches!(
+        infer(r#"
+trait Index<Idx> { type Output; }
+trait Deref { type Target; }
+struct Vec<T> {}
+impl<T> Deref for Vec<T> { type Target = [T]; }
+impl<T, I: SliceIndex<[T]>> Index<I> for Vec<T> { type Output = I::Output; }
+struct HashMap<K, V> {}
+impl<K, V> Index<&K> for HashMap<K, V> { type Output = V; }
+
+fn test(v: Vec<u32>, a: [u8; 2], s: &str, m: &HashMap<u8, i64>) {
+    let i = 0;
+    let v0 = v[i];
+    let vs = &v[1..2];
+    let a0 = a[0];
+    let a_all = &a[..];
+    let s1 = &s[1..];
+    let m1 = m[&1];
+}
+"#),
+        @r###"
+[299; 300) 'v': Vec<u32>
+[312; 313) 'a': [u8;_]
+[324; 325) 's': &str
+[333; 334) 'm': &HashMap<u8, i64>
+[355; 500) '{     ...&1]; }': ()
+[365; 366) 'i': usize
+[369; 370) '0': usize
+[380; 382) 'v0': u32
+[385; 386) 'v': Vec<u32>
+[385; 389) 'v[i]': u32
+[387; 388) 'i': usize
+[399; 401) 'vs': &[u32]
+[404; 412) '&v[1..2]': &[u32]
+[405; 406) 'v': Vec<u32>
+[405; 412) 'v[1..2]': [u32]
+[407; 408) '1': i32
+[407; 411) '1..2': {unknown}
+[410; 411) '2': i32
+[422; 424) 'a0': u8
+[427; 428) 'a': [u8;_]
+[427; 431) 'a[0]': u8
+[429; 430) '0': usize
+[441; 446) 'a_all': &[u8]
+[449; 455) '&a[..]': &[u8]
+[450; 451) 'a': [u8;_]
+[450; 455) 'a[..]': [u8]
+[452; 454) '..': {unknown}
+[465; 467) 's1': &str
+[470; 477) '&s[1..]': &str
+[471; 472) 's': &str
+[471; 477) 's[1..]': str
+[473; 474) '1': i32
+[473; 476) '1..': {unknown}
+[487; 489) 'm1': i64
+[492; 493) 'm': &HashMap<u8, i64>
+[492; 497) 'm[&1]': i64
+[494; 496) '&1': &i32
+[495; 496) '1': i32"###
+    );
+}
+
+#[test]
+fn infer_for_loop_items() {
+    assert_snapshot_matches!(
+        infer(r#"
+trait IntoIterator { type Item; }
+trait Iterator { type Item; }
+struct Vec<T> {}
+impl<T> IntoIterator for Vec<T> { type Item = T; }
+impl<'a, T> IntoIterator for &'a Vec<T> { type Item = &'a T; }
+struct Chars {}
+impl Iterator for Chars { type Item = char; }
+
+fn test(v: Vec<u32>, r: &Vec<i8>, s: &[u8], a: &mut [i16; 2], c: Chars) {
+    for x in v {}
+    for x in r {}
+    for x in s {}
+    for x in a {}
+    for x in c {}
+}
+"#),
+        @r###"
+[267; 268) 'v': Vec<u32>
+[280; 281) 'r': &Vec<i8>
+[293; 294) 's': &[u8]
+[303; 304) 'a': &mut [i16;_]
+[321; 322) 'c': Chars
+[331; 424) '{     ...c {} }': ()
+[337; 350) 'for x in v {}': ()
+[341; 342) 'x': u32
+[346; 347) 'v': Vec<u32>
+[348; 350) '{}': ()
+[355; 368) 'for x in r {}': ()
+[359; 360) 'x': &i8
+[364; 365) 'r': &Vec<i8>
+[366; 368) '{}': ()
+[373; 386) 'for x in s {}': ()
+[377; 378) 'x': &u8
+[382; 383) 's': &[u8]
+[384; 386) '{}': ()
+[391; 404) 'for x in a {}': ()
+[395; 396) 'x': &mut i16
+[400; 401) 'a': &mut [i16;_]
+[402; 404) '{}': ()
+[409; 422) 'for x in c {}': ()
+[413; 414) 'x': char
+[418; 419) 'c': Chars
+[420; 422) '{}': ()"###
+    );
+}
+
+#[test]
+fn infer_generic_constructors_from_expected_type() {
+    assert_snapshot_matches!(
+        infer(r#"
+enum Option<T> { Some(T), None }
+use Option::*;
+struct Vec<T> {}
+impl<T> Vec<T> { fn new() -> Vec<T> { loop {} } }
+struct Wrap<T> { t: T }
+impl<T> Wrap<T> { fn new(t: T) -> Wrap<T> { loop {} } }
+fn test() {
+    let a: Option<Vec<u8>> = Some(Vec::new());
+    let b: Option<fn(u32) -> u32> = Some(|x| x);
+    let c: Wrap<fn(u8) -> u8> = Wrap::new(|y| y);
+    let d: Wrap<fn(u16) -> u16> = Wrap { t: |z| z };
+}
+"#),
+        @r###"
+[102; 113) '{ loop {} }': !
+[104; 111) 'loop {}': !
+[109; 111) '{}': ()
+[165; 166) 't': T
+[182; 193) '{ loop {} }': !
+[184; 191) 'loop {}': !
+[189; 191) '{}': ()
+[206; 408) '{     ...z }; }': ()
+[216; 217) 'a': Option<Vec<u8>>
+[237; 241) 'Some': Some<Vec<u8>>(T) -> Option<T>
+[237; 253) 'Some(V...new())': Option<Vec<u8>>
+[242; 250) 'Vec::new': fn new<u8>() -> Vec<T>
+[242; 252) 'Vec::new()': Vec<u8>
+[263; 264) 'b': Option<fn(u32) -> u32>
+[291; 295) 'Some': Some<fn(u32) -> u32>(T) -> Option<T>
+[291; 302) 'Some(|x| x)': Option<fn(u32) -> u32>
+[296; 301) '|x| x': |u32| -> u32
+[297; 298) 'x': u32
+[300; 301) 'x': u32
+[312; 313) 'c': Wrap<fn(u8) -> u8>
+[336; 345) 'Wrap::new': fn new<fn(u8) -> u8>(T) -> Wrap<T>
+[336; 352) 'Wrap::...|y| y)': Wrap<fn(u8) -> u8>
+[346; 351) '|y| y': |u8| -> u8
+[347; 348) 'y': u8
+[350; 351) 'y': u8
+[362; 363) 'd': Wrap<fn(u16) -> u16>
+[388; 405) 'Wrap {...z| z }': Wrap<fn(u16) -> u16>
+[398; 403) '|z| z': |u16| -> u16
+[399; 400) 'z': u16
+[402; 403) 'z': u16"###
+    );
+}
+
+#[test]
+fn display_forms() {
+    let (db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+mod foo {
+    pub trait Trait<T> {}
+    pub struct Wrapper<T>(T);
+    impl<T> Trait<u32> for Wrapper<T> {}
+}
+use foo::Wrapper;
+trait Clone {}
+fn bar<T: foo::Trait<u32>>(t: T) -> Wrapper<Wrapper<T>> where T: Clone { Wrapper(Wrapper(t)) }
+fn test() { bar(Wrapper(Wrapper(1u64)))<|>; bar::<Wrapper<u8>>; }
+"#,
+    );
+    let file = db.parse(pos.file_id);
+    let type_of = |text: &str| {
+        let expr = file
+            .syntax()
+            .descendants()
+            .filter_map(ast::Expr::cast)
+            .find(|it| it.syntax().text() == text)
+            .unwrap();
+        let analyzer = SourceAnalyzer::new(&db, pos.file_id, expr.syntax(), None);
+        analyzer.type_of(&db, expr).unwrap()
+    };
+    let ty = type_of("bar(Wrapper(Wrapper(1u64)))");
+    assert_eq!(ty.display(&db).to_string(), "Wrapper<Wrapper<Wrapper<Wrapper<u64>>>>");
+    assert_eq!(ty.display_truncated(&db, 16).to_string(), "Wrapper<Wrapper<…>>");
+    let module = crate::source_binder::module_from_position(&db, pos).unwrap();
+    assert_eq!(
+        ty.display_source_code(&db, module).to_string(),
+        "Wrapper<Wrapper<Wrapper<Wrapper<u64>>>>"
+    );
+
+    let ty = type_of("bar::<Wrapper<u8>>");
+    assert_eq!(
+        ty.display_with_where_clauses(&db).to_string(),
+        "fn bar<Wrapper<u8>>(T) -> Wrapper<Wrapper<T>> \
+         where Wrapper<u8>: Trait<u32>, Wrapper<u8>: Clone"
+    );
+    assert_eq!(ty.display_truncated(&db, 8).to_string(), "fn bar<Wrapper<…>>(…) -> …");
+}
+
+#[test]
+fn method_resolution_dyn_trait() {
+    let t = type_at(
+        r#"
+//- /main.rs
+mod foo {
+    pub trait Trait<T> { fn foo(&self) -> T; }
+}
+fn test(x: &dyn foo::Trait<u64>) { x.foo()<|>; }
+"#,
+    );
+    assert_eq!(t, "u64");
+}
+
+#[test]
+fn infer_dyn_trait_param() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Trait<T> {}
+fn test(x: &dyn Trait<u64>) { x<|>; }
+"#,
+    );
+    assert_eq!(t, "&dyn Trait<u64>");
+}
+
+#[test]
+fn method_resolution_dyn_trait_skips_non_object_safe() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Trait {
+    fn generic<T>(&self) -> u32;
+    fn sized(&self) -> u32 where Self: Sized;
+}
+fn test(x: &dyn Trait) { (x.generic(), x.sized())<|>; }
+"#,
+    );
+    assert_eq!(t, "({unknown}, {unknown})");
+}
+
+#[test]
+fn infer_async_fn() {
+    assert_snapshot_matches!(
+        infer(r#"
+async fn foo() -> u64 {
+    128
+}
+async fn test() {
+    let x = foo();
+    let y = foo().await;
+    let z = async { y }.await;
+}
+"#),
+        @r###"
+[23; 34) '{     128 }': u64
+[29; 32) '128': u64
+[51; 129) '{     ...ait; }': ()
+[61; 62) 'x': impl Future<Output = u64>
+[65; 68) 'foo': fn foo() -> impl Future<Output = u64>
+[65; 70) 'foo()': impl Future<Output = u64>
+[80; 81) 'y': u64
+[84; 87) 'foo': fn foo() -> impl Future<Output = u64>
+[84; 89) 'foo()': impl Future<Output = u64>
+[84; 95) 'foo().await': u64
+[105; 106) 'z': u64
+[109; 120) 'async { y }': impl Future<Output = u64>
+[109; 126) 'async ....await': u64
+[115; 120) '{ y }': u64
+[117; 118) 'y': u64"###
+    );
+}
+
+#[test]
+fn infer_await_future_impl() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Future { type Output; }
+struct Ready<T>(T);
+impl<T> Future for Ready<T> { type Output = T; }
+async fn test() { Ready(1u8).await<|>; }
+"#,
+    );
+    assert_eq!(t, "u8");
+}
+
+#[test]
+fn infer_async_method_call() {
+    let t = type_at(
+        r#"
+//- /main.rs
+struct S;
+impl S { async fn foo(&self) -> i8 { 0 } }
+async fn test(s: S) { s.foo()<|>; }
+"#,
+    );
+    assert_eq!(t, "impl Future<Output = i8>");
+}
+
 fn type_at_pos(db: &MockDatabase, pos: FilePosition) -> String {
     let file = db.parse(pos.file_id);
     let expr = algo::find_node_at_offset::<ast::Expr>(file.syntax(), pos.offset).unwrap();
@@ -2537,7 +3144,400 @@ fn no_such_field_diagnostics() {
     .diagnostics();
 
     assert_snapshot_matches!(diagnostics, @r###"
+"{\n            foo: 92,\n            baz: 62,\n        }": missing structure fields:
+- bar
 "baz: 62": no such field
 "###
     );
 }
+
+#[test]
+fn unresolved_value_diagnostics() {
+    let mut db = MockDatabase::with_files(
+        r"
+        //- /main.rs
+        mod foo;
+        use foo::bar;
+        fn baz(x: u32) {}
+        fn main() {
+            let local = 1;
+            local;
+            bar;
+            baz;
+            Some;
+            nope;
+            foo::nope;
+            Self;
+        }
+        fn with_item() {
+            fn inner() {}
+            inner;
+        }
+        fn with_macro() {
+            m!();
+            from_macro;
+        }
+
+        //- /foo.rs
+        pub fn bar() {}
+
+        //- /std.rs
+        #[prelude_import]
+        use prelude::*;
+        mod prelude {
+            pub enum Option<T> { Some(T), None }
+            pub use self::Option::*;
+        }
+        ",
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+
+    assert_snapshot_matches!(db.diagnostics(), @r###"
+"x": unused variable: `x`
+"nope": cannot find value `nope` in this scope
+"###
+    );
+}
+
+#[test]
+fn unresolved_method_diagnostics() {
+    let mut db = MockDatabase::with_files(
+        r"
+        //- /main.rs
+        struct S;
+        impl S { fn foo(&self) {} }
+        trait Tr { fn bar(&self); }
+        impl Tr for S { fn bar(&self) {} }
+        struct W;
+        impl std::ops::Deref for W {}
+        fn main(s: S, r: &&S, w: W) {
+            s.foo();
+            s.bar();
+            s.clone();
+            s.nope();
+            r.nope();
+            w.nope();
+            1.nope();
+        }
+
+        //- /std.rs
+        #[prelude_import]
+        use prelude::*;
+        pub mod ops { pub trait Deref {} }
+        mod prelude {
+            pub trait Clone { fn clone(&self) -> Self; }
+        }
+        ",
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+
+    assert_snapshot_matches!(db.diagnostics(), @r###"
+"nope": no method named `nope` found for type `S` in the current scope
+"nope": no method named `nope` found for type `S` in the current scope
+"###
+    );
+}
+
+#[test]
+fn trait_not_in_scope_diagnostics() {
+    let db = MockDatabase::with_files(
+        r"
+        //- /main.rs crate:main deps:std
+        mod ext {
+            pub trait Frob { fn frob(&self); }
+            impl Frob for super::S { fn frob(&self) {} }
+        }
+        mod other {
+            use crate::ext::Frob;
+            fn f(s: crate::S) { s.frob(); }
+        }
+        struct S;
+        fn main(s: S) {
+            s.frob();
+        }
+
+        //- /std.rs crate:std
+        #[prelude_import]
+        use prelude::*;
+        mod prelude {}
+        ",
+    );
+
+    assert_snapshot_matches!(db.diagnostics(), @r###"
+"frob": no method named `frob` found for type `S` in the current scope, the trait `Frob` which provides it is not in scope
+"###
+    );
+}
+
+#[test]
+fn ambiguous_method_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r"
+        //- /lib.rs
+        trait Tr1 { fn foo(&self); fn bar(&self); }
+        trait Tr2 { fn foo(&self); }
+        trait Tr3 { fn foo(&self); }
+        struct S;
+        impl Tr1 for S { fn foo(&self) {} fn bar(&self) {} }
+        impl Tr2 for S { fn foo(&self) {} }
+        struct T;
+        impl T { fn foo(&self) {} }
+        impl Tr1 for T { fn foo(&self) {} fn bar(&self) {} }
+        impl Tr2 for T { fn foo(&self) {} }
+        fn main(s: S, t: T) {
+            s.foo();
+            s.bar();
+            t.foo();
+        }
+        ",
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"foo": multiple applicable methods named `foo` in scope, from the traits `Tr1`, `Tr2`
+"###
+    );
+}
+
+#[test]
+fn missing_unsafe_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r"
+        //- /lib.rs
+        struct S;
+        impl S { unsafe fn method(&self) {} }
+        unsafe fn unsafe_fn() -> u32 { 0 }
+        static mut STATIC_MUT: u32 = 0;
+        static STATIC: u32 = 0;
+
+        fn test(ptr: *const u32, s: S) {
+            unsafe_fn();
+            s.method();
+            *ptr;
+            STATIC_MUT;
+            STATIC;
+            unsafe {
+                unsafe_fn();
+                *ptr;
+            }
+        }
+
+        unsafe fn in_unsafe_fn(ptr: *const u32) {
+            *ptr;
+        }
+        ",
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"unsafe_fn()": this operation is unsafe and requires an unsafe function or block
+"s.method()": this operation is unsafe and requires an unsafe function or block
+"*ptr": this operation is unsafe and requires an unsafe function or block
+"STATIC_MUT": this operation is unsafe and requires an unsafe function or block
+"###
+    );
+}
+
+#[test]
+fn infer_macro_hygiene() {
+    assert_snapshot_matches!(
+        infer(r#"
+macro_rules! shadows_x {
+    ($e:expr) => { { let x = 1u8; $e } }
+}
+macro_rules! uses_x {
+    () => { x }
+}
+fn test() {
+    let x = 1u32;
+    let a = shadows_x!(x);
+    let b = uses_x!();
+}
+"#),
+        @r###"
+[119; 190) '{     ...!(); }': ()
+[129; 130) 'x': u32
+[133; 137) '1u32': u32
+[147; 148) 'a': u32
+[174; 175) 'b': {unknown}"###
+    );
+}
+
+#[test]
+fn unused_variable_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r#"
+        //- /lib.rs
+        struct S { field: u32 }
+        enum Option<T> { Some(T), None }
+        use Option::*;
+        const C: u32 = 0;
+
+        fn test(param: u32, _ignored: u32, used: u32, s: S) {
+            let unused = 1;
+            let mut assigned = 0;
+            assigned = used;
+            let S { field } = s;
+            let in_macro = 1;
+            println!(in_macro);
+            let in_format_string = 1;
+            println!("{in_format_string}");
+            let indexed = [1, 2];
+            indexed[0];
+            match Some(1) {
+                Some(x) => {}
+                None => {}
+            }
+            match 1u32 {
+                C => {}
+                y => {}
+            }
+        }
+        "#,
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"param": unused variable: `param`
+"unused": unused variable: `unused`
+"field": unused variable: `field`
+"x": unused variable: `x`
+"y": unused variable: `y`
+"###
+    );
+}
+
+#[test]
+fn macro_recursion_limit_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r"
+        //- /lib.rs
+        macro_rules! items {
+            () => { items!(); }
+        }
+        items!();
+
+        macro_rules! expr {
+            ($e:expr) => { expr!($e) }
+        }
+        fn test() -> u32 {
+            expr!(1)
+        }
+        ",
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"items!();": recursion limit reached while expanding `items!`
+"expr!(1)": recursion limit reached while expanding `expr!`
+"###
+    );
+}
+
+#[test]
+fn missing_fields_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r"
+        //- /lib.rs
+        struct S { foo: i32, bar: (), baz: u32 }
+        enum E { V { foo: i32 } }
+        fn main(s: S) {
+            S { foo: 92 };
+            S { bar: (), ..s };
+            E::V {};
+            S { foo: 1, bar: (), baz: 2 };
+        }
+        ",
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"{ foo: 92 }": missing structure fields:
+- bar
+- baz
+"{}": missing structure fields:
+- foo
+"###
+    );
+}
+
+#[test]
+fn type_mismatch_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r#"
+        //- /lib.rs
+        struct S;
+        fn foo(x: u32) -> u32 {
+            let a: u32 = true;
+            let b: S = foo(1);
+            let c: &u32 = &x;
+            let d: u64 = 1;
+            if true { S } else { 1 }
+        }
+        fn bar(c: bool) {
+            let x = if c { 1u32 } else { true };
+            let y = match c { true => return, false => 1i64, _ => S };
+        }
+        "#,
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"true": mismatched types: expected `u32`, found `bool`
+"S": mismatched types: expected `i64`, found `S`
+"x": unused variable: `x`
+"y": unused variable: `y`
+"true": mismatched types: expected `u32`, found `bool`
+"foo(1)": mismatched types: expected `S`, found `u32`
+"S": mismatched types: expected `u32`, found `S`
+"a": unused variable: `a`
+"b": unused variable: `b`
+"c": unused variable: `c`
+"d": unused variable: `d`
+"###
+    );
+}
+
+#[test]
+fn unresolved_type_diagnostics() {
+    let mut db = MockDatabase::with_files(
+        r"
+        //- /main.rs
+        struct S<T> { a: T, b: Nope, c: Option<u32> }
+        enum E { A(Missing) }
+        fn foo<T>(_x: T, _y: Option<Undefined>) -> S<T> {
+            let _z: Unknown = 1;
+        }
+        fn with_item() -> Local {
+            struct Local;
+            Local
+        }
+        fn path(_x: foo::Nope) {}
+
+        //- /std.rs
+        #[prelude_import]
+        use prelude::*;
+        mod prelude {
+            pub enum Option<T> { Some(T), None }
+        }
+        ",
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+
+    assert_snapshot_matches!(db.diagnostics(), @r###"
+"Nope": cannot find type `Nope` in this scope
+"Missing": cannot find type `Missing` in this scope
+"Undefined": cannot find type `Undefined` in this scope
+"Unknown": cannot find type `Unknown` in this scope
+"###
+    );
+}
@@ -2,7 +2,8 @@
 use std::collections::HashMap;
 
 use crate::db::HirDatabase;
-use super::{ TraitRef, Substs, infer::{ TypeVarId, InferTy}, Ty};
+use super::{ TraitRef, Substs, infer::{ TypeVarId, InferTy}, Ty, TypeCtor};
+use super::method_resolution::match_impl_ty;
 
 // Copied (and simplified) from Chalk
 
@@ -52,16 +53,32 @@ pub enum Obligation {
 /// Rudimentary check whether an impl exists for a given type and trait; this
 /// will actually be done by chalk.
 pub(crate) fn implements(db: &impl HirDatabase, trait_ref: TraitRef) -> Option<Solution> {
-    // FIXME use all trait impls in the whole crate graph
-    let krate = trait_ref.trait_.module(db).krate(db);
-    let krate = match krate {
-        Some(krate) => krate,
-        None => return None,
-    };
-    let crate_impl_blocks = db.impls_in_crate(krate);
-    let mut impl_blocks = crate_impl_blocks.lookup_impl_blocks_for_trait(&trait_ref.trait_);
-    impl_blocks
-        .find_map(|impl_block| unify_trait_refs(&trait_ref, &impl_block.target_trait_ref(db)?))
+    // `dyn Trait` implements `Trait`, with the parameters it was written with
+    if let Ty::Apply(a_ty) = trait_ref.self_ty() {
+        if a_ty.ctor == TypeCtor::Dyn(trait_ref.trait_) {
+            let substs: Vec<_> = std::iter::once(Ty::Apply(a_ty.clone()))
+                .chain(a_ty.parameters.iter().cloned())
+                .collect();
+            let dyn_trait_ref = TraitRef { trait_: trait_ref.trait_, substs: substs.into() };
+            return unify_trait_refs(&trait_ref, &dyn_trait_ref);
+        }
+    }
+    // FIXME use all trait impls in the whole crate graph, not only those of the
+    // crates of the trait and of the self type
+    let mut krates: Vec<_> = trait_ref.trait_.module(db).krate(db).into_iter().collect();
+    if let Some((def, _)) = trait_ref.self_ty().as_adt() {
+        krates.extend(def.krate(db).filter(|it| !krates.contains(it)));
+    }
+    for krate in krates {
+        let crate_impl_blocks = db.impls_in_crate(krate);
+        let solution = crate_impl_blocks
+            .lookup_impl_blocks_for_trait(&trait_ref.trait_)
+            .find_map(|impl_block| unify_trait_refs(&trait_ref, &impl_block.target_trait_ref(db)?));
+        if solution.is_some() {
+            return solution;
+        }
+    }
+    None
 }
 
 pub(super) fn canonicalize(trait_ref: TraitRef) -> (TraitRef, Vec<TypeVarId>) {
@@ -101,8 +118,8 @@ fn unify_trait_refs(tr1: &TraitRef, tr2: &TraitRef) -> Option<Solution> {
                 solution_substs[v1.0 as usize] = t2.clone();
             }
             _ => {
-                // check that they're equal (actually we'd have to recurse etc.)
-                if t1 != t2 {
+                // check that they're equal, up to the generic parameters of the impl
+                if !match_impl_ty(t2, t1, &mut Vec::new()) {
                     return None;
                 }
             }
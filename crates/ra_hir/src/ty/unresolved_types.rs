@@ -0,0 +1,57 @@
+//! Reports the types, in the syntax of an item, which don't resolve to
+//! anything.
+use ra_syntax::{
+    AstPtr, SyntaxNode,
+    SyntaxKind::MACRO_CALL,
+    ast::{self, AstNode},
+};
+
+use crate::{
+    HirDatabase, HirFileId, Path,
+    diagnostics::{DiagnosticSink, UnresolvedType},
+    resolve::Resolver,
+};
+
+/// Reports the types of a single name which don't resolve. Longer paths might
+/// point into crates we don't know about.
+pub(crate) fn add_diagnostics(
+    db: &impl HirDatabase,
+    file: HirFileId,
+    node: &SyntaxNode,
+    resolver: &Resolver,
+    sink: &mut DiagnosticSink,
+) {
+    if !resolver.is_complete() {
+        return;
+    }
+    // Items and macros in bodies are not resolved yet, they might define the
+    // name.
+    let has_local_defs = node
+        .descendants()
+        .skip(1)
+        .any(|it| it.kind() == MACRO_CALL || ast::ModuleItem::cast(it).is_some());
+    if has_local_defs {
+        return;
+    }
+    for path_type in node.descendants().filter_map(ast::PathType::cast) {
+        let path = match path_type.path().and_then(Path::from_ast) {
+            Some(it) => it,
+            None => continue,
+        };
+        let name = match path.as_ident() {
+            Some(it) => it,
+            None => continue,
+        };
+        // Primitive types and `Self` are not in the scopes.
+        if name.as_known_name().is_some() {
+            continue;
+        }
+        if resolver.resolve_name(db, name).take_types().is_none() {
+            sink.push(UnresolvedType {
+                file,
+                path_type: AstPtr::new(path_type),
+                name: name.clone(),
+            });
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //! HIR for references to types. Paths in these are not yet resolved. They can
 //! be directly created from an ast::TypeRef, without further queries.
 
-use ra_syntax::ast::{self, TypeAscriptionOwner};
+use ra_syntax::ast::{self, TypeAscriptionOwner, TypeBoundsOwner};
 
 use crate::Path;
 
@@ -48,9 +48,10 @@ pub enum TypeRef {
     Slice(Box<TypeRef>),
     /// A fn pointer. Last element of the vector is the return type.
     Fn(Vec<TypeRef>),
+    /// A trait object, with the bounds of `dyn Trait + Send`.
+    DynTrait(Vec<TypeRef>),
     // For
     // ImplTrait,
-    // DynTrait,
     Error,
 }
 
@@ -91,7 +92,15 @@ impl TypeRef {
             // for types are close enough for our purposes to the inner type for now...
             ForType(inner) => TypeRef::from_ast_opt(inner.type_ref()),
             ImplTraitType(_inner) => TypeRef::Error,
-            DynTraitType(_inner) => TypeRef::Error,
+            DynTraitType(inner) => TypeRef::DynTrait(
+                inner
+                    .type_bound_list()
+                    .into_iter()
+                    .flat_map(|it| it.bounds())
+                    .filter_map(|it| it.type_ref())
+                    .map(TypeRef::from_ast)
+                    .collect(),
+            ),
         }
     }
 
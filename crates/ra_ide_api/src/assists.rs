@@ -1,8 +1,8 @@
 use ra_db::{FileRange, FilePosition};
 
-use crate::{SourceFileEdit, SourceChange, db::RootDatabase};
+use crate::{SourceFileEdit, SourceChange, FileSystemEdit, db::RootDatabase};
 
-pub use ra_assists::AssistId;
+pub use ra_assists::{AssistId, AssistLabel, GroupLabel};
 
 #[derive(Debug)]
 pub struct Assist {
@@ -13,14 +13,38 @@ pub struct Assist {
 pub(crate) fn assists(db: &RootDatabase, frange: FileRange) -> Vec<Assist> {
     ra_assists::assists(db, frange)
         .into_iter()
-        .map(|(label, action)| {
-            let file_id = frange.file_id;
-            let file_edit = SourceFileEdit { file_id, edit: action.edit };
-            let id = label.id;
-            let change = SourceChange::source_file_edit(label.label, file_edit).with_cursor_opt(
-                action.cursor_position.map(|offset| FilePosition { offset, file_id }),
-            );
-            Assist { id, change }
-        })
+        .map(|(label, action)| to_assist(frange, label, action))
         .collect()
 }
+
+pub(crate) fn assist_labels(db: &RootDatabase, frange: FileRange) -> Vec<AssistLabel> {
+    ra_assists::applicable_assists(db, frange)
+}
+
+pub(crate) fn resolve_assist(
+    db: &RootDatabase,
+    frange: FileRange,
+    id: &str,
+    label: &str,
+) -> Option<Assist> {
+    let (label, action) = ra_assists::resolve_assist(db, frange, id, label)?;
+    Some(to_assist(frange, label, action))
+}
+
+fn to_assist(frange: FileRange, label: AssistLabel, action: ra_assists::AssistAction) -> Assist {
+    let file_id = frange.file_id;
+    let file_edit = SourceFileEdit { file_id, edit: action.edit };
+    let file_system_edits = action
+        .new_files
+        .into_iter()
+        .map(|it| FileSystemEdit::CreateFile {
+            source_root: it.source_root,
+            path: it.path,
+            text: it.text,
+        })
+        .collect();
+    let id = label.id;
+    let change = SourceChange::from_edits(label.label, vec![file_edit], file_system_edits)
+        .with_cursor_opt(action.cursor_position.map(|offset| FilePosition { offset, file_id }));
+    Assist { id, change }
+}
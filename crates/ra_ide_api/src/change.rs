@@ -1,9 +1,11 @@
 use std::{
-    fmt, time,
+    fmt, fs, time,
+    hash::{Hash, Hasher},
+    path::Path,
     sync::Arc,
 };
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHasher};
 use ra_db::{
     SourceRootId, FileId, CrateGraph, SourceDatabase, SourceRoot,
     salsa::{Database, SweepStrategy},
@@ -11,10 +13,11 @@ use ra_db::{
 use ra_syntax::SourceFile;
 use ra_prof::profile;
 use relative_path::RelativePathBuf;
+#[cfg(not(target_arch = "wasm32"))]
 use rayon::prelude::*;
 
 use crate::{
-    db::RootDatabase,
+    db::{self, RootDatabase},
     symbol_index::{SymbolIndex, SymbolsDatabase},
     status::syntax_tree_stats,
 };
@@ -137,10 +140,59 @@ impl LibraryData {
         root_id: SourceRootId,
         files: Vec<(FileId, RelativePathBuf, Arc<String>)>,
     ) -> LibraryData {
-        let symbol_index = SymbolIndex::for_files(files.par_iter().map(|(file_id, _, text)| {
-            let file = SourceFile::parse(text);
-            (*file_id, file)
-        }));
+        let symbol_index = index_library_files(&files);
+        LibraryData::new(root_id, files, symbol_index)
+    }
+
+    /// Like `prepare`, but loads the symbol index from `cache_dir` if it was
+    /// saved there for the same files, and saves it there otherwise, as the
+    /// files of libraries don't change.
+    pub fn prepare_with_symbol_cache(
+        root_id: SourceRootId,
+        mut files: Vec<(FileId, RelativePathBuf, Arc<String>)>,
+        cache_dir: &Path,
+    ) -> LibraryData {
+        // The files are saved by their index in this order.
+        files.sort_by(|(_, path1, _), (_, path2, _)| path1.cmp(path2));
+        let mut hasher = FxHasher::default();
+        for (_, path, text) in files.iter() {
+            (path.as_str(), text.as_str()).hash(&mut hasher);
+        }
+        let cache_file = cache_dir.join(format!("{:016x}.symbols", hasher.finish()));
+        let cached = fs::read_to_string(&cache_file).ok().and_then(|text| {
+            SymbolIndex::load(&text, |idx| files.get(idx).map(|(file_id, _, _)| *file_id))
+        });
+        let symbol_index = match cached {
+            Some(it) => it,
+            None => {
+                let symbol_index = index_library_files(&files);
+                let file_idx: FxHashMap<FileId, usize> = files
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, (file_id, _, _))| (*file_id, idx))
+                    .collect();
+                if let Some(text) = symbol_index.save(|file_id| file_idx.get(&file_id).cloned()) {
+                    // The file is renamed once written, so that a partially
+                    // written one is never loaded.
+                    let tmp_file = cache_file.with_extension("tmp");
+                    let res = fs::create_dir_all(cache_dir)
+                        .and_then(|()| fs::write(&tmp_file, text))
+                        .and_then(|()| fs::rename(&tmp_file, &cache_file));
+                    if let Err(e) = res {
+                        log::warn!("failed to save symbols to {}: {}", cache_file.display(), e);
+                    }
+                }
+                symbol_index
+            }
+        };
+        LibraryData::new(root_id, files, symbol_index)
+    }
+
+    fn new(
+        root_id: SourceRootId,
+        files: Vec<(FileId, RelativePathBuf, Arc<String>)>,
+        symbol_index: SymbolIndex,
+    ) -> LibraryData {
         let mut root_change = RootChange::default();
         root_change.added = files
             .into_iter()
@@ -150,6 +202,17 @@ impl LibraryData {
     }
 }
 
+fn index_library_files(files: &[(FileId, RelativePathBuf, Arc<String>)]) -> SymbolIndex {
+    let parse = |(file_id, _, text): &(FileId, RelativePathBuf, Arc<String>)| {
+        (*file_id, SourceFile::parse(text))
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let files = files.par_iter().map(parse).collect();
+    #[cfg(target_arch = "wasm32")]
+    let files = files.iter().map(parse).collect();
+    SymbolIndex::for_files(files)
+}
+
 const GC_COOLDOWN: time::Duration = time::Duration::from_millis(100);
 
 impl RootDatabase {
@@ -204,8 +267,8 @@ impl RootDatabase {
     }
 
     pub(crate) fn maybe_collect_garbage(&mut self) {
-        if self.last_gc_check.elapsed() > GC_COOLDOWN {
-            self.last_gc_check = time::Instant::now();
+        if self.last_gc_check.map_or(true, |it| it.elapsed() > GC_COOLDOWN) {
+            self.last_gc_check = db::now();
             let retained_trees = syntax_tree_stats(self).retained;
             if retained_trees > 100 {
                 log::info!("automatic garbadge collection, {} retained trees", retained_trees);
@@ -215,7 +278,7 @@ impl RootDatabase {
     }
 
     pub(crate) fn collect_garbage(&mut self) {
-        self.last_gc = time::Instant::now();
+        self.last_gc = db::now();
 
         let sweep = SweepStrategy::default().discard_values().sweep_all_revisions();
 
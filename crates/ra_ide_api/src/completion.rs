@@ -11,8 +11,12 @@ mod complete_snippet;
 mod complete_path;
 mod complete_scope;
 mod complete_postfix;
+mod complete_attribute;
+mod complete_lifetime;
+mod complete_mod;
 
 use ra_db::SourceDatabase;
+use ra_syntax::TextRange;
 
 use crate::{
     db,
@@ -24,9 +28,26 @@ use crate::{
 
 };
 #[cfg(test)]
-use crate::completion::completion_item::{do_completion, check_completion};
+use crate::completion::completion_item::{do_completion, do_completion_with_options, check_completion};
 
-pub use crate::completion::completion_item::{CompletionItem, CompletionItemKind, InsertTextFormat};
+pub use crate::completion::completion_item::{
+    CompletionItem, CompletionItemKind, CompletionRelevance, InsertTextFormat, Locality,
+};
+
+/// Client-configurable behavior of completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionOptions {
+    /// Whether `()` are added after the name of a completed function.
+    pub add_call_parenthesis: bool,
+    /// Whether postfix snippets, like `expr.if`, are offered.
+    pub enable_postfix_completions: bool,
+}
+
+impl Default for CompletionOptions {
+    fn default() -> CompletionOptions {
+        CompletionOptions { add_call_parenthesis: true, enable_postfix_completions: true }
+    }
+}
 
 /// Main entry point for completion. We run completion as a two-phase process.
 ///
@@ -49,10 +70,15 @@ pub use crate::completion::completion_item::{CompletionItem, CompletionItemKind,
 ///
 /// `foo` *should* be present among the completion variants. Filtering by
 /// identifier prefix/fuzzy match should be done higher in the stack, together
-/// with ordering of completions (currently this is done by the client).
-pub(crate) fn completions(db: &db::RootDatabase, position: FilePosition) -> Option<Completions> {
+/// with ordering of completions (currently this is done by the client, using
+/// the relevance of the items).
+pub(crate) fn completions(
+    db: &db::RootDatabase,
+    position: FilePosition,
+    options: &CompletionOptions,
+) -> Option<Completions> {
     let original_file = db.parse(position.file_id);
-    let ctx = CompletionContext::new(db, &original_file, position)?;
+    let ctx = CompletionContext::new(db, &original_file, position, options)?;
 
     let mut acc = Completions::default();
 
@@ -66,6 +92,27 @@ pub(crate) fn completions(db: &db::RootDatabase, position: FilePosition) -> Opti
     complete_dot::complete_dot(&mut acc, &ctx);
     complete_struct_literal::complete_struct_literal(&mut acc, &ctx);
     complete_pattern::complete_pattern(&mut acc, &ctx);
-    complete_postfix::complete_postfix(&mut acc, &ctx);
+    if options.enable_postfix_completions {
+        complete_postfix::complete_postfix(&mut acc, &ctx);
+    }
+    complete_attribute::complete_attribute(&mut acc, &ctx);
+    complete_lifetime::complete_lifetime(&mut acc, &ctx);
+    complete_mod::complete_mod(&mut acc, &ctx);
+
+    let typed_range = TextRange::from_to(ctx.source_range().start(), position.offset);
+    let typed = &db.file_text(position.file_id)[typed_range];
+    if !typed.is_empty() {
+        for item in acc.iter_mut() {
+            item.relevance_mut().is_prefix_match = is_prefix_match(typed, item.lookup());
+        }
+    }
     Some(acc)
 }
+
+/// Whether `typed` is a prefix of `name`, or of one of the words of `name` in
+/// snake case, ignoring the case.
+fn is_prefix_match(typed: &str, name: &str) -> bool {
+    let typed = typed.to_lowercase();
+    name.to_lowercase().split('_').any(|word| word.starts_with(&typed))
+        || name.to_lowercase().starts_with(&typed)
+}
@@ -0,0 +1,542 @@
+//! Completion for attributes, like `#[de<|>]`, and for the arguments of the
+//! attributes which take a list of paths, like `#[derive(Cl<|>)]`.
+use ra_syntax::{
+    AstNode, SmolStr, SyntaxElement, SyntaxToken, Direction,
+    ast::{self, AttrsOwner},
+    algo::non_trivia_sibling,
+    SyntaxKind::*,
+};
+
+use crate::completion::{
+    CompletionContext, CompletionItem, Completions, CompletionKind, CompletionItemKind,
+};
+
+pub(super) fn complete_attribute(acc: &mut Completions, ctx: &CompletionContext) {
+    let attribute = match ctx.attribute_under_caret {
+        Some(it) => it,
+        None => return,
+    };
+    let top_level_tt = match attribute.value() {
+        Some(it) => it,
+        None => return,
+    };
+    let tt = ctx.token.parent();
+    if tt == top_level_tt.syntax() {
+        if prev_non_trivia(ctx).map(|it| it.kind()) == Some(L_BRACK) {
+            complete_attribute_name(acc, ctx, attribute);
+        }
+        return;
+    }
+    if tt.kind() != TOKEN_TREE || tt.parent() != Some(top_level_tt.syntax()) {
+        return;
+    }
+    let attribute_name = match non_trivia_sibling(SyntaxElement::Node(tt), Direction::Prev) {
+        Some(SyntaxElement::Token(it)) if it.kind() == IDENT => it.text().clone(),
+        _ => return,
+    };
+    let existing = existing_arguments(ctx, tt);
+    let candidates = match attribute_name.as_str() {
+        "derive" => derive_completions(ctx),
+        "allow" | "warn" | "deny" | "forbid" => {
+            LINT_COMPLETIONS.iter().map(|it| (it.to_string(), None)).collect()
+        }
+        _ => return,
+    };
+    for (name, detail) in candidates.into_iter().filter(|(it, _)| !existing.contains(it)) {
+        CompletionItem::new(CompletionKind::Attribute, ctx.source_range(), name)
+            .kind(CompletionItemKind::Attribute)
+            .set_detail(detail)
+            .add_to(acc);
+    }
+}
+
+/// The derives which can be used in the current module: the ones built into
+/// the compiler, and the custom derives declared at the root of the crates it
+/// depends on, detailed with the name of their crate.
+fn derive_completions(ctx: &CompletionContext) -> Vec<(String, Option<String>)> {
+    let mut res: Vec<_> = BUILTIN_DERIVES.iter().map(|it| (it.to_string(), None)).collect();
+    let krate = match ctx.module.and_then(|it| it.krate(ctx.db)) {
+        Some(it) => it,
+        None => return res,
+    };
+    for dep in krate.dependencies(ctx.db) {
+        let root = match dep.krate.root_module(ctx.db) {
+            Some(it) => it,
+            None => continue,
+        };
+        for def in root.declarations(ctx.db) {
+            if let hir::ModuleDef::Function(function) = def {
+                if let Some(name) = custom_derive_name(&*function.source(ctx.db).1) {
+                    res.push((name.to_string(), Some(dep.name.to_string())));
+                }
+            }
+        }
+    }
+    res
+}
+
+/// The name of the derive implemented by a proc macro, like `Foo` for
+/// `#[proc_macro_derive(Foo, attributes(foo))] pub fn derive_foo(...)`.
+fn custom_derive_name(function: &ast::FnDef) -> Option<SmolStr> {
+    function.attrs().find_map(|attr| {
+        let (name, args) = attr.as_call()?;
+        if name != "proc_macro_derive" {
+            return None;
+        }
+        args.syntax()
+            .children_with_tokens()
+            .filter_map(|it| it.as_token())
+            .find(|it| it.kind() == IDENT)
+            .map(|it| it.text().clone())
+    })
+}
+
+fn complete_attribute_name(acc: &mut Completions, ctx: &CompletionContext, attribute: &ast::Attr) {
+    let is_inner = attribute.is_inner();
+    for attr in ATTRIBUTES.iter().filter(|it| is_inner || !it.inner_only) {
+        let mut builder =
+            CompletionItem::new(CompletionKind::Attribute, ctx.source_range(), attr.label)
+                .kind(CompletionItemKind::Attribute);
+        if let Some(snippet) = attr.snippet {
+            builder = builder.insert_snippet(snippet);
+        }
+        builder.add_to(acc);
+    }
+}
+
+/// Collects names which are already present in the argument list, excluding
+/// the one under the caret.
+fn existing_arguments(ctx: &CompletionContext, tt: &ra_syntax::SyntaxNode) -> Vec<String> {
+    tt.children_with_tokens()
+        .filter_map(|it| it.as_token())
+        .filter(|it| it.kind() == IDENT && *it != ctx.token)
+        .map(|it| it.text().to_string())
+        .collect()
+}
+
+fn prev_non_trivia<'a>(ctx: &CompletionContext<'a>) -> Option<SyntaxToken<'a>> {
+    if ctx.token.kind() == IDENT || ctx.token.kind().is_trivia() {
+        non_trivia_sibling(SyntaxElement::Token(ctx.token), Direction::Prev)?.as_token()
+    } else {
+        Some(ctx.token)
+    }
+}
+
+struct AttrCompletion {
+    label: &'static str,
+    snippet: Option<&'static str>,
+    /// Whether the attribute is only valid as an inner one, `#![...]`.
+    inner_only: bool,
+}
+
+const fn attr(label: &'static str, snippet: Option<&'static str>) -> AttrCompletion {
+    AttrCompletion { label, snippet, inner_only: false }
+}
+
+const fn inner_attr(label: &'static str, snippet: Option<&'static str>) -> AttrCompletion {
+    AttrCompletion { label, snippet, inner_only: true }
+}
+
+const ATTRIBUTES: &[AttrCompletion] = &[
+    attr("allow(…)", Some("allow(${0:lint})")),
+    attr("cfg(…)", Some("cfg(${0:predicate})")),
+    attr("cfg_attr(…)", Some("cfg_attr(${1:predicate}, ${0:attr})")),
+    attr("deny(…)", Some("deny(${0:lint})")),
+    attr("deprecated = \"…\"", Some("deprecated = \"${0:reason}\"")),
+    attr("derive(…)", Some("derive(${0:Debug})")),
+    attr("doc = \"…\"", Some("doc = \"${0:docs}\"")),
+    inner_attr("feature(…)", Some("feature(${0:flag})")),
+    attr("forbid(…)", Some("forbid(${0:lint})")),
+    attr("ignore", None),
+    attr("inline", None),
+    attr("macro_export", None),
+    attr("macro_use", None),
+    attr("must_use", None),
+    attr("no_mangle", None),
+    inner_attr("no_std", None),
+    attr("path = \"…\"", Some("path = \"${0:path}\"")),
+    inner_attr("recursion_limit = …", Some("recursion_limit = ${0:128}")),
+    attr("repr(…)", Some("repr(${0:C})")),
+    attr("should_panic", None),
+    attr("test", None),
+    attr("warn(…)", Some("warn(${0:lint})")),
+];
+
+/// The traits which the compiler knows how to derive.
+const BUILTIN_DERIVES: &[&str] =
+    &["Clone", "Copy", "Debug", "Default", "Eq", "Hash", "Ord", "PartialEq", "PartialOrd"];
+
+const LINT_COMPLETIONS: &[&str] = &[
+    "dead_code",
+    "deprecated",
+    "missing_docs",
+    "non_camel_case_types",
+    "non_snake_case",
+    "non_upper_case_globals",
+    "unreachable_code",
+    "unused_imports",
+    "unused_mut",
+    "unused_variables",
+    "warnings",
+];
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_debug_snapshot_matches;
+    use crate::completion::{CompletionItem, CompletionKind, do_completion};
+
+    fn complete(code: &str) -> Vec<CompletionItem> {
+        do_completion(code, CompletionKind::Attribute)
+    }
+
+    #[test]
+    fn completes_attribute_names() {
+        let completions = complete(
+            r"
+            #[<|>]
+            struct Foo;
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "allow(…)",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "allow(${0:lint})",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "cfg(…)",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "cfg(${0:predicate})",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "cfg_attr(…)",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "cfg_attr(${1:predicate}, ${0:attr})",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "deny(…)",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "deny(${0:lint})",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "deprecated = \"…\"",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "deprecated = \"${0:reason}\"",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "derive(…)",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "derive(${0:Debug})",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "doc = \"…\"",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "doc = \"${0:docs}\"",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "forbid(…)",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "forbid(${0:lint})",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "ignore",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "ignore",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "inline",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "inline",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "macro_export",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "macro_export",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "macro_use",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "macro_use",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "must_use",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "must_use",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "no_mangle",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "no_mangle",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "path = \"…\"",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "path = \"${0:path}\"",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "repr(…)",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "repr(${0:C})",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "should_panic",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "should_panic",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "test",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "test",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "warn(…)",
+        source_range: [15; 15),
+        delete: [15; 15),
+        insert: "warn(${0:lint})",
+        kind: Attribute
+    }
+]"###);
+    }
+
+    #[test]
+    fn completes_inner_attribute_names() {
+        let completions = complete(
+            r"
+            #![no<|>]
+            ",
+        );
+        let labels: Vec<&str> = completions.iter().map(|it| it.label()).collect();
+        assert!(labels.contains(&"no_std"));
+        assert!(labels.contains(&"feature(…)"));
+    }
+
+    #[test]
+    fn completes_derives() {
+        let completions = complete(
+            r"
+            #[derive(Debug, Cl<|>)]
+            struct Foo;
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "Clone",
+        source_range: [29; 31),
+        delete: [29; 31),
+        insert: "Clone",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "Copy",
+        source_range: [29; 31),
+        delete: [29; 31),
+        insert: "Copy",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "Default",
+        source_range: [29; 31),
+        delete: [29; 31),
+        insert: "Default",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "Eq",
+        source_range: [29; 31),
+        delete: [29; 31),
+        insert: "Eq",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "Hash",
+        source_range: [29; 31),
+        delete: [29; 31),
+        insert: "Hash",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "Ord",
+        source_range: [29; 31),
+        delete: [29; 31),
+        insert: "Ord",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "PartialEq",
+        source_range: [29; 31),
+        delete: [29; 31),
+        insert: "PartialEq",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "PartialOrd",
+        source_range: [29; 31),
+        delete: [29; 31),
+        insert: "PartialOrd",
+        kind: Attribute
+    }
+]"###);
+    }
+
+    #[test]
+    fn completes_custom_derives_of_dependencies() {
+        let completions = complete(
+            r"
+            //- /main.rs crate:main deps:foo_derive
+            #[derive(Fo<|>)]
+            struct Foo;
+            //- /foo_derive/lib.rs crate:foo_derive
+            #[proc_macro_derive(Foo, attributes(foo))]
+            pub fn derive_foo(input: TokenStream) -> TokenStream { input }
+            pub fn not_a_derive() {}
+            ",
+        );
+        let custom: Vec<_> = completions.into_iter().filter(|it| it.detail().is_some()).collect();
+        assert_debug_snapshot_matches!(custom, @r###"[
+    CompletionItem {
+        label: "Foo",
+        source_range: [9; 11),
+        delete: [9; 11),
+        insert: "Foo",
+        kind: Attribute,
+        detail: "foo_derive"
+    }
+]"###);
+    }
+
+    #[test]
+    fn completes_lints() {
+        let completions = complete(
+            r"
+            #[allow(<|>)]
+            fn foo() {}
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "dead_code",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "dead_code",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "deprecated",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "deprecated",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "missing_docs",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "missing_docs",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "non_camel_case_types",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "non_camel_case_types",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "non_snake_case",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "non_snake_case",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "non_upper_case_globals",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "non_upper_case_globals",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "unreachable_code",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "unreachable_code",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "unused_imports",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "unused_imports",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "unused_mut",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "unused_mut",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "unused_variables",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "unused_variables",
+        kind: Attribute
+    },
+    CompletionItem {
+        label: "warnings",
+        source_range: [21; 21),
+        delete: [21; 21),
+        insert: "warnings",
+        kind: Attribute
+    }
+]"###);
+    }
+
+    #[test]
+    fn does_not_complete_attribute_arguments_as_names() {
+        let completions = complete(
+            r"
+            #[cfg(test), <|>]
+            fn foo() {}
+            ",
+        );
+        assert!(completions.is_empty());
+    }
+}
@@ -1,38 +1,45 @@
-use hir::{Ty, AdtDef, TypeCtor};
+use hir::{Ty, ApplicationTy, TypeCtor, HirDisplay};
 
-use crate::completion::{CompletionContext, Completions};
+use crate::completion::{
+    CompletionContext, Completions, CompletionItem, CompletionKind, CompletionItemKind,
+};
 
 /// Complete dot accesses, i.e. fields or methods (currently only fields).
 pub(super) fn complete_dot(acc: &mut Completions, ctx: &CompletionContext) {
     let receiver_ty = match ctx.dot_receiver.and_then(|it| ctx.analyzer.type_of(ctx.db, it)) {
         Some(it) => it,
-        None => return,
+        None => match &ctx.macro_receiver_ty {
+            Some(it) => it.clone(),
+            None => return,
+        },
     };
     if !ctx.is_call {
         complete_fields(acc, ctx, receiver_ty.clone());
+        complete_await(acc, ctx, &receiver_ty);
     }
     complete_methods(acc, ctx, receiver_ty);
 }
 
+fn complete_await(acc: &mut Completions, ctx: &CompletionContext, receiver: &Ty) {
+    if let Ty::Apply(ApplicationTy { ctor: TypeCtor::Future, parameters }) = receiver {
+        CompletionItem::new(CompletionKind::Keyword, ctx.source_range(), "await")
+            .kind(CompletionItemKind::Keyword)
+            .detail(parameters.as_single().display(ctx.db).to_string())
+            .add_to(acc);
+    }
+}
+
 fn complete_fields(acc: &mut Completions, ctx: &CompletionContext, receiver: Ty) {
     for receiver in receiver.autoderef(ctx.db) {
-        match receiver {
-            Ty::Apply(a_ty) => match a_ty.ctor {
-                TypeCtor::Adt(AdtDef::Struct(s)) => {
-                    for field in s.fields(ctx.db) {
-                        acc.add_field(ctx, field, &a_ty.parameters);
-                    }
-                }
-                // FIXME unions
-                TypeCtor::Tuple => {
-                    for (i, ty) in a_ty.parameters.iter().enumerate() {
-                        acc.add_pos_field(ctx, i, ty);
-                    }
-                }
-                _ => {}
-            },
-            _ => {}
-        };
+        // FIXME unions
+        for (field, ty) in receiver.struct_fields(ctx.db) {
+            acc.add_field(ctx, field, &ty);
+        }
+        if let Some(substs) = receiver.as_tuple() {
+            for (i, ty) in substs.iter().enumerate() {
+                acc.add_pos_field(ctx, i, ty);
+            }
+        }
     }
 }
 
@@ -55,6 +62,33 @@ mod tests {
         do_completion(code, CompletionKind::Reference)
     }
 
+    #[test]
+    fn test_await_completion() {
+        let completions = do_completion(
+            r"
+            async fn foo() -> u32 { 92 }
+            async fn bar() {
+                foo().<|>
+            }
+            ",
+            CompletionKind::Keyword,
+        );
+        assert_eq!(completions.len(), 1);
+        assert_eq!(completions[0].label(), "await");
+        assert_eq!(completions[0].detail(), Some("u32"));
+
+        let completions = do_completion(
+            r"
+            fn foo() -> u32 { 92 }
+            fn bar() {
+                foo().<|>
+            }
+            ",
+            CompletionKind::Keyword,
+        );
+        assert!(completions.is_empty());
+    }
+
     #[test]
     fn test_struct_field_completion() {
         assert_debug_snapshot_matches!(
@@ -79,6 +113,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_struct_field_completion_in_macro_arguments() {
+        assert_debug_snapshot_matches!(
+        do_ref_completion(
+                r"
+                macro_rules! id { ($e:expr) => { $e } }
+                struct A { the_field: u32 }
+                fn foo(a: A) {
+                    id!(a.<|>)
+                }
+                ",
+        ),
+            @r###"[
+    CompletionItem {
+        label: "the_field",
+        source_range: [158; 158),
+        delete: [158; 158),
+        insert: "the_field",
+        kind: Field,
+        detail: "u32"
+    }
+]"###
+        );
+    }
+
     #[test]
     fn test_struct_field_completion_self() {
         assert_debug_snapshot_matches!(
@@ -0,0 +1,179 @@
+use ra_syntax::{
+    AstNode,
+    SyntaxKind::*,
+    SyntaxNode,
+    ast::{self, TypeParamsOwner},
+};
+
+use crate::completion::{
+    CompletionContext, CompletionItem, Completions, CompletionKind, CompletionItemKind,
+};
+
+/// Completes lifetimes declared in scope, like `'a` in `&'<|>`, and loop labels
+/// after `break` and `continue`.
+pub(super) fn complete_lifetime(acc: &mut Completions, ctx: &CompletionContext) {
+    if ctx.token.kind() != LIFETIME {
+        return;
+    }
+    let parent = ctx.token.parent();
+    match parent.kind() {
+        // We are declaring a new one, there's nothing to complete.
+        LABEL | LIFETIME_PARAM => (),
+        BREAK_EXPR | CONTINUE_EXPR => complete_labels(acc, ctx, parent),
+        _ => complete_lifetimes(acc, ctx, parent),
+    }
+}
+
+fn complete_lifetimes(acc: &mut Completions, ctx: &CompletionContext, parent: &SyntaxNode) {
+    let mut seen = Vec::new();
+    for node in parent.ancestors() {
+        let type_params = match type_param_list(node) {
+            Some(it) => it,
+            None => continue,
+        };
+        for lifetime in type_params.lifetime_params().filter_map(|it| it.lifetime_token()) {
+            let name = lifetime.text().to_string();
+            if seen.contains(&name) {
+                continue;
+            }
+            CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name.clone())
+                .kind(CompletionItemKind::TypeParam)
+                .add_to(acc);
+            seen.push(name);
+        }
+    }
+    CompletionItem::new(CompletionKind::Reference, ctx.source_range(), "'static")
+        .kind(CompletionItemKind::TypeParam)
+        .add_to(acc);
+}
+
+fn type_param_list(node: &SyntaxNode) -> Option<&ast::TypeParamList> {
+    if let Some(it) = ast::FnDef::cast(node) {
+        return it.type_param_list();
+    }
+    if let Some(it) = ast::StructDef::cast(node) {
+        return it.type_param_list();
+    }
+    if let Some(it) = ast::EnumDef::cast(node) {
+        return it.type_param_list();
+    }
+    if let Some(it) = ast::TraitDef::cast(node) {
+        return it.type_param_list();
+    }
+    if let Some(it) = ast::TypeAliasDef::cast(node) {
+        return it.type_param_list();
+    }
+    if let Some(it) = ast::ImplBlock::cast(node) {
+        return it.type_param_list();
+    }
+    None
+}
+
+fn complete_labels(acc: &mut Completions, ctx: &CompletionContext, parent: &SyntaxNode) {
+    for node in parent.ancestors() {
+        if node.kind() == FN_DEF || node.kind() == LAMBDA_EXPR {
+            break;
+        }
+        let label = match node.kind() {
+            LOOP_EXPR | WHILE_EXPR | FOR_EXPR => {
+                node.children().find(|it| it.kind() == LABEL).and_then(|it| it.first_token())
+            }
+            _ => None,
+        };
+        if let Some(label) = label {
+            CompletionItem::new(
+                CompletionKind::Reference,
+                ctx.source_range(),
+                label.text().as_str(),
+            )
+            .kind(CompletionItemKind::Binding)
+            .add_to(acc);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_debug_snapshot_matches;
+    use crate::completion::{CompletionItem, CompletionKind, do_completion};
+
+    fn complete(code: &str) -> Vec<CompletionItem> {
+        do_completion(code, CompletionKind::Reference)
+    }
+
+    #[test]
+    fn completes_lifetimes_in_scope() {
+        let completions = complete(
+            r"
+            struct S<'a, 'b>(&'a u32, &'b u32);
+            impl<'a> S<'a, 'a> {
+                fn foo<'c>(&self, x: &'c u32) -> &'c<|> u32 {}
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "\'a",
+        source_range: [132; 134),
+        delete: [132; 134),
+        insert: "\'a",
+        kind: TypeParam
+    },
+    CompletionItem {
+        label: "\'c",
+        source_range: [132; 134),
+        delete: [132; 134),
+        insert: "\'c",
+        kind: TypeParam
+    },
+    CompletionItem {
+        label: "\'static",
+        source_range: [132; 134),
+        delete: [132; 134),
+        insert: "\'static",
+        kind: TypeParam
+    }
+]"###);
+    }
+
+    #[test]
+    fn completes_loop_labels() {
+        let completions = complete(
+            r"
+            fn foo() {
+                'outer: for _ in 0..10 {
+                    'inner: loop {
+                        break 'o<|>;
+                    }
+                }
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "\'inner",
+        source_range: [130; 132),
+        delete: [130; 132),
+        insert: "\'inner",
+        kind: Binding
+    },
+    CompletionItem {
+        label: "\'outer",
+        source_range: [130; 132),
+        delete: [130; 132),
+        insert: "\'outer",
+        kind: Binding
+    }
+]"###);
+    }
+
+    #[test]
+    fn does_not_complete_lifetime_declarations() {
+        let completions = complete(
+            r"
+            fn foo<'a, 'b<|>>() {}
+            ",
+        );
+        assert!(completions.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    AstNode,
+    SyntaxKind::SOURCE_FILE,
+    ast::{self, NameOwner},
+};
+use relative_path::RelativePath;
+
+use crate::completion::{
+    CompletionContext, CompletionItem, Completions, CompletionKind, CompletionItemKind,
+};
+
+/// Completes names of module declarations, like `mod <|>;`, with the files
+/// which could be a child module of the current file but are not declared yet.
+pub(super) fn complete_mod(acc: &mut Completions, ctx: &CompletionContext) {
+    let mod_decl = match ctx.mod_decl_under_caret {
+        Some(it) => it,
+        None => return,
+    };
+    // FIXME: support declarations inside inline modules, which live in a
+    // nested directory.
+    if mod_decl.syntax().parent().map(|it| it.kind()) != Some(SOURCE_FILE) {
+        return;
+    }
+    let file_id = ctx.file_id;
+    let declared: Vec<String> = mod_decl
+        .syntax()
+        .parent()
+        .into_iter()
+        .flat_map(|it| it.children())
+        .filter_map(ast::Module::cast)
+        .filter(|it| it.syntax() != mod_decl.syntax())
+        .filter_map(|it| it.name())
+        .map(|it| it.text().to_string())
+        .collect();
+
+    let path = ctx.db.file_relative_path(file_id);
+    let dir_path = parent_dir(&path);
+    let mod_name = path.file_stem().unwrap_or("unknown");
+    let is_root = ctx.db.crate_graph().crate_id_for_crate_root(file_id).is_some();
+    let is_dir_owner = is_root || mod_name == "mod";
+    let children_dir =
+        if is_dir_owner { dir_path.to_relative_path_buf() } else { dir_path.join(mod_name) };
+
+    let source_root = ctx.db.source_root(ctx.db.file_source_root(file_id));
+    let mut candidates: Vec<String> = source_root
+        .files
+        .keys()
+        .filter_map(|file| child_module_name(&children_dir, file, is_dir_owner))
+        .filter(|name| !declared.contains(name))
+        .filter(|name| !(is_dir_owner && (name == "lib" || name == "main")))
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    for name in candidates {
+        CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name)
+            .kind(CompletionItemKind::Module)
+            .add_to(acc);
+    }
+}
+
+/// If `file` would be the file of a child module declared in `children_dir`,
+/// returns the name of the module. This mirrors `resolve_submodule` in hir.
+fn child_module_name(
+    children_dir: &RelativePath,
+    file: &RelativePath,
+    is_dir_owner: bool,
+) -> Option<String> {
+    if file.extension() != Some("rs") {
+        return None;
+    }
+    let parent = parent_dir(file);
+    if parent == children_dir {
+        let stem = file.file_stem()?;
+        return if stem == "mod" { None } else { Some(stem.to_string()) };
+    }
+    if is_dir_owner && file.file_name() == Some("mod.rs") && parent_dir(parent) == children_dir {
+        return parent.file_name().map(|it| it.to_string());
+    }
+    None
+}
+
+fn parent_dir(path: &RelativePath) -> &RelativePath {
+    path.parent().unwrap_or_else(|| RelativePath::new(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_debug_snapshot_matches;
+    use crate::completion::{CompletionItem, CompletionKind, do_completion};
+
+    fn complete(code: &str) -> Vec<CompletionItem> {
+        do_completion(code, CompletionKind::Reference)
+    }
+
+    #[test]
+    fn completes_undeclared_sibling_files() {
+        let completions = complete(
+            "
+            //- /lib.rs
+            mod foo;
+            mod <|>
+            //- /foo.rs
+            fn foo() {}
+            //- /bar.rs
+            fn bar() {}
+            //- /baz/mod.rs
+            fn baz() {}
+            //- /baz/quux.rs
+            fn quux() {}
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "bar",
+        source_range: [13; 13),
+        delete: [13; 13),
+        insert: "bar",
+        kind: Module
+    },
+    CompletionItem {
+        label: "baz",
+        source_range: [13; 13),
+        delete: [13; 13),
+        insert: "baz",
+        kind: Module
+    }
+]"###);
+    }
+
+    #[test]
+    fn completes_files_in_module_directory() {
+        let completions = complete(
+            "
+            //- /lib.rs
+            mod foo;
+            //- /foo.rs
+            mod b<|>;
+            //- /foo/bar.rs
+            fn bar() {}
+            //- /baz.rs
+            fn baz() {}
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "bar",
+        source_range: [4; 5),
+        delete: [4; 5),
+        insert: "bar",
+        kind: Module
+    }
+]"###);
+    }
+}
@@ -1,12 +1,15 @@
+use hir::AdtDef;
+use ra_syntax::ast::{AstNode, NameOwner};
+
 use crate::completion::{CompletionContext, Completions};
 
 /// Completes constats and paths in patterns.
 pub(super) fn complete_pattern(acc: &mut Completions, ctx: &CompletionContext) {
+    complete_pattern_for_scrutinee(acc, ctx);
+    complete_struct_pattern_fields(acc, ctx);
     if !ctx.is_pat_binding {
         return;
     }
-    // FIXME: ideally, we should look at the type we are matching against and
-    // suggest variants + auto-imports
     let names = ctx.analyzer.all_names(ctx.db);
     for (name, res) in names.into_iter() {
         let r = res.as_ref();
@@ -25,6 +28,82 @@ pub(super) fn complete_pattern(acc: &mut Completions, ctx: &CompletionContext) {
     }
 }
 
+/// Completes whole patterns of the right shape for the type of the matched
+/// expression: variants of an enum (only in refutable positions) and
+/// destructuring of a struct.
+fn complete_pattern_for_scrutinee(acc: &mut Completions, ctx: &CompletionContext) {
+    let ty = match ctx.scrutinee.and_then(|it| ctx.analyzer.type_of(ctx.db, it)) {
+        Some(it) => it,
+        None => return,
+    };
+    let (adt, _substs) = match ty.as_adt() {
+        Some(it) => it,
+        None => return,
+    };
+    match adt {
+        AdtDef::Enum(e) if ctx.is_pat_binding => {
+            let names = ctx.analyzer.all_names(ctx.db);
+            for variant in e.variants(ctx.db) {
+                // Variants imported into scope don't need to be qualified.
+                let in_scope =
+                    variant.name(ctx.db).and_then(|name| names.get(&name)).map_or(false, |res| {
+                        match res.as_ref().take_types().or(res.as_ref().take_values()) {
+                            Some(hir::Resolution::Def(hir::ModuleDef::EnumVariant(it))) => {
+                                *it == variant
+                            }
+                            _ => false,
+                        }
+                    });
+                acc.add_enum_variant_pat(ctx, variant, !in_scope);
+            }
+        }
+        AdtDef::Enum(_) => (),
+        AdtDef::Struct(s) => acc.add_struct_pat(ctx, s),
+    }
+}
+
+/// Completes field shorthands, like `x` in `Point { <|> }`, skipping the
+/// fields which are already mentioned in the pattern.
+fn complete_struct_pattern_fields(acc: &mut Completions, ctx: &CompletionContext) {
+    let struct_pat = match ctx.struct_pat_syntax {
+        Some(it) => it,
+        None => return,
+    };
+    let ty = ctx.analyzer.type_of_pat(ctx.db, struct_pat.into());
+    let (fields, field_types) =
+        match struct_pat.path().and_then(|it| ctx.analyzer.resolve_path(ctx.db, it)) {
+            Some(hir::PathResolution::Def(hir::ModuleDef::Struct(it))) => {
+                (it.fields(ctx.db), ty.map(|ty| ty.struct_fields(ctx.db)))
+            }
+            Some(hir::PathResolution::Def(hir::ModuleDef::EnumVariant(it))) => {
+                (it.fields(ctx.db), ty.map(|ty| ty.variant_fields(ctx.db, it)))
+            }
+            _ => return,
+        };
+    let field_types = field_types.unwrap_or_default();
+    let mentioned: Vec<String> = match struct_pat.field_pat_list() {
+        Some(list) => list
+            .field_pats()
+            .filter_map(|it| it.name())
+            .chain(list.bind_pats().filter_map(|it| it.name()))
+            .filter(|it| !it.syntax().range().is_subrange(&ctx.source_range()))
+            .map(|it| it.text().to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+    for field in fields {
+        if mentioned.contains(&field.name(ctx.db).to_string()) {
+            continue;
+        }
+        // fall back to the declared type if the type of the pattern is unknown
+        let ty = match field_types.iter().find(|(it, _)| *it == field) {
+            Some((_, ty)) => ty.clone(),
+            None => field.ty(ctx.db),
+        };
+        acc.add_field(ctx, field, &ty);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot_matches;
@@ -34,6 +113,10 @@ mod tests {
         do_completion(code, CompletionKind::Reference)
     }
 
+    fn complete_magic(code: &str) -> Vec<CompletionItem> {
+        do_completion(code, CompletionKind::Magic)
+    }
+
     #[test]
     fn completes_enum_variants_and_modules() {
         let completions = complete(
@@ -82,6 +165,157 @@ mod tests {
         insert: "m",
         kind: Module
     }
+]"###);
+    }
+
+    #[test]
+    fn completes_variant_patterns_for_match_scrutinee() {
+        let completions = complete_magic(
+            r"
+            enum E { A, B(u32), C(u32, bool), D { x: u32, y: u32 } }
+            fn foo(e: E) {
+               match e {
+                   <|>
+               }
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "E::A",
+        source_range: [141; 141),
+        delete: [141; 141),
+        insert: "E::A",
+        kind: EnumVariant
+    },
+    CompletionItem {
+        label: "E::B(…)",
+        source_range: [141; 141),
+        delete: [141; 141),
+        insert: "E::B($0)",
+        kind: EnumVariant,
+        lookup: "E::B"
+    },
+    CompletionItem {
+        label: "E::C(…)",
+        source_range: [141; 141),
+        delete: [141; 141),
+        insert: "E::C(${1:_}, ${2:_})$0",
+        kind: EnumVariant,
+        lookup: "E::C"
+    },
+    CompletionItem {
+        label: "E::D { … }",
+        source_range: [141; 141),
+        delete: [141; 141),
+        insert: "E::D { x$0, y }",
+        kind: EnumVariant,
+        lookup: "E::D"
+    }
+]"###);
+    }
+
+    #[test]
+    fn does_not_qualify_imported_variants() {
+        let completions = complete_magic(
+            r"
+            enum Option<T> { None, Some(T) }
+            use Option::*;
+            fn foo(o: Option<i32>) {
+               if let S<|> = o {}
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "None",
+        source_range: [132; 133),
+        delete: [132; 133),
+        insert: "None",
+        kind: EnumVariant
+    },
+    CompletionItem {
+        label: "Some(…)",
+        source_range: [132; 133),
+        delete: [132; 133),
+        insert: "Some($0)",
+        kind: EnumVariant,
+        lookup: "Some"
+    }
+]"###);
+    }
+
+    #[test]
+    fn completes_struct_destructuring_in_let() {
+        let completions = complete_magic(
+            r"
+            struct Point { x: i32, y: i32 }
+            fn foo(p: Point) {
+               let <|> = p;
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "Point { … }",
+        source_range: [95; 95),
+        delete: [95; 95),
+        insert: "Point { x$0, y }",
+        kind: Struct,
+        lookup: "Point"
+    }
+]"###);
+    }
+
+    #[test]
+    fn completes_field_shorthands_in_struct_pattern() {
+        let completions = complete(
+            r"
+            struct Point { x: i32, y: i32, z: i32 }
+            fn foo(p: Point) {
+               let Point { y, <|> } = p;
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "x",
+        source_range: [114; 114),
+        delete: [114; 114),
+        insert: "x",
+        kind: Field,
+        detail: "i32"
+    },
+    CompletionItem {
+        label: "z",
+        source_range: [114; 114),
+        delete: [114; 114),
+        insert: "z",
+        kind: Field,
+        detail: "i32"
+    }
+]"###);
+    }
+
+    #[test]
+    fn completes_generic_field_shorthands_in_struct_pattern() {
+        let completions = complete(
+            r"
+            struct Point<T> { x: T, y: T }
+            fn foo(p: Point<u8>) {
+               let Point { y, <|> } = p;
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "x",
+        source_range: [109; 109),
+        delete: [109; 109),
+        insert: "x",
+        kind: Field,
+        detail: "u8"
+    }
 ]"###);
     }
 }
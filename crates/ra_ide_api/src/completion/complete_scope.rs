@@ -1,17 +1,38 @@
+use hir::{PerNs, Resolution};
+
 use crate::completion::{Completions, CompletionContext};
 
 pub(super) fn complete_scope(acc: &mut Completions, ctx: &CompletionContext) {
     if !ctx.is_trivial_path {
         return;
     }
-    let names = ctx.analyzer.all_names(ctx.db);
+    let locals = ctx.analyzer.scope_at(ctx.offset);
+    for local in locals.iter() {
+        if let Some(pat) = ctx.analyzer.local_binding(local) {
+            let res = PerNs::values(Resolution::LocalBinding(pat));
+            acc.add_resolution(ctx, local.name().to_string(), &res);
+        }
+    }
 
-    names.into_iter().for_each(|(name, res)| acc.add_resolution(ctx, name.to_string(), &res));
+    // Locals come from `scope_at` above, they shadow the items in the value namespace.
+    let names = ctx.analyzer.all_names(ctx.db);
+    for (name, mut res) in names {
+        let is_local = match res.values {
+            Some(Resolution::LocalBinding(_)) => true,
+            _ => locals.iter().any(|local| *local.name() == name),
+        };
+        if is_local {
+            res.values = None;
+        }
+        if !res.is_none() {
+            acc.add_resolution(ctx, name.to_string(), &res);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::completion::{CompletionKind, check_completion};
+    use crate::completion::{CompletionKind, check_completion, do_completion};
 
     fn check_reference_completion(name: &str, code: &str) {
         check_completion(name, code, CompletionKind::Reference);
@@ -156,6 +177,37 @@ mod tests {
         )
     }
 
+    #[test]
+    fn completes_innermost_of_shadowed_bindings() {
+        let completions = do_completion(
+            r#"
+            fn foo() {
+                let bar = 92;
+                let bar = "";
+                let baz: &str = <|>;
+            }
+            "#,
+            CompletionKind::Reference,
+        );
+        let bars: Vec<_> = completions.iter().filter(|it| it.label() == "bar").collect();
+        assert_eq!(bars.len(), 1);
+        assert!(bars[0].relevance().exact_type_match);
+    }
+
+    #[test]
+    fn completes_bindings_in_macro_arguments() {
+        check_reference_completion(
+            "bindings_in_macro_arguments",
+            r"
+            macro_rules! id { ($e:expr) => { $e } }
+            fn quux(x: i32) {
+                let y = 92;
+                id!(1 + <|>);
+            }
+            ",
+        );
+    }
+
     #[test]
     fn completes_self_in_methods() {
         check_reference_completion("self_in_methods", r"impl S { fn foo(&self) { <|> } }")
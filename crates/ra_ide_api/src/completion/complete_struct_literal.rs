@@ -1,27 +1,106 @@
-use hir::AdtDef;
+use hir::{AdtDef, StructField, Ty};
+use ra_syntax::{
+    ast::{self, AstNode},
+    SyntaxKind::{ARG_LIST, BIN_EXPR, LET_STMT},
+};
 
-use crate::completion::{CompletionContext, Completions};
+use crate::completion::{
+    CompletionContext, CompletionItem, CompletionItemKind, CompletionKind, Completions,
+};
 
 /// Complete fields in fields literals.
 pub(super) fn complete_struct_literal(acc: &mut Completions, ctx: &CompletionContext) {
-    let ty = match ctx.struct_lit_syntax.and_then(|it| ctx.analyzer.type_of(ctx.db, it.into())) {
+    let struct_lit = match ctx.struct_lit_syntax {
         Some(it) => it,
         None => return,
     };
-    let (adt, substs) = match ty.as_adt() {
-        Some(res) => res,
-        _ => return,
+    let fields = match variant_fields(ctx, struct_lit) {
+        Some(it) => it,
+        None => return,
     };
-    match adt {
-        AdtDef::Struct(s) => {
-            for field in s.fields(ctx.db) {
-                acc.add_field(ctx, field, substs);
+    let initialized = initialized_fields(ctx, struct_lit);
+    let mut missing_fields = false;
+    for (field, ty) in fields {
+        if initialized.contains(&field.name(ctx.db).to_string()) {
+            continue;
+        }
+        missing_fields = true;
+        acc.add_field(ctx, field, &ty);
+    }
+    let has_spread = struct_lit.named_field_list().and_then(|it| it.spread()).is_some();
+    if missing_fields && !has_spread && is_default_struct(ctx, struct_lit) {
+        CompletionItem::new(CompletionKind::Reference, ctx.source_range(), "..Default::default()")
+            .kind(CompletionItemKind::Snippet)
+            .add_to(acc);
+    }
+}
+
+/// Whether the literal is of a struct which implements `Default`, so that the
+/// missing fields can be taken from its default value.
+fn is_default_struct(ctx: &CompletionContext, struct_lit: &ast::StructLit) -> bool {
+    let ty = match struct_lit_type(ctx, struct_lit) {
+        Some(it) => it,
+        None => return false,
+    };
+    match ty.as_adt() {
+        Some((AdtDef::Struct(_), _)) => ctx.analyzer.is_default(ctx.db, &ty),
+        _ => false,
+    }
+}
+
+/// The type of the literal. When its path doesn't tell, like for a type
+/// alias, the type is taken from the one expected of the literal.
+fn struct_lit_type(ctx: &CompletionContext, struct_lit: &ast::StructLit) -> Option<Ty> {
+    match ctx.analyzer.type_of(ctx.db, struct_lit.into()) {
+        Some(Ty::Unknown) | None => {}
+        ty => return ty,
+    }
+    // The expected type is the one of the outermost expression around the
+    // caret, so it's only the literal's if there's nothing in between.
+    match struct_lit.syntax().parent()?.kind() {
+        LET_STMT | ARG_LIST | BIN_EXPR => ctx.expected_type.clone(),
+        _ => None,
+    }
+}
+
+fn variant_fields(
+    ctx: &CompletionContext,
+    struct_lit: &ast::StructLit,
+) -> Option<Vec<(StructField, Ty)>> {
+    let ty = struct_lit_type(ctx, struct_lit);
+    if let Some((AdtDef::Struct(_), _)) = ty.as_ref().and_then(|ty| ty.as_adt()) {
+        return ty.map(|ty| ty.struct_fields(ctx.db));
+    }
+    // The type of the literal doesn't tell us which variant of an enum is
+    // constructed, so fall back to resolving the path.
+    let fields = match ctx.analyzer.resolve_path(ctx.db, struct_lit.path()?)? {
+        hir::PathResolution::Def(hir::ModuleDef::Struct(it)) => it.fields(ctx.db),
+        hir::PathResolution::Def(hir::ModuleDef::EnumVariant(it)) => {
+            match ty.as_ref().and_then(|ty| ty.as_adt()) {
+                Some((AdtDef::Enum(e), _)) if e == it.parent_enum(ctx.db) => {
+                    return ty.map(|ty| ty.variant_fields(ctx.db, it));
+                }
+                _ => it.fields(ctx.db),
             }
         }
+        _ => return None,
+    };
+    Some(fields.into_iter().map(|field| (field, field.ty(ctx.db))).collect())
+}
 
-        // FIXME unions
-        AdtDef::Enum(_) => (),
+/// Names of the fields which are already present in the literal, excluding
+/// the one under the caret.
+fn initialized_fields(ctx: &CompletionContext, struct_lit: &ast::StructLit) -> Vec<String> {
+    let field_list = match struct_lit.named_field_list() {
+        Some(it) => it,
+        None => return Vec::new(),
     };
+    field_list
+        .fields()
+        .filter_map(|it| it.name_ref())
+        .filter(|it| !it.syntax().range().is_subrange(&ctx.source_range()))
+        .map(|it| it.text().to_string())
+        .collect()
 }
 
 #[cfg(test)]
@@ -52,6 +131,269 @@ mod tests {
         kind: Field,
         detail: "u32"
     }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_skips_initialized_fields() {
+        let completions = complete(
+            r"
+            struct A { foo: u32, bar: u32, baz: u32 }
+            fn foo() {
+               A { bar: 92, <|> ..Default::default() }
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "baz",
+        source_range: [106; 106),
+        delete: [106; 106),
+        insert: "baz",
+        kind: Field,
+        detail: "u32"
+    },
+    CompletionItem {
+        label: "foo",
+        source_range: [106; 106),
+        delete: [106; 106),
+        insert: "foo",
+        kind: Field,
+        detail: "u32"
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_enum_variant_fields() {
+        let completions = complete(
+            r"
+            enum E { A { a: u32, b: bool } }
+            fn foo() {
+               E::A { <|> }
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "a",
+        source_range: [91; 91),
+        delete: [91; 91),
+        insert: "a",
+        kind: Field,
+        detail: "u32"
+    },
+    CompletionItem {
+        label: "b",
+        source_range: [91; 91),
+        delete: [91; 91),
+        insert: "b",
+        kind: Field,
+        detail: "bool"
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_generic_enum_variant_fields() {
+        let completions = complete(
+            r"
+            enum E<T> { A { a: T } }
+            fn foo() {
+               let e: E<u32> = E::A { <|> };
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "a",
+        source_range: [99; 99),
+        delete: [99; 99),
+        insert: "a",
+        kind: Field,
+        detail: "u32"
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_generic_field() {
+        let completions = complete(
+            r"
+            struct A<T> { the_field: T }
+            fn foo() {
+               A::<bool> { the<|> }
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "the_field",
+        source_range: [92; 95),
+        delete: [92; 95),
+        insert: "the_field",
+        kind: Field,
+        detail: "bool"
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_default_spread() {
+        let completions = complete(
+            r"
+            //- /main.rs crate:main deps:core
+            #[derive(Default)]
+            struct A { foo: u32, bar: u32 }
+            fn foo() {
+               A { foo: 92, <|> }
+            }
+            //- /core/lib.rs crate:core
+            pub mod default { pub trait Default { fn default() -> Self; } }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "..Default::default()",
+        source_range: [78; 78),
+        delete: [78; 78),
+        insert: "..Default::default()",
+        kind: Snippet
+    },
+    CompletionItem {
+        label: "bar",
+        source_range: [78; 78),
+        delete: [78; 78),
+        insert: "bar",
+        kind: Field,
+        detail: "u32"
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_default_spread_with_impl() {
+        let completions = complete(
+            r"
+            //- /main.rs crate:main deps:core
+            struct A<T> { foo: T }
+            impl<T> core::default::Default for A<T> { fn default() -> Self { loop {} } }
+            fn foo() {
+               A::<u32> { <|> };
+            }
+            //- /core/lib.rs crate:core
+            pub mod default { pub trait Default { fn default() -> Self; } }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "..Default::default()",
+        source_range: [125; 125),
+        delete: [125; 125),
+        insert: "..Default::default()",
+        kind: Snippet
+    },
+    CompletionItem {
+        label: "foo",
+        source_range: [125; 125),
+        delete: [125; 125),
+        insert: "foo",
+        kind: Field,
+        detail: "u32"
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_default_spread_with_blanket_impl() {
+        let completions = complete(
+            r"
+            //- /main.rs crate:main deps:core
+            struct A { foo: u32 }
+            fn foo() {
+               A { <|> };
+            }
+            //- /core/lib.rs crate:core
+            pub mod default {
+                pub trait Default { fn default() -> Self; }
+                impl<T> Default for T { fn default() -> Self { loop {} } }
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "..Default::default()",
+        source_range: [40; 40),
+        delete: [40; 40),
+        insert: "..Default::default()",
+        kind: Snippet
+    },
+    CompletionItem {
+        label: "foo",
+        source_range: [40; 40),
+        delete: [40; 40),
+        insert: "foo",
+        kind: Field,
+        detail: "u32"
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_no_default_spread_for_other_default_trait() {
+        let completions = complete(
+            r"
+            trait Default { fn default() -> Self; }
+            struct A { foo: u32 }
+            impl Default for A { fn default() -> Self { loop {} } }
+            fn foo() {
+               A { <|> };
+            }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "foo",
+        source_range: [197; 197),
+        delete: [197; 197),
+        insert: "foo",
+        kind: Field,
+        detail: "u32"
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_struct_literal_type_from_expected_type() {
+        let completions = complete(
+            r"
+            //- /main.rs crate:main deps:core
+            #[derive(Default)]
+            struct A { foo: u32 }
+            type B = A;
+            fn foo() {
+               let a: A = B { <|> };
+            }
+            //- /core/lib.rs crate:core
+            pub mod default { pub trait Default { fn default() -> Self; } }
+            ",
+        );
+        assert_debug_snapshot_matches!(completions, @r###"[
+    CompletionItem {
+        label: "..Default::default()",
+        source_range: [82; 82),
+        delete: [82; 82),
+        insert: "..Default::default()",
+        kind: Snippet
+    },
+    CompletionItem {
+        label: "foo",
+        source_range: [82; 82),
+        delete: [82; 82),
+        insert: "foo",
+        kind: Field,
+        detail: "u32"
+    }
 ]"###);
     }
 }
@@ -6,21 +6,35 @@ use ra_syntax::{
     SyntaxKind::*,
 };
 use hir::source_binder;
+use ra_db::SourceDatabase;
 
-use crate::{db, FilePosition};
+use crate::{db, FileId, FilePosition, completion::CompletionOptions};
+
+const FAKE_IDENT: &str = "intellijRulezz";
 
 /// `CompletionContext` is created early during completion to figure out, where
 /// exactly is the cursor, syntax-wise.
 #[derive(Debug)]
 pub(crate) struct CompletionContext<'a> {
     pub(super) db: &'a db::RootDatabase,
+    pub(super) options: &'a CompletionOptions,
     pub(super) analyzer: hir::SourceAnalyzer,
+    pub(super) file_id: FileId,
     pub(super) offset: TextUnit,
     pub(super) token: SyntaxToken<'a>,
     pub(super) module: Option<hir::Module>,
     pub(super) function_syntax: Option<&'a ast::FnDef>,
     pub(super) use_item_syntax: Option<&'a ast::UseItem>,
     pub(super) struct_lit_syntax: Option<&'a ast::StructLit>,
+    /// The struct pattern whose field list contains the cursor, like `S { <|> }`.
+    pub(super) struct_pat_syntax: Option<&'a ast::StructPat>,
+    /// The expression matched against the pattern being completed, like `e`
+    /// in `match e { <|> }` or `let <|> = e;`.
+    pub(super) scrutinee: Option<&'a ast::Expr>,
+    /// The module declaration whose name is being typed, like `mod <|>;`.
+    pub(super) mod_decl_under_caret: Option<&'a ast::Module>,
+    /// The attribute whose token tree contains the cursor, like `#[<|>]`.
+    pub(super) attribute_under_caret: Option<&'a ast::Attr>,
     pub(super) is_param: bool,
     /// If a name-binding or reference to a const in a pattern.
     /// Irrefutable patterns (like let) are excluded.
@@ -36,8 +50,15 @@ pub(crate) struct CompletionContext<'a> {
     pub(super) is_new_item: bool,
     /// The receiver if this is a field or method access, i.e. writing something.<|>
     pub(super) dot_receiver: Option<&'a ast::Expr>,
+    /// The type of the receiver of a field or method access in the arguments
+    /// of a macro call, where the receiver is only an expression in the
+    /// expansion.
+    pub(super) macro_receiver_ty: Option<hir::Ty>,
     /// If this is a call (method or function) in particular, i.e. the () are already there.
     pub(super) is_call: bool,
+    /// The type of the expression expected at the cursor, like the type of the
+    /// parameter in `foo(<|>)` or of the binding in `let x: u32 = <|>;`.
+    pub(super) expected_type: Option<hir::Ty>,
 }
 
 impl<'a> CompletionContext<'a> {
@@ -45,6 +66,7 @@ impl<'a> CompletionContext<'a> {
         db: &'a db::RootDatabase,
         original_file: &'a SourceFile,
         position: FilePosition,
+        options: &'a CompletionOptions,
     ) -> Option<CompletionContext<'a>> {
         let module = source_binder::module_from_position(db, position);
         let token = find_token_at_offset(original_file.syntax(), position.offset).left_biased()?;
@@ -52,13 +74,19 @@ impl<'a> CompletionContext<'a> {
             hir::SourceAnalyzer::new(db, position.file_id, token.parent(), Some(position.offset));
         let mut ctx = CompletionContext {
             db,
+            options,
             analyzer,
             token,
+            file_id: position.file_id,
             offset: position.offset,
             module,
             function_syntax: None,
             use_item_syntax: None,
             struct_lit_syntax: None,
+            struct_pat_syntax: None,
+            scrutinee: None,
+            mod_decl_under_caret: None,
+            attribute_under_caret: None,
             is_param: false,
             is_pat_binding: false,
             is_trivial_path: false,
@@ -67,7 +95,9 @@ impl<'a> CompletionContext<'a> {
             can_be_stmt: false,
             is_new_item: false,
             dot_receiver: None,
+            macro_receiver_ty: None,
             is_call: false,
+            expected_type: None,
         };
         ctx.fill(original_file, position.offset);
         Some(ctx)
@@ -77,20 +107,44 @@ impl<'a> CompletionContext<'a> {
     pub(crate) fn source_range(&self) -> TextRange {
         match self.token.kind() {
             // workaroud when completion is triggered by trigger characters.
-            IDENT => self.token.range(),
+            IDENT | LIFETIME => self.token.range(),
             _ => TextRange::offset_len(self.offset, 0.into()),
         }
     }
 
     fn fill(&mut self, original_file: &'a SourceFile, offset: TextUnit) {
+        // Attributes contain only token trees, so there's nothing to classify
+        // in the reparsed file.
+        self.attribute_under_caret = self.token.parent().ancestors().find_map(ast::Attr::cast);
+        if self.attribute_under_caret.is_some() {
+            return;
+        }
+        self.expected_type =
+            self.find_expected_type(original_file, offset).filter(|it| *it != hir::Ty::Unknown);
+
         // Insert a fake ident to get a valid parse tree. We will use this file
         // to determine context, though the original_file will be used for
         // actual completion.
         let file = {
-            let edit = AtomTextEdit::insert(offset, "intellijRulezz".to_string());
-            original_file.reparse(&edit)
+            let edit = AtomTextEdit::insert(offset, FAKE_IDENT.to_string());
+            original_file.reparse(&edit, self.db.file_edition(self.file_id))
         };
 
+        // Macro arguments are only token trees as well, but the macro can be
+        // expanded to find out what they are.
+        let macro_call = self
+            .token
+            .parent()
+            .ancestors()
+            .filter_map(ast::TokenTree::cast)
+            .last()
+            .and_then(|it| it.syntax().parent())
+            .and_then(ast::MacroCall::cast);
+        if let Some(macro_call) = macro_call {
+            self.classify_in_macro_call(macro_call, &file, offset);
+            return;
+        }
+
         // First, let's try to complete a reference to some declaration.
         if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(file.syntax(), offset) {
             // Special case, `trait T { fn foo(i_am_a_name_ref) {} }`.
@@ -113,11 +167,23 @@ impl<'a> CompletionContext<'a> {
                 {
                     self.is_pat_binding = true;
                 }
+                if parent.and_then(ast::FieldPatList::cast).is_some() {
+                    self.struct_pat_syntax = find_node_at_offset(original_file.syntax(), offset);
+                }
+                self.scrutinee = parent.and_then(|it| find_scrutinee(original_file, it, offset));
             }
             if is_node::<ast::Param>(name.syntax()) {
                 self.is_param = true;
                 return;
             }
+            if let Some(module) = name.syntax().parent().and_then(ast::Module::cast) {
+                // The module starts before the cursor, so it's at the same
+                // offset in the original file.
+                let start = module.syntax().range().start();
+                self.mod_decl_under_caret =
+                    find_node_at_offset::<ast::Module>(original_file.syntax(), start)
+                        .filter(|it| it.item_list().is_none());
+            }
         }
     }
 
@@ -153,12 +219,7 @@ impl<'a> CompletionContext<'a> {
         };
         if let Some(segment) = ast::PathSegment::cast(parent) {
             let path = segment.parent_path();
-            self.is_call = path
-                .syntax()
-                .parent()
-                .and_then(ast::PathExpr::cast)
-                .and_then(|it| it.syntax().parent().and_then(ast::CallExpr::cast))
-                .is_some();
+            self.is_call = is_callee(path);
 
             if let Some(mut path) = hir::Path::from_ast(path) {
                 if !path.is_ident() {
@@ -217,6 +278,124 @@ impl<'a> CompletionContext<'a> {
             self.is_call = true;
         }
     }
+
+    fn find_expected_type(&self, original_file: &SourceFile, offset: TextUnit) -> Option<hir::Ty> {
+        let token = find_token_at_offset(original_file.syntax(), offset).left_biased()?;
+        for node in token.parent().ancestors() {
+            if let Some(arg_list) = ast::ArgList::cast(node) {
+                let index = arg_list
+                    .syntax()
+                    .children_with_tokens()
+                    .filter(|it| it.kind() == COMMA && it.range().end() <= offset)
+                    .count();
+                let parent = arg_list.syntax().parent()?;
+                let (sig, index) = if let Some(call) = ast::CallExpr::cast(parent) {
+                    let callee_ty = self.analyzer.type_of(self.db, call.expr()?)?;
+                    (callee_ty.callable_sig(self.db)?, index)
+                } else {
+                    let call = ast::MethodCallExpr::cast(parent)?;
+                    let function = self.analyzer.resolve_method_call(call)?;
+                    let has_self_param = function.signature(self.db).has_self_param();
+                    (function.ty(self.db).callable_sig(self.db)?, index + has_self_param as usize)
+                };
+                return sig.params().get(index).cloned();
+            }
+            if let Some(let_stmt) = ast::LetStmt::cast(node) {
+                let eq = let_stmt.syntax().children_with_tokens().find(|it| it.kind() == EQ)?;
+                if eq.range().end() > offset {
+                    return None;
+                }
+                return self.analyzer.type_of_pat(self.db, let_stmt.pat()?);
+            }
+            if let Some(bin_expr) = ast::BinExpr::cast(node) {
+                if bin_expr.op_kind() == Some(ast::BinOp::Assignment)
+                    && bin_expr.lhs()?.syntax().range().end() < offset
+                {
+                    return self.analyzer.type_of(self.db, bin_expr.lhs()?);
+                }
+            }
+            match node.kind() {
+                EXPR_STMT | BLOCK | FN_DEF => return None,
+                _ => (),
+            }
+        }
+        None
+    }
+
+    /// Classifies the reference being completed in the arguments of
+    /// `macro_call`, by expanding the call of `file`, which has the fake ident.
+    fn classify_in_macro_call(
+        &mut self,
+        macro_call: &ast::MacroCall,
+        file: &SourceFile,
+        offset: TextUnit,
+    ) -> Option<()> {
+        let sema = hir::Semantics::new(self.db);
+        // `parse` is memoized, so this registers the tree of `macro_call`.
+        sema.parse(self.file_id);
+        let fake_token = find_token_at_offset(file.syntax(), offset).right_biased()?;
+        let speculative_call = fake_token.parent().ancestors().find_map(ast::MacroCall::cast)?;
+        let expansion = sema.expand_expr_speculative(macro_call, speculative_call)?;
+        let range = sema.map_token_down(&expansion, fake_token.range())?;
+        let name_ref = find_covering_element(expansion.syntax(), range)
+            .ancestors()
+            .find_map(ast::NameRef::cast)?;
+
+        self.function_syntax = self.token.parent().ancestors().find_map(ast::FnDef::cast);
+        let parent = name_ref.syntax().parent()?;
+        if let Some(segment) = ast::PathSegment::cast(parent) {
+            let path = segment.parent_path();
+            self.is_call = is_callee(path);
+            match hir::Path::from_ast(path).filter(|it| !it.is_ident()) {
+                Some(mut path) => {
+                    path.segments.pop().unwrap();
+                    self.path_prefix = Some(path);
+                }
+                None => self.is_trivial_path = path.qualifier().is_none(),
+            }
+        }
+        if let Some(field_expr) = ast::FieldExpr::cast(parent) {
+            self.macro_receiver_ty = field_expr.expr().and_then(|it| sema.type_of_expr(it));
+        }
+        if let Some(method_call_expr) = ast::MethodCallExpr::cast(parent) {
+            self.macro_receiver_ty = method_call_expr.expr().and_then(|it| sema.type_of_expr(it));
+            self.is_call = true;
+        }
+        Some(())
+    }
+}
+
+/// Whether `path` is the callee of a call expression, like `foo` in `foo()`.
+fn is_callee(path: &ast::Path) -> bool {
+    path.syntax()
+        .parent()
+        .and_then(ast::PathExpr::cast)
+        .and_then(|it| it.syntax().parent().and_then(ast::CallExpr::cast))
+        .is_some()
+}
+
+/// Finds the expression matched against the pattern whose parent is
+/// `pat_parent`. The pattern may be missing from the original file, so we look
+/// at the file with the fake ident and map the range of the expression back.
+fn find_scrutinee<'a>(
+    original_file: &'a SourceFile,
+    pat_parent: &SyntaxNode,
+    offset: TextUnit,
+) -> Option<&'a ast::Expr> {
+    let expr = match pat_parent.kind() {
+        MATCH_ARM => pat_parent.ancestors().find_map(ast::MatchExpr::cast)?.expr()?,
+        CONDITION => ast::Condition::cast(pat_parent)?.expr()?,
+        LET_STMT => ast::LetStmt::cast(pat_parent)?.initializer()?,
+        _ => return None,
+    };
+    let range = expr.syntax().range();
+    let range = if range.start() >= offset {
+        let shift = TextUnit::of_str(FAKE_IDENT);
+        TextRange::from_to(range.start() - shift, range.end() - shift)
+    } else {
+        range
+    };
+    find_node_with_range(original_file.syntax(), range)
 }
 
 fn find_node_with_range<N: AstNode>(syntax: &SyntaxNode, range: TextRange) -> Option<&N> {
@@ -42,6 +42,11 @@ pub struct CompletionItem {
     /// Additional info to show in the UI pop up.
     detail: Option<String>,
     documentation: Option<Documentation>,
+    /// Whether the item is marked `#[deprecated]`.
+    deprecated: bool,
+
+    /// How likely this is the item the user wants, to sort the completions.
+    relevance: CompletionRelevance,
 }
 
 // We use custom debug for CompletionItem to make `insta`'s diffs more readable.
@@ -68,10 +73,69 @@ impl fmt::Debug for CompletionItem {
         if let Some(documentation) = self.documentation() {
             s.field("documentation", &documentation);
         }
+        if self.deprecated {
+            s.field("deprecated", &true);
+        }
         s.finish()
     }
 }
 
+/// What makes a completion item more likely to be the one the user wants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompletionRelevance {
+    /// The type of the item is the type expected at the cursor, like the type
+    /// of the parameter of a call.
+    pub exact_type_match: bool,
+    /// Where the item is defined, relative to the cursor.
+    pub locality: Locality,
+    /// The text before the cursor is a prefix of the name of the item, or of
+    /// one of the words of its snake case name.
+    pub is_prefix_match: bool,
+    /// The item is marked `#[deprecated]`, which is set by
+    /// `Builder::set_deprecated`.
+    pub is_deprecated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Locality {
+    Elsewhere,
+    SameCrate,
+    /// In the module of the cursor, including the local bindings.
+    SameModule,
+}
+
+impl Default for Locality {
+    fn default() -> Locality {
+        Locality::Elsewhere
+    }
+}
+
+impl CompletionRelevance {
+    const MAX_SCORE: u32 = 31;
+
+    /// The score of the item, the higher the more relevant. The deprecated
+    /// items score lower than all the others, then the criteria are weighted
+    /// in the order of the fields.
+    pub fn score(&self) -> u32 {
+        let mut score = 0;
+        if !self.is_deprecated {
+            score += 16;
+        }
+        if self.exact_type_match {
+            score += 8;
+        }
+        if self.is_prefix_match {
+            score += 4;
+        }
+        score += match self.locality {
+            Locality::Elsewhere => 0,
+            Locality::SameCrate => 1,
+            Locality::SameModule => 2,
+        };
+        score
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompletionItemKind {
     Snippet,
@@ -89,6 +153,7 @@ pub enum CompletionItemKind {
     TypeAlias,
     Method,
     TypeParam,
+    Attribute,
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -101,6 +166,7 @@ pub(crate) enum CompletionKind {
     Magic,
     Snippet,
     Postfix,
+    Attribute,
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -127,6 +193,8 @@ impl CompletionItem {
             lookup: None,
             kind: None,
             text_edit: None,
+            deprecated: false,
+            relevance: CompletionRelevance::default(),
         }
     }
     /// What user sees in pop-up in the UI.
@@ -161,6 +229,24 @@ impl CompletionItem {
     pub fn kind(&self) -> Option<CompletionItemKind> {
         self.kind
     }
+
+    pub fn deprecated(&self) -> bool {
+        self.deprecated
+    }
+
+    pub fn relevance(&self) -> CompletionRelevance {
+        self.relevance
+    }
+
+    /// The text by which the editor sorts the items: the most relevant come
+    /// first, and the ones of the same relevance by label.
+    pub fn sort_text(&self) -> String {
+        format!("{:02}{}", CompletionRelevance::MAX_SCORE - self.relevance.score(), self.label)
+    }
+
+    pub(crate) fn relevance_mut(&mut self) -> &mut CompletionRelevance {
+        &mut self.relevance
+    }
 }
 
 /// A helper to make `CompletionItem`s.
@@ -176,6 +262,8 @@ pub(crate) struct Builder {
     lookup: Option<String>,
     kind: Option<CompletionItemKind>,
     text_edit: Option<TextEdit>,
+    deprecated: bool,
+    relevance: CompletionRelevance,
 }
 
 impl Builder {
@@ -205,6 +293,8 @@ impl Builder {
             lookup: self.lookup,
             kind: self.kind,
             completion_kind: self.completion_kind,
+            deprecated: self.deprecated,
+            relevance: CompletionRelevance { is_deprecated: self.deprecated, ..self.relevance },
         }
     }
     pub(crate) fn lookup_by(mut self, lookup: impl Into<String>) -> Builder {
@@ -247,6 +337,14 @@ impl Builder {
         self.documentation = docs.map(Into::into);
         self
     }
+    pub(crate) fn set_deprecated(mut self, deprecated: bool) -> Builder {
+        self.deprecated = deprecated;
+        self
+    }
+    pub(crate) fn relevance(mut self, relevance: CompletionRelevance) -> Builder {
+        self.relevance = relevance;
+        self
+    }
 }
 
 impl<'a> Into<CompletionItem> for Builder {
@@ -272,6 +370,9 @@ impl Completions {
     {
         items.into_iter().for_each(|item| self.add(item.into()))
     }
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut CompletionItem> {
+        self.buf.iter_mut()
+    }
 }
 
 impl Into<Vec<CompletionItem>> for Completions {
@@ -282,6 +383,15 @@ impl Into<Vec<CompletionItem>> for Completions {
 
 #[cfg(test)]
 pub(crate) fn do_completion(code: &str, kind: CompletionKind) -> Vec<CompletionItem> {
+    do_completion_with_options(code, kind, &Default::default())
+}
+
+#[cfg(test)]
+pub(crate) fn do_completion_with_options(
+    code: &str,
+    kind: CompletionKind,
+    options: &crate::completion::CompletionOptions,
+) -> Vec<CompletionItem> {
     use crate::mock_analysis::{single_file_with_position, analysis_and_position};
     use crate::completion::completions;
     let (analysis, position) = if code.contains("//-") {
@@ -289,7 +399,7 @@ pub(crate) fn do_completion(code: &str, kind: CompletionKind) -> Vec<CompletionI
     } else {
         single_file_with_position(code)
     };
-    let completions = completions(&analysis.db, position).unwrap();
+    let completions = completions(&analysis.db, position, options).unwrap();
     let completion_items: Vec<CompletionItem> = completions.into();
     let mut kind_completions: Vec<CompletionItem> =
         completion_items.into_iter().filter(|c| c.completion_kind == kind).collect();
@@ -1,11 +1,12 @@
 //! This modules takes care of rendering various defenitions as completion items.
 use join_to_string::join;
 use test_utils::tested_by;
-use hir::{Docs, PerNs, Resolution, HirDisplay};
-use ra_syntax::ast::NameOwner;
+use hir::{Attrs, Docs, PerNs, Resolution, HirDisplay};
+use ra_syntax::ast::{NameOwner, StructKind};
 
 use crate::completion::{
     Completions, CompletionKind, CompletionItemKind, CompletionContext, CompletionItem,
+    completion_item::{CompletionRelevance, Locality},
 };
 
 use crate::display::{
@@ -17,7 +18,7 @@ impl Completions {
         &mut self,
         ctx: &CompletionContext,
         field: hir::StructField,
-        substs: &hir::Substs,
+        ty: &hir::Ty,
     ) {
         CompletionItem::new(
             CompletionKind::Reference,
@@ -25,8 +26,10 @@ impl Completions {
             field.name(ctx.db).to_string(),
         )
         .kind(CompletionItemKind::Field)
-        .detail(field.ty(ctx.db).subst(substs).display(ctx.db).to_string())
+        .detail(ty.display(ctx.db).to_string())
         .set_documentation(field.docs(ctx.db))
+        .set_deprecated(field.deprecation(ctx.db).is_some())
+        .relevance(relevance(ctx, Some(ty), None))
         .add_to(self);
     }
 
@@ -34,6 +37,7 @@ impl Completions {
         CompletionItem::new(CompletionKind::Reference, ctx.source_range(), field.to_string())
             .kind(CompletionItemKind::Field)
             .detail(ty.display(ctx.db).to_string())
+            .relevance(relevance(ctx, Some(ty), None))
             .add_to(self);
     }
 
@@ -57,20 +61,58 @@ impl Completions {
             }
             Some(it) => it,
         };
+        let deprecated = match def {
+            Resolution::Def(it) => it.deprecation(ctx.db).is_some(),
+            _ => false,
+        };
+        let mut detail = None;
+        let mut ty = None;
+        let mut module = None;
         let (kind, docs) = match def {
-            Resolution::Def(Module(it)) => (CompletionItemKind::Module, it.docs(ctx.db)),
+            Resolution::Def(Module(it)) => {
+                detail = extern_crate_version(ctx, *it);
+                module = it.parent(ctx.db);
+                (CompletionItemKind::Module, it.docs(ctx.db))
+            }
             Resolution::Def(Function(func)) => {
                 return self.add_function_with_name(ctx, Some(local_name), *func);
             }
-            Resolution::Def(Struct(it)) => (CompletionItemKind::Struct, it.docs(ctx.db)),
-            Resolution::Def(Enum(it)) => (CompletionItemKind::Enum, it.docs(ctx.db)),
-            Resolution::Def(EnumVariant(it)) => (CompletionItemKind::EnumVariant, it.docs(ctx.db)),
-            Resolution::Def(Const(it)) => (CompletionItemKind::Const, it.docs(ctx.db)),
-            Resolution::Def(Static(it)) => (CompletionItemKind::Static, it.docs(ctx.db)),
-            Resolution::Def(Trait(it)) => (CompletionItemKind::Trait, it.docs(ctx.db)),
-            Resolution::Def(TypeAlias(it)) => (CompletionItemKind::TypeAlias, it.docs(ctx.db)),
+            Resolution::Def(Struct(it)) => {
+                module = Some(it.module(ctx.db));
+                (CompletionItemKind::Struct, it.docs(ctx.db))
+            }
+            Resolution::Def(Enum(it)) => {
+                module = Some(it.module(ctx.db));
+                (CompletionItemKind::Enum, it.docs(ctx.db))
+            }
+            Resolution::Def(EnumVariant(it)) => {
+                module = Some(it.module(ctx.db));
+                (CompletionItemKind::EnumVariant, it.docs(ctx.db))
+            }
+            Resolution::Def(Const(it)) => {
+                ty = Some(it.ty(ctx.db));
+                module = Some(it.module(ctx.db));
+                (CompletionItemKind::Const, it.docs(ctx.db))
+            }
+            Resolution::Def(Static(it)) => {
+                ty = Some(it.ty(ctx.db));
+                module = Some(it.module(ctx.db));
+                (CompletionItemKind::Static, it.docs(ctx.db))
+            }
+            Resolution::Def(Trait(it)) => {
+                module = Some(it.module(ctx.db));
+                (CompletionItemKind::Trait, it.docs(ctx.db))
+            }
+            Resolution::Def(TypeAlias(it)) => {
+                module = Some(it.module(ctx.db));
+                (CompletionItemKind::TypeAlias, it.docs(ctx.db))
+            }
             Resolution::GenericParam(..) => (CompletionItemKind::TypeParam, None),
-            Resolution::LocalBinding(..) => (CompletionItemKind::Binding, None),
+            Resolution::LocalBinding(pat) => {
+                ty = ctx.analyzer.type_of_binding(*pat);
+                module = ctx.module;
+                (CompletionItemKind::Binding, None)
+            }
             Resolution::SelfType(..) => (
                 CompletionItemKind::TypeParam, // (does this need its own kind?)
                 None,
@@ -79,6 +121,9 @@ impl Completions {
         CompletionItem::new(CompletionKind::Reference, ctx.source_range(), local_name)
             .kind(kind)
             .set_documentation(docs)
+            .set_detail(detail)
+            .set_deprecated(deprecated)
+            .relevance(relevance(ctx, ty.as_ref(), module))
             .add_to(self)
     }
 
@@ -96,6 +141,8 @@ impl Completions {
         let name = name.unwrap_or_else(|| sig.name().to_string());
         let (_, ast_node) = func.source(ctx.db);
         let detail = function_label(&ast_node);
+        let ret_ty = func.ty(ctx.db).callable_sig(ctx.db).map(|it| it.ret().clone());
+        let relevance = relevance(ctx, ret_ty.as_ref(), Some(func.module(ctx.db)));
 
         let mut builder = CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name)
             .kind(if sig.has_self_param() {
@@ -104,9 +151,11 @@ impl Completions {
                 CompletionItemKind::Function
             })
             .set_documentation(func.docs(ctx.db))
-            .detail(detail);
+            .detail(detail)
+            .set_deprecated(func.deprecation(ctx.db).is_some())
+            .relevance(relevance);
         // If not an import, add parenthesis automatically.
-        if ctx.use_item_syntax.is_none() && !ctx.is_call {
+        if ctx.options.add_call_parenthesis && ctx.use_item_syntax.is_none() && !ctx.is_call {
             tested_by!(inserts_parens_for_function_calls);
             let snippet =
                 if sig.params().is_empty() || sig.has_self_param() && sig.params().len() == 1 {
@@ -127,11 +176,14 @@ impl Completions {
         };
         let (_, ast_node) = constant.source(ctx.db);
         let detail = const_label(&ast_node);
+        let relevance = relevance(ctx, Some(&constant.ty(ctx.db)), Some(constant.module(ctx.db)));
 
         CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name.text().to_string())
             .kind(CompletionItemKind::Const)
             .set_documentation(constant.docs(ctx.db))
             .detail(detail)
+            .set_deprecated(constant.deprecation(ctx.db).is_some())
+            .relevance(relevance)
             .add_to(self);
     }
 
@@ -148,6 +200,8 @@ impl Completions {
             .kind(CompletionItemKind::TypeAlias)
             .set_documentation(type_alias.docs(ctx.db))
             .detail(detail)
+            .set_deprecated(type_alias.deprecation(ctx.db).is_some())
+            .relevance(relevance(ctx, None, Some(type_alias.module(ctx.db))))
             .add_to(self);
     }
 
@@ -166,20 +220,133 @@ impl Completions {
             .kind(CompletionItemKind::EnumVariant)
             .set_documentation(variant.docs(ctx.db))
             .detail(detail)
+            .set_deprecated(variant.deprecation(ctx.db).is_some())
+            .relevance(relevance(ctx, None, Some(variant.module(ctx.db))))
+            .add_to(self);
+    }
+
+    /// Adds a pattern matching `variant`, like `Some($0)`.
+    pub(crate) fn add_enum_variant_pat(
+        &mut self,
+        ctx: &CompletionContext,
+        variant: hir::EnumVariant,
+        qualify: bool,
+    ) {
+        let name = match variant.name(ctx.db) {
+            Some(it) => it.to_string(),
+            None => return,
+        };
+        let path = match variant.parent_enum(ctx.db).name(ctx.db) {
+            Some(ref enum_name) if qualify => format!("{}::{}", enum_name, name),
+            _ => name,
+        };
+        let (_, ast_node) = variant.source(ctx.db);
+        self.add_pat(ctx, path, ast_node.kind(), CompletionItemKind::EnumVariant);
+    }
+
+    /// Adds a pattern destructuring `strukt`, like `Point { x$0, y }`.
+    pub(crate) fn add_struct_pat(&mut self, ctx: &CompletionContext, strukt: hir::Struct) {
+        let name = match strukt.name(ctx.db) {
+            Some(it) => it.to_string(),
+            None => return,
+        };
+        let (_, ast_node) = strukt.source(ctx.db);
+        self.add_pat(ctx, name, ast_node.kind(), CompletionItemKind::Struct);
+    }
+
+    fn add_pat(
+        &mut self,
+        ctx: &CompletionContext,
+        path: String,
+        struct_kind: StructKind,
+        kind: CompletionItemKind,
+    ) {
+        let (label, snippet) = match struct_kind {
+            StructKind::Unit => (path.clone(), path.clone()),
+            StructKind::Tuple(fields) => {
+                let snippet = match fields.fields().count() {
+                    1 => format!("{}($0)", path),
+                    n => {
+                        let placeholders = join((1..=n).map(|i| format!("${{{}:_}}", i)))
+                            .separator(", ")
+                            .to_string();
+                        format!("{}({})$0", path, placeholders)
+                    }
+                };
+                (format!("{}(…)", path), snippet)
+            }
+            StructKind::Named(fields) => {
+                let names =
+                    fields.fields().filter_map(|it| it.name()).enumerate().map(|(i, it)| {
+                        if i == 0 {
+                            format!("{}$0", it.text())
+                        } else {
+                            it.text().to_string()
+                        }
+                    });
+                let snippet = join(names).separator(", ").surround_with(" { ", " }").to_string();
+                (format!("{} {{ … }}", path), format!("{}{}", path, snippet))
+            }
+        };
+        CompletionItem::new(CompletionKind::Magic, ctx.source_range(), label)
+            .kind(kind)
+            .lookup_by(path)
+            .insert_snippet(snippet)
             .add_to(self);
     }
 }
 
+/// The relevance of an item of type `ty`, defined in `module`.
+fn relevance(
+    ctx: &CompletionContext,
+    ty: Option<&hir::Ty>,
+    module: Option<hir::Module>,
+) -> CompletionRelevance {
+    let exact_type_match = match (ty, &ctx.expected_type) {
+        (Some(ty), Some(expected)) => ty == expected,
+        _ => false,
+    };
+    let locality = match (module, ctx.module) {
+        (Some(module), Some(current)) if module == current => Locality::SameModule,
+        (Some(module), Some(current)) if module.krate(ctx.db) == current.krate(ctx.db) => {
+            Locality::SameCrate
+        }
+        _ => Locality::Elsewhere,
+    };
+    CompletionRelevance { exact_type_match, locality, ..Default::default() }
+}
+
+/// If `module` is the root of a dependency crate, returns the version of the
+/// package it comes from, to be shown as the completion detail.
+fn extern_crate_version(ctx: &CompletionContext, module: hir::Module) -> Option<String> {
+    if module.parent(ctx.db).is_some() {
+        return None;
+    }
+    let krate = module.krate(ctx.db)?;
+    if ctx.module.and_then(|it| it.krate(ctx.db)) == Some(krate) {
+        return None;
+    }
+    krate.version(ctx.db).map(|it| it.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use test_utils::covers;
 
-    use crate::completion::{CompletionKind, check_completion};
+    use crate::completion::{CompletionKind, CompletionOptions, InsertTextFormat, check_completion};
+    use crate::completion::{do_completion, do_completion_with_options};
 
     fn check_reference_completion(code: &str, expected_completions: &str) {
         check_completion(code, expected_completions, CompletionKind::Reference);
     }
 
+    /// The labels of the reference completions, the most relevant first.
+    fn ranked_completions(code: &str) -> Vec<String> {
+        let mut completions = do_completion(code, CompletionKind::Reference);
+        completions.sort_by_key(|it| it.sort_text());
+        completions.into_iter().map(|it| it.label().to_string()).collect()
+    }
+
     #[test]
     fn inserts_parens_for_function_calls() {
         covers!(inserts_parens_for_function_calls);
@@ -211,6 +378,21 @@ mod tests {
         )
     }
 
+    #[test]
+    fn does_not_insert_parens_when_disabled() {
+        let options = CompletionOptions { add_call_parenthesis: false, ..Default::default() };
+        let completions = do_completion_with_options(
+            r"
+            fn with_args(x: i32, y: String) {}
+            fn main() { with_<|> }
+            ",
+            CompletionKind::Reference,
+            &options,
+        );
+        let item = completions.iter().find(|it| it.label() == "with_args").unwrap();
+        assert_eq!(item.insert_text_format(), InsertTextFormat::PlainText);
+    }
+
     #[test]
     fn dont_render_function_parens_in_use_item() {
         check_reference_completion(
@@ -248,4 +430,113 @@ mod tests {
         )
     }
 
+    #[test]
+    fn ranks_items_of_the_expected_type_first() {
+        let completions = ranked_completions(
+            r"
+            struct A;
+            struct B;
+            fn take_b(b: B) {}
+            fn main() {
+                let a = A;
+                let b = B;
+                take_b(<|>)
+            }
+            ",
+        );
+        assert_eq!(completions[0], "b");
+    }
+
+    #[test]
+    fn ranks_prefix_matches_first() {
+        let completions = ranked_completions(
+            r"
+            fn main() {
+                let abc = 1;
+                let xylophone = 2;
+                let zoo_x = 3;
+                x<|>
+            }
+            ",
+        );
+        assert_eq!(completions, vec!["xylophone", "zoo_x", "abc", "main"]);
+    }
+
+    #[test]
+    fn ranks_deprecated_items_last() {
+        let completions = ranked_completions(
+            r"
+            #[deprecated]
+            fn frobnicate_old() {}
+            fn frobnicate() {}
+            fn main() { frob<|> }
+            ",
+        );
+        assert_eq!(completions, vec!["frobnicate", "main", "frobnicate_old"]);
+    }
+
+    #[test]
+    fn marks_deprecated_items() {
+        let completions = do_completion(
+            r"
+            struct S {
+                #[deprecated]
+                old: u32,
+                new: u32,
+            }
+            fn foo(s: S) { s.<|> }
+            ",
+            CompletionKind::Reference,
+        );
+        let deprecated: Vec<_> =
+            completions.iter().map(|it| (it.label(), it.deprecated())).collect();
+        assert_eq!(deprecated, vec![("new", false), ("old", true)]);
+    }
+
+    #[test]
+    fn marks_deprecated_tuple_fields() {
+        let completions = do_completion(
+            r"
+            struct S(#[deprecated] u32, u32);
+            fn foo(s: S) { s.<|> }
+            ",
+            CompletionKind::Reference,
+        );
+        let deprecated: Vec<_> =
+            completions.iter().map(|it| (it.label(), it.deprecated())).collect();
+        assert_eq!(deprecated, vec![("0", true), ("1", false)]);
+    }
+
+    #[test]
+    fn ranks_items_of_the_current_module_first() {
+        let completions = ranked_completions(
+            r"
+            mod other {
+                pub fn far() {}
+            }
+            use other::far;
+            fn near() {}
+            fn main() { <|> }
+            ",
+        );
+        let position = |label| completions.iter().position(|it| it == label).unwrap();
+        assert!(position("near") < position("far"));
+    }
+
+    #[test]
+    fn shows_versions_of_dependencies() {
+        let completions = do_completion(
+            r"
+            //- /main.rs crate:main deps:foo,bar_renamed=bar
+            use <|>
+            //- /foo/lib.rs crate:foo version:0.1.0
+            pub struct Foo;
+            //- /bar/lib.rs crate:bar version:2.0.0
+            pub struct Bar;
+            ",
+            CompletionKind::Reference,
+        );
+        let details: Vec<_> = completions.iter().map(|it| (it.label(), it.detail())).collect();
+        assert_eq!(details, vec![("bar_renamed", Some("2.0.0")), ("foo", Some("0.1.0"))]);
+    }
 }
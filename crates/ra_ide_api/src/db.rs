@@ -7,6 +7,10 @@ use ra_db::{
     CheckCanceled, FileId, Canceled, SourceDatabase,
     salsa,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use ra_db::salsa::ParallelDatabase;
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
 
 use crate::{LineIndex, symbol_index::{self, SymbolsDatabase}};
 
@@ -20,8 +24,10 @@ use crate::{LineIndex, symbol_index::{self, SymbolsDatabase}};
 #[derive(Debug)]
 pub(crate) struct RootDatabase {
     runtime: salsa::Runtime<RootDatabase>,
-    pub(crate) last_gc: time::Instant,
-    pub(crate) last_gc_check: time::Instant,
+    /// The times of the last garbage collection and of the last check for
+    /// it, which are unknown on wasm, where there is no clock.
+    pub(crate) last_gc: Option<time::Instant>,
+    pub(crate) last_gc_check: Option<time::Instant>,
 }
 
 impl salsa::Database for RootDatabase {
@@ -37,8 +43,8 @@ impl Default for RootDatabase {
     fn default() -> RootDatabase {
         let mut db = RootDatabase {
             runtime: salsa::Runtime::default(),
-            last_gc: time::Instant::now(),
-            last_gc_check: time::Instant::now(),
+            last_gc: now(),
+            last_gc_check: now(),
         };
         db.set_crate_graph(Default::default());
         db.set_local_roots(Default::default());
@@ -57,6 +63,48 @@ impl salsa::ParallelDatabase for RootDatabase {
     }
 }
 
+/// A snapshot of the database for the threads of parallel iterators, wrapped
+/// to provide the `Clone` impl needed by `map_with`.
+#[cfg(not(target_arch = "wasm32"))]
+struct Snap(salsa::Snapshot<RootDatabase>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clone for Snap {
+    fn clone(&self) -> Snap {
+        Snap(self.0.snapshot())
+    }
+}
+
+/// Maps `f` over `items` in parallel, with a snapshot of the database for
+/// each thread. On wasm, which has no threads, the items are mapped in order.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn par_map<T: Sync, R: Send>(
+    db: &RootDatabase,
+    items: &[T],
+    f: impl Fn(&RootDatabase, &T) -> R + Sync + Send,
+) -> Vec<R> {
+    let snap = Snap(db.snapshot());
+    items.par_iter().map_with(snap, |db, it| f(&db.0, it)).collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn par_map<T: Sync, R: Send>(
+    db: &RootDatabase,
+    items: &[T],
+    f: impl Fn(&RootDatabase, &T) -> R + Sync + Send,
+) -> Vec<R> {
+    items.iter().map(|it| f(db, it)).collect()
+}
+
+/// The current time, except on wasm, where `Instant::now` panics.
+pub(crate) fn now() -> Option<time::Instant> {
+    if cfg!(target_arch = "wasm32") {
+        None
+    } else {
+        Some(time::Instant::now())
+    }
+}
+
 #[salsa::query_group(LineIndexDatabaseStorage)]
 pub(crate) trait LineIndexDatabase: ra_db::SourceDatabase + CheckCanceled {
     fn line_index(&self, file_id: FileId) -> Arc<LineIndex>;
@@ -1,19 +1,32 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, iter::successors};
 
 use itertools::Itertools;
-use hir::{source_binder, diagnostics::{Diagnostic as _, DiagnosticSink}};
+use hir::{Module, Name, source_binder, diagnostics::{Diagnostic as _, DiagnosticSink}};
+use ra_fmt::leading_indent;
 use ra_db::SourceDatabase;
 use ra_syntax::{
-    Location, SourceFile, SyntaxKind, TextRange, SyntaxNode,
-    ast::{self, AstNode},
+    Direction, Location, SourceFile, SyntaxElement, SyntaxKind, TextRange, TextUnit, SyntaxNode,
+    SyntaxKind::COMMA,
+    algo::find_covering_element,
+    ast::{self, AstNode, NameOwner},
 };
 use ra_text_edit::{TextEdit, TextEditBuilder};
 
-use crate::{Diagnostic, FileId, FileSystemEdit, SourceChange, SourceFileEdit, db::RootDatabase};
+use crate::{
+    Diagnostic, FileId, FilePosition, FileRange, FileSystemEdit, SourceChange, SourceFileEdit,
+    assists,
+    db::RootDatabase,
+    organize_imports,
+    format_string::format_strings,
+};
+
+mod dead_code;
+mod lint_levels;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Severity {
     Error,
+    Warning,
     WeakWarning,
 }
 
@@ -27,31 +40,641 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
         check_unnecessary_braces_in_use_statement(&mut res, file_id, node);
         check_struct_shorthand_initialization(&mut res, file_id, node);
     }
+    check_unused_imports(&mut res, db, file_id, &source_file);
+    check_inline_format_args(&mut res, db, file_id, &source_file);
+    dead_code::check(&mut res, db, file_id);
     let res = RefCell::new(res);
+    // Diagnostics of child modules declared in other files are reported for
+    // those files.
+    let in_file = |d: &dyn hir::diagnostics::Diagnostic| d.file().original_file(db) == file_id;
+    // The ranges of diagnostics in macro expansions are offsets into the
+    // expansion, not into the file.
+    let range =
+        |d: &dyn hir::diagnostics::Diagnostic| d.file().original_range(db, d.highlight_range());
     let mut sink = DiagnosticSink::new(|d| {
+        if !in_file(d) {
+            return;
+        }
         res.borrow_mut().push(Diagnostic {
             message: d.message(),
-            range: d.highlight_range(),
+            code: Some(d.code()),
+            range: range(d),
             severity: Severity::Error,
-            fix: None,
+            fixes: Vec::new(),
         })
     })
     .on::<hir::diagnostics::UnresolvedModule, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
         let source_root = db.file_source_root(d.file().original_file(db));
-        let create_file = FileSystemEdit::CreateFile { source_root, path: d.candidate.clone() };
+        let create_file = FileSystemEdit::CreateFile {
+            source_root,
+            path: d.candidate.clone(),
+            text: String::new(),
+        };
         let fix = SourceChange::file_system_edit("create module", create_file);
         res.borrow_mut().push(Diagnostic {
-            range: d.highlight_range(),
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::Error,
+            fixes: vec![fix],
+        })
+    })
+    .on::<hir::diagnostics::UnresolvedImport, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
+        let fix = if d.file() == file_id.into() {
+            let use_tree = d.use_tree.to_node(&source_file);
+            fix_unresolved_import(db, file_id, use_tree)
+        } else {
+            None
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::Error,
+            fixes: fix.into_iter().collect(),
+        })
+    })
+    .on::<hir::diagnostics::MissingFields, _>(|d| {
+        // FIXME: offer the fix for struct literals in macro calls as well.
+        if d.file() != file_id.into() {
+            return;
+        }
+        let field_list = d.field_list.to_node(&source_file);
+        let fix = SourceChange::source_file_edit(
+            "fill struct fields",
+            SourceFileEdit { file_id, edit: fill_missing_fields(field_list, &d.missed_fields) },
+        );
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::Error,
+            fixes: vec![fix],
+        })
+    })
+    .on::<hir::diagnostics::UnresolvedValue, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
+        let fix = if d.file() == file_id.into() {
+            let path_expr = d.expr.to_node(&source_file);
+            fix_unresolved_value(db, file_id, path_expr, &d.name)
+        } else {
+            None
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::Error,
+            fixes: fix.into_iter().collect(),
+        })
+    })
+    .on::<hir::diagnostics::MissingUnsafe, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
+        let fix = if d.file() == file_id.into() {
+            let expr = d.expr.to_node(&source_file);
+            let mut edit = TextEditBuilder::default();
+            edit.replace(expr.syntax().range(), format!("unsafe {{ {} }}", expr.syntax().text()));
+            Some(SourceChange::source_file_edit(
+                "add unsafe block",
+                SourceFileEdit { file_id, edit: edit.finish() },
+            ))
+        } else {
+            None
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
             message: d.message(),
+            code: Some(d.code()),
             severity: Severity::Error,
-            fix: Some(fix),
+            fixes: fix.into_iter().collect(),
+        })
+    })
+    .on::<hir::diagnostics::UnusedVariable, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
+        let fix = if d.file() == file_id.into() {
+            let bind_pat = d.pat.to_node(&source_file);
+            fix_unused_variable(file_id, bind_pat)
+        } else {
+            None
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::WeakWarning,
+            fixes: fix.into_iter().collect(),
+        })
+    })
+    .on::<hir::diagnostics::UnusedMustUse, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
+        let fixes = if d.file() == file_id.into() {
+            let expr = d.expr.to_node(&source_file);
+            fix_unused_must_use(file_id, expr, d)
+        } else {
+            Vec::new()
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::Warning,
+            fixes,
+        })
+    })
+    .on::<hir::diagnostics::UnresolvedMethod, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
+        let fix = if d.file() == file_id.into() {
+            let name_ref = d.name_ref.to_node(&source_file);
+            fix_unresolved_method(db, file_id, name_ref, &d.method_name)
+        } else {
+            None
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::Error,
+            fixes: fix.into_iter().collect(),
+        })
+    })
+    .on::<hir::diagnostics::TraitNotInScope, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
+        let fixes = if d.file() == file_id.into() {
+            let name_ref = d.name_ref.to_node(&source_file);
+            fix_trait_not_in_scope(db, file_id, name_ref, &d.traits)
+        } else {
+            Vec::new()
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::Error,
+            fixes,
+        })
+    })
+    .on::<hir::diagnostics::MissingImplMembers, _>(|d| {
+        if !in_file(d) {
+            return;
+        }
+        let fix = if d.file() == file_id.into() {
+            let range = TextRange::offset_len(d.header_range.start(), 0.into());
+            assists::resolve_assist(
+                db,
+                FileRange { file_id, range },
+                "add_impl_missing_members",
+                "add missing impl members",
+            )
+            .map(|it| it.change)
+        } else {
+            None
+        };
+        res.borrow_mut().push(Diagnostic {
+            range: range(d),
+            message: d.message(),
+            code: Some(d.code()),
+            severity: Severity::Error,
+            fixes: fix.into_iter().collect(),
         })
     });
     if let Some(m) = source_binder::module_from_file_id(db, file_id) {
         m.diagnostics(db, &mut sink);
     };
     drop(sink);
-    res.into_inner()
+    lint_levels::apply(db, file_id, res.into_inner())
+}
+
+/// Adds the `missed_fields` to the struct literal with the `field_list`, with
+/// `todo!()` as their values.
+fn fill_missing_fields(field_list: &ast::NamedFieldList, missed_fields: &[Name]) -> TextEdit {
+    let mut edit = TextEditBuilder::default();
+    match field_list.fields().last() {
+        Some(last) => {
+            // The new fields go before the trailing comma, if any, so that
+            // it ends up after them.
+            let separator = if field_list.syntax().text().contains('\n') {
+                format!(",\n{}", leading_indent(last.syntax()).unwrap_or(""))
+            } else {
+                ", ".to_string()
+            };
+            let mut buf = String::new();
+            for name in missed_fields {
+                buf += &format!("{}{}: todo!()", separator, name);
+            }
+            edit.insert(last.syntax().range().end(), buf);
+        }
+        None => {
+            let fields = missed_fields.iter().map(|name| format!("{}: todo!()", name)).join(", ");
+            edit.replace(field_list.syntax().range(), format!("{{ {} }}", fields));
+        }
+    }
+    edit.finish()
+}
+
+/// Replaces an unresolved name with a similar one in scope, or imports an
+/// item of that name if there is exactly one in the crate.
+fn fix_unresolved_value(
+    db: &RootDatabase,
+    file_id: FileId,
+    path_expr: &ast::PathExpr,
+    name: &Name,
+) -> Option<SourceChange> {
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, path_expr.syntax(), None);
+    let candidates = analyzer
+        .all_names(db)
+        .into_iter()
+        .filter(|(_, res)| res.as_ref().take_values().is_some())
+        .map(|(name, _)| name.to_string());
+    if let Some(similar) = most_similar(&name.to_string(), candidates) {
+        let mut edit = TextEditBuilder::default();
+        edit.replace(path_expr.syntax().range(), similar.clone());
+        return Some(SourceChange::source_file_edit(
+            format!("change to `{}`", similar),
+            SourceFileEdit { file_id, edit: edit.finish() },
+        ));
+    }
+
+    let module = source_binder::module_from_file_id(db, file_id)?;
+    let mut paths = Vec::new();
+    let mut modules = vec![module.crate_root(db)];
+    while let Some(module) = modules.pop() {
+        modules.extend(module.children(db));
+        let res = match module.scope(db).get(name) {
+            Some(it) => it.clone(),
+            None => continue,
+        };
+        if res.import.is_none() && res.def.take_values().is_some() {
+            paths.extend(import_path(db, module, module, name));
+        }
+    }
+    if paths.len() != 1 {
+        return None;
+    }
+    let path = paths.pop()?;
+    Some(SourceChange::source_file_edit(
+        format!("import `{}`", path),
+        SourceFileEdit { file_id, edit: add_use(path_expr.syntax(), &path) },
+    ))
+}
+
+/// Replaces the first segment of an import which doesn't resolve with a
+/// similar name of the module it's looked up in.
+fn fix_unresolved_import(
+    db: &RootDatabase,
+    file_id: FileId,
+    use_tree: &ast::UseTree,
+) -> Option<SourceChange> {
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, use_tree.syntax(), None);
+    let resolve_module = |path: &ast::Path| match analyzer.resolve_path(db, path)? {
+        hir::PathResolution::Def(hir::ModuleDef::Module(it)) => Some(it),
+        _ => None,
+    };
+    // The path of an import in a list is relative to the path of the list.
+    let mut module = use_tree
+        .syntax()
+        .ancestors()
+        .skip(1)
+        .filter_map(ast::UseTree::cast)
+        .next()
+        .and_then(|it| it.path())
+        .map(|it| resolve_module(it));
+    let mut paths: Vec<&ast::Path> = successors(use_tree.path(), |it| it.qualifier()).collect();
+    paths.reverse();
+    let unresolved = paths.into_iter().find(|path| match resolve_module(path) {
+        Some(it) => {
+            module = Some(Some(it));
+            false
+        }
+        None => true,
+    })?;
+    let module = module??;
+    let name_ref = unresolved.segment()?.name_ref()?;
+    let candidates =
+        module.scope(db).entries().map(|(name, _)| name.to_string()).collect::<Vec<_>>();
+    let similar = most_similar(name_ref.text().as_str(), candidates.into_iter())?;
+    let mut edit = TextEditBuilder::default();
+    edit.replace(name_ref.syntax().range(), similar.clone());
+    Some(SourceChange::source_file_edit(
+        format!("change to `{}`", similar),
+        SourceFileEdit { file_id, edit: edit.finish() },
+    ))
+}
+
+/// Imports the traits which provide an unresolved method, one fix per trait.
+fn fix_trait_not_in_scope(
+    db: &RootDatabase,
+    file_id: FileId,
+    name_ref: &ast::NameRef,
+    traits: &[(hir::Trait, Name)],
+) -> Vec<SourceChange> {
+    let from = match source_binder::module_from_file_id(db, file_id) {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    traits
+        .iter()
+        .filter_map(|(tr, name)| import_path(db, from, tr.module(db), name))
+        .map(|path| {
+            SourceChange::source_file_edit(
+                format!("import `{}`", path),
+                SourceFileEdit { file_id, edit: add_use(name_ref.syntax(), &path) },
+            )
+        })
+        .collect()
+}
+
+/// The path to import the item `name` of `module` in the crate of `from`:
+/// starting with `crate` for the items of that crate, and with the name of
+/// the dependency for the items of its dependencies.
+fn import_path(db: &RootDatabase, from: Module, module: Module, name: &Name) -> Option<String> {
+    let from_crate = from.krate(db)?;
+    let krate = module.krate(db)?;
+    let mut segments = if krate == from_crate {
+        vec!["crate".to_string()]
+    } else {
+        let dep = from_crate.dependencies(db).into_iter().find(|it| it.krate == krate)?;
+        vec![dep.name.to_string()]
+    };
+    segments.extend(
+        module
+            .path_to_root(db)
+            .into_iter()
+            .rev()
+            .filter_map(|it| it.name(db))
+            .map(|it| it.to_string()),
+    );
+    segments.push(name.to_string());
+    Some(segments.join("::"))
+}
+
+/// Adds `use path;` after the last import of the module containing `node`, or
+/// at its start.
+fn add_use(node: &SyntaxNode, path: &str) -> TextEdit {
+    let container = node
+        .ancestors()
+        .filter_map(ast::Module::cast)
+        .find_map(|it| it.item_list())
+        .map(|it| it.syntax())
+        .unwrap_or_else(|| node.ancestors().last().unwrap());
+    let last_use = container.children().filter_map(ast::UseItem::cast).last();
+    let mut edit = TextEditBuilder::default();
+    match last_use {
+        Some(last_use) => edit.insert(last_use.syntax().range().end(), format!("\nuse {};", path)),
+        None => {
+            let offset = match ast::ItemList::cast(container) {
+                Some(item_list) => item_list
+                    .syntax()
+                    .first_child_or_token()
+                    .map_or(item_list.syntax().range().start(), |it| it.range().end()),
+                None => container.range().start(),
+            };
+            edit.insert(offset, format!("use {};\n", path))
+        }
+    }
+    edit.finish()
+}
+
+/// Prefixes the name of an unused binding with an underscore, keeping the
+/// field name of struct pattern shorthands.
+/// Uses the discarded value of `expr`: by binding it to `_`, and for a
+/// `Result`, by propagating its error or panicking on it.
+fn fix_unused_must_use(
+    file_id: FileId,
+    expr: &ast::Expr,
+    d: &hir::diagnostics::UnusedMustUse,
+) -> Vec<SourceChange> {
+    let range = expr.syntax().range();
+    let mut res = Vec::new();
+    if d.is_result && d.returns_result {
+        let mut edit = TextEditBuilder::default();
+        edit.insert(range.end(), "?".to_string());
+        res.push(SourceChange::source_file_edit_from(
+            "propagate the error with `?`",
+            file_id,
+            edit.finish(),
+        ));
+    }
+    if d.is_result {
+        let mut edit = TextEditBuilder::default();
+        edit.insert(range.end(), ".expect(\"\")".to_string());
+        let cursor = range.end() + TextUnit::of_str(".expect(\"");
+        res.push(
+            SourceChange::source_file_edit_from(
+                "panic on error with `expect`",
+                file_id,
+                edit.finish(),
+            )
+            .with_cursor(FilePosition { file_id, offset: cursor }),
+        );
+    }
+    let mut edit = TextEditBuilder::default();
+    edit.insert(range.start(), "let _ = ".to_string());
+    res.push(SourceChange::source_file_edit_from("discard with `let _ =`", file_id, edit.finish()));
+    res
+}
+
+fn fix_unused_variable(file_id: FileId, bind_pat: &ast::BindPat) -> Option<SourceChange> {
+    let name = bind_pat.name()?;
+    let is_shorthand =
+        bind_pat.syntax().parent().map_or(false, |it| it.kind() == SyntaxKind::FIELD_PAT_LIST);
+    let mut edit = TextEditBuilder::default();
+    if is_shorthand {
+        edit.replace(name.syntax().range(), format!("{}: _{}", name.text(), name.text()));
+    } else {
+        edit.insert(name.syntax().range().start(), "_".to_string());
+    }
+    Some(SourceChange::source_file_edit(
+        "prefix with underscore",
+        SourceFileEdit { file_id, edit: edit.finish() },
+    ))
+}
+
+fn check_unused_imports(
+    acc: &mut Vec<Diagnostic>,
+    db: &RootDatabase,
+    file_id: FileId,
+    source_file: &SourceFile,
+) {
+    for (range, name) in organize_imports::unused_imports(db, file_id) {
+        let tree = match find_covering_element(source_file.syntax(), range)
+            .ancestors()
+            .find_map(ast::UseTree::cast)
+        {
+            Some(it) => it,
+            None => continue,
+        };
+        let mut edit = TextEditBuilder::default();
+        edit.delete(use_tree_removal_range(tree));
+        acc.push(Diagnostic {
+            range,
+            message: format!("unused import: `{}`", name),
+            code: Some("unused_imports"),
+            severity: Severity::WeakWarning,
+            fixes: vec![SourceChange::source_file_edit(
+                "remove import",
+                SourceFileEdit { file_id, edit: edit.finish() },
+            )],
+        });
+    }
+}
+
+/// Reports the inline arguments of format strings which don't refer to
+/// anything, like `x` in `println!("{x}")` without a local or a constant `x`.
+fn check_inline_format_args(
+    acc: &mut Vec<Diagnostic>,
+    db: &RootDatabase,
+    file_id: FileId,
+    source_file: &SourceFile,
+) {
+    for format_string in format_strings(source_file.syntax()) {
+        let macro_call = format_string.macro_call.syntax();
+        // Items in the body are not resolved yet, they might define the name.
+        let has_local_items = match macro_call.ancestors().find_map(ast::FnDef::cast) {
+            Some(fn_def) => fn_def.body().map_or(true, |body| {
+                body.syntax().descendants().any(|it| ast::ModuleItem::cast(it).is_some())
+            }),
+            None => continue,
+        };
+        let analyzer = hir::SourceAnalyzer::new(db, file_id, macro_call, None);
+        if has_local_items || !analyzer.is_complete() {
+            continue;
+        }
+        for placeholder in format_string.inline_args() {
+            let offset = placeholder.range.start();
+            if analyzer.resolve_value_name(db, &placeholder.name, offset).is_none() {
+                acc.push(Diagnostic {
+                    range: placeholder.range,
+                    message: format!("there is no argument named `{}`", placeholder.name),
+                    code: Some("unknown_format_argument"),
+                    severity: Severity::Error,
+                    fixes: Vec::new(),
+                });
+            }
+        }
+    }
+}
+
+/// The range to delete to remove `tree` with its comma, or its whole `use`
+/// item if nothing else is left in it.
+fn use_tree_removal_range(mut tree: &ast::UseTree) -> TextRange {
+    loop {
+        let parent = match tree.syntax().parent() {
+            Some(it) => it,
+            None => return tree.syntax().range(),
+        };
+        if let Some(use_item) = ast::UseItem::cast(parent) {
+            let node = use_item.syntax();
+            return match node.next_sibling_or_token() {
+                Some(ws) if ws.kind() == SyntaxKind::WHITESPACE => {
+                    TextRange::from_to(node.range().start(), ws.range().end())
+                }
+                _ => node.range(),
+            };
+        }
+        let list = match ast::UseTreeList::cast(parent) {
+            Some(it) => it,
+            None => return tree.syntax().range(),
+        };
+        if list.use_trees().count() == 1 {
+            match list.syntax().parent().and_then(ast::UseTree::cast) {
+                Some(it) => {
+                    tree = it;
+                    continue;
+                }
+                None => return tree.syntax().range(),
+            }
+        }
+        let is_trivia = |it: &SyntaxElement| it.kind() == SyntaxKind::WHITESPACE;
+        let mut next = tree.syntax().siblings_with_tokens(Direction::Next).skip(1);
+        if let Some(comma) = next.find(|it| !is_trivia(it)).filter(|it| it.kind() == COMMA) {
+            let end =
+                next.next().filter(is_trivia).map_or(comma.range().end(), |ws| ws.range().end());
+            return TextRange::from_to(tree.syntax().range().start(), end);
+        }
+        let mut prev = tree.syntax().siblings_with_tokens(Direction::Prev).skip(1);
+        return match prev.find(|it| !is_trivia(it)).filter(|it| it.kind() == COMMA) {
+            Some(comma) => TextRange::from_to(comma.range().start(), tree.syntax().range().end()),
+            None => tree.syntax().range(),
+        };
+    }
+}
+
+/// Replaces the name of an unresolved method with the most similar name of a
+/// method of the receiver.
+fn fix_unresolved_method(
+    db: &RootDatabase,
+    file_id: FileId,
+    name_ref: &ast::NameRef,
+    method_name: &Name,
+) -> Option<SourceChange> {
+    let call = name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast)?;
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, call.syntax(), None);
+    let receiver_ty = analyzer.type_of(db, call.expr()?)?;
+    let mut candidates = Vec::new();
+    analyzer.iterate_method_candidates(db, receiver_ty, None, |_ty, func| {
+        candidates.push(func.name(db).to_string());
+        None::<()>
+    });
+    let similar = most_similar(&method_name.to_string(), candidates.into_iter())?;
+    let mut edit = TextEditBuilder::default();
+    edit.replace(name_ref.syntax().range(), similar.clone());
+    Some(SourceChange::source_file_edit(
+        format!("change to `{}`", similar),
+        SourceFileEdit { file_id, edit: edit.finish() },
+    ))
+}
+
+/// The candidate closest to `name`, if it's close enough to be a typo.
+fn most_similar(name: &str, candidates: impl Iterator<Item = String>) -> Option<String> {
+    let max_distance = std::cmp::max(1, name.chars().count() / 3);
+    candidates
+        .filter(|it| it != name)
+        .map(|it| (edit_distance(name, &it), it))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, it)| it)
+}
+
+/// The edit distance between `a` and `b`, counting swapped adjacent characters
+/// as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..=a.len() {
+        d[i][0] = i;
+    }
+    for j in 0..=b.len() {
+        d[0][j] = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
 }
 
 fn syntax_errors(acc: &mut Vec<Diagnostic>, source_file: &SourceFile) {
@@ -65,8 +688,9 @@ fn syntax_errors(acc: &mut Vec<Diagnostic>, source_file: &SourceFile) {
     acc.extend(source_file.errors().into_iter().map(|err| Diagnostic {
         range: location_to_range(err.location()),
         message: format!("Syntax Error: {}", err),
+        code: None,
         severity: Severity::Error,
-        fix: None,
+        fixes: Vec::new(),
     }));
 }
 
@@ -91,11 +715,12 @@ fn check_unnecessary_braces_in_use_statement(
         acc.push(Diagnostic {
             range,
             message: format!("Unnecessary braces in use statement"),
+            code: Some("unnecessary_braces"),
             severity: Severity::WeakWarning,
-            fix: Some(SourceChange::source_file_edit(
+            fixes: vec![SourceChange::source_file_edit(
                 "Remove unnecessary braces",
                 SourceFileEdit { file_id, edit },
-            )),
+            )],
         });
     }
 
@@ -139,11 +764,12 @@ fn check_struct_shorthand_initialization(
                 acc.push(Diagnostic {
                     range: named_field.syntax().range(),
                     message: format!("Shorthand struct initialization"),
+                    code: Some("struct_field_shorthand"),
                     severity: Severity::WeakWarning,
-                    fix: Some(SourceChange::source_file_edit(
+                    fixes: vec![SourceChange::source_file_edit(
                         "use struct shorthand initialization",
                         SourceFileEdit { file_id, edit },
-                    )),
+                    )],
                 });
             }
         }
@@ -156,7 +782,7 @@ mod tests {
     use test_utils::assert_eq_text;
     use insta::assert_debug_snapshot_matches;
 
-    use crate::mock_analysis::single_file;
+    use crate::mock_analysis::{single_file, MockAnalysis};
 
     use super::*;
 
@@ -179,7 +805,16 @@ mod tests {
         }
         let diagnostic =
             diagnostics.pop().unwrap_or_else(|| panic!("no diagnostics for:\n{}\n", before));
-        let mut fix = diagnostic.fix.unwrap();
+        let mut fix = diagnostic.fixes.into_iter().next().unwrap();
+        let edit = fix.source_file_edits.pop().unwrap().edit;
+        let actual = edit.apply(&before);
+        assert_eq_text!(after, &actual);
+    }
+
+    fn check_apply_diagnostic_fix(before: &str, after: &str) {
+        let (analysis, file_id) = single_file(before);
+        let diagnostic = analysis.diagnostics(file_id).unwrap().pop().unwrap();
+        let mut fix = diagnostic.fixes.into_iter().next().unwrap();
         let edit = fix.source_file_edits.pop().unwrap().edit;
         let actual = edit.apply(&before);
         assert_eq_text!(after, &actual);
@@ -192,8 +827,11 @@ mod tests {
         assert_debug_snapshot_matches!(diagnostics, @r####"[
     Diagnostic {
         message: "unresolved module",
+        code: Some(
+            "unresolved_module"
+        ),
         range: [0; 8),
-        fix: Some(
+        fixes: [
             SourceChange {
                 label: "create module",
                 source_file_edits: [],
@@ -202,17 +840,543 @@ mod tests {
                         source_root: SourceRootId(
                             0
                         ),
-                        path: "foo.rs"
+                        path: "foo.rs",
+                        text: ""
                     }
                 ],
                 cursor_position: None
             }
-        ),
+        ],
         severity: Error
     }
 ]"####);
     }
 
+    #[test]
+    fn test_unresolved_module_diagnostic_in_submodule() {
+        let analysis = MockAnalysis::with_files(
+            "
+            //- /lib.rs
+            mod foo;
+            //- /foo.rs
+            mod bar;
+            ",
+        );
+        let lib = analysis.id_of("/lib.rs");
+        let foo = analysis.id_of("/foo.rs");
+        let analysis = analysis.analysis();
+        assert!(analysis.diagnostics(lib).unwrap().is_empty());
+
+        let diagnostics = analysis.diagnostics(foo).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let fix = &diagnostics[0].fixes[0];
+        match &fix.file_system_edits[..] {
+            [FileSystemEdit::CreateFile { path, .. }] => assert_eq!(path, "foo/bar.rs"),
+            edits => panic!("unexpected edits: {:?}", edits),
+        }
+    }
+
+    #[test]
+    fn test_fill_struct_fields_empty() {
+        check_apply_diagnostic_fix(
+            "struct S { foo: i32, bar: () }\nfn main() { S {}; }",
+            "struct S { foo: i32, bar: () }\nfn main() { S { foo: todo!(), bar: todo!() }; }",
+        );
+    }
+
+    #[test]
+    fn test_fill_struct_fields_partial() {
+        check_apply_diagnostic_fix(
+            "struct S { foo: i32, bar: () }\nfn main() { S { foo: 92, }; }",
+            "struct S { foo: i32, bar: () }\nfn main() { S { foo: 92, bar: todo!(), }; }",
+        );
+        check_apply_diagnostic_fix(
+            r"
+struct S { foo: i32, bar: (), baz: u32 }
+fn main() {
+    S {
+        bar: ()
+    };
+}",
+            r"
+struct S { foo: i32, bar: (), baz: u32 }
+fn main() {
+    S {
+        bar: (),
+        foo: todo!(),
+        baz: todo!()
+    };
+}",
+        );
+    }
+
+    #[test]
+    fn test_fill_struct_fields_not_applicable() {
+        let (analysis, file_id) = single_file(
+            "struct S { foo: i32, bar: () }\nfn main(s: S) { S { foo: 92, bar: () }; S { ..s }; }",
+        );
+        assert!(analysis.diagnostics(file_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_add_unsafe_block() {
+        check_apply_diagnostic_fix(
+            "unsafe fn foo() -> u32 { 0 }\nfn main() { let _x = foo(); }",
+            "unsafe fn foo() -> u32 { 0 }\nfn main() { let _x = unsafe { foo() }; }",
+        );
+        check_apply_diagnostic_fix(
+            "fn main(ptr: *const u32) { *ptr; }",
+            "fn main(ptr: *const u32) { unsafe { *ptr }; }",
+        );
+    }
+
+    #[test]
+    fn test_prefix_unused_variable_with_underscore() {
+        check_apply_diagnostic_fix("fn main() { let mut x = 1; }", "fn main() { let mut _x = 1; }");
+        check_apply_diagnostic_fix(
+            "struct S { x: u32 }\nfn main(s: S) { let S { x } = s; }",
+            "struct S { x: u32 }\nfn main(s: S) { let S { x: _x } = s; }",
+        );
+    }
+
+    /// The diagnostic of `code`, with its message and its fixes applied.
+    fn check_fixes(text: &str, code: &str) -> (String, Vec<(String, String)>) {
+        let (analysis, file_id) = single_file(text);
+        let diagnostic = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .find(|it| it.code == Some(code))
+            .unwrap();
+        let fixes = diagnostic
+            .fixes
+            .into_iter()
+            .map(|mut fix| (fix.label, fix.source_file_edits.pop().unwrap().edit.apply(text)))
+            .collect();
+        (diagnostic.message, fixes)
+    }
+
+    #[test]
+    fn test_unused_must_use_function() {
+        let (message, fixes) = check_fixes(
+            r#"#[must_use = "it's new"] fn new() -> u32 { 0 } fn main() { new(); }"#,
+            "unused_must_use",
+        );
+        assert_eq!(message, "unused return value of `new` that must be used: it's new");
+        assert_eq!(
+            fixes,
+            vec![(
+                "discard with `let _ =`".to_string(),
+                r#"#[must_use = "it's new"] fn new() -> u32 { 0 } fn main() { let _ = new(); }"#
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unused_must_use_diagnostic() {
+        let (analysis, file_id) =
+            single_file("#[must_use] fn new() -> u32 { 0 } fn main() { new(); }");
+        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        assert_debug_snapshot_matches!(diagnostics, @r###"[
+    Diagnostic {
+        message: "unused return value of `new` that must be used"
+        code: Some(
+            "unused_must_use"
+        )
+        range: [46; 51)
+        fixes: [
+            SourceChange {
+                label: "discard with `let _ =`"
+                source_file_edits: [
+                    SourceFileEdit {
+                        file_id: FileId(
+                            1
+                        )
+                        edit: TextEdit {
+                            atoms: [
+                                AtomTextEdit {
+                                    delete: [46; 46)
+                                    insert: "let _ = "
+                                }
+                            ]
+                        }
+                    }
+                ]
+                file_system_edits: []
+                cursor_position: None
+            }
+        ]
+        severity: Warning
+    }
+]"###);
+    }
+
+    #[test]
+    fn test_missing_impl_members() {
+        let text = "
+trait Shape {
+    type Unit;
+    const SIDES: u32;
+    fn area(&self) -> u32;
+    fn perimeter(&self) -> u32;
+    fn name(&self) -> &str { \"shape\" }
+}
+struct Square;
+impl Shape for Square {
+    type Unit = u32;
+    fn area(&self) -> u32 { 0 }
+}
+";
+        let (analysis, file_id) = single_file(text);
+        let diagnostic = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .find(|it| it.code == Some("missing_impl_members"))
+            .unwrap();
+        assert_eq!(
+            diagnostic.message,
+            "not all trait items implemented, missing: `SIDES`, `perimeter`"
+        );
+        assert_eq!(&text[diagnostic.range], "impl Shape for Square");
+
+        let (_, fixes) = check_fixes(text, "missing_impl_members");
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].0, "add missing impl members");
+        assert!(fixes[0].1.contains(
+            "    fn area(&self) -> u32 { 0 }\n    fn perimeter(&self) -> u32 { unimplemented!() }\n}"
+        ));
+    }
+
+    #[test]
+    fn test_no_missing_impl_members() {
+        let text = "
+trait Shape {
+    type Unit = u32;
+    fn area(&self) -> u32;
+    fn name(&self) -> &str { \"shape\" }
+}
+struct Square;
+impl Shape for Square {
+    fn area(&self) -> u32 { 0 }
+}
+impl Square {}
+";
+        let (analysis, file_id) = single_file(text);
+        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        assert!(diagnostics.iter().all(|it| it.code != Some("missing_impl_members")));
+    }
+
+    #[test]
+    fn test_not_object_safe() {
+        let text = "
+trait Shape {
+    const SIDES: u32;
+    fn new() -> Self;
+    fn scale<T>(&self, by: T);
+    fn eq(&self, other: &Self) -> bool;
+    fn area(&self) -> u32;
+    fn into_parts(self) -> (u32, u32) where Self: Sized;
+}
+trait Named { fn name(&self) -> &str; }
+struct Shapes { first: Box<dyn Named>, rest: Vec<Box<dyn Shape>> }
+";
+        let (analysis, file_id) = single_file(text);
+        let diagnostics: Vec<_> = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.code == Some("not_object_safe"))
+            .collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "the trait `Shape` cannot be made into an object\n\
+             - it contains the associated const `SIDES`\n\
+             - associated function `new` has no `self` parameter\n\
+             - method `scale` has generic type parameters\n\
+             - method `eq` references the `Self` type in its parameters or return type"
+        );
+        assert_eq!(&text[diagnostics[0].range], "dyn Shape");
+    }
+
+    #[test]
+    fn test_unused_result() {
+        let text = "
+#[must_use]
+enum Result<T, E> { Ok(T), Err(E) }
+fn parse() -> Result<u32, ()> { Result::Ok(1) }
+fn main() -> Result<(), ()> {
+    parse();
+    Result::Ok(())
+}
+";
+        let (message, fixes) = check_fixes(text, "unused_must_use");
+        assert_eq!(message, "unused `Result` that must be used");
+        let labels: Vec<_> = fixes.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "propagate the error with `?`",
+                "panic on error with `expect`",
+                "discard with `let _ =`"
+            ]
+        );
+        assert!(fixes[0].1.contains("    parse()?;\n"));
+        assert!(fixes[1].1.contains("    parse().expect(\"\");\n"));
+        assert!(fixes[2].1.contains("    let _ = parse();\n"));
+    }
+
+    #[test]
+    fn test_used_must_use_values() {
+        let (analysis, file_id) = single_file(
+            "
+#[must_use]
+fn new() -> u32 { 0 }
+fn foo() {}
+fn main() -> u32 {
+    let _ = new();
+    foo();
+    new()
+}
+",
+        );
+        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        assert!(diagnostics.iter().all(|it| it.code != Some("unused_must_use")));
+    }
+
+    #[test]
+    fn test_remove_unused_import() {
+        check_apply_diagnostic_fix(
+            "mod foo { pub struct A; pub struct B; }\nuse foo::A;\nuse foo::B;\npub fn f(_: A) {}",
+            "mod foo { pub struct A; pub struct B; }\nuse foo::A;\npub fn f(_: A) {}",
+        );
+        check_apply_diagnostic_fix(
+            "mod foo { pub struct A; pub struct B; }\nuse foo::{A, B};\npub fn f(_: B) {}",
+            "mod foo { pub struct A; pub struct B; }\nuse foo::{B};\npub fn f(_: B) {}",
+        );
+        check_apply_diagnostic_fix(
+            "mod foo { pub struct A; pub struct B; }\nuse foo::{A, B};\npub fn f(_: A) {}",
+            "mod foo { pub struct A; pub struct B; }\nuse foo::{A};\npub fn f(_: A) {}",
+        );
+    }
+
+    #[test]
+    fn test_unused_import_severity() {
+        let (analysis, file_id) = single_file(
+            "
+mod foo { pub struct A; pub trait T {} }
+use foo::{A, T};
+pub use foo::A as B;
+#[allow(unused_imports)]
+use foo::A as C;
+",
+        );
+        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        assert_debug_snapshot_matches!(diagnostics, @r###"[
+    Diagnostic {
+        message: "unused import: `A`",
+        code: Some(
+            "unused_imports"
+        ),
+        range: [52; 53),
+        fixes: [
+            SourceChange {
+                label: "remove import",
+                source_file_edits: [
+                    SourceFileEdit {
+                        file_id: FileId(
+                            1
+                        ),
+                        edit: TextEdit {
+                            atoms: [
+                                AtomTextEdit {
+                                    delete: [52; 55),
+                                    insert: ""
+                                }
+                            ]
+                        }
+                    }
+                ],
+                file_system_edits: [],
+                cursor_position: None
+            }
+        ],
+        severity: WeakWarning
+    }
+]"###);
+    }
+
+    fn check_apply_diagnostic_fix_with_std(before: &str, after: &str) {
+        let mut analysis = MockAnalysis::new();
+        let file_id = analysis.add_file("/main.rs", before);
+        analysis.add_file(
+            "/std/lib.rs",
+            "#[prelude_import] use prelude::*; mod prelude { pub trait Clone {} }",
+        );
+        let diagnostic = analysis.analysis().diagnostics(file_id).unwrap().pop().unwrap();
+        let mut fix = diagnostic.fixes.into_iter().next().unwrap();
+        let edit = fix.source_file_edits.pop().unwrap().edit;
+        let actual = edit.apply(&before);
+        assert_eq_text!(after, &actual);
+    }
+
+    #[test]
+    fn test_unresolved_value_typo() {
+        check_apply_diagnostic_fix_with_std(
+            "fn main() { let count = 1; count + coutn; }",
+            "fn main() { let count = 1; count + count; }",
+        );
+    }
+
+    #[test]
+    fn test_unresolved_value_import() {
+        check_apply_diagnostic_fix_with_std(
+            r"
+mod foo { pub mod bar { pub fn baz() {} } }
+fn main() { baz; }",
+            r"use crate::foo::bar::baz;
+
+mod foo { pub mod bar { pub fn baz() {} } }
+fn main() { baz; }",
+        );
+        check_apply_diagnostic_fix_with_std(
+            r"
+use foo::bar;
+mod foo { pub mod bar { pub fn baz() {} } }
+fn main() { baz; }",
+            r"
+use foo::bar;
+use crate::foo::bar::baz;
+mod foo { pub mod bar { pub fn baz() {} } }
+fn main() { baz; }",
+        );
+    }
+
+    #[test]
+    fn test_unresolved_import_typo() {
+        check_apply_diagnostic_fix_with_std(
+            r"
+mod foo { pub mod bar { pub struct Baz; } }
+use foo::bra::Baz;",
+            r"
+mod foo { pub mod bar { pub struct Baz; } }
+use foo::bar::Baz;",
+        );
+        check_apply_diagnostic_fix_with_std(
+            r"
+mod foo { pub struct Bar; pub struct Baz; }
+use foo::{Bar, Bza};",
+            r"
+mod foo { pub struct Bar; pub struct Baz; }
+use foo::{Bar, Baz};",
+        );
+    }
+
+    #[test]
+    fn test_unresolved_method_typo() {
+        check_apply_diagnostic_fix_with_std(
+            r"
+struct S;
+impl S { fn frobnicate(&self) {} }
+fn main(s: S) { s.frobincate(); }",
+            r"
+struct S;
+impl S { fn frobnicate(&self) {} }
+fn main(s: S) { s.frobnicate(); }",
+        );
+    }
+
+    #[test]
+    fn test_trait_not_in_scope() {
+        check_apply_diagnostic_fix_with_std(
+            r"
+mod ext {
+    pub trait Frob { fn frob(&self); }
+    impl Frob for super::S { fn frob(&self) {} }
+}
+struct S;
+fn main(s: S) { s.frob(); }",
+            r"use crate::ext::Frob;
+
+mod ext {
+    pub trait Frob { fn frob(&self); }
+    impl Frob for super::S { fn frob(&self) {} }
+}
+struct S;
+fn main(s: S) { s.frob(); }",
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_in_macro_expansion() {
+        let text = "
+macro_rules! make { () => { fn generated(_x: Nope) {} } }
+make!();
+";
+        let mut analysis = MockAnalysis::new();
+        let file_id = analysis.add_file("/main.rs", text);
+        analysis.add_file(
+            "/std/lib.rs",
+            "#[prelude_import] use prelude::*; mod prelude { pub trait Clone {} }",
+        );
+        let diagnostics = analysis.analysis().diagnostics(file_id).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "cannot find type `Nope` in this scope");
+        assert_eq!(&text[diagnostics[0].range], "make!();");
+    }
+
+    #[test]
+    fn test_unresolved_without_prelude() {
+        let (analysis, file_id) = single_file("struct S;\nfn main(s: S) { nope; s.nope(); }");
+        assert!(analysis.diagnostics(file_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_format_argument() {
+        let mut analysis = MockAnalysis::new();
+        let file_id = analysis.add_file(
+            "/main.rs",
+            r#"
+const WIDTH: usize = 4;
+fn main() {
+    let x = 1;
+    println!("{x:>WIDTH$} {y} {z:.prec$} {{w}}", z = 2);
+}"#,
+        );
+        analysis.add_file("/std/lib.rs", "#[prelude_import] use prelude::*; mod prelude {}");
+        let diagnostics: Vec<_> = analysis
+            .analysis()
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .map(|it| (it.message, it.range))
+            .collect();
+        assert_debug_snapshot_matches!(diagnostics, @r###"[
+    (
+        "there is no argument named `y`",
+        [79; 80)
+    ),
+    (
+        "there is no argument named `prec`",
+        [86; 90)
+    )
+]"###);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("coutn", "count"), 1);
+        assert_eq!(
+            most_similar("fo", vec!["foo".to_string(), "bar".to_string()].into_iter()),
+            Some("foo".to_string())
+        );
+        assert_eq!(most_similar("fo", vec!["bar".to_string()].into_iter()), None);
+    }
+
     #[test]
     fn test_check_unnecessary_braces_in_use_statement() {
         check_not_applicable(
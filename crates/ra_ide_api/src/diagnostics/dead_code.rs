@@ -0,0 +1,241 @@
+//! Finds the private functions, structs and constants which are not referenced
+//! anywhere in their crate.
+//!
+//! The candidates are the symbols of the file, as collected for the symbol
+//! index, and their references are the name references of the crate's files
+//! which resolve to them, like with goto definition. Names which can't be
+//! resolved exactly, and names in macro calls, including the inline arguments
+//! of format strings, are assumed to refer to the candidates.
+
+use hir::source_binder;
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    AstNode, SmolStr, SyntaxKind, SyntaxNode,
+    SyntaxKind::{CONST_DEF, FN_DEF, IDENT, STRUCT_DEF},
+    ast::{self, AttrsOwner, NameOwner, VisibilityOwner},
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    Diagnostic, FileId,
+    db::RootDatabase,
+    diagnostics::Severity,
+    format_string::format_strings,
+    goto_definition::{reference_definition, ReferenceResult},
+    symbol_index::{FileSymbol, source_file_to_file_symbols},
+};
+
+/// Attributes of items which are used even if they are not referenced.
+const USED_ATTRS: &[&str] =
+    &["test", "bench", "no_mangle", "export_name", "used", "lang", "panic_handler", "cfg"];
+
+pub(super) fn check(acc: &mut Vec<Diagnostic>, db: &RootDatabase, file_id: FileId) {
+    let module = match source_binder::module_from_file_id(db, file_id) {
+        Some(it) => it,
+        None => return,
+    };
+    let crate_root = module.crate_root(db);
+    let is_crate_root = crate_root.definition_source(db).0.original_file(db) == file_id;
+    let source_file = db.parse(file_id);
+    let candidates: Vec<FileSymbol> = source_file_to_file_symbols(&source_file, file_id)
+        .into_iter()
+        .filter(|it| is_candidate(it.ptr.to_node(&source_file), is_crate_root))
+        .collect();
+    if candidates.is_empty() {
+        return;
+    }
+    let mut by_name: FxHashMap<&str, Vec<usize>> = FxHashMap::default();
+    for (idx, symbol) in candidates.iter().enumerate() {
+        by_name.entry(symbol.name.as_str()).or_default().push(idx);
+    }
+
+    let mut modules = vec![crate_root];
+    let mut crate_files = Vec::new();
+    while let Some(module) = modules.pop() {
+        if let (file, hir::ModuleSource::SourceFile(_)) = module.definition_source(db) {
+            crate_files.push(file.original_file(db));
+        }
+        modules.extend(module.children(db));
+    }
+
+    let mut used: FxHashSet<usize> = FxHashSet::default();
+    for file in crate_files {
+        let source_file = db.parse(file);
+        for name_ref in source_file.syntax().descendants().filter_map(ast::NameRef::cast) {
+            let indices = match by_name.get(name_ref.text().as_str()) {
+                Some(it) => it,
+                None => continue,
+            };
+            match reference_definition(db, file, name_ref) {
+                ReferenceResult::Exact(nav) => used.extend(indices.iter().filter(|&&idx| {
+                    let symbol = &candidates[idx];
+                    nav.file_id() == symbol.file_id && nav.full_range() == symbol.ptr.range()
+                })),
+                ReferenceResult::Approximate(_) => used.extend(indices.iter()),
+            }
+        }
+        let inline_format_args = format_strings(source_file.syntax())
+            .flat_map(|it| it.inline_args().map(|it| it.name.clone()).collect::<Vec<_>>());
+        for name in names_in_token_trees(source_file.syntax()).chain(inline_format_args) {
+            if let Some(indices) = by_name.get(name.as_str()) {
+                used.extend(indices.iter());
+            }
+        }
+    }
+
+    for (idx, symbol) in candidates.iter().enumerate() {
+        if used.contains(&idx) {
+            continue;
+        }
+        acc.push(Diagnostic {
+            message: format!("{} is never used: `{}`", item_kind(symbol.ptr.kind()), symbol.name),
+            code: Some("dead_code"),
+            range: symbol.name_range.unwrap_or_else(|| symbol.ptr.range()),
+            severity: Severity::WeakWarning,
+            fixes: Vec::new(),
+        });
+    }
+}
+
+/// Whether `node` is a private item which needs to be referenced to be used.
+fn is_candidate(node: &SyntaxNode, is_crate_root: bool) -> bool {
+    let (name, attrs): (_, Vec<&ast::Attr>) = match node.kind() {
+        FN_DEF => {
+            let fn_def = ast::FnDef::cast(node).unwrap();
+            if fn_def.visibility().is_some() || is_trait_item(node) {
+                return false;
+            }
+            let name = fn_def.name().map(|it| it.text().clone());
+            if is_crate_root && name.as_ref().map_or(false, |it| it == "main") {
+                return false;
+            }
+            (name, fn_def.attrs().collect())
+        }
+        STRUCT_DEF => {
+            let struct_def = ast::StructDef::cast(node).unwrap();
+            if struct_def.visibility().is_some() {
+                return false;
+            }
+            (struct_def.name().map(|it| it.text().clone()), struct_def.attrs().collect())
+        }
+        CONST_DEF => {
+            let const_def = ast::ConstDef::cast(node).unwrap();
+            if const_def.visibility().is_some() || is_trait_item(node) {
+                return false;
+            }
+            (const_def.name().map(|it| it.text().clone()), const_def.attrs().collect())
+        }
+        _ => return false,
+    };
+    let is_used_by_attr = attrs
+        .iter()
+        .filter_map(|it| it.as_named().or_else(|| it.as_call().map(|(name, _)| name)))
+        .any(|it| USED_ATTRS.contains(&it.as_str()));
+    match name {
+        Some(name) => !name.starts_with('_') && !is_used_by_attr,
+        None => false,
+    }
+}
+
+/// Whether `node` is an item of a trait or of a trait impl, which is used
+/// through the trait.
+fn is_trait_item(node: &SyntaxNode) -> bool {
+    let container = match node.parent().and_then(|it| it.parent()) {
+        Some(it) => it,
+        None => return false,
+    };
+    if ast::TraitDef::cast(container).is_some() {
+        return true;
+    }
+    match ast::ImplBlock::cast(container) {
+        Some(impl_block) => impl_block.target_trait().is_some(),
+        None => false,
+    }
+}
+
+fn names_in_token_trees(node: &SyntaxNode) -> impl Iterator<Item = SmolStr> + '_ {
+    node.descendants()
+        .filter_map(ast::TokenTree::cast)
+        .filter(|it| it.syntax().parent().and_then(ast::TokenTree::cast).is_none())
+        .flat_map(|it| it.syntax().descendants_with_tokens())
+        .filter_map(|it| it.as_token())
+        .filter(|it| it.kind() == IDENT)
+        .map(|it| it.text().clone())
+}
+
+fn item_kind(kind: SyntaxKind) -> &'static str {
+    match kind {
+        FN_DEF => "function",
+        STRUCT_DEF => "struct",
+        _ => "constant",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::{single_file, MockAnalysis};
+
+    fn dead_code(analysis: &crate::Analysis, file_id: crate::FileId) -> Vec<String> {
+        analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.code == Some("dead_code"))
+            .map(|it| it.message)
+            .collect()
+    }
+
+    #[test]
+    fn reports_unreferenced_private_items() {
+        let (analysis, file_id) = single_file(
+            r#"
+fn main() { used(); println!("{IN_FORMAT}"); }
+fn used() -> S { S }
+const IN_FORMAT: u32 = 0;
+fn unused() {}
+struct S;
+struct Unused;
+const UNUSED: u32 = 0;
+const USED: u32 = 0;
+pub fn exported() {}
+#[test]
+fn test() { let _ = USED; }
+#[allow(dead_code)]
+fn allowed() {}
+fn _ignored() {}
+trait T { fn method(&self); }
+impl T for S { fn method(&self) { helper!(in_macro); } }
+fn in_macro() {}
+impl S { fn unused_method(&self) {} }
+"#,
+        );
+        assert_eq!(
+            dead_code(&analysis, file_id),
+            vec![
+                "function is never used: `unused`",
+                "struct is never used: `Unused`",
+                "constant is never used: `UNUSED`",
+                "function is never used: `unused_method`",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_references_in_other_files_of_the_crate() {
+        let analysis = MockAnalysis::with_files(
+            r"
+//- /lib.rs
+mod foo;
+fn used_in_child() {}
+fn unused() {}
+//- /foo.rs
+fn f() { super::used_in_child() }
+",
+        );
+        let lib = analysis.id_of("/lib.rs");
+        let foo = analysis.id_of("/foo.rs");
+        let analysis = analysis.analysis();
+        assert_eq!(dead_code(&analysis, lib), vec!["function is never used: `unused`"]);
+        assert_eq!(dead_code(&analysis, foo), vec!["function is never used: `f`"]);
+    }
+}
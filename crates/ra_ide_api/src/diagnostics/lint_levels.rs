@@ -0,0 +1,177 @@
+//! Lint levels of diagnostics, as set by `#[allow]`, `#[warn]`, `#[deny]` and
+//! `#[forbid]` attributes.
+//!
+//! Like in rustc, the attribute closest to the diagnostic wins, looking at the
+//! enclosing items, then at the declarations of the enclosing modules up to
+//! the crate root.
+
+use hir::source_binder;
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    AstNode, SyntaxNode, TextRange,
+    algo::find_covering_element,
+    ast,
+};
+
+use crate::{Diagnostic, FileId, db::RootDatabase, diagnostics::Severity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Drops the allowed diagnostics and adjusts the severity of the others.
+pub(super) fn apply(
+    db: &RootDatabase,
+    file_id: FileId,
+    diagnostics: Vec<Diagnostic>,
+) -> Vec<Diagnostic> {
+    diagnostics
+        .into_iter()
+        .filter_map(|mut d| {
+            let code = match d.code {
+                Some(it) => it,
+                None => return Some(d),
+            };
+            match lint_level(db, file_id, d.range, code) {
+                Some(LintLevel::Allow) => return None,
+                Some(LintLevel::Warn) => d.severity = Severity::Warning,
+                Some(LintLevel::Deny) => d.severity = Severity::Error,
+                None => (),
+            }
+            Some(d)
+        })
+        .collect()
+}
+
+fn lint_level(
+    db: &RootDatabase,
+    file_id: FileId,
+    range: TextRange,
+    code: &str,
+) -> Option<LintLevel> {
+    let source_file = db.parse(file_id);
+    let element = find_covering_element(source_file.syntax(), range);
+    if let Some(level) = level_in_ancestors(element.ancestors(), code) {
+        return Some(level);
+    }
+
+    // The file might be a module declared with `mod foo;` elsewhere, whose
+    // attributes and enclosing items apply as well.
+    let mut module = source_binder::module_from_file_id(db, file_id)?;
+    loop {
+        if let Some((_, declaration)) = module.declaration_source(db) {
+            if let Some(level) = level_in_ancestors(declaration.syntax().ancestors(), code) {
+                return Some(level);
+            }
+        }
+        module = module.parent(db)?;
+    }
+}
+
+fn level_in_ancestors<'a>(
+    mut ancestors: impl Iterator<Item = &'a SyntaxNode>,
+    code: &str,
+) -> Option<LintLevel> {
+    // Both inner and outer attributes are children of the node they apply to,
+    // inner ones being in the `ItemList` for inline modules.
+    ancestors.find_map(|node| {
+        node.children().filter_map(ast::Attr::cast).filter_map(|it| level_of_attr(it, code)).last()
+    })
+}
+
+fn level_of_attr(attr: &ast::Attr, code: &str) -> Option<LintLevel> {
+    let (name, paths) = attr.as_path_list()?;
+    let level = match name.as_str() {
+        "allow" => LintLevel::Allow,
+        "warn" => LintLevel::Warn,
+        "deny" | "forbid" => LintLevel::Deny,
+        _ => return None,
+    };
+    if paths.iter().any(|it| it == code) {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::{single_file, MockAnalysis};
+
+    use super::*;
+
+    fn check_codes(analysis: &crate::Analysis, file_id: FileId, expected: &[(&str, Severity)]) {
+        let actual: Vec<_> = analysis
+            .diagnostics(file_id)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.code.unwrap(), d.severity))
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn lint_attributes_on_items() {
+        let (analysis, file_id) = single_file(
+            r"
+#![allow(dead_code)]
+struct S { foo: u32 }
+#[allow(missing_structure_fields)]
+fn allowed() { S {}; }
+#[warn(missing_structure_fields)]
+fn warned() { S {}; }
+#[deny(unnecessary_braces)]
+mod m {
+    use a::{b};
+    #[allow(unnecessary_braces)]
+    mod n {
+        use a::{b};
+    }
+}
+fn inner() {
+    #![allow(missing_structure_fields)]
+    S {};
+}
+",
+        );
+        check_codes(
+            &analysis,
+            file_id,
+            &[
+                ("unnecessary_braces", Severity::Error),
+                ("missing_structure_fields", Severity::Warning),
+            ],
+        );
+    }
+
+    #[test]
+    fn lint_attributes_on_crate_and_module_declarations() {
+        let analysis = MockAnalysis::with_files(
+            r"
+//- /lib.rs
+#![allow(unresolved_module, dead_code)]
+#[warn(missing_structure_fields)]
+mod foo;
+mod nope;
+//- /foo.rs
+mod bar;
+mod nope;
+struct S { foo: u32 }
+fn f() { S {}; }
+//- /foo/bar.rs
+#![deny(missing_structure_fields)]
+fn f() { super::S {}; }
+",
+        );
+        let lib = analysis.id_of("/lib.rs");
+        let foo = analysis.id_of("/foo.rs");
+        let bar = analysis.id_of("/foo/bar.rs");
+        let analysis = analysis.analysis();
+        check_codes(&analysis, lib, &[]);
+        check_codes(&analysis, foo, &[("missing_structure_fields", Severity::Warning)]);
+        check_codes(&analysis, bar, &[("missing_structure_fields", Severity::Error)]);
+    }
+}
@@ -5,7 +5,7 @@ mod function_signature;
 mod navigation_target;
 mod structure;
 
-use crate::db::RootDatabase;
+use crate::{db::RootDatabase, doc_code::format_docs, doc_links::resolve_doc_links};
 use ra_syntax::{ast::{self, AstNode, TypeParamsOwner}, SyntaxKind::{ATTR, COMMENT}};
 
 pub use navigation_target::NavigationTarget;
@@ -71,12 +71,42 @@ where
     }
 }
 
+/// The links to items in the docs are rewritten as reference links, and their
+/// labels and targets are added to `links`.
 // FIXME: this should not really use navigation target. Rather, approximately
 // resolved symbol should return a `DefId`.
-pub(crate) fn doc_text_for(db: &RootDatabase, nav: NavigationTarget) -> Option<String> {
-    match (nav.description(db), nav.docs(db)) {
+pub(crate) fn doc_text_for(
+    db: &RootDatabase,
+    nav: NavigationTarget,
+    links: &mut Vec<(String, NavigationTarget)>,
+) -> Option<String> {
+    let docs = nav.docs(db).map(|docs| match nav.node(db) {
+        Some(node) => {
+            let (docs, doc_links) = resolve_doc_links(db, nav.file_id(), &node, &docs);
+            links.extend(doc_links);
+            format_docs(&docs)
+        }
+        None => format_docs(&docs),
+    });
+    let docs = match (nav.deprecation(db).map(|it| deprecation_text(&it)), docs) {
+        (Some(deprecation), Some(docs)) => Some(format!("{}\n\n{}", deprecation, docs)),
+        (deprecation, docs) => deprecation.or(docs),
+    };
+    match (nav.description(db), docs) {
         (Some(desc), docs) => Some(rust_code_markup_with_doc(desc, docs)),
         (None, Some(docs)) => Some(docs),
         _ => None,
     }
 }
+
+/// The deprecation of an item, like `**Deprecated** since 1.2.0: use bar`.
+fn deprecation_text(deprecation: &hir::Deprecation) -> String {
+    let mut res = "**Deprecated**".to_string();
+    if let Some(since) = deprecation.since() {
+        res.push_str(&format!(" since {}", since));
+    }
+    if let Some(note) = deprecation.note() {
+        res.push_str(&format!(": {}", note));
+    }
+    res
+}
@@ -13,6 +13,8 @@ pub struct FunctionSignature {
     pub visibility: Option<String>,
     /// Name of the function
     pub name: Option<String>,
+    /// Whether the function is `async`
+    pub is_async: bool,
     /// Documentation for the function
     pub doc: Option<Documentation>,
     /// Generic parameters
@@ -55,6 +57,7 @@ impl From<&'_ ast::FnDef> for FunctionSignature {
         FunctionSignature {
             visibility: node.visibility().map(|n| n.syntax().text().to_string()),
             name: node.name().map(|n| n.text().to_string()),
+            is_async: node.is_async(),
             ret_type: node
                 .ret_type()
                 .and_then(|r| r.type_ref())
@@ -74,6 +77,10 @@ impl Display for FunctionSignature {
             write!(f, "{} ", t)?;
         }
 
+        if self.is_async {
+            write!(f, "async ")?;
+        }
+
         if let Some(name) = &self.name {
             write!(f, "fn {}", name)?;
         }
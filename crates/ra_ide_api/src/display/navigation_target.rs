@@ -213,6 +213,17 @@ impl NavigationTarget {
         }
     }
 
+    pub(crate) fn from_trait_item(
+        db: &RootDatabase,
+        trait_item: hir::TraitItem,
+    ) -> NavigationTarget {
+        match trait_item {
+            hir::TraitItem::Function(f) => NavigationTarget::from_function(db, f),
+            hir::TraitItem::Const(c) => NavigationTarget::from_def(db, c.into()),
+            hir::TraitItem::TypeAlias(a) => NavigationTarget::from_def(db, a.into()),
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn assert_match(&self, expected: &str) {
         let actual = self.debug_render();
@@ -292,6 +303,26 @@ impl NavigationTarget {
             .accept(&node)?
     }
 
+    pub(crate) fn deprecation(&self, db: &RootDatabase) -> Option<hir::Deprecation> {
+        let node = self.node(db)?;
+        fn deprecation<N: ast::AttrsOwner>(node: &N) -> Option<hir::Deprecation> {
+            hir::Deprecation::from_attrs(node)
+        }
+
+        visitor()
+            .visit(deprecation::<ast::FnDef>)
+            .visit(deprecation::<ast::StructDef>)
+            .visit(deprecation::<ast::EnumDef>)
+            .visit(deprecation::<ast::TraitDef>)
+            .visit(deprecation::<ast::Module>)
+            .visit(deprecation::<ast::TypeAliasDef>)
+            .visit(deprecation::<ast::ConstDef>)
+            .visit(deprecation::<ast::StaticDef>)
+            .visit(deprecation::<ast::NamedFieldDef>)
+            .visit(deprecation::<ast::EnumVariant>)
+            .accept(&node)?
+    }
+
     /// Get a description of this node.
     ///
     /// e.g. `struct Name`, `enum Name`, `fn Name`
@@ -0,0 +1,119 @@
+//! The Rust code blocks of doc comments, which rustdoc compiles as tests.
+//!
+//! The code of a block is injected in the file of its doc comment: it's
+//! analyzed as a file of its own, whose ranges are mapped back to the lines of
+//! the comment.
+
+use ra_syntax::{
+    SyntaxNode, TextRange, TextUnit,
+    ast::{self, AstToken},
+};
+
+/// The attributes of the code blocks which rustdoc knows to be Rust, like the
+/// blocks without a language, marked `rust` or `should_panic`.
+const RUSTDOC_ATTRS: &[&str] = &[
+    "",
+    "rust",
+    "should_panic",
+    "no_run",
+    "ignore",
+    "compile_fail",
+    "allow_fail",
+    "test_harness",
+    "edition2015",
+    "edition2018",
+];
+
+/// Whether the code block opened by the fence line `fence` is Rust code.
+pub(crate) fn is_rust_fence(fence: &str) -> bool {
+    let info = fence.trim_start().trim_start_matches('`');
+    info.split(',').all(|attr| RUSTDOC_ATTRS.contains(&attr.trim()))
+}
+
+/// Rewrites `docs` for rendering as markdown, like rustdoc does: the Rust code
+/// blocks are marked `rust`, and their hidden lines, starting with `# `, are
+/// removed.
+pub(crate) fn format_docs(docs: &str) -> String {
+    let mut res = Vec::new();
+    let mut in_code_block = false;
+    let mut is_rust = false;
+    for line in docs.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            is_rust = in_code_block && is_rust_fence(line);
+            if is_rust {
+                res.push("```rust");
+                continue;
+            }
+        } else if is_rust {
+            let trimmed = line.trim_start();
+            if trimmed == "#" || trimmed.starts_with("# ") {
+                continue;
+            }
+        }
+        res.push(line);
+    }
+    res.join("\n")
+}
+
+/// The code of a Rust code block of a doc comment.
+#[derive(Debug)]
+pub(crate) struct DocCode {
+    /// The code, in the body of a function like rustdoc does.
+    pub(crate) text: String,
+    /// The ranges of the lines of the code in `text`, with their offsets in
+    /// the file.
+    lines: Vec<(TextRange, TextUnit)>,
+}
+
+impl DocCode {
+    /// The range in the file of `range` of the code, if it's in a line.
+    pub(crate) fn map_range_up(&self, range: TextRange) -> Option<TextRange> {
+        let &(line, offset) = self.lines.iter().find(|(line, _)| range.is_subrange(line))?;
+        Some(range - line.start() + offset)
+    }
+}
+
+/// The Rust code blocks of the line doc comments of `node`.
+pub(crate) fn doc_code_blocks(node: &SyntaxNode) -> Vec<DocCode> {
+    let mut res = Vec::new();
+    // `Some` in a code block, with its code if it's Rust.
+    let mut block: Option<Option<DocCode>> = None;
+    let comments = node
+        .children_with_tokens()
+        .filter_map(|it| it.as_token())
+        .filter_map(ast::Comment::cast)
+        .filter(|it| it.kind().doc.is_some() && it.kind().shape.is_line());
+    for comment in comments {
+        let text = comment.text().as_str();
+        let mut start = comment.prefix().len();
+        if text[start..].starts_with(' ') {
+            start += 1;
+        }
+        let line = &text[start..];
+        if line.trim_start().starts_with("```") {
+            block = match block.take() {
+                Some(code) => {
+                    res.extend(code.map(|mut it| {
+                        it.text.push('}');
+                        it
+                    }));
+                    None
+                }
+                None if is_rust_fence(line) => {
+                    Some(Some(DocCode { text: "fn main() {\n".to_string(), lines: Vec::new() }))
+                }
+                None => Some(None),
+            };
+            continue;
+        }
+        if let Some(Some(code)) = block.as_mut() {
+            let line_start = TextUnit::of_str(&code.text);
+            code.text.push_str(line);
+            code.text.push('\n');
+            let offset = comment.syntax().range().start() + TextUnit::from_usize(start);
+            code.lines.push((TextRange::offset_len(line_start, TextUnit::of_str(line)), offset));
+        }
+    }
+    res
+}
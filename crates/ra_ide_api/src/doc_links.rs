@@ -0,0 +1,260 @@
+//! Links to items in doc comments, like [`Foo`] or [the module](crate::foo).
+//!
+//! Like rustdoc's intra-doc links, the path of a link is resolved in the scope
+//! of the documented item. Links to URLs, and brackets which are not links,
+//! just don't resolve.
+
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    AstNode, SourceFile, SyntaxKind::ITEM_LIST, SyntaxNode, TextRange, TextUnit,
+    algo::find_token_at_offset,
+    ast::{self, AstToken, DocCommentsOwner, NameOwner},
+};
+
+use crate::{FileId, FilePosition, NavigationTarget, RangeInfo, db::RootDatabase};
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct DocLink {
+    /// The range of the whole link in the docs.
+    pub(crate) range: TextRange,
+    /// The label of the link, which is the path for `[text](path)` links.
+    pub(crate) label: String,
+    pub(crate) path: String,
+    /// The range of the path in the docs.
+    pub(crate) path_range: TextRange,
+    /// Whether the link is written `[text](path)`, not `[path]`.
+    pub(crate) is_inline: bool,
+}
+
+/// The links in `docs`, outside of code blocks.
+pub(crate) fn doc_links(docs: &str) -> Vec<DocLink> {
+    let mut res = Vec::new();
+    let mut in_code_block = false;
+    let mut line_start = 0;
+    for line in docs.split('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block {
+            res.extend(line_links(line).into_iter().map(|mut link| {
+                link.range = link.range + TextUnit::from_usize(line_start);
+                link.path_range = link.path_range + TextUnit::from_usize(line_start);
+                link
+            }));
+        }
+        line_start += line.len() + 1;
+    }
+    res
+}
+
+fn line_links(line: &str) -> Vec<DocLink> {
+    let mut res = Vec::new();
+    let mut pos = 0;
+    while let Some(open) = line[pos..].find('[').map(|it| pos + it) {
+        let close = match line[open..].find(']') {
+            Some(it) => open + it,
+            None => break,
+        };
+        pos = close + 1;
+        let text = &line[open + 1..close];
+        let rest = &line[close + 1..];
+        let (label, path, path_start, end, is_inline) = if rest.starts_with('(') {
+            let dest_end = match rest.find(')') {
+                Some(it) => close + 1 + it,
+                None => continue,
+            };
+            pos = dest_end + 1;
+            let dest = &line[close + 2..dest_end];
+            let path_start = dest_end - dest.trim_start().len();
+            let dest = dest.trim();
+            (dest, dest, path_start, dest_end + 1, true)
+        } else if rest.starts_with('[') {
+            // A reference link, with its label.
+            pos = rest.find(']').map_or(line.len(), |it| close + 1 + it + 1);
+            continue;
+        } else if rest.starts_with(':') {
+            // The definition of a reference.
+            continue;
+        } else {
+            let path_start = close - text.trim_start_matches('`').len();
+            (text, text.trim_matches('`'), path_start, close + 1, false)
+        };
+        let path_start = TextUnit::from_usize(path_start);
+        res.push(DocLink {
+            range: TextRange::from_to(TextUnit::from_usize(open), TextUnit::from_usize(end)),
+            label: label.to_string(),
+            path: path.to_string(),
+            path_range: TextRange::offset_len(path_start, TextUnit::of_str(path)),
+            is_inline,
+        });
+    }
+    res
+}
+
+/// Resolves the `path` of a link in the docs of `owner`, the documented item.
+pub(crate) fn resolve_doc_link(
+    db: &RootDatabase,
+    file_id: FileId,
+    owner: &SyntaxNode,
+    path: &str,
+) -> Option<NavigationTarget> {
+    // Disambiguators like `struct@Foo`, `foo()` or `foo!` are not needed to
+    // find the item.
+    let path = path.rsplit('@').next()?.trim_end_matches("()").trim_end_matches('!');
+    let is_path = !path.is_empty()
+        && path.chars().all(|c| c.is_alphanumeric() || c == '_' || c == ':')
+        && !path.starts_with(|c: char| c.is_numeric());
+    if !is_path {
+        return None;
+    }
+    let file = SourceFile::parse(&format!("type T = {};", path));
+    if !file.errors().is_empty() {
+        return None;
+    }
+    let path = hir::Path::from_ast(file.syntax().descendants().find_map(ast::Path::cast)?)?;
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, owner, None);
+    let res = analyzer.resolve_hir_path(db, &path);
+    match res.clone().take_types().or_else(|| res.take_values())? {
+        hir::Resolution::Def(def) => Some(NavigationTarget::from_def(db, def)),
+        _ => None,
+    }
+}
+
+/// Rewrites the links of `docs`, the docs of `owner`, which resolve to items,
+/// as reference links, and returns them with the labels of the references.
+pub(crate) fn resolve_doc_links(
+    db: &RootDatabase,
+    file_id: FileId,
+    owner: &SyntaxNode,
+    docs: &str,
+) -> (String, Vec<(String, NavigationTarget)>) {
+    let mut res = String::new();
+    let mut links = Vec::new();
+    let mut last = 0;
+    for link in doc_links(docs) {
+        let target = match resolve_doc_link(db, file_id, owner, &link.path) {
+            Some(it) => it,
+            None => continue,
+        };
+        if link.is_inline {
+            let range = link.range;
+            let text_end = docs[range].find("](").unwrap() + range.start().to_usize();
+            res.push_str(&docs[last..text_end]);
+            res.push_str(&format!("][{}]", link.label));
+            last = range.end().to_usize();
+        }
+        links.push((link.label, target));
+    }
+    res.push_str(&docs[last..]);
+    (res, links)
+}
+
+/// The parameter of the function `owner` named by the `path` of a link in its
+/// docs, like [`x`] in the docs of `fn foo(x: u32)`. Parameters are found
+/// before the items of the same name.
+pub(crate) fn resolve_doc_link_param<'a>(
+    owner: &'a SyntaxNode,
+    path: &str,
+) -> Option<&'a ast::BindPat> {
+    let fn_def = ast::FnDef::cast(owner)?;
+    fn_def
+        .param_list()?
+        .params()
+        .filter_map(|param| match param.pat()?.kind() {
+            ast::PatKind::BindPat(it) => Some(it),
+            _ => None,
+        })
+        .find(|it| it.name().map_or(false, |name| *name.text() == *path))
+}
+
+/// The ranges of the paths of the links naming the parameter `binding` in the
+/// docs of its function.
+pub(crate) fn param_doc_links(binding: &ast::BindPat) -> Vec<TextRange> {
+    let mut res = Vec::new();
+    let fn_def = binding
+        .syntax()
+        .parent()
+        .and_then(ast::Param::cast)
+        .and_then(|it| it.syntax().ancestors().find_map(ast::FnDef::cast));
+    let (fn_def, name) = match (fn_def, binding.name()) {
+        (Some(fn_def), Some(name)) => (fn_def, name),
+        _ => return res,
+    };
+    for comment in fn_def.doc_comments().filter(|it| it.kind().doc.is_some()) {
+        let start = comment.syntax().range().start();
+        for link in doc_links(comment.text().as_str()) {
+            if link.path == *name.text() {
+                res.push(link.path_range + start);
+            }
+        }
+    }
+    res
+}
+
+/// The link at `offset` in a doc comment, with the range of the file, and the
+/// documented item.
+pub(crate) fn doc_link_at_offset(
+    file: &SourceFile,
+    offset: TextUnit,
+) -> Option<(DocLink, &SyntaxNode)> {
+    let comment = find_token_at_offset(file.syntax(), offset)
+        .filter_map(ast::Comment::cast)
+        .find(|it| it.kind().doc.is_some())?;
+    let start = comment.syntax().range().start();
+    let mut link = doc_links(comment.text().as_str())
+        .into_iter()
+        .find(|it| (it.range + start).contains_inclusive(offset))?;
+    link.range = link.range + start;
+    link.path_range = link.path_range + start;
+    // Inner doc comments of inline modules are in their item list.
+    let mut owner = comment.syntax().parent();
+    if owner.kind() == ITEM_LIST {
+        owner = owner.parent()?;
+    }
+    Some((link, owner))
+}
+
+/// Goes to the parameter or the item of the link at `position` in a doc
+/// comment.
+pub(crate) fn doc_link_definition(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    let file = db.parse(position.file_id);
+    let (link, owner) = doc_link_at_offset(&file, position.offset)?;
+    let nav = match resolve_doc_link_param(owner, &link.path) {
+        Some(binding) => NavigationTarget::from_bind_pat(position.file_id, binding),
+        None => resolve_doc_link(db, position.file_id, owner, &link.path)?,
+    };
+    Some(RangeInfo::new(link.range, vec![nav]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::doc_links;
+
+    #[test]
+    fn finds_links() {
+        let docs = "See [`Foo`], [the module](crate::foo) and [Bar][bar].\n\
+                    ```\nlet x = [a];\n```\n[`baz`]\n\n[bar]: Bar";
+        let links: Vec<_> = doc_links(docs)
+            .into_iter()
+            .map(|it| format!("{} {} {:?} {}", it.label, it.path, it.range, it.is_inline))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                "`Foo` Foo [4; 11) false",
+                "crate::foo crate::foo [13; 37) true",
+                "`baz` baz [75; 82) false",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_link_paths() {
+        let docs = "[`x`] and [y]( crate::y )";
+        let paths: Vec<_> =
+            doc_links(docs).into_iter().map(|it| docs[it.path_range].to_string()).collect();
+        assert_eq!(paths, vec!["x", "crate::y"]);
+    }
+}
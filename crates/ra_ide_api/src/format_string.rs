@@ -0,0 +1,216 @@
+//! The arguments referred to by name in the format strings of the `format!`
+//! family of macros, like `x` in `println!("{x} {y:>width$}", y = 1)`.
+//!
+//! The std macros are builtins which are not expanded, so their calls are
+//! recognized by the name of the macro, and the format string is parsed here.
+//! Names without an explicit `name = value` argument in the call are inline
+//! arguments, which refer to the value of that name at the call site.
+
+use hir::PathResolution;
+use ra_syntax::{
+    AstNode, SmolStr, SyntaxElement, SyntaxNode, SyntaxToken, TextRange, TextUnit,
+    SyntaxKind::{COMMA, EQ, IDENT, RAW_STRING, STRING},
+    algo::find_token_at_offset,
+    ast,
+};
+
+use crate::{FileId, db::RootDatabase};
+
+/// The macros taking a format string, with the index of the format string in
+/// their arguments.
+const FORMAT_MACROS: &[(&str, usize)] = &[
+    ("format", 0),
+    ("format_args", 0),
+    ("print", 0),
+    ("println", 0),
+    ("eprint", 0),
+    ("eprintln", 0),
+    ("panic", 0),
+    ("write", 1),
+    ("writeln", 1),
+];
+
+pub(crate) struct FormatString<'a> {
+    pub(crate) macro_call: &'a ast::MacroCall,
+    pub(crate) string: SyntaxToken<'a>,
+    /// The names of the explicit `name = value` arguments.
+    named_args: Vec<SmolStr>,
+    placeholders: Vec<Placeholder>,
+}
+
+/// A name referring to an argument in a format string, either as the argument
+/// of a placeholder or as its width or precision, like `width` in `{:width$}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Placeholder {
+    pub(crate) name: SmolStr,
+    pub(crate) range: TextRange,
+}
+
+impl<'a> FormatString<'a> {
+    /// The placeholders which have no explicit argument in the call.
+    pub(crate) fn inline_args(&self) -> impl Iterator<Item = &Placeholder> {
+        let named_args = &self.named_args;
+        self.placeholders.iter().filter(move |it| !named_args.contains(&it.name))
+    }
+}
+
+pub(crate) fn format_string(macro_call: &ast::MacroCall) -> Option<FormatString> {
+    let macro_name = macro_call.path()?.segment()?.name_ref()?;
+    let &(_, string_idx) =
+        FORMAT_MACROS.iter().find(|(name, _)| *name == macro_name.text().as_str())?;
+    let token_tree = macro_call.token_tree()?;
+    let args = split_args(token_tree);
+    let string = match args.get(string_idx)?.as_slice() {
+        [SyntaxElement::Token(it)] if it.kind() == STRING || it.kind() == RAW_STRING => *it,
+        _ => return None,
+    };
+    let named_args = args[string_idx + 1..]
+        .iter()
+        .filter(|arg| arg.len() > 2 && arg[0].kind() == IDENT && arg[1].kind() == EQ)
+        .filter_map(|arg| arg[0].as_token())
+        .map(|name| name.text().clone())
+        .collect();
+    let text = string.text().as_str();
+    let start = text.find('"')? + 1;
+    let end = text.rfind('"').filter(|&it| it >= start)?;
+    let offset = string.range().start() + TextUnit::from_usize(start);
+    let placeholders = parse_placeholders(&text[start..end], string.kind() == RAW_STRING)
+        .into_iter()
+        .map(|it| Placeholder { name: it.name, range: it.range + offset })
+        .collect();
+    Some(FormatString { macro_call, string, named_args, placeholders })
+}
+
+/// The format strings in `node`.
+pub(crate) fn format_strings(node: &SyntaxNode) -> impl Iterator<Item = FormatString> {
+    node.descendants().filter_map(ast::MacroCall::cast).filter_map(format_string)
+}
+
+/// The inline argument of a format string at `offset`.
+pub(crate) fn inline_arg_at_offset(
+    node: &SyntaxNode,
+    offset: TextUnit,
+) -> Option<(FormatString, Placeholder)> {
+    let string = find_token_at_offset(node, offset)
+        .find(|it| it.kind() == STRING || it.kind() == RAW_STRING)?;
+    let macro_call = string.parent().parent().and_then(ast::MacroCall::cast)?;
+    let format_string = format_string(macro_call).filter(|it| it.string == string)?;
+    let placeholder =
+        format_string.inline_args().find(|it| it.range.contains_inclusive(offset))?.clone();
+    Some((format_string, placeholder))
+}
+
+/// What the inline argument `placeholder` of `format_string` refers to.
+pub(crate) fn resolve_inline_arg(
+    db: &RootDatabase,
+    file_id: FileId,
+    format_string: &FormatString,
+    placeholder: &Placeholder,
+) -> Option<PathResolution> {
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, format_string.macro_call.syntax(), None);
+    analyzer.resolve_value_name(db, &placeholder.name, placeholder.range.start())
+}
+
+/// The arguments of a macro call, separated by top-level commas, without
+/// trivia.
+fn split_args(token_tree: &ast::TokenTree) -> Vec<Vec<SyntaxElement>> {
+    let mut elements: Vec<SyntaxElement> =
+        token_tree.syntax().children_with_tokens().filter(|it| !it.kind().is_trivia()).collect();
+    // The delimiters of the token tree.
+    if elements.len() < 2 {
+        return Vec::new();
+    }
+    elements.pop();
+    elements.remove(0);
+    elements.split(|it| it.kind() == COMMA).map(|it| it.to_vec()).collect()
+}
+
+/// The named arguments of the placeholders of the contents of a string, with
+/// their range in the contents.
+fn parse_placeholders(text: &str, is_raw: bool) -> Vec<Placeholder> {
+    let mut res = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, it)| it);
+        match c {
+            // Escapes like `\u{7FFF}` have braces which are not placeholders.
+            '\\' if !is_raw => {
+                if let Some((_, 'u')) = chars.next() {
+                    while let Some((_, c)) = chars.next() {
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                }
+            }
+            '{' | '}' if next == Some(c) => {
+                chars.next();
+            }
+            '{' => {
+                let start = idx + 1;
+                let end = match text[start..].find('}') {
+                    Some(it) => start + it,
+                    None => break,
+                };
+                while chars.peek().map_or(false, |&(idx, _)| idx <= end) {
+                    chars.next();
+                }
+                let (arg, spec) = match text[start..end].find(':') {
+                    Some(colon) => (start..start + colon, Some(start + colon + 1..end)),
+                    None => (start..end, None),
+                };
+                push_name(&mut res, text, arg.start, arg.end);
+                // The width and the precision may be arguments, like `{:.prec$}`.
+                if let Some(spec) = spec {
+                    for (dollar, _) in text[spec.clone()].match_indices('$') {
+                        let name_end = spec.start + dollar;
+                        let name_start = text[spec.start..name_end]
+                            .rfind(|c: char| !is_ident_char(c))
+                            .map_or(spec.start, |it| spec.start + it + 1);
+                        push_name(&mut res, text, name_start, name_end);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    res
+}
+
+fn push_name(acc: &mut Vec<Placeholder>, text: &str, start: usize, end: usize) {
+    let name = &text[start..end];
+    let is_ident = match name.chars().next() {
+        Some(c) => (c.is_alphabetic() || c == '_') && name != "_",
+        None => false,
+    };
+    if is_ident && name.chars().all(is_ident_char) {
+        acc.push(Placeholder {
+            name: name.into(),
+            range: TextRange::from_to(TextUnit::from_usize(start), TextUnit::from_usize(end)),
+        });
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_placeholders;
+
+    fn names(text: &str) -> Vec<String> {
+        parse_placeholders(text, false)
+            .into_iter()
+            .map(|it| format!("{} {:?}", it.name, it.range))
+            .collect()
+    }
+
+    #[test]
+    fn parses_named_arguments() {
+        assert_eq!(
+            names("{x} {} {0} {y:?} {{z}} {:>width$.prec$} {_} \\u{7FFF} {a1:0$}"),
+            vec!["x [1; 2)", "y [12; 13)", "width [26; 31)", "prec [33; 37)", "a1 [54; 56)"]
+        );
+    }
+}
@@ -1,12 +1,22 @@
 use ra_db::{FileId, SourceDatabase};
 use ra_syntax::{
     AstNode, ast,
-    algo::{find_node_at_offset, visit::{visitor, Visitor}},
+    algo::{
+        find_node_at_offset, find_token_at_offset, find_covering_element,
+        visit::{visitor, Visitor},
+    },
     SyntaxNode,
+    SyntaxKind::IDENT,
 };
 use test_utils::tested_by;
 
-use crate::{FilePosition, NavigationTarget, db::RootDatabase, RangeInfo};
+use crate::{
+    FilePosition, NavigationTarget,
+    db::RootDatabase,
+    RangeInfo,
+    doc_links::doc_link_definition,
+    format_string::{inline_arg_at_offset, resolve_inline_arg},
+};
 
 pub(crate) fn goto_definition(
     db: &RootDatabase,
@@ -22,7 +32,48 @@ pub(crate) fn goto_definition(
         let navs = name_definition(db, position.file_id, name)?;
         return Some(RangeInfo::new(name.syntax().range(), navs));
     }
-    None
+    macro_arg_definition(db, position)
+        .or_else(|| inline_format_arg_definition(db, position))
+        .or_else(|| doc_link_definition(db, position))
+}
+
+/// Goes to the definition of a name in the arguments of a macro call in
+/// expression position, by finding the name in the expansion of the call.
+fn macro_arg_definition(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    let sema = hir::Semantics::new(db);
+    let file = sema.parse(position.file_id);
+    let token =
+        find_token_at_offset(file.syntax(), position.offset).find(|it| it.kind() == IDENT)?;
+    let token_tree = token.parent().ancestors().filter_map(ast::TokenTree::cast).last()?;
+    let macro_call = token_tree.syntax().parent().and_then(ast::MacroCall::cast)?;
+    let expansion = sema.expand_expr(macro_call)?;
+    let range = sema.map_token_down(&expansion, token.range())?;
+    let name_ref = find_covering_element(expansion.syntax(), range)
+        .ancestors()
+        .find_map(ast::NameRef::cast)?;
+    let navs = resolve_reference(&sema, db, position.file_id, name_ref).to_vec();
+    Some(RangeInfo::new(token.range(), navs))
+}
+
+/// Goes to the definition of an inline argument of a format string, like `x` in
+/// `println!("{x}")`.
+fn inline_format_arg_definition(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    let file = db.parse(position.file_id);
+    let (format_string, placeholder) = inline_arg_at_offset(file.syntax(), position.offset)?;
+    let nav = match resolve_inline_arg(db, position.file_id, &format_string, &placeholder)? {
+        hir::PathResolution::LocalBinding(pat) => {
+            NavigationTarget::from_pat(db, position.file_id, pat)
+        }
+        hir::PathResolution::Def(def) => NavigationTarget::from_def(db, def),
+        _ => return None,
+    };
+    Some(RangeInfo::new(placeholder.range, vec![nav]))
 }
 
 pub(crate) enum ReferenceResult {
@@ -45,69 +96,54 @@ pub(crate) fn reference_definition(
     file_id: FileId,
     name_ref: &ast::NameRef,
 ) -> ReferenceResult {
-    use self::ReferenceResult::*;
-
-    let analyzer = hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+    let sema = hir::Semantics::new(db);
+    // `parse` is memoized, so this registers the tree of `name_ref`.
+    sema.parse(file_id);
+    resolve_reference(&sema, db, file_id, name_ref)
+}
 
-    // Special cases:
+/// Resolves `name_ref` of a tree known to `sema`, which is in `file_id` or in
+/// a macro expansion of it.
+fn resolve_reference(
+    sema: &hir::Semantics<RootDatabase>,
+    db: &RootDatabase,
+    file_id: FileId,
+    name_ref: &ast::NameRef,
+) -> ReferenceResult {
+    use self::ReferenceResult::*;
 
-    // Check if it is a method
-    if let Some(method_call) = name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast) {
-        tested_by!(goto_definition_works_for_methods);
-        if let Some(func) = analyzer.resolve_method_call(method_call) {
-            return Exact(NavigationTarget::from_function(db, func));
+    let parent = name_ref.syntax().parent();
+    match sema.resolve_name_ref(name_ref) {
+        Some(hir::Definition::Def(def)) => {
+            if parent.and_then(ast::MethodCallExpr::cast).is_some() {
+                tested_by!(goto_definition_works_for_methods);
+            }
+            return Exact(NavigationTarget::from_def(db, def));
         }
-    }
-    // It could also be a field access
-    if let Some(field_expr) = name_ref.syntax().parent().and_then(ast::FieldExpr::cast) {
-        tested_by!(goto_definition_works_for_fields);
-        if let Some(field) = analyzer.resolve_field(field_expr) {
-            return Exact(NavigationTarget::from_field(db, field));
-        };
-    }
-
-    // It could also be a named field
-    if let Some(field_expr) = name_ref.syntax().parent().and_then(ast::NamedField::cast) {
-        tested_by!(goto_definition_works_for_named_fields);
-
-        let struct_lit = field_expr.syntax().ancestors().find_map(ast::StructLit::cast);
-
-        if let Some(ty) = struct_lit.and_then(|lit| analyzer.type_of(db, lit.into())) {
-            if let Some((hir::AdtDef::Struct(s), _)) = ty.as_adt() {
-                let hir_path = hir::Path::from_name_ref(name_ref);
-                let hir_name = hir_path.as_ident().unwrap();
-
-                if let Some(field) = s.field(db, hir_name) {
-                    return Exact(NavigationTarget::from_field(db, field));
-                }
+        Some(hir::Definition::Field(field)) => {
+            if parent.and_then(ast::NamedField::cast).is_some() {
+                tested_by!(goto_definition_works_for_named_fields);
+            } else {
+                tested_by!(goto_definition_works_for_fields);
             }
+            return Exact(NavigationTarget::from_field(db, field));
         }
-    }
-
-    // General case, a path or a local:
-    if let Some(path) = name_ref.syntax().ancestors().find_map(ast::Path::cast) {
-        if let Some(resolved) = analyzer.resolve_path(db, path) {
-            match resolved {
-                hir::PathResolution::Def(def) => return Exact(NavigationTarget::from_def(db, def)),
-                hir::PathResolution::LocalBinding(pat) => {
-                    let nav = NavigationTarget::from_pat(db, file_id, pat);
-                    return Exact(nav);
-                }
-                hir::PathResolution::GenericParam(..) => {
-                    // FIXME: go to the generic param def
-                }
-                hir::PathResolution::SelfType(impl_block) => {
-                    let ty = impl_block.target_ty(db);
-
-                    if let Some((def_id, _)) = ty.as_adt() {
-                        return Exact(NavigationTarget::from_adt_def(db, def_id));
-                    }
-                }
-                hir::PathResolution::AssocItem(assoc) => {
-                    return Exact(NavigationTarget::from_impl_item(db, assoc));
-                }
+        Some(hir::Definition::Local(pat)) => {
+            return Exact(NavigationTarget::from_pat(db, file_id, pat));
+        }
+        Some(hir::Definition::SelfType(impl_block)) => {
+            let ty = impl_block.target_ty(db);
+            if let Some((def_id, _)) = ty.as_adt() {
+                return Exact(NavigationTarget::from_adt_def(db, def_id));
             }
         }
+        Some(hir::Definition::AssocItem(assoc)) => {
+            return Exact(NavigationTarget::from_impl_item(db, assoc));
+        }
+        Some(hir::Definition::GenericParam(..)) => {
+            // FIXME: go to the generic param def
+        }
+        None => (),
     }
 
     // Fallback index based approach:
@@ -140,6 +176,13 @@ pub(crate) fn name_definition(
         return Some(vec![nav]);
     }
 
+    let sema = hir::Semantics::new(db);
+    sema.parse(file_id);
+    if let Some(field) = sema.resolve_field_pat(name) {
+        tested_by!(goto_definition_works_for_field_pats);
+        return Some(vec![NavigationTarget::from_field(db, field)]);
+    }
+
     None
 }
 
@@ -226,6 +269,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn goto_definition_works_across_crates() {
+        check_goto(
+            "
+            //- /main.rs crate:main deps:foo
+            use foo::Bar;
+            fn main() { let _: <|>Bar; }
+            //- /foo/lib.rs crate:foo
+            pub struct Bar;
+            ",
+            "Bar STRUCT_DEF FileId(2) [0; 15) [11; 14)",
+        );
+    }
+
     #[test]
     fn goto_definition_works_for_methods() {
         covers!(goto_definition_works_for_methods);
@@ -245,6 +302,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn goto_definition_works_for_trait_items_in_paths() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait Make { fn make() -> Self; }
+            struct Foo;
+            impl Make for Foo {
+                fn make() -> Foo { Foo }
+            }
+
+            fn bar() {
+                Foo::make<|>();
+            }
+            ",
+            "make FN_DEF FileId(1) [70; 94) [73; 77)",
+        );
+        check_goto(
+            "
+            //- /lib.rs
+            trait Make { fn make() -> Self; }
+            struct Foo;
+
+            fn bar() {
+                <Foo as Make>::make<|>();
+            }
+            ",
+            "make FN_DEF FileId(1) [13; 31) [16; 20)",
+        );
+    }
+
     #[test]
     fn goto_definition_works_for_fields() {
         covers!(goto_definition_works_for_fields);
@@ -263,6 +351,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn goto_definition_works_for_named_fields_of_enum_variants() {
+        covers!(goto_definition_works_for_named_fields);
+        check_goto(
+            "
+            //- /lib.rs
+            enum E {
+                V { spam: u32 },
+            }
+
+            fn bar() -> E {
+                let spam = 0;
+                E::V { spam<|> }
+            }
+            ",
+            "spam NAMED_FIELD_DEF FileId(1) [17; 26) [17; 21)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_works_for_field_pats() {
+        covers!(goto_definition_works_for_field_pats);
+        check_goto(
+            "
+            //- /lib.rs
+            enum E {
+                V { spam: u32, eggs: u32 },
+            }
+
+            fn bar(e: E) {
+                match e {
+                    E::V { spam<|>: s, eggs } => (),
+                }
+            }
+            ",
+            "spam NAMED_FIELD_DEF FileId(1) [17; 26) [17; 21)",
+        );
+        check_goto(
+            "
+            //- /lib.rs
+            enum E {
+                V { spam: u32, eggs: u32 },
+            }
+
+            fn bar(e: E) {
+                match e {
+                    E::V { spam: s, ref eggs<|> } => (),
+                }
+            }
+            ",
+            "eggs NAMED_FIELD_DEF FileId(1) [28; 37) [28; 32)",
+        );
+    }
+
     #[test]
     fn goto_definition_works_for_named_fields() {
         covers!(goto_definition_works_for_named_fields);
@@ -282,6 +424,94 @@ mod tests {
             "spam NAMED_FIELD_DEF FileId(1) [17; 26) [17; 21)",
         );
     }
+    #[test]
+    fn goto_definition_works_in_macro_arguments() {
+        check_goto(
+            "
+            //- /lib.rs
+            macro_rules! id {
+                ($e:expr) => { $e }
+            }
+            struct Foo { spam: u32 }
+            fn bar(foo: Foo) -> u32 {
+                id!(foo.spam<|>)
+            }
+            ",
+            "spam NAMED_FIELD_DEF FileId(1) [57; 66) [57; 61)",
+        );
+        check_goto(
+            "
+            //- /lib.rs
+            macro_rules! id {
+                ($e:expr) => { $e }
+            }
+            fn bar() -> u32 {
+                let spam = 1;
+                id!(spam<|> + 1)
+            }
+            ",
+            "spam BIND_PAT FileId(1) [70; 74) [70; 74)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_works_for_inline_format_args() {
+        check_goto(
+            r#"
+            //- /lib.rs
+            const WIDTH: usize = 4;
+            fn bar() {
+                let spam = 1;
+                println!("{spam<|>:>WIDTH$}", WIDTH = 2);
+            }
+            "#,
+            "spam BIND_PAT FileId(1) [43; 47) [43; 47)",
+        );
+        check_goto(
+            r#"
+            //- /lib.rs
+            const WIDTH: usize = 4;
+            fn bar() {
+                let spam = 1;
+                println!("{spam:>WIDTH<|>$}");
+            }
+            "#,
+            "WIDTH CONST_DEF FileId(1) [0; 23) [6; 11)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_works_in_doc_links() {
+        check_goto(
+            "
+            //- /lib.rs
+            mod foo { pub struct Bar; }
+            /// Makes a [`Bar`](foo::Bar<|>).
+            fn make() {}
+            ",
+            "Bar STRUCT_DEF FileId(1) [10; 25) [21; 24)",
+        );
+        check_goto(
+            "
+            //- /lib.rs
+            mod foo {
+                //! See [`Baz<|>`].
+                pub struct Baz;
+            }
+            ",
+            "Baz STRUCT_DEF FileId(1) [14; 50) [46; 49)",
+        );
+        check_goto(
+            "
+            //- /lib.rs
+            struct x;
+            /// Uses [`x<|>`].
+            fn foo(x: u32) {}
+            ",
+            "x BIND_PAT FileId(1) [33; 34) [33; 34)",
+        );
+    }
+
     #[test]
     fn goto_definition_on_self() {
         check_goto(
@@ -0,0 +1,483 @@
+//! Highlights the ranges related to the one under the cursor, in the current
+//! file: the references to the symbol with their kind of access, and for
+//! locals the end of their scope where they're implicitly dropped, the exit
+//! points of the function on `return` and `?`, or the `break`s and `continue`s
+//! of a loop on its keyword.
+
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    AstNode, SourceFile, SyntaxNode, TextRange,
+    SyntaxKind::{self, *},
+    algo::{find_covering_element, find_node_at_offset, find_token_at_offset},
+    ast,
+};
+
+use crate::{
+    FileId, FilePosition, NavigationTarget,
+    db::RootDatabase,
+    goto_definition::{name_definition, reference_definition, ReferenceResult},
+    references::find_local_refs,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedRange {
+    pub range: TextRange,
+    /// `None` for the ranges which are not references, like the definition of
+    /// an item or the keywords of a loop.
+    pub access: Option<ReferenceAccess>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceAccess {
+    Read,
+    Write,
+}
+
+pub(crate) fn highlight_related(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<Vec<RelatedRange>> {
+    let file = db.parse(position.file_id);
+    for token in find_token_at_offset(file.syntax(), position.offset) {
+        let parent = token.parent();
+        match (token.kind(), parent.kind()) {
+            (RETURN_KW, RETURN_EXPR) | (QUESTION, TRY_EXPR) => return exit_points(parent),
+            (LOOP_KW, LOOP_EXPR)
+            | (WHILE_KW, WHILE_EXPR)
+            | (FOR_KW, FOR_EXPR)
+            | (BREAK_KW, BREAK_EXPR)
+            | (CONTINUE_KW, CONTINUE_EXPR) => return loop_jumps(parent),
+            _ => (),
+        }
+    }
+    references(db, position, &file)
+}
+
+/// The `return`s, `?`s and the tail expression of the function or closure.
+fn exit_points(node: &SyntaxNode) -> Option<Vec<RelatedRange>> {
+    let owner = node.ancestors().find(|it| it.kind() == FN_DEF || it.kind() == LAMBDA_EXPR)?;
+    let body = match ast::FnDef::cast(owner) {
+        Some(fn_def) => fn_def.body()?.syntax(),
+        None => ast::LambdaExpr::cast(owner)?.body()?.syntax(),
+    };
+
+    let mut res = Vec::new();
+    visit_body(body, &mut |node| match node.kind() {
+        RETURN_EXPR => res.extend(keyword(node, RETURN_KW)),
+        TRY_EXPR => res.extend(keyword(node, QUESTION)),
+        _ => (),
+    });
+    let tail = match ast::Block::cast(body) {
+        Some(block) => block.expr(),
+        None => ast::Expr::cast(body),
+    };
+    let tail = match tail.and_then(|it| ast::BlockExpr::cast(it.syntax())) {
+        Some(block) => block.block().and_then(|it| it.expr()),
+        None => tail,
+    };
+    if let Some(tail) = tail {
+        if tail.syntax().kind() != RETURN_EXPR {
+            res.push(RelatedRange { range: tail.syntax().range(), access: None });
+        }
+    }
+    res.sort_by_key(|it| it.range.start());
+    Some(res)
+}
+
+/// The keyword of the loop of `node`, which is the loop itself or a `break`
+/// or `continue`, and the keywords of the `break`s and `continue`s of it.
+fn loop_jumps(node: &SyntaxNode) -> Option<Vec<RelatedRange>> {
+    let loop_expr = if is_loop(node.kind()) { node } else { target_loop(node)? };
+    let mut res = Vec::new();
+    res.extend([LOOP_KW, WHILE_KW, FOR_KW].iter().filter_map(|&kw| keyword(loop_expr, kw)));
+    visit_body(loop_expr, &mut |node| {
+        let kw = match node.kind() {
+            BREAK_EXPR => BREAK_KW,
+            CONTINUE_EXPR => CONTINUE_KW,
+            _ => return,
+        };
+        if target_loop(node).map(|it| it.range()) == Some(loop_expr.range()) {
+            res.extend(keyword(node, kw));
+        }
+    });
+    Some(res)
+}
+
+fn is_loop(kind: SyntaxKind) -> bool {
+    kind == LOOP_EXPR || kind == WHILE_EXPR || kind == FOR_EXPR
+}
+
+/// The loop a `break` or `continue` jumps out of, taking its label into
+/// account.
+fn target_loop(jump: &SyntaxNode) -> Option<&SyntaxNode> {
+    let label = lifetime(jump);
+    for node in jump.ancestors().skip(1) {
+        match node.kind() {
+            FN_DEF | LAMBDA_EXPR => return None,
+            kind if is_loop(kind) => {
+                let loop_label = node.children().find(|it| it.kind() == LABEL).and_then(lifetime);
+                if label.is_none() || label == loop_label {
+                    return Some(node);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+fn lifetime(node: &SyntaxNode) -> Option<String> {
+    node.children_with_tokens()
+        .filter_map(|it| it.as_token())
+        .find(|it| it.kind() == LIFETIME)
+        .map(|it| it.text().to_string())
+}
+
+fn keyword(node: &SyntaxNode, kind: SyntaxKind) -> Option<RelatedRange> {
+    let token = node.children_with_tokens().find(|it| it.kind() == kind)?;
+    Some(RelatedRange { range: token.range(), access: None })
+}
+
+/// Calls `f` on the descendants of `node`, but not on the ones of the nested
+/// functions and closures.
+fn visit_body(node: &SyntaxNode, f: &mut impl FnMut(&SyntaxNode)) {
+    for child in node.children() {
+        if child.kind() == FN_DEF || child.kind() == LAMBDA_EXPR {
+            continue;
+        }
+        f(child);
+        visit_body(child, f);
+    }
+}
+
+fn references(
+    db: &RootDatabase,
+    position: FilePosition,
+    file: &SourceFile,
+) -> Option<Vec<RelatedRange>> {
+    let syntax = file.syntax();
+    if let Some(refs) = find_local_refs(db, position) {
+        let declaration = refs.declaration().range();
+        let binding =
+            find_covering_element(syntax, declaration).ancestors().find_map(ast::BindPat::cast);
+        let access = binding.and_then(declaration_access);
+        let mut res = vec![RelatedRange { range: declaration, access }];
+        for reference in refs.references() {
+            let access = range_access(syntax, reference.range);
+            res.push(RelatedRange { range: reference.range, access });
+        }
+        // Bindings are resolved through the scopes, so a shadowed binding only
+        // has its own uses.
+        if let Some(drop) = binding.and_then(|it| drop_point(db, position.file_id, it)) {
+            res.push(RelatedRange { range: drop, access: None });
+        }
+        return Some(res);
+    }
+
+    let (name, definition) = definition(db, position.file_id, syntax, position)?;
+    let mut res = Vec::new();
+    if definition.file_id() == position.file_id {
+        res.push(RelatedRange { range: definition.range(), access: None });
+    }
+    for name_ref in syntax.descendants().filter_map(ast::NameRef::cast) {
+        if *name_ref.text() != name {
+            continue;
+        }
+        if let ReferenceResult::Exact(nav) = reference_definition(db, position.file_id, name_ref) {
+            if nav.file_id() == definition.file_id() && nav.full_range() == definition.full_range()
+            {
+                let access = Some(reference_access(name_ref));
+                res.push(RelatedRange { range: name_ref.syntax().range(), access });
+            }
+        }
+    }
+    Some(res)
+}
+
+/// The name and the definition of the symbol at `position`.
+pub(crate) fn definition(
+    db: &RootDatabase,
+    file_id: FileId,
+    syntax: &SyntaxNode,
+    position: FilePosition,
+) -> Option<(String, NavigationTarget)> {
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(syntax, position.offset) {
+        return match reference_definition(db, file_id, name_ref) {
+            ReferenceResult::Exact(nav) => Some((name_ref.text().to_string(), nav)),
+            ReferenceResult::Approximate(_) => None,
+        };
+    }
+    let name = find_node_at_offset::<ast::Name>(syntax, position.offset)?;
+    let nav = name_definition(db, file_id, name)?.pop()?;
+    Some((name.text().to_string(), nav))
+}
+
+/// The closing brace of the scope of the local `binding`, where it's dropped
+/// if it still owns its value. The bindings of `Copy` types, which have no
+/// drop, don't have one.
+fn drop_point(db: &RootDatabase, file_id: FileId, binding: &ast::BindPat) -> Option<TextRange> {
+    let scope = binding.syntax().ancestors().find_map(|node| match node.kind() {
+        LET_STMT => node.parent().filter(|it| it.kind() == BLOCK),
+        PARAM => ast::FnDef::cast(node.ancestors().nth(2)?)?.body().map(|it| it.syntax()),
+        _ => None,
+    })?;
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, binding.syntax(), None);
+    let ty = analyzer.type_of_pat(db, binding.into())?;
+    let krate = hir::source_binder::module_from_child_node(db, file_id, binding.syntax())
+        .and_then(|it| it.krate(db));
+    if ty.is_copy(db, krate) {
+        return None;
+    }
+    let r_curly = scope.last_child_or_token().filter(|it| it.kind() == R_CURLY)?;
+    Some(r_curly.range())
+}
+
+/// A binding is written to when it's declared with a value.
+fn declaration_access(pat: &ast::BindPat) -> Option<ReferenceAccess> {
+    let stmt = pat.syntax().ancestors().find_map(ast::LetStmt::cast)?;
+    let stmt_pat = stmt.pat()?.syntax().range();
+    if stmt.initializer().is_some() && pat.syntax().range().is_subrange(&stmt_pat) {
+        Some(ReferenceAccess::Write)
+    } else {
+        None
+    }
+}
+
+/// The access of the reference at `range`. The references which are not names
+/// are the inline arguments of format strings, which are read, and the links
+/// of doc comments.
+pub(crate) fn range_access(syntax: &SyntaxNode, range: TextRange) -> Option<ReferenceAccess> {
+    let element = find_covering_element(syntax, range);
+    match element.ancestors().find_map(ast::NameRef::cast) {
+        Some(name_ref) => Some(reference_access(name_ref)),
+        None if element.kind() == COMMENT => None,
+        None => Some(ReferenceAccess::Read),
+    }
+}
+
+/// A reference is written to when it's assigned, like `x` in `x.y += 1`, or
+/// borrowed mutably.
+fn reference_access(name_ref: &ast::NameRef) -> ReferenceAccess {
+    let place = name_ref
+        .syntax()
+        .ancestors()
+        .skip(1)
+        .take_while(|it| {
+            [PATH_SEGMENT, PATH, PATH_EXPR, FIELD_EXPR, INDEX_EXPR].contains(&it.kind())
+        })
+        .last();
+    let place = match place {
+        Some(it) => it,
+        None => return ReferenceAccess::Read,
+    };
+    let is_written = match place.parent() {
+        Some(parent) => match ast::BinExpr::cast(parent) {
+            Some(bin_expr) => {
+                bin_expr.lhs().map(|it| it.syntax().range()) == Some(place.range())
+                    && bin_expr.op_kind().map_or(false, is_assignment)
+            }
+            None => ast::RefExpr::cast(parent).map_or(false, |it| it.is_mut()),
+        },
+        None => false,
+    };
+    if is_written {
+        ReferenceAccess::Write
+    } else {
+        ReferenceAccess::Read
+    }
+}
+
+fn is_assignment(op: ast::BinOp) -> bool {
+    use ast::BinOp::*;
+    match op {
+        Assignment | AddAssign | DivAssign | MulAssign | RemAssign | ShrAssign | ShlAssign
+        | SubAssign | BitOrAssign | BitAndAssign | BitXorAssign => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file_with_position;
+
+    use super::*;
+
+    fn check(fixture: &str, expected: &[(&str, Option<ReferenceAccess>)]) {
+        let (analysis, position) = single_file_with_position(fixture);
+        let text = analysis.file_text(position.file_id);
+        let actual: Vec<_> = analysis
+            .highlight_related(position)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|it| (text[it.range].to_string(), it.access))
+            .collect();
+        let expected: Vec<_> =
+            expected.iter().map(|&(text, access)| (text.to_string(), access)).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn highlights_local_with_accesses() {
+        check(
+            r"
+fn foo() {
+    let mut x<|> = 1;
+    x += 1;
+    let r = &mut x;
+    let y = x;
+}",
+            &[
+                ("x", Some(ReferenceAccess::Write)),
+                ("x", Some(ReferenceAccess::Write)),
+                ("x", Some(ReferenceAccess::Write)),
+                ("x", Some(ReferenceAccess::Read)),
+            ],
+        );
+    }
+
+    #[test]
+    fn highlights_inline_format_args() {
+        check(
+            r#"
+fn foo() {
+    let x<|> = 1;
+    println!("{x} {x:?} {y}", y = 2);
+}"#,
+            &[
+                ("x", Some(ReferenceAccess::Write)),
+                ("x", Some(ReferenceAccess::Read)),
+                ("x", Some(ReferenceAccess::Read)),
+            ],
+        );
+    }
+
+    #[test]
+    fn highlights_items() {
+        check(
+            r"
+struct Foo { x: u32 }
+fn foo(f: Foo<|>) -> Foo {
+    let mut f = f;
+    f.x = 1;
+    Foo { x: f.x }
+}",
+            &[
+                ("Foo", None),
+                ("Foo", Some(ReferenceAccess::Read)),
+                ("Foo", Some(ReferenceAccess::Read)),
+                ("Foo", Some(ReferenceAccess::Read)),
+            ],
+        );
+        check(
+            r"
+struct Foo { x<|>: u32 }
+fn foo(mut f: Foo) -> u32 {
+    f.x = 1;
+    f.x
+}",
+            &[("x", None), ("x", Some(ReferenceAccess::Write)), ("x", Some(ReferenceAccess::Read))],
+        );
+    }
+
+    #[test]
+    fn highlights_exit_points() {
+        check(
+            r"
+fn foo() -> Option<u32> {
+    if true {
+        return<|> None;
+    }
+    let f = || { return 0; };
+    let x = bar()?;
+    Some(x)
+}",
+            &[("return", None), ("?", None), ("Some(x)", None)],
+        );
+    }
+
+    #[test]
+    fn highlights_loop_jumps() {
+        check(
+            r"
+fn foo() {
+    'outer: loop {
+        while true {
+            break<|> 'outer;
+            continue;
+        }
+        for _ in 0..1 {
+            continue 'outer;
+        }
+        break;
+    }
+}",
+            &[("loop", None), ("break", None), ("continue", None), ("break", None)],
+        );
+        check(
+            r"
+fn foo() {
+    loop {
+        while<|> true {
+            let f = || loop { break; };
+            break;
+        }
+    }
+}",
+            &[("while", None), ("break", None)],
+        );
+    }
+
+    #[test]
+    fn highlights_shadowed_locals_separately() {
+        check(
+            r#"
+fn foo(x<|>: u32) {
+    let y = x;
+    let x = x + 1;
+    println!("{x}");
+    x;
+}"#,
+            &[("x", None), ("x", Some(ReferenceAccess::Read)), ("x", Some(ReferenceAccess::Read))],
+        );
+        check(
+            r"
+fn foo(x: u32) {
+    let x<|> = x + 1;
+    if true { let x = 2; x; }
+    let f = |x: u32| x;
+    match x { x => x };
+    x;
+}",
+            &[
+                ("x", Some(ReferenceAccess::Write)),
+                ("x", Some(ReferenceAccess::Read)),
+                ("x", Some(ReferenceAccess::Read)),
+            ],
+        );
+    }
+
+    #[test]
+    fn highlights_drop_of_locals() {
+        check(
+            r"
+struct S;
+fn foo(s: S) {
+    let t<|> = S;
+    let u = &t;
+    if true {
+        let t = S;
+    }
+}",
+            &[("t", Some(ReferenceAccess::Write)), ("t", Some(ReferenceAccess::Read)), ("}", None)],
+        );
+        check(
+            r"
+struct S;
+fn foo(s<|>: S) {
+    s;
+}",
+            &[("s", None), ("s", Some(ReferenceAccess::Read)), ("}", None)],
+        );
+    }
+}
@@ -1,17 +1,23 @@
-use ra_db::SourceDatabase;
+use ra_db::{SourceDatabase, FileId};
 use ra_syntax::{
     AstNode, ast,
     algo::{find_covering_element, find_node_at_offset, find_token_at_offset},
 };
 use hir::HirDisplay;
 
-use crate::{db::RootDatabase, RangeInfo, FilePosition, FileRange, display::{rust_code_markup, doc_text_for}};
+use crate::{
+    db::RootDatabase, RangeInfo, FilePosition, FileRange, NavigationTarget,
+    display::{rust_code_markup, doc_text_for},
+};
 
 /// Contains the results when hovering over an item
 #[derive(Debug, Clone)]
 pub struct HoverResult {
     results: Vec<String>,
     exact: bool,
+    /// The labels of the links to items in the docs of the results, with
+    /// their targets.
+    links: Vec<(String, NavigationTarget)>,
 }
 
 impl HoverResult {
@@ -20,6 +26,7 @@ impl HoverResult {
             results: Vec::new(),
             // We assume exact by default
             exact: true,
+            links: Vec::new(),
         }
     }
 
@@ -65,6 +72,26 @@ impl HoverResult {
 
         markup
     }
+
+    /// Returns the results converted into markup, with the links to items in
+    /// the docs pointing to the URLs given by `url`.
+    pub fn to_markup_with_links(
+        &self,
+        url: impl Fn(&NavigationTarget) -> Option<String>,
+    ) -> String {
+        let mut markup = self.to_markup();
+        let mut labels = Vec::new();
+        for (label, target) in self.links.iter() {
+            if labels.contains(&label) {
+                continue;
+            }
+            if let Some(url) = url(target) {
+                markup.push_str(&format!("\n\n[{}]: {}", label, url));
+                labels.push(label);
+            }
+        }
+        markup
+    }
 }
 
 pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeInfo<HoverResult>> {
@@ -76,13 +103,17 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
         use crate::goto_definition::{ReferenceResult::*, reference_definition};
         let ref_result = reference_definition(db, position.file_id, name_ref);
         match ref_result {
-            Exact(nav) => res.extend(doc_text_for(db, nav)),
+            Exact(nav) => {
+                let text = doc_text_for(db, nav, &mut res.links);
+                res.extend(text)
+            }
             Approximate(navs) => {
                 // We are no longer exact
                 res.exact = false;
 
                 for nav in navs {
-                    res.extend(doc_text_for(db, nav))
+                    let text = doc_text_for(db, nav, &mut res.links);
+                    res.extend(text)
                 }
             }
         }
@@ -94,7 +125,16 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
 
         if let Some(navs) = navs {
             for nav in navs {
-                res.extend(doc_text_for(db, nav))
+                let text = doc_text_for(db, nav, &mut res.links);
+                res.extend(text)
+            }
+        }
+        if let Some((_, trait_item)) = crate::impls::trait_item_at(db, position) {
+            if let (Some(last), Some(label)) =
+                (res.results.last_mut(), trait_item_label(db, trait_item))
+            {
+                last.push_str(&format!("\n\nImplements [{}]", label));
+                res.links.push((label, NavigationTarget::from_trait_item(db, trait_item)));
             }
         }
 
@@ -112,6 +152,9 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
         })?;
         let frange = FileRange { file_id: position.file_id, range: node.range() };
         res.extend(type_of(db, frange).map(rust_code_markup));
+        if let Some(closure) = ast::LambdaExpr::cast(node) {
+            res.extend(closure_captures(db, position.file_id, closure));
+        }
         range = Some(node.range());
     }
 
@@ -123,14 +166,53 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
     Some(res)
 }
 
+/// The list of the bindings a closure captures, with their capture modes.
+fn closure_captures(
+    db: &RootDatabase,
+    file_id: FileId,
+    closure: &ast::LambdaExpr,
+) -> Option<String> {
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, closure.syntax(), None);
+    let captures = analyzer.closure_captures(db, closure);
+    if captures.is_empty() {
+        return None;
+    }
+    let mut res = String::from("Captures:\n");
+    for capture in captures {
+        let kind = match capture.kind {
+            hir::CaptureKind::ByRef => "by reference",
+            hir::CaptureKind::ByMutRef => "by mutable reference",
+            hir::CaptureKind::ByValue => "by value",
+        };
+        res.push_str(&format!("\n- `{}` {}", capture.name, kind));
+    }
+    Some(res)
+}
+
+/// The label of the link to a trait item, like `` `Default::default` ``.
+fn trait_item_label(db: &RootDatabase, trait_item: hir::TraitItem) -> Option<String> {
+    let trait_name = trait_item.parent_trait(db)?.name(db)?;
+    Some(format!("`{}::{}`", trait_name, trait_item.name(db)?))
+}
+
 pub(crate) fn type_of(db: &RootDatabase, frange: FileRange) -> Option<String> {
+    type_of_selection(db, frange, false).map(|it| it.info)
+}
+
+/// The type of the innermost expression or pattern covering the range, with
+/// the range of that expression. The `qualified` form has the full paths to
+/// the items.
+pub(crate) fn type_of_selection(
+    db: &RootDatabase,
+    frange: FileRange,
+    qualified: bool,
+) -> Option<RangeInfo<String>> {
     let file = db.parse(frange.file_id);
     let syntax = file.syntax();
     let leaf_node = find_covering_element(syntax, frange.range);
-    // if we picked identifier, expand to pattern/expression
     let node = leaf_node
         .ancestors()
-        .take_while(|it| it.range() == leaf_node.range())
+        .take_while(|&it| ast::ModuleItem::cast(it).is_none())
         .find(|&it| ast::Expr::cast(it).is_some() || ast::Pat::cast(it).is_some())?;
     let analyzer = hir::SourceAnalyzer::new(db, frange.file_id, node, None);
     let ty = if let Some(ty) = ast::Expr::cast(node).and_then(|e| analyzer.type_of(db, e)) {
@@ -140,7 +222,13 @@ pub(crate) fn type_of(db: &RootDatabase, frange: FileRange) -> Option<String> {
     } else {
         return None;
     };
-    Some(ty.display(db).to_string())
+    let ty = if qualified {
+        let module = hir::source_binder::module_from_child_node(db, frange.file_id, node)?;
+        ty.display_qualified(db, module).to_string()
+    } else {
+        ty.display_with_where_clauses(db).to_string()
+    };
+    Some(RangeInfo::new(node.range(), ty))
 }
 
 #[cfg(test)]
@@ -169,6 +257,43 @@ mod tests {
         assert_eq!(hover.info.len(), expected.len());
     }
 
+    #[test]
+    fn hover_shows_closure_captures() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            struct S { n: u32 }
+            impl S { fn bump(&mut self) {} }
+            fn main() {
+                let x = 1u32;
+                let mut s = S { n: 0 };
+                let mut v = S { n: 0 };
+                let t = S { n: 0 };
+                let f = |<|>y: u32| {
+                    s.bump();
+                    v.n += x + y;
+                    let u = t;
+                    s.n
+                };
+            }
+            "#,
+            &[
+                "|u32| -> u32",
+                "Captures:\n\n- `s` by mutable reference\n- `v` by mutable reference\n- `x` by reference\n- `t` by value",
+            ],
+        );
+        check_hover_result(
+            r#"
+            //- /main.rs
+            fn main() {
+                let x = 1u32;
+                let f = move |<|>| x;
+            }
+            "#,
+            &["|| -> u32", "Captures:\n\n- `x` by value"],
+        );
+    }
+
     #[test]
     fn hover_shows_type_of_an_expression() {
         let (analysis, position) = single_file_with_position(
@@ -289,6 +414,21 @@ mod tests {
         "#,
             &["field_a: u32"],
         );
+
+        // Hovering over the field in a pattern
+        check_hover_result(
+            r#"
+            //- /main.rs
+            struct Foo {
+                field_a: u32,
+            }
+
+            fn main() {
+                let Foo { field_a<|>: a } = Foo { field_a: 0 };
+            }
+        "#,
+            &["field_a: u32"],
+        );
     }
 
     #[test]
@@ -314,6 +454,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hover_resolves_doc_links() {
+        let (analysis, position) = single_file_with_position(
+            "
+            mod foo { pub struct Bar; }
+            /// Makes a [`Bar`](foo::Bar) from a [`Baz`], see [`make`].
+            fn make() {}
+
+            fn main() {
+                ma<|>ke();
+            }
+            ",
+        );
+        let hover = analysis.hover(position).unwrap().unwrap();
+        let markup = hover.info.to_markup_with_links(|nav| Some(format!("{}", nav.name())));
+        assert_eq!(
+            markup,
+            "```rust\nfn make()\n```\n\nMakes a [`Bar`][foo::Bar] from a [`Baz`], see [`make`].\n\n\
+             [foo::Bar]: Bar\n\n[`make`]: make"
+        );
+    }
+
+    #[test]
+    fn hover_links_impl_items_to_trait_items() {
+        let (analysis, position) = single_file_with_position(
+            "
+            trait Shape { fn area(&self) -> u32; }
+            struct Square;
+            impl Shape for Square {
+                fn ar<|>ea(&self) -> u32 { 0 }
+            }
+            ",
+        );
+        let hover = analysis.hover(position).unwrap().unwrap();
+        let markup = hover.info.to_markup_with_links(|nav| Some(format!("{}", nav.name())));
+        assert_eq!(
+            markup,
+            "```rust\nfn area(&self) -> u32\n```\n\nImplements [`Shape::area`]\n\n\
+             [`Shape::area`]: area"
+        );
+    }
+
+    #[test]
+    fn hover_formats_code_blocks_of_docs() {
+        let (analysis, position) = single_file_with_position(
+            "
+            /// Makes one.
+            ///
+            /// ```no_run
+            /// # fn setup() {}
+            /// let x = make();
+            /// ```
+            fn make() {}
+
+            fn main() {
+                ma<|>ke();
+            }
+            ",
+        );
+        let hover = analysis.hover(position).unwrap().unwrap();
+        assert_eq!(
+            hover.info.results(),
+            ["```rust\nfn make()\n```\n\nMakes one.\n\n```rust\nlet x = make();\n```"]
+        );
+    }
+
+    #[test]
+    fn hover_shows_deprecation_note() {
+        let (analysis, position) = single_file_with_position(
+            r#"
+            /// Makes one.
+            #[deprecated(since = "1.2.0", note = "use `make_two`")]
+            fn make() {}
+
+            fn main() {
+                ma<|>ke();
+            }
+            "#,
+        );
+        let hover = analysis.hover(position).unwrap().unwrap();
+        assert_eq!(
+            hover.info.results(),
+            ["```rust\nfn make()\n```\n\n**Deprecated** since 1.2.0: use `make_two`\n\nMakes one."]
+        );
+    }
+
     #[test]
     fn hover_some() {
         let (analysis, position) = single_file_with_position(
@@ -428,6 +654,63 @@ The Some variant
         assert_eq!("usize", &type_name);
     }
 
+    #[test]
+    fn test_type_of_selection_in_chain() {
+        let (analysis, range) = single_file_with_range(
+            "
+            struct S;
+            impl S { fn map(self, f: fn(u32) -> u64) -> u64 { 0 } }
+
+            fn main() {
+                let x = S.m<|>ap(|i<|>| 1).clone();
+            }
+            ",
+        );
+
+        let res = analysis.type_of_selection(range, false).unwrap().unwrap();
+        assert_eq!("u64", &res.info);
+        assert!(range.range.is_subrange(&res.range));
+    }
+
+    #[test]
+    fn test_type_of_selection_closure_param() {
+        let (analysis, range) = single_file_with_range(
+            "
+            struct S;
+            impl S { fn map(self, f: fn(u32) -> u64) -> u64 { 0 } }
+
+            fn main() {
+                let x = S.map(|<|>i<|>| 1);
+            }
+            ",
+        );
+
+        let type_name = analysis.type_of(range).unwrap().unwrap();
+        assert_eq!("u32", &type_name);
+    }
+
+    #[test]
+    fn test_type_of_selection_qualified() {
+        let (analysis, range) = single_file_with_range(
+            "
+            mod foo {
+                pub struct S<T>(T);
+                pub struct Q;
+            }
+            use foo::{S, Q};
+
+            fn main() {
+                let x = <|>S(Q)<|>;
+            }
+            ",
+        );
+
+        let type_name = analysis.type_of_selection(range, true).unwrap().unwrap().info;
+        assert_eq!("crate::foo::S<crate::foo::Q>", &type_name);
+        let type_name = analysis.type_of_selection(range, false).unwrap().unwrap().info;
+        assert_eq!("S<Q>", &type_name);
+    }
+
     #[test]
     fn test_hover_infer_associated_method_result() {
         let (analysis, position) = single_file_with_position(
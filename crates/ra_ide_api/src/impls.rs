@@ -1,11 +1,11 @@
 use ra_db::SourceDatabase;
 use ra_syntax::{
-    AstNode, ast,
+    AstNode, SyntaxNode, TextUnit, ast,
     algo::find_node_at_offset,
 };
 use hir::{db::HirDatabase, source_binder};
 
-use crate::{FilePosition, NavigationTarget, db::RootDatabase, RangeInfo};
+use crate::{FilePosition, NavigationTarget, db::RootDatabase, RangeInfo, TextRange};
 
 pub(crate) fn goto_implementation(
     db: &RootDatabase,
@@ -16,6 +16,14 @@ pub(crate) fn goto_implementation(
 
     let module = source_binder::module_from_position(db, position)?;
 
+    if let Some((name, item, container)) = assoc_item_at(syntax, position.offset) {
+        if let Some(trait_def) = ast::TraitDef::cast(container) {
+            return Some(RangeInfo::new(
+                name.syntax().range(),
+                impls_for_trait_item(db, trait_def, item, module)?,
+            ));
+        }
+    }
     if let Some(nominal_def) = find_node_at_offset::<ast::NominalDef>(syntax, position.offset) {
         return Some(RangeInfo::new(
             nominal_def.syntax().range(),
@@ -31,6 +39,40 @@ pub(crate) fn goto_implementation(
     None
 }
 
+/// The item of the trait implemented by the item of an impl block whose name
+/// is at `position`.
+pub(crate) fn goto_trait_item(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<NavigationTarget>> {
+    let (range, trait_item) = trait_item_at(db, position)?;
+    Some(RangeInfo::new(range, NavigationTarget::from_trait_item(db, trait_item)))
+}
+
+pub(crate) fn trait_item_at(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<(TextRange, hir::TraitItem)> {
+    let file = db.parse(position.file_id);
+    let (name, item, container) = assoc_item_at(file.syntax(), position.offset)?;
+    ast::ImplBlock::cast(container)?;
+    let module = source_binder::module_from_child_node(db, position.file_id, item.syntax())?;
+    let trait_item = source_binder::impl_item_from_module(db, module, item).trait_item(db)?;
+    Some((name.syntax().range(), trait_item))
+}
+
+/// The item of a trait or of an impl block whose name is at `offset`, with
+/// the trait or the impl block.
+fn assoc_item_at(
+    syntax: &SyntaxNode,
+    offset: TextUnit,
+) -> Option<(&ast::Name, &ast::ImplItem, &SyntaxNode)> {
+    let name = find_node_at_offset::<ast::Name>(syntax, offset)?;
+    let item = ast::ImplItem::cast(name.syntax().parent()?)?;
+    let container = item.syntax().parent().and_then(ast::ItemList::cast)?.syntax().parent()?;
+    Some((name, item, container))
+}
+
 fn impls_for_def(
     db: &RootDatabase,
     node: &ast::NominalDef,
@@ -74,6 +116,28 @@ fn impls_for_trait(
     )
 }
 
+fn impls_for_trait_item(
+    db: &RootDatabase,
+    trait_def: &ast::TraitDef,
+    item: &ast::ImplItem,
+    module: hir::Module,
+) -> Option<Vec<NavigationTarget>> {
+    let tr = source_binder::trait_from_module(db, module, trait_def);
+    let trait_item = source_binder::trait_item_from_module(db, module, item);
+
+    let krate = module.krate(db)?;
+    let impls = db.impls_in_crate(krate);
+
+    Some(
+        impls
+            .lookup_impl_blocks_for_trait(&tr)
+            .flat_map(|imp| imp.items(db))
+            .filter(|it| it.trait_item(db) == Some(trait_item))
+            .map(|it| NavigationTarget::from_impl_item(db, it))
+            .collect(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use crate::mock_analysis::analysis_and_position;
@@ -176,4 +240,49 @@ mod tests {
             &["impl IMPL_BLOCK FileId(2) [0; 31)", "impl IMPL_BLOCK FileId(3) [0; 31)"],
         );
     }
+
+    #[test]
+    fn goto_implementation_for_trait_items() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait T { fn f<|>oo(&self); const C: u32; }
+            struct Foo;
+            impl T for Foo { fn foo(&self) {} const C: u32 = 0; }
+            struct Bar;
+            impl T for Bar { const C: u32 = 1; fn foo(&self) {} }
+            impl Bar { fn foo(&self) {} }
+            ",
+            &[
+                "foo FN_DEF FileId(1) [70; 86) [73; 76)",
+                "foo FN_DEF FileId(1) [154; 170) [157; 160)",
+            ],
+        );
+    }
+
+    #[test]
+    fn goto_trait_item_works() {
+        let (analysis, pos) = analysis_and_position(
+            "
+            //- /lib.rs
+            trait T { fn foo(&self); type Out; }
+            struct Foo;
+            impl T for Foo { fn foo(&self) {} type O<|>ut = (); }
+            ",
+        );
+        let nav = analysis.goto_trait_item(pos).unwrap().unwrap().info;
+        nav.assert_match("Out TYPE_ALIAS_DEF FileId(1) [25; 34) [30; 33)");
+    }
+
+    #[test]
+    fn goto_trait_item_is_none_for_inherent_impls() {
+        let (analysis, pos) = analysis_and_position(
+            "
+            //- /lib.rs
+            struct Foo;
+            impl Foo { fn f<|>oo(&self) {} }
+            ",
+        );
+        assert!(analysis.goto_trait_item(pos).unwrap().is_none());
+    }
 }
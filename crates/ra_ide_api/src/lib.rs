@@ -25,6 +25,10 @@ mod call_info;
 mod syntax_highlighting;
 mod parent_module;
 mod references;
+mod format_string;
+mod doc_links;
+mod doc_code;
+mod highlight_related;
 mod impls;
 mod assists;
 mod diagnostics;
@@ -33,8 +37,11 @@ mod folding_ranges;
 mod line_index;
 mod line_index_utils;
 mod join_lines;
+mod move_item;
 mod typing;
 mod matching_brace;
+mod organize_imports;
+mod ssr;
 mod display;
 
 #[cfg(test)]
@@ -53,27 +60,32 @@ use ra_db::{
 use relative_path::RelativePathBuf;
 
 use crate::{
-    symbol_index::FileSymbol,
+    symbol_index::{FileSymbol, SymbolsDatabase},
     db::LineIndexDatabase,
 };
 
 pub use crate::{
     change::{AnalysisChange, LibraryData},
-    completion::{CompletionItem, CompletionItemKind, InsertTextFormat},
+    completion::{
+        CompletionItem, CompletionItemKind, CompletionOptions, CompletionRelevance,
+        InsertTextFormat, Locality,
+    },
     runnables::{Runnable, RunnableKind},
-    references::ReferenceSearchResult,
-    assists::{Assist, AssistId},
+    references::{ReferenceFilter, ReferenceSearchResult, SearchScope},
+    highlight_related::{RelatedRange, ReferenceAccess},
+    assists::{Assist, AssistId, AssistLabel, GroupLabel},
     hover::{HoverResult},
-    line_index::{LineIndex, LineCol},
+    line_index::{LineIndex, LineCol, LineColUtf8},
     line_index_utils::translate_offset_with_edit,
     folding_ranges::{Fold, FoldKind},
+    move_item::Direction,
     syntax_highlighting::HighlightedRange,
     diagnostics::Severity,
     display::{FunctionSignature, NavigationTarget, StructureNode, file_structure},
 };
 
 pub use ra_db::{
-    Canceled, CrateGraph, CrateId, FileId, FilePosition, FileRange, SourceRootId,
+    Canceled, CfgOptions, CrateGraph, CrateId, FileId, FilePosition, FileRange, SourceRootId,
     Edition
 };
 pub use hir::Documentation;
@@ -175,18 +187,41 @@ pub struct SourceFileEdit {
 
 #[derive(Debug)]
 pub enum FileSystemEdit {
-    CreateFile { source_root: SourceRootId, path: RelativePathBuf },
-    MoveFile { src: FileId, dst_source_root: SourceRootId, dst_path: RelativePathBuf },
+    /// Creates a new file, filled with `text`.
+    CreateFile {
+        source_root: SourceRootId,
+        path: RelativePathBuf,
+        text: String,
+    },
+    MoveFile {
+        src: FileId,
+        dst_source_root: SourceRootId,
+        dst_path: RelativePathBuf,
+    },
 }
 
 #[derive(Debug)]
 pub struct Diagnostic {
     pub message: String,
+    /// The name of the diagnostic, `None` for syntax errors.
+    pub code: Option<&'static str>,
     pub range: TextRange,
-    pub fix: Option<SourceChange>,
+    /// The quick fixes of the diagnostic, the preferred one first.
+    pub fixes: Vec<SourceChange>,
     pub severity: Severity,
 }
 
+impl Diagnostic {
+    /// Whether the diagnostic is about code which can be removed, which
+    /// editors render faded out.
+    pub fn is_unnecessary(&self) -> bool {
+        match self.code {
+            Some("dead_code") | Some("unused_variables") | Some("unused_imports") => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Query {
     query: String,
@@ -271,6 +306,12 @@ impl AnalysisHost {
     pub fn collect_garbage(&mut self) {
         self.db.collect_garbage();
     }
+    /// The memory retained by each query, which is only measured if the global
+    /// allocator is jemalloc or `ra_prof::CountingAlloc`. This discards the
+    /// values of the queries.
+    pub fn per_query_memory_usage(&mut self) -> Vec<(String, ra_prof::Bytes)> {
+        status::per_query_memory_usage(&mut self.db)
+    }
 }
 
 /// Analysis is a snapshot of a world state at a moment in time. It is the main
@@ -356,6 +397,15 @@ impl Analysis {
         SourceChange::source_file_edit("join lines", file_edit)
     }
 
+    /// Returns an edit to swap the item, statement or match arm at point with
+    /// its sibling in `direction`, moving the cursor along with it.
+    pub fn move_item(&self, position: FilePosition, direction: Direction) -> Option<SourceChange> {
+        let file = self.db.parse(position.file_id);
+        let (edit, offset) = move_item::move_item(&file, position.offset, direction)?;
+        let change = SourceChange::source_file_edit_from("move item", position.file_id, edit);
+        Some(change.with_cursor(FilePosition { file_id: position.file_id, offset }))
+    }
+
     /// Returns an edit which should be applied when opening a new line, fixing
     /// up minor stuff like continuing the comment.
     pub fn on_enter(&self, position: FilePosition) -> Option<SourceChange> {
@@ -392,6 +442,23 @@ impl Analysis {
         folding_ranges::folding_ranges(&file)
     }
 
+    /// The files of the local source roots, like the members of the workspace.
+    pub fn local_files(&self) -> Cancelable<Vec<FileId>> {
+        self.with_db(|db| {
+            db.local_roots()
+                .iter()
+                .flat_map(|&root| db.source_root(root).files.values().cloned().collect::<Vec<_>>())
+                .collect()
+        })
+    }
+
+    /// Computes the symbols of a file ahead of the first symbol search.
+    pub fn prime_symbols(&self, file_id: FileId) -> Cancelable<()> {
+        self.with_db(|db| {
+            db.file_symbols(file_id);
+        })
+    }
+
     /// Fuzzy searches for a symbol.
     pub fn symbol_search(&self, query: Query) -> Cancelable<Vec<NavigationTarget>> {
         self.with_db(|db| {
@@ -416,6 +483,15 @@ impl Analysis {
         self.with_db(|db| impls::goto_implementation(db, position))
     }
 
+    /// Returns the item of the trait implemented by the item of an impl block
+    /// at the position.
+    pub fn goto_trait_item(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<RangeInfo<NavigationTarget>>> {
+        self.with_db(|db| impls::goto_trait_item(db, position))
+    }
+
     /// Finds all usages of the reference at point.
     pub fn find_all_refs(
         &self,
@@ -424,6 +500,24 @@ impl Analysis {
         self.with_db(|db| references::find_all_refs(db, position))
     }
 
+    /// Finds the usages of the reference at point which pass `filter`.
+    pub fn find_refs(
+        &self,
+        position: FilePosition,
+        filter: ReferenceFilter,
+    ) -> Cancelable<Option<ReferenceSearchResult>> {
+        self.with_db(|db| references::find_refs(db, position, filter))
+    }
+
+    /// Finds the ranges to highlight along with the element at point: the
+    /// references to it, or the exit points of a function or the jumps of a loop.
+    pub fn highlight_related(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<Vec<RelatedRange>>> {
+        self.with_db(|db| highlight_related::highlight_related(db, position))
+    }
+
     /// Returns a short text describing element at position.
     pub fn hover(&self, position: FilePosition) -> Cancelable<Option<RangeInfo<HoverResult>>> {
         self.with_db(|db| hover::hover(db, position))
@@ -460,8 +554,12 @@ impl Analysis {
     }
 
     /// Computes completions at the given position.
-    pub fn completions(&self, position: FilePosition) -> Cancelable<Option<Vec<CompletionItem>>> {
-        self.with_db(|db| completion::completions(db, position).map(Into::into))
+    pub fn completions(
+        &self,
+        position: FilePosition,
+        options: &CompletionOptions,
+    ) -> Cancelable<Option<Vec<CompletionItem>>> {
+        self.with_db(|db| completion::completions(db, position, options).map(Into::into))
     }
 
     /// Computes assists (aks code actons aka intentions) for the given
@@ -470,16 +568,59 @@ impl Analysis {
         self.with_db(|db| assists::assists(db, frange))
     }
 
+    /// Computes the labels of the assists applicable at the given position,
+    /// without computing their edits.
+    pub fn assist_labels(&self, frange: FileRange) -> Cancelable<Vec<AssistLabel>> {
+        self.with_db(|db| assists::assist_labels(db, frange))
+    }
+
+    /// Computes the edit of the assist identified by `id` and `label`, as
+    /// returned by `assist_labels`, if it still applies.
+    pub fn resolve_assist(
+        &self,
+        frange: FileRange,
+        id: &str,
+        label: &str,
+    ) -> Cancelable<Option<Assist>> {
+        self.with_db(|db| assists::resolve_assist(db, frange, id, label))
+    }
+
     /// Computes the set of diagnostics for the given file.
     pub fn diagnostics(&self, file_id: FileId) -> Cancelable<Vec<Diagnostic>> {
         self.with_db(|db| diagnostics::diagnostics(db, file_id))
     }
 
+    /// Sorts, groups and merges the `use` items of the file and removes the
+    /// unused imports. Returns `None` if they are already organized.
+    pub fn organize_imports(&self, file_id: FileId) -> Cancelable<Option<SourceChange>> {
+        self.with_db(|db| organize_imports::organize_imports(db, file_id))
+    }
+
     /// Computes the type of the expression at the given position.
     pub fn type_of(&self, frange: FileRange) -> Cancelable<Option<String>> {
         self.with_db(|db| hover::type_of(db, frange))
     }
 
+    /// Computes the type of the innermost expression covering the range,
+    /// returning its range as well. The `qualified` form of the type has the
+    /// full paths to the items.
+    pub fn type_of_selection(
+        &self,
+        frange: FileRange,
+        qualified: bool,
+    ) -> Cancelable<Option<RangeInfo<String>>> {
+        self.with_db(|db| hover::type_of_selection(db, frange, qualified))
+    }
+
+    /// Returns the range and the text of the identifier to rename at the
+    /// position, or a message explaining why it can't be renamed.
+    pub fn prepare_rename(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Result<RangeInfo<String>, String>> {
+        self.with_db(|db| references::prepare_rename(db, position))
+    }
+
     /// Returns the edit required to rename reference at the position to the new
     /// name.
     pub fn rename(
@@ -490,6 +631,16 @@ impl Analysis {
         self.with_db(|db| references::rename(db, position, new_name))
     }
 
+    /// Applies the structural search and replace rule `query`, like
+    /// `foo($a, $b) ==>> bar($b, $a)`, to the files of the workspace. Returns
+    /// a message explaining why the rule is invalid otherwise.
+    pub fn structural_search_replace(
+        &self,
+        query: &str,
+    ) -> Cancelable<Result<SourceChange, String>> {
+        self.with_db(|db| ssr::structural_search_replace(db, query))
+    }
+
     fn with_db<F: FnOnce(&db::RootDatabase) -> T + std::panic::UnwindSafe, T>(
         &self,
         f: F,
@@ -6,6 +6,7 @@ use superslice::Ext;
 pub struct LineIndex {
     pub(crate) newlines: Vec<TextUnit>,
     pub(crate) utf16_lines: FxHashMap<u32, Vec<Utf16Char>>,
+    len: TextUnit,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -14,6 +15,14 @@ pub struct LineCol {
     pub col_utf16: u32,
 }
 
+/// A position whose column is counted in bytes, for the clients which use
+/// UTF-8 offsets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LineColUtf8 {
+    pub line: u32,
+    pub col: u32,
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub(crate) struct Utf16Char {
     pub(crate) start: TextUnit,
@@ -24,6 +33,16 @@ impl Utf16Char {
     fn len(&self) -> TextUnit {
         self.end - self.start
     }
+
+    /// The number of UTF-16 code units of the character, two for the ones
+    /// outside of the basic multilingual plane.
+    pub(crate) fn len_utf16(&self) -> TextUnit {
+        if self.len() == TextUnit::from(4) {
+            TextUnit::from(2)
+        } else {
+            TextUnit::from(1)
+        }
+    }
 }
 
 impl LineIndex {
@@ -65,7 +84,7 @@ impl LineIndex {
             utf16_lines.insert(line, utf16_chars);
         }
 
-        LineIndex { newlines, utf16_lines }
+        LineIndex { newlines, utf16_lines, len: curr_row }
     }
 
     pub fn line_col(&self, offset: TextUnit) -> LineCol {
@@ -82,12 +101,52 @@ impl LineIndex {
         self.newlines[line_col.line as usize] + col
     }
 
+    pub fn line_col_utf8(&self, offset: TextUnit) -> LineColUtf8 {
+        let line = self.newlines.upper_bound(&offset) - 1;
+        let col = offset - self.newlines[line];
+        LineColUtf8 { line: line as u32, col: col.to_usize() as u32 }
+    }
+
+    /// Positions past the end of a line or of the text are clamped to it, and
+    /// the ones inside of a character to its start.
+    pub fn offset_utf8(&self, line_col: LineColUtf8) -> TextUnit {
+        let line = (line_col.line as usize).min(self.newlines.len() - 1);
+        let line_start = self.newlines[line];
+        let line_end = match self.newlines.get(line + 1) {
+            Some(&next) => next - TextUnit::of_char('\n'),
+            None => self.len,
+        };
+        let mut col = TextUnit::from(line_col.col).min(line_end - line_start);
+        if let Some(utf16_chars) = self.utf16_lines.get(&(line as u32)) {
+            if let Some(c) = utf16_chars.iter().find(|c| c.start < col && col < c.end) {
+                col = c.start;
+            }
+        }
+        line_start + col
+    }
+
+    /// The offset of a position whose column is counted in chars, like in the
+    /// spans of rustc.
+    pub fn offset_of_char_col(&self, line: u32, col: u32) -> TextUnit {
+        let mut col = TextUnit::from(col);
+        if let Some(utf16_chars) = self.utf16_lines.get(&line) {
+            for c in utf16_chars {
+                if col > c.start {
+                    col += c.len() - TextUnit::from(1);
+                } else {
+                    break;
+                }
+            }
+        }
+        self.offset_utf8(LineColUtf8 { line, col: col.to_usize() as u32 })
+    }
+
     fn utf8_to_utf16_col(&self, line: u32, mut col: TextUnit) -> usize {
         if let Some(utf16_chars) = self.utf16_lines.get(&line) {
             let mut correction = TextUnit::from_usize(0);
             for c in utf16_chars {
                 if col >= c.end {
-                    correction += c.len() - TextUnit::from_usize(1);
+                    correction += c.len() - c.len_utf16();
                 } else {
                     // From here on, all utf16 characters come *after* the character we are mapping,
                     // so we don't need to take them into account
@@ -106,7 +165,7 @@ impl LineIndex {
         if let Some(utf16_chars) = self.utf16_lines.get(&line) {
             for c in utf16_chars {
                 if col >= c.start {
-                    col += c.len() - TextUnit::from_usize(1);
+                    col += c.len() - c.len_utf16();
                 } else {
                     // From here on, all utf16 characters come *after* the character we are mapping,
                     // so we don't need to take them into account
@@ -133,7 +192,7 @@ pub fn to_line_col(text: &str, offset: TextUnit) -> LineCol {
             res.line += 1;
             res.col_utf16 = 0;
         } else {
-            res.col_utf16 += 1;
+            res.col_utf16 += c.len_utf16() as u32;
         }
     }
     res
@@ -168,6 +227,40 @@ mod test_line_index {
         assert_eq!(index.line_col(7.into()), LineCol { line: 2, col_utf16: 0 });
     }
 
+    #[test]
+    fn test_line_col_utf8() {
+        let text = "fn main() {\n    \"メ\"; x\n}";
+        let index = LineIndex::new(text);
+        let offset = TextUnit::from_usize(text.find('x').unwrap());
+        assert_eq!(index.line_col(offset), LineCol { line: 1, col_utf16: 9 });
+        assert_eq!(index.line_col_utf8(offset), LineColUtf8 { line: 1, col: 11 });
+        assert_eq!(index.offset_utf8(LineColUtf8 { line: 1, col: 11 }), offset);
+    }
+
+    #[test]
+    fn test_offset_utf8_is_clamped() {
+        let text = "fn main() {\n    \"メ\"; x\n}";
+        let index = LineIndex::new(text);
+        let line_end = TextUnit::from_usize(text.rfind('\n').unwrap());
+        assert_eq!(index.offset_utf8(LineColUtf8 { line: 1, col: 100 }), line_end);
+        assert_eq!(
+            index.offset_utf8(LineColUtf8 { line: 5, col: 0 }),
+            TextUnit::of_str(text) - TextUnit::from(1)
+        );
+        assert_eq!(index.offset_utf8(LineColUtf8 { line: 5, col: 10 }), TextUnit::of_str(text));
+        let in_char = LineColUtf8 { line: 1, col: 6 };
+        assert_eq!(index.offset_utf8(in_char), TextUnit::from_usize(text.find('メ').unwrap()));
+    }
+
+    #[test]
+    fn test_offset_of_char_col() {
+        let text = "fn main() {\n    \"メ\"; x\n}";
+        let index = LineIndex::new(text);
+        let offset = TextUnit::from_usize(text.find('x').unwrap());
+        assert_eq!(index.offset_of_char_col(1, 9), offset);
+        assert_eq!(index.offset_of_char_col(1, 5), TextUnit::from_usize(text.find('メ').unwrap()));
+    }
+
     fn arb_text_with_offset() -> BoxedStrategy<(TextUnit, String)> {
         arb_text().prop_flat_map(|text| (arb_offset(&text), Just(text))).boxed()
     }
@@ -184,7 +277,7 @@ mod test_line_index {
                 res.line += 1;
                 res.col_utf16 = 0;
             } else {
-                res.col_utf16 += 1;
+                res.col_utf16 += c.len_utf16() as u32;
             }
         }
         res
@@ -277,4 +370,13 @@ const C: char = \"メ メ\";
         assert_eq!(col_index.utf16_to_utf8_col(2, 15), TextUnit::from_usize(15));
     }
 
+    #[test]
+    fn test_surrogate_pair() {
+        let col_index = LineIndex::new("let s = \"🦀 x\";");
+        assert_eq!(col_index.utf16_lines[&0][0], Utf16Char { start: 9.into(), end: 13.into() });
+
+        // The crab is two UTF-16 code units.
+        assert_eq!(col_index.utf8_to_utf16_col(0, 14.into()), 12);
+        assert_eq!(col_index.utf16_to_utf8_col(0, 12), TextUnit::from(14));
+    }
 }
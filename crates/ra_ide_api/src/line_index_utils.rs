@@ -215,7 +215,10 @@ impl RunningLineCol {
     }
 
     fn adjust_col(&mut self, range: &TextRange) {
-        self.col_adjust += range.len() - TextUnit::from(1);
+        // Characters outside of the basic multilingual plane are two UTF-16
+        // code units.
+        let len_utf16 = if range.len() == TextUnit::from(4) { 2 } else { 1 };
+        self.col_adjust += range.len() - TextUnit::from(len_utf16);
     }
 }
 
@@ -3,6 +3,7 @@ test_utils::marks!(
     goto_definition_works_for_methods
     goto_definition_works_for_fields
     goto_definition_works_for_named_fields
+    goto_definition_works_for_field_pats
     call_info_bad_offset
     dont_complete_current_use
 );
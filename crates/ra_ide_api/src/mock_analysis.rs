@@ -1,15 +1,22 @@
 use std::sync::Arc;
 
 use relative_path::RelativePathBuf;
-use test_utils::{extract_offset, extract_range, parse_fixture, CURSOR_MARKER};
+use test_utils::{extract_offset, extract_range, parse_fixture, FixtureEntry, CURSOR_MARKER};
 
-use crate::{Analysis, AnalysisChange, AnalysisHost, CrateGraph, FileId, FilePosition, FileRange, SourceRootId, Edition::Edition2018};
+use crate::{
+    Analysis, AnalysisChange, AnalysisHost, CrateGraph, FileId, FilePosition, FileRange,
+    SourceRootId,
+    Edition::{self, Edition2018},
+};
 
 /// Mock analysis is used in test to bootstrap an AnalysisHost/Analysis
 /// from a set of in-memory files.
 #[derive(Debug, Default)]
 pub struct MockAnalysis {
     files: Vec<(String, String)>,
+    /// The crates declared in the fixture, with their roots, editions,
+    /// versions and dependencies, as `name=crate` for renamed ones.
+    crates: Vec<(String, FileId, Edition, Option<String>, Vec<String>)>,
 }
 
 impl MockAnalysis {
@@ -26,10 +33,15 @@ impl MockAnalysis {
     /// //- /foo.rs
     /// struct Baz;
     /// ```
+    ///
+    /// The crates are the `/lib.rs` or `/main.rs` file, which depends on the
+    /// crates of the other `lib.rs` files, unless the crates are declared in
+    /// the metas, like `//- /main.rs crate:main deps:foo`.
     pub fn with_files(fixture: &str) -> MockAnalysis {
         let mut res = MockAnalysis::new();
         for entry in parse_fixture(fixture) {
-            res.add_file(&entry.meta, &entry.text);
+            let file_id = res.add_file(entry.path(), &entry.text);
+            res.add_crate_from_meta(&entry, file_id);
         }
         res
     }
@@ -40,17 +52,29 @@ impl MockAnalysis {
         let mut position = None;
         let mut res = MockAnalysis::new();
         for entry in parse_fixture(fixture) {
-            if entry.text.contains(CURSOR_MARKER) {
+            let file_id = if entry.text.contains(CURSOR_MARKER) {
                 assert!(position.is_none(), "only one marker (<|>) per fixture is allowed");
-                position = Some(res.add_file_with_position(&entry.meta, &entry.text));
+                let file_position = res.add_file_with_position(entry.path(), &entry.text);
+                position = Some(file_position);
+                file_position.file_id
             } else {
-                res.add_file(&entry.meta, &entry.text);
-            }
+                res.add_file(entry.path(), &entry.text)
+            };
+            res.add_crate_from_meta(&entry, file_id);
         }
         let position = position.expect("expected a marker (<|>)");
         (res, position)
     }
 
+    fn add_crate_from_meta(&mut self, entry: &FixtureEntry, file_id: FileId) {
+        if let Some(crate_name) = entry.crate_name() {
+            let edition = entry.edition().map_or(Edition2018, Edition::from_string);
+            let version = entry.version().map(String::from);
+            let deps = entry.deps().into_iter().map(String::from).collect();
+            self.crates.push((crate_name.to_string(), file_id, edition, version, deps));
+        }
+    }
+
     pub fn add_file(&mut self, path: &str, text: &str) -> FileId {
         let file_id = FileId((self.files.len() + 1) as u32);
         self.files.push((path.to_string(), text.to_string()));
@@ -84,11 +108,14 @@ impl MockAnalysis {
         change.add_root(source_root, true);
         let mut crate_graph = CrateGraph::default();
         let mut root_crate = None;
+        let declared_crates = !self.crates.is_empty();
         for (i, (path, contents)) in self.files.into_iter().enumerate() {
             assert!(path.starts_with('/'));
             let path = RelativePathBuf::from_path(&path[1..]).unwrap();
             let file_id = FileId(i as u32 + 1);
-            if path == "/lib.rs" || path == "/main.rs" {
+            if declared_crates {
+                // the crates are added below
+            } else if path == "/lib.rs" || path == "/main.rs" {
                 root_crate = Some(crate_graph.add_crate_root(file_id, Edition2018));
             } else if path.ends_with("/lib.rs") {
                 let other_crate = crate_graph.add_crate_root(file_id, Edition2018);
@@ -99,6 +126,29 @@ impl MockAnalysis {
             }
             change.add_file(source_root, file_id, path, Arc::new(contents));
         }
+        let crate_ids: Vec<_> = self
+            .crates
+            .iter()
+            .map(|(_, root, edition, version, _)| {
+                let crate_id = crate_graph.add_crate_root(*root, *edition);
+                if let Some(version) = version {
+                    crate_graph.set_version(crate_id, version.as_str().into());
+                }
+                crate_id
+            })
+            .collect();
+        for (from, (_, _, _, _, deps)) in crate_ids.iter().zip(self.crates.iter()) {
+            for dep in deps {
+                let mut parts = dep.splitn(2, '=');
+                let name = parts.next().unwrap();
+                let krate = parts.next().unwrap_or(name);
+                let to = match self.crates.iter().position(|(it, _, _, _, _)| it == krate) {
+                    Some(idx) => crate_ids[idx],
+                    None => panic!("unknown crate in the fixture: {}", krate),
+                };
+                crate_graph.add_dep(*from, name.into(), to).unwrap();
+            }
+        }
         change.set_crate_graph(crate_graph);
         host.apply_change(change);
         host
@@ -0,0 +1,243 @@
+use ra_syntax::{
+    SourceFile, SyntaxElement, SyntaxNode, TextRange, TextUnit,
+    SyntaxKind::*,
+    algo::find_token_at_offset,
+    ast::{self, AstNode},
+};
+use ra_text_edit::{TextEdit, TextEditBuilder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+/// Swaps the item, statement or match arm at `offset` with its previous or
+/// next sibling, along with their attributes and comments. Returns the edit and
+/// the new offset of the cursor.
+pub fn move_item(
+    file: &SourceFile,
+    offset: TextUnit,
+    direction: Direction,
+) -> Option<(TextEdit, TextUnit)> {
+    let node = find_token_at_offset(file.syntax(), offset)
+        .find_map(|token| token.parent().ancestors().find(|it| is_movable(it)))?;
+    let sibling = match direction {
+        Direction::Up => node.prev_sibling(),
+        Direction::Down => node.next_sibling(),
+    }?;
+    if !is_movable(sibling) {
+        return None;
+    }
+    let (first, second) = match direction {
+        Direction::Up => (sibling, node),
+        Direction::Down => (node, sibling),
+    };
+
+    let first_range = range_with_comments(first);
+    let second_range = range_with_comments(second);
+    let mut first_text = file.syntax().text().slice(first_range).to_string();
+    let mut second_text = file.syntax().text().slice(second_range).to_string();
+    if needs_comma(first, second) {
+        second_text.push(',');
+    }
+    if needs_comma(second, first) {
+        first_text.push(',');
+    }
+
+    let cursor = match direction {
+        Direction::Up => first_range.start() + (offset - second_range.start()),
+        Direction::Down => {
+            second_range.start() + TextUnit::of_str(&second_text) - first_range.len()
+                + (offset - first_range.start())
+        }
+    };
+    let mut edit = TextEditBuilder::default();
+    edit.replace(first_range, second_text);
+    edit.replace(second_range, first_text);
+    Some((edit.finish(), cursor))
+}
+
+fn is_movable(node: &SyntaxNode) -> bool {
+    match node.kind() {
+        LET_STMT | EXPR_STMT | MATCH_ARM => true,
+        _ => match node.parent() {
+            Some(parent) if parent.kind() == SOURCE_FILE || parent.kind() == ITEM_LIST => {
+                ast::ModuleItem::cast(node).is_some()
+                    || ast::ImplItem::cast(node).is_some()
+                    || node.kind() == MACRO_CALL
+            }
+            _ => false,
+        },
+    }
+}
+
+/// Whether the match arm taking the place of `arm` needs a comma, because
+/// there is none after `arm` and the arm taking its place isn't a block.
+fn needs_comma(arm: &SyntaxNode, new_arm: &SyntaxNode) -> bool {
+    let new_arm = match ast::MatchArm::cast(new_arm) {
+        Some(it) => it,
+        None => return false,
+    };
+    let has_comma = arm
+        .siblings_with_tokens(ra_syntax::Direction::Next)
+        .skip(1)
+        .find(|it| !it.kind().is_trivia())
+        .map_or(false, |it| it.kind() == COMMA);
+    let is_last = arm.next_sibling().is_none();
+    let is_block = new_arm.expr().map_or(false, |it| it.syntax().kind() == BLOCK_EXPR);
+    !has_comma && !is_last && !is_block
+}
+
+/// The range of `node`, extended to the comments right above it, which are
+/// not already part of items.
+fn range_with_comments(node: &SyntaxNode) -> TextRange {
+    let mut start = node.range().start();
+    let mut element = node.prev_sibling_or_token();
+    while let Some(SyntaxElement::Token(token)) = element {
+        match token.kind() {
+            COMMENT => start = token.range().start(),
+            WHITESPACE if !token.text().contains("\n\n") => (),
+            _ => break,
+        }
+        element = token.prev_sibling_or_token();
+    }
+    TextRange::from_to(start, node.range().end())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::{add_cursor, assert_eq_text, extract_offset};
+
+    use super::*;
+
+    fn check(direction: Direction, before: &str, after: &str) {
+        let (offset, before) = extract_offset(before);
+        let file = SourceFile::parse(&before);
+        let (edit, cursor) = move_item(&file, offset, direction).expect("can't move the item");
+        let actual = add_cursor(&edit.apply(&before), cursor);
+        assert_eq_text!(after, &actual);
+    }
+
+    #[test]
+    fn moves_items_with_their_comments() {
+        check(
+            Direction::Up,
+            r"
+/// Foo.
+struct Foo;
+
+// Bar.
+#[inline]
+fn bar() {
+    <|>1
+}
+",
+            r"
+// Bar.
+#[inline]
+fn bar() {
+    <|>1
+}
+
+/// Foo.
+struct Foo;
+",
+        );
+        check(
+            Direction::Down,
+            r"
+impl Foo {
+    // Foo.
+    fn f<|>oo() {}
+    // Bar.
+    fn bar() {}
+}
+",
+            r"
+impl Foo {
+    // Bar.
+    fn bar() {}
+    // Foo.
+    fn f<|>oo() {}
+}
+",
+        );
+    }
+
+    #[test]
+    fn moves_statements() {
+        check(
+            Direction::Down,
+            r"
+fn foo() {
+    let x<|> = 1;
+    // y
+    bar(y);
+    x
+}
+",
+            r"
+fn foo() {
+    // y
+    bar(y);
+    let x<|> = 1;
+    x
+}
+",
+        );
+    }
+
+    #[test]
+    fn does_not_move_past_tail_expression() {
+        let (offset, before) = extract_offset("fn foo() { let x<|> = 1; x }");
+        let file = SourceFile::parse(&before);
+        assert!(move_item(&file, offset, Direction::Down).is_none());
+    }
+
+    #[test]
+    fn moves_match_arms() {
+        check(
+            Direction::Up,
+            r"
+fn foo() {
+    match x {
+        A => {}
+        B<|> => 1,
+        C => 2
+    }
+}
+",
+            r"
+fn foo() {
+    match x {
+        B<|> => 1,
+        A => {},
+        C => 2
+    }
+}
+",
+        );
+        check(
+            Direction::Down,
+            r"
+fn foo() {
+    match x {
+        A => {}
+        B<|> => 1,
+        C => 2
+    }
+}
+",
+            r"
+fn foo() {
+    match x {
+        A => {}
+        C => 2,
+        B<|> => 1
+    }
+}
+",
+        );
+    }
+}
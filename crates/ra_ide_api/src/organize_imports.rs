@@ -0,0 +1,537 @@
+//! Organizes the `use` items of a file: they are sorted in groups of imports
+//! from the standard library, from other crates and from the current crate,
+//! the ones with the same prefix are merged, duplicates are dropped and the
+//! private imports which are not used are removed.
+//!
+//! An import is considered unused only if its name doesn't appear anywhere in
+//! the file, nor in the files of its child modules, and if it doesn't import a
+//! trait, whose methods might be used. `use` items with attributes or comments
+//! are left alone.
+
+use hir::{Either, ModuleDef, PerNs, source_binder};
+use itertools::Itertools;
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    AstNode, SourceFile, SyntaxElement, SyntaxNode, TextRange, TextUnit,
+    SyntaxKind::{COMMENT, IDENT, WHITESPACE},
+    ast::{self, AttrsOwner, ModuleItemOwner, VisibilityOwner},
+};
+use ra_text_edit::TextEditBuilder;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{FileId, SourceChange, db::RootDatabase};
+
+const STD_CRATES: &[&str] = &["std", "core", "alloc", "proc_macro", "test"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Group {
+    Std,
+    External,
+    Crate,
+}
+
+/// A single imported name, like `b as c` in `use a::{b as c, d};`.
+#[derive(Debug)]
+struct Import {
+    visibility: Option<String>,
+    prefix: Vec<String>,
+    /// The last segment with its alias, `self` or `*` included.
+    leaf: String,
+    tree: TextRange,
+}
+
+impl Import {
+    fn group(&self) -> Group {
+        let first = self.prefix.first().unwrap_or(&self.leaf);
+        let first = first.trim_start_matches("::");
+        if STD_CRATES.contains(&first) {
+            Group::Std
+        } else if ["crate", "self", "super"].contains(&first) {
+            Group::Crate
+        } else {
+            Group::External
+        }
+    }
+}
+
+pub(crate) fn organize_imports(db: &RootDatabase, file_id: FileId) -> Option<SourceChange> {
+    let file = db.parse(file_id);
+    let text = db.file_text(file_id);
+    let used_names = used_names(db, file_id, &file);
+    let mut edit = TextEditBuilder::default();
+    let module = source_binder::module_from_file_id(db, file_id);
+    let mut changed = organize_items(db, module, &*file, &text, &used_names, &mut edit);
+    for inline in file.syntax().descendants().filter_map(ast::Module::cast) {
+        if let Some(items) = inline.item_list() {
+            let module = source_binder::module_from_child_node(db, file_id, items.syntax());
+            changed |= organize_items(db, module, items, &text, &used_names, &mut edit);
+        }
+    }
+    if !changed {
+        return None;
+    }
+    Some(SourceChange::source_file_edit_from("organize imports", file_id, edit.finish()))
+}
+
+/// The ranges of the use trees of the file which import unused names, with
+/// these names.
+pub(crate) fn unused_imports(db: &RootDatabase, file_id: FileId) -> Vec<(TextRange, String)> {
+    let file = db.parse(file_id);
+    let used_names = used_names(db, file_id, &file);
+    let mut res = Vec::new();
+    let module = source_binder::module_from_file_id(db, file_id);
+    add_unused_imports(db, module, &*file, &used_names, &mut res);
+    for inline in file.syntax().descendants().filter_map(ast::Module::cast) {
+        if let Some(items) = inline.item_list() {
+            let module = source_binder::module_from_child_node(db, file_id, items.syntax());
+            add_unused_imports(db, module, items, &used_names, &mut res);
+        }
+    }
+    res
+}
+
+fn add_unused_imports(
+    db: &RootDatabase,
+    module: Option<hir::Module>,
+    items: &impl ModuleItemOwner,
+    used_names: &FxHashSet<String>,
+    acc: &mut Vec<(TextRange, String)>,
+) {
+    let mut imports = Vec::new();
+    for item in items.items() {
+        if let ast::ModuleItemKind::UseItem(use_item) = item.kind() {
+            let visibility = use_item.visibility().map(|it| it.syntax().text().to_string());
+            if let Some(tree) = use_item.use_tree() {
+                flatten(tree, &visibility, &[], &mut imports);
+            }
+        }
+    }
+    let imported_names = module.map(|it| imported_names(db, it)).unwrap_or_default();
+    for import in imports {
+        if is_unused(&import, &imported_names, used_names) {
+            acc.push((import.tree, imported_names[&import.tree].0.clone()));
+        }
+    }
+}
+
+/// Replaces the first `use` item of `items` with the organized imports and
+/// removes the others. Returns `false` if they are already organized.
+fn organize_items(
+    db: &RootDatabase,
+    module: Option<hir::Module>,
+    items: &impl ModuleItemOwner,
+    file_text: &str,
+    used_names: &FxHashSet<String>,
+    edit: &mut TextEditBuilder,
+) -> bool {
+    let use_items: Vec<&ast::UseItem> = items
+        .items()
+        .filter_map(|it| match it.kind() {
+            ast::ModuleItemKind::UseItem(it) => Some(it),
+            _ => None,
+        })
+        .filter(|it| {
+            it.attrs().next().is_none()
+                && it.syntax().descendants_with_tokens().all(|it| it.kind() != COMMENT)
+        })
+        .collect();
+    let (first, last) = match (use_items.first(), use_items.last()) {
+        (Some(first), Some(last)) => (first.syntax(), last.syntax()),
+        _ => return false,
+    };
+
+    let mut imports = Vec::new();
+    for use_item in use_items.iter() {
+        let visibility = use_item.visibility().map(|it| it.syntax().text().to_string());
+        if let Some(tree) = use_item.use_tree() {
+            flatten(tree, &visibility, &[], &mut imports);
+        }
+    }
+    let imported_names = module.map(|it| imported_names(db, it)).unwrap_or_default();
+    imports.retain(|import| !is_unused(import, &imported_names, used_names));
+
+    let indent = match first.prev_sibling_or_token() {
+        Some(SyntaxElement::Token(ws)) if ws.kind() == WHITESPACE => {
+            ws.text().rsplit('\n').next().unwrap_or("").to_string()
+        }
+        _ => String::new(),
+    };
+    let text = render(imports, &indent);
+
+    let is_contiguous = use_items.iter().tuple_windows().all(|(a, b)| {
+        let between = TextRange::from_to(a.syntax().range().end(), b.syntax().range().start());
+        file_text[between].trim().is_empty()
+    });
+    let range = TextRange::from_to(first.range().start(), last.range().end());
+    if is_contiguous && file_text[range] == text {
+        return false;
+    }
+
+    let mut use_items = use_items.into_iter();
+    let mut edited_until = first.range().start();
+    if !text.is_empty() {
+        edit.replace(use_items.next().unwrap().syntax().range(), text);
+        edited_until = first.range().end();
+    }
+    for use_item in use_items {
+        let range = range_with_whitespace(use_item.syntax(), edited_until);
+        edit.delete(range);
+        edited_until = range.end();
+    }
+    true
+}
+
+/// The range of `node` with the whitespace before or after it, whichever has
+/// less newlines, so that the empty lines around it are kept. The whitespace
+/// before `edited_until` is already removed.
+fn range_with_whitespace(node: &SyntaxNode, edited_until: TextUnit) -> TextRange {
+    let whitespace = |element: Option<SyntaxElement>| match element {
+        Some(SyntaxElement::Token(ws))
+            if ws.kind() == WHITESPACE && ws.range().start() >= edited_until =>
+        {
+            Some((ws.text().matches('\n').count(), ws.range()))
+        }
+        _ => None,
+    };
+    match (whitespace(node.prev_sibling_or_token()), whitespace(node.next_sibling_or_token())) {
+        (Some((prev_newlines, prev)), Some((next_newlines, _)))
+            if prev_newlines <= next_newlines =>
+        {
+            TextRange::from_to(prev.start(), node.range().end())
+        }
+        (_, Some((_, next))) => TextRange::from_to(node.range().start(), next.end()),
+        (Some((_, prev)), None) => TextRange::from_to(prev.start(), node.range().end()),
+        (None, None) => node.range(),
+    }
+}
+
+fn flatten(
+    tree: &ast::UseTree,
+    visibility: &Option<String>,
+    prefix: &[String],
+    acc: &mut Vec<Import>,
+) {
+    let mut segments = prefix.to_vec();
+    segments.extend(path_segments(tree.path()));
+    if let Some(list) = tree.use_tree_list() {
+        for tree in list.use_trees() {
+            flatten(tree, visibility, &segments, acc);
+        }
+        return;
+    }
+    let leaf = if tree.has_star() {
+        "*".to_string()
+    } else {
+        let mut leaf = match segments.pop() {
+            Some(it) => it,
+            None => return,
+        };
+        if let Some(alias) = tree.alias() {
+            leaf.push_str(" ");
+            leaf.push_str(&alias.syntax().text().to_string());
+        }
+        leaf
+    };
+    let tree = tree.syntax().range();
+    acc.push(Import { visibility: visibility.clone(), prefix: segments, leaf, tree });
+}
+
+fn path_segments(path: Option<&ast::Path>) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut path = path;
+    while let Some(it) = path {
+        if let Some(segment) = it.segment() {
+            res.push(segment.syntax().text().to_string());
+        }
+        path = it.qualifier();
+    }
+    res.reverse();
+    res
+}
+
+/// Renders the imports in groups separated by an empty line, merging the ones
+/// with the same prefix and visibility.
+fn render(imports: Vec<Import>, indent: &str) -> String {
+    let mut merged: FxHashMap<(Group, Vec<String>, Option<String>), Vec<String>> =
+        FxHashMap::default();
+    for import in imports {
+        let key = (import.group(), import.prefix, import.visibility);
+        merged.entry(key).or_default().push(import.leaf);
+    }
+    let mut merged: Vec<_> = merged.into_iter().collect();
+    merged.sort();
+
+    let mut lines: Vec<(Group, String)> = Vec::new();
+    for ((group, prefix, visibility), mut leaves) in merged {
+        leaves.sort_by(|a, b| leaf_key(a).cmp(&leaf_key(b)));
+        leaves.dedup();
+        let visibility = visibility.map(|it| it + " ").unwrap_or_default();
+        if prefix.is_empty() {
+            for leaf in leaves {
+                lines.push((group, format!("{}use {};", visibility, leaf)));
+            }
+        } else if leaves.len() == 1 && !leaves[0].starts_with("self") {
+            lines.push((group, format!("{}use {}::{};", visibility, prefix.join("::"), leaves[0])));
+        } else {
+            let line =
+                format!("{}use {}::{{{}}};", visibility, prefix.join("::"), leaves.join(", "));
+            lines.push((group, line));
+        }
+    }
+
+    let mut res = String::new();
+    let mut last_group = None;
+    for (group, line) in lines {
+        if last_group.is_some() {
+            res.push_str("\n");
+            if last_group != Some(group) {
+                res.push_str("\n");
+            }
+            res.push_str(indent);
+        }
+        last_group = Some(group);
+        res.push_str(&line);
+    }
+    res
+}
+
+/// `self` comes first and `*` last, like with rustfmt.
+fn leaf_key(leaf: &str) -> (u8, &str) {
+    if leaf == "self" || leaf.starts_with("self ") {
+        (0, leaf)
+    } else if leaf == "*" {
+        (2, leaf)
+    } else {
+        (1, leaf)
+    }
+}
+
+/// The names brought in scope by the imports of `module`, by the range of the
+/// use tree they come from.
+fn imported_names(
+    db: &RootDatabase,
+    module: hir::Module,
+) -> FxHashMap<TextRange, (String, PerNs<ModuleDef>)> {
+    let mut res = FxHashMap::default();
+    for (name, resolution) in module.scope(db).entries() {
+        let import = match resolution.import {
+            Some(it) => it,
+            None => continue,
+        };
+        if let Either::A(tree) = module.import_source(db, import) {
+            res.insert(tree.syntax().range(), (name.to_string(), resolution.def.clone()));
+        }
+    }
+    res
+}
+
+fn is_unused(
+    import: &Import,
+    imported_names: &FxHashMap<TextRange, (String, PerNs<ModuleDef>)>,
+    used_names: &FxHashSet<String>,
+) -> bool {
+    if import.visibility.is_some() || import.leaf == "*" {
+        return false;
+    }
+    let (name, def) = match imported_names.get(&import.tree) {
+        Some(it) => it,
+        None => return false,
+    };
+    if def.is_none() || name == "_" {
+        return false;
+    }
+    if let Some(ModuleDef::Trait(_)) = def.clone().take_types() {
+        return false;
+    }
+    !used_names.contains(name)
+}
+
+/// The identifiers of the file and of the files of its child modules, macro
+/// calls and attributes included, but not the names of definitions and
+/// imports.
+fn used_names(db: &RootDatabase, file_id: FileId, file: &SourceFile) -> FxHashSet<String> {
+    let mut res = FxHashSet::default();
+    add_used_names(file.syntax(), &mut res);
+
+    let mut modules: Vec<_> = source_binder::module_from_file_id(db, file_id)
+        .map(|it| it.children(db).collect())
+        .unwrap_or_default();
+    while let Some(module) = modules.pop() {
+        if let (_, hir::ModuleSource::SourceFile(file)) = module.definition_source(db) {
+            add_used_names(file.syntax(), &mut res);
+        }
+        modules.extend(module.children(db));
+    }
+    res
+}
+
+fn add_used_names(node: &SyntaxNode, acc: &mut FxHashSet<String>) {
+    for token in node.descendants_with_tokens().filter_map(|it| it.as_token()) {
+        if token.kind() != IDENT {
+            continue;
+        }
+        let parent = token.parent();
+        // Unlike the names of items, those of bindings may be unit structs.
+        if ast::Name::cast(parent).is_some()
+            && parent.parent().and_then(ast::BindPat::cast).is_none()
+        {
+            continue;
+        }
+        let in_use_item = parent.ancestors().any(|it| ast::UseItem::cast(it).is_some());
+        if !in_use_item || is_name_in_scope(parent) {
+            acc.insert(token.text().to_string());
+        }
+    }
+}
+
+/// Whether the name of a use tree refers to a name in scope, like `foo` in
+/// `use foo::bar;`, rather than being imported.
+fn is_name_in_scope(name_ref: &SyntaxNode) -> bool {
+    let path = match name_ref.parent().and_then(ast::PathSegment::cast) {
+        Some(segment) => segment.parent_path(),
+        None => return false,
+    };
+    if path.qualifier().is_some() {
+        return false;
+    }
+    let tree = match path.syntax().ancestors().find_map(ast::UseTree::cast) {
+        Some(it) => it,
+        None => return false,
+    };
+    let is_top_level = tree.syntax().parent().and_then(ast::UseItem::cast).is_some();
+    let is_leaf = tree.path().map(|it| it.syntax().range()) == Some(path.syntax().range())
+        && tree.use_tree_list().is_none()
+        && !tree.has_star();
+    is_top_level && !is_leaf
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::assert_eq_text;
+
+    use crate::mock_analysis::{single_file, MockAnalysis};
+
+    fn check(before: &str, after: &str) {
+        let (analysis, file_id) = single_file(before);
+        let change = analysis.organize_imports(file_id).unwrap().expect("expected a change");
+        let actual = change.source_file_edits[0].edit.apply(before);
+        assert_eq_text!(after, &actual);
+    }
+
+    fn check_unchanged(text: &str) {
+        let (analysis, file_id) = single_file(text);
+        assert!(analysis.organize_imports(file_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn sorts_groups_and_merges_imports() {
+        check(
+            r"
+use crate::foo::{Foo, Bar};
+use itertools::Itertools;
+use std::sync::Arc;
+use super::baz;
+use std::collections::HashMap;
+use itertools::Itertools;
+pub use crate::foo::Baz;
+
+fn f(_: Foo, _: Bar, _: Arc<HashMap<(), ()>>) {
+    baz();
+    vec![1].into_iter().sorted();
+}
+",
+            r"
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::foo::{Bar, Foo};
+pub use crate::foo::Baz;
+use super::baz;
+
+fn f(_: Foo, _: Bar, _: Arc<HashMap<(), ()>>) {
+    baz();
+    vec![1].into_iter().sorted();
+}
+",
+        );
+    }
+
+    #[test]
+    fn removes_unused_imports() {
+        check(
+            r"
+mod foo {
+    pub struct Used;
+    pub struct Unused;
+    pub trait Trait {}
+    pub mod m {}
+}
+use crate::foo::{Used, Unused, Trait, m::{self}};
+use crate::foo::*;
+use crate::unresolved::Thing;
+pub use crate::foo::Unused as Exported;
+fn main() {
+    let _ = Used;
+}
+mod inner {
+    use crate::foo::Used;
+    use crate::foo::Unused;
+    fn f() -> Used { Used }
+}
+",
+            r"
+mod foo {
+    pub struct Used;
+    pub struct Unused;
+    pub trait Trait {}
+    pub mod m {}
+}
+use crate::foo::{Trait, Used, *};
+pub use crate::foo::Unused as Exported;
+use crate::unresolved::Thing;
+fn main() {
+    let _ = Used;
+}
+mod inner {
+    use crate::foo::Used;
+    fn f() -> Used { Used }
+}
+",
+        );
+    }
+
+    #[test]
+    fn keeps_imports_used_in_macros_and_child_modules() {
+        let text = r"
+//- /lib.rs
+mod foo { pub struct Bar; pub struct Baz; pub struct Qux; }
+use crate::foo::{Bar, Baz, Qux};
+mod child;
+fn main() { println!({}, Bar); }
+//- /child.rs
+fn f() -> super::Baz { loop {} }
+";
+        let analysis = MockAnalysis::with_files(text);
+        let file_id = analysis.id_of("/lib.rs");
+        let analysis = analysis.analysis();
+        let change = analysis.organize_imports(file_id).unwrap().unwrap();
+        let actual = change.source_file_edits[0].edit.apply(&analysis.file_text(file_id));
+        assert!(actual.contains("use crate::foo::{Bar, Baz};"), "{}", actual);
+    }
+
+    #[test]
+    fn organized_imports_are_unchanged() {
+        check_unchanged(
+            r"
+use std::sync::Arc;
+
+use crate::foo::{self, Bar};
+
+fn f(_: Arc<Bar>, _: foo::Baz) {}
+",
+        );
+        check_unchanged("fn main() {}");
+    }
+}
@@ -1,22 +1,30 @@
 use relative_path::{RelativePath, RelativePathBuf};
-use hir::{ModuleSource, source_binder, Either};
-use ra_db::{SourceDatabase};
+use hir::{ModuleSource, PathResolution, source_binder, Either};
+use ra_db::SourceDatabase;
 use ra_syntax::{
-    AstNode, SyntaxNode, SourceFile,
-    ast,
-    algo::find_node_at_offset,
+    AstNode, SyntaxNode, SyntaxToken, SourceFile,
+    ast::{self, AttrsOwner, NameOwner},
+    TextUnit, tokenize,
+    SyntaxKind::IDENT,
+    algo::{find_node_at_offset, find_covering_element, find_token_at_offset},
 };
 
 use crate::{
-    db::RootDatabase,
+    db::{self, RootDatabase},
     FilePosition,
     FileRange,
     FileId,
     NavigationTarget,
+    RangeInfo,
     FileSystemEdit,
     SourceChange,
     SourceFileEdit,
     TextRange,
+    goto_definition::{reference_definition, ReferenceResult},
+    highlight_related::{definition, range_access, ReferenceAccess},
+    symbol_index::SymbolsDatabase,
+    doc_links::{doc_link_at_offset, param_doc_links, resolve_doc_link_param},
+    format_string::{format_strings, inline_arg_at_offset, resolve_inline_arg},
 };
 
 #[derive(Debug, Clone)]
@@ -56,19 +64,177 @@ impl IntoIterator for ReferenceSearchResult {
     }
 }
 
+/// Where to look for the references of a definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchScope {
+    /// The files of the workspace.
+    Workspace,
+    /// The module of the searched position, without its submodules.
+    CurrentModule,
+    /// The test functions, and the modules which are only compiled for tests.
+    Tests,
+}
+
+/// Restricts the references found by a search. The declaration is always
+/// found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceFilter {
+    pub scope: SearchScope,
+    /// Only the references which read, or which write, the definition.
+    pub access: Option<ReferenceAccess>,
+}
+
+impl Default for ReferenceFilter {
+    fn default() -> ReferenceFilter {
+        ReferenceFilter { scope: SearchScope::Workspace, access: None }
+    }
+}
+
 pub(crate) fn find_all_refs(
     db: &RootDatabase,
     position: FilePosition,
+) -> Option<ReferenceSearchResult> {
+    find_refs(db, position, ReferenceFilter::default())
+}
+
+pub(crate) fn find_refs(
+    db: &RootDatabase,
+    position: FilePosition,
+    filter: ReferenceFilter,
+) -> Option<ReferenceSearchResult> {
+    let mut res = find_local_refs(db, position).or_else(|| find_definition_refs(db, position))?;
+    let module = source_binder::module_from_position(db, position);
+    res.references.retain(|it| filter.matches(db, module, it));
+    Some(res)
+}
+
+/// The references of the definition at `position`, other than a local
+/// binding, in the files of the workspace.
+///
+/// The files are searched in parallel, in two phases: a file is lexed to find
+/// the identifiers with the name of the definition, which skips the strings
+/// and the comments, and only the files with such candidates are parsed, to
+/// resolve the candidates.
+fn find_definition_refs(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<ReferenceSearchResult> {
+    let file = db.parse(position.file_id);
+    let (name, declaration) = definition(db, position.file_id, file.syntax(), position)?;
+    let mut files = Vec::new();
+    for &root in db.local_roots().iter() {
+        files.extend(db.source_root(root).files.values().map(|&it| it));
+    }
+    let mut references: Vec<FileRange> = db::par_map(db, &files, |db, &file_id| {
+        let candidates = candidate_ranges(&db.file_text(file_id), &name);
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+        let file = db.parse(file_id);
+        candidates
+            .into_iter()
+            .filter_map(|range| {
+                let name_ref = find_covering_element(file.syntax(), range)
+                    .ancestors()
+                    .find_map(ast::NameRef::cast)?;
+                let nav = match reference_definition(db, file_id, name_ref) {
+                    ReferenceResult::Exact(nav) => nav,
+                    ReferenceResult::Approximate(_) => return None,
+                };
+                if nav.file_id() == declaration.file_id()
+                    && nav.full_range() == declaration.full_range()
+                {
+                    Some(FileRange { file_id, range })
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .into_iter()
+    .flatten()
+    .collect();
+    references.sort_by_key(|it| (it.file_id, it.range.start()));
+    Some(ReferenceSearchResult { declaration, references })
+}
+
+/// The ranges of the identifiers `name` in `text`.
+fn candidate_ranges(text: &str, name: &str) -> Vec<TextRange> {
+    let mut res = Vec::new();
+    if !text.contains(name) {
+        return res;
+    }
+    let mut offset = TextUnit::from(0);
+    for token in tokenize(text) {
+        let range = TextRange::offset_len(offset, token.len);
+        if token.kind == IDENT && text[range] == *name {
+            res.push(range);
+        }
+        offset += token.len;
+    }
+    res
+}
+
+impl ReferenceFilter {
+    fn matches(
+        &self,
+        db: &RootDatabase,
+        module: Option<hir::Module>,
+        reference: &FileRange,
+    ) -> bool {
+        let file = db.parse(reference.file_id);
+        if self.access.is_some() && range_access(file.syntax(), reference.range) != self.access {
+            return false;
+        }
+        match self.scope {
+            SearchScope::Workspace => true,
+            SearchScope::CurrentModule => {
+                let position =
+                    FilePosition { file_id: reference.file_id, offset: reference.range.start() };
+                module.is_some() && source_binder::module_from_position(db, position) == module
+            }
+            SearchScope::Tests => {
+                find_covering_element(file.syntax(), reference.range).ancestors().any(is_test_code)
+            }
+        }
+    }
+}
+
+/// Whether `node` is a test function, or a module only compiled for tests.
+fn is_test_code(node: &SyntaxNode) -> bool {
+    if let Some(fn_def) = ast::FnDef::cast(node) {
+        return fn_def.has_atom_attr("test");
+    }
+    match ast::Module::cast(node) {
+        Some(module) => module.attrs().filter_map(|it| it.as_call()).any(|(name, args)| {
+            name == "cfg" && args.syntax().text().to_string().replace(' ', "") == "(test)"
+        }),
+        None => false,
+    }
+}
+
+/// The references of the local binding at `position`, which are all in its
+/// file.
+pub(crate) fn find_local_refs(
+    db: &RootDatabase,
+    position: FilePosition,
 ) -> Option<ReferenceSearchResult> {
     let file = db.parse(position.file_id);
     let (binding, analyzer) = find_binding(db, &file, position)?;
     let declaration = NavigationTarget::from_bind_pat(position.file_id, binding);
 
-    let references = analyzer
-        .find_all_refs(binding)
+    let mut references = analyzer
+        .find_all_refs(db, binding)
         .into_iter()
         .map(move |ref_desc| FileRange { file_id: position.file_id, range: ref_desc.range })
         .collect::<Vec<_>>();
+    references.extend(inline_format_args(db, position.file_id, &file, binding));
+    references.extend(
+        param_doc_links(binding)
+            .into_iter()
+            .map(|range| FileRange { file_id: position.file_id, range }),
+    );
+    references.sort_by_key(|it| it.range.start());
 
     return Some(ReferenceSearchResult { declaration, references });
 
@@ -82,11 +248,29 @@ pub(crate) fn find_all_refs(
             let analyzer = hir::SourceAnalyzer::new(db, position.file_id, binding.syntax(), None);
             return Some((binding, analyzer));
         };
-        let name_ref = find_node_at_offset::<ast::NameRef>(syntax, position.offset)?;
-        let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
-        let resolved = analyzer.resolve_local_name(name_ref)?;
-        if let Either::A(ptr) = resolved.ptr() {
+        if let Some((link, owner)) = doc_link_at_offset(source_file, position.offset) {
+            let binding = resolve_doc_link_param(owner, &link.path)?;
+            let analyzer = hir::SourceAnalyzer::new(db, position.file_id, binding.syntax(), None);
+            return Some((binding, analyzer));
+        }
+        let ptr = match find_node_at_offset::<ast::NameRef>(syntax, position.offset) {
+            Some(name_ref) => {
+                let analyzer =
+                    hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
+                analyzer.resolve_local_name(name_ref)?.ptr()
+            }
+            None => {
+                let (format_string, placeholder) = inline_arg_at_offset(syntax, position.offset)?;
+                match resolve_inline_arg(db, position.file_id, &format_string, &placeholder)? {
+                    PathResolution::LocalBinding(ptr) => ptr,
+                    _ => return None,
+                }
+            }
+        };
+        if let Either::A(ptr) = ptr {
             if let ast::PatKind::BindPat(binding) = ptr.to_node(source_file).kind() {
+                let analyzer =
+                    hir::SourceAnalyzer::new(db, position.file_id, binding.syntax(), None);
                 return Some((binding, analyzer));
             }
         }
@@ -94,6 +278,115 @@ pub(crate) fn find_all_refs(
     }
 }
 
+/// The inline arguments of the format strings of `file` which refer to
+/// `binding`.
+fn inline_format_args(
+    db: &RootDatabase,
+    file_id: FileId,
+    file: &SourceFile,
+    binding: &ast::BindPat,
+) -> Vec<FileRange> {
+    let name = match binding.name() {
+        Some(it) => it.text(),
+        None => return Vec::new(),
+    };
+    let mut res = Vec::new();
+    for format_string in format_strings(file.syntax()) {
+        for placeholder in format_string.inline_args().filter(|it| it.name == *name) {
+            let is_binding = match resolve_inline_arg(db, file_id, &format_string, placeholder) {
+                Some(PathResolution::LocalBinding(Either::A(ptr))) => {
+                    ptr.syntax_node_ptr().range() == binding.syntax().range()
+                }
+                _ => false,
+            };
+            if is_binding {
+                res.push(FileRange { file_id, range: placeholder.range });
+            }
+        }
+    }
+    res
+}
+
+/// The range and the text of the identifier to rename at `position`, or why
+/// it can't be renamed, for the editor to check before asking for a new name.
+pub(crate) fn prepare_rename(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Result<RangeInfo<String>, String> {
+    let source_file = db.parse(position.file_id);
+    let syntax = source_file.syntax();
+    let (range, name) = identifier_at_offset(&source_file, position.offset)?;
+
+    if find_name_and_module_at_offset(syntax, position).is_some() {
+        check_in_workspace(db, position.file_id, &name)?;
+        return Ok(RangeInfo::new(range, name));
+    }
+    if let Some(refs) = find_local_refs(db, position) {
+        check_in_workspace(db, refs.declaration().file_id(), &name)?;
+        return Ok(RangeInfo::new(range, name));
+    }
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(syntax, position.offset) {
+        match reference_definition(db, position.file_id, name_ref) {
+            ReferenceResult::Exact(nav) => check_in_workspace(db, nav.file_id(), &name)?,
+            ReferenceResult::Approximate(ref navs)
+                if navs.is_empty() && is_builtin_type(name_ref) =>
+            {
+                return Err(format!("Cannot rename builtin type `{}`", name));
+            }
+            ReferenceResult::Approximate(_) => {}
+        }
+    }
+    Err(format!("Cannot rename `{}`, only local bindings and modules can be renamed", name))
+}
+
+/// The identifier at `offset`, which is also an inline argument of a format
+/// string or a parameter linked from the docs.
+fn identifier_at_offset(
+    file: &SourceFile,
+    offset: TextUnit,
+) -> Result<(TextRange, String), String> {
+    let tokens: Vec<SyntaxToken> = find_token_at_offset(file.syntax(), offset).collect();
+    if let Some(ident) = tokens.iter().find(|it| it.kind() == IDENT) {
+        return Ok((ident.range(), ident.text().to_string()));
+    }
+    if let Some((_, placeholder)) = inline_arg_at_offset(file.syntax(), offset) {
+        return Ok((placeholder.range, placeholder.name.to_string()));
+    }
+    if let Some((link, _)) = doc_link_at_offset(file, offset) {
+        return Ok((link.path_range, link.path));
+    }
+    match tokens.iter().find(|it| !it.kind().is_trivia()) {
+        Some(token) if token.kind().is_keyword() => {
+            Err(format!("Cannot rename keyword `{}`", token.text()))
+        }
+        Some(token) if token.kind().is_literal() => Err("Cannot rename a literal".to_string()),
+        _ => Err("No identifier to rename at the cursor".to_string()),
+    }
+}
+
+/// The edits of a rename are only applied to the files of the workspace.
+fn check_in_workspace(db: &RootDatabase, file_id: FileId, name: &str) -> Result<(), String> {
+    if db.local_roots().contains(&db.file_source_root(file_id)) {
+        Ok(())
+    } else {
+        Err(format!("Cannot rename `{}`, it is defined in a dependency crate", name))
+    }
+}
+
+/// Whether `name_ref`, which resolves to nothing, is a primitive type.
+fn is_builtin_type(name_ref: &ast::NameRef) -> bool {
+    const BUILTIN_TYPES: [&str; 17] = [
+        "isize", "i8", "i16", "i32", "i64", "i128", "usize", "u8", "u16", "u32", "u64", "u128",
+        "f32", "f64", "bool", "char", "str",
+    ];
+    let is_unqualified = name_ref
+        .syntax()
+        .ancestors()
+        .find_map(ast::Path::cast)
+        .map_or(false, |path| path.qualifier().is_none());
+    is_unqualified && BUILTIN_TYPES.contains(&name_ref.text().as_str())
+}
+
 pub(crate) fn rename(
     db: &RootDatabase,
     position: FilePosition,
@@ -194,11 +487,15 @@ fn rename_reference(
     position: FilePosition,
     new_name: &str,
 ) -> Option<SourceChange> {
-    let refs = find_all_refs(db, position)?;
+    let file = db.parse(position.file_id);
+    let refs = find_local_refs(db, position)?;
 
     let edit = refs
         .into_iter()
-        .map(|range| source_edit_from_fileid_range(range.file_id, range.range, new_name))
+        .map(|range| match field_shorthand_rename(&file, range.range, new_name) {
+            Some((range, text)) => source_edit_from_fileid_range(position.file_id, range, &text),
+            None => source_edit_from_fileid_range(range.file_id, range.range, new_name),
+        })
         .collect::<Vec<_>>();
 
     if edit.is_empty() {
@@ -208,14 +505,46 @@ fn rename_reference(
     Some(SourceChange::source_file_edits("rename", edit))
 }
 
+/// Renaming a binding of a field shorthand, `S { field }` in a struct literal
+/// or a struct pattern, keeps the field by expanding the shorthand to
+/// `S { field: new_name }`.
+fn field_shorthand_rename(
+    file: &SourceFile,
+    range: TextRange,
+    new_name: &str,
+) -> Option<(TextRange, String)> {
+    let node = find_covering_element(file.syntax(), range);
+    if let Some(name_ref) = node.ancestors().find_map(ast::NameRef::cast) {
+        let field = name_ref.syntax().parent().and_then(ast::NamedField::cast)?;
+        if field.expr().is_some() {
+            return None;
+        }
+        return Some((range, format!("{}: {}", name_ref.text(), new_name)));
+    }
+    let name = node.ancestors().find_map(ast::Name::cast)?;
+    let bind_pat = name.syntax().parent().and_then(ast::BindPat::cast)?;
+    ast::FieldPatList::cast(bind_pat.syntax().parent()?)?;
+    // Keep the `ref` and `mut` of the binding, which follow the field name.
+    let start = bind_pat.syntax().range().start();
+    let prefix = TextRange::from_to(start, range.start()) - start;
+    let prefix = &bind_pat.syntax().text().to_string()[prefix];
+    let text = format!("{}: {}{}", name.text(), prefix, new_name);
+    Some((TextRange::from_to(start, range.end()), text))
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot_matches;
-    use test_utils::assert_eq_text;
+    use std::sync::Arc;
+
+    use ra_syntax::TextUnit;
+    use test_utils::{assert_eq_text, extract_offset};
     use crate::{
         mock_analysis::single_file_with_position,
         mock_analysis::analysis_and_position,
-        FileId, ReferenceSearchResult
+        FileId, ReferenceSearchResult, ReferenceFilter, SearchScope, ReferenceAccess,
+        AnalysisHost, AnalysisChange, CrateGraph, SourceRootId, FilePosition,
+        Edition::Edition2018,
 };
 
     #[test]
@@ -259,6 +588,93 @@ mod tests {
         assert_eq!(refs.len(), 2);
     }
 
+    #[test]
+    fn test_find_all_refs_for_field() {
+        let code = r#"
+    struct S { f<|>: u32 }
+    fn foo(s: &mut S) -> u32 {
+        s.f += 1;
+        s.f
+    }
+    mod bar {
+        fn bar(s: super::S) -> u32 { s.f }
+    }"#;
+
+        let refs = get_all_refs(code);
+        assert_eq!(refs.len(), 4);
+    }
+
+    #[test]
+    fn test_find_all_refs_across_files() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            pub struct Foo<|>;
+            // Foo
+            fn f() -> Foo { Foo }
+            //- /foo.rs
+            use crate::Foo;
+            const FOO: &str = \"Foo\";
+            struct Bar(Foo);
+            ",
+        );
+        let refs = analysis.find_all_refs(position).unwrap().unwrap();
+        let files: Vec<_> = refs.references().iter().map(|it| it.file_id).collect();
+        assert_eq!(files, vec![FileId(1), FileId(1), FileId(2), FileId(2)]);
+    }
+
+    #[test]
+    fn candidate_ranges_skip_strings_and_comments() {
+        let text = "fn foo() { foo(\"foo\"); /* foo */ food; foo }";
+        let ranges: Vec<_> = super::candidate_ranges(text, "foo")
+            .into_iter()
+            .map(|it| it.start().to_usize())
+            .collect();
+        assert_eq!(ranges, vec![3, 11, 39]);
+    }
+
+    #[test]
+    fn test_find_refs_with_filters() {
+        let code = r#"
+    struct S { f<|>: u32 }
+    fn foo(s: &mut S) {
+        s.f = s.f + 1;
+    }
+    mod tests {
+        fn check(s: &mut super::S) { s.f = 0 }
+        #[test]
+        fn test() {
+            let s = super::S { f: 1 };
+            let _ = s.f;
+        }
+    }"#;
+        let (analysis, position) = single_file_with_position(code);
+        let text = analysis.file_text(position.file_id);
+        // The lines of the references, up to the references.
+        let find_refs = |scope, access| {
+            let filter = ReferenceFilter { scope, access };
+            let refs = analysis.find_refs(position, filter).unwrap().unwrap();
+            refs.references()
+                .iter()
+                .map(|it| {
+                    let end = it.range.end().to_usize();
+                    text[text[..end].rfind('\n').unwrap() + 1..end].trim_start().to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            find_refs(SearchScope::Workspace, Some(ReferenceAccess::Write)),
+            vec!["s.f", "fn check(s: &mut super::S) { s.f"]
+        );
+        assert_eq!(find_refs(SearchScope::CurrentModule, None), vec!["s.f", "s.f = s.f"]);
+        assert_eq!(
+            find_refs(SearchScope::Tests, Some(ReferenceAccess::Read)),
+            vec!["let s = super::S { f", "let _ = s.f"]
+        );
+    }
+
     fn get_all_refs(text: &str) -> ReferenceSearchResult {
         let (analysis, position) = single_file_with_position(text);
         analysis.find_all_refs(position).unwrap().unwrap()
@@ -295,6 +711,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rename_for_inline_format_args() {
+        test_rename(
+            r#"
+    fn main() {
+        let width = 1;
+        let i = 1;
+        println!("{i:width$} {i:?} {{i}} {i}", i = 2);
+        {
+            let width = 2;
+            println!("{:width$}", 0);
+        }
+        println!("{i:>width<|>$}");
+    }"#,
+            "w",
+            r#"
+    fn main() {
+        let w = 1;
+        let i = 1;
+        println!("{i:w$} {i:?} {{i}} {i}", i = 2);
+        {
+            let width = 2;
+            println!("{:width$}", 0);
+        }
+        println!("{i:>w$}");
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_for_params_in_docs_and_format_strings() {
+        test_rename(
+            r#"
+    /// Prints [`i`], then [j](j).
+    fn foo(i<|>: u32, j: u32) {
+        println!("{i} {j}");
+    }"#,
+            "k",
+            r#"
+    /// Prints [`k`], then [j](j).
+    fn foo(k: u32, j: u32) {
+        println!("{k} {j}");
+    }"#,
+        );
+        test_rename(
+            r#"
+    /// Prints [`i`], then [j](j<|>).
+    fn foo(i: u32, j: u32) {
+        println!("{i} {j}");
+    }"#,
+            "k",
+            r#"
+    /// Prints [`i`], then [j](k).
+    fn foo(i: u32, k: u32) {
+        println!("{i} {k}");
+    }"#,
+        );
+    }
+
     #[test]
     fn test_rename_for_param_inside() {
         test_rename(
@@ -340,6 +815,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rename_for_field_shorthands() {
+        test_rename(
+            r#"
+    struct S { i: u32, j: u32 }
+    fn foo(s: S) -> S {
+        let S { ref i, j } = s;
+        S { i<|>: *i, j }
+    }"#,
+            "k",
+            r#"
+    struct S { i: u32, j: u32 }
+    fn foo(s: S) -> S {
+        let S { i: ref k, j } = s;
+        S { i: *k, j }
+    }"#,
+        );
+        test_rename(
+            r#"
+    struct S { i: u32, j: u32 }
+    fn foo(s: S) -> S {
+        let S { i, j<|> } = s;
+        S { i, j }
+    }"#,
+            "k",
+            r#"
+    struct S { i: u32, j: u32 }
+    fn foo(s: S) -> S {
+        let S { i, j: k } = s;
+        S { i, j: k }
+    }"#,
+        );
+    }
+
     #[test]
     fn test_rename_mod() {
         let (analysis, position) = analysis_and_position(
@@ -374,6 +883,92 @@ mod tests {
         assert_debug_snapshot_matches!("rename_mod_in_dir", &source_change);
     }
 
+    #[test]
+    fn test_prepare_rename_for_local() {
+        let (analysis, position) = single_file_with_position(
+            "
+            fn main() {
+                let i = 0;
+                i<|> + 1;
+            }",
+        );
+        let info = analysis.prepare_rename(position).unwrap().unwrap();
+        assert_eq!(info.info, "i");
+        assert_eq!(&analysis.file_text(position.file_id)[info.range], "i");
+        assert_eq!(info.range.end(), position.offset);
+    }
+
+    #[test]
+    fn test_prepare_rename_for_inline_format_arg_and_mod() {
+        let (analysis, position) = single_file_with_position(
+            r#"
+            fn main() {
+                let count = 0;
+                format!("{co<|>unt}");
+            }"#,
+        );
+        let info = analysis.prepare_rename(position).unwrap().unwrap();
+        assert_eq!(info.info, "count");
+        assert_eq!(&analysis.file_text(position.file_id)[info.range], "count");
+
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod fo<|>o;
+            //- /foo.rs
+            // empty
+            ",
+        );
+        let info = analysis.prepare_rename(position).unwrap().unwrap();
+        assert_eq!(info.info, "foo");
+    }
+
+    #[test]
+    fn test_prepare_rename_rejects_keywords_literals_and_builtin_types() {
+        check_prepare_rename_error("f<|>n main() {}", "Cannot rename keyword `fn`");
+        check_prepare_rename_error("fn main() { let x = 9<|>2; }", "Cannot rename a literal");
+        check_prepare_rename_error(
+            "fn main() { let x: i3<|>2 = 92; }",
+            "Cannot rename builtin type `i32`",
+        );
+        check_prepare_rename_error(
+            "fn foo() {} fn main() { fo<|>o(); }",
+            "Cannot rename `foo`, only local bindings and modules can be renamed",
+        );
+    }
+
+    #[test]
+    fn test_prepare_rename_rejects_dependencies() {
+        let mut host = AnalysisHost::default();
+        let mut change = AnalysisChange::new();
+        change.add_root(SourceRootId(0), true);
+        change.add_root(SourceRootId(1), false);
+        let (offset, text) = extract_offset("fn main() { dep::fo<|>o(); }");
+        change.add_file(SourceRootId(0), FileId(1), "main.rs".into(), Arc::new(text));
+        let dep_text = "pub fn foo() { let bar = 92; bar; }".to_string();
+        let dep_offset = TextUnit::from_usize(dep_text.rfind("bar").unwrap());
+        change.add_file(SourceRootId(1), FileId(2), "lib.rs".into(), Arc::new(dep_text));
+        let mut crate_graph = CrateGraph::default();
+        let main = crate_graph.add_crate_root(FileId(1), Edition2018);
+        let dep = crate_graph.add_crate_root(FileId(2), Edition2018);
+        crate_graph.add_dep(main, "dep".into(), dep).unwrap();
+        change.set_crate_graph(crate_graph);
+        host.apply_change(change);
+        let analysis = host.analysis();
+
+        let err = analysis.prepare_rename(FilePosition { file_id: FileId(1), offset }).unwrap();
+        assert_eq!(err.unwrap_err(), "Cannot rename `foo`, it is defined in a dependency crate");
+        let position = FilePosition { file_id: FileId(2), offset: dep_offset };
+        let err = analysis.prepare_rename(position).unwrap();
+        assert_eq!(err.unwrap_err(), "Cannot rename `bar`, it is defined in a dependency crate");
+    }
+
+    fn check_prepare_rename_error(text: &str, expected: &str) {
+        let (analysis, position) = single_file_with_position(text);
+        let err = analysis.prepare_rename(position).unwrap().unwrap_err();
+        assert_eq!(err, expected);
+    }
+
     fn test_rename(text: &str, new_name: &str, expected: &str) {
         let (analysis, position) = single_file_with_position(text);
         let source_change = analysis.rename(position, new_name).unwrap();
@@ -1,11 +1,12 @@
 use itertools::Itertools;
 use ra_syntax::{
     TextRange, SyntaxNode,
-    ast::{self, AstNode, NameOwner, ModuleItemOwner, AttrsOwner},
+    ast::{self, AstNode, NameOwner, ModuleItemOwner, AttrsOwner, DocCommentsOwner},
+    algo::visit::{visitor, Visitor},
 };
 use ra_db::SourceDatabase;
 
-use crate::{db::RootDatabase, FileId};
+use crate::{db::RootDatabase, FileId, doc_code::is_rust_fence};
 
 #[derive(Debug)]
 pub struct Runnable {
@@ -18,6 +19,7 @@ pub enum RunnableKind {
     Test { name: String },
     TestMod { path: String },
     Bench { name: String },
+    DocTest { test_id: String },
     Bin,
 }
 
@@ -28,11 +30,11 @@ pub(crate) fn runnables(db: &RootDatabase, file_id: FileId) -> Vec<Runnable> {
 
 fn runnable(db: &RootDatabase, file_id: FileId, item: &SyntaxNode) -> Option<Runnable> {
     if let Some(fn_def) = ast::FnDef::cast(item) {
-        runnable_fn(fn_def)
+        runnable_fn(fn_def).or_else(|| runnable_doctest(db, file_id, item))
     } else if let Some(m) = ast::Module::cast(item) {
         runnable_mod(db, file_id, m)
     } else {
-        None
+        runnable_doctest(db, file_id, item)
     }
 }
 
@@ -69,6 +71,65 @@ fn runnable_mod(db: &RootDatabase, file_id: FileId, module: &ast::Module) -> Opt
     Some(Runnable { range, kind: RunnableKind::TestMod { path } })
 }
 
+/// An item with Rust code blocks in its docs, which rustdoc runs as tests.
+fn runnable_doctest(db: &RootDatabase, file_id: FileId, item: &SyntaxNode) -> Option<Runnable> {
+    fn name_and_docs<N: NameOwner + DocCommentsOwner>(node: &N) -> Option<(String, String)> {
+        Some((node.name()?.text().to_string(), node.doc_comment_text()?))
+    }
+
+    let (name, docs) = visitor()
+        .visit(name_and_docs::<ast::FnDef>)
+        .visit(name_and_docs::<ast::StructDef>)
+        .visit(name_and_docs::<ast::EnumDef>)
+        .visit(name_and_docs::<ast::TraitDef>)
+        .visit(name_and_docs::<ast::TypeAliasDef>)
+        .visit(name_and_docs::<ast::ConstDef>)
+        .visit(name_and_docs::<ast::StaticDef>)
+        .accept(item)??;
+    if !has_runnable_code_block(&docs) {
+        return None;
+    }
+
+    let module = hir::source_binder::module_from_child_node(db, file_id, item)?;
+    let mut path: Vec<String> = module
+        .path_to_root(db)
+        .into_iter()
+        .rev()
+        .filter_map(|it| it.name(db))
+        .map(|it| it.to_string())
+        .collect();
+    // Doc tests of associated items are named after their type or trait.
+    if let Some(container) = item.parent().and_then(|it| it.parent()) {
+        if let Some(impl_block) = ast::ImplBlock::cast(container) {
+            path.push(impl_block.target_type()?.syntax().text().to_string());
+        } else if let Some(trait_def) = ast::TraitDef::cast(container) {
+            path.push(trait_def.name()?.text().to_string());
+        }
+    }
+    path.push(name);
+    Some(Runnable { range: item.range(), kind: RunnableKind::DocTest { test_id: path.join("::") } })
+}
+
+/// Whether `docs` have a code block which is tested, like the Rust code blocks
+/// which are not marked `ignore`.
+fn has_runnable_code_block(docs: &str) -> bool {
+    let mut in_code_block = false;
+    for line in docs.lines() {
+        let line = line.trim_start();
+        if !line.starts_with("```") {
+            continue;
+        }
+        if !in_code_block
+            && is_rust_fence(line)
+            && !line[3..].split(',').any(|attr| attr.trim() == "ignore")
+        {
+            return true;
+        }
+        in_code_block = !in_code_block;
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot_matches;
@@ -149,6 +210,61 @@ mod tests {
         assert_debug_snapshot_matches!("runnables_multiple_depth_module", &runnables)
     }
 
+    #[test]
+    fn test_runnables_doctest() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+        //- /lib.rs
+        <|> //empty
+        /// ```
+        /// let x = 5;
+        /// ```
+        fn foo() {}
+
+        mod bar {
+            /// ```rust,should_panic
+            /// panic!();
+            /// ```
+            pub struct Baz;
+
+            impl Baz {
+                /// ```ignore
+                /// ```
+                /// ```text
+                /// ```
+                fn ignored() {}
+
+                /// ```no_run
+                /// Baz::new();
+                /// ```
+                fn new() -> Baz { Baz }
+            }
+        }
+        "#,
+        );
+        let runnables = analysis.runnables(pos.file_id).unwrap();
+        assert_debug_snapshot_matches!(runnables, @r###"[
+    Runnable {
+        range: [1; 51),
+        kind: DocTest {
+            test_id: "foo"
+        }
+    },
+    Runnable {
+        range: [66; 140),
+        kind: DocTest {
+            test_id: "bar::Baz"
+        }
+    },
+    Runnable {
+        range: [262; 347),
+        kind: DocTest {
+            test_id: "bar::Baz::new"
+        }
+    }
+]"###);
+    }
+
     #[test]
     fn test_runnables_no_test_function_in_module() {
         let (analysis, pos) = analysis_and_position(
@@ -0,0 +1,560 @@
+//! Structural search and replace: rewrites the expressions of the workspace
+//! which match a search pattern, with a rule like
+//!
+//! ```text
+//! foo($a, $b) ==>> bar($b, $a)
+//! ```
+//!
+//! A placeholder `$name` matches any expression, and a placeholder used twice
+//! in the search pattern matches the same expression twice. Trivia is ignored
+//! while matching, and the rule is applied to the expressions bound to the
+//! placeholders as well.
+//!
+//! The placeholders can be constrained in a `where` clause after the rule, by
+//! the kind of the expression or by a trait which its type, as inferred, must
+//! implement:
+//!
+//! ```text
+//! foo($a) ==>> bar($a) where $a: kind(literal)
+//! String::from($s) ==>> $s.into() where $s: Into<String>
+//! ```
+
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    AstNode, SourceFile, SyntaxElement, SyntaxNode, TextRange, TreeArc, tokenize,
+    SyntaxKind::{self, *},
+    ast,
+};
+use ra_text_edit::TextEditBuilder;
+use rustc_hash::FxHashMap;
+
+use crate::{FileId, SourceChange, SourceFileEdit, db::RootDatabase, symbol_index::SymbolsDatabase};
+
+/// Placeholders are parsed as paths to an identifier with this prefix.
+const PLACEHOLDER_PREFIX: &str = "__ssr_";
+
+/// The expression kinds of the `kind(..)` constraints.
+const KINDS: &[(&str, SyntaxKind)] = &[
+    ("literal", LITERAL),
+    ("path", PATH_EXPR),
+    ("call", CALL_EXPR),
+    ("method_call", METHOD_CALL_EXPR),
+    ("field", FIELD_EXPR),
+    ("ref", REF_EXPR),
+];
+
+struct Rule {
+    search: TreeArc<ast::Expr>,
+    template: Vec<Piece>,
+    constraints: FxHashMap<String, Vec<Constraint>>,
+}
+
+enum Piece {
+    Text(String),
+    /// A placeholder of the replacement. It is `delimited` if the expression
+    /// replacing it doesn't need parentheses, like an argument of a call.
+    Placeholder {
+        name: String,
+        delimited: bool,
+    },
+}
+
+enum Constraint {
+    Kind(SyntaxKind),
+    Implements(TreeArc<ast::TypeRef>),
+}
+
+struct Match<'a> {
+    range: TextRange,
+    placeholders: FxHashMap<String, Binding<'a>>,
+}
+
+struct Binding<'a> {
+    node: &'a SyntaxNode,
+    /// The matches inside of `node`.
+    nested: Vec<Match<'a>>,
+}
+
+pub(crate) fn structural_search_replace(
+    db: &RootDatabase,
+    query: &str,
+) -> Result<SourceChange, String> {
+    let rule = parse_rule(query)?;
+    let mut edits = Vec::new();
+    for &root in db.local_roots().iter() {
+        let mut files: Vec<FileId> = db.source_root(root).files.values().cloned().collect();
+        files.sort();
+        for file_id in files {
+            let file = db.parse(file_id);
+            let mut matches = Vec::new();
+            find_matches(db, file_id, &rule, file.syntax(), &mut matches);
+            if matches.is_empty() {
+                continue;
+            }
+            let mut edit = TextEditBuilder::default();
+            for m in matches.iter() {
+                edit.replace(m.range, render(&rule, m));
+            }
+            edits.push(SourceFileEdit { file_id, edit: edit.finish() });
+        }
+    }
+    Ok(SourceChange::source_file_edits("structural search replace", edits))
+}
+
+fn parse_rule(query: &str) -> Result<Rule, String> {
+    let mut parts = query.split("==>>");
+    let (search, rest) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(search), Some(rest), None) => (search, rest),
+        _ => return Err("expected a rule of the form `search ==>> replacement`".to_string()),
+    };
+    let (template, constraints) = match find_where(rest) {
+        Some(idx) => (&rest[..idx], Some(&rest[idx + "where".len()..])),
+        None => (rest, None),
+    };
+
+    let (search_text, names) = substitute_placeholders(search)?;
+    let search = parse_expr(&search_text)
+        .ok_or_else(|| format!("the search pattern `{}` is not an expression", search.trim()))?;
+    for name in names.iter() {
+        let is_expr = search.syntax().descendants().any(|it| {
+            placeholder_name(it).map_or(false, |placeholder| placeholder == name.as_str())
+        });
+        if !is_expr {
+            return Err(format!("the placeholder `${}` doesn't stand for an expression", name));
+        }
+    }
+
+    let (template_text, template_names) = substitute_placeholders(template)?;
+    if let Some(name) = template_names.iter().find(|it| !names.contains(it)) {
+        return Err(format!("the placeholder `${}` is not in the search pattern", name));
+    }
+    let template = parse_expr(&template_text)
+        .ok_or_else(|| format!("the replacement `{}` is not an expression", template.trim()))?;
+
+    let mut rule =
+        Rule { search, template: template_pieces(&template), constraints: Default::default() };
+    if let Some(constraints) = constraints {
+        for constraint in split_top_level(constraints) {
+            let (name, constraint) = parse_constraint(constraint)?;
+            if !names.contains(&name) {
+                return Err(format!("the placeholder `${}` is not in the search pattern", name));
+            }
+            rule.constraints.entry(name).or_default().push(constraint);
+        }
+    }
+    Ok(rule)
+}
+
+/// The offset of the `where` keyword of the constraints of a rule.
+fn find_where(text: &str) -> Option<usize> {
+    text.match_indices("where").map(|(idx, _)| idx).find(|&idx| {
+        let before = text[..idx].chars().next_back();
+        let after = text[idx + "where".len()..].chars().next();
+        before.map_or(false, char::is_whitespace) && after.map_or(false, char::is_whitespace)
+    })
+}
+
+/// Replaces the placeholder tokens `$name` of `text` with identifiers,
+/// returning the names of the placeholders. A `$` in a literal is left as is.
+fn substitute_placeholders(text: &str) -> Result<(String, Vec<String>), String> {
+    let mut res = String::new();
+    let mut names = Vec::new();
+    let mut offset = 0;
+    let mut tokens = tokenize(text).into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let len = token.len.to_usize();
+        let token_text = &text[offset..offset + len];
+        offset += len;
+        if token.kind != DOLLAR {
+            res.push_str(token_text);
+            continue;
+        }
+        let name = match tokens.peek() {
+            Some(next) if next.kind == IDENT => {
+                let len = next.len.to_usize();
+                let name = text[offset..offset + len].to_string();
+                offset += len;
+                tokens.next();
+                name
+            }
+            _ => return Err("expected the name of a placeholder after `$`".to_string()),
+        };
+        res.push_str(PLACEHOLDER_PREFIX);
+        res.push_str(&name);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok((res, names))
+}
+
+fn parse_expr(text: &str) -> Option<TreeArc<ast::Expr>> {
+    let file = SourceFile::parse(&format!("fn __ssr() {{ {} }}", text));
+    if !file.errors().is_empty() {
+        return None;
+    }
+    let block = file.syntax().descendants().find_map(ast::Block::cast)?;
+    if block.statements().next().is_some() {
+        return None;
+    }
+    Some(block.expr()?.to_owned())
+}
+
+/// The name of the placeholder which `node` of a pattern stands for.
+fn placeholder_name(node: &SyntaxNode) -> Option<String> {
+    if node.kind() != PATH_EXPR {
+        return None;
+    }
+    let text = node.text().to_string();
+    if text.starts_with(PLACEHOLDER_PREFIX) {
+        Some(text[PLACEHOLDER_PREFIX.len()..].to_string())
+    } else {
+        None
+    }
+}
+
+fn template_pieces(template: &ast::Expr) -> Vec<Piece> {
+    let root = template.syntax();
+    let text = root.text().to_string();
+    let start = root.range().start();
+    let mut pieces = Vec::new();
+    let mut cursor = 0;
+    for node in root.descendants() {
+        let name = match placeholder_name(node) {
+            Some(it) => it,
+            None => continue,
+        };
+        let range = node.range();
+        let (from, to) = ((range.start() - start).to_usize(), (range.end() - start).to_usize());
+        if from < cursor {
+            continue;
+        }
+        pieces.push(Piece::Text(text[cursor..from].to_string()));
+        let delimited = node == root
+            || node.parent().map_or(false, |it| {
+                [ARG_LIST, PAREN_EXPR, TUPLE_EXPR, ARRAY_EXPR, NAMED_FIELD].contains(&it.kind())
+            });
+        pieces.push(Piece::Placeholder { name, delimited });
+        cursor = to;
+    }
+    pieces.push(Piece::Text(text[cursor..].to_string()));
+    pieces
+}
+
+/// Splits the constraints of a rule at the commas which aren't in the generic
+/// arguments of a trait.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                res.push(&text[start..idx]);
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    res.push(&text[start..]);
+    res.into_iter().filter(|it| !it.trim().is_empty()).collect()
+}
+
+fn parse_constraint(text: &str) -> Result<(String, Constraint), String> {
+    let text = text.trim();
+    let colon = text.find(':');
+    let (name, bound) = match colon {
+        Some(idx) if text.starts_with('$') => (text[1..idx].trim(), text[idx + 1..].trim()),
+        _ => return Err(format!("expected a constraint like `$a: Trait`, found `{}`", text)),
+    };
+    if bound.starts_with("kind(") && bound.ends_with(')') {
+        let kind = bound["kind(".len()..bound.len() - 1].trim();
+        return match KINDS.iter().find(|(it, _)| *it == kind) {
+            Some(&(_, kind)) => Ok((name.to_string(), Constraint::Kind(kind))),
+            None => Err(format!("unknown expression kind `{}`", kind)),
+        };
+    }
+    let file = SourceFile::parse(&format!("type __Ssr = {};", bound));
+    let type_ref = file
+        .syntax()
+        .descendants()
+        .find_map(ast::TypeAliasDef::cast)
+        .and_then(|it| it.type_ref())
+        .filter(|_| file.errors().is_empty())
+        .ok_or_else(|| format!("the bound `{}` is not a trait", bound))?;
+    Ok((name.to_string(), Constraint::Implements(type_ref.to_owned())))
+}
+
+/// Collects the outermost matches of `node` and its descendants.
+fn find_matches<'a>(
+    db: &RootDatabase,
+    file_id: FileId,
+    rule: &Rule,
+    node: &'a SyntaxNode,
+    matches: &mut Vec<Match<'a>>,
+) {
+    if let Some(m) = try_match(db, file_id, rule, node) {
+        matches.push(m);
+        return;
+    }
+    for child in node.children() {
+        find_matches(db, file_id, rule, child, matches);
+    }
+}
+
+fn try_match<'a>(
+    db: &RootDatabase,
+    file_id: FileId,
+    rule: &Rule,
+    node: &'a SyntaxNode,
+) -> Option<Match<'a>> {
+    ast::Expr::cast(node)?;
+    let mut placeholders = FxHashMap::default();
+    if !match_nodes(rule.search.syntax(), node, &mut placeholders) {
+        return None;
+    }
+    for (name, constraints) in rule.constraints.iter() {
+        let bound = placeholders[name];
+        if !constraints.iter().all(|it| satisfies(db, file_id, bound, it)) {
+            return None;
+        }
+    }
+    let placeholders = placeholders
+        .into_iter()
+        .map(|(name, node)| {
+            let mut nested = Vec::new();
+            find_matches(db, file_id, rule, node, &mut nested);
+            (name, Binding { node, nested })
+        })
+        .collect();
+    Some(Match { range: node.range(), placeholders })
+}
+
+fn match_nodes<'a>(
+    pattern: &SyntaxNode,
+    code: &'a SyntaxNode,
+    placeholders: &mut FxHashMap<String, &'a SyntaxNode>,
+) -> bool {
+    if let Some(name) = placeholder_name(pattern) {
+        if ast::Expr::cast(code).is_none() {
+            return false;
+        }
+        if let Some(bound) = placeholders.get(&name) {
+            return same_tokens(bound, code);
+        }
+        placeholders.insert(name, code);
+        return true;
+    }
+    if pattern.kind() != code.kind() {
+        return false;
+    }
+    let mut pattern_children = pattern.children_with_tokens().filter(|it| !it.kind().is_trivia());
+    let mut code_children = code.children_with_tokens().filter(|it| !it.kind().is_trivia());
+    loop {
+        match (pattern_children.next(), code_children.next()) {
+            (None, None) => return true,
+            (Some(SyntaxElement::Node(pattern)), Some(SyntaxElement::Node(code))) => {
+                if !match_nodes(pattern, code, placeholders) {
+                    return false;
+                }
+            }
+            (Some(SyntaxElement::Token(pattern)), Some(SyntaxElement::Token(code))) => {
+                if pattern.kind() != code.kind() || pattern.text() != code.text() {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Whether the nodes are the same code, ignoring trivia.
+fn same_tokens(first: &SyntaxNode, second: &SyntaxNode) -> bool {
+    let tokens = |node: &SyntaxNode| {
+        node.descendants_with_tokens()
+            .filter_map(|it| match it {
+                SyntaxElement::Token(token) if !token.kind().is_trivia() => {
+                    Some((token.kind(), token.text().clone()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    tokens(first) == tokens(second)
+}
+
+fn satisfies(
+    db: &RootDatabase,
+    file_id: FileId,
+    node: &SyntaxNode,
+    constraint: &Constraint,
+) -> bool {
+    match constraint {
+        Constraint::Kind(kind) => node.kind() == *kind,
+        Constraint::Implements(bound) => {
+            let expr = match ast::Expr::cast(node) {
+                Some(it) => it,
+                None => return false,
+            };
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, node, None);
+            match analyzer.type_of(db, expr) {
+                Some(ty) => analyzer.implements(db, ty, bound).unwrap_or(false),
+                None => false,
+            }
+        }
+    }
+}
+
+fn render(rule: &Rule, m: &Match) -> String {
+    let mut buf = String::new();
+    for piece in rule.template.iter() {
+        match piece {
+            Piece::Text(text) => buf.push_str(text),
+            Piece::Placeholder { name, delimited } => {
+                let binding = &m.placeholders[name];
+                let text = render_binding(rule, binding);
+                let needs_parens =
+                    [BIN_EXPR, RANGE_EXPR, CAST_EXPR, LAMBDA_EXPR, PREFIX_EXPR, REF_EXPR]
+                        .contains(&binding.node.kind());
+                if needs_parens && !delimited {
+                    buf.push('(');
+                    buf.push_str(&text);
+                    buf.push(')');
+                } else {
+                    buf.push_str(&text);
+                }
+            }
+        }
+    }
+    buf
+}
+
+/// The text of the expression bound to a placeholder, with the rule applied
+/// to its nested matches.
+fn render_binding(rule: &Rule, binding: &Binding) -> String {
+    let mut text = binding.node.text().to_string();
+    let start = binding.node.range().start();
+    for m in binding.nested.iter().rev() {
+        let range = (m.range.start() - start).to_usize()..(m.range.end() - start).to_usize();
+        text.replace_range(range, &render(rule, m));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file;
+
+    fn check_ssr(query: &str, before: &str, after: &str) {
+        let (analysis, file_id) = single_file(before);
+        let change = analysis.structural_search_replace(query).unwrap().unwrap();
+        let text = analysis.file_text(file_id).to_string();
+        let actual = match change.source_file_edits.first() {
+            Some(edit) => edit.edit.apply(&text),
+            None => text,
+        };
+        assert_eq!(actual, after);
+    }
+
+    fn check_ssr_error(query: &str, message: &str) {
+        let (analysis, _) = single_file("");
+        let error = analysis.structural_search_replace(query).unwrap().unwrap_err();
+        assert_eq!(error, message);
+    }
+
+    #[test]
+    fn ssr_swaps_arguments() {
+        check_ssr(
+            "foo($a, $b) ==>> bar($b, $a)",
+            "fn main() { foo(1, x + 2); foo (y,\n    3); }",
+            "fn main() { bar(x + 2, 1); bar(3, y); }",
+        );
+    }
+
+    #[test]
+    fn ssr_leaves_dollars_in_literals() {
+        check_ssr(
+            r#"foo($a) ==>> bar($a, "$a")"#,
+            "fn main() { foo(1); }",
+            r#"fn main() { bar(1, "$a"); }"#,
+        );
+    }
+
+    #[test]
+    fn ssr_matches_a_repeated_placeholder_only_with_the_same_expression() {
+        check_ssr(
+            "$a + $a ==>> 2 * $a",
+            "fn main() { let x = y + y; let z = y + x; }",
+            "fn main() { let x = 2 * y; let z = y + x; }",
+        );
+    }
+
+    #[test]
+    fn ssr_applies_to_nested_matches() {
+        check_ssr(
+            "foo($a) ==>> bar($a)",
+            "fn main() { foo(foo(1)); }",
+            "fn main() { bar(bar(1)); }",
+        );
+    }
+
+    #[test]
+    fn ssr_parenthesizes_placeholders() {
+        check_ssr(
+            "foo($a) ==>> $a.len()",
+            "fn main() { foo(x + y); foo(z); }",
+            "fn main() { (x + y).len(); z.len(); }",
+        );
+    }
+
+    #[test]
+    fn ssr_checks_the_kind_of_placeholders() {
+        check_ssr(
+            "foo($a) ==>> bar($a) where $a: kind(literal)",
+            "fn main() { foo(1); foo(x); }",
+            "fn main() { bar(1); foo(x); }",
+        );
+    }
+
+    #[test]
+    fn ssr_checks_the_traits_of_placeholders() {
+        check_ssr(
+            "foo($a) ==>> bar($a) where $a: Tr<u32>",
+            "
+trait Tr<T> {}
+struct A;
+struct B;
+impl Tr<u32> for A {}
+impl Tr<i32> for B {}
+fn foo<T>(t: T) {}
+fn main() { foo(A); foo(B); }
+",
+            "
+trait Tr<T> {}
+struct A;
+struct B;
+impl Tr<u32> for A {}
+impl Tr<i32> for B {}
+fn foo<T>(t: T) {}
+fn main() { bar(A); foo(B); }
+",
+        );
+    }
+
+    #[test]
+    fn ssr_reports_invalid_rules() {
+        check_ssr_error("foo($a)", "expected a rule of the form `search ==>> replacement`");
+        check_ssr_error(
+            "foo($a) ==>> bar($b)",
+            "the placeholder `$b` is not in the search pattern",
+        );
+        check_ssr_error("foo($a ==>> bar($a)", "the search pattern `foo($a` is not an expression");
+        check_ssr_error("$a.$b() ==>> $a", "the placeholder `$b` doesn't stand for an expression");
+        check_ssr_error(
+            "foo($a) ==>> bar($a) where $a: kind(loop)",
+            "unknown expression kind `loop`",
+        );
+    }
+}
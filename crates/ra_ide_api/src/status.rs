@@ -7,29 +7,72 @@ use std::{
 use ra_syntax::{AstNode, TreeArc, SourceFile};
 use ra_db::{
     ParseQuery, FileTextQuery, SourceRootId,
-    salsa::{Database, debug::{DebugQueryTable, TableEntry}},
+    salsa::{Database, SweepStrategy, debug::{DebugQueryTable, TableEntry}},
 };
+use ra_prof::Bytes;
 
 use crate::{
     FileId, db::RootDatabase,
-    symbol_index::{SymbolIndex, LibrarySymbolsQuery},
+    symbol_index::{SymbolIndex, FileSymbolsQuery, LibrarySymbolsQuery},
 };
 
 pub(crate) fn syntax_tree_stats(db: &RootDatabase) -> SyntaxTreeStats {
     db.query(ParseQuery).entries::<SyntaxTreeStats>()
 }
 
+/// The memory retained by the values of each query, measured as the memory
+/// freed when they are discarded, so they are recomputed afterwards.
+pub(crate) fn per_query_memory_usage(db: &mut RootDatabase) -> Vec<(String, Bytes)> {
+    let mut acc = Vec::new();
+    let sweep = SweepStrategy::default().discard_values().sweep_all_revisions();
+    macro_rules! sweep_each_query {
+        ($($q:path)*) => {$(
+            let before = MemoryStats::current().allocated;
+            db.query($q).sweep(sweep);
+            let after = MemoryStats::current().allocated;
+            let q: $q = Default::default();
+            let name = format!("{:?}", q);
+            acc.push((name, before - after));
+        )*}
+    }
+    sweep_each_query![
+        ra_db::ParseQuery
+        hir::db::HirParseQuery
+        hir::db::AstIdMapQuery
+        hir::db::AstIdToNodeQuery
+        hir::db::RawItemsWithSourceMapQuery
+        hir::db::RawItemsQuery
+        hir::db::CrateDefMapQuery
+        hir::db::ImplsInModuleWithSourceMapQuery
+        hir::db::ImplsInModuleQuery
+        hir::db::GenericParamsQuery
+        hir::db::FnSignatureQuery
+        hir::db::BodyWithSourceMapQuery
+        hir::db::BodyHirQuery
+        hir::db::ExprScopesQuery
+        hir::db::InferQuery
+        hir::db::TypeForDefQuery
+        hir::db::FieldTypesQuery
+        hir::db::CallableItemSignatureQuery
+        hir::db::ImplsInCrateQuery
+        FileSymbolsQuery
+        LibrarySymbolsQuery
+    ];
+    acc.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+    acc
+}
+
 pub(crate) fn status(db: &RootDatabase) -> String {
     let files_stats = db.query(FileTextQuery).entries::<FilesStats>();
     let syntax_tree_stats = syntax_tree_stats(db);
     let symbols_stats = db.query(LibrarySymbolsQuery).entries::<LibrarySymbolsStats>();
     format!(
-        "{}\n{}\n{}\n\n\nmemory:\n{}\ngc {:?} seconds ago",
+        "{}\n{}\n{}\n\n\nmemory:\n{}\ngc {} seconds ago",
         files_stats,
         symbols_stats,
         syntax_tree_stats,
         MemoryStats::current(),
-        db.last_gc.elapsed().as_secs(),
+        db.last_gc.map_or("?".to_string(), |it| it.elapsed().as_secs().to_string()),
     )
 }
 
@@ -133,7 +176,7 @@ impl MemoryStats {
 
     #[cfg(not(feature = "jemalloc"))]
     fn current() -> MemoryStats {
-        MemoryStats { allocated: Bytes(0), resident: Bytes(0) }
+        MemoryStats { allocated: ra_prof::allocated_bytes(), resident: Bytes(0) }
     }
 }
 
@@ -142,27 +185,3 @@ impl fmt::Display for MemoryStats {
         write!(fmt, "{} allocated {} resident", self.allocated, self.resident,)
     }
 }
-
-#[derive(Default)]
-struct Bytes(usize);
-
-impl fmt::Display for Bytes {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let bytes = self.0;
-        if bytes < 4096 {
-            return write!(f, "{} bytes", bytes);
-        }
-        let kb = bytes / 1024;
-        if kb < 4096 {
-            return write!(f, "{}kb", kb);
-        }
-        let mb = kb / 1024;
-        write!(f, "{}mb", mb)
-    }
-}
-
-impl std::ops::AddAssign<usize> for Bytes {
-    fn add_assign(&mut self, x: usize) {
-        self.0 += x;
-    }
-}
@@ -34,17 +34,18 @@ use ra_syntax::{
     SyntaxKind::{self, *},
     ast::{self, NameOwner},
     WalkEvent,
-    TextRange,
+    TextRange, TextUnit,
 };
 use ra_db::{
     SourceRootId, SourceDatabase,
-    salsa::{self, ParallelDatabase},
+    salsa,
 };
+#[cfg(not(target_arch = "wasm32"))]
 use rayon::prelude::*;
 
 use crate::{
     FileId, Query,
-    db::RootDatabase,
+    db::{self, RootDatabase},
 };
 
 #[salsa::query_group(SymbolsDatabaseStorage)]
@@ -74,20 +75,8 @@ fn file_symbols(db: &impl SymbolsDatabase, file_id: FileId) -> Arc<SymbolIndex>
 }
 
 pub(crate) fn world_symbols(db: &RootDatabase, query: Query) -> Vec<FileSymbol> {
-    /// Need to wrap Snapshot to provide `Clone` impl for `map_with`
-    struct Snap(salsa::Snapshot<RootDatabase>);
-    impl Clone for Snap {
-        fn clone(&self) -> Snap {
-            Snap(self.0.snapshot())
-        }
-    }
-
     let buf: Vec<Arc<SymbolIndex>> = if query.libs {
-        let snap = Snap(db.snapshot());
-        db.library_roots()
-            .par_iter()
-            .map_with(snap, |db, &lib_id| db.0.library_symbols(lib_id))
-            .collect()
+        db::par_map(db, &db.library_roots(), |db, &lib_id| db.library_symbols(lib_id))
     } else {
         let mut files = Vec::new();
         for &root in db.local_roots().iter() {
@@ -95,8 +84,7 @@ pub(crate) fn world_symbols(db: &RootDatabase, query: Query) -> Vec<FileSymbol>
             files.extend(sr.files.values().map(|&it| it))
         }
 
-        let snap = Snap(db.snapshot());
-        files.par_iter().map_with(snap, |db, &file_id| db.0.file_symbols(file_id)).collect()
+        db::par_map(db, &files, |db, &file_id| db.file_symbols(file_id))
     };
     query.search(&buf)
 }
@@ -140,7 +128,10 @@ impl SymbolIndex {
         fn cmp(s1: &FileSymbol, s2: &FileSymbol) -> Ordering {
             unicase::Ascii::new(s1.name.as_str()).cmp(&unicase::Ascii::new(s2.name.as_str()))
         }
+        #[cfg(not(target_arch = "wasm32"))]
         symbols.par_sort_by(cmp);
+        #[cfg(target_arch = "wasm32")]
+        symbols.sort_by(cmp);
         symbols.dedup_by(|s1, s2| cmp(s1, s2) == Ordering::Equal);
         let names = symbols.iter().map(|it| it.name.as_str().to_lowercase());
         let map = fst::Map::from_iter(names.zip(0u64..)).unwrap();
@@ -155,16 +146,84 @@ impl SymbolIndex {
         self.map.as_fst().size() + self.symbols.len() * mem::size_of::<FileSymbol>()
     }
 
-    pub(crate) fn for_files(
-        files: impl ParallelIterator<Item = (FileId, TreeArc<SourceFile>)>,
-    ) -> SymbolIndex {
+    pub(crate) fn for_files(files: Vec<(FileId, TreeArc<SourceFile>)>) -> SymbolIndex {
         let symbols = files
+            .into_iter()
             .flat_map(|(file_id, file)| source_file_to_file_symbols(&file, file_id))
             .collect::<Vec<_>>();
         SymbolIndex::new(symbols)
     }
+
+    /// Saves the symbols as text, one per line, with their files given by
+    /// `file_idx`, as file ids are not the same in each session.
+    pub(crate) fn save(&self, file_idx: impl Fn(FileId) -> Option<usize>) -> Option<String> {
+        let mut res = format!("symbols {}\n", SAVE_FORMAT_VERSION);
+        for symbol in self.symbols.iter() {
+            let kind = SYMBOL_KINDS.iter().position(|&it| it == symbol.ptr.kind())?;
+            let range = symbol.ptr.range();
+            let name_range = match symbol.name_range {
+                Some(it) => format!("{}\t{}", it.start().to_usize(), it.end().to_usize()),
+                None => "-\t-".to_string(),
+            };
+            res += &format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                file_idx(symbol.file_id)?,
+                kind,
+                range.start().to_usize(),
+                range.end().to_usize(),
+                name_range,
+                symbol.name,
+                symbol.container_name.as_ref().map_or("-", |it| it.as_str()),
+            );
+        }
+        Some(res)
+    }
+
+    /// Loads the symbols saved with `save`, with the files of the indices
+    /// given by `file_id`.
+    pub(crate) fn load(
+        text: &str,
+        file_id: impl Fn(usize) -> Option<FileId>,
+    ) -> Option<SymbolIndex> {
+        let mut lines = text.lines();
+        if lines.next()? != format!("symbols {}", SAVE_FORMAT_VERSION) {
+            return None;
+        }
+        let mut symbols = Vec::new();
+        for line in lines {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 8 {
+                return None;
+            }
+            let offset = |idx: usize| fields[idx].parse::<u32>().ok().map(TextUnit::from);
+            let range = TextRange::from_to(offset(2)?, offset(3)?);
+            let name_range = match fields[4] {
+                "-" => None,
+                _ => Some(TextRange::from_to(offset(4)?, offset(5)?)),
+            };
+            let kind = *SYMBOL_KINDS.get(fields[1].parse::<usize>().ok()?)?;
+            symbols.push(FileSymbol {
+                file_id: file_id(fields[0].parse().ok()?)?,
+                name: fields[6].into(),
+                ptr: SyntaxNodePtr::from_parts(range, kind),
+                name_range,
+                container_name: match fields[7] {
+                    "-" => None,
+                    it => Some(it.into()),
+                },
+            });
+        }
+        Some(SymbolIndex::new(symbols))
+    }
 }
 
+/// The version of the format of saved symbols, which changes with it.
+const SAVE_FORMAT_VERSION: u32 = 1;
+
+/// The kinds of the symbols, which are saved as their index here.
+const SYMBOL_KINDS: &[SyntaxKind] =
+    &[FN_DEF, STRUCT_DEF, ENUM_DEF, TRAIT_DEF, MODULE, TYPE_ALIAS_DEF, CONST_DEF, STATIC_DEF];
+
 impl Query {
     pub(crate) fn search(self, indices: &[Arc<SymbolIndex>]) -> Vec<FileSymbol> {
         let mut op = fst::map::OpBuilder::new();
@@ -214,7 +273,10 @@ pub(crate) struct FileSymbol {
     pub(crate) container_name: Option<SmolStr>,
 }
 
-fn source_file_to_file_symbols(source_file: &SourceFile, file_id: FileId) -> Vec<FileSymbol> {
+pub(crate) fn source_file_to_file_symbols(
+    source_file: &SourceFile,
+    file_id: FileId,
+) -> Vec<FileSymbol> {
     let mut symbols = Vec::new();
     let mut stack = Vec::new();
 
@@ -273,11 +335,12 @@ fn to_file_symbol(node: &SyntaxNode, file_id: FileId) -> Option<FileSymbol> {
 
 #[cfg(test)]
 mod tests {
-    use ra_syntax::SmolStr;
+    use ra_syntax::{SmolStr, SourceFile};
     use crate::{
         display::NavigationTarget,
         mock_analysis::single_file,
-        Query,
+        FileId, Query,
+        symbol_index::{SymbolIndex, source_file_to_file_symbols},
 };
 
     #[test]
@@ -323,6 +386,25 @@ mod foo {
         assert_eq!(s.container_name(), Some(&SmolStr::new("foo")));
     }
 
+    #[test]
+    fn test_save_and_load_symbols() {
+        let file = SourceFile::parse(
+            r#"
+mod foo {
+    pub struct Bar;
+    impl Bar { fn baz() {} }
+}
+static QUX: u32 = 0;
+"#,
+        );
+        let index = SymbolIndex::new(source_file_to_file_symbols(&file, FileId(7)));
+        let saved = index.save(|file_id| Some(file_id.0 as usize)).unwrap();
+        let loaded = SymbolIndex::load(&saved, |idx| Some(FileId(idx as u32))).unwrap();
+        assert_eq!(index.len(), 4);
+        assert_eq!(loaded, index);
+        assert!(SymbolIndex::load(&saved.replace("symbols 1", "symbols 0"), |_| None).is_none());
+    }
+
     fn get_symbols_matching(text: &str, query: &str) -> Vec<NavigationTarget> {
         let (analysis, _) = single_file(text);
         analysis.symbol_search(Query::new(query.into())).unwrap()
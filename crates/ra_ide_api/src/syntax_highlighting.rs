@@ -1,23 +1,215 @@
-use rustc_hash::FxHashSet;
+use std::hash::{Hash, Hasher};
 
-use ra_syntax::{ast, AstNode, TextRange, Direction, SyntaxKind::*, SyntaxElement};
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+
+use ra_syntax::{
+    ast::{self, NameOwner}, AstNode, SourceFile, SyntaxNode, TextRange, Direction, SyntaxKind::*,
+    SyntaxElement, SmolStr,
+};
 use ra_db::SourceDatabase;
+use hir::{source_binder, Attrs, Crate, Either, SourceAnalyzer};
 
-use crate::{FileId, db::RootDatabase};
+use crate::{FileId, db::RootDatabase, doc_code::doc_code_blocks};
 
 #[derive(Debug)]
 pub struct HighlightedRange {
     pub range: TextRange,
     pub tag: &'static str,
+    /// For the names of local bindings and their uses, a hash which is the
+    /// same for all the names of a binding, to color each binding differently.
+    pub binding_hash: Option<u64>,
 }
 
 pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRange> {
     let source_file = db.parse(file_id);
 
+    let krate = source_binder::module_from_file_id(db, file_id).and_then(|it| it.krate(db));
+
+    let mut res = Vec::new();
+    highlight_syntax(source_file.syntax(), &mut res);
+    let mut binding_hashes = FxHashMap::default();
+    for node in source_file.syntax().descendants().filter(|it| is_body_owner(it)) {
+        if let Some(def) = source_binder::def_with_body_from_child_node(db, file_id, node) {
+            for op in def.unsafe_operations(db) {
+                res.push(HighlightedRange {
+                    range: op.expr.range(),
+                    tag: "unsafe",
+                    binding_hash: None,
+                })
+            }
+        }
+        highlight_bindings(db, file_id, krate, node, &mut res, &mut binding_hashes);
+    }
+    let sema = hir::Semantics::new(db);
+    sema.parse(file_id);
+    for name_ref in source_file.syntax().descendants().filter_map(ast::NameRef::cast) {
+        if sema.resolve_name_ref(name_ref).and_then(|it| it.deprecation(db)).is_some() {
+            res.push(HighlightedRange {
+                range: name_ref.syntax().range(),
+                tag: "deprecated",
+                binding_hash: None,
+            });
+        }
+    }
+    for range in res.iter_mut() {
+        if range.tag == "function" || range.tag == "text" {
+            range.binding_hash = binding_hashes.get(&range.range).cloned();
+        }
+    }
+    res
+}
+
+fn is_body_owner(node: &SyntaxNode) -> bool {
+    match node.kind() {
+        FN_DEF | CONST_DEF | STATIC_DEF => true,
+        _ => false,
+    }
+}
+
+/// Highlights the local bindings of the body of `owner` and their uses, which
+/// are `mutable` for the `mut` bindings and `consuming` when they move their
+/// value, and records the hash of each binding for the ranges of its names.
+fn highlight_bindings(
+    db: &RootDatabase,
+    file_id: FileId,
+    krate: Option<Crate>,
+    owner: &SyntaxNode,
+    res: &mut Vec<HighlightedRange>,
+    binding_hashes: &mut FxHashMap<TextRange, u64>,
+) {
+    let analyzer = SourceAnalyzer::new(db, file_id, owner, None);
+    // The nested items have their own bodies.
+    let nodes: Vec<&SyntaxNode> = owner
+        .descendants()
+        .filter(|node| node.ancestors().find(|it| is_body_owner(it)) == Some(owner))
+        .collect();
+
+    // The bindings by the range of their pattern, with their hash and whether
+    // they are mutable. Bindings which shadow each other get different hashes.
+    let mut bindings: FxHashMap<TextRange, (u64, bool)> = FxHashMap::default();
+    let mut shadow_counts: FxHashMap<SmolStr, u32> = FxHashMap::default();
+    for bind_pat in nodes.iter().filter_map(|it| ast::BindPat::cast(it)) {
+        let name = match bind_pat.name() {
+            Some(it) => it,
+            None => continue,
+        };
+        let text = name.text().clone();
+        let shadow_count = shadow_counts.entry(text.clone()).or_default();
+        let mut hasher = FxHasher::default();
+        (text, *shadow_count).hash(&mut hasher);
+        *shadow_count += 1;
+        let hash = hasher.finish();
+        bindings.insert(bind_pat.syntax().range(), (hash, bind_pat.is_mutable()));
+        binding_hashes.insert(name.syntax().range(), hash);
+        if bind_pat.is_mutable() {
+            res.push(HighlightedRange {
+                range: name.syntax().range(),
+                tag: "mutable",
+                binding_hash: None,
+            });
+        }
+    }
+
+    for name_ref in nodes.iter().filter_map(|it| ast::NameRef::cast(it)) {
+        let expr = match local_use(name_ref) {
+            Some(it) => it,
+            None => continue,
+        };
+        let pat = match analyzer.resolve_local_name(name_ref).map(|it| it.ptr()) {
+            Some(Either::A(pat)) => pat,
+            _ => continue,
+        };
+        let &(hash, is_mutable) = match bindings.get(&pat.syntax_node_ptr().range()) {
+            Some(it) => it,
+            None => continue,
+        };
+        let range = name_ref.syntax().range();
+        binding_hashes.insert(range, hash);
+        if is_mutable {
+            res.push(HighlightedRange { range, tag: "mutable", binding_hash: None });
+        }
+        let is_copy = match expr {
+            Some(expr) => analyzer.type_of(db, expr).map_or(true, |ty| ty.is_copy(db, krate)),
+            // A shorthand field of a struct literal.
+            None => analyzer
+                .type_of_field_shorthand(db, name_ref)
+                .map_or(true, |ty| ty.is_copy(db, krate)),
+        };
+        if !is_copy && expr.map_or(true, |it| is_moved(db, &analyzer, it)) {
+            res.push(HighlightedRange { range, tag: "consuming", binding_hash: None });
+        }
+    }
+}
+
+/// If `name_ref` may be the use of a local binding, the path expression it
+/// names, or `None` if it's the shorthand of a struct literal field.
+fn local_use(name_ref: &ast::NameRef) -> Option<Option<&ast::Expr>> {
+    let parent = name_ref.syntax().parent()?;
+    if let Some(field) = ast::NamedField::cast(parent) {
+        return if field.expr().is_none() { Some(None) } else { None };
+    }
+    let path = ast::Path::cast(ast::PathSegment::cast(parent)?.syntax().parent()?)?;
+    if path.qualifier().is_some() {
+        return None;
+    }
+    let path_expr = ast::PathExpr::cast(path.syntax().parent()?)?;
+    Some(Some(ast::Expr::cast(path_expr.syntax())?))
+}
+
+/// Whether the value of `expr` is moved out of it by the expression using it,
+/// rather than borrowed, assigned or compared.
+fn is_moved(db: &RootDatabase, analyzer: &SourceAnalyzer, expr: &ast::Expr) -> bool {
+    let mut node = expr.syntax();
+    while let Some(paren) = node.parent().and_then(ast::ParenExpr::cast) {
+        node = paren.syntax();
+    }
+    let parent = match node.parent() {
+        Some(it) => it,
+        None => return true,
+    };
+    let is_first_child = parent.children().next() == Some(node);
+    match parent.kind() {
+        REF_EXPR | FIELD_EXPR | MATCH_EXPR | CONDITION => false,
+        INDEX_EXPR => !is_first_child,
+        BIN_EXPR => {
+            let op = ast::BinExpr::cast(parent).and_then(|it| it.op_kind());
+            match op {
+                Some(ast::BinOp::Assignment) => !is_first_child,
+                Some(op) => !is_comparison(op),
+                None => true,
+            }
+        }
+        METHOD_CALL_EXPR if is_first_child => {
+            let call = ast::MethodCallExpr::cast(parent).unwrap();
+            let function = match analyzer.resolve_method_call(call) {
+                Some(it) => it,
+                None => return false,
+            };
+            let fn_def = function.source(db).1;
+            let self_param = fn_def.param_list().and_then(|it| it.self_param());
+            self_param.map_or(false, |it| it.kind() == ast::SelfParamKind::Owned)
+        }
+        _ => true,
+    }
+}
+
+fn is_comparison(op: ast::BinOp) -> bool {
+    match op {
+        ast::BinOp::EqualityTest
+        | ast::BinOp::NegatedEqualityTest
+        | ast::BinOp::LesserEqualTest
+        | ast::BinOp::GreaterEqualTest
+        | ast::BinOp::LesserTest
+        | ast::BinOp::GreaterTest => true,
+        _ => false,
+    }
+}
+
+/// Highlights the tree of `root`, and the Rust code blocks of its doc comments.
+fn highlight_syntax(root: &SyntaxNode, res: &mut Vec<HighlightedRange>) {
     // Visited nodes to handle highlighting priorities
     let mut highlighted: FxHashSet<SyntaxElement> = FxHashSet::default();
-    let mut res = Vec::new();
-    for node in source_file.syntax().descendants_with_tokens() {
+    for node in root.descendants_with_tokens() {
         if highlighted.contains(&node) {
             continue;
         }
@@ -47,6 +239,7 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
                                 res.push(HighlightedRange {
                                     range: TextRange::from_to(range_start, range_end),
                                     tag: "macro",
+                                    binding_hash: None,
                                 })
                             }
                         }
@@ -55,9 +248,22 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
                 continue;
             }
         };
-        res.push(HighlightedRange { range: node.range(), tag })
+        res.push(HighlightedRange { range: node.range(), tag, binding_hash: None })
+    }
+    for node in root.descendants() {
+        for code in doc_code_blocks(node) {
+            let file = SourceFile::parse(&code.text);
+            let mut injected = Vec::new();
+            highlight_syntax(file.syntax(), &mut injected);
+            res.extend(injected.into_iter().filter_map(|it| {
+                Some(HighlightedRange {
+                    range: code.map_range_up(it.range)?,
+                    tag: it.tag,
+                    binding_hash: None,
+                })
+            }));
+        }
     }
-    res
 }
 
 #[cfg(test)]
@@ -78,4 +284,149 @@ fn main() {}
         let result = analysis.highlight(file_id);
         assert_debug_snapshot_matches!("highlighting", result);
     }
+
+    #[test]
+    fn highlights_unsafe_operations() {
+        let text = r#"
+unsafe fn foo() {}
+fn main(ptr: *const u32) {
+    unsafe { foo(); *ptr; }
+    *ptr;
+}
+"#;
+        let (analysis, file_id) = single_file(text);
+        let unsafe_ops: Vec<_> = analysis
+            .highlight(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.tag == "unsafe")
+            .map(|it| &text[it.range])
+            .collect();
+        assert_eq!(unsafe_ops, vec!["foo()", "*ptr", "*ptr"]);
+    }
+
+    #[test]
+    fn highlights_mutable_and_consuming_uses() {
+        let text = r#"
+struct Foo;
+#[derive(Clone, Copy)]
+struct Bar;
+struct Wrapper { foo: Foo }
+impl Foo {
+    fn by_ref(&self) {}
+    fn by_value(self) {}
+}
+fn take_foo(foo: Foo) {}
+fn take_bar(bar: Bar) {}
+fn main() {
+    let mut x = 1;
+    x += 1;
+    let a = Foo;
+    a.by_ref();
+    let r = &a;
+    a.by_value();
+    let b = Foo;
+    take_foo(b);
+    let bar = Bar;
+    take_bar(bar);
+    let foo = Foo;
+    let w = Wrapper { foo };
+}
+"#;
+        let (analysis, file_id) = single_file(text);
+        let modifiers: Vec<_> = analysis
+            .highlight(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.tag == "mutable" || it.tag == "consuming")
+            .map(|it| (&text[it.range], it.tag))
+            .collect();
+        assert_eq!(
+            modifiers,
+            vec![
+                ("x", "mutable"),
+                ("x", "mutable"),
+                ("a", "consuming"),
+                ("b", "consuming"),
+                ("foo", "consuming"),
+            ]
+        );
+    }
+
+    #[test]
+    fn hashes_bindings() {
+        let text = r#"
+fn main() {
+    let x = 1;
+    let y = x;
+    let x = y;
+    x;
+}
+"#;
+        let (analysis, file_id) = single_file(text);
+        let hashes: Vec<_> = analysis
+            .highlight(file_id)
+            .unwrap()
+            .into_iter()
+            .filter_map(|it| Some((&text[it.range], it.binding_hash?)))
+            .collect();
+        let names: Vec<_> = hashes.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["x", "y", "x", "x", "y", "x"]);
+        assert_eq!(hashes[0].1, hashes[2].1);
+        assert_eq!(hashes[1].1, hashes[4].1);
+        assert_eq!(hashes[3].1, hashes[5].1);
+        assert_ne!(hashes[0].1, hashes[3].1);
+    }
+
+    #[test]
+    fn highlights_deprecated_items() {
+        let text = r#"
+#[deprecated]
+fn old() {}
+struct S { #[deprecated(note = "use new")] old: u32, new: u32 }
+fn main(s: S) {
+    old();
+    s.old;
+    s.new;
+}
+"#;
+        let (analysis, file_id) = single_file(text);
+        let deprecated: Vec<_> = analysis
+            .highlight(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.tag == "deprecated")
+            .map(|it| it.range.start().to_usize())
+            .collect();
+        assert_eq!(deprecated, vec![text.find("old();").unwrap(), text.find("old;").unwrap()]);
+    }
+
+    #[test]
+    fn highlights_rust_code_blocks_of_docs() {
+        let text = r#"
+/// Adds one.
+///
+/// ```
+/// let x = add_one(1);
+/// ```
+///
+/// ```text
+/// let y = 2;
+/// ```
+fn add_one(x: u32) -> u32 { x + 1 }
+"#;
+        let (analysis, file_id) = single_file(text);
+        let docs_end = text.find("fn add_one").unwrap();
+        let injected: Vec<_> = analysis
+            .highlight(file_id)
+            .unwrap()
+            .into_iter()
+            .filter(|it| it.range.end().to_usize() < docs_end && it.tag != "comment")
+            .map(|it| (&text[it.range], it.tag))
+            .collect();
+        assert_eq!(
+            injected,
+            vec![("let", "keyword"), ("x", "function"), ("add_one", "text"), ("1", "literal")]
+        );
+    }
 }
@@ -0,0 +1,69 @@
+//! JavaScript bindings to the single file features of `ra_ide_api`, for
+//! in-browser playgrounds. Build with `wasm-pack build` in this directory.
+//!
+//! The results are JSON strings, with the ranges as pairs of byte offsets.
+use ra_ide_api::{Analysis, CompletionOptions, FileId, FilePosition};
+use ra_syntax::{TextRange, TextUnit};
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+/// The analysis of a single file, without any dependencies or stdlib. Create
+/// a new one when the text of the file changes.
+#[wasm_bindgen]
+pub struct SingleFile {
+    analysis: Analysis,
+    file_id: FileId,
+}
+
+#[wasm_bindgen]
+impl SingleFile {
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: String) -> SingleFile {
+        let (analysis, file_id) = Analysis::from_single_file(text);
+        SingleFile { analysis, file_id }
+    }
+
+    /// The syntax tree of the file, as text.
+    pub fn syntax_tree(&self) -> String {
+        self.analysis.syntax_tree(self.file_id, None)
+    }
+
+    /// The highlighted ranges, as `[{ "range": [start, end], "tag": tag }]`.
+    pub fn highlight(&self) -> String {
+        let ranges = self.analysis.highlight(self.file_id).unwrap_or_default();
+        let ranges = ranges
+            .into_iter()
+            .map(|it| json!({ "range": range_to_json(it.range), "tag": it.tag }))
+            .collect();
+        Value::Array(ranges).to_string()
+    }
+
+    /// The completions at `offset`, as
+    /// `[{ "label": label, "kind": kind, "detail": detail, "range": [start, end] }]`,
+    /// where the range is the one replaced by the completion.
+    pub fn completions(&self, offset: u32) -> String {
+        let position = FilePosition { file_id: self.file_id, offset: TextUnit::from(offset) };
+        let items = self
+            .analysis
+            .completions(position, &CompletionOptions::default())
+            .ok()
+            .and_then(|it| it)
+            .unwrap_or_default();
+        let items = items
+            .iter()
+            .map(|it| {
+                json!({
+                    "label": it.label(),
+                    "kind": it.kind().map(|kind| format!("{:?}", kind)),
+                    "detail": it.detail(),
+                    "range": range_to_json(it.source_range()),
+                })
+            })
+            .collect();
+        Value::Array(items).to_string()
+    }
+}
+
+fn range_to_json(range: TextRange) -> Value {
+    json!([range.start().to_usize(), range.end().to_usize()])
+}
@@ -2,8 +2,12 @@ use lsp_types::{
     CodeActionProviderCapability, CodeLensOptions, CompletionOptions, DocumentOnTypeFormattingOptions,
     ExecuteCommandOptions, FoldingRangeProviderCapability, RenameOptions, RenameProviderCapability,
     ServerCapabilities, SignatureHelpOptions, TextDocumentSyncCapability, TextDocumentSyncKind,
-    TextDocumentSyncOptions, ImplementationProviderCapability,
+    TextDocumentSyncOptions, ImplementationProviderCapability, SaveOptions, CodeActionOptions,
+    WorkspaceCapability, WorkspaceFolderCapability, WorkspaceFolderCapabilityChangeNotifications,
 };
+use serde_json::Value;
+
+use crate::config::OffsetEncoding;
 
 pub fn server_capabilities() -> ServerCapabilities {
     ServerCapabilities {
@@ -12,7 +16,7 @@ pub fn server_capabilities() -> ServerCapabilities {
             change: Some(TextDocumentSyncKind::Full),
             will_save: None,
             will_save_wait_until: None,
-            save: None,
+            save: Some(SaveOptions { include_text: Some(false) }),
         })),
         hover_provider: Some(true),
         completion_provider: Some(CompletionOptions {
@@ -29,7 +33,13 @@ pub fn server_capabilities() -> ServerCapabilities {
         document_highlight_provider: Some(true),
         document_symbol_provider: Some(true),
         workspace_symbol_provider: Some(true),
-        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+            code_action_kinds: Some(vec![
+                "quickfix".to_string(),
+                "refactor".to_string(),
+                "source.organizeImports".to_string(),
+            ]),
+        })),
         code_lens_provider: Some(CodeLensOptions { resolve_provider: Some(true) }),
         document_formatting_provider: Some(true),
         document_range_formatting_provider: None,
@@ -45,6 +55,54 @@ pub fn server_capabilities() -> ServerCapabilities {
         execute_command_provider: Some(ExecuteCommandOptions {
             commands: vec!["apply_code_action".to_string()],
         }),
-        workspace: None,
+        workspace: Some(WorkspaceCapability {
+            workspace_folders: Some(WorkspaceFolderCapability {
+                supported: Some(true),
+                change_notifications: Some(WorkspaceFolderCapabilityChangeNotifications::Bool(
+                    true,
+                )),
+            }),
+        }),
+    }
+}
+
+/// Picks the encoding of the columns of positions among the ones of the
+/// `general.positionEncodings` client capability, preferring UTF-8, and
+/// returns the server capabilities with the chosen `positionEncoding`.
+///
+/// `lsp_types` doesn't know about these capabilities yet, hence the raw
+/// params of the `initialize` request.
+pub fn negotiate_capabilities(initialize_params: &Value) -> (Value, OffsetEncoding) {
+    let supports_utf8 = initialize_params
+        .pointer("/capabilities/general/positionEncodings")
+        .and_then(|it| it.as_array())
+        .map_or(false, |encodings| encodings.iter().any(|it| it == "utf-8"));
+    let (encoding, name) = if supports_utf8 {
+        (OffsetEncoding::Utf8, "utf-8")
+    } else {
+        (OffsetEncoding::Utf16, "utf-16")
+    };
+    let mut caps = serde_json::to_value(server_capabilities()).unwrap();
+    caps["positionEncoding"] = name.into();
+    (caps, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn negotiates_utf8_when_supported() {
+        let params =
+            json!({ "capabilities": { "general": { "positionEncodings": ["utf-8", "utf-16"] } } });
+        let (caps, encoding) = negotiate_capabilities(&params);
+        assert_eq!(encoding, OffsetEncoding::Utf8);
+        assert_eq!(caps["positionEncoding"], "utf-8");
+
+        let (caps, encoding) = negotiate_capabilities(&json!({ "capabilities": {} }));
+        assert_eq!(encoding, OffsetEncoding::Utf16);
+        assert_eq!(caps["positionEncoding"], "utf-16");
     }
 }
@@ -1,5 +1,11 @@
+use std::process::{Command, Stdio};
+
+use failure::format_err;
+use serde::Deserialize;
+
 use crate::{
     project_model::{self, TargetKind},
+    req,
     server_world::ServerWorld,
     Result
 };
@@ -41,6 +47,19 @@ pub(crate) fn runnable_args(
             res.push(name.to_string());
             res.push("--nocapture".to_string());
         }
+        RunnableKind::DocTest { test_id } => {
+            // Doc tests are only in libraries, and `--doc` can't be mixed with
+            // the other target options.
+            res.push("test".to_string());
+            if let Some(spec) = spec {
+                res.push("--package".to_string());
+                res.push(spec.package);
+            }
+            res.push("--doc".to_string());
+            res.push("--".to_string());
+            res.push(test_id.to_string());
+            res.push("--nocapture".to_string());
+        }
         RunnableKind::Bin => {
             res.push("run".to_string());
             if let Some(spec) = spec {
@@ -51,6 +70,59 @@ pub(crate) fn runnable_args(
     Ok(res)
 }
 
+/// A line of the output of cargo with `--message-format=json`.
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    executable: Option<String>,
+}
+
+/// The program to launch in a debugger to debug `runnable`, found by building
+/// its `cargo` invocation with `--message-format=json`. The arguments after
+/// `--` are the arguments of the program.
+pub(crate) fn debug_configuration(runnable: &req::Runnable) -> Result<req::DebugConfiguration> {
+    let mut args = runnable.args.splitn(2, |it| it == "--");
+    let cargo_args = args.next().unwrap_or_default();
+    let program_args = args.next().unwrap_or_default().to_vec();
+    let mut build_args = match cargo_args.split_first() {
+        Some((command, rest)) if command == "test" || command == "bench" => {
+            let mut res = vec![command.to_string(), "--no-run".to_string()];
+            res.extend(rest.iter().cloned());
+            res
+        }
+        Some((command, rest)) if command == "run" => {
+            let mut res = vec!["build".to_string()];
+            res.extend(rest.iter().cloned());
+            res
+        }
+        _ => return Err(format_err!("can't debug `{} {}`", runnable.bin, runnable.args.join(" "))),
+    };
+    build_args.push("--message-format=json".to_string());
+
+    let mut cargo = Command::new(&runnable.bin);
+    cargo.args(&build_args).envs(&runnable.env).stdout(Stdio::piped()).stderr(Stdio::null());
+    if let Some(cwd) = &runnable.cwd {
+        cargo.current_dir(cwd);
+    }
+    let output = cargo.output()?;
+    if !output.status.success() {
+        return Err(format_err!("`{} {}` failed", runnable.bin, build_args.join(" ")));
+    }
+    let program = String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .filter(|it| it.reason == "compiler-artifact")
+        .filter_map(|it| it.executable)
+        .last()
+        .ok_or_else(|| format_err!("no executable for `{}`", runnable.label))?;
+    Ok(req::DebugConfiguration {
+        program,
+        args: program_args,
+        env: runnable.env.clone(),
+        cwd: runnable.cwd.clone(),
+    })
+}
+
 pub struct CargoTargetSpec {
     pub package: String,
     pub target: String,
@@ -64,7 +136,7 @@ impl CargoTargetSpec {
             None => return Ok(None),
         };
         let file_id = world.analysis().crate_root(crate_id)?;
-        let path = world.vfs.read().file2path(ra_vfs::VfsFile(file_id.0.into()));
+        let path = world.file_id_to_path(file_id);
         let res = world.workspaces.iter().find_map(|ws| match ws {
             project_model::ProjectWorkspace::Cargo { cargo, .. } => {
                 let tgt = cargo.target_by_root(&path)?;
@@ -102,7 +174,7 @@ impl CargoTargetSpec {
             TargetKind::Lib => {
                 buf.push("--lib".to_string());
             }
-            TargetKind::Other => (),
+            TargetKind::BuildScript | TargetKind::Other => (),
         }
     }
 }
@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+
+use ra_ide_api::CompletionOptions;
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Deserializer};
+
+use crate::project_model::CargoConfig;
+
+/// Client provided configuration, sent as the initialization options and
+/// again each time the user changes it.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    /// Whether the client supports our custom highlighting publishing decorations.
+    /// This is different to the highlightingOn setting, which is whether the user
+    /// wants our custom highlighting to be used.
+    ///
+    /// Defaults to `false`
+    #[serde(deserialize_with = "nullable_bool_false")]
+    pub publish_decorations: bool,
+
+    /// Whether or not the workspace loaded notification should be sent
+    ///
+    /// Defaults to `true`
+    #[serde(deserialize_with = "nullable_bool_true")]
+    pub show_workspace_loaded: bool,
+
+    /// Whether the client shows the `$/progress` notifications of the
+    /// workspace loading and indexing, like with the `window.workDoneProgress`
+    /// capability.
+    ///
+    /// Defaults to `false`
+    #[serde(deserialize_with = "nullable_bool_false")]
+    pub work_done_progress: bool,
+
+    /// The codes of the diagnostics which should not be published, like
+    /// `unresolved_import`.
+    ///
+    /// Defaults to none
+    #[serde(deserialize_with = "nullable_default")]
+    pub disabled_diagnostics: FxHashSet<String>,
+
+    /// Whether `cargo check` should be run when a file is saved, publishing
+    /// its diagnostics along with ours.
+    ///
+    /// Defaults to `false`
+    #[serde(deserialize_with = "nullable_bool_false")]
+    pub cargo_check_on_save: bool,
+
+    /// Whether `cargo check` should be run when loading a Cargo workspace,
+    /// to run the build scripts and build the proc macros.
+    ///
+    /// Defaults to `false`
+    #[serde(deserialize_with = "nullable_bool_false")]
+    pub cargo_load_out_dirs_from_check: bool,
+
+    /// Whether `cfg(test)` is enabled in the crates of the workspace members,
+    /// to analyze their tests.
+    ///
+    /// Defaults to `true`
+    #[serde(deserialize_with = "nullable_bool_true")]
+    pub cargo_cfg_test: bool,
+
+    /// Whether all the features of the workspace members are enabled, rather
+    /// than the ones of `cargo_features`.
+    ///
+    /// Defaults to `true`
+    #[serde(deserialize_with = "nullable_bool_true")]
+    pub cargo_all_features: bool,
+
+    /// Whether the default features of the workspace members are disabled.
+    ///
+    /// Defaults to `false`
+    #[serde(deserialize_with = "nullable_bool_false")]
+    pub cargo_no_default_features: bool,
+
+    /// The features of the workspace members to enable, like `foo` or
+    /// `dep/bar`.
+    ///
+    /// Defaults to none
+    #[serde(deserialize_with = "nullable_default")]
+    pub cargo_features: Vec<String>,
+
+    /// Whether `()` are added after the name of a completed function.
+    ///
+    /// Defaults to `true`
+    #[serde(deserialize_with = "nullable_bool_true")]
+    pub completion_add_call_parenthesis: bool,
+
+    /// Whether postfix completions, like `expr.if`, are offered.
+    ///
+    /// Defaults to `true`
+    #[serde(deserialize_with = "nullable_bool_true")]
+    pub completion_enable_postfix: bool,
+
+    /// Additional arguments of `rustfmt` when formatting a file, like
+    /// `--config-path`.
+    ///
+    /// Defaults to none
+    #[serde(deserialize_with = "nullable_default")]
+    pub rustfmt_args: Vec<String>,
+
+    /// The directories which are neither analyzed nor watched, with the
+    /// `.gitignore` syntax and relative to the workspace folders, like
+    /// `vendor/` or `**/generated`. They apply to the workspace members and to
+    /// the dependencies inside of the workspace folders.
+    ///
+    /// Defaults to none
+    #[serde(deserialize_with = "nullable_default")]
+    pub exclude_dirs: Vec<String>,
+
+    /// The directory where the symbol index of libraries is saved, to load it
+    /// rather than index the libraries again on the next start. Nothing else,
+    /// like name resolution, is cached.
+    ///
+    /// Defaults to none, which doesn't save them
+    #[serde(deserialize_with = "nullable_default")]
+    pub symbol_cache_directory: Option<PathBuf>,
+
+    /// How the columns of positions are counted. It's negotiated with the
+    /// client when initializing, rather than configured.
+    ///
+    /// Defaults to UTF-16 code units
+    #[serde(skip)]
+    pub offset_encoding: OffsetEncoding,
+}
+
+/// How the columns of the positions exchanged with the client are counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OffsetEncoding {
+    /// In UTF-16 code units, as required by LSP unless the client supports
+    /// another encoding.
+    Utf16,
+    /// In bytes.
+    Utf8,
+}
+
+impl Default for OffsetEncoding {
+    fn default() -> OffsetEncoding {
+        OffsetEncoding::Utf16
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            publish_decorations: false,
+            show_workspace_loaded: true,
+            work_done_progress: false,
+            disabled_diagnostics: FxHashSet::default(),
+            cargo_check_on_save: false,
+            cargo_load_out_dirs_from_check: false,
+            cargo_cfg_test: true,
+            cargo_all_features: true,
+            cargo_no_default_features: false,
+            cargo_features: Vec::new(),
+            completion_add_call_parenthesis: true,
+            completion_enable_postfix: true,
+            rustfmt_args: Vec::new(),
+            exclude_dirs: Vec::new(),
+            symbol_cache_directory: None,
+            offset_encoding: OffsetEncoding::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn cargo_config(&self) -> CargoConfig {
+        CargoConfig {
+            load_out_dirs_from_check: self.cargo_load_out_dirs_from_check,
+            cfg_test: self.cargo_cfg_test,
+            all_features: self.cargo_all_features,
+            no_default_features: self.cargo_no_default_features,
+            features: self.cargo_features.clone(),
+        }
+    }
+
+    pub fn completion_options(&self) -> CompletionOptions {
+        CompletionOptions {
+            add_call_parenthesis: self.completion_add_call_parenthesis,
+            enable_postfix_completions: self.completion_enable_postfix,
+        }
+    }
+}
+
+/// Deserializes a null value to a bool false by default
+fn nullable_bool_false<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let opt = Option::deserialize(deserializer)?;
+    Ok(opt.unwrap_or(false))
+}
+
+/// Deserializes a null value to a bool true by default
+fn nullable_bool_true<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let opt = Option::deserialize(deserializer)?;
+    Ok(opt.unwrap_or(true))
+}
+
+/// Deserializes a null value to the default value of the type
+fn nullable_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    let opt = Option::deserialize(deserializer)?;
+    Ok(opt.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deserialize_config_defaults() {
+        // check that null == default for all fields
+        let default = Config::default();
+        assert_eq!(default, serde_json::from_str(r#"{}"#).unwrap());
+        assert_eq!(
+            default,
+            serde_json::from_str(
+                r#"{"publishDecorations":null, "showWorkspaceLoaded":null, "workDoneProgress":null,
+                    "disabledDiagnostics":null, "cargoCheckOnSave":null,
+                    "cargoLoadOutDirsFromCheck":null, "completionAddCallParenthesis":null,
+                    "completionEnablePostfix":null, "rustfmtArgs":null,
+                    "excludeDirs":null, "symbolCacheDirectory":null}"#
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn deserialize_disabled_diagnostics() {
+        let config: Config =
+            serde_json::from_str(r#"{"disabledDiagnostics":["unresolved_import"]}"#).unwrap();
+        assert!(config.disabled_diagnostics.contains("unresolved_import"));
+        assert!(config.show_workspace_loaded);
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use lsp_types::{
     self, CreateFile, Documentation, DocumentChangeOperation, DocumentChanges, Location, LocationLink,
     MarkupContent, MarkupKind, Position, Range, RenameFile, ResourceOp, SymbolKind, TextDocumentEdit, TextDocumentIdentifier,
@@ -6,13 +8,20 @@ use lsp_types::{
 };
 use ra_ide_api::{
     CompletionItem, CompletionItemKind, FileId, FilePosition, FileRange, FileSystemEdit,
-    NavigationTarget, SourceChange, SourceFileEdit, RangeInfo,
-    LineCol, LineIndex, translate_offset_with_edit, InsertTextFormat
+    NavigationTarget, SourceChange, SourceFileEdit, RangeInfo, ReferenceAccess, SearchScope,
+    LineCol, LineColUtf8, LineIndex, translate_offset_with_edit, InsertTextFormat
 };
 use ra_syntax::{SyntaxKind, TextRange, TextUnit};
 use ra_text_edit::{AtomTextEdit, TextEdit};
 
-use crate::{req, server_world::ServerWorld, Result};
+use crate::{config::OffsetEncoding, req, server_world::ServerWorld, Result};
+
+/// The line index of a file, along with the encoding of the columns of the
+/// positions exchanged with the client.
+pub struct FileLineIndex {
+    pub index: Arc<LineIndex>,
+    pub encoding: OffsetEncoding,
+}
 
 pub trait Conv {
     type Output;
@@ -73,15 +82,51 @@ impl Conv for CompletionItemKind {
             CompletionItemKind::Static => Value,
             CompletionItemKind::Method => Method,
             CompletionItemKind::TypeParam => TypeParameter,
+            CompletionItemKind::Attribute => EnumMember,
+        }
+    }
+}
+
+impl Conv for Option<ReferenceAccess> {
+    type Output = lsp_types::DocumentHighlightKind;
+
+    fn conv(self) -> <Self as Conv>::Output {
+        match self {
+            Some(ReferenceAccess::Read) => lsp_types::DocumentHighlightKind::Read,
+            Some(ReferenceAccess::Write) => lsp_types::DocumentHighlightKind::Write,
+            None => lsp_types::DocumentHighlightKind::Text,
+        }
+    }
+}
+
+impl Conv for req::SearchScope {
+    type Output = SearchScope;
+
+    fn conv(self) -> <Self as Conv>::Output {
+        match self {
+            req::SearchScope::Workspace => SearchScope::Workspace,
+            req::SearchScope::CurrentModule => SearchScope::CurrentModule,
+            req::SearchScope::Tests => SearchScope::Tests,
+        }
+    }
+}
+
+impl Conv for req::ReferenceAccess {
+    type Output = ReferenceAccess;
+
+    fn conv(self) -> <Self as Conv>::Output {
+        match self {
+            req::ReferenceAccess::Read => ReferenceAccess::Read,
+            req::ReferenceAccess::Write => ReferenceAccess::Write,
         }
     }
 }
 
 impl ConvWith for CompletionItem {
-    type Ctx = LineIndex;
+    type Ctx = FileLineIndex;
     type Output = ::lsp_types::CompletionItem;
 
-    fn conv_with(self, ctx: &LineIndex) -> ::lsp_types::CompletionItem {
+    fn conv_with(self, ctx: &FileLineIndex) -> ::lsp_types::CompletionItem {
         let mut additional_text_edits = Vec::new();
         let mut text_edit = None;
         // LSP does not allow arbitrary edits in completion, so we have to do a
@@ -111,6 +156,8 @@ impl ConvWith for CompletionItem {
             label: self.label().to_string(),
             detail: self.detail().map(|it| it.to_string()),
             filter_text: Some(self.lookup().to_string()),
+            sort_text: Some(self.sort_text()),
+            deprecated: if self.deprecated() { Some(true) } else { None },
             kind: self.kind().map(|it| it.conv()),
             text_edit: Some(text_edit),
             additional_text_edits: Some(additional_text_edits),
@@ -127,39 +174,54 @@ impl ConvWith for CompletionItem {
 }
 
 impl ConvWith for Position {
-    type Ctx = LineIndex;
+    type Ctx = FileLineIndex;
     type Output = TextUnit;
 
-    fn conv_with(self, line_index: &LineIndex) -> TextUnit {
-        let line_col = LineCol { line: self.line as u32, col_utf16: self.character as u32 };
-        line_index.offset(line_col)
+    fn conv_with(self, line_index: &FileLineIndex) -> TextUnit {
+        let line = self.line as u32;
+        match line_index.encoding {
+            OffsetEncoding::Utf16 => {
+                line_index.index.offset(LineCol { line, col_utf16: self.character as u32 })
+            }
+            OffsetEncoding::Utf8 => {
+                line_index.index.offset_utf8(LineColUtf8 { line, col: self.character as u32 })
+            }
+        }
     }
 }
 
 impl ConvWith for TextUnit {
-    type Ctx = LineIndex;
+    type Ctx = FileLineIndex;
     type Output = Position;
 
-    fn conv_with(self, line_index: &LineIndex) -> Position {
-        let line_col = line_index.line_col(self);
-        Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16))
+    fn conv_with(self, line_index: &FileLineIndex) -> Position {
+        match line_index.encoding {
+            OffsetEncoding::Utf16 => {
+                let line_col = line_index.index.line_col(self);
+                Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16))
+            }
+            OffsetEncoding::Utf8 => {
+                let line_col = line_index.index.line_col_utf8(self);
+                Position::new(u64::from(line_col.line), u64::from(line_col.col))
+            }
+        }
     }
 }
 
 impl ConvWith for TextRange {
-    type Ctx = LineIndex;
+    type Ctx = FileLineIndex;
     type Output = Range;
 
-    fn conv_with(self, line_index: &LineIndex) -> Range {
+    fn conv_with(self, line_index: &FileLineIndex) -> Range {
         Range::new(self.start().conv_with(line_index), self.end().conv_with(line_index))
     }
 }
 
 impl ConvWith for Range {
-    type Ctx = LineIndex;
+    type Ctx = FileLineIndex;
     type Output = TextRange;
 
-    fn conv_with(self, line_index: &LineIndex) -> TextRange {
+    fn conv_with(self, line_index: &FileLineIndex) -> TextRange {
         TextRange::from_to(self.start.conv_with(line_index), self.end.conv_with(line_index))
     }
 }
@@ -197,19 +259,19 @@ impl Conv for ra_ide_api::FunctionSignature {
 }
 
 impl ConvWith for TextEdit {
-    type Ctx = LineIndex;
+    type Ctx = FileLineIndex;
     type Output = Vec<lsp_types::TextEdit>;
 
-    fn conv_with(self, line_index: &LineIndex) -> Vec<lsp_types::TextEdit> {
+    fn conv_with(self, line_index: &FileLineIndex) -> Vec<lsp_types::TextEdit> {
         self.as_atoms().iter().map_conv_with(line_index).collect()
     }
 }
 
 impl<'a> ConvWith for &'a AtomTextEdit {
-    type Ctx = LineIndex;
+    type Ctx = FileLineIndex;
     type Output = lsp_types::TextEdit;
 
-    fn conv_with(self, line_index: &LineIndex) -> lsp_types::TextEdit {
+    fn conv_with(self, line_index: &FileLineIndex) -> lsp_types::TextEdit {
         lsp_types::TextEdit {
             range: self.delete.conv_with(line_index),
             new_text: self.insert.clone(),
@@ -270,7 +332,7 @@ impl<'a> TryConvWith for &'a TextDocumentPositionParams {
     type Output = FilePosition;
     fn try_conv_with(self, world: &ServerWorld) -> Result<FilePosition> {
         let file_id = self.text_document.try_conv_with(world)?;
-        let line_index = world.analysis().file_line_index(file_id);
+        let line_index = world.line_index(file_id);
         let offset = self.position.conv_with(&line_index);
         Ok(FilePosition { file_id, offset })
     }
@@ -281,7 +343,7 @@ impl<'a> TryConvWith for (&'a TextDocumentIdentifier, Range) {
     type Output = FileRange;
     fn try_conv_with(self, world: &ServerWorld) -> Result<FileRange> {
         let file_id = self.0.try_conv_with(world)?;
-        let line_index = world.analysis().file_line_index(file_id);
+        let line_index = world.line_index(file_id);
         let range = self.1.conv_with(&line_index);
         Ok(FileRange { file_id, range })
     }
@@ -306,18 +368,29 @@ impl TryConvWith for SourceChange {
         let cursor_position = match self.cursor_position {
             None => None,
             Some(pos) => {
-                let line_index = world.analysis().file_line_index(pos.file_id);
+                let line_index = world.line_index(pos.file_id);
                 let edit = self
                     .source_file_edits
                     .iter()
                     .find(|it| it.file_id == pos.file_id)
                     .map(|it| &it.edit);
-                let line_col = match edit {
-                    Some(edit) => translate_offset_with_edit(&*line_index, pos.offset, edit),
-                    None => line_index.line_col(pos.offset),
+                let position = match (edit, line_index.encoding) {
+                    (Some(edit), OffsetEncoding::Utf16) => {
+                        let line_col =
+                            translate_offset_with_edit(&line_index.index, pos.offset, edit);
+                        Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16))
+                    }
+                    // Byte columns are cheap to compute from the edited text.
+                    (Some(edit), OffsetEncoding::Utf8) => {
+                        let text = edit.apply(&world.analysis().file_text(pos.file_id));
+                        let line_index = FileLineIndex {
+                            index: Arc::new(LineIndex::new(&text)),
+                            encoding: OffsetEncoding::Utf8,
+                        };
+                        pos.offset.conv_with(&line_index)
+                    }
+                    (None, _) => pos.offset.conv_with(&line_index),
                 };
-                let position =
-                    Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16));
                 Some(TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier::new(pos.file_id.try_conv_with(world)?),
                     position,
@@ -325,9 +398,25 @@ impl TryConvWith for SourceChange {
             }
         };
         let mut document_changes: Vec<DocumentChangeOperation> = Vec::new();
-        for resource_op in self.file_system_edits.try_conv_with(world)? {
+        let mut new_file_edits: Vec<TextDocumentEdit> = Vec::new();
+        for file_system_edit in self.file_system_edits {
+            if let FileSystemEdit::CreateFile { source_root, path, text } = &file_system_edit {
+                if !text.is_empty() {
+                    let text_document = VersionedTextDocumentIdentifier {
+                        uri: world.path_to_uri(*source_root, path)?,
+                        version: None,
+                    };
+                    let start = Position::new(0, 0);
+                    let edit = lsp_types::TextEdit::new(Range::new(start, start), text.clone());
+                    new_file_edits.push(TextDocumentEdit { text_document, edits: vec![edit] });
+                }
+            }
+            let resource_op = file_system_edit.try_conv_with(world)?;
             document_changes.push(DocumentChangeOperation::Op(resource_op));
         }
+        for text_document_edit in new_file_edits {
+            document_changes.push(DocumentChangeOperation::Edit(text_document_edit));
+        }
         for text_document_edit in self.source_file_edits.try_conv_with(world)? {
             document_changes.push(DocumentChangeOperation::Edit(text_document_edit));
         }
@@ -343,11 +432,11 @@ impl TryConvWith for SourceFileEdit {
     type Ctx = ServerWorld;
     type Output = TextDocumentEdit;
     fn try_conv_with(self, world: &ServerWorld) -> Result<TextDocumentEdit> {
-        let text_document = VersionedTextDocumentIdentifier {
-            uri: self.file_id.try_conv_with(world)?,
-            version: None,
-        };
-        let line_index = world.analysis().file_line_index(self.file_id);
+        let uri = self.file_id.try_conv_with(world)?;
+        // The client rejects the edit if the document has changed since.
+        let version = world.document_versions.get(&uri).cloned();
+        let text_document = VersionedTextDocumentIdentifier { uri, version };
+        let line_index = world.line_index(self.file_id);
         let edits = self.edit.as_atoms().iter().map_conv_with(&line_index).collect();
         Ok(TextDocumentEdit { text_document, edits })
     }
@@ -358,7 +447,7 @@ impl TryConvWith for FileSystemEdit {
     type Output = ResourceOp;
     fn try_conv_with(self, world: &ServerWorld) -> Result<ResourceOp> {
         let res = match self {
-            FileSystemEdit::CreateFile { source_root, path } => {
+            FileSystemEdit::CreateFile { source_root, path, .. } => {
                 let uri = world.path_to_uri(source_root, &path)?;
                 ResourceOp::Create(CreateFile { uri, options: None })
             }
@@ -376,7 +465,7 @@ impl TryConvWith for &NavigationTarget {
     type Ctx = ServerWorld;
     type Output = Location;
     fn try_conv_with(self, world: &ServerWorld) -> Result<Location> {
-        let line_index = world.analysis().file_line_index(self.file_id());
+        let line_index = world.line_index(self.file_id());
         let range = self.range();
         to_location(self.file_id(), range, &world, &line_index)
     }
@@ -386,10 +475,10 @@ pub fn to_location_link(
     target: &RangeInfo<NavigationTarget>,
     world: &ServerWorld,
     // line index for original range file
-    line_index: &LineIndex,
+    line_index: &FileLineIndex,
 ) -> Result<LocationLink> {
     let target_uri = target.info.file_id().try_conv_with(world)?;
-    let tgt_line_index = world.analysis().file_line_index(target.info.file_id());
+    let tgt_line_index = world.line_index(target.info.file_id());
 
     let target_range = target.info.full_range().conv_with(&tgt_line_index);
 
@@ -409,7 +498,7 @@ pub fn to_location(
     file_id: FileId,
     range: TextRange,
     world: &ServerWorld,
-    line_index: &LineIndex,
+    line_index: &FileLineIndex,
 ) -> Result<Location> {
     let url = file_id.try_conv_with(world)?;
     let loc = Location::new(url, range.conv_with(line_index));
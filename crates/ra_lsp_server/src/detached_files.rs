@@ -0,0 +1,63 @@
+//! The files opened by the client which belong to none of the workspaces.
+//!
+//! The vfs only knows the files of its roots, so these are tracked here. Each
+//! one is the root of a crate of its own, in a source root of its own, which
+//! gets the sysroot of the workspaces as dependencies.
+
+use std::path::{Path, PathBuf};
+
+use ra_ide_api::{FileId, SourceRootId};
+use rustc_hash::FxHashMap;
+
+#[derive(Debug, Default, Clone)]
+pub struct DetachedFiles {
+    files: FxHashMap<PathBuf, u32>,
+    next_idx: u32,
+}
+
+// The ids of detached files are taken from the top of the range, which the vfs,
+// numbering its files and roots from zero, doesn't reach.
+fn file_id(idx: u32) -> FileId {
+    FileId(u32::max_value() - idx)
+}
+
+fn source_root_id(idx: u32) -> SourceRootId {
+    SourceRootId(u32::max_value() - idx)
+}
+
+impl DetachedFiles {
+    /// Adds the file at `path`, returning its ids.
+    pub fn add(&mut self, path: PathBuf) -> (FileId, SourceRootId) {
+        let idx = self.next_idx;
+        self.next_idx += 1;
+        self.files.insert(path, idx);
+        (file_id(idx), source_root_id(idx))
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<(FileId, SourceRootId)> {
+        let idx = self.files.remove(path)?;
+        Some((file_id(idx), source_root_id(idx)))
+    }
+
+    pub fn file_id(&self, path: &Path) -> Option<FileId> {
+        self.files.get(path).map(|&idx| file_id(idx))
+    }
+
+    pub fn path(&self, id: FileId) -> Option<&Path> {
+        self.files.iter().find(|(_, &idx)| file_id(idx) == id).map(|(path, _)| path.as_path())
+    }
+
+    /// The directory of the file of the source root `root`, to which the paths
+    /// of the root are relative.
+    pub fn root_path(&self, root: SourceRootId) -> Option<&Path> {
+        let (path, _) = self.files.iter().find(|(_, &idx)| source_root_id(idx) == root)?;
+        path.parent()
+    }
+
+    /// The ids of the files, in the order they were added.
+    pub fn file_ids(&self) -> Vec<FileId> {
+        let mut indices: Vec<u32> = self.files.values().cloned().collect();
+        indices.sort();
+        indices.into_iter().map(file_id).collect()
+    }
+}
@@ -0,0 +1,520 @@
+//! Runs `cargo check` when files are saved and converts the diagnostics of
+//! rustc to LSP diagnostics, with code actions for the suggestions which can
+//! be applied automatically.
+
+use std::{
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
+
+use crossbeam_channel::{after, select, unbounded, Receiver};
+use lsp_types::{
+    CodeAction, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
+    NumberOrString, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use thread_worker::{ScopedThread, Worker};
+
+/// Saves in quick succession only trigger a single check.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub enum CheckTask {
+    /// A new check started, the diagnostics of the previous one are stale.
+    Begin,
+    Diagnostic(Url, CheckDiagnostic),
+    End,
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckDiagnostic {
+    pub diagnostic: Diagnostic,
+    pub fixes: Vec<CheckFix>,
+}
+
+/// A suggestion of rustc which can be applied without review.
+#[derive(Debug, Clone)]
+pub struct CheckFix {
+    pub title: String,
+    pub edit: WorkspaceEdit,
+}
+
+/// The diagnostics of the last check, by file.
+#[derive(Debug, Clone, Default)]
+pub struct CheckDiagnostics {
+    by_file: FxHashMap<Url, Vec<CheckDiagnostic>>,
+}
+
+impl CheckDiagnostics {
+    pub fn files<'a>(&'a self) -> impl Iterator<Item = &'a Url> + 'a {
+        self.by_file.keys()
+    }
+
+    pub fn add(&mut self, url: Url, diagnostic: CheckDiagnostic) {
+        let diagnostics = self.by_file.entry(url).or_default();
+        // The same diagnostic is reported for each target including a file.
+        if !diagnostics.iter().any(|it| it.diagnostic == diagnostic.diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Converts the columns of all ranges, which are counted in chars like in
+    /// the spans of rustc, with `convert`.
+    pub fn convert_columns(&mut self, mut convert: impl FnMut(&Url, Range) -> Range) {
+        for (url, diagnostics) in self.by_file.iter_mut() {
+            for it in diagnostics.iter_mut() {
+                it.diagnostic.range = convert(url, it.diagnostic.range);
+                for related in it.diagnostic.related_information.iter_mut().flatten() {
+                    related.location.range = convert(&related.location.uri, related.location.range);
+                }
+                for fix in it.fixes.iter_mut() {
+                    for (url, edits) in fix.edit.changes.iter_mut().flatten() {
+                        for edit in edits.iter_mut() {
+                            edit.range = convert(url, edit.range);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn diagnostics_for<'a>(&'a self, url: &Url) -> impl Iterator<Item = &'a Diagnostic> + 'a {
+        self.by_file.get(url).into_iter().flatten().map(|it| &it.diagnostic)
+    }
+
+    pub fn fixes_for<'a>(
+        &'a self,
+        url: &Url,
+        range: Range,
+    ) -> impl Iterator<Item = CodeAction> + 'a {
+        self.by_file
+            .get(url)
+            .into_iter()
+            .flatten()
+            .filter(move |it| ranges_overlap(it.diagnostic.range, range))
+            .flat_map(|it| {
+                it.fixes.iter().map(move |fix| CodeAction {
+                    title: fix.title.clone(),
+                    kind: Some("quickfix".to_string()),
+                    diagnostics: Some(vec![it.diagnostic.clone()]),
+                    edit: Some(fix.edit.clone()),
+                    command: None,
+                })
+            })
+    }
+}
+
+/// Spawns the worker which runs `cargo check` in each of `workspace_roots`
+/// each time it receives a message.
+pub fn check_watcher(workspace_roots: Vec<PathBuf>) -> Worker<(), CheckTask> {
+    Worker::<(), CheckTask>::spawn("cargo check", 1, move |input_receiver, output_sender| {
+        let mut pending = false;
+        loop {
+            if !pending && input_receiver.recv().is_err() {
+                return;
+            }
+            loop {
+                select! {
+                    recv(input_receiver) -> msg => if msg.is_err() { return },
+                    recv(after(DEBOUNCE)) -> _ => break,
+                }
+            }
+            pending = false;
+
+            output_sender.send(CheckTask::Begin).unwrap();
+            for workspace_root in workspace_roots.iter() {
+                let check = match CargoCheck::spawn(workspace_root) {
+                    Ok(it) => it,
+                    Err(e) => {
+                        log::error!("failed to run cargo check: {}", e);
+                        continue;
+                    }
+                };
+                loop {
+                    select! {
+                        recv(input_receiver) -> msg => {
+                            // Restart the check with the new changes.
+                            if msg.is_err() {
+                                return;
+                            }
+                            pending = true;
+                            break;
+                        }
+                        recv(check.diagnostics) -> rd => match rd {
+                            Ok(rd) => {
+                                if let Some((url, d)) = map_rust_diagnostic(&rd, workspace_root) {
+                                    output_sender.send(CheckTask::Diagnostic(url, d)).unwrap();
+                                }
+                            }
+                            Err(_) => break,
+                        },
+                    }
+                }
+                if pending {
+                    break;
+                }
+            }
+            // The diagnostics of a restarted check are incomplete, they are
+            // dropped when the next one begins.
+            if !pending {
+                output_sender.send(CheckTask::End).unwrap();
+            }
+        }
+    })
+}
+
+const COMPILER_MESSAGE: &str = "compiler-message";
+
+/// A line of the output of `cargo check --message-format=json`.
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<RustDiagnostic>,
+}
+
+/// A diagnostic of rustc, as emitted with `--error-format=json`.
+#[derive(Debug, Deserialize)]
+struct RustDiagnostic {
+    message: String,
+    code: Option<DiagnosticCode>,
+    level: String,
+    spans: Vec<DiagnosticSpan>,
+    children: Vec<RustDiagnostic>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiagnosticCode {
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiagnosticSpan {
+    file_name: String,
+    line_start: u64,
+    line_end: u64,
+    /// 1-based, in chars.
+    column_start: u64,
+    column_end: u64,
+    is_primary: bool,
+    label: Option<String>,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+    expansion: Option<Box<DiagnosticSpanMacroExpansion>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiagnosticSpanMacroExpansion {
+    span: DiagnosticSpan,
+    macro_decl_name: String,
+}
+
+/// A running `cargo check`, which is killed when dropped.
+struct CargoCheck {
+    child: Child,
+    diagnostics: Receiver<RustDiagnostic>,
+    _reader: ScopedThread,
+}
+
+impl CargoCheck {
+    fn spawn(workspace_root: &Path) -> std::io::Result<CargoCheck> {
+        let mut child = Command::new("cargo")
+            .args(&["check", "--message-format=json", "--all-targets"])
+            .current_dir(workspace_root)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child.stdout.take().unwrap();
+        let (sender, diagnostics) = unbounded();
+        let _reader = ScopedThread::spawn("cargo check output", move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(it) => it,
+                    Err(e) => {
+                        log::error!("failed to read cargo check output: {}", e);
+                        break;
+                    }
+                };
+                // Build scripts might print other lines as well.
+                let message = match serde_json::from_str::<CargoMessage>(&line) {
+                    Ok(it) => it,
+                    Err(_) => continue,
+                };
+                if let (COMPILER_MESSAGE, Some(rd)) = (message.reason.as_str(), message.message) {
+                    if sender.send(rd).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(CargoCheck { child, diagnostics, _reader })
+    }
+}
+
+impl Drop for CargoCheck {
+    fn drop(&mut self) {
+        // This closes stdout, so that the reader thread finishes as well.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Converts a diagnostic of rustc into an LSP diagnostic for the file of its
+/// primary span. Spans in macros defined outside of the workspace are mapped
+/// to the macro calls in the workspace.
+fn map_rust_diagnostic(
+    rd: &RustDiagnostic,
+    workspace_root: &Path,
+) -> Option<(Url, CheckDiagnostic)> {
+    let primary = rd.spans.iter().find(|it| it.is_primary)?;
+    let mut related_information = Vec::new();
+    let mut span = primary;
+    while !is_in_workspace(span, workspace_root) {
+        span = &span.expansion.as_ref()?.span;
+    }
+    // The spans of the macro calls the diagnostic comes from.
+    let mut backtrace = span.expansion.as_ref();
+    while let Some(expansion) = backtrace {
+        if is_in_workspace(&expansion.span, workspace_root) {
+            related_information.push(DiagnosticRelatedInformation {
+                location: map_span(&expansion.span, workspace_root)?,
+                message: format!("in this expansion of `{}`", expansion.macro_decl_name),
+            });
+        }
+        backtrace = expansion.span.expansion.as_ref();
+    }
+    let location = map_span(span, workspace_root)?;
+
+    let mut message = rd.message.clone();
+    if let Some(label) = primary.label.as_ref().filter(|it| !it.is_empty()) {
+        message.push_str(&format!("\n{}", label));
+    }
+    for secondary in rd.spans.iter().filter(|it| !it.is_primary) {
+        if let (Some(label), Some(location)) =
+            (secondary.label.as_ref(), map_span(secondary, workspace_root))
+        {
+            related_information
+                .push(DiagnosticRelatedInformation { location, message: label.clone() });
+        }
+    }
+
+    let mut fixes = Vec::new();
+    for child in &rd.children {
+        let child_message = format!("{}: {}", child.level, child.message);
+        match child.spans.iter().find(|it| it.is_primary).or_else(|| child.spans.first()) {
+            Some(child_span) => match map_span(child_span, workspace_root) {
+                Some(location) => {
+                    related_information
+                        .push(DiagnosticRelatedInformation { location, message: child_message });
+                }
+                None => message.push_str(&format!("\n{}", child_message)),
+            },
+            None => message.push_str(&format!("\n{}", child_message)),
+        }
+        if let Some(edit) = map_suggestion(child, workspace_root) {
+            fixes.push(CheckFix { title: child.message.clone(), edit });
+        }
+    }
+
+    let diagnostic = Diagnostic {
+        range: location.range,
+        severity: Some(map_level(&rd.level)),
+        code: rd.code.as_ref().map(|it| NumberOrString::String(it.code.clone())),
+        source: Some("rustc".to_string()),
+        message,
+        related_information: if related_information.is_empty() {
+            None
+        } else {
+            Some(related_information)
+        },
+    };
+    Some((location.uri, CheckDiagnostic { diagnostic, fixes }))
+}
+
+/// The edit of a suggestion which can be applied without review.
+fn map_suggestion(child: &RustDiagnostic, workspace_root: &Path) -> Option<WorkspaceEdit> {
+    let mut changes: FxHashMap<Url, Vec<TextEdit>> = FxHashMap::default();
+    for span in &child.spans {
+        let replacement = match (&span.suggested_replacement, &span.suggestion_applicability) {
+            (Some(it), Some(applicability)) if applicability == "MachineApplicable" => it,
+            _ => continue,
+        };
+        let location = map_span(span, workspace_root)?;
+        changes
+            .entry(location.uri)
+            .or_default()
+            .push(TextEdit { range: location.range, new_text: replacement.clone() });
+    }
+    if changes.is_empty() {
+        return None;
+    }
+    Some(WorkspaceEdit { changes: Some(changes.into_iter().collect()), document_changes: None })
+}
+
+fn is_in_workspace(span: &DiagnosticSpan, workspace_root: &Path) -> bool {
+    // Spans in macros of the standard library have names like `<::std::macros::panic macros>`.
+    !span.file_name.starts_with('<')
+        && workspace_root.join(&span.file_name).starts_with(workspace_root)
+}
+
+fn map_span(span: &DiagnosticSpan, workspace_root: &Path) -> Option<Location> {
+    let uri = Url::from_file_path(workspace_root.join(&span.file_name)).ok()?;
+    // The columns are counted in chars, `CheckDiagnostics::convert_columns`
+    // converts them to the encoding of the client.
+    let range = Range::new(
+        Position::new(span.line_start - 1, span.column_start - 1),
+        Position::new(span.line_end - 1, span.column_end - 1),
+    );
+    Some(Location::new(uri, range))
+}
+
+fn map_level(level: &str) -> DiagnosticSeverity {
+    match level {
+        "error" | "error: internal compiler error" => DiagnosticSeverity::Error,
+        "warning" => DiagnosticSeverity::Warning,
+        "help" => DiagnosticSeverity::Hint,
+        _ => DiagnosticSeverity::Information,
+    }
+}
+
+fn ranges_overlap(a: Range, b: Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_diagnostic(json: &str) -> RustDiagnostic {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn span(file_name: &str, line: usize, columns: (usize, usize)) -> String {
+        format!(
+            r#"{{"file_name":"{}","byte_start":0,"byte_end":0,"line_start":{},"line_end":{},
+            "column_start":{},"column_end":{},"is_primary":true,"text":[],"label":null,
+            "suggested_replacement":null,"suggestion_applicability":null,"expansion":null}}"#,
+            file_name, line, line, columns.0, columns.1
+        )
+    }
+
+    #[test]
+    fn map_diagnostic_with_suggestion() {
+        let rd = parse_diagnostic(
+            r##"{
+                "message": "unused variable: `x`",
+                "code": {"code": "unused_variables", "explanation": null},
+                "level": "warning",
+                "spans": [{
+                    "file_name": "src/lib.rs", "byte_start": 20, "byte_end": 21,
+                    "line_start": 2, "line_end": 2, "column_start": 9, "column_end": 10,
+                    "is_primary": true, "text": [], "label": null,
+                    "suggested_replacement": null, "suggestion_applicability": null,
+                    "expansion": null
+                }],
+                "children": [{
+                    "message": "#[warn(unused_variables)] on by default",
+                    "code": null, "level": "note", "spans": [], "children": [], "rendered": null
+                }, {
+                    "message": "consider prefixing with an underscore",
+                    "code": null, "level": "help",
+                    "spans": [{
+                        "file_name": "src/lib.rs", "byte_start": 20, "byte_end": 21,
+                        "line_start": 2, "line_end": 2, "column_start": 9, "column_end": 10,
+                        "is_primary": true, "text": [], "label": null,
+                        "suggested_replacement": "_x",
+                        "suggestion_applicability": "MachineApplicable",
+                        "expansion": null
+                    }],
+                    "children": [], "rendered": null
+                }],
+                "rendered": null
+            }"##,
+        );
+        let (url, check) = map_rust_diagnostic(&rd, Path::new("/ws")).unwrap();
+        assert_eq!(url.as_str(), "file:///ws/src/lib.rs");
+        let d = &check.diagnostic;
+        assert_eq!(d.range, Range::new(Position::new(1, 8), Position::new(1, 9)));
+        assert_eq!(d.severity, Some(DiagnosticSeverity::Warning));
+        assert_eq!(d.code, Some(NumberOrString::String("unused_variables".to_string())));
+        assert_eq!(
+            d.message,
+            "unused variable: `x`\nnote: #[warn(unused_variables)] on by default"
+        );
+        let related = d.related_information.as_ref().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].message, "help: consider prefixing with an underscore");
+
+        assert_eq!(check.fixes.len(), 1);
+        let fix = &check.fixes[0];
+        assert_eq!(fix.title, "consider prefixing with an underscore");
+        let edits = &fix.edit.changes.as_ref().unwrap()[&url];
+        assert_eq!(edits, &vec![TextEdit { range: d.range, new_text: "_x".to_string() }]);
+    }
+
+    #[test]
+    fn map_diagnostic_in_external_macro() {
+        let mut external = span("<::std::macros::panic macros>", 3, (1, 5));
+        let call = span("src/main.rs", 5, (5, 20));
+        external.truncate(external.len() - "null}".len());
+        external +=
+            &format!(r#"{{"span":{},"macro_decl_name":"panic!","def_site_span":null}}}}"#, call);
+        let rd = parse_diagnostic(&format!(
+            r#"{{"message":"mismatched types","code":null,"level":"error",
+            "spans":[{}],"children":[],"rendered":null}}"#,
+            external
+        ));
+        let (url, check) = map_rust_diagnostic(&rd, Path::new("/ws")).unwrap();
+        assert_eq!(url.as_str(), "file:///ws/src/main.rs");
+        assert_eq!(check.diagnostic.range, Range::new(Position::new(4, 4), Position::new(4, 19)));
+        assert_eq!(check.diagnostic.severity, Some(DiagnosticSeverity::Error));
+        assert!(check.fixes.is_empty());
+    }
+
+    #[test]
+    fn diagnostics_outside_of_workspace_are_ignored() {
+        let rd = parse_diagnostic(&format!(
+            r#"{{"message":"oops","code":null,"level":"error",
+            "spans":[{}],"children":[],"rendered":null}}"#,
+            span("/registry/foo/src/lib.rs", 1, (1, 2))
+        ));
+        assert!(map_rust_diagnostic(&rd, Path::new("/ws")).is_none());
+    }
+
+    #[test]
+    fn duplicated_diagnostics_are_added_once() {
+        let rd = parse_diagnostic(&format!(
+            r#"{{"message":"oops","code":null,"level":"error",
+            "spans":[{}],"children":[],"rendered":null}}"#,
+            span("src/lib.rs", 1, (1, 2))
+        ));
+        let mut diagnostics = CheckDiagnostics::default();
+        for _ in 0..2 {
+            let (url, check) = map_rust_diagnostic(&rd, Path::new("/ws")).unwrap();
+            diagnostics.add(url, check);
+        }
+        let url = Url::from_file_path("/ws/src/lib.rs").unwrap();
+        assert_eq!(diagnostics.diagnostics_for(&url).count(), 1);
+        assert_eq!(diagnostics.files().collect::<Vec<_>>(), vec![&url]);
+    }
+
+    #[test]
+    fn columns_are_converted() {
+        let rd = parse_diagnostic(&format!(
+            r#"{{"message":"oops","code":null,"level":"error",
+            "spans":[{}],"children":[],"rendered":null}}"#,
+            span("src/lib.rs", 1, (3, 4))
+        ));
+        let mut diagnostics = CheckDiagnostics::default();
+        let (url, check) = map_rust_diagnostic(&rd, Path::new("/ws")).unwrap();
+        diagnostics.add(url.clone(), check);
+        diagnostics.convert_columns(|_, range| {
+            let widen = |pos: Position| Position::new(pos.line, pos.character * 2);
+            Range::new(widen(range.start), widen(range.end))
+        });
+        let d = diagnostics.diagnostics_for(&url).next().unwrap();
+        assert_eq!(d.range, Range::new(Position::new(0, 4), Position::new(0, 6)));
+    }
+}
@@ -1,13 +1,20 @@
 mod caps;
 mod cargo_target_spec;
 mod conv;
+mod detached_files;
+mod flycheck;
 mod main_loop;
 mod markdown;
 mod project_model;
 mod vfs_filter;
 pub mod req;
-pub mod init;
+pub mod config;
 mod server_world;
 
 pub type Result<T> = ::std::result::Result<T, ::failure::Error>;
-pub use crate::{caps::server_capabilities, main_loop::main_loop, main_loop::LspError, init::InitializationOptions};
+pub use crate::{
+    caps::{server_capabilities, negotiate_capabilities},
+    main_loop::main_loop,
+    main_loop::LspError,
+    config::Config,
+};
@@ -1,10 +1,17 @@
+use std::cell::Cell;
+
 use serde::Deserialize;
 use flexi_logger::{Duplicate, Logger};
-use gen_lsp_server::{run_server, stdio_transport};
+use gen_lsp_server::{run_server_with, stdio_transport};
 
-use ra_lsp_server::{Result, InitializationOptions};
+use ra_lsp_server::{Result, Config};
 use ra_prof;
 
+// With jemalloc, its statistics are used instead.
+#[cfg(not(feature = "jemalloc"))]
+#[global_allocator]
+static ALLOC: ra_prof::CountingAlloc = ra_prof::CountingAlloc;
+
 fn main() -> Result<()> {
     std::env::set_var("RUST_BACKTRACE", "short");
     let logger = Logger::with_env_or_str("error").duplicate_to_stderr(Duplicate::All);
@@ -32,7 +39,13 @@ fn main() -> Result<()> {
 fn main_inner() -> Result<()> {
     let (receiver, sender, threads) = stdio_transport();
     let cwd = std::env::current_dir()?;
-    run_server(ra_lsp_server::server_capabilities(), receiver, sender, |params, r, s| {
+    let offset_encoding = Cell::new(Default::default());
+    let caps = |params: &serde_json::Value| {
+        let (caps, encoding) = ra_lsp_server::negotiate_capabilities(params);
+        offset_encoding.set(encoding);
+        caps
+    };
+    run_server_with(caps, receiver, sender, |params, r, s| {
         let root = params.root_uri.and_then(|it| it.to_file_path().ok()).unwrap_or(cwd);
 
         let workspace_roots = params
@@ -46,12 +59,13 @@ fn main_inner() -> Result<()> {
             .filter(|workspaces| !workspaces.is_empty())
             .unwrap_or_else(|| vec![root]);
 
-        let opts = params
+        let mut config = params
             .initialization_options
-            .and_then(|v| InitializationOptions::deserialize(v).ok())
-            .unwrap_or(InitializationOptions::default());
+            .and_then(|v| Config::deserialize(v).ok())
+            .unwrap_or(Config::default());
+        config.offset_encoding = offset_encoding.get();
 
-        ra_lsp_server::main_loop(workspace_roots, opts, r, s)
+        ra_lsp_server::main_loop(workspace_roots, config, r, s)
     })?;
     log::info!("shutting down IO...");
     threads.join()?;
@@ -1,28 +1,32 @@
 mod handlers;
+mod progress;
 mod subscriptions;
 
 use std::{fmt, path::PathBuf, sync::Arc};
 
-use crossbeam_channel::{select, unbounded, Receiver, RecvError, Sender};
+use crossbeam_channel::{never, select, unbounded, Receiver, RecvError, Sender};
 use failure::{bail, format_err};
 use failure_derive::Fail;
 use gen_lsp_server::{
     handle_shutdown, ErrorCode, RawMessage, RawNotification, RawRequest, RawResponse,
 };
-use lsp_types::NumberOrString;
+use lsp_types::{NumberOrString, Position, Range, Url};
 use ra_ide_api::{Canceled, FileId, LibraryData};
 use ra_vfs::VfsTask;
 use rustc_hash::FxHashSet;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thread_worker::Worker;
 use threadpool::ThreadPool;
 
 use crate::{
-    main_loop::subscriptions::Subscriptions,
-    project_model::workspace_loader,
+    config::Config,
+    conv::ConvWith,
+    flycheck::{check_watcher, CheckDiagnostics, CheckTask},
+    main_loop::{progress::Progress, subscriptions::Subscriptions},
+    project_model::{workspace_loader, ProjectWorkspace},
     req,
     server_world::{ServerWorld, ServerWorldState},
     Result,
-    InitializationOptions,
 };
 use ra_prof::profile;
 
@@ -43,49 +47,36 @@ impl LspError {
 enum Task {
     Respond(RawResponse),
     Notify(RawNotification),
+    /// A request canceled by a change, to run again on the new state.
+    Retry(RawRequest),
+    /// The workspaces of a reload, loaded on the thread pool.
+    Reload {
+        generation: u64,
+        roots: Vec<PathBuf>,
+        workspaces: Vec<ProjectWorkspace>,
+    },
 }
 
 const THREADPOOL_SIZE: usize = 8;
 
 pub fn main_loop(
     ws_roots: Vec<PathBuf>,
-    options: InitializationOptions,
+    config: Config,
     msg_receiver: &Receiver<RawMessage>,
     msg_sender: &Sender<RawMessage>,
 ) -> Result<()> {
     let pool = ThreadPool::new(THREADPOOL_SIZE);
     let (task_sender, task_receiver) = unbounded::<Task>();
 
-    // FIXME: support dynamic workspace loading.
-    let workspaces = {
-        let ws_worker = workspace_loader();
-        let mut loaded_workspaces = Vec::new();
-        for ws_root in &ws_roots {
-            ws_worker.sender().send(ws_root.clone()).unwrap();
-            match ws_worker.receiver().recv().unwrap() {
-                Ok(ws) => loaded_workspaces.push(ws),
-                Err(e) => {
-                    log::error!("loading workspace failed: {}", e);
+    let workspaces = load_workspaces(&ws_roots, &config, msg_sender);
 
-                    show_message(
-                        req::MessageType::Error,
-                        format!("rust-analyzer failed to load workspace: {}", e),
-                        msg_sender,
-                    );
-                }
-            }
-        }
-        loaded_workspaces
-    };
-
-    let mut state = ServerWorldState::new(ws_roots, workspaces);
+    let mut state = ServerWorldState::new(ws_roots, workspaces, Arc::new(config));
 
     log::info!("server initialized, serving requests");
 
     let mut pending_requests = FxHashSet::default();
     let mut subs = Subscriptions::new();
     let main_res = main_loop_inner(
-        options,
         &pool,
         msg_sender,
         msg_receiver,
@@ -109,11 +100,48 @@ pub fn main_loop(
     main_res
 }
 
+fn load_workspaces(
+    ws_roots: &[PathBuf],
+    config: &Config,
+    msg_sender: &Sender<RawMessage>,
+) -> Vec<ProjectWorkspace> {
+    let mut progress = if config.work_done_progress {
+        Some(Progress::begin(msg_sender, "loadWorkspaces", "Loading workspaces", ws_roots.len()))
+    } else {
+        None
+    };
+    let ws_worker = workspace_loader(config.cargo_config());
+    let mut loaded_workspaces = Vec::new();
+    for (i, ws_root) in ws_roots.iter().enumerate() {
+        ws_worker.sender().send(ws_root.clone()).unwrap();
+        match ws_worker.receiver().recv().unwrap() {
+            Ok(ws) => loaded_workspaces.push(ws),
+            Err(e) => {
+                log::error!("loading workspace failed: {}", e);
+
+                show_message(
+                    req::MessageType::Error,
+                    format!("rust-analyzer failed to load workspace: {}", e),
+                    msg_sender,
+                );
+            }
+        }
+        if let Some(not) = progress.as_mut().and_then(|it| it.report(i + 1)) {
+            msg_sender.send(not.into()).unwrap();
+        }
+    }
+    if let Some(progress) = progress {
+        msg_sender.send(progress.end().into()).unwrap();
+    }
+    loaded_workspaces
+}
+
 enum Event {
     Msg(RawMessage),
     Task(Task),
     Vfs(VfsTask),
     Lib(LibraryData),
+    CheckWatcher(CheckTask),
 }
 
 impl fmt::Debug for Event {
@@ -133,6 +161,13 @@ impl fmt::Debug for Event {
                     return debug_verbose_not(not, f);
                 }
             }
+            Event::Task(Task::Reload { generation, roots, .. }) => {
+                return f
+                    .debug_struct("Reload")
+                    .field("generation", generation)
+                    .field("roots", roots)
+                    .finish();
+            }
             Event::Task(Task::Respond(resp)) => {
                 return f
                     .debug_struct("RawResponse")
@@ -147,12 +182,12 @@ impl fmt::Debug for Event {
             Event::Task(it) => fmt::Debug::fmt(it, f),
             Event::Vfs(it) => fmt::Debug::fmt(it, f),
             Event::Lib(it) => fmt::Debug::fmt(it, f),
+            Event::CheckWatcher(it) => fmt::Debug::fmt(it, f),
         }
     }
 }
 
 fn main_loop_inner(
-    options: InitializationOptions,
     pool: &ThreadPool,
     msg_sender: &Sender<RawMessage>,
     msg_receiver: &Receiver<RawMessage>,
@@ -167,11 +202,25 @@ fn main_loop_inner(
     let mut in_flight_libraries = 0;
     let mut pending_libraries = Vec::new();
     let mut send_workspace_notification = true;
+    let mut roots_total = state.roots_to_scan;
+    let mut indexing_progress = begin_indexing_progress(msg_sender, state);
 
-    let (libdata_sender, libdata_receiver) = unbounded();
+    let mut check_watcher = start_check_watcher(state);
+    let no_check_tasks = never();
+    // The diagnostics of the running check, published when it's done.
+    let mut pending_check_diagnostics = CheckDiagnostics::default();
+
+    // Only the workspaces of the last requested reload are applied, with the
+    // roots it was requested for.
+    let mut reload_generation = 0;
+    let mut reload_roots = state.roots.clone();
+
+    let (mut libdata_sender, mut libdata_receiver) = unbounded();
     loop {
         state.maybe_collect_garbage();
         log::trace!("selecting");
+        let check_task_receiver =
+            check_watcher.as_ref().map_or(&no_check_tasks, |it| it.receiver());
         let event = select! {
             recv(msg_receiver) -> msg => match msg {
                 Ok(msg) => Event::Msg(msg),
@@ -182,12 +231,27 @@ fn main_loop_inner(
                 Ok(task) => Event::Vfs(task),
                 Err(RecvError) => bail!("vfs died"),
             },
-            recv(libdata_receiver) -> data => Event::Lib(data.unwrap())
+            recv(libdata_receiver) -> data => Event::Lib(data.unwrap()),
+            recv(check_task_receiver) -> task => Event::CheckWatcher(task.unwrap()),
         };
         log::info!("loop_turn = {:?}", event);
         let _p = profile("loop_turn");
         let mut state_changed = false;
+        let mut workspaces_reloaded = false;
         match event {
+            Event::Task(Task::Retry(req)) => {
+                // The client might have canceled the request in the meantime.
+                if pending_requests.remove(&req.id) {
+                    on_request(state, pending_requests, pool, &task_sender, req)?;
+                }
+            }
+            Event::Task(Task::Reload { generation, roots, workspaces }) => {
+                if generation == reload_generation {
+                    apply_workspaces(msg_sender, state, subs, roots, workspaces);
+                    workspaces_reloaded = true;
+                    state_changed = true;
+                }
+            }
             Event::Task(task) => on_task(task, msg_sender, pending_requests),
             Event::Vfs(task) => {
                 state.vfs.write().handle_task(task);
@@ -197,6 +261,20 @@ fn main_loop_inner(
                 state.add_lib(lib);
                 in_flight_libraries -= 1;
             }
+            Event::CheckWatcher(task) => match task {
+                CheckTask::Begin => pending_check_diagnostics = CheckDiagnostics::default(),
+                CheckTask::Diagnostic(url, diagnostic) => {
+                    pending_check_diagnostics.add(url, diagnostic)
+                }
+                CheckTask::End => {
+                    let check_diagnostics = std::mem::replace(
+                        &mut pending_check_diagnostics,
+                        CheckDiagnostics::default(),
+                    );
+                    on_check_end(msg_sender, state, subs, check_diagnostics);
+                    state_changed = true;
+                }
+            },
             Event::Msg(msg) => match msg {
                 RawMessage::Request(req) => {
                     let req = match handle_shutdown(req, msg_sender) {
@@ -209,50 +287,148 @@ fn main_loop_inner(
                             let resp = RawResponse::ok::<req::CollectGarbage>(id, &());
                             msg_sender.send(resp.into()).unwrap()
                         }
-                        Err(req) => {
-                            match on_request(state, pending_requests, pool, &task_sender, req)? {
-                                None => (),
-                                Some(req) => {
-                                    log::error!("unknown request: {:?}", req);
-                                    let resp = RawResponse::err(
-                                        req.id,
-                                        ErrorCode::MethodNotFound as i32,
-                                        "unknown request".to_string(),
-                                    );
-                                    msg_sender.send(resp.into()).unwrap()
+                        Err(req) => match req.cast::<req::MemoryUsage>() {
+                            Ok((id, ())) => {
+                                let usage = state.memory_usage();
+                                let resp = RawResponse::ok::<req::MemoryUsage>(id, &usage);
+                                msg_sender.send(resp.into()).unwrap()
+                            }
+                            Err(req) => {
+                                match on_request(state, pending_requests, pool, &task_sender, req)?
+                                {
+                                    None => (),
+                                    Some(req) => {
+                                        log::error!("unknown request: {:?}", req);
+                                        let resp = RawResponse::err(
+                                            req.id,
+                                            ErrorCode::MethodNotFound as i32,
+                                            "unknown request".to_string(),
+                                        );
+                                        msg_sender.send(resp.into()).unwrap()
+                                    }
                                 }
                             }
-                        }
+                        },
                     }
                 }
                 RawMessage::Notification(not) => {
-                    on_notification(msg_sender, state, pending_requests, subs, not)?;
+                    match not.cast::<req::DidChangeWorkspaceFolders>() {
+                        Ok(params) => {
+                            reload_roots = on_workspace_folders_change(&reload_roots, params);
+                            reload_generation += 1;
+                            reload_workspaces_on_threadpool(
+                                pool,
+                                state,
+                                msg_sender,
+                                task_sender.clone(),
+                                reload_generation,
+                                reload_roots.clone(),
+                            );
+                        }
+                        Err(not) => match not.cast::<req::DidChangeConfiguration>() {
+                            Ok(params) => {
+                                let old_config = Arc::clone(&state.config);
+                                on_config_change(state, params);
+                                if state.config.cargo_config() != old_config.cargo_config()
+                                    || state.config.exclude_dirs != old_config.exclude_dirs
+                                {
+                                    reload_generation += 1;
+                                    reload_workspaces_on_threadpool(
+                                        pool,
+                                        state,
+                                        msg_sender,
+                                        task_sender.clone(),
+                                        reload_generation,
+                                        reload_roots.clone(),
+                                    );
+                                } else if state.config.cargo_check_on_save
+                                    != old_config.cargo_check_on_save
+                                {
+                                    check_watcher = start_check_watcher(state);
+                                    pending_check_diagnostics = CheckDiagnostics::default();
+                                    on_check_end(msg_sender, state, subs, Default::default());
+                                }
+                            }
+                            Err(not) => on_notification(
+                                msg_sender,
+                                state,
+                                pending_requests,
+                                subs,
+                                check_watcher.as_ref(),
+                                not,
+                            )?,
+                        },
+                    }
                     state_changed = true;
                 }
-                RawMessage::Response(resp) => log::error!("unexpected response: {:?}", resp),
+                // Our only requests are `window/workDoneProgress/create`,
+                // whose responses are empty.
+                RawMessage::Response(resp) => {
+                    if resp.error.is_some() {
+                        log::error!("request failed: {:?}", resp)
+                    }
+                }
             },
         };
 
+        if workspaces_reloaded {
+            // Libraries being indexed belong to the previous state, their data
+            // is dropped when it's ready.
+            pending_libraries.clear();
+            in_flight_libraries = 0;
+            let (sender, receiver) = unbounded();
+            libdata_sender = sender;
+            libdata_receiver = receiver;
+            pending_check_diagnostics = CheckDiagnostics::default();
+            check_watcher = start_check_watcher(state);
+            if let Some(check_watcher) = &check_watcher {
+                let _ = check_watcher.sender().try_send(());
+            }
+            send_workspace_notification = true;
+            if let Some(progress) = indexing_progress.take() {
+                msg_sender.send(progress.end().into()).unwrap();
+            }
+            roots_total = state.roots_to_scan;
+            indexing_progress = begin_indexing_progress(msg_sender, state);
+        }
+
         pending_libraries.extend(state.process_changes());
         while in_flight_libraries < THREADPOOL_SIZE - 3 && !pending_libraries.is_empty() {
             let (root, files) = pending_libraries.pop().unwrap();
             in_flight_libraries += 1;
             let sender = libdata_sender.clone();
+            let cache_dir = state.config.symbol_cache_directory.clone();
             pool.execute(move || {
                 log::info!("indexing {:?} ... ", root);
                 let _p = profile(&format!("indexed {:?}", root));
-                let data = LibraryData::prepare(root, files);
-                sender.send(data).unwrap();
+                let data = match cache_dir {
+                    Some(cache_dir) => {
+                        LibraryData::prepare_with_symbol_cache(root, files, &cache_dir)
+                    }
+                    None => LibraryData::prepare(root, files),
+                };
+                // The receiver is gone if the workspace folders have changed.
+                let _ = sender.send(data);
             });
         }
 
+        if let Some(not) =
+            indexing_progress.as_mut().and_then(|it| it.report(roots_total - state.roots_to_scan))
+        {
+            msg_sender.send(not.into()).unwrap();
+        }
+
         if send_workspace_notification
             && state.roots_to_scan == 0
             && pending_libraries.is_empty()
             && in_flight_libraries == 0
         {
+            if let Some(progress) = indexing_progress.take() {
+                msg_sender.send(progress.end().into()).unwrap();
+            }
+            prime_caches_on_threadpool(pool, state, msg_sender, task_sender.clone());
             let n_packages: usize = state.workspaces.iter().map(|it| it.count()).sum();
-            if options.show_workspace_loaded {
+            if state.config.show_workspace_loaded {
                 let msg = format!("workspace loaded, {} rust packages", n_packages);
                 show_message(req::MessageType::Info, msg, msg_sender);
             }
@@ -264,7 +440,6 @@ fn main_loop_inner(
             update_file_notifications_on_threadpool(
                 pool,
                 state.snapshot(),
-                options.publish_decorations,
                 task_sender.clone(),
                 subs.subscriptions(),
             )
@@ -272,6 +447,71 @@ fn main_loop_inner(
     }
 }
 
+fn begin_indexing_progress(
+    msg_sender: &Sender<RawMessage>,
+    state: &ServerWorldState,
+) -> Option<Progress> {
+    if state.config.work_done_progress {
+        Some(Progress::begin(msg_sender, "indexing", "Indexing", state.roots_to_scan))
+    } else {
+        None
+    }
+}
+
+/// Computes the symbols of the workspace in the background, so that the first
+/// symbol search doesn't have to.
+fn prime_caches_on_threadpool(
+    pool: &ThreadPool,
+    state: &ServerWorldState,
+    msg_sender: &Sender<RawMessage>,
+    sender: Sender<Task>,
+) {
+    let world = state.snapshot();
+    let files = match world.analysis().local_files() {
+        Ok(it) => it,
+        Err(_) => return,
+    };
+    let mut progress = if state.config.work_done_progress {
+        Some(Progress::begin(msg_sender, "primeCaches", "Indexing symbols", files.len()))
+    } else {
+        None
+    };
+    pool.execute(move || {
+        for (i, &file_id) in files.iter().enumerate() {
+            // The remaining symbols are computed on demand if a change cancels
+            // the priming.
+            if world.analysis().prime_symbols(file_id).is_err() {
+                break;
+            }
+            if let Some(not) = progress.as_mut().and_then(|it| it.report(i + 1)) {
+                sender.send(Task::Notify(not)).unwrap();
+            }
+        }
+        if let Some(progress) = progress {
+            sender.send(Task::Notify(progress.end())).unwrap();
+        }
+    });
+}
+
+/// Runs `cargo check` on save in the Cargo workspaces, if enabled.
+fn start_check_watcher(state: &ServerWorldState) -> Option<Worker<(), CheckTask>> {
+    if !state.config.cargo_check_on_save {
+        return None;
+    }
+    let workspace_roots: Vec<PathBuf> = state
+        .workspaces
+        .iter()
+        .filter_map(|ws| match ws {
+            ProjectWorkspace::Cargo { cargo, .. } => Some(cargo.workspace_root().to_path_buf()),
+            ProjectWorkspace::Json { .. } => None,
+        })
+        .collect();
+    if workspace_roots.is_empty() {
+        return None;
+    }
+    Some(check_watcher(workspace_roots))
+}
+
 fn on_task(task: Task, msg_sender: &Sender<RawMessage>, pending_requests: &mut FxHashSet<u64>) {
     match task {
         Task::Respond(response) => {
@@ -282,6 +522,18 @@ fn on_task(task: Task, msg_sender: &Sender<RawMessage>, pending_requests: &mut F
         Task::Notify(n) => {
             msg_sender.send(n.into()).unwrap();
         }
+        // The server is shutting down, there's nothing to reload.
+        Task::Reload { .. } => (),
+        Task::Retry(req) => {
+            if pending_requests.remove(&req.id) {
+                let response = RawResponse::err(
+                    req.id,
+                    ErrorCode::RequestCanceled as i32,
+                    "canceled by shutdown".to_string(),
+                );
+                msg_sender.send(response.into()).unwrap();
+            }
+        }
     }
 }
 
@@ -295,10 +547,14 @@ fn on_request(
     let mut pool_dispatcher = PoolDispatcher { req: Some(req), res: None, pool, world, sender };
     let req = pool_dispatcher
         .on::<req::AnalyzerStatus>(handlers::handle_analyzer_status)?
+        .on::<req::Profile>(handlers::handle_profile)?
         .on::<req::SyntaxTree>(handlers::handle_syntax_tree)?
         .on::<req::ExtendSelection>(handlers::handle_extend_selection)?
+        .on::<req::TypeOfSelection>(handlers::handle_type_of_selection)?
         .on::<req::FindMatchingBrace>(handlers::handle_find_matching_brace)?
         .on::<req::JoinLines>(handlers::handle_join_lines)?
+        .on::<req::MoveItem>(handlers::handle_move_item)?
+        .on::<req::Ssr>(handlers::handle_ssr)?
         .on::<req::OnEnter>(handlers::handle_on_enter)?
         .on::<req::OnTypeFormatting>(handlers::handle_on_type_formatting)?
         .on::<req::DocumentSymbolRequest>(handlers::handle_document_symbol)?
@@ -306,10 +562,13 @@ fn on_request(
         .on::<req::GotoDefinition>(handlers::handle_goto_definition)?
         .on::<req::GotoImplementation>(handlers::handle_goto_implementation)?
         .on::<req::ParentModule>(handlers::handle_parent_module)?
+        .on::<req::OpenCargoToml>(handlers::handle_open_cargo_toml)?
         .on::<req::Runnables>(handlers::handle_runnables)?
+        .on::<req::DebugSingle>(handlers::handle_debug_single)?
         .on::<req::DecorationsRequest>(handlers::handle_decorations)?
         .on::<req::Completion>(handlers::handle_completion)?
         .on::<req::CodeActionRequest>(handlers::handle_code_action)?
+        .on::<req::ResolveAssist>(handlers::handle_resolve_assist)?
         .on::<req::CodeLensRequest>(handlers::handle_code_lens)?
         .on::<req::CodeLensResolve>(handlers::handle_code_lens_resolve)?
         .on::<req::FoldingRangeRequest>(handlers::handle_folding_range)?
@@ -318,6 +577,7 @@ fn on_request(
         .on::<req::PrepareRenameRequest>(handlers::handle_prepare_rename)?
         .on::<req::Rename>(handlers::handle_rename)?
         .on::<req::References>(handlers::handle_references)?
+        .on::<req::FindReferences>(handlers::handle_find_references)?
         .on::<req::Formatting>(handlers::handle_formatting)?
         .on::<req::DocumentHighlightRequest>(handlers::handle_document_highlight)?
         .finish();
@@ -336,6 +596,7 @@ fn on_notification(
     state: &mut ServerWorldState,
     pending_requests: &mut FxHashSet<u64>,
     subs: &mut Subscriptions,
+    check_watcher: Option<&Worker<(), CheckTask>>,
     not: RawNotification,
 ) -> Result<()> {
     let not = match not.cast::<req::Cancel>() {
@@ -362,11 +623,16 @@ fn on_notification(
         Ok(params) => {
             let uri = params.text_document.uri;
             let path = uri.to_file_path().map_err(|()| format_err!("invalid uri: {}", uri))?;
-            if let Some(file_id) =
-                state.vfs.write().add_file_overlay(&path, params.text_document.text)
-            {
-                subs.add_sub(FileId(file_id.0.into()));
-            }
+            let text = params.text_document.text;
+            // The vfs only takes the files of the workspaces, the others are
+            // analyzed on their own.
+            let vfs_file = state.vfs.write().add_file_overlay(&path, text.clone());
+            let file_id = match vfs_file {
+                Some(file_id) => FileId(file_id.0.into()),
+                None => state.add_detached_file(path, text),
+            };
+            subs.add_sub(file_id);
+            Arc::make_mut(&mut state.document_versions).insert(uri, params.text_document.version);
             return Ok(());
         }
         Err(not) => not,
@@ -377,7 +643,15 @@ fn on_notification(
             let path = uri.to_file_path().map_err(|()| format_err!("invalid uri: {}", uri))?;
             let text =
                 params.content_changes.pop().ok_or_else(|| format_err!("empty changes"))?.text;
-            state.vfs.write().change_file_overlay(path.as_path(), text);
+            match state.detached_files.file_id(&path) {
+                Some(file_id) => state.change_detached_file(file_id, text),
+                None => state.vfs.write().change_file_overlay(path.as_path(), text),
+            }
+            let versions = Arc::make_mut(&mut state.document_versions);
+            match params.text_document.version {
+                Some(version) => versions.insert(uri, version),
+                None => versions.remove(&uri),
+            };
             return Ok(());
         }
         Err(not) => not,
@@ -386,20 +660,172 @@ fn on_notification(
         Ok(params) => {
             let uri = params.text_document.uri;
             let path = uri.to_file_path().map_err(|()| format_err!("invalid uri: {}", uri))?;
-            if let Some(file_id) = state.vfs.write().remove_file_overlay(path.as_path()) {
-                subs.remove_sub(FileId(file_id.0.into()));
+            let vfs_file = state.vfs.write().remove_file_overlay(path.as_path());
+            let file_id = match vfs_file {
+                Some(file_id) => Some(FileId(file_id.0.into())),
+                None => state.remove_detached_file(&path),
+            };
+            if let Some(file_id) = file_id {
+                subs.remove_sub(file_id);
             }
-            let params = req::PublishDiagnosticsParams { uri, diagnostics: Vec::new() };
+            Arc::make_mut(&mut state.document_versions).remove(&uri);
+            // The errors of `cargo check` are still relevant for closed files.
+            let diagnostics =
+                state.check_diagnostics.diagnostics_for(&uri).cloned().map(Into::into).collect();
+            let params = req::PublishDiagnosticsParams { uri, diagnostics };
             let not = RawNotification::new::<req::PublishDiagnostics>(&params);
             msg_sender.send(not.into()).unwrap();
             return Ok(());
         }
         Err(not) => not,
     };
+    let not = match not.cast::<req::DidSaveTextDocument>() {
+        Ok(_params) => {
+            if let Some(check_watcher) = check_watcher {
+                // A check is already requested if the channel is full.
+                let _ = check_watcher.sender().try_send(());
+            }
+            return Ok(());
+        }
+        Err(not) => not,
+    };
     log::error!("unhandled notification: {:?}", not);
     Ok(())
 }
 
+/// Replaces the diagnostics of the previous check. The diagnostics of the open
+/// files are published with ours, the others are published right away.
+fn on_check_end(
+    msg_sender: &Sender<RawMessage>,
+    state: &mut ServerWorldState,
+    subs: &Subscriptions,
+    mut check_diagnostics: CheckDiagnostics,
+) {
+    let world = state.snapshot();
+    check_diagnostics.convert_columns(|uri, range| match world.uri_to_file_id(uri) {
+        Ok(file_id) => {
+            let line_index = world.line_index(file_id);
+            let convert = |pos: Position| {
+                line_index
+                    .index
+                    .offset_of_char_col(pos.line as u32, pos.character as u32)
+                    .conv_with(&line_index)
+            };
+            Range::new(convert(range.start), convert(range.end))
+        }
+        Err(_) => range,
+    });
+
+    let mut changed: Vec<_> = state.check_diagnostics.files().cloned().collect();
+    changed.extend(check_diagnostics.files().cloned());
+    changed.sort();
+    changed.dedup();
+    state.check_diagnostics = Arc::new(check_diagnostics);
+
+    let subscriptions = subs.subscriptions();
+    for uri in changed {
+        let file_id = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| state.vfs.read().path2file(&path))
+            .map(|it| FileId(it.0.into()));
+        if file_id.map_or(false, |it| subscriptions.contains(&it)) {
+            continue;
+        }
+        let diagnostics =
+            state.check_diagnostics.diagnostics_for(&uri).cloned().map(Into::into).collect();
+        let params = req::PublishDiagnosticsParams { uri, diagnostics };
+        let not = RawNotification::new::<req::PublishDiagnostics>(&params);
+        msg_sender.send(not.into()).unwrap();
+    }
+}
+
+/// The workspace roots after the change of the folders of the client.
+fn on_workspace_folders_change(
+    roots: &[PathBuf],
+    params: req::DidChangeWorkspaceFoldersParams,
+) -> Vec<PathBuf> {
+    let mut roots = roots.to_vec();
+    let removed: Vec<PathBuf> =
+        params.event.removed.iter().filter_map(|it| it.uri.to_file_path().ok()).collect();
+    roots.retain(|root| !removed.contains(root));
+    for root in params.event.added.iter().filter_map(|it| it.uri.to_file_path().ok()) {
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    roots
+}
+
+/// Replaces the configuration with the one sent by the client. The settings
+/// have the same shape as the initialization options.
+fn on_config_change(state: &mut ServerWorldState, params: req::DidChangeConfigurationParams) {
+    match Config::deserialize(params.settings) {
+        Ok(mut config) => {
+            config.offset_encoding = state.config.offset_encoding;
+            state.config = Arc::new(config)
+        }
+        Err(e) => log::error!("invalid configuration: {}", e),
+    }
+}
+
+/// Loads the workspaces of `roots` on the thread pool, as `cargo metadata` can
+/// take a while, and sends them back as a `Task::Reload`.
+fn reload_workspaces_on_threadpool(
+    pool: &ThreadPool,
+    state: &ServerWorldState,
+    msg_sender: &Sender<RawMessage>,
+    sender: Sender<Task>,
+    generation: u64,
+    roots: Vec<PathBuf>,
+) {
+    let config = Arc::clone(&state.config);
+    let msg_sender = msg_sender.clone();
+    pool.execute(move || {
+        let workspaces = load_workspaces(&roots, &config, &msg_sender);
+        sender.send(Task::Reload { generation, roots, workspaces }).unwrap();
+    });
+}
+
+/// Replaces the state with a fresh one for the loaded workspaces, so that
+/// nothing is left of the previous ones. The open files are carried over, the
+/// ones which are no longer part of a workspace as detached files, and the ones
+/// which now are part of one are moved into it.
+fn apply_workspaces(
+    msg_sender: &Sender<RawMessage>,
+    state: &mut ServerWorldState,
+    subs: &mut Subscriptions,
+    roots: Vec<PathBuf>,
+    workspaces: Vec<ProjectWorkspace>,
+) {
+    let analysis = state.analysis_host.analysis();
+    let open_files: Vec<(PathBuf, Arc<String>)> = subs
+        .subscriptions()
+        .into_iter()
+        .map(|file_id| (state.file_id_to_path(file_id), analysis.file_text(file_id)))
+        .collect();
+    let check_files: Vec<Url> = state.check_diagnostics.files().cloned().collect();
+    let document_versions = Arc::clone(&state.document_versions);
+
+    *state = ServerWorldState::new(roots, workspaces, Arc::clone(&state.config));
+    state.document_versions = document_versions;
+
+    *subs = Subscriptions::new();
+    for (path, text) in open_files {
+        let vfs_file = state.vfs.write().add_file_overlay(&path, text.to_string());
+        let file_id = match vfs_file {
+            Some(file_id) => FileId(file_id.0.into()),
+            None => state.add_detached_file(path, text.to_string()),
+        };
+        subs.add_sub(file_id);
+    }
+    for uri in check_files {
+        let params = req::PublishDiagnosticsParams { uri, diagnostics: Vec::new() };
+        let not = RawNotification::new::<req::PublishDiagnostics>(&params);
+        msg_sender.send(not.into()).unwrap();
+    }
+}
+
 struct PoolDispatcher<'a> {
     req: Option<RawRequest>,
     res: Option<u64>,
@@ -419,11 +845,17 @@ impl<'a> PoolDispatcher<'a> {
             None => return Ok(self),
             Some(req) => req,
         };
+        if req.method != R::METHOD {
+            self.req = Some(req);
+            return Ok(self);
+        }
+        let retry = req.clone();
         match req.cast::<R>() {
             Ok((id, params)) => {
                 let world = self.world.snapshot();
                 let sender = self.sender.clone();
                 self.pool.execute(move || {
+                    let _p = profile(R::METHOD);
                     let resp = match f(world, params) {
                         Ok(resp) => RawResponse::ok::<R>(id, &resp),
                         Err(e) => match e.downcast::<LspError>() {
@@ -432,27 +864,20 @@ impl<'a> PoolDispatcher<'a> {
                             }
                             Err(e) => {
                                 if is_canceled(&e) {
-                                    // FIXME: When https://github.com/Microsoft/vscode-languageserver-node/issues/457
-                                    // gets fixed, we can return the proper response.
-                                    // This works around the issue where "content modified" error would continuously
-                                    // show an message pop-up in VsCode
-                                    // RawResponse::err(
-                                    //     id,
-                                    //     ErrorCode::ContentModified as i32,
-                                    //     "content modified".to_string(),
-                                    // )
-                                    RawResponse {
-                                        id,
-                                        result: Some(serde_json::to_value(&()).unwrap()),
-                                        error: None,
-                                    }
-                                } else {
-                                    RawResponse::err(
-                                        id,
-                                        ErrorCode::InternalError as i32,
-                                        format!("{}\n{}", e, e.backtrace()),
-                                    )
+                                    // The result would be computed for an
+                                    // outdated text, like a completion at an
+                                    // offset which has moved. Rather than
+                                    // answering with an error, which VS Code
+                                    // shows as a pop-up, the request runs
+                                    // again once the change is applied.
+                                    sender.send(Task::Retry(retry)).unwrap();
+                                    return;
                                 }
+                                RawResponse::err(
+                                    id,
+                                    ErrorCode::InternalError as i32,
+                                    format!("{}\n{}", e, e.backtrace()),
+                                )
                             }
                         },
                     };
@@ -478,7 +903,6 @@ impl<'a> PoolDispatcher<'a> {
 fn update_file_notifications_on_threadpool(
     pool: &ThreadPool,
     world: ServerWorld,
-    publish_decorations: bool,
     sender: Sender<Task>,
     subscriptions: Vec<FileId>,
 ) {
@@ -495,7 +919,7 @@ fn update_file_notifications_on_threadpool(
                     sender.send(Task::Notify(not)).unwrap();
                 }
             }
-            if publish_decorations {
+            if world.config.publish_decorations {
                 match handlers::publish_decorations(&world, file_id) {
                     Err(e) => {
                         if !is_canceled(&e) {
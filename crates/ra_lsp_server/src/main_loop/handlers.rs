@@ -1,15 +1,16 @@
+use failure::format_err;
 use gen_lsp_server::ErrorCode;
 use lsp_types::{
     CodeActionResponse, CodeLens, Command, Diagnostic, DiagnosticSeverity, CodeAction,
     DocumentFormattingParams, DocumentHighlight, DocumentSymbol, FoldingRange,
     FoldingRangeKind, FoldingRangeParams, Hover, HoverContents, Location, MarkupContent,
-    MarkupKind, Position, PrepareRenameResponse, Range,
+    MarkupKind, NumberOrString, Position, PrepareRenameResponse, Range,
     RenameParams,SymbolInformation, TextDocumentIdentifier, TextEdit,
-    WorkspaceEdit,
+    Url, WorkspaceEdit,
 };
 use ra_ide_api::{
     FileId, FilePosition, FileRange, FoldKind, Query, RangeInfo, RunnableKind, Severity, Cancelable,
-    AssistId,
+    AssistId, GroupLabel, Direction, ReferenceFilter, ReferenceSearchResult,
 };
 use ra_syntax::{AstNode, SyntaxKind, TextUnit};
 use ra_prof::profile;
@@ -20,7 +21,7 @@ use std::io::Write;
 use url_serde::Ser;
 
 use crate::{
-    cargo_target_spec::{runnable_args, CargoTargetSpec},
+    cargo_target_spec::{debug_configuration, runnable_args, CargoTargetSpec},
     conv::{to_location, to_location_link, Conv, ConvWith, MapConvWith, TryConvWith},
     req::{self, Decoration},
     server_world::ServerWorld,
@@ -31,9 +32,23 @@ pub fn handle_analyzer_status(world: ServerWorld, _: ()) -> Result<String> {
     Ok(world.status())
 }
 
+/// Returns the traces of the profiler recorded since the last request, and
+/// sets its filter if there is a new one.
+pub fn handle_profile(_world: ServerWorld, params: req::ProfileParams) -> Result<Vec<String>> {
+    let traces = ra_prof::take_traces();
+    if let Some(filter) = params.filter {
+        ra_prof::set_filter(if filter.is_empty() {
+            ra_prof::Filter::disabled()
+        } else {
+            ra_prof::Filter::from_spec(&filter)
+        });
+    }
+    Ok(traces)
+}
+
 pub fn handle_syntax_tree(world: ServerWorld, params: req::SyntaxTreeParams) -> Result<String> {
     let id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(id);
+    let line_index = world.line_index(id);
     let text_range = params.range.map(|p| p.conv_with(&line_index));
     let res = world.analysis().syntax_tree(id, text_range);
     Ok(res)
@@ -44,7 +59,7 @@ pub fn handle_extend_selection(
     params: req::ExtendSelectionParams,
 ) -> Result<req::ExtendSelectionResult> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let selections = params
         .selections
         .into_iter()
@@ -55,12 +70,24 @@ pub fn handle_extend_selection(
     Ok(req::ExtendSelectionResult { selections })
 }
 
+pub fn handle_type_of_selection(
+    world: ServerWorld,
+    params: req::TypeOfSelectionParams,
+) -> Result<Option<req::TypeOfSelectionResult>> {
+    let frange = (&params.text_document, params.range).try_conv_with(&world)?;
+    let line_index = world.line_index(frange.file_id);
+    let res = world.analysis().type_of_selection(frange, params.qualified)?.map(|info| {
+        req::TypeOfSelectionResult { range: info.range.conv_with(&line_index), ty: info.info }
+    });
+    Ok(res)
+}
+
 pub fn handle_find_matching_brace(
     world: ServerWorld,
     params: req::FindMatchingBraceParams,
 ) -> Result<Vec<Position>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let res = params
         .offsets
         .into_iter()
@@ -81,6 +108,29 @@ pub fn handle_join_lines(
     world.analysis().join_lines(frange).try_conv_with(&world)
 }
 
+pub fn handle_ssr(world: ServerWorld, params: req::SsrParams) -> Result<req::SourceChange> {
+    match world.analysis().structural_search_replace(&params.query)? {
+        Ok(change) => change.try_conv_with(&world),
+        Err(message) => Err(LspError::new(ErrorCode::InvalidParams as i32, message).into()),
+    }
+}
+
+pub fn handle_move_item(
+    world: ServerWorld,
+    params: req::MoveItemParams,
+) -> Result<Option<req::SourceChange>> {
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let offset = params.position.conv_with(&world.line_index(file_id));
+    let direction = match params.direction {
+        req::MoveItemDirection::Up => Direction::Up,
+        req::MoveItemDirection::Down => Direction::Down,
+    };
+    match world.analysis().move_item(FilePosition { file_id, offset }, direction) {
+        None => Ok(None),
+        Some(edit) => Ok(Some(edit.try_conv_with(&world)?)),
+    }
+}
+
 pub fn handle_on_enter(
     world: ServerWorld,
     params: req::TextDocumentPositionParams,
@@ -97,7 +147,7 @@ pub fn handle_on_type_formatting(
     params: req::DocumentOnTypeFormattingParams,
 ) -> Result<Option<Vec<TextEdit>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let position = FilePosition {
         file_id,
         /// in `ra_ide_api`, the `on_type` invariant is that
@@ -127,7 +177,7 @@ pub fn handle_document_symbol(
     params: req::DocumentSymbolParams,
 ) -> Result<Option<req::DocumentSymbolResponse>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
 
     let mut parents: Vec<(DocumentSymbol, Option<usize>)> = Vec::new();
 
@@ -208,7 +258,7 @@ pub fn handle_goto_definition(
     params: req::TextDocumentPositionParams,
 ) -> Result<Option<req::GotoDefinitionResponse>> {
     let position = params.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(position.file_id);
+    let line_index = world.line_index(position.file_id);
     let nav_info = match world.analysis().goto_definition(position)? {
         None => return Ok(None),
         Some(it) => it,
@@ -228,7 +278,7 @@ pub fn handle_goto_implementation(
     params: req::TextDocumentPositionParams,
 ) -> Result<Option<req::GotoImplementationResponse>> {
     let position = params.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(position.file_id);
+    let line_index = world.line_index(position.file_id);
     let nav_info = match world.analysis().goto_implementation(position)? {
         None => return Ok(None),
         Some(it) => it,
@@ -256,12 +306,26 @@ pub fn handle_parent_module(
         .collect::<Result<Vec<_>>>()
 }
 
+pub fn handle_open_cargo_toml(
+    world: ServerWorld,
+    params: req::OpenCargoTomlParams,
+) -> Result<Option<Location>> {
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let manifest = match world.manifest_for(file_id) {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let uri = Url::from_file_path(manifest)
+        .map_err(|_| format_err!("can't convert path to url: {}", manifest.display()))?;
+    Ok(Some(Location::new(uri, Range::default())))
+}
+
 pub fn handle_runnables(
     world: ServerWorld,
     params: req::RunnablesParams,
 ) -> Result<Vec<req::Runnable>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let offset = params.position.map(|it| it.conv_with(&line_index));
     let mut res = Vec::new();
     let workspace_root = world.workspace_root_for(file_id);
@@ -280,6 +344,7 @@ pub fn handle_runnables(
                 RunnableKind::Test { name } => format!("test {}", name),
                 RunnableKind::TestMod { path } => format!("test-mod {}", path),
                 RunnableKind::Bench { name } => format!("bench {}", name),
+                RunnableKind::DocTest { test_id } => format!("doctest {}", test_id),
                 RunnableKind::Bin => "run binary".to_string(),
             },
             bin: "cargo".to_string(),
@@ -317,6 +382,13 @@ pub fn handle_runnables(
     Ok(res)
 }
 
+pub fn handle_debug_single(
+    _world: ServerWorld,
+    params: req::Runnable,
+) -> Result<req::DebugConfiguration> {
+    debug_configuration(&params)
+}
+
 pub fn handle_decorations(
     world: ServerWorld,
     params: TextDocumentIdentifier,
@@ -332,7 +404,7 @@ pub fn handle_completion(
     let _p = profile("handle_completion");
     let position = {
         let file_id = params.text_document.try_conv_with(&world)?;
-        let line_index = world.analysis().file_line_index(file_id);
+        let line_index = world.line_index(file_id);
         let offset = params.position.conv_with(&line_index);
         FilePosition { file_id, offset }
     };
@@ -358,11 +430,12 @@ pub fn handle_completion(
         return Ok(None);
     }
 
-    let items = match world.analysis().completions(position)? {
+    let options = world.config.completion_options();
+    let items = match world.analysis().completions(position, &options)? {
         None => return Ok(None),
         Some(items) => items,
     };
-    let line_index = world.analysis().file_line_index(position.file_id);
+    let line_index = world.line_index(position.file_id);
     let items = items.into_iter().map(|item| item.conv_with(&line_index)).collect();
 
     Ok(Some(req::CompletionResponse::Array(items)))
@@ -373,7 +446,7 @@ pub fn handle_folding_range(
     params: FoldingRangeParams,
 ) -> Result<Option<Vec<FoldingRange>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
 
     let res = Some(
         world
@@ -430,44 +503,42 @@ pub fn handle_hover(
         None => return Ok(None),
         Some(info) => info,
     };
-    let line_index = world.analysis.file_line_index(position.file_id);
+    let line_index = world.line_index(position.file_id);
     let range = info.range.conv_with(&line_index);
     let res = Hover {
         contents: HoverContents::Markup(MarkupContent {
             kind: MarkupKind::Markdown,
-            value: info.info.to_markup(),
+            value: info.info.to_markup_with_links(|nav| {
+                let location = nav.try_conv_with(&world).ok()?;
+                Some(format!("{}#L{}", location.uri, location.range.start.line + 1))
+            }),
         }),
         range: Some(range),
     };
     Ok(Some(res))
 }
 
-/// Test doc comment
 pub fn handle_prepare_rename(
     world: ServerWorld,
     params: req::TextDocumentPositionParams,
 ) -> Result<Option<PrepareRenameResponse>> {
     let position = params.try_conv_with(&world)?;
+    let line_index = world.line_index(position.file_id);
 
-    // We support renaming references like handle_rename does.
-    // In the future we may want to reject the renaming of things like keywords here too.
-    let refs = match world.analysis().find_all_refs(position)? {
-        None => return Ok(None),
-        Some(refs) => refs,
+    let RangeInfo { range, info: placeholder } = match world.analysis().prepare_rename(position)? {
+        Ok(it) => it,
+        Err(message) => {
+            return Err(LspError::new(ErrorCode::InvalidParams as i32, message).into());
+        }
     };
+    let range = range.conv_with(&line_index);
 
-    // Refs should always have a declaration
-    let r = refs.declaration();
-    let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
-    let loc = to_location(r.file_id(), r.range(), &world, &line_index)?;
-
-    Ok(Some(PrepareRenameResponse::Range(loc.range)))
+    Ok(Some(PrepareRenameResponse::RangeWithPlaceholder { range, placeholder }))
 }
 
 pub fn handle_rename(world: ServerWorld, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let offset = params.position.conv_with(&line_index);
 
     if params.new_name.is_empty() {
@@ -495,7 +566,7 @@ pub fn handle_references(
     params: req::ReferenceParams,
 ) -> Result<Option<Vec<Location>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let offset = params.position.conv_with(&line_index);
 
     let refs = match world.analysis().find_all_refs(FilePosition { file_id, offset })? {
@@ -503,19 +574,47 @@ pub fn handle_references(
         Some(refs) => refs,
     };
 
-    let locations = if params.context.include_declaration {
+    Ok(Some(reference_locations(&world, refs, params.context.include_declaration)))
+}
+
+pub fn handle_find_references(
+    world: ServerWorld,
+    params: req::FindReferencesParams,
+) -> Result<Option<Vec<Location>>> {
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let line_index = world.line_index(file_id);
+    let offset = params.position.conv_with(&line_index);
+    let filter =
+        ReferenceFilter { scope: params.scope.conv(), access: params.access.map(Conv::conv) };
+
+    let refs = match world.analysis().find_refs(FilePosition { file_id, offset }, filter)? {
+        None => return Ok(None),
+        Some(refs) => refs,
+    };
+
+    Ok(Some(reference_locations(&world, refs, params.include_declaration)))
+}
+
+fn reference_locations(
+    world: &ServerWorld,
+    refs: ReferenceSearchResult,
+    include_declaration: bool,
+) -> Vec<Location> {
+    if include_declaration {
         refs.into_iter()
-            .filter_map(|r| to_location(r.file_id, r.range, &world, &line_index).ok())
+            .filter_map(|r| {
+                to_location(r.file_id, r.range, world, &world.line_index(r.file_id)).ok()
+            })
             .collect()
     } else {
         // Only iterate over the references if include_declaration was false
         refs.references()
             .iter()
-            .filter_map(|r| to_location(r.file_id, r.range, &world, &line_index).ok())
+            .filter_map(|r| {
+                to_location(r.file_id, r.range, world, &world.line_index(r.file_id)).ok()
+            })
             .collect()
-    };
-
-    Ok(Some(locations))
+    }
 }
 
 pub fn handle_formatting(
@@ -525,11 +624,12 @@ pub fn handle_formatting(
     let file_id = params.text_document.try_conv_with(&world)?;
     let file = world.analysis().file_text(file_id);
 
-    let file_line_index = world.analysis().file_line_index(file_id);
+    let file_line_index = world.line_index(file_id);
     let end_position = TextUnit::of_str(&file).conv_with(&file_line_index);
 
     use std::process;
     let mut rustfmt = process::Command::new("rustfmt");
+    rustfmt.args(&world.config.rustfmt_args);
     rustfmt.stdin(process::Stdio::piped()).stdout(process::Stdio::piped());
 
     if let Ok(path) = params.text_document.uri.to_file_path() {
@@ -568,18 +668,17 @@ pub fn handle_code_action(
 ) -> Result<Option<CodeActionResponse>> {
     let _p = profile("handle_code_action");
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let range = params.range.conv_with(&line_index);
 
-    let assists = world.analysis().assists(FileRange { file_id, range })?.into_iter();
+    let assists = world.analysis().assist_labels(FileRange { file_id, range })?.into_iter();
     let diagnostics = world.analysis().diagnostics(file_id)?;
     let mut res: Vec<CodeAction> = Vec::new();
 
     let fixes_from_diagnostics = diagnostics
         .into_iter()
-        .filter_map(|d| Some((d.range, d.fix?)))
-        .filter(|(diag_range, _fix)| diag_range.intersection(&range).is_some())
-        .map(|(_range, fix)| fix);
+        .filter(|d| d.range.intersection(&range).is_some())
+        .flat_map(|d| d.fixes);
 
     for source_edit in fixes_from_diagnostics {
         let title = source_edit.label.clone();
@@ -599,15 +698,64 @@ pub fn handle_code_action(
         };
         res.push(action);
     }
+    res.extend(world.check_diagnostics.fixes_for(&params.text_document.uri, params.range));
+
+    // Organizing the imports is a source action of the whole file, which is
+    // only offered when the client asks for it.
+    let organize_imports = "source.organizeImports";
+    let wants_organize_imports =
+        params.context.only.as_ref().map_or(false, |only| {
+            only.iter().any(|kind| organize_imports.starts_with(kind.as_str()))
+        });
+    if wants_organize_imports {
+        if let Some(change) = world.analysis().organize_imports(file_id)? {
+            let command = Command {
+                title: "Organize imports".to_string(),
+                command: "rust-analyzer.applySourceChange".to_string(),
+                arguments: Some(vec![to_value(change.try_conv_with(&world)?).unwrap()]),
+            };
+            res.push(CodeAction {
+                title: command.title.clone(),
+                kind: Some(organize_imports.to_string()),
+                diagnostics: None,
+                edit: None,
+                command: Some(command),
+            });
+        }
+    }
 
+    // Only the labels of the assists are computed here, the edit of the one
+    // picked by the user is computed by `handle_resolve_assist`.
+    let mut groups: Vec<(GroupLabel, usize, Vec<req::ResolveAssistParams>)> = Vec::new();
     for assist in assists {
-        let title = assist.change.label.clone();
-        let edit = assist.change.try_conv_with(&world)?;
+        let resolve_params = req::ResolveAssistParams {
+            text_document: params.text_document.clone(),
+            range: params.range,
+            id: assist.id.0.to_string(),
+            label: assist.label.clone(),
+        };
+        if let Some(group) = assist.group {
+            match groups.iter_mut().find(|(it, _, _)| *it == group) {
+                Some((_, _, alternatives)) => alternatives.push(resolve_params),
+                None => {
+                    // Reserve the place of the group among the other actions.
+                    groups.push((group.clone(), res.len(), vec![resolve_params]));
+                    res.push(CodeAction {
+                        title: group.0.clone(),
+                        kind: None,
+                        diagnostics: None,
+                        edit: None,
+                        command: None,
+                    });
+                }
+            }
+            continue;
+        }
 
         let command = Command {
-            title,
-            command: "rust-analyzer.applySourceChange".to_string(),
-            arguments: Some(vec![to_value(edit).unwrap()]),
+            title: assist.label,
+            command: "rust-analyzer.resolveAssist".to_string(),
+            arguments: Some(vec![to_value(resolve_params).unwrap()]),
         };
         let action = CodeAction {
             title: command.title.clone(),
@@ -621,16 +769,34 @@ pub fn handle_code_action(
         };
         res.push(action);
     }
+    for (group, idx, alternatives) in groups {
+        res[idx].command = Some(Command {
+            title: group.0,
+            command: "rust-analyzer.selectAndResolveAssist".to_string(),
+            arguments: Some(alternatives.into_iter().map(|it| to_value(it).unwrap()).collect()),
+        });
+    }
 
     Ok(Some(CodeActionResponse::Actions(res)))
 }
 
+pub fn handle_resolve_assist(
+    world: ServerWorld,
+    params: req::ResolveAssistParams,
+) -> Result<Option<req::SourceChange>> {
+    let frange = (&params.text_document, params.range).try_conv_with(&world)?;
+    match world.analysis().resolve_assist(frange, &params.id, &params.label)? {
+        None => Ok(None),
+        Some(assist) => Ok(Some(assist.change.try_conv_with(&world)?)),
+    }
+}
+
 pub fn handle_code_lens(
     world: ServerWorld,
     params: req::CodeLensParams,
 ) -> Result<Option<Vec<CodeLens>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
 
     let mut lenses: Vec<CodeLens> = Default::default();
     let workspace_root = world.workspace_root_for(file_id);
@@ -640,6 +806,7 @@ pub fn handle_code_lens(
         let title = match &runnable.kind {
             RunnableKind::Test { .. } | RunnableKind::TestMod { .. } => Some("▶️Run Test"),
             RunnableKind::Bench { .. } => Some("Run Bench"),
+            RunnableKind::DocTest { .. } => Some("▶️Run Doctest"),
             _ => None,
         };
 
@@ -662,21 +829,33 @@ pub fn handle_code_lens(
                 command: Some(Command {
                     title: title.into(),
                     command: "rust-analyzer.runSingle".into(),
-                    arguments: Some(vec![to_value(r).unwrap()]),
+                    arguments: Some(vec![to_value(&r).unwrap()]),
                 }),
                 data: None,
             };
 
             lenses.push(lens);
+
+            // Tests can also be built to be launched in a debugger.
+            if let RunnableKind::Test { .. } | RunnableKind::TestMod { .. } = runnable.kind {
+                lenses.push(CodeLens {
+                    range,
+                    command: Some(Command {
+                        title: "Debug".into(),
+                        command: "rust-analyzer.debugSingle".into(),
+                        arguments: Some(vec![to_value(r).unwrap()]),
+                    }),
+                    data: None,
+                });
+            }
         }
     }
 
     // Handle impls
+    let structure = world.analysis().file_structure(file_id);
     lenses.extend(
-        world
-            .analysis()
-            .file_structure(file_id)
-            .into_iter()
+        structure
+            .iter()
             .filter(|it| match it.kind {
                 SyntaxKind::TRAIT_DEF | SyntaxKind::STRUCT_DEF | SyntaxKind::ENUM_DEF => true,
                 _ => false,
@@ -694,6 +873,50 @@ pub fn handle_code_lens(
             }),
     );
 
+    // Handle the items of traits and of trait impls
+    for it in structure.iter() {
+        match it.kind {
+            SyntaxKind::FN_DEF | SyntaxKind::CONST_DEF | SyntaxKind::TYPE_ALIAS_DEF => (),
+            _ => continue,
+        }
+        let parent_kind = match it.parent {
+            Some(parent) => structure[parent].kind,
+            None => continue,
+        };
+        let range = it.navigation_range.conv_with(&line_index);
+        let lens_params =
+            req::TextDocumentPositionParams::new(params.text_document.clone(), range.start);
+        match parent_kind {
+            SyntaxKind::TRAIT_DEF => lenses.push(CodeLens {
+                range,
+                command: None,
+                data: Some(to_value(CodeLensResolveData::Impls(lens_params)).unwrap()),
+            }),
+            SyntaxKind::IMPL_BLOCK => {
+                let position = FilePosition { file_id, offset: it.navigation_range.start() };
+                let nav = match world.analysis().goto_trait_item(position)? {
+                    Some(it) => it.info,
+                    None => continue,
+                };
+                let location = nav.try_conv_with(&world)?;
+                lenses.push(CodeLens {
+                    range,
+                    command: Some(Command {
+                        title: "Go to trait item".into(),
+                        command: "rust-analyzer.showReferences".into(),
+                        arguments: Some(vec![
+                            to_value(&Ser::new(&params.text_document.uri)).unwrap(),
+                            to_value(range.start).unwrap(),
+                            to_value(vec![location]).unwrap(),
+                        ]),
+                    }),
+                    data: None,
+                });
+            }
+            _ => (),
+        }
+    }
+
     Ok(Some(lenses))
 }
 
@@ -752,16 +975,20 @@ pub fn handle_document_highlight(
     params: req::TextDocumentPositionParams,
 ) -> Result<Option<Vec<DocumentHighlight>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
 
-    let refs = match world.analysis().find_all_refs(params.try_conv_with(&world)?)? {
+    let ranges = match world.analysis().highlight_related(params.try_conv_with(&world)?)? {
         None => return Ok(None),
-        Some(refs) => refs,
+        Some(ranges) => ranges,
     };
 
     Ok(Some(
-        refs.into_iter()
-            .map(|r| DocumentHighlight { range: r.range.conv_with(&line_index), kind: None })
+        ranges
+            .into_iter()
+            .map(|it| DocumentHighlight {
+                range: it.range.conv_with(&line_index),
+                kind: Some(it.access.conv()),
+            })
             .collect(),
     ))
 }
@@ -771,19 +998,24 @@ pub fn publish_diagnostics(
     file_id: FileId,
 ) -> Result<req::PublishDiagnosticsParams> {
     let uri = world.file_id_to_uri(file_id)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let diagnostics = world
         .analysis()
         .diagnostics(file_id)?
         .into_iter()
-        .map(|d| Diagnostic {
-            range: d.range.conv_with(&line_index),
-            severity: Some(to_diagnostic_severity(d.severity)),
-            code: None,
-            source: Some("rust-analyzer".to_string()),
-            message: d.message,
-            related_information: None,
+        .filter(|d| d.code.map_or(true, |code| !world.config.disabled_diagnostics.contains(code)))
+        .map(|d| req::Diagnostic {
+            tags: if d.is_unnecessary() { vec![req::DiagnosticTag::Unnecessary] } else { vec![] },
+            diagnostic: Diagnostic {
+                range: d.range.conv_with(&line_index),
+                severity: Some(to_diagnostic_severity(d.severity)),
+                code: d.code.map(|it| NumberOrString::String(it.to_string())),
+                source: Some("rust-analyzer".to_string()),
+                message: d.message,
+                related_information: None,
+            },
         })
+        .chain(world.check_diagnostics.diagnostics_for(&uri).cloned().map(Into::into))
         .collect();
     Ok(req::PublishDiagnosticsParams { uri, diagnostics })
 }
@@ -797,12 +1029,16 @@ pub fn publish_decorations(
 }
 
 fn highlight(world: &ServerWorld, file_id: FileId) -> Result<Vec<Decoration>> {
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let res = world
         .analysis()
         .highlight(file_id)?
         .into_iter()
-        .map(|h| Decoration { range: h.range.conv_with(&line_index), tag: h.tag })
+        .map(|h| Decoration {
+            range: h.range.conv_with(&line_index),
+            tag: h.tag,
+            binding_hash: h.binding_hash.map(|it| it.to_string()),
+        })
         .collect();
     Ok(res)
 }
@@ -812,6 +1048,7 @@ fn to_diagnostic_severity(severity: Severity) -> DiagnosticSeverity {
 
     match severity {
         Error => DiagnosticSeverity::Error,
+        Warning => DiagnosticSeverity::Warning,
         WeakWarning => DiagnosticSeverity::Hint,
     }
 }
@@ -0,0 +1,73 @@
+//! Reports the progress of long running operations, like the loading of the
+//! workspaces, with `$/progress` notifications.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam_channel::Sender;
+use gen_lsp_server::{RawMessage, RawNotification, RawRequest};
+
+use crate::req::{self, WorkDoneProgress};
+
+/// The ids of our requests to the client, and the suffixes of the tokens.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+pub(super) struct Progress {
+    token: String,
+    total: usize,
+    percentage: Option<u32>,
+}
+
+impl Progress {
+    /// Creates the progress token on the client and reports the beginning of
+    /// an operation with `total` steps.
+    pub(super) fn begin(
+        sender: &Sender<RawMessage>,
+        name: &str,
+        title: &str,
+        total: usize,
+    ) -> Progress {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let token = format!("rustAnalyzer/{}/{}", name, id);
+        let params = req::WorkDoneProgressCreateParams { token: token.clone() };
+        let request = RawRequest::new::<req::WorkDoneProgressCreate>(id as u64, &params);
+        sender.send(request.into()).unwrap();
+
+        let progress = Progress { token, total, percentage: Some(0) };
+        let value = WorkDoneProgress::Begin {
+            title: title.to_string(),
+            message: Some(progress.message(0)),
+            percentage: Some(0),
+        };
+        sender.send(progress.notification(value).into()).unwrap();
+        progress
+    }
+
+    /// Reports that `done` steps are done. Nothing is reported if the
+    /// percentage is the same as the last time.
+    pub(super) fn report(&mut self, done: usize) -> Option<RawNotification> {
+        let percentage = if self.total == 0 { 100 } else { (done * 100 / self.total) as u32 };
+        if self.percentage == Some(percentage) {
+            return None;
+        }
+        self.percentage = Some(percentage);
+        let value = WorkDoneProgress::Report {
+            message: Some(self.message(done)),
+            percentage: Some(percentage),
+        };
+        Some(self.notification(value))
+    }
+
+    pub(super) fn end(self) -> RawNotification {
+        self.notification(WorkDoneProgress::End { message: None })
+    }
+
+    fn message(&self, done: usize) -> String {
+        format!("{}/{}", done, self.total)
+    }
+
+    fn notification(&self, value: WorkDoneProgress) -> RawNotification {
+        let params = req::ProgressParams { token: self.token.clone(), value };
+        RawNotification::new::<req::Progress>(&params)
+    }
+}
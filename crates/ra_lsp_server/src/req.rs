@@ -5,9 +5,11 @@ use url_serde;
 
 pub use lsp_types::{
     notification::*, request::*, ApplyWorkspaceEditParams, CodeActionParams, CodeLens, CodeLensParams,
-    CompletionParams, CompletionResponse, DocumentOnTypeFormattingParams, DocumentSymbolParams,
+    CompletionParams, CompletionResponse, DidChangeConfigurationParams,
+    DidChangeWorkspaceFoldersParams,
+    DocumentOnTypeFormattingParams, DocumentSymbolParams,
     DocumentSymbolResponse, ExecuteCommandParams, Hover, InitializeResult,
-    PublishDiagnosticsParams, ReferenceParams, SignatureHelp, TextDocumentEdit,
+    ReferenceParams, SignatureHelp, TextDocumentEdit,
     TextDocumentPositionParams, TextEdit, WorkspaceEdit, WorkspaceSymbolParams,
     MessageType, ShowMessageParams,
 };
@@ -20,6 +22,117 @@ impl Request for AnalyzerStatus {
     const METHOD: &'static str = "rust-analyzer/analyzerStatus";
 }
 
+pub enum WorkDoneProgressCreate {}
+
+impl Request for WorkDoneProgressCreate {
+    type Params = WorkDoneProgressCreateParams;
+    type Result = ();
+    const METHOD: &'static str = "window/workDoneProgress/create";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkDoneProgressCreateParams {
+    pub token: String,
+}
+
+pub enum Progress {}
+
+impl Notification for Progress {
+    type Params = ProgressParams;
+    const METHOD: &'static str = "$/progress";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressParams {
+    pub token: String,
+    pub value: WorkDoneProgress,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum WorkDoneProgress {
+    Begin {
+        title: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        percentage: Option<u32>,
+    },
+    Report {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        percentage: Option<u32>,
+    },
+    End {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+}
+
+/// `textDocument/publishDiagnostics`, with the diagnostic tags of LSP 3.15.
+pub enum PublishDiagnostics {}
+
+impl Notification for PublishDiagnostics {
+    type Params = PublishDiagnosticsParams;
+    const METHOD: &'static str = "textDocument/publishDiagnostics";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishDiagnosticsParams {
+    #[serde(with = "url_serde")]
+    pub uri: Url,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    #[serde(flatten)]
+    pub diagnostic: lsp_types::Diagnostic,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<DiagnosticTag>,
+}
+
+impl From<lsp_types::Diagnostic> for Diagnostic {
+    fn from(diagnostic: lsp_types::Diagnostic) -> Diagnostic {
+        Diagnostic { diagnostic, tags: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticTag {
+    /// Unused or unnecessary code, which clients render faded out.
+    Unnecessary,
+}
+
+impl Serialize for DiagnosticTag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DiagnosticTag::Unnecessary => serializer.serialize_u8(1),
+        }
+    }
+}
+
+pub enum Profile {}
+
+impl Request for Profile {
+    type Params = ProfileParams;
+    type Result = Vec<String>;
+    const METHOD: &'static str = "rust-analyzer/profile";
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileParams {
+    /// The new filter of the profiler, like `RA_PROFILE`. An empty filter
+    /// disables profiling.
+    pub filter: Option<String>,
+}
+
 pub enum CollectGarbage {}
 
 impl Request for CollectGarbage {
@@ -28,6 +141,14 @@ impl Request for CollectGarbage {
     const METHOD: &'static str = "rust-analyzer/collectGarbage";
 }
 
+pub enum MemoryUsage {}
+
+impl Request for MemoryUsage {
+    type Params = ();
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/memoryUsage";
+}
+
 pub enum SyntaxTree {}
 
 impl Request for SyntaxTree {
@@ -64,6 +185,78 @@ pub struct ExtendSelectionResult {
     pub selections: Vec<Range>,
 }
 
+pub enum TypeOfSelection {}
+
+impl Request for TypeOfSelection {
+    type Params = TypeOfSelectionParams;
+    type Result = Option<TypeOfSelectionResult>;
+    const METHOD: &'static str = "rust-analyzer/typeOfSelection";
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeOfSelectionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+    /// Whether the paths to the items in the type are written in full.
+    #[serde(default)]
+    pub qualified: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeOfSelectionResult {
+    /// The range of the expression whose type this is.
+    pub range: Range,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+pub enum FindReferences {}
+
+impl Request for FindReferences {
+    type Params = FindReferencesParams;
+    type Result = Option<Vec<Location>>;
+    const METHOD: &'static str = "rust-analyzer/findReferences";
+}
+
+/// The parameters of `textDocument/references`, with filters of the
+/// references.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FindReferencesParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+    #[serde(default)]
+    pub include_declaration: bool,
+    #[serde(default)]
+    pub scope: SearchScope,
+    /// Only the references which read, or which write, the definition.
+    #[serde(default)]
+    pub access: Option<ReferenceAccess>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchScope {
+    Workspace,
+    CurrentModule,
+    Tests,
+}
+
+impl Default for SearchScope {
+    fn default() -> SearchScope {
+        SearchScope::Workspace
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ReferenceAccess {
+    Read,
+    Write,
+}
+
 pub enum FindMatchingBrace {}
 
 impl Request for FindMatchingBrace {
@@ -107,6 +300,10 @@ pub struct PublishDecorationsParams {
 pub struct Decoration {
     pub range: Range,
     pub tag: &'static str,
+    /// The hash of the local binding of the range, as a string because it
+    /// doesn't fit in a JavaScript number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binding_hash: Option<String>,
 }
 
 pub enum ParentModule {}
@@ -117,6 +314,20 @@ impl Request for ParentModule {
     const METHOD: &'static str = "rust-analyzer/parentModule";
 }
 
+pub enum OpenCargoToml {}
+
+impl Request for OpenCargoToml {
+    type Params = OpenCargoTomlParams;
+    type Result = Option<Location>;
+    const METHOD: &'static str = "rust-analyzer/openCargoToml";
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenCargoTomlParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
 pub enum JoinLines {}
 
 impl Request for JoinLines {
@@ -132,6 +343,62 @@ pub struct JoinLinesParams {
     pub range: Range,
 }
 
+pub enum Ssr {}
+
+impl Request for Ssr {
+    type Params = SsrParams;
+    type Result = SourceChange;
+    const METHOD: &'static str = "rust-analyzer/ssr";
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SsrParams {
+    pub query: String,
+}
+
+pub enum MoveItem {}
+
+impl Request for MoveItem {
+    type Params = MoveItemParams;
+    type Result = Option<SourceChange>;
+    const METHOD: &'static str = "rust-analyzer/moveItem";
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveItemParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+    pub direction: MoveItemDirection,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum MoveItemDirection {
+    Up,
+    Down,
+}
+
+pub enum ResolveAssist {}
+
+impl Request for ResolveAssist {
+    type Params = ResolveAssistParams;
+    type Result = Option<SourceChange>;
+    const METHOD: &'static str = "rust-analyzer/resolveAssist";
+}
+
+/// Identifies an assist listed by a code action, whose edit is only computed
+/// once it's picked by the user.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveAssistParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+    pub id: String,
+    pub label: String,
+}
+
 pub enum OnEnter {}
 
 impl Request for OnEnter {
@@ -155,7 +422,7 @@ pub struct RunnablesParams {
     pub position: Option<Position>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Runnable {
     pub range: Range,
@@ -166,6 +433,24 @@ pub struct Runnable {
     pub cwd: Option<String>,
 }
 
+/// Builds the program of a runnable, to launch it in a debugger.
+pub enum DebugSingle {}
+
+impl Request for DebugSingle {
+    type Params = Runnable;
+    type Result = DebugConfiguration;
+    const METHOD: &'static str = "rust-analyzer/debugSingle";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugConfiguration {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: FxHashMap<String, String>,
+    pub cwd: Option<String>,
+}
+
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SourceChange {
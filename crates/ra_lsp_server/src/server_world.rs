@@ -5,17 +5,22 @@ use std::{
 
 use lsp_types::Url;
 use ra_ide_api::{
-    Analysis, AnalysisChange, AnalysisHost, CrateGraph, FileId, LibraryData,
+    Analysis, AnalysisChange, AnalysisHost, CfgOptions, CrateGraph, FileId, LibraryData,
     SourceRootId
 };
 use ra_vfs::{Vfs, VfsChange, VfsFile, VfsRoot};
 use relative_path::RelativePathBuf;
+use rustc_hash::FxHashMap;
 use parking_lot::RwLock;
 use failure::{Error, format_err};
 use gen_lsp_server::ErrorCode;
 
 use crate::{
-    project_model::ProjectWorkspace,
+    config::Config,
+    conv::FileLineIndex,
+    detached_files::DetachedFiles,
+    flycheck::CheckDiagnostics,
+    project_model::{detached_file_crate_graph, get_rustc_cfg_options, ProjectRoot, ProjectWorkspace},
     vfs_filter::IncludeRustFiles,
     Result,
     LspError,
@@ -23,28 +28,49 @@ use crate::{
 
 #[derive(Debug)]
 pub struct ServerWorldState {
+    pub config: Arc<Config>,
     pub roots_to_scan: usize,
     pub roots: Vec<PathBuf>,
     pub workspaces: Arc<Vec<ProjectWorkspace>>,
     pub analysis_host: AnalysisHost,
     pub vfs: Arc<RwLock<Vfs>>,
+    pub check_diagnostics: Arc<CheckDiagnostics>,
+    /// The versions of the open documents, as sent by the client.
+    pub document_versions: Arc<FxHashMap<Url, u64>>,
+    pub detached_files: Arc<DetachedFiles>,
+    /// The crate graph of the workspaces, without the detached files.
+    crate_graph: CrateGraph,
+    cfg_options: CfgOptions,
 }
 
 pub struct ServerWorld {
+    pub config: Arc<Config>,
     pub workspaces: Arc<Vec<ProjectWorkspace>>,
     pub analysis: Analysis,
     pub vfs: Arc<RwLock<Vfs>>,
+    pub check_diagnostics: Arc<CheckDiagnostics>,
+    pub document_versions: Arc<FxHashMap<Url, u64>>,
+    pub detached_files: Arc<DetachedFiles>,
 }
 
 impl ServerWorldState {
-    pub fn new(folder_roots: Vec<PathBuf>, workspaces: Vec<ProjectWorkspace>) -> ServerWorldState {
+    pub fn new(
+        folder_roots: Vec<PathBuf>,
+        workspaces: Vec<ProjectWorkspace>,
+        config: Arc<Config>,
+    ) -> ServerWorldState {
         let mut change = AnalysisChange::new();
 
-        let mut roots = Vec::new();
-        roots.extend(folder_roots.iter().cloned().map(IncludeRustFiles::member));
-        for ws in workspaces.iter() {
-            roots.extend(IncludeRustFiles::from_roots(ws.to_roots()));
-        }
+        let project_roots = folder_roots
+            .iter()
+            .map(|it| ProjectRoot::new(it.clone(), true))
+            .chain(workspaces.iter().flat_map(ProjectWorkspace::to_roots));
+        let roots = IncludeRustFiles::from_roots(project_roots.filter_map(|root| {
+            folder_roots
+                .iter()
+                .try_fold(root, |root, folder| root.exclude(folder, &config.exclude_dirs))
+        }))
+        .collect();
 
         let (mut vfs, vfs_roots) = Vfs::new(roots);
         let roots_to_scan = vfs_roots.len();
@@ -56,23 +82,30 @@ impl ServerWorldState {
 
         // Create crate graph from all the workspaces
         let mut crate_graph = CrateGraph::default();
+        let default_cfg_options = get_rustc_cfg_options();
         let mut load = |path: &std::path::Path| {
             let vfs_file = vfs.load(path);
             vfs_file.map(|f| FileId(f.0.into()))
         };
         for ws in workspaces.iter() {
-            crate_graph.extend(ws.to_crate_graph(&mut load));
+            crate_graph.extend(ws.to_crate_graph(&default_cfg_options, &mut load));
         }
-        change.set_crate_graph(crate_graph);
+        change.set_crate_graph(crate_graph.clone());
 
         let mut analysis_host = AnalysisHost::default();
         analysis_host.apply_change(change);
         ServerWorldState {
+            config,
             roots_to_scan,
             roots: folder_roots,
             workspaces: Arc::new(workspaces),
             analysis_host,
             vfs: Arc::new(RwLock::new(vfs)),
+            check_diagnostics: Arc::new(CheckDiagnostics::default()),
+            document_versions: Arc::new(FxHashMap::default()),
+            detached_files: Arc::new(DetachedFiles::default()),
+            crate_graph,
+            cfg_options: default_cfg_options,
         }
     }
 
@@ -125,6 +158,63 @@ impl ServerWorldState {
         libs
     }
 
+    /// Adds a file which belongs to none of the workspaces, to analyze it as a
+    /// crate of its own.
+    pub fn add_detached_file(&mut self, path: PathBuf, text: String) -> FileId {
+        let rel_path = RelativePathBuf::from(
+            path.file_name().map(|it| it.to_string_lossy().into_owned()).unwrap_or_default(),
+        );
+        let (file_id, root) = Arc::make_mut(&mut self.detached_files).add(path);
+        let mut change = AnalysisChange::new();
+        change.add_root(root, true);
+        change.add_file(root, file_id, rel_path, Arc::new(text));
+        change.set_crate_graph(self.crate_graph_with_detached_files());
+        self.analysis_host.apply_change(change);
+        file_id
+    }
+
+    pub fn change_detached_file(&mut self, file_id: FileId, text: String) {
+        let mut change = AnalysisChange::new();
+        change.change_file(file_id, Arc::new(text));
+        self.analysis_host.apply_change(change);
+    }
+
+    pub fn remove_detached_file(&mut self, path: &Path) -> Option<FileId> {
+        let (file_id, root) = Arc::make_mut(&mut self.detached_files).remove(path)?;
+        let rel_path = RelativePathBuf::from(
+            path.file_name().map(|it| it.to_string_lossy().into_owned()).unwrap_or_default(),
+        );
+        let mut change = AnalysisChange::new();
+        change.remove_file(root, file_id, rel_path);
+        change.set_crate_graph(self.crate_graph_with_detached_files());
+        self.analysis_host.apply_change(change);
+        Some(file_id)
+    }
+
+    /// The crate graph of the workspaces, with a crate for each detached file
+    /// which uses the sysroot of the first workspace.
+    fn crate_graph_with_detached_files(&self) -> CrateGraph {
+        let mut crate_graph = self.crate_graph.clone();
+        let sysroot = self.workspaces.iter().find_map(|ws| ws.sysroot());
+        let mut vfs = self.vfs.write();
+        let mut load = |path: &Path| vfs.load(path).map(|f| FileId(f.0.into()));
+        for file_id in self.detached_files.file_ids() {
+            crate_graph.extend(detached_file_crate_graph(
+                file_id,
+                sysroot,
+                &self.cfg_options,
+                &mut load,
+            ));
+        }
+        crate_graph
+    }
+
+    /// The path of `file_id`, which is either a file of the vfs or a detached
+    /// file.
+    pub fn file_id_to_path(&self, file_id: FileId) -> PathBuf {
+        file_id_to_path(&self.vfs, &self.detached_files, file_id)
+    }
+
     pub fn add_lib(&mut self, data: LibraryData) {
         self.roots_to_scan -= 1;
         let mut change = AnalysisChange::new();
@@ -134,9 +224,13 @@ impl ServerWorldState {
 
     pub fn snapshot(&self) -> ServerWorld {
         ServerWorld {
+            config: Arc::clone(&self.config),
             workspaces: Arc::clone(&self.workspaces),
             analysis: self.analysis_host.analysis(),
             vfs: Arc::clone(&self.vfs),
+            check_diagnostics: Arc::clone(&self.check_diagnostics),
+            document_versions: Arc::clone(&self.document_versions),
+            detached_files: Arc::clone(&self.detached_files),
         }
     }
 
@@ -147,6 +241,16 @@ impl ServerWorldState {
     pub fn collect_garbage(&mut self) {
         self.analysis_host.collect_garbage()
     }
+
+    /// The memory retained by each query, as a table. The values of the
+    /// queries are discarded to measure it.
+    pub fn memory_usage(&mut self) -> String {
+        let mut res = String::new();
+        for (name, bytes) in self.analysis_host.per_query_memory_usage() {
+            res += &format!("{} {}\n", bytes, name);
+        }
+        res
+    }
 }
 
 impl ServerWorld {
@@ -154,8 +258,20 @@ impl ServerWorld {
         &self.analysis
     }
 
+    /// The line index of `file_id`, to convert the positions exchanged with
+    /// the client.
+    pub fn line_index(&self, file_id: FileId) -> FileLineIndex {
+        FileLineIndex {
+            index: self.analysis.file_line_index(file_id),
+            encoding: self.config.offset_encoding,
+        }
+    }
+
     pub fn uri_to_file_id(&self, uri: &Url) -> Result<FileId> {
         let path = uri.to_file_path().map_err(|()| format_err!("invalid uri: {}", uri))?;
+        if let Some(file_id) = self.detached_files.file_id(&path) {
+            return Ok(file_id);
+        }
         let file = self.vfs.read().path2file(&path).ok_or_else(|| {
             // Show warning as this file is outside current workspace
             Error::from(LspError {
@@ -166,15 +282,22 @@ impl ServerWorld {
         Ok(FileId(file.0.into()))
     }
 
+    pub fn file_id_to_path(&self, id: FileId) -> PathBuf {
+        file_id_to_path(&self.vfs, &self.detached_files, id)
+    }
+
     pub fn file_id_to_uri(&self, id: FileId) -> Result<Url> {
-        let path = self.vfs.read().file2path(VfsFile(id.0.into()));
+        let path = self.file_id_to_path(id);
         let url = Url::from_file_path(&path)
             .map_err(|_| format_err!("can't convert path to url: {}", path.display()))?;
         Ok(url)
     }
 
     pub fn path_to_uri(&self, root: SourceRootId, path: &RelativePathBuf) -> Result<Url> {
-        let base = self.vfs.read().root2path(VfsRoot(root.0.into()));
+        let base = match self.detached_files.root_path(root) {
+            Some(it) => it.to_path_buf(),
+            None => self.vfs.read().root2path(VfsRoot(root.0.into())),
+        };
         let path = path.to_path(base);
         let url = Url::from_file_path(&path)
             .map_err(|_| format_err!("can't convert path to url: {}", path.display()))?;
@@ -197,7 +320,19 @@ impl ServerWorld {
     }
 
     pub fn workspace_root_for(&self, file_id: FileId) -> Option<&Path> {
-        let path = self.vfs.read().file2path(VfsFile(file_id.0.into()));
+        let path = self.file_id_to_path(file_id);
         self.workspaces.iter().find_map(|ws| ws.workspace_root_for(&path))
     }
+
+    pub fn manifest_for(&self, file_id: FileId) -> Option<&Path> {
+        let path = self.file_id_to_path(file_id);
+        self.workspaces.iter().find_map(|ws| ws.manifest_for(&path))
+    }
+}
+
+fn file_id_to_path(vfs: &RwLock<Vfs>, detached_files: &DetachedFiles, id: FileId) -> PathBuf {
+    match detached_files.path(id) {
+        Some(path) => path.to_path_buf(),
+        None => vfs.read().file2path(VfsFile(id.0.into())),
+    }
 }
@@ -25,6 +25,7 @@ impl IncludeRustFiles {
         IncludeRustFiles::from_root(ProjectRoot::new(path, false))
     }
 
+    #[allow(unused)]
     pub fn member(path: PathBuf) -> RootEntry {
         IncludeRustFiles::from_root(ProjectRoot::new(path, true))
     }
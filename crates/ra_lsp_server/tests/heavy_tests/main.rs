@@ -93,6 +93,51 @@ fn foo() {
     );
 }
 
+#[test]
+fn test_runnables_detached_file() {
+    let server = Project::with_fixture(
+        r"
+//- workspace/lib.rs
+fn bar() {}
+
+//- scratch.rs
+#[test]
+fn foo() {
+}
+",
+    )
+    .root("workspace")
+    .server();
+    server.wait_until_workspace_is_loaded();
+    server.request::<Runnables>(
+        RunnablesParams { text_document: server.doc_id("scratch.rs"), position: None },
+        json!([
+          {
+            "args": [ "test", "--", "foo", "--nocapture" ],
+            "bin": "cargo",
+            "env": { "RUST_BACKTRACE": "short" },
+            "cwd": null,
+            "label": "test foo",
+            "range": {
+              "end": { "character": 1, "line": 2 },
+              "start": { "character": 0, "line": 0 }
+            }
+          },
+          {
+            "args": [ "check", "--all" ],
+            "bin": "cargo",
+            "env": {},
+            "cwd": null,
+            "label": "cargo check --all",
+            "range": {
+              "end": { "character": 0, "line": 0 },
+              "start": { "character": 0, "line": 0 }
+            }
+          }
+        ]),
+    );
+}
+
 #[test]
 fn test_runnables_project() {
     let code = r#"
@@ -23,7 +23,7 @@ use test_utils::{parse_fixture, find_mismatch};
 
 use ra_lsp_server::{
     main_loop, req,
-    InitializationOptions,
+    Config,
 };
 
 pub struct Project<'a> {
@@ -57,7 +57,7 @@ impl<'a> Project<'a> {
         let mut paths = vec![];
 
         for entry in parse_fixture(self.fixture) {
-            let path = tmp_dir.path().join(entry.meta);
+            let path = tmp_dir.path().join(entry.path());
             fs::create_dir_all(path.parent().unwrap()).unwrap();
             fs::write(path.as_path(), entry.text.as_bytes()).unwrap();
             paths.push((path, entry.text));
@@ -90,13 +90,7 @@ impl Server {
             "test server",
             128,
             move |mut msg_receiver, mut msg_sender| {
-                main_loop(
-                    roots,
-                    InitializationOptions::default(),
-                    &mut msg_receiver,
-                    &mut msg_sender,
-                )
-                .unwrap()
+                main_loop(roots, Config::default(), &mut msg_receiver, &mut msg_sender).unwrap()
             },
         );
         let res = Server {
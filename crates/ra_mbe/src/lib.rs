@@ -37,9 +37,14 @@ pub enum ExpandError {
     NoMatchingRule,
     UnexpectedToken,
     BindingError(String),
+    ConversionError,
 }
 
-pub use crate::syntax_bridge::{ast_to_token_tree, token_tree_to_ast_item_list, syntax_node_to_token_tree};
+pub use crate::syntax_bridge::{
+    ast_to_token_tree, token_tree_to_ast_item_list, syntax_node_to_token_tree,
+    token_tree_to_source_file, token_tree_to_expr, token_tree_to_ty, token_tree_to_pat, TokenMap,
+    RevTokenMap,
+};
 
 /// This struct contains AST for a single `macro_rules` definition. What might
 /// be very confusing is that AST has almost exactly the same shape as
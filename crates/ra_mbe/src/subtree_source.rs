@@ -66,6 +66,8 @@ struct TtToken {
     pub is_joint_to_next: bool,
     pub text: SmolStr,
     pub n_tokens: usize,
+    /// The identity of the source token, for identifiers.
+    pub id: Option<tt::TokenId>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -212,6 +214,7 @@ impl<'a> SubTreeWalker<'a> {
 
 pub(crate) trait Querier {
     fn token(&self, uidx: usize) -> (SyntaxKind, SmolStr);
+    fn token_id(&self, uidx: usize) -> Option<tt::TokenId>;
 }
 
 // A wrapper class for ref cell
@@ -279,6 +282,10 @@ impl<'a> Querier for WalkerOwner<'a> {
         let tkn = self.get(uidx).unwrap();
         (tkn.kind, tkn.text)
     }
+
+    fn token_id(&self, uidx: usize) -> Option<tt::TokenId> {
+        self.get(uidx).and_then(|tkn| tkn.id)
+    }
 }
 
 pub(crate) struct SubtreeTokenSource<'a> {
@@ -435,7 +442,7 @@ fn convert_delim(d: tt::Delimiter, closing: bool) -> TtToken {
     let idx = closing as usize;
     let kind = kinds[idx];
     let text = if texts.len() > 0 { &texts[idx..texts.len() - (1 - idx)] } else { "" };
-    TtToken { kind, is_joint_to_next: false, text: SmolStr::new(text), n_tokens: 1 }
+    TtToken { kind, is_joint_to_next: false, text: SmolStr::new(text), n_tokens: 1, id: None }
 }
 
 fn convert_literal(l: &tt::Literal) -> TtToken {
@@ -444,12 +451,18 @@ fn convert_literal(l: &tt::Literal) -> TtToken {
         is_joint_to_next: false,
         text: l.text.clone(),
         n_tokens: 1,
+        id: None,
     }
 }
 
 fn convert_ident(ident: &tt::Ident) -> TtToken {
-    let kind = SyntaxKind::from_keyword(ident.text.as_str()).unwrap_or(IDENT);
-    TtToken { kind, is_joint_to_next: false, text: ident.text.clone(), n_tokens: 1 }
+    let kind = if ident.text.starts_with('\'') {
+        LIFETIME
+    } else {
+        SyntaxKind::from_keyword(ident.text.as_str()).unwrap_or(IDENT)
+    };
+    let id = Some(ident.id).filter(|&id| id != tt::TokenId::unspecified());
+    TtToken { kind, is_joint_to_next: false, text: ident.text.clone(), n_tokens: 1, id }
 }
 
 fn convert_punct(p: &tt::Punct, next_tokens: &[tt::TokenTree]) -> TtToken {
@@ -458,7 +471,7 @@ fn convert_punct(p: &tt::Punct, next_tokens: &[tt::TokenTree]) -> TtToken {
     let mut peek = TokenPeek::new(iter);
 
     if let Some((kind, is_joint_to_next, text, size)) = convert_multi_char_punct(p, &mut peek) {
-        TtToken { kind, is_joint_to_next, text: text.into(), n_tokens: size }
+        TtToken { kind, is_joint_to_next, text: text.into(), n_tokens: size, id: None }
     } else {
         let kind = match p.char {
             // lexer may produce combpund tokens for these ones
@@ -474,7 +487,13 @@ fn convert_punct(p: &tt::Punct, next_tokens: &[tt::TokenTree]) -> TtToken {
             let s: &str = p.char.encode_utf8(&mut buf);
             SmolStr::new(s)
         };
-        TtToken { kind, is_joint_to_next: p.spacing == tt::Spacing::Joint, text, n_tokens: 1 }
+        TtToken {
+            kind,
+            is_joint_to_next: p.spacing == tt::Spacing::Joint,
+            text,
+            n_tokens: 1,
+            id: None,
+        }
     }
 }
 
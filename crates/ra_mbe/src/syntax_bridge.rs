@@ -1,10 +1,13 @@
-use ra_parser::{TreeSink, ParseError};
+use ra_parser::{TokenSource, TreeSink, ParseError};
 use ra_syntax::{
     AstNode, SyntaxNode, TextRange, SyntaxKind, SmolStr, SyntaxTreeBuilder, TreeArc, SyntaxElement,
     ast, SyntaxKind::*, TextUnit
 };
 
-use crate::subtree_source::{SubtreeTokenSource, Querier};
+use crate::{
+    ExpandError,
+    subtree_source::{SubtreeTokenSource, Querier},
+};
 
 /// Maps `tt::TokenId` to the relative range of the original token.
 #[derive(Default)]
@@ -13,6 +16,17 @@ pub struct TokenMap {
     tokens: Vec<TextRange>,
 }
 
+/// Maps the ranges of the tokens of a syntax tree built from a token tree
+/// (the result of a macro expansion) back to the `tt::TokenId`s of the
+/// original tokens, which can be looked up in a `TokenMap`.
+///
+/// Note that tokens coming from a macro definition carry ids of the
+/// definition's `TokenMap`, not of the macro call's one.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RevTokenMap {
+    ranges: Vec<(TextRange, tt::TokenId)>,
+}
+
 /// Convert the syntax tree (what user has written) to a `TokenTree` (what macro
 /// will consume).
 pub fn ast_to_token_tree(ast: &ast::TokenTree) -> Option<(tt::Subtree, TokenMap)> {
@@ -32,11 +46,53 @@ pub fn syntax_node_to_token_tree(node: &SyntaxNode) -> Option<(tt::Subtree, Toke
 
 /// Parses the token tree (result of macro expansion) as a sequence of items
 pub fn token_tree_to_ast_item_list(tt: &tt::Subtree) -> TreeArc<ast::SourceFile> {
+    let (syntax, _) = token_tree_to_syntax_node(tt, ra_parser::parse);
+    ast::SourceFile::cast(&syntax).unwrap().to_owned()
+}
+
+/// Parses the token tree (result of macro expansion) as a sequence of items,
+/// keeping track of the original tokens.
+pub fn token_tree_to_source_file(tt: &tt::Subtree) -> (TreeArc<ast::SourceFile>, RevTokenMap) {
+    let (syntax, rev_token_map) = token_tree_to_syntax_node(tt, ra_parser::parse);
+    (ast::SourceFile::cast(&syntax).unwrap().to_owned(), rev_token_map)
+}
+
+/// Parses the token tree (result of macro expansion) as an expression.
+pub fn token_tree_to_expr(
+    tt: &tt::Subtree,
+) -> Result<(TreeArc<ast::Expr>, RevTokenMap), ExpandError> {
+    let (syntax, rev_token_map) = token_tree_to_syntax_node(tt, ra_parser::parse_expr);
+    let expr = ast::Expr::cast(&syntax).ok_or(ExpandError::ConversionError)?;
+    Ok((expr.to_owned(), rev_token_map))
+}
+
+/// Parses the token tree (result of macro expansion) as a type.
+pub fn token_tree_to_ty(
+    tt: &tt::Subtree,
+) -> Result<(TreeArc<ast::TypeRef>, RevTokenMap), ExpandError> {
+    let (syntax, rev_token_map) = token_tree_to_syntax_node(tt, ra_parser::parse_ty);
+    let ty = ast::TypeRef::cast(&syntax).ok_or(ExpandError::ConversionError)?;
+    Ok((ty.to_owned(), rev_token_map))
+}
+
+/// Parses the token tree (result of macro expansion) as a pattern.
+pub fn token_tree_to_pat(
+    tt: &tt::Subtree,
+) -> Result<(TreeArc<ast::Pat>, RevTokenMap), ExpandError> {
+    let (syntax, rev_token_map) = token_tree_to_syntax_node(tt, ra_parser::parse_pat);
+    let pat = ast::Pat::cast(&syntax).ok_or(ExpandError::ConversionError)?;
+    Ok((pat.to_owned(), rev_token_map))
+}
+
+fn token_tree_to_syntax_node<F>(tt: &tt::Subtree, f: F) -> (TreeArc<SyntaxNode>, RevTokenMap)
+where
+    F: FnOnce(&dyn TokenSource, &mut dyn TreeSink),
+{
     let token_source = SubtreeTokenSource::new(tt);
     let mut tree_sink = TtTreeSink::new(token_source.querier());
-    ra_parser::parse(&token_source, &mut tree_sink);
-    let syntax = tree_sink.inner.finish();
-    ast::SourceFile::cast(&syntax).unwrap().to_owned()
+    f(&token_source, &mut tree_sink);
+    let rev_token_map = RevTokenMap { ranges: tree_sink.ranges };
+    (tree_sink.inner.finish(), rev_token_map)
 }
 
 impl TokenMap {
@@ -45,6 +101,12 @@ impl TokenMap {
         self.tokens.get(idx).map(|&it| it)
     }
 
+    /// The id of the token at the *relative* `range`.
+    pub fn token_id_of(&self, relative_range: TextRange) -> Option<tt::TokenId> {
+        let idx = self.tokens.iter().position(|&it| it == relative_range)?;
+        Some(tt::TokenId(idx as u32))
+    }
+
     fn alloc(&mut self, relative_range: TextRange) -> tt::TokenId {
         let id = self.tokens.len();
         self.tokens.push(relative_range);
@@ -52,6 +114,19 @@ impl TokenMap {
     }
 }
 
+impl RevTokenMap {
+    /// The original token of the token at `range` in the syntax tree.
+    pub fn token_id_of(&self, range: TextRange) -> Option<tt::TokenId> {
+        self.ranges.iter().find(|(it, _)| *it == range).map(|&(_, id)| id)
+    }
+
+    /// The ranges in the syntax tree of the tokens which were produced from
+    /// the original token `id`. A macro may use a token more than once.
+    pub fn ranges_of(&self, id: tt::TokenId) -> impl Iterator<Item = TextRange> + '_ {
+        self.ranges.iter().filter(move |&&(_, it)| it == id).map(|&(range, _)| range)
+    }
+}
+
 fn convert_tt(
     token_map: &mut TokenMap,
     global_offset: TextUnit,
@@ -91,7 +166,10 @@ fn convert_tt(
                         );
                     }
                 } else {
-                    let child = if token.kind().is_keyword() || token.kind() == IDENT {
+                    let child = if token.kind().is_keyword()
+                        || token.kind() == IDENT
+                        || token.kind() == LIFETIME
+                    {
                         let relative_range = token.range() - global_offset;
                         let id = token_map.alloc(relative_range);
                         let text = token.text().clone();
@@ -105,8 +183,14 @@ fn convert_tt(
                 }
             }
             SyntaxElement::Node(node) => {
-                let child = convert_tt(token_map, global_offset, node)?.into();
-                token_trees.push(child);
+                let child = convert_tt(token_map, global_offset, node)?;
+                // Only token trees have delimiters, other nodes are flattened
+                // into their parent.
+                if child.delimiter == tt::Delimiter::None {
+                    token_trees.extend(child.token_trees);
+                } else {
+                    token_trees.push(child.into());
+                }
             }
         };
     }
@@ -121,6 +205,7 @@ struct TtTreeSink<'a, Q: Querier> {
     text_pos: TextUnit,
     token_pos: usize,
     inner: SyntaxTreeBuilder,
+    ranges: Vec<(TextRange, tt::TokenId)>,
 }
 
 impl<'a, Q: Querier> TtTreeSink<'a, Q> {
@@ -131,6 +216,7 @@ impl<'a, Q: Querier> TtTreeSink<'a, Q> {
             text_pos: 0.into(),
             token_pos: 0,
             inner: SyntaxTreeBuilder::default(),
+            ranges: Vec::new(),
         }
     }
 }
@@ -142,6 +228,12 @@ impl<'a, Q: Querier> TreeSink for TtTreeSink<'a, Q> {
             return;
         }
 
+        if n_tokens == 1 {
+            if let Some(id) = self.src_querier.token_id(self.token_pos) {
+                let len = TextUnit::of_str(&self.src_querier.token(self.token_pos).1);
+                self.ranges.push((TextRange::offset_len(self.text_pos, len), id));
+            }
+        }
         for _ in 0..n_tokens {
             self.buf += &self.src_querier.token(self.token_pos).1;
             self.token_pos += 1;
@@ -168,6 +260,8 @@ impl<'a, Q: Querier> TreeSink for TtTreeSink<'a, Q> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ra_syntax::ast::TypeAscriptionOwner;
+
     use crate::tests::{expand, create_rules};
 
     #[test]
@@ -206,4 +300,68 @@ mod tests {
         assert_eq!(query.token(2 + 15 + 3).1, "\"rust1\"");
         assert_eq!(query.token(2 + 15 + 3).0, STRING);
     }
+
+    #[test]
+    fn maps_expanded_tokens_back_to_the_macro_input() {
+        let rules = create_rules(
+            r#"
+            macro_rules! getter {
+                ($i:ident) => { fn $i<'a>(x: &'a u32) {} }
+            }
+            "#,
+        );
+        let source_file = ast::SourceFile::parse("getter!(foo);");
+        let macro_call = source_file.syntax().descendants().find_map(ast::MacroCall::cast).unwrap();
+        let token_tree = macro_call.token_tree().unwrap();
+        let (input, token_map) = ast_to_token_tree(token_tree).unwrap();
+        let expansion = rules.expand(&input).unwrap();
+
+        let (expanded, rev_token_map) = token_tree_to_source_file(&expansion);
+        assert_eq!(expanded.syntax().text().to_string(), "fnfoo<'a>(x:&'au32){}");
+        let name = expanded.syntax().descendants().find_map(ast::Name::cast).unwrap();
+        let id = rev_token_map.token_id_of(name.syntax().range()).unwrap();
+        let range = token_map.relative_range_of(id).unwrap() + token_tree.syntax().range().start();
+        assert_eq!(&source_file.syntax().text().to_string()[range], "foo");
+        assert_eq!(rev_token_map.ranges_of(id).count(), 1);
+    }
+
+    #[test]
+    fn preserves_spans_of_converted_syntax() {
+        let text = "fn foo<'a>(x: &'a u32) -> Option<[u8; 2]> { bar(x) }";
+        let source_file = ast::SourceFile::parse(text);
+        let (tt, token_map) = syntax_node_to_token_tree(source_file.syntax()).unwrap();
+        assert_eq!(tt.token_trees.len(), 13);
+
+        let (file, rev_token_map) = token_tree_to_source_file(&tt);
+        assert_eq!(file.syntax().text().to_string(), "fnfoo<'a>(x:&'au32)->Option<[u8;2]>{bar(x)}");
+        let tokens = file
+            .syntax()
+            .descendants_with_tokens()
+            .filter_map(|it| it.as_token())
+            .filter(|it| it.kind() == IDENT || it.kind() == LIFETIME || it.kind().is_keyword());
+        for token in tokens {
+            let id = rev_token_map.token_id_of(token.range()).unwrap();
+            let range = token_map.relative_range_of(id).unwrap();
+            assert_eq!(&text[range], token.text().as_str());
+        }
+    }
+
+    #[test]
+    fn converts_token_trees_to_fragments() {
+        let source_file = ast::SourceFile::parse("const A: Vec<u32> = (1 + 2) * 3;");
+        let const_def = source_file.syntax().descendants().find_map(ast::ConstDef::cast).unwrap();
+
+        let (tt, _) = syntax_node_to_token_tree(const_def.body().unwrap().syntax()).unwrap();
+        let (expr, _) = token_tree_to_expr(&tt).unwrap();
+        assert_eq!(expr.syntax().kind(), BIN_EXPR);
+        assert_eq!(expr.syntax().text().to_string(), "(1+2)*3");
+
+        let (tt, _) =
+            syntax_node_to_token_tree(const_def.ascribed_type().unwrap().syntax()).unwrap();
+        let (ty, _) = token_tree_to_ty(&tt).unwrap();
+        assert_eq!(ty.syntax().kind(), PATH_TYPE);
+
+        let empty = tt::Subtree { delimiter: tt::Delimiter::None, token_trees: Vec::new() };
+        assert_eq!(token_tree_to_expr(&empty).map(|_| ()), Err(ExpandError::ConversionError));
+    }
 }
@@ -52,6 +52,7 @@ pub(super) const ATOM_EXPR_FIRST: TokenSet =
         CONTINUE_KW,
         LIFETIME,
         ASYNC_KW,
+        TRY_KW,
     ]);
 
 const EXPR_RECOVERY_SET: TokenSet = token_set![LET_KW];
@@ -101,6 +102,15 @@ pub(super) fn atom_expr(p: &mut Parser, r: Restrictions) -> Option<(CompletedMar
             block_expr(p, Some(m))
         }
         MATCH_KW => match_expr(p),
+        // test try_block_expr
+        // fn foo() {
+        //     let _ = try {};
+        // }
+        TRY_KW if la == L_CURLY => {
+            let m = p.start();
+            p.bump();
+            block_expr(p, Some(m))
+        }
         UNSAFE_KW if la == L_CURLY => {
             let m = p.start();
             p.bump();
@@ -27,6 +27,7 @@ fn type_with_bounds_cond(p: &mut Parser, allow_bounds: bool) {
         FOR_KW => for_type(p),
         IMPL_KW => impl_trait_type(p),
         DYN_KW => dyn_trait_type(p),
+        IDENT if is_contextual_dyn(p) => dyn_trait_type(p),
         // Some path types are not allowed to have bounds (no plus)
         L_ANGLE => path_type_(p, allow_bounds),
         _ if paths::is_path_start(p) => path_or_macro_type_(p, allow_bounds),
@@ -227,12 +228,18 @@ fn impl_trait_type(p: &mut Parser) {
     m.complete(p, IMPL_TRAIT_TYPE);
 }
 
+/// Whether the parser is at `dyn` used as a keyword, in the 2015 edition where
+/// it is lexed as an identifier. It is a path if followed by `::` or nothing.
+fn is_contextual_dyn(p: &Parser) -> bool {
+    p.at_contextual_kw("dyn") && token_set![IDENT, LIFETIME, QUESTION, FOR_KW].contains(p.nth(1))
+}
+
 // test dyn_trait_type
 // type A = dyn Iterator<Item=Foo<'a>> + 'a;
 fn dyn_trait_type(p: &mut Parser) {
-    assert!(p.at(DYN_KW));
+    assert!(p.at(DYN_KW) || p.at_contextual_kw("dyn"));
     let m = p.start();
-    p.bump();
+    p.bump_remap(DYN_KW);
     type_params::bounds_without_colon(p);
     m.complete(p, DYN_TRAIT_TYPE);
 }
@@ -103,6 +103,7 @@ pub enum SyntaxKind {
     LET_KW,
     MOVE_KW,
     RETURN_KW,
+    TRY_KW,
     AUTO_KW,
     DEFAULT_KW,
     UNION_KW,
@@ -291,6 +292,7 @@ impl SyntaxKind {
             | LET_KW
             | MOVE_KW
             | RETURN_KW
+            | TRY_KW
             | AUTO_KW
             | DEFAULT_KW
             | UNION_KW
@@ -462,6 +464,7 @@ impl SyntaxKind {
             LET_KW => &SyntaxInfo { name: "LET_KW" },
             MOVE_KW => &SyntaxInfo { name: "MOVE_KW" },
             RETURN_KW => &SyntaxInfo { name: "RETURN_KW" },
+            TRY_KW => &SyntaxInfo { name: "TRY_KW" },
             AUTO_KW => &SyntaxInfo { name: "AUTO_KW" },
             DEFAULT_KW => &SyntaxInfo { name: "DEFAULT_KW" },
             UNION_KW => &SyntaxInfo { name: "UNION_KW" },
@@ -635,6 +638,7 @@ impl SyntaxKind {
             "let" => LET_KW,
             "move" => MOVE_KW,
             "return" => RETURN_KW,
+            "try" => TRY_KW,
             _ => return None,
         };
         Some(kw)
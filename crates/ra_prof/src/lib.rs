@@ -3,12 +3,16 @@ use std::time::{Duration, Instant};
 use std::mem;
 use std::io::{stderr, Write};
 use std::iter::repeat;
-use std::collections::{HashSet};
+use std::collections::{HashSet, VecDeque};
 use std::default::Default;
 use std::iter::FromIterator;
-use std::sync::{RwLock, atomic::{AtomicBool, Ordering}};
+use std::sync::{Mutex, RwLock, atomic::{AtomicBool, Ordering}};
 use lazy_static::lazy_static;
 
+mod memory_usage;
+
+pub use crate::memory_usage::{allocated_bytes, Bytes, CountingAlloc};
+
 /// Set profiling filter. It specifies descriptions allowed to profile.
 /// This is helpful when call stack has too many nested profiling scopes.
 /// Additionally filter can specify maximum depth of profiling scopes nesting.
@@ -91,6 +95,13 @@ pub fn profile(desc: &str) -> Profiler {
     })
 }
 
+/// Takes the traces printed since the last call, up to the last
+/// `MAX_TRACES`, so that they can be dumped on request rather than only to
+/// the stderr.
+pub fn take_traces() -> Vec<String> {
+    TRACES.lock().unwrap().drain(..).collect()
+}
+
 pub struct Profiler {
     desc: Option<String>,
 }
@@ -164,8 +175,11 @@ struct FilterData {
 
 static PROFILING_ENABLED: AtomicBool = AtomicBool::new(false);
 
+const MAX_TRACES: usize = 100;
+
 lazy_static! {
     static ref FILTER: RwLock<FilterData> = RwLock::new(Default::default());
+    static ref TRACES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
 }
 
 thread_local!(static PROFILE_STACK: RefCell<ProfileStack> = RefCell::new(ProfileStack::new()));
@@ -182,9 +196,15 @@ impl Drop for Profiler {
                     let message = mem::replace(desc, String::new());
                     stack.messages.push(Message { level, duration, message });
                     if level == 0 {
-                        let stdout = stderr();
                         if duration >= stack.filter_data.longer_than {
-                            print(0, &stack.messages, &mut stdout.lock());
+                            let mut trace = Vec::new();
+                            print(0, &stack.messages, &mut trace);
+                            let _ = stderr().write_all(&trace);
+                            let mut traces = TRACES.lock().unwrap();
+                            if traces.len() == MAX_TRACES {
+                                traces.pop_front();
+                            }
+                            traces.push_back(String::from_utf8(trace).unwrap());
                         }
                         stack.messages.clear();
                     }
@@ -222,6 +242,16 @@ mod tests {
         profiling_function1();
     }
 
+    #[test]
+    fn test_take_traces() {
+        let s = vec!["profile1".to_string(), "profile2".to_string()];
+        let f = Filter::new(2, s, Duration::new(0, 0));
+        set_filter(f);
+        profiling_function1();
+        let traces = take_traces();
+        assert!(traces.iter().any(|it| it.contains("- profile1") && it.contains("- profile2")));
+    }
+
     fn profiling_function1() {
         let _p = profile("profile1");
         profiling_function2();
@@ -0,0 +1,81 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting the bytes currently allocated.
+///
+/// Binaries which don't use jemalloc install it to get heap usage statistics:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOC: ra_prof::CountingAlloc = ra_prof::CountingAlloc;
+/// ```
+pub struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+/// The bytes currently allocated with `CountingAlloc`, which are always zero
+/// if it is not the global allocator.
+pub fn allocated_bytes() -> Bytes {
+    Bytes(ALLOCATED.load(Ordering::Relaxed))
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bytes(pub usize);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = self.0;
+        if bytes < 4096 {
+            return write!(f, "{} bytes", bytes);
+        }
+        let kb = bytes / 1024;
+        if kb < 4096 {
+            return write!(f, "{}kb", kb);
+        }
+        let mb = kb / 1024;
+        write!(f, "{}mb", mb)
+    }
+}
+
+impl std::ops::AddAssign<usize> for Bytes {
+    fn add_assign(&mut self, x: usize) {
+        self.0 += x;
+    }
+}
+
+impl std::ops::Sub for Bytes {
+    type Output = Bytes;
+
+    /// The difference of two measures, saturating at zero as allocations
+    /// of other threads can make it negative.
+    fn sub(self, rhs: Bytes) -> Bytes {
+        Bytes(self.0.saturating_sub(rhs.0))
+    }
+}
@@ -1,10 +1,15 @@
-use std::path::{Path, PathBuf};
+use std::{
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
 
-use cargo_metadata::{MetadataCommand, CargoOpt};
+use cargo_metadata::{MetadataCommand, DependencyKind, PackageId};
 use ra_arena::{Arena, RawId, impl_arena_id};
 use rustc_hash::FxHashMap;
-use failure::format_err;
+use failure::{bail, format_err};
 use ra_db::Edition;
+use serde::Deserialize;
 
 use crate::Result;
 
@@ -22,6 +27,54 @@ pub struct CargoWorkspace {
     pub(crate) workspace_root: PathBuf,
 }
 
+/// Options for loading a Cargo workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoConfig {
+    /// Whether to run `cargo check` to get the `OUT_DIR`s and cfg flags set
+    /// by the build scripts and the dylibs of the proc macros.
+    pub load_out_dirs_from_check: bool,
+    /// Whether `cfg(test)` is enabled in the crates of the workspace members,
+    /// which it never is in the crates of their dependencies.
+    pub cfg_test: bool,
+    /// Whether all the features of the workspace members are enabled, in
+    /// which case `no_default_features` and `features` are ignored.
+    pub all_features: bool,
+    pub no_default_features: bool,
+    /// The features of the workspace members to enable, like with
+    /// `cargo build --features`.
+    pub features: Vec<String>,
+}
+
+impl Default for CargoConfig {
+    fn default() -> CargoConfig {
+        CargoConfig {
+            load_out_dirs_from_check: false,
+            cfg_test: true,
+            all_features: true,
+            no_default_features: false,
+            features: Vec::new(),
+        }
+    }
+}
+
+impl CargoConfig {
+    /// The arguments of `cargo` which select the features.
+    fn feature_flags(&self) -> Vec<String> {
+        if self.all_features {
+            return vec!["--all-features".to_string()];
+        }
+        let mut res = Vec::new();
+        if self.no_default_features {
+            res.push("--no-default-features".to_string());
+        }
+        if !self.features.is_empty() {
+            res.push("--features".to_string());
+            res.push(self.features.join(" "));
+        }
+        res
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Package(RawId);
 impl_arena_id!(Package);
@@ -33,17 +86,42 @@ impl_arena_id!(Target);
 #[derive(Debug, Clone)]
 struct PackageData {
     name: String,
+    version: String,
     manifest: PathBuf,
     targets: Vec<Target>,
     is_member: bool,
     dependencies: Vec<PackageDependency>,
     edition: Edition,
+    /// The features enabled by the resolve of cargo, with the features of
+    /// the workspace members selected by the `CargoConfig`.
+    features: Vec<String>,
+    /// The directory the build script of the package writes to, if it was run.
+    out_dir: Option<PathBuf>,
+    /// The cfg flags set by the build script, like `foo` or `foo="bar"`.
+    build_cfgs: Vec<String>,
+    cfg_test: bool,
+    /// The compiled proc macro library, if the package is a proc macro crate
+    /// and it was built.
+    proc_macro_dylib: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
 pub struct PackageDependency {
     pub pkg: Package,
     pub name: String,
+    /// The kinds of the declarations of the dependency, a package can be both
+    /// a dependency and a build-dependency for example.
+    pub kinds: Vec<DepKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepKind {
+    /// Available to all the targets but the build script.
+    Normal,
+    /// Available to the tests, benches and examples.
+    Dev,
+    /// Available to the build script only.
+    Build,
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +139,8 @@ pub enum TargetKind {
     Example,
     Test,
     Bench,
+    /// The `build.rs` of the package, which can't use its library.
+    BuildScript,
     Other,
 }
 
@@ -72,21 +152,48 @@ impl TargetKind {
                 "test" => TargetKind::Test,
                 "bench" => TargetKind::Bench,
                 "example" => TargetKind::Example,
+                "custom-build" => TargetKind::BuildScript,
+                "proc-macro" => TargetKind::Lib,
                 _ if kind.contains("lib") => TargetKind::Lib,
                 _ => continue,
             };
         }
         TargetKind::Other
     }
+
+    /// Whether the target can use the dev-dependencies of its package.
+    pub fn has_dev_deps(self) -> bool {
+        match self {
+            TargetKind::Example | TargetKind::Test | TargetKind::Bench => true,
+            TargetKind::Bin | TargetKind::Lib | TargetKind::BuildScript | TargetKind::Other => {
+                false
+            }
+        }
+    }
+
+    /// Whether the target can use the dependencies of its package of `kind`.
+    pub fn can_use(self, kind: DepKind) -> bool {
+        match kind {
+            DepKind::Normal => self != TargetKind::BuildScript,
+            DepKind::Dev => self.has_dev_deps(),
+            DepKind::Build => self == TargetKind::BuildScript,
+        }
+    }
 }
 
 impl Package {
     pub fn name(self, ws: &CargoWorkspace) -> &str {
         ws.packages[self].name.as_str()
     }
+    pub fn version(self, ws: &CargoWorkspace) -> &str {
+        ws.packages[self].version.as_str()
+    }
     pub fn root(self, ws: &CargoWorkspace) -> &Path {
         ws.packages[self].manifest.parent().unwrap()
     }
+    pub fn manifest(self, ws: &CargoWorkspace) -> &Path {
+        ws.packages[self].manifest.as_path()
+    }
     pub fn edition(self, ws: &CargoWorkspace) -> Edition {
         ws.packages[self].edition
     }
@@ -103,6 +210,22 @@ impl Package {
     ) -> impl Iterator<Item = &'a PackageDependency> + 'a {
         ws.packages[self].dependencies.iter()
     }
+    pub fn features(self, ws: &CargoWorkspace) -> &[String] {
+        &ws.packages[self].features
+    }
+    /// Whether the crates of the package are analyzed with `cfg(test)`.
+    pub fn cfg_test(self, ws: &CargoWorkspace) -> bool {
+        ws.packages[self].cfg_test
+    }
+    pub fn out_dir(self, ws: &CargoWorkspace) -> Option<&Path> {
+        ws.packages[self].out_dir.as_ref().map(|it| it.as_path())
+    }
+    pub fn build_cfgs(self, ws: &CargoWorkspace) -> &[String] {
+        &ws.packages[self].build_cfgs
+    }
+    pub fn proc_macro_dylib(self, ws: &CargoWorkspace) -> Option<&Path> {
+        ws.packages[self].proc_macro_dylib.as_ref().map(|it| it.as_path())
+    }
 }
 
 impl Target {
@@ -121,14 +244,15 @@ impl Target {
 }
 
 impl CargoWorkspace {
-    pub fn from_cargo_metadata(cargo_toml: &Path) -> Result<CargoWorkspace> {
+    pub fn from_cargo_metadata(cargo_toml: &Path, config: &CargoConfig) -> Result<CargoWorkspace> {
         let mut meta = MetadataCommand::new();
-        meta.manifest_path(cargo_toml).features(CargoOpt::AllFeatures);
+        meta.manifest_path(cargo_toml).other_options(config.feature_flags());
         if let Some(parent) = cargo_toml.parent() {
             meta.current_dir(parent);
         }
         let meta = meta.exec().map_err(|e| format_err!("cargo metadata failed: {}", e))?;
         let mut pkg_by_id = FxHashMap::default();
+        let mut dep_kinds_by_id = FxHashMap::default();
         let mut packages = Arena::default();
         let mut targets = Arena::default();
 
@@ -138,14 +262,21 @@ impl CargoWorkspace {
             let is_member = ws_members.contains(&meta_pkg.id);
             let pkg = packages.alloc(PackageData {
                 name: meta_pkg.name.into(),
+                version: meta_pkg.version.to_string(),
                 manifest: meta_pkg.manifest_path.clone(),
                 targets: Vec::new(),
                 is_member,
                 edition: Edition::from_string(&meta_pkg.edition),
                 dependencies: Vec::new(),
+                features: Vec::new(),
+                out_dir: None,
+                build_cfgs: Vec::new(),
+                cfg_test: is_member && config.cfg_test,
+                proc_macro_dylib: None,
             });
             let pkg_data = &mut packages[pkg];
             pkg_by_id.insert(meta_pkg.id.clone(), pkg);
+            dep_kinds_by_id.insert(meta_pkg.id.clone(), dep_kinds(&meta_pkg.dependencies));
             for meta_tgt in meta_pkg.targets {
                 let tgt = targets.alloc(TargetData {
                     pkg,
@@ -159,11 +290,30 @@ impl CargoWorkspace {
         let resolve = meta.resolve.expect("metadata executed with deps");
         for node in resolve.nodes {
             let source = pkg_by_id[&node.id];
+            let dep_kinds = &dep_kinds_by_id[&node.id];
             for dep_node in node.deps {
-                let dep =
-                    PackageDependency { name: dep_node.name.into(), pkg: pkg_by_id[&dep_node.pkg] };
+                let pkg = pkg_by_id[&dep_node.pkg];
+                let kinds = dep_kinds.get(&packages[pkg].name, &dep_node.name);
+                let dep = PackageDependency { name: dep_node.name.into(), pkg, kinds };
                 packages[source].dependencies.push(dep);
             }
+            packages[source].features.extend(node.features);
+        }
+
+        if config.load_out_dirs_from_check {
+            match load_build_data(cargo_toml, config) {
+                Ok(build_data) => {
+                    for (id, data) in build_data {
+                        if let Some(&pkg) = pkg_by_id.get(&id) {
+                            let pkg = &mut packages[pkg];
+                            pkg.out_dir = data.out_dir;
+                            pkg.build_cfgs = data.cfgs;
+                            pkg.proc_macro_dylib = data.proc_macro_dylib;
+                        }
+                    }
+                }
+                Err(e) => log::error!("failed to run build scripts: {}", e),
+            }
         }
 
         Ok(CargoWorkspace { packages, targets, workspace_root: meta.workspace_root })
@@ -176,4 +326,255 @@ impl CargoWorkspace {
     pub fn target_by_root(&self, root: &Path) -> Option<Target> {
         self.packages().filter_map(|pkg| pkg.targets(self).find(|it| it.root(self) == root)).next()
     }
+
+    pub fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
+}
+
+/// What building a package produced.
+#[derive(Debug, Default)]
+struct BuildData {
+    out_dir: Option<PathBuf>,
+    cfgs: Vec<String>,
+    proc_macro_dylib: Option<PathBuf>,
+}
+
+/// A line of the output of `cargo check --message-format=json`, only the
+/// fields of the `build-script-executed` and `compiler-artifact` messages we
+/// need are there.
+#[derive(Deserialize)]
+struct BuildMessage {
+    reason: String,
+    package_id: PackageId,
+    /// Only reported by recent versions of Cargo.
+    #[serde(default)]
+    out_dir: Option<PathBuf>,
+    #[serde(default)]
+    cfgs: Vec<String>,
+    #[serde(default)]
+    target: Option<BuildTarget>,
+    #[serde(default)]
+    filenames: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct BuildTarget {
+    kind: Vec<String>,
+}
+
+/// The kinds of the dependencies of a package, by the name of the package they
+/// depend on and their rename. A dependency which isn't renamed is known to
+/// the code by the name of the lib target, which can differ from the name of
+/// its package.
+#[derive(Debug, Default)]
+struct DepKinds(FxHashMap<(String, Option<String>), Vec<DepKind>>);
+
+impl DepKinds {
+    /// The kinds of the dependency on package `package` known as `name` in
+    /// the resolve.
+    fn get(&self, package: &str, name: &str) -> Vec<DepKind> {
+        self.0
+            .get(&(package.to_string(), Some(name.to_string())))
+            .or_else(|| self.0.get(&(package.to_string(), None)))
+            .cloned()
+            .unwrap_or_else(|| vec![DepKind::Normal])
+    }
+}
+
+fn dep_kinds(deps: &[cargo_metadata::Dependency]) -> DepKinds {
+    let mut res = DepKinds::default();
+    for dep in deps {
+        let rename = dep.rename.as_ref().map(|it| it.replace('-', "_"));
+        let kind = match dep.kind {
+            DependencyKind::Development => DepKind::Dev,
+            DependencyKind::Build => DepKind::Build,
+            _ => DepKind::Normal,
+        };
+        let kinds = res.0.entry((dep.name.clone(), rename)).or_default();
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+    res
+}
+
+/// Runs the build scripts and builds the proc macros of the workspace and its
+/// dependencies with `cargo check`.
+fn load_build_data(
+    cargo_toml: &Path,
+    config: &CargoConfig,
+) -> Result<FxHashMap<PackageId, BuildData>> {
+    let mut child = Command::new("cargo")
+        .args(&["check", "--message-format=json", "--all-targets"])
+        .args(config.feature_flags())
+        .arg("--manifest-path")
+        .arg(cargo_toml)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child.stdout.take().unwrap();
+    let mut res: FxHashMap<PackageId, BuildData> = FxHashMap::default();
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        // Build scripts might print other lines as well.
+        let message = match serde_json::from_str::<BuildMessage>(&line) {
+            Ok(it) => it,
+            Err(_) => continue,
+        };
+        apply_build_message(&mut res, message);
+    }
+    let status = child.wait()?;
+    // Errors in the workspace itself don't prevent the build scripts of its
+    // dependencies from running, so these are still useful.
+    if res.is_empty() && !status.success() {
+        bail!("cargo check exited with {}", status);
+    }
+    Ok(res)
+}
+
+fn apply_build_message(res: &mut FxHashMap<PackageId, BuildData>, message: BuildMessage) {
+    match message.reason.as_str() {
+        "build-script-executed" => {
+            let data = res.entry(message.package_id).or_default();
+            data.out_dir = message.out_dir;
+            data.cfgs = message.cfgs;
+        }
+        "compiler-artifact" => {
+            let is_proc_macro =
+                message.target.map_or(false, |it| it.kind.iter().any(|it| it == "proc-macro"));
+            if !is_proc_macro {
+                return;
+            }
+            let dylib = message.filenames.into_iter().find(|it| {
+                let ext = it.extension().and_then(|it| it.to_str());
+                ext == Some("so") || ext == Some("dylib") || ext == Some("dll")
+            });
+            if let Some(dylib) = dylib {
+                res.entry(message.package_id).or_default().proc_macro_dylib = Some(dylib);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_kinds() {
+        let kind = |kinds: &[&str]| {
+            TargetKind::new(&kinds.iter().map(|it| it.to_string()).collect::<Vec<_>>())
+        };
+        assert_eq!(kind(&["lib"]), TargetKind::Lib);
+        assert_eq!(kind(&["rlib", "cdylib"]), TargetKind::Lib);
+        assert_eq!(kind(&["proc-macro"]), TargetKind::Lib);
+        assert_eq!(kind(&["bin"]), TargetKind::Bin);
+        assert_eq!(kind(&["test"]), TargetKind::Test);
+        assert_eq!(kind(&["bench"]), TargetKind::Bench);
+        assert_eq!(kind(&["example"]), TargetKind::Example);
+        assert_eq!(kind(&["custom-build"]), TargetKind::BuildScript);
+        assert_eq!(kind(&[]), TargetKind::Other);
+    }
+
+    #[test]
+    fn targets_use_the_dependencies_of_their_kind() {
+        assert!(TargetKind::Lib.can_use(DepKind::Normal));
+        assert!(!TargetKind::Lib.can_use(DepKind::Dev));
+        assert!(!TargetKind::Lib.can_use(DepKind::Build));
+        assert!(TargetKind::Test.can_use(DepKind::Dev));
+        assert!(TargetKind::BuildScript.can_use(DepKind::Build));
+        assert!(!TargetKind::BuildScript.can_use(DepKind::Normal));
+    }
+
+    #[test]
+    fn feature_flags() {
+        let mut config = CargoConfig::default();
+        assert_eq!(config.feature_flags(), vec!["--all-features"]);
+        config.all_features = false;
+        assert!(config.feature_flags().is_empty());
+        config.features = vec!["foo".to_string(), "bar/baz".to_string()];
+        assert_eq!(config.feature_flags(), vec!["--features", "foo bar/baz"]);
+        config.no_default_features = true;
+        assert_eq!(
+            config.feature_flags(),
+            vec!["--no-default-features", "--features", "foo bar/baz"]
+        );
+    }
+
+    #[test]
+    fn dependency_kinds() {
+        let deps: Vec<cargo_metadata::Dependency> = serde_json::from_str(
+            r#"[
+                {"name":"serde","source":null,"req":"*","kind":null,"rename":null,
+                 "optional":false,"uses_default_features":true,"features":[],"target":null},
+                {"name":"serde","source":null,"req":"*","kind":"dev","rename":null,
+                 "optional":false,"uses_default_features":true,"features":[],"target":null},
+                {"name":"foo-test","source":null,"req":"*","kind":"dev","rename":null,
+                 "optional":false,"uses_default_features":true,"features":[],"target":null},
+                {"name":"bar","source":null,"req":"*","kind":"dev","rename":"baz",
+                 "optional":false,"uses_default_features":true,"features":[],"target":null},
+                {"name":"cc","source":null,"req":"*","kind":"build","rename":null,
+                 "optional":false,"uses_default_features":true,"features":[],"target":null}
+            ]"#,
+        )
+        .unwrap();
+        let kinds = dep_kinds(&deps);
+        assert_eq!(kinds.get("bar", "baz"), vec![DepKind::Dev]);
+        assert_eq!(kinds.get("cc", "cc"), vec![DepKind::Build]);
+        assert_eq!(kinds.get("foo-test", "foo_test"), vec![DepKind::Dev]);
+        assert_eq!(kinds.get("serde", "serde"), vec![DepKind::Normal, DepKind::Dev]);
+    }
+
+    #[test]
+    fn dependency_kinds_of_renamed_libs() {
+        // `[lib] name = "foolib"` in the manifest of `foo`
+        let deps: Vec<cargo_metadata::Dependency> = serde_json::from_str(
+            r#"[
+                {"name":"foo","source":null,"req":"*","kind":"dev","rename":null,
+                 "optional":false,"uses_default_features":true,"features":[],"target":null},
+                {"name":"foo-build","source":null,"req":"*","kind":"build","rename":"gen",
+                 "optional":false,"uses_default_features":true,"features":[],"target":null}
+            ]"#,
+        )
+        .unwrap();
+        let kinds = dep_kinds(&deps);
+        assert_eq!(kinds.get("foo", "foolib"), vec![DepKind::Dev]);
+        assert_eq!(kinds.get("foo-build", "gen"), vec![DepKind::Build]);
+    }
+
+    #[test]
+    fn build_messages() {
+        let lines = [
+            r#"{"reason":"build-script-executed","package_id":"foo 0.1.0 (path+file:///foo)",
+               "linked_libs":[],"linked_paths":[],"cfgs":["has_foo","foo=\"bar\""],"env":[],
+               "out_dir":"/target/debug/build/foo-1234/out"}"#,
+            r#"{"reason":"compiler-artifact","package_id":"derive 0.1.0 (path+file:///derive)",
+               "target":{"kind":["proc-macro"],"crate_types":["proc-macro"],"name":"derive",
+               "src_path":"/derive/src/lib.rs","edition":"2018"},
+               "filenames":["/target/debug/deps/libderive-5678.so"],"fresh":true}"#,
+            r#"{"reason":"compiler-artifact","package_id":"foo 0.1.0 (path+file:///foo)",
+               "target":{"kind":["lib"],"crate_types":["lib"],"name":"foo",
+               "src_path":"/foo/src/lib.rs","edition":"2018"},
+               "filenames":["/target/debug/deps/libfoo-1234.rmeta"],"fresh":true}"#,
+        ];
+        let mut res = FxHashMap::default();
+        for line in lines.iter() {
+            apply_build_message(&mut res, serde_json::from_str(line).unwrap());
+        }
+        let id = |repr: &str| PackageId { repr: repr.to_string() };
+
+        let foo = &res[&id("foo 0.1.0 (path+file:///foo)")];
+        assert_eq!(foo.out_dir.as_ref().unwrap(), Path::new("/target/debug/build/foo-1234/out"));
+        assert_eq!(foo.cfgs, vec!["has_foo".to_string(), "foo=\"bar\"".to_string()]);
+        assert_eq!(foo.proc_macro_dylib, None);
+
+        let derive = &res[&id("derive 0.1.0 (path+file:///derive)")];
+        assert_eq!(derive.out_dir, None);
+        assert_eq!(
+            derive.proc_macro_dylib.as_ref().unwrap(),
+            Path::new("/target/debug/deps/libderive-5678.so")
+        );
+    }
 }
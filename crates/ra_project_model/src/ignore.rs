@@ -0,0 +1,287 @@
+//! A subset of `.gitignore` patterns, to skip the files of a project root
+//! which are not part of the project, like generated ones.
+//!
+//! Only `*`, `?` and `**` wildcards, anchored patterns, negated patterns and
+//! trailing slashes for directories are supported. As in git, the last pattern
+//! which matches a path decides whether it is ignored, and the patterns of
+//! nested `.gitignore` files come after the ones of their parent directories.
+
+use std::{fs, path::Path};
+
+use relative_path::{RelativePath, RelativePathBuf};
+
+#[derive(Debug, Clone, Default)]
+pub struct IgnorePatterns {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    /// The path of the project root relative to the directory of the
+    /// `.gitignore` file the pattern comes from.
+    base: RelativePathBuf,
+    /// The path of the directory of the `.gitignore` file relative to the
+    /// project root, for the files inside of the root.
+    dir: RelativePathBuf,
+    /// The components of the pattern, `**` included.
+    components: Vec<String>,
+    /// Whether the pattern is relative to its `.gitignore`, rather than
+    /// matching at any depth.
+    anchored: bool,
+    dir_only: bool,
+    /// Whether the pattern re-includes the paths it matches (`!foo`).
+    negated: bool,
+}
+
+impl IgnorePatterns {
+    /// Reads the `.gitignore` files of `root`, of its parent directories up to
+    /// the root of the git repository, and of the directories inside of it
+    /// which are not ignored.
+    pub fn load(root: &Path) -> IgnorePatterns {
+        let mut res = IgnorePatterns::default();
+        let mut parents = Vec::new();
+        let mut base = RelativePathBuf::new();
+        let mut dir = Some(root);
+        while let Some(path) = dir {
+            if let Ok(text) = fs::read_to_string(path.join(".gitignore")) {
+                parents.push((base.clone(), text));
+            }
+            if path.join(".git").exists() {
+                break;
+            }
+            let name = match path.file_name().and_then(|it| it.to_str()) {
+                Some(it) => it,
+                None => break,
+            };
+            base = RelativePath::new(name).join(&base);
+            dir = path.parent();
+        }
+        for (base, text) in parents.iter().rev() {
+            res.add(base, RelativePath::new(""), text);
+        }
+        res.load_nested(root, RelativePath::new(""));
+        res
+    }
+
+    fn load_nested(&mut self, root: &Path, dir: &RelativePath) {
+        let entries = match fs::read_dir(dir.to_path(root)) {
+            Ok(it) => it,
+            Err(_) => return,
+        };
+        for entry in entries.filter_map(|it| it.ok()) {
+            if !entry.file_type().map_or(false, |it| it.is_dir()) {
+                continue;
+            }
+            let name = match entry.file_name().into_string() {
+                Ok(it) => it,
+                Err(_) => continue,
+            };
+            let path = dir.join(&name);
+            if name.starts_with('.') || self.is_ignored(&path, true) {
+                continue;
+            }
+            if let Ok(text) = fs::read_to_string(path.to_path(root).join(".gitignore")) {
+                self.add(RelativePath::new(""), &path, &text);
+            }
+            self.load_nested(root, &path);
+        }
+    }
+
+    /// Adds patterns with the `.gitignore` syntax which don't come from a
+    /// `.gitignore` file, like the excluded directories of the configuration.
+    /// `base` is the path of the project root relative to the directory the
+    /// patterns are relative to.
+    pub fn add_patterns(&mut self, base: &RelativePath, patterns: &[String]) {
+        self.add(base, RelativePath::new(""), &patterns.join("\n"))
+    }
+
+    /// Adds the patterns of a `.gitignore` file. `base` is the path of the
+    /// project root relative to the directory of that file, for the files of
+    /// parent directories, and `dir` the path of that directory relative to the
+    /// project root, for the nested ones.
+    fn add(&mut self, base: &RelativePath, dir: &RelativePath, text: &str) {
+        for line in text.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let negated = line.starts_with('!');
+            let line = if negated { &line[1..] } else { line };
+            // `\!` and `\#` match names starting with those characters.
+            let line = if line.starts_with('\\') { &line[1..] } else { line };
+            let dir_only = line.ends_with('/');
+            let line = line.trim_end_matches('/');
+            let anchored = line.contains('/');
+            let components = line
+                .trim_start_matches('/')
+                .split('/')
+                .filter(|it| !it.is_empty())
+                .map(|it| it.to_string())
+                .collect::<Vec<_>>();
+            if components.is_empty() {
+                continue;
+            }
+            self.patterns.push(Pattern {
+                base: base.to_owned(),
+                dir: dir.to_owned(),
+                components,
+                anchored,
+                dir_only,
+                negated,
+            });
+        }
+    }
+
+    /// Whether `path`, relative to the project root, is ignored. The parent
+    /// directories of `path` are assumed not to be ignored.
+    pub fn is_ignored(&self, path: &RelativePath, is_dir: bool) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(path, is_dir))
+            .map_or(false, |pattern| !pattern.negated)
+    }
+}
+
+impl Pattern {
+    fn matches(&self, path: &RelativePath, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let path = match path.strip_prefix(&self.dir) {
+            Ok(it) => self.base.join(it),
+            Err(_) => return false,
+        };
+        let components: Vec<&str> = path.components().map(|it| it.as_str()).collect();
+        if self.anchored {
+            match_components(&self.components, &components)
+        } else {
+            components.last().map_or(false, |name| match_component(&self.components[0], name))
+        }
+    }
+}
+
+fn match_components(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| match_components(rest, &path[skip..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((name, path)) => match_component(first, name) && match_components(rest, path),
+            None => false,
+        },
+    }
+}
+
+/// Matches a single path component against a pattern with `*` and `?`.
+fn match_component(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    fn go(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skip| go(rest, &name[skip..])),
+            Some((&c, rest)) => match name.split_first() {
+                Some((&n, name)) => (c == '?' || c == n) && go(rest, name),
+                None => false,
+            },
+        }
+    }
+    go(&pattern, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn patterns(base: &str, text: &str) -> IgnorePatterns {
+        let mut res = IgnorePatterns::default();
+        res.add(RelativePath::new(base), RelativePath::new(""), text);
+        res
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        let ignore = patterns("", "*.bk\n# comment\n\ngenerated/\n!keep.bk\n");
+        assert!(ignore.is_ignored(RelativePath::new("foo.bk"), false));
+        assert!(ignore.is_ignored(RelativePath::new("src/foo.bk"), false));
+        assert!(!ignore.is_ignored(RelativePath::new("src/foo.rs"), false));
+        assert!(ignore.is_ignored(RelativePath::new("src/generated"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("src/generated"), false));
+        assert!(!ignore.is_ignored(RelativePath::new("src/keep.bk"), false));
+    }
+
+    #[test]
+    fn last_matching_pattern_wins() {
+        let ignore = patterns("", "gen_*.rs\n!gen_keep.rs\ngen_keep.rs\n!gen_api*\n\\!important\n");
+        assert!(ignore.is_ignored(RelativePath::new("gen_foo.rs"), false));
+        assert!(ignore.is_ignored(RelativePath::new("gen_keep.rs"), false));
+        assert!(!ignore.is_ignored(RelativePath::new("src/gen_api.rs"), false));
+        assert!(ignore.is_ignored(RelativePath::new("!important"), false));
+    }
+
+    #[test]
+    fn patterns_of_nested_directories() {
+        let mut ignore = patterns("", "*.bk\n");
+        ignore.add(RelativePath::new(""), RelativePath::new("src"), "/gen\n!keep.bk\n");
+        assert!(ignore.is_ignored(RelativePath::new("src/gen"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("gen"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("src/a/gen"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("src/a/keep.bk"), false));
+        assert!(ignore.is_ignored(RelativePath::new("keep.bk"), false));
+    }
+
+    #[test]
+    fn anchored_patterns_match_from_the_gitignore() {
+        let ignore = patterns("", "/out\nsrc/gen_*.rs\ndocs/**/*.rs\n");
+        assert!(ignore.is_ignored(RelativePath::new("out"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("src/out"), true));
+        assert!(ignore.is_ignored(RelativePath::new("src/gen_foo.rs"), false));
+        assert!(!ignore.is_ignored(RelativePath::new("src/foo.rs"), false));
+        assert!(ignore.is_ignored(RelativePath::new("docs/foo.rs"), false));
+        assert!(ignore.is_ignored(RelativePath::new("docs/a/b/foo.rs"), false));
+    }
+
+    #[test]
+    fn patterns_of_parent_directories() {
+        let ignore = patterns("crates/foo", "/crates/foo/gen\n/crates/bar\n");
+        assert!(ignore.is_ignored(RelativePath::new("gen"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("bar"), true));
+    }
+
+    #[test]
+    fn added_patterns() {
+        let mut ignore = IgnorePatterns::default();
+        let patterns = vec!["vendor/".to_string(), "**/generated/**".to_string()];
+        ignore.add_patterns(RelativePath::new("crates/foo"), &patterns);
+        assert!(ignore.is_ignored(RelativePath::new("generated"), true));
+        assert!(ignore.is_ignored(RelativePath::new("src/generated/mod.rs"), false));
+        assert!(ignore.is_ignored(RelativePath::new("vendor"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("vendor.rs"), false));
+        assert!(!ignore.is_ignored(RelativePath::new("src/lib.rs"), false));
+    }
+
+    #[test]
+    fn loads_nested_gitignore_files() {
+        let tmp_dir = TempDir::new().unwrap();
+        let root = tmp_dir.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("src/gen")).unwrap();
+        fs::create_dir_all(root.join("out/sub")).unwrap();
+        fs::write(root.join(".gitignore"), "/out\n*.bk\n").unwrap();
+        fs::write(root.join("src/.gitignore"), "gen/\n!keep.bk\n").unwrap();
+        // Not read, its directory is ignored.
+        fs::write(root.join("out/sub/.gitignore"), "!*.bk\n").unwrap();
+
+        let ignore = IgnorePatterns::load(root);
+        assert!(ignore.is_ignored(RelativePath::new("out"), true));
+        assert!(ignore.is_ignored(RelativePath::new("src/gen"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("gen"), true));
+        assert!(!ignore.is_ignored(RelativePath::new("src/keep.bk"), false));
+        assert!(ignore.is_ignored(RelativePath::new("keep.bk"), false));
+        assert!(ignore.is_ignored(RelativePath::new("out/sub/foo.bk"), false));
+    }
+}
@@ -1,7 +1,14 @@
-use std::path::PathBuf;
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
 
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::Deserialize;
 
+use crate::Result;
+
 /// A root points to the directory which contains Rust crates. rust-analyzer watches all files in
 /// all roots. Roots might be nested.
 #[derive(Clone, Debug, Deserialize)]
@@ -17,6 +24,13 @@ pub struct Crate {
     pub(crate) root_module: PathBuf,
     pub(crate) edition: Edition,
     pub(crate) deps: Vec<Dep>,
+    /// The cfg flags of the crate in addition to the ones of rustc, like `test` or
+    /// `feature="std"`.
+    #[serde(default)]
+    pub(crate) cfg: FxHashSet<String>,
+    /// The environment variables set when compiling the crate, like `OUT_DIR`.
+    #[serde(default)]
+    pub(crate) env: FxHashMap<String, String>,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize)]
@@ -46,4 +60,70 @@ pub struct Dep {
 pub struct JsonProject {
     pub(crate) roots: Vec<Root>,
     pub(crate) crates: Vec<Crate>,
+    /// The directory with the sources of the standard library, like
+    /// `lib/rustlib/src/rust/src` in a sysroot. All the crates depend on its
+    /// `std` crate.
+    #[serde(default)]
+    pub(crate) sysroot_src: Option<PathBuf>,
+}
+
+impl JsonProject {
+    /// Loads a `rust-project.json` file. Relative paths in it are relative to
+    /// the directory of the file.
+    pub fn load(path: &Path) -> Result<JsonProject> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut project: JsonProject = serde_json::from_reader(reader)?;
+        if let Some(base) = path.parent() {
+            project.make_paths_absolute(base);
+        }
+        Ok(project)
+    }
+
+    fn make_paths_absolute(&mut self, base: &Path) {
+        for root in self.roots.iter_mut() {
+            root.path = base.join(&root.path);
+        }
+        for krate in self.crates.iter_mut() {
+            krate.root_module = base.join(&krate.root_module);
+        }
+        if let Some(sysroot_src) = self.sysroot_src.as_mut() {
+            *sysroot_src = base.join(&sysroot_src);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_paths_are_relative_to_the_project_file() {
+        let mut project: JsonProject = serde_json::from_str(
+            r#"{
+                "roots": ["src", "/other"],
+                "crates": [
+                    {
+                        "root_module": "src/lib.rs",
+                        "edition": "2018",
+                        "deps": [],
+                        "cfg": ["test", "feature=\"std\""],
+                        "env": { "OUT_DIR": "/out" }
+                    },
+                    { "root_module": "/other/main.rs", "edition": "2015", "deps": [{ "crate": 0, "name": "foo" }] }
+                ],
+                "sysroot_src": "sysroot/src"
+            }"#,
+        )
+        .unwrap();
+        project.make_paths_absolute(Path::new("/project"));
+
+        let roots: Vec<_> = project.roots.iter().map(|it| it.path.as_path()).collect();
+        assert_eq!(roots, vec![Path::new("/project/src"), Path::new("/other")]);
+        assert_eq!(project.crates[0].root_module, Path::new("/project/src/lib.rs"));
+        assert_eq!(project.crates[1].root_module, Path::new("/other/main.rs"));
+        assert!(project.crates[0].cfg.contains("feature=\"std\""));
+        assert_eq!(project.crates[0].env["OUT_DIR"], "/out");
+        assert!(project.crates[1].cfg.is_empty());
+        assert_eq!(project.sysroot_src.as_ref().unwrap(), Path::new("/project/sysroot/src"));
+    }
 }
@@ -1,26 +1,25 @@
 mod cargo_workspace;
+mod ignore;
 mod json_project;
 mod sysroot;
 
 use std::{
-    fs::File,
-    io::BufReader,
     path::{Path, PathBuf},
+    process::Command,
 };
 
 use failure::bail;
 use rustc_hash::FxHashMap;
 
-use ra_db::{CrateGraph, FileId, Edition};
+use ra_db::{CrateGraph, CrateId, FileId, Edition, CfgOptions, Env};
 
-use serde_json::from_reader;
-
-use relative_path::RelativePath;
+use relative_path::{RelativePath, RelativePathBuf};
 
 pub use crate::{
-    cargo_workspace::{CargoWorkspace, Package, Target, TargetKind},
+    cargo_workspace::{CargoConfig, CargoWorkspace, DepKind, Package, Target, TargetKind},
+    ignore::IgnorePatterns,
     json_project::JsonProject,
-    sysroot::Sysroot,
+    sysroot::{Sysroot, SysrootCrate},
 };
 
 // FIXME use proper error enum
@@ -31,7 +30,7 @@ pub enum ProjectWorkspace {
     /// Project workspace was discovered by running `cargo metadata` and `rustc --print sysroot`.
     Cargo { cargo: CargoWorkspace, sysroot: Sysroot },
     /// Project workspace was manually specified using a `rust-project.json` file.
-    Json { project: JsonProject },
+    Json { project: JsonProject, sysroot: Option<Sysroot> },
 }
 
 /// `ProjectRoot` describes a workspace root folder.
@@ -42,11 +41,16 @@ pub struct ProjectRoot {
     path: PathBuf,
     /// Is a member of the current workspace
     is_member: bool,
+    /// The `.gitignore` patterns which apply to the root, for members, and
+    /// the excluded directories of the configuration
+    ignore: IgnorePatterns,
 }
 
 impl ProjectRoot {
     pub fn new(path: PathBuf, is_member: bool) -> ProjectRoot {
-        ProjectRoot { path, is_member }
+        let ignore =
+            if is_member { IgnorePatterns::load(&path) } else { IgnorePatterns::default() };
+        ProjectRoot { path, is_member, ignore }
     }
 
     pub fn path(&self) -> &PathBuf {
@@ -57,6 +61,27 @@ impl ProjectRoot {
         self.is_member
     }
 
+    /// Excludes the paths matching `patterns`, which have the `.gitignore`
+    /// syntax and are relative to `dir`, if the root is inside of `dir`.
+    /// Returns `None` if the root itself is excluded.
+    pub fn exclude(mut self, dir: &Path, patterns: &[String]) -> Option<ProjectRoot> {
+        let base = match self.path.strip_prefix(dir).ok().map(RelativePathBuf::from_path) {
+            Some(Ok(it)) => it,
+            _ => return Some(self),
+        };
+        let mut exclude = IgnorePatterns::default();
+        exclude.add_patterns(RelativePath::new(""), patterns);
+        let mut ancestor = RelativePathBuf::new();
+        for component in base.components() {
+            ancestor.push(component.as_str());
+            if exclude.is_ignored(&ancestor, true) {
+                return None;
+            }
+        }
+        self.ignore.add_patterns(&base, patterns);
+        Some(self)
+    }
+
     pub fn include_dir(&self, dir_path: &RelativePath) -> bool {
         const COMMON_IGNORED_DIRS: &[&str] = &["node_modules", "target", ".git"];
         const EXTERNAL_IGNORED_DIRS: &[&str] = &["examples", "tests", "benches"];
@@ -72,26 +97,26 @@ impl ProjectRoot {
 
         let hidden = dir_path.components().any(|c| c.as_str().starts_with("."));
 
-        !is_ignored && !hidden
+        !is_ignored && !hidden && !self.ignore.is_ignored(dir_path, true)
     }
 
     pub fn include_file(&self, file_path: &RelativePath) -> bool {
-        file_path.extension() == Some("rs")
+        file_path.extension() == Some("rs") && !self.ignore.is_ignored(file_path, false)
     }
 }
 
 impl ProjectWorkspace {
-    pub fn discover(path: &Path) -> Result<ProjectWorkspace> {
+    pub fn discover(path: &Path, cargo_config: &CargoConfig) -> Result<ProjectWorkspace> {
         match find_rust_project_json(path) {
             Some(json_path) => {
-                let file = File::open(json_path)?;
-                let reader = BufReader::new(file);
-                Ok(ProjectWorkspace::Json { project: from_reader(reader)? })
+                let project = JsonProject::load(&json_path)?;
+                let sysroot = project.sysroot_src.as_ref().map(|it| Sysroot::load(it));
+                Ok(ProjectWorkspace::Json { project, sysroot })
             }
             None => {
                 let cargo_toml = find_cargo_toml(path)?;
                 Ok(ProjectWorkspace::Cargo {
-                    cargo: CargoWorkspace::from_cargo_metadata(&cargo_toml)?,
+                    cargo: CargoWorkspace::from_cargo_metadata(&cargo_toml, cargo_config)?,
                     sysroot: Sysroot::discover(&cargo_toml)?,
                 })
             }
@@ -103,11 +128,16 @@ impl ProjectWorkspace {
     /// the root is a member of the current workspace
     pub fn to_roots(&self) -> Vec<ProjectRoot> {
         match self {
-            ProjectWorkspace::Json { project } => {
+            ProjectWorkspace::Json { project, sysroot } => {
                 let mut roots = Vec::with_capacity(project.roots.len());
                 for root in &project.roots {
                     roots.push(ProjectRoot::new(root.path.clone(), true));
                 }
+                if let Some(sysroot) = sysroot {
+                    for krate in sysroot.crates() {
+                        roots.push(ProjectRoot::new(krate.root_dir(&sysroot).to_path_buf(), false))
+                    }
+                }
                 roots
             }
             ProjectWorkspace::Cargo { cargo, sysroot } => {
@@ -117,6 +147,10 @@ impl ProjectWorkspace {
                     let root = pkg.root(&cargo).to_path_buf();
                     let member = pkg.is_member(&cargo);
                     roots.push(ProjectRoot::new(root, member));
+                    // The files generated by the build script, for `include!`.
+                    if let Some(out_dir) = pkg.out_dir(&cargo) {
+                        roots.push(ProjectRoot::new(out_dir.to_path_buf(), false));
+                    }
                 }
                 for krate in sysroot.crates() {
                     roots.push(ProjectRoot::new(krate.root_dir(&sysroot).to_path_buf(), false))
@@ -128,15 +162,30 @@ impl ProjectWorkspace {
 
     pub fn count(&self) -> usize {
         match self {
-            ProjectWorkspace::Json { project } => project.crates.len(),
+            ProjectWorkspace::Json { project, .. } => project.crates.len(),
             ProjectWorkspace::Cargo { cargo, .. } => cargo.packages().count(),
         }
     }
 
-    pub fn to_crate_graph(&self, load: &mut dyn FnMut(&Path) -> Option<FileId>) -> CrateGraph {
+    pub fn to_crate_graph(
+        &self,
+        default_cfg_options: &CfgOptions,
+        load: &mut dyn FnMut(&Path) -> Option<FileId>,
+    ) -> CrateGraph {
         let mut crate_graph = CrateGraph::default();
         match self {
-            ProjectWorkspace::Json { project } => {
+            ProjectWorkspace::Json { project, sysroot } => {
+                let sysroot_crates = match sysroot {
+                    Some(sysroot) => {
+                        sysroot_to_crate_graph(&mut crate_graph, sysroot, default_cfg_options, load)
+                    }
+                    None => FxHashMap::default(),
+                };
+                let sysroot_deps = match sysroot {
+                    Some(sysroot) => public_sysroot_deps(sysroot, &sysroot_crates),
+                    None => Vec::new(),
+                };
+
                 let mut crates = FxHashMap::default();
                 for (id, krate) in project.crates.iter().enumerate() {
                     let crate_id = json_project::CrateId(id);
@@ -145,7 +194,23 @@ impl ProjectWorkspace {
                             json_project::Edition::Edition2015 => Edition::Edition2015,
                             json_project::Edition::Edition2018 => Edition::Edition2018,
                         };
-                        crates.insert(crate_id, crate_graph.add_crate_root(file_id, edition));
+                        let id = crate_graph.add_crate_root(file_id, edition);
+                        let mut cfg_options = default_cfg_options.clone();
+                        for cfg in &krate.cfg {
+                            insert_cfg(&mut cfg_options, cfg);
+                        }
+                        crate_graph.set_cfg_options(id, cfg_options);
+                        let mut env = Env::default();
+                        for (key, value) in &krate.env {
+                            env.set(key, value.clone());
+                        }
+                        crate_graph.set_env(id, env);
+                        for (name, to) in &sysroot_deps {
+                            if let Err(_) = crate_graph.add_dep(id, name.as_str().into(), *to) {
+                                log::error!("cyclic dependency on {} for {:?}", name, crate_id)
+                            }
+                        }
+                        crates.insert(crate_id, id);
                     }
                 }
 
@@ -168,54 +233,76 @@ impl ProjectWorkspace {
                 }
             }
             ProjectWorkspace::Cargo { cargo, sysroot } => {
-                let mut sysroot_crates = FxHashMap::default();
-                for krate in sysroot.crates() {
-                    if let Some(file_id) = load(krate.root(&sysroot)) {
-                        sysroot_crates.insert(
-                            krate,
-                            crate_graph.add_crate_root(file_id, Edition::Edition2015),
-                        );
-                    }
-                }
-                for from in sysroot.crates() {
-                    for to in from.deps(&sysroot) {
-                        let name = to.name(&sysroot);
-                        if let (Some(&from), Some(&to)) =
-                            (sysroot_crates.get(&from), sysroot_crates.get(&to))
-                        {
-                            if let Err(_) = crate_graph.add_dep(from, name.into(), to) {
-                                log::error!("cyclic dependency between sysroot crates")
-                            }
-                        }
-                    }
-                }
+                let sysroot_crates =
+                    sysroot_to_crate_graph(&mut crate_graph, sysroot, default_cfg_options, load);
 
-                let libstd = sysroot.std().and_then(|it| sysroot_crates.get(&it).map(|&it| it));
+                let sysroot_deps = public_sysroot_deps(sysroot, &sysroot_crates);
 
                 let mut pkg_to_lib_crate = FxHashMap::default();
                 let mut pkg_crates = FxHashMap::default();
                 // Next, create crates for each package, target pair
                 for pkg in cargo.packages() {
+                    let cfg_options = {
+                        let mut opts = default_cfg_options.clone();
+                        for feature in pkg.features(&cargo) {
+                            opts.insert_key_value("feature".into(), feature.as_str().into());
+                        }
+                        for cfg in pkg.build_cfgs(&cargo) {
+                            insert_cfg(&mut opts, cfg);
+                        }
+                        if pkg.cfg_test(&cargo) {
+                            opts.insert_atom("test".into());
+                        }
+                        opts
+                    };
+                    let env = {
+                        let mut env = Env::default();
+                        env.set("CARGO_PKG_NAME", pkg.name(&cargo).to_string());
+                        env.set("CARGO_PKG_VERSION", pkg.version(&cargo).to_string());
+                        env.set("CARGO_MANIFEST_DIR", pkg.root(&cargo).display().to_string());
+                        if let Some(out_dir) = pkg.out_dir(&cargo) {
+                            env.set("OUT_DIR", out_dir.display().to_string());
+                        }
+                        env
+                    };
+                    let out_dir_files = match pkg.out_dir(&cargo) {
+                        Some(out_dir) => load_out_dir(out_dir, load),
+                        None => Vec::new(),
+                    };
                     let mut lib_tgt = None;
                     for tgt in pkg.targets(&cargo) {
                         let root = tgt.root(&cargo);
                         if let Some(file_id) = load(root) {
                             let edition = pkg.edition(&cargo);
                             let crate_id = crate_graph.add_crate_root(file_id, edition);
+                            crate_graph.set_version(crate_id, pkg.version(&cargo).into());
+                            crate_graph.set_cfg_options(crate_id, cfg_options.clone());
+                            crate_graph.set_env(crate_id, env.clone());
+                            if let Some(dylib) = pkg.proc_macro_dylib(&cargo) {
+                                crate_graph.set_proc_macro_dylib(crate_id, dylib.to_path_buf());
+                            }
+                            for (path, file_id) in out_dir_files.iter() {
+                                crate_graph.add_out_dir_file(crate_id, path.clone(), *file_id);
+                            }
                             if tgt.kind(&cargo) == TargetKind::Lib {
-                                lib_tgt = Some(crate_id);
+                                // The name the other targets of the package use
+                                // for the library, `foo-bar` becomes `foo_bar`.
+                                lib_tgt = Some((crate_id, tgt.name(&cargo).replace('-', "_")));
                                 pkg_to_lib_crate.insert(pkg, crate_id);
                             }
-                            pkg_crates.entry(pkg).or_insert_with(Vec::new).push(crate_id);
+                            pkg_crates
+                                .entry(pkg)
+                                .or_insert_with(Vec::new)
+                                .push((crate_id, tgt.kind(&cargo)));
                         }
                     }
 
-                    // Set deps to the std and to the lib target of the current package
-                    for &from in pkg_crates.get(&pkg).into_iter().flatten() {
-                        if let Some(to) = lib_tgt {
-                            if to != from {
-                                if let Err(_) =
-                                    crate_graph.add_dep(from, pkg.name(&cargo).into(), to)
+                    // Set deps to the std and to the lib target of the current
+                    // package, which the build script can't use.
+                    for &(from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
+                        if let Some((to, name)) = &lib_tgt {
+                            if *to != from && kind != TargetKind::BuildScript {
+                                if let Err(_) = crate_graph.add_dep(from, name.as_str().into(), *to)
                                 {
                                     log::error!(
                                         "cyclic dependency between targets of {}",
@@ -224,20 +311,30 @@ impl ProjectWorkspace {
                                 }
                             }
                         }
-                        if let Some(std) = libstd {
-                            if let Err(_) = crate_graph.add_dep(from, "std".into(), std) {
-                                log::error!("cyclic dependency on std for {}", pkg.name(&cargo))
+                        for (name, to) in &sysroot_deps {
+                            if let Err(_) = crate_graph.add_dep(from, name.as_str().into(), *to) {
+                                log::error!(
+                                    "cyclic dependency on {} for {}",
+                                    name,
+                                    pkg.name(&cargo)
+                                )
                             }
                         }
                     }
                 }
 
                 // Now add a dep ednge from all targets of upstream to the lib
-                // target of downstream.
+                // target of downstream. Dev-dependencies are only available
+                // to the tests, benches and examples, which also breaks the
+                // cycles of a package and the packages it uses in its tests,
+                // and build-dependencies only to the build script.
                 for pkg in cargo.packages() {
                     for dep in pkg.dependencies(&cargo) {
                         if let Some(&to) = pkg_to_lib_crate.get(&dep.pkg) {
-                            for &from in pkg_crates.get(&pkg).into_iter().flatten() {
+                            for &(from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
+                                if !dep.kinds.iter().any(|&it| kind.can_use(it)) {
+                                    continue;
+                                }
                                 if let Err(_) =
                                     crate_graph.add_dep(from, dep.name.clone().into(), to)
                                 {
@@ -261,12 +358,150 @@ impl ProjectWorkspace {
             ProjectWorkspace::Cargo { cargo, .. } => {
                 Some(cargo.workspace_root.as_ref()).filter(|root| path.starts_with(root))
             }
-            ProjectWorkspace::Json { project: JsonProject { roots, .. } } => roots
+            ProjectWorkspace::Json { project: JsonProject { roots, .. }, .. } => roots
                 .iter()
                 .find(|root| path.starts_with(&root.path))
                 .map(|root| root.path.as_ref()),
         }
     }
+
+    pub fn sysroot(&self) -> Option<&Sysroot> {
+        match self {
+            ProjectWorkspace::Cargo { sysroot, .. } => Some(sysroot),
+            ProjectWorkspace::Json { sysroot, .. } => sysroot.as_ref(),
+        }
+    }
+
+    /// The `Cargo.toml` of the package of the file at `path`, which is the
+    /// innermost package containing it.
+    pub fn manifest_for(&self, path: &Path) -> Option<&Path> {
+        match self {
+            ProjectWorkspace::Cargo { cargo, .. } => cargo
+                .packages()
+                .filter(|pkg| path.starts_with(pkg.root(cargo)))
+                .max_by_key(|pkg| pkg.root(cargo).components().count())
+                .map(|pkg| pkg.manifest(cargo)),
+            ProjectWorkspace::Json { .. } => None,
+        }
+    }
+}
+
+/// The crate graph of a file which belongs to no workspace, which is the root
+/// of a crate of its own depending on the public crates of `sysroot`.
+pub fn detached_file_crate_graph(
+    file_id: FileId,
+    sysroot: Option<&Sysroot>,
+    default_cfg_options: &CfgOptions,
+    load: &mut dyn FnMut(&Path) -> Option<FileId>,
+) -> CrateGraph {
+    let mut crate_graph = CrateGraph::default();
+    let sysroot_deps = match sysroot {
+        Some(sysroot) => {
+            let sysroot_crates =
+                sysroot_to_crate_graph(&mut crate_graph, sysroot, default_cfg_options, load);
+            public_sysroot_deps(sysroot, &sysroot_crates)
+        }
+        None => Vec::new(),
+    };
+    let crate_id = crate_graph.add_crate_root(file_id, Edition::Edition2018);
+    crate_graph.set_cfg_options(crate_id, default_cfg_options.clone());
+    for (name, to) in sysroot_deps {
+        if let Err(_) = crate_graph.add_dep(crate_id, name.as_str().into(), to) {
+            log::error!("cyclic dependency on {} for a detached file", name)
+        }
+    }
+    crate_graph
+}
+
+/// Loads the Rust files the build script generated in `out_dir`, with their
+/// paths relative to it.
+fn load_out_dir(
+    out_dir: &Path,
+    load: &mut dyn FnMut(&Path) -> Option<FileId>,
+) -> Vec<(RelativePathBuf, FileId)> {
+    walkdir::WalkDir::new(out_dir)
+        .into_iter()
+        .filter_map(|it| it.ok())
+        .filter(|it| it.file_type().is_file() && it.path().extension() == Some("rs".as_ref()))
+        .filter_map(|it| {
+            let path = it.path().strip_prefix(out_dir).ok()?;
+            let path = RelativePathBuf::from_path(path).ok()?;
+            Some((path, load(it.path())?))
+        })
+        .collect()
+}
+
+/// Adds the crates of the sysroot to the crate graph, returning their ids.
+fn sysroot_to_crate_graph(
+    crate_graph: &mut CrateGraph,
+    sysroot: &Sysroot,
+    default_cfg_options: &CfgOptions,
+    load: &mut dyn FnMut(&Path) -> Option<FileId>,
+) -> FxHashMap<SysrootCrate, CrateId> {
+    let mut sysroot_crates = FxHashMap::default();
+    for krate in sysroot.crates() {
+        if let Some(file_id) = load(krate.root(&sysroot)) {
+            let crate_id = crate_graph.add_crate_root(file_id, Edition::Edition2015);
+            crate_graph.set_cfg_options(crate_id, default_cfg_options.clone());
+            sysroot_crates.insert(krate, crate_id);
+        }
+    }
+    for from in sysroot.crates() {
+        for to in from.deps(&sysroot) {
+            let name = to.name(&sysroot);
+            if let (Some(&from), Some(&to)) = (sysroot_crates.get(&from), sysroot_crates.get(&to)) {
+                if let Err(_) = crate_graph.add_dep(from, name.into(), to) {
+                    log::error!("cyclic dependency between sysroot crates")
+                }
+            }
+        }
+    }
+    sysroot_crates
+}
+
+/// The crates of the sysroot every crate depends on, with their names.
+fn public_sysroot_deps(
+    sysroot: &Sysroot,
+    sysroot_crates: &FxHashMap<SysrootCrate, CrateId>,
+) -> Vec<(String, CrateId)> {
+    sysroot
+        .public_deps()
+        .filter_map(|it| Some((it.name(sysroot).to_string(), *sysroot_crates.get(&it)?)))
+        .collect()
+}
+
+/// The cfg flags of the host target, like `unix` and `target_os = "linux"`.
+pub fn get_rustc_cfg_options() -> CfgOptions {
+    let mut cfg_options = CfgOptions::default();
+    match rustc_cfg_output() {
+        Ok(output) => {
+            for line in output.lines() {
+                insert_cfg(&mut cfg_options, line);
+            }
+        }
+        Err(e) => log::error!("failed to get the cfg flags of rustc: {}", e),
+    }
+    cfg_options
+}
+
+/// Parses a cfg flag as printed by `rustc --print cfg`, `foo` or `key="value"`.
+fn insert_cfg(cfg_options: &mut CfgOptions, cfg: &str) {
+    match cfg.find('=') {
+        None => cfg_options.insert_atom(cfg.trim().into()),
+        Some(idx) => {
+            let key = cfg[..idx].trim();
+            let value = cfg[idx + 1..].trim().trim_matches('"');
+            cfg_options.insert_key_value(key.into(), value.into());
+        }
+    }
+}
+
+fn rustc_cfg_output() -> Result<String> {
+    let output = Command::new("rustc").args(&["--print", "cfg"]).output()?;
+    if !output.status.success() {
+        bail!("rustc --print cfg exited with {}", output.status);
+    }
+    Ok(String::from_utf8(output.stdout)?)
 }
 
 fn find_rust_project_json(path: &Path) -> Option<PathBuf> {
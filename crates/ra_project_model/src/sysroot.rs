@@ -28,21 +28,40 @@ impl Sysroot {
         self.by_name("std")
     }
 
+    /// The crates of the sysroot which the crates of a workspace can use
+    /// without declaring them as dependencies.
+    pub fn public_deps<'a>(&'a self) -> impl Iterator<Item = SysrootCrate> + 'a {
+        PUBLIC_CRATES.iter().filter_map(move |&name| self.by_name(name))
+    }
+
     pub fn crates<'a>(&'a self) -> impl Iterator<Item = SysrootCrate> + 'a {
         self.crates.iter().map(|(id, _data)| id)
     }
 
+    /// Finds the sources of the standard library, either from `RUST_SRC_PATH`
+    /// or from the sysroot of the toolchain used in the workspace.
     pub fn discover(cargo_toml: &Path) -> Result<Sysroot> {
-        let rustc_output = Command::new("rustc")
-            .current_dir(cargo_toml.parent().unwrap())
-            .args(&["--print", "sysroot"])
-            .output()?;
-        if !rustc_output.status.success() {
-            failure::bail!("failed to locate sysroot")
-        }
-        let stdout = String::from_utf8(rustc_output.stdout)?;
-        let sysroot_path = Path::new(stdout.trim());
-        let src = sysroot_path.join("lib/rustlib/src/rust/src");
+        let src = match std::env::var_os("RUST_SRC_PATH") {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let rustc_output = Command::new("rustc")
+                    .current_dir(cargo_toml.parent().unwrap())
+                    .args(&["--print", "sysroot"])
+                    .output()?;
+                if !rustc_output.status.success() {
+                    failure::bail!("failed to locate sysroot")
+                }
+                let stdout = String::from_utf8(rustc_output.stdout)?;
+                let rust_src = Path::new(stdout.trim()).join("lib/rustlib/src/rust");
+                // Newer toolchains have the crates in `library` rather than in `src`.
+                let library = rust_src.join("library");
+                if library.exists() {
+                    library
+                } else {
+                    rust_src.join("src")
+                }
+            }
+        };
         if !src.exists() {
             failure::bail!(
                 "can't load standard library from sysroot\n\
@@ -51,26 +70,36 @@ impl Sysroot {
                 src,
             );
         }
+        Ok(Sysroot::load(&src))
+    }
 
+    /// Loads the crates of the standard library from `src`, the directory
+    /// with either `libstd/lib.rs` or `std/src/lib.rs` and so on.
+    pub fn load(src: &Path) -> Sysroot {
         let mut sysroot = Sysroot { crates: Arena::default() };
         for name in SYSROOT_CRATES.trim().lines() {
-            let root = src.join(format!("lib{}", name)).join("lib.rs");
-            if root.exists() {
+            let candidates = [
+                src.join(format!("lib{}", name)).join("lib.rs"),
+                src.join(name).join("src/lib.rs"),
+            ];
+            if let Some(root) = candidates.iter().find(|it| it.exists()) {
                 sysroot.crates.alloc(SysrootCrateData {
                     name: name.into(),
-                    root,
+                    root: root.clone(),
                     deps: Vec::new(),
                 });
             }
         }
-        if let Some(std) = sysroot.std() {
-            for dep in STD_DEPS.trim().lines() {
-                if let Some(dep) = sysroot.by_name(dep) {
-                    sysroot.crates[std].deps.push(dep)
+        for (krate, deps) in SYSROOT_DEPS {
+            if let Some(krate) = sysroot.by_name(krate) {
+                for dep in deps.trim().lines() {
+                    if let Some(dep) = sysroot.by_name(dep) {
+                        sysroot.crates[krate].deps.push(dep)
+                    }
                 }
             }
         }
-        Ok(sysroot)
+        sysroot
     }
 
     fn by_name(&self, name: &str) -> Option<SysrootCrate> {
@@ -120,6 +149,15 @@ rustc_msan
 rustc_tsan
 syntax";
 
+const PUBLIC_CRATES: &[&str] = &["core", "alloc", "std", "proc_macro", "test"];
+
+const SYSROOT_DEPS: &[(&str, &str)] = &[
+    ("std", STD_DEPS),
+    ("alloc", "core"),
+    ("proc_macro", "std"),
+    ("test", "std\nproc_macro\ngetopts\nterm"),
+];
+
 const STD_DEPS: &str = "
 alloc
 alloc_jemalloc
@@ -134,3 +172,42 @@ rustc_lsan
 rustc_msan
 rustc_tsan
 build_helper";
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn create_crate(src: &Path, path: &str) {
+        let path = src.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    fn dep_names(sysroot: &Sysroot, krate: &str) -> Vec<String> {
+        let krate = sysroot.by_name(krate).unwrap();
+        krate.deps(sysroot).map(|it| it.name(sysroot).to_string()).collect()
+    }
+
+    #[test]
+    fn loads_crates_in_both_layouts() {
+        for layout in &["lib{}/lib.rs", "{}/src/lib.rs"] {
+            let tmp_dir = TempDir::new().unwrap();
+            let src = tmp_dir.path();
+            for name in &["core", "alloc", "std", "proc_macro"] {
+                create_crate(src, &layout.replace("{}", name));
+            }
+            let sysroot = Sysroot::load(src);
+
+            let public: Vec<_> = sysroot.public_deps().map(|it| it.name(&sysroot)).collect();
+            assert_eq!(public, vec!["core", "alloc", "std", "proc_macro"]);
+            assert_eq!(dep_names(&sysroot, "std"), vec!["alloc", "core"]);
+            assert_eq!(dep_names(&sysroot, "alloc"), vec!["core"]);
+            assert_eq!(dep_names(&sysroot, "proc_macro"), vec!["std"]);
+            assert!(sysroot.std().unwrap().root(&sysroot).starts_with(src));
+        }
+    }
+}
@@ -181,3 +181,26 @@ where
     assert_eq!("for<'a> F", pred.type_ref().unwrap().syntax().text().to_string());
     assert_bound("Fn(&'a str)", bounds.next());
 }
+
+#[test]
+fn test_attr_as_path_list() {
+    let file = SourceFile::parse(
+        r#"
+#[derive(Debug, serde::Serialize , Clone,)]
+#[cfg(test)]
+#[cfg(any(test))]
+struct Foo;
+        "#,
+    );
+    let mut attrs = file.syntax().descendants().filter_map(Attr::cast);
+
+    let (name, paths) = attrs.next().unwrap().as_path_list().unwrap();
+    assert_eq!(name, "derive");
+    assert_eq!(paths, vec!["Debug", "serde::Serialize", "Clone"]);
+
+    let (name, paths) = attrs.next().unwrap().as_path_list().unwrap();
+    assert_eq!(name, "cfg");
+    assert_eq!(paths, vec!["test"]);
+
+    assert!(attrs.next().unwrap().as_path_list().is_none());
+}
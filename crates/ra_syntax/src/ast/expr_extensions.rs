@@ -193,6 +193,40 @@ impl ast::BinExpr {
     }
 }
 
+impl ast::IndexExpr {
+    pub fn base(&self) -> Option<&ast::Expr> {
+        children(self).nth(0)
+    }
+
+    pub fn index(&self) -> Option<&ast::Expr> {
+        children(self).nth(1)
+    }
+}
+
+impl ast::RangeExpr {
+    fn op_token(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(|it| it.as_token())
+            .find(|it| it.kind() == DOTDOT || it.kind() == DOTDOTEQ)
+    }
+
+    /// Whether the range includes its end, like `a..=b`.
+    pub fn is_inclusive(&self) -> bool {
+        self.op_token().map_or(false, |it| it.kind() == DOTDOTEQ)
+    }
+
+    pub fn start(&self) -> Option<&ast::Expr> {
+        let op = self.op_token()?;
+        children::<_, ast::Expr>(self).find(|it| it.syntax().range().end() <= op.range().start())
+    }
+
+    pub fn end(&self) -> Option<&ast::Expr> {
+        let op = self.op_token()?;
+        children::<_, ast::Expr>(self).find(|it| it.syntax().range().start() >= op.range().end())
+    }
+}
+
 pub enum ArrayExprKind<'a> {
     Repeat { initializer: Option<&'a ast::Expr>, repeat: Option<&'a ast::Expr> },
     ElementList(AstChildren<'a, ast::Expr>),
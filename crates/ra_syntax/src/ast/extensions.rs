@@ -27,7 +27,7 @@ impl ast::Attr {
             Some(tt) => tt,
         };
 
-        let prev = match tt.syntax().prev_sibling() {
+        let prev = match tt.syntax().prev_sibling_or_token() {
             None => return false,
             Some(prev) => prev,
         };
@@ -56,6 +56,29 @@ impl ast::Attr {
         }
     }
 
+    /// For an attribute taking a list of paths, like
+    /// `#[derive(Clone, serde::Serialize)]`, returns its name and the paths.
+    pub fn as_path_list(&self) -> Option<(SmolStr, Vec<String>)> {
+        let (name, args) = self.as_call()?;
+        let mut paths = Vec::new();
+        let mut path = String::new();
+        let mut tokens = args.syntax().children_with_tokens().skip(1).peekable();
+        while let Some(element) = tokens.next() {
+            if tokens.peek().is_none() {
+                // This is the closing delimiter.
+                break;
+            }
+            match element.kind() {
+                WHITESPACE | COMMENT => (),
+                COMMA => paths.push(std::mem::replace(&mut path, String::new())),
+                _ => path.push_str(element.as_token()?.text()),
+            }
+        }
+        paths.push(path);
+        paths.retain(|it| !it.is_empty());
+        Some((name, paths))
+    }
+
     pub fn as_named(&self) -> Option<SmolStr> {
         let tt = self.value()?;
         let attr = tt.syntax().children_with_tokens().nth(1)?;
@@ -65,6 +88,23 @@ impl ast::Attr {
             None
         }
     }
+
+    /// For an attribute like `#![recursion_limit = "128"]`, returns its name
+    /// and the unquoted value.
+    pub fn as_key_value(&self) -> Option<(SmolStr, SmolStr)> {
+        let tt = self.value()?;
+        let tokens: Vec<SyntaxElement> =
+            tt.syntax().children_with_tokens().filter(|it| !it.kind().is_trivia()).collect();
+        let (key, eq, value) = match tokens.as_slice() {
+            [_bra, key, eq, value, _ket] => (key.as_token()?, eq, value.as_token()?),
+            _ => return None,
+        };
+        if key.kind() != IDENT || eq.kind() != EQ || value.kind() != STRING {
+            return None;
+        }
+        let value = value.text().trim_matches('"').into();
+        Some((key.text().clone(), value))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -73,6 +113,11 @@ pub enum PathSegmentKind<'a> {
     SelfKw,
     SuperKw,
     CrateKw,
+    /// A qualified segment, like `<T as Trait>` in `<T as Trait>::Item`.
+    Type {
+        type_ref: Option<&'a ast::TypeRef>,
+        trait_ref: Option<&'a ast::PathType>,
+    },
 }
 
 impl ast::PathSegment {
@@ -91,6 +136,12 @@ impl ast::PathSegment {
                 SELF_KW => PathSegmentKind::SelfKw,
                 SUPER_KW => PathSegmentKind::SuperKw,
                 CRATE_KW => PathSegmentKind::CrateKw,
+                L_ANGLE => {
+                    let mut children = self.syntax().children();
+                    let type_ref = children.next().and_then(ast::TypeRef::cast);
+                    let trait_ref = children.next().and_then(ast::PathType::cast);
+                    PathSegmentKind::Type { type_ref, trait_ref }
+                }
                 _ => return None,
             }
         };
@@ -257,6 +308,38 @@ impl ast::BindPat {
     }
 }
 
+impl ast::BlockExpr {
+    pub fn is_unsafe(&self) -> bool {
+        self.syntax().children_with_tokens().any(|n| n.kind() == UNSAFE_KW)
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.syntax().children_with_tokens().any(|n| n.kind() == ASYNC_KW)
+    }
+}
+
+impl ast::LambdaExpr {
+    pub fn is_move(&self) -> bool {
+        self.syntax().children_with_tokens().any(|n| n.kind() == MOVE_KW)
+    }
+}
+
+impl ast::FnDef {
+    pub fn is_unsafe(&self) -> bool {
+        self.syntax().children_with_tokens().any(|n| n.kind() == UNSAFE_KW)
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.syntax().children_with_tokens().any(|n| n.kind() == ASYNC_KW)
+    }
+}
+
+impl ast::StaticDef {
+    pub fn is_mut(&self) -> bool {
+        self.syntax().children_with_tokens().any(|n| n.kind() == MUT_KW)
+    }
+}
+
 impl ast::PointerType {
     pub fn is_mut(&self) -> bool {
         self.syntax().children_with_tokens().any(|n| n.kind() == MUT_KW)
@@ -307,6 +307,10 @@ impl Block {
         super::children(self)
     }
 
+    pub fn items(&self) -> impl Iterator<Item = &ModuleItem> {
+        super::children(self)
+    }
+
     pub fn expr(&self) -> Option<&Expr> {
         super::child_opt(self)
     }
@@ -2303,6 +2307,10 @@ impl NamedFieldList {
     pub fn fields(&self) -> impl Iterator<Item = &NamedField> {
         super::children(self)
     }
+
+    pub fn spread(&self) -> Option<&Expr> {
+        super::child_opt(self)
+    }
 }
 
 // NeverType
@@ -2777,6 +2785,14 @@ impl PathSegment {
     pub fn type_arg_list(&self) -> Option<&TypeArgList> {
         super::child_opt(self)
     }
+
+    pub fn param_list(&self) -> Option<&ParamList> {
+        super::child_opt(self)
+    }
+
+    pub fn ret_type(&self) -> Option<&RetType> {
+        super::child_opt(self)
+    }
 }
 
 // PathType
@@ -3330,6 +3346,7 @@ impl ToOwned for SourceFile {
 
 impl ast::ModuleItemOwner for SourceFile {}
 impl ast::FnDefOwner for SourceFile {}
+impl ast::AttrsOwner for SourceFile {}
 impl SourceFile {
     pub fn modules(&self) -> impl Iterator<Item = &Module> {
         super::children(self)
@@ -3496,10 +3513,6 @@ impl StructLit {
     pub fn named_field_list(&self) -> Option<&NamedFieldList> {
         super::child_opt(self)
     }
-
-    pub fn spread(&self) -> Option<&Expr> {
-        super::child_opt(self)
-    }
 }
 
 // StructPat
@@ -4179,6 +4192,7 @@ impl ToOwned for UseItem {
 
 
 impl ast::AttrsOwner for UseItem {}
+impl ast::VisibilityOwner for UseItem {}
 impl UseItem {
     pub fn use_tree(&self) -> Option<&UseTree> {
         super::child_opt(self)
@@ -1,16 +1,17 @@
-use crate::{SourceFile, validation, TextUnit, TextRange, AstNode};
+use crate::{SourceFile, validation, TextUnit, TextRange, AstNode, Edition};
 use ra_text_edit::AtomTextEdit;
 use std::str::{self, FromStr};
 
-fn check_file_invariants(file: &SourceFile) {
+fn check_file_invariants(file: &SourceFile, text: &str) {
     let root = file.syntax();
+    validation::validate_tree_structure(root, text);
     validation::validate_block_structure(root);
     let _ = file.errors();
 }
 
 pub fn check_parser(text: &str) {
     let file = SourceFile::parse(text);
-    check_file_invariants(&file);
+    check_file_invariants(&file, text);
 }
 
 #[derive(Debug, Clone)]
@@ -45,9 +46,8 @@ impl CheckReparse {
 
     pub fn run(&self) {
         let file = SourceFile::parse(&self.text);
-        let new_file = file.reparse(&self.edit);
-        check_file_invariants(&new_file);
-        assert_eq!(&new_file.syntax().text().to_string(), &self.edited_text);
+        let new_file = file.reparse(&self.edit, Edition::Edition2018);
+        check_file_invariants(&new_file, &self.edited_text);
         let full_reparse = SourceFile::parse(&self.edited_text);
         for (a, b) in new_file.syntax().descendants().zip(full_reparse.syntax().descendants()) {
             if (a.kind(), a.range()) != (b.kind(), b.range()) {
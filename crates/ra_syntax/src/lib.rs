@@ -40,7 +40,7 @@ pub use crate::{
     syntax_text::SyntaxText,
     syntax_node::{Direction,  SyntaxNode, WalkEvent, TreeArc, SyntaxTreeBuilder, SyntaxElement, SyntaxToken},
     ptr::{SyntaxNodePtr, AstPtr},
-    parsing::{tokenize, classify_literal, Token},
+    parsing::{tokenize, classify_literal, Token, Edition},
 };
 
 use ra_text_edit::AtomTextEdit;
@@ -59,23 +59,32 @@ impl SourceFile {
         TreeArc::cast(root)
     }
 
+    /// Parses `text` as 2018 edition code.
     pub fn parse(text: &str) -> TreeArc<SourceFile> {
-        let (green, errors) = parsing::parse_text(text);
+        SourceFile::parse_with_edition(text, Edition::Edition2018)
+    }
+
+    pub fn parse_with_edition(text: &str, edition: Edition) -> TreeArc<SourceFile> {
+        let (green, errors) = parsing::parse_text(text, edition);
         SourceFile::new(green, errors)
     }
 
-    pub fn reparse(&self, edit: &AtomTextEdit) -> TreeArc<SourceFile> {
-        self.incremental_reparse(edit).unwrap_or_else(|| self.full_reparse(edit))
+    pub fn reparse(&self, edit: &AtomTextEdit, edition: Edition) -> TreeArc<SourceFile> {
+        self.incremental_reparse(edit, edition).unwrap_or_else(|| self.full_reparse(edit, edition))
     }
 
-    pub fn incremental_reparse(&self, edit: &AtomTextEdit) -> Option<TreeArc<SourceFile>> {
-        parsing::incremental_reparse(self.syntax(), edit, self.errors())
+    pub fn incremental_reparse(
+        &self,
+        edit: &AtomTextEdit,
+        edition: Edition,
+    ) -> Option<TreeArc<SourceFile>> {
+        parsing::incremental_reparse(self.syntax(), edit, self.errors(), edition)
             .map(|(green_node, errors, _reparsed_range)| SourceFile::new(green_node, errors))
     }
 
-    fn full_reparse(&self, edit: &AtomTextEdit) -> TreeArc<SourceFile> {
+    fn full_reparse(&self, edit: &AtomTextEdit, edition: Edition) -> TreeArc<SourceFile> {
         let text = edit.apply(self.syntax().text().to_string());
-        SourceFile::parse(&text)
+        SourceFile::parse_with_edition(&text, edition)
     }
 
     pub fn errors(&self) -> Vec<SyntaxError> {
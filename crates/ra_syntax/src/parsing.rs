@@ -15,10 +15,50 @@ pub use self::lexer::{tokenize, classify_literal, Token};
 
 pub(crate) use self::reparsing::incremental_reparse;
 
-pub(crate) fn parse_text(text: &str) -> (GreenNode, Vec<SyntaxError>) {
+/// The edition of Rust the code is written in. It decides which identifiers
+/// are keywords, like `async`, `dyn` and `try` in 2018.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edition {
+    Edition2018,
+    Edition2015,
+}
+
+impl Edition {
+    pub fn from_string(s: &str) -> Edition {
+        match s {
+            "2015" => Edition::Edition2015,
+            "2018" | _ => Edition::Edition2018,
+        }
+    }
+}
+
+pub(crate) fn parse_text(text: &str, edition: Edition) -> (GreenNode, Vec<SyntaxError>) {
     let tokens = tokenize(&text);
-    let token_source = text_token_source::TextTokenSource::new(text, &tokens);
+    let token_source = text_token_source::TextTokenSource::new(text, &tokens, edition);
     let mut tree_sink = text_tree_sink::TextTreeSink::new(text, &tokens);
     ra_parser::parse(&token_source, &mut tree_sink);
     tree_sink.finish()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{AstNode, SourceFile, SyntaxKind::*};
+
+    use super::Edition;
+
+    #[test]
+    fn edition_2015_keywords_are_identifiers() {
+        let text = "fn f(x: &dyn T, y: dyn::S) { let async = 1; let try = 2; }";
+        let kinds = |edition| {
+            SourceFile::parse_with_edition(text, edition)
+                .syntax()
+                .descendants_with_tokens()
+                .map(|it| it.kind())
+                .filter(|&it| it == DYN_TRAIT_TYPE || it == ASYNC_KW || it == TRY_KW)
+                .collect::<Vec<_>>()
+        };
+        assert!(SourceFile::parse_with_edition(text, Edition::Edition2015).errors().is_empty());
+        assert_eq!(kinds(Edition::Edition2015), vec![DYN_TRAIT_TYPE]);
+        assert_ne!(kinds(Edition::Edition2018), vec![DYN_TRAIT_TYPE]);
+    }
+}
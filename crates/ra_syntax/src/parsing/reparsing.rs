@@ -14,6 +14,7 @@ use crate::{
     algo,
     syntax_node::{GreenNode, SyntaxNode, GreenToken, SyntaxElement},
     parsing::{
+        Edition,
         text_token_source::TextTokenSource,
         text_tree_sink::TextTreeSink,
         lexer::{tokenize, Token},
@@ -24,12 +25,13 @@ pub(crate) fn incremental_reparse(
     node: &SyntaxNode,
     edit: &AtomTextEdit,
     errors: Vec<SyntaxError>,
+    edition: Edition,
 ) -> Option<(GreenNode, Vec<SyntaxError>, TextRange)> {
     if let Some((green, old_range)) = reparse_token(node, &edit) {
         return Some((green, merge_errors(errors, Vec::new(), old_range, edit), old_range));
     }
 
-    if let Some((green, new_errors, old_range)) = reparse_block(node, &edit) {
+    if let Some((green, new_errors, old_range)) = reparse_block(node, &edit, edition) {
         return Some((green, merge_errors(errors, new_errors, old_range, edit), old_range));
     }
     None
@@ -77,6 +79,7 @@ fn reparse_token<'node>(
 fn reparse_block<'node>(
     root: &'node SyntaxNode,
     edit: &AtomTextEdit,
+    edition: Edition,
 ) -> Option<(GreenNode, Vec<SyntaxError>, TextRange)> {
     let (node, reparser) = find_reparsable_node(root, edit.delete)?;
     let text = get_text_after_edit(node.into(), &edit);
@@ -84,7 +87,7 @@ fn reparse_block<'node>(
     if !is_balanced(&tokens) {
         return None;
     }
-    let token_source = TextTokenSource::new(&text, &tokens);
+    let token_source = TextTokenSource::new(&text, &tokens, edition);
     let mut tree_sink = TextTreeSink::new(&text, &tokens);
     reparser.parse(&token_source, &mut tree_sink);
     let (green, new_errors) = tree_sink.finish();
@@ -102,7 +105,7 @@ fn get_text_after_edit(element: SyntaxElement, edit: &AtomTextEdit) -> String {
 
 fn is_contextual_kw(text: &str) -> bool {
     match text {
-        "auto" | "default" | "union" => true,
+        "auto" | "default" | "union" | "dyn" => true,
         _ => false,
     }
 }
@@ -180,7 +183,7 @@ mod tests {
             let f = SourceFile::parse(&before);
             let edit = AtomTextEdit { delete: range, insert: replace_with.to_string() };
             let (green, new_errors, range) =
-                incremental_reparse(f.syntax(), &edit, f.errors()).unwrap();
+                incremental_reparse(f.syntax(), &edit, f.errors(), Edition::Edition2018).unwrap();
             assert_eq!(range.len(), reparsed_len.into(), "reparsed fragment has wrong length");
             SourceFile::new(green, new_errors)
         };
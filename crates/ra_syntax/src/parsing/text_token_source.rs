@@ -1,8 +1,8 @@
 use ra_parser::TokenSource;
 
 use crate::{
-    SyntaxKind, SyntaxKind::EOF, TextRange, TextUnit,
-    parsing::lexer::Token,
+    SyntaxKind, SyntaxKind::*, TextRange, TextUnit,
+    parsing::{Edition, lexer::Token},
 };
 
 pub(crate) struct TextTokenSource<'t> {
@@ -50,13 +50,19 @@ impl<'t> TokenSource for TextTokenSource<'t> {
 
 impl<'t> TextTokenSource<'t> {
     /// Generate input from tokens(expect comment and whitespace).
-    pub fn new(text: &'t str, raw_tokens: &'t [Token]) -> TextTokenSource<'t> {
+    /// Keywords which don't exist in `edition` are turned back into
+    /// identifiers.
+    pub fn new(text: &'t str, raw_tokens: &'t [Token], edition: Edition) -> TextTokenSource<'t> {
         let mut tokens = Vec::new();
         let mut start_offsets = Vec::new();
         let mut len = 0.into();
         for &token in raw_tokens.iter() {
             if !token.kind.is_trivia() {
-                tokens.push(token);
+                let kind = match token.kind {
+                    ASYNC_KW | DYN_KW | TRY_KW if edition == Edition::Edition2015 => IDENT,
+                    kind => kind,
+                };
+                tokens.push(Token { kind, ..token });
                 start_offsets.push(len);
             }
             len += token.len;
@@ -19,6 +19,12 @@ impl SyntaxNodePtr {
         SyntaxNodePtr { range: node.range(), kind: node.kind() }
     }
 
+    /// The pointer to the node of `kind` at `range`, like a pointer which
+    /// was saved with its parts.
+    pub fn from_parts(range: TextRange, kind: SyntaxKind) -> SyntaxNodePtr {
+        SyntaxNodePtr { range, kind }
+    }
+
     pub fn to_node(self, source_file: &SourceFile) -> &SyntaxNode {
         successors(Some(source_file.syntax()), |&node| {
             node.children().find(|it| self.range.is_subrange(&it.range()))
@@ -62,3 +62,35 @@ pub(crate) fn validate_block_structure(root: &SyntaxNode) {
         }
     }
 }
+
+/// Checks that the tree is a full-fidelity representation of `text`: the
+/// tree's text is the source text, and the children of each node cover its
+/// range, in order, without gaps or overlaps.
+pub(crate) fn validate_tree_structure(root: &SyntaxNode, text: &str) {
+    assert_eq!(
+        root.text().to_string(),
+        text,
+        "\nthe tree doesn't round-trip the text:\n{}\n",
+        root.debug_dump(),
+    );
+    for node in root.descendants() {
+        let mut offset = node.range().start();
+        for child in node.children_with_tokens() {
+            assert_eq!(
+                child.range().start(),
+                offset,
+                "\ngap or overlap between the children of {:?}\nfile:\n{}\n",
+                node,
+                root.debug_dump(),
+            );
+            offset = child.range().end();
+        }
+        assert_eq!(
+            offset,
+            node.range().end(),
+            "\nthe children of {:?} don't cover it\nfile:\n{}\n",
+            node,
+            root.debug_dump(),
+        );
+    }
+}
@@ -46,7 +46,14 @@ fn parser_tests() {
 
 #[test]
 fn parser_fuzz_tests() {
-    for (_, text) in collect_tests(&test_data_dir(), &["parser/fuzz-failures"]) {
+    let dirs = &[
+        "parser/fuzz-failures",
+        "parser/ok",
+        "parser/err",
+        "parser/inline/ok",
+        "parser/inline/err",
+    ];
+    for (_, text) in collect_tests(&test_data_dir(), dirs) {
         fuzz::check_parser(&text)
     }
 }
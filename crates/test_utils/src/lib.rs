@@ -113,6 +113,47 @@ pub struct FixtureEntry {
     pub text: String,
 }
 
+impl FixtureEntry {
+    /// The path of the file, the first word of the meta.
+    pub fn path(&self) -> &str {
+        self.meta.split_whitespace().next().unwrap_or("")
+    }
+
+    /// The name of the crate this file is the root of, given by `crate:name`.
+    pub fn crate_name(&self) -> Option<&str> {
+        self.attr("crate")
+    }
+
+    /// The names of the crates the crate of this file depends on, given by
+    /// `deps:foo,bar`. A crate used under another name is given as
+    /// `name=crate`.
+    pub fn deps(&self) -> Vec<&str> {
+        self.attr("deps").map_or(Vec::new(), |it| it.split(',').collect())
+    }
+
+    /// The edition of the crate of this file, given by `edition:2015`.
+    pub fn edition(&self) -> Option<&str> {
+        self.attr("edition")
+    }
+
+    /// The version of the package of the crate of this file, given by
+    /// `version:0.1.0`.
+    pub fn version(&self) -> Option<&str> {
+        self.attr("version")
+    }
+
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.meta.split_whitespace().skip(1).find_map(|it| {
+            let mut parts = it.splitn(2, ':');
+            if parts.next() == Some(key) {
+                parts.next()
+            } else {
+                None
+            }
+        })
+    }
+}
+
 /// Parses text which looks like this:
 ///
 ///  ```not_rust
@@ -121,6 +162,9 @@ pub struct FixtureEntry {
 ///  line 2
 ///  // - other meta
 ///  ```
+///
+/// The meta of a file is its path, optionally followed by the attributes of
+/// the crate it's the root of, like `//- /lib.rs crate:foo deps:bar,baz`.
 pub fn parse_fixture(fixture: &str) -> Vec<FixtureEntry> {
     let mut res = Vec::new();
     let mut buf = String::new();
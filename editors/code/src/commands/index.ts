@@ -3,10 +3,17 @@ import * as applySourceChange from './apply_source_change';
 import * as extendSelection from './extend_selection';
 import * as joinLines from './join_lines';
 import * as matchingBrace from './matching_brace';
+import * as memoryUsage from './memory_usage';
+import * as moveItem from './move_item';
 import * as onEnter from './on_enter';
+import * as openCargoToml from './open_cargo_toml';
 import * as parentModule from './parent_module';
+import * as profile from './profile';
+import * as resolveAssist from './resolve_assist';
 import * as runnables from './runnables';
+import * as ssr from './ssr';
 import * as syntaxTree from './syntaxTree';
+import * as typeOfSelection from './type_of_selection';
 
 export {
     analyzerStatus,
@@ -14,8 +21,15 @@ export {
     extendSelection,
     joinLines,
     matchingBrace,
+    memoryUsage,
+    moveItem,
+    openCargoToml,
     parentModule,
+    profile,
+    resolveAssist,
     runnables,
+    ssr,
     syntaxTree,
+    typeOfSelection,
     onEnter
 };
@@ -0,0 +1,16 @@
+import * as vscode from 'vscode';
+
+import { Server } from '../server';
+
+// Shows the memory retained by each query of the server. The values of the
+// queries are discarded to measure it, so they are recomputed afterwards.
+export async function handle() {
+    const usage = await Server.client.sendRequest<string>(
+        'rust-analyzer/memoryUsage',
+        null
+    );
+    const document = await vscode.workspace.openTextDocument({
+        content: usage
+    });
+    return vscode.window.showTextDocument(document, vscode.ViewColumn.Two);
+}
@@ -0,0 +1,43 @@
+import * as vscode from 'vscode';
+
+import { Position, TextDocumentIdentifier } from 'vscode-languageclient';
+import { Server } from '../server';
+import {
+    handle as applySourceChange,
+    SourceChange
+} from './apply_source_change';
+
+interface MoveItemParams {
+    textDocument: TextDocumentIdentifier;
+    position: Position;
+    direction: 'up' | 'down';
+}
+
+async function moveItem(direction: 'up' | 'down') {
+    const editor = vscode.window.activeTextEditor;
+    if (editor == null || editor.document.languageId !== 'rust') {
+        return;
+    }
+    const request: MoveItemParams = {
+        textDocument: { uri: editor.document.uri.toString() },
+        position: Server.client.code2ProtocolConverter.asPosition(
+            editor.selection.active
+        ),
+        direction
+    };
+    const change = await Server.client.sendRequest<SourceChange | null>(
+        'rust-analyzer/moveItem',
+        request
+    );
+    if (change) {
+        await applySourceChange(change);
+    }
+}
+
+export async function handleUp() {
+    await moveItem('up');
+}
+
+export async function handleDown() {
+    await moveItem('down');
+}
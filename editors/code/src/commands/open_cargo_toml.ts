@@ -0,0 +1,32 @@
+import * as vscode from 'vscode';
+
+import * as lc from 'vscode-languageclient';
+import { Server } from '../server';
+
+interface OpenCargoTomlParams {
+    textDocument: lc.TextDocumentIdentifier;
+}
+
+export async function handle() {
+    const editor = vscode.window.activeTextEditor;
+    if (editor == null || editor.document.languageId !== 'rust') {
+        return;
+    }
+    const request: OpenCargoTomlParams = {
+        textDocument: { uri: editor.document.uri.toString() }
+    };
+    const loc = await Server.client.sendRequest<lc.Location | null>(
+        'rust-analyzer/openCargoToml',
+        request
+    );
+    if (loc == null) {
+        return;
+    }
+    const uri = Server.client.protocol2CodeConverter.asUri(loc.uri);
+    const range = Server.client.protocol2CodeConverter.asRange(loc.range);
+
+    const doc = await vscode.workspace.openTextDocument(uri);
+    const e = await vscode.window.showTextDocument(doc);
+    e.selection = new vscode.Selection(range.start, range.start);
+    e.revealRange(range, vscode.TextEditorRevealType.InCenter);
+}
@@ -0,0 +1,31 @@
+import * as vscode from 'vscode';
+
+import { Server } from '../server';
+
+interface ProfileParams {
+    filter: string | null;
+}
+
+// Dumps the traces of the profiler recorded since the last call, and sets the
+// filter of the profiler, in the syntax of `RA_PROFILE`.
+export async function handle() {
+    const filter = await vscode.window.showInputBox({
+        prompt: 'Profiler filter, like `*>10` (empty to disable)',
+        value: '*>10'
+    });
+    if (filter === undefined) {
+        return;
+    }
+    const request: ProfileParams = { filter };
+    const traces = await Server.client.sendRequest<string[]>(
+        'rust-analyzer/profile',
+        request
+    );
+    if (traces.length === 0) {
+        return;
+    }
+    const document = await vscode.workspace.openTextDocument({
+        content: traces.join('\n')
+    });
+    return vscode.window.showTextDocument(document, vscode.ViewColumn.Two);
+}
@@ -0,0 +1,35 @@
+import * as vscode from 'vscode';
+
+import { Range, TextDocumentIdentifier } from 'vscode-languageclient';
+import { Server } from '../server';
+import {
+    handle as applySourceChange,
+    SourceChange
+} from './apply_source_change';
+
+export interface ResolveAssistParams {
+    textDocument: TextDocumentIdentifier;
+    range: Range;
+    id: string;
+    label: string;
+}
+
+export async function handle(params: ResolveAssistParams) {
+    const change = await Server.client.sendRequest<SourceChange | null>(
+        'rust-analyzer/resolveAssist',
+        params
+    );
+    if (change) {
+        await applySourceChange(change);
+    }
+}
+
+export async function handleSelect(...alternatives: ResolveAssistParams[]) {
+    const picked = await vscode.window.showQuickPick(
+        alternatives.map(it => it.label)
+    );
+    const params = alternatives.find(it => it.label === picked);
+    if (params) {
+        await handle(params);
+    }
+}
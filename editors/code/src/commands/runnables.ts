@@ -130,6 +130,36 @@ export async function handleSingle(runnable: Runnable) {
     return vscode.tasks.executeTask(task);
 }
 
+interface DebugConfiguration {
+    program: string;
+    args: string[];
+    env: { [index: string]: string };
+    cwd?: string;
+}
+
+/**
+ * Builds the program of a runnable with the language server and launches it
+ * in the debugger of the CodeLLDB extension.
+ */
+export async function handleDebugSingle(runnable: Runnable) {
+    const config = await Server.client.sendRequest<DebugConfiguration>(
+        'rust-analyzer/debugSingle',
+        runnable
+    );
+    const folder = vscode.workspace.workspaceFolders
+        ? vscode.workspace.workspaceFolders[0]
+        : undefined;
+    return vscode.debug.startDebugging(folder, {
+        type: 'lldb',
+        request: 'launch',
+        name: runnable.label || 'Debug',
+        program: config.program,
+        args: config.args,
+        env: config.env,
+        cwd: config.cwd || '.'
+    });
+}
+
 /**
  * Interactively asks the user whether we should run `cargo check` in order to
  * provide inline diagnostics; the user is met with a series of dialog boxes
@@ -0,0 +1,31 @@
+import * as vscode from 'vscode';
+
+import { Server } from '../server';
+import {
+    handle as applySourceChange,
+    SourceChange
+} from './apply_source_change';
+
+interface SsrParams {
+    query: string;
+}
+
+export async function handle() {
+    const query = await vscode.window.showInputBox({
+        prompt: 'Structural search replace rule, like `foo($a, $b) ==>> bar($b, $a)`',
+        placeHolder: 'foo($a) ==>> bar($a) where $a: kind(literal)'
+    });
+    if (!query) {
+        return;
+    }
+    const request: SsrParams = { query };
+    try {
+        const change = await Server.client.sendRequest<SourceChange>(
+            'rust-analyzer/ssr',
+            request
+        );
+        await applySourceChange(change);
+    } catch (err) {
+        vscode.window.showErrorMessage(err.message);
+    }
+}
@@ -0,0 +1,48 @@
+import * as vscode from 'vscode';
+
+import { Range, TextDocumentIdentifier } from 'vscode-languageclient';
+import { Server } from '../server';
+
+interface TypeOfSelectionParams {
+    textDocument: TextDocumentIdentifier;
+    range: Range;
+    qualified: boolean;
+}
+
+interface TypeOfSelectionResult {
+    range: Range;
+    type: string;
+}
+
+async function showTypeOfSelection(qualified: boolean) {
+    const editor = vscode.window.activeTextEditor;
+    if (editor == null || editor.document.languageId !== 'rust') {
+        return;
+    }
+    const request: TypeOfSelectionParams = {
+        textDocument: { uri: editor.document.uri.toString() },
+        range: Server.client.code2ProtocolConverter.asRange(editor.selection),
+        qualified
+    };
+    const response = await Server.client.sendRequest<TypeOfSelectionResult>(
+        'rust-analyzer/typeOfSelection',
+        request
+    );
+    if (response == null) {
+        vscode.window.showInformationMessage('No type for the selection');
+        return;
+    }
+    // Select the expression the type is of, which may be larger than the
+    // original selection.
+    const r = Server.client.protocol2CodeConverter.asRange(response.range);
+    editor.selection = new vscode.Selection(r.start, r.end);
+    vscode.window.showInformationMessage(response.type);
+}
+
+export async function handle() {
+    await showTypeOfSelection(false);
+}
+
+export async function handleQualified() {
+    await showTypeOfSelection(true);
+}
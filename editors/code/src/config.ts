@@ -15,9 +15,22 @@ export interface CargoWatchOptions {
 
 export class Config {
     public highlightingOn = true;
+    public rainbowHighlightingOn = false;
     public enableEnhancedTyping = true;
     public raLspServerPath = RA_LSP_DEBUG || 'ra_lsp_server';
     public showWorkspaceLoadedNotification = true;
+    public disabledDiagnostics: string[] = [];
+    public cargoCheckOnSave = false;
+    public cargoLoadOutDirsFromCheck = false;
+    public cargoCfgTest = true;
+    public cargoAllFeatures = true;
+    public cargoNoDefaultFeatures = false;
+    public cargoFeatures: string[] = [];
+    public completionAddCallParenthesis = true;
+    public completionEnablePostfix = true;
+    public rustfmtArgs: string[] = [];
+    public excludeDirs: string[] = [];
+    public symbolCacheDirectory: string | null = null;
     public cargoWatchOptions: CargoWatchOptions = {
         enableOnStartup: 'ask',
         trace: 'off',
@@ -45,6 +58,80 @@ export class Config {
             ) as boolean;
         }
 
+        if (config.has('disabledDiagnostics')) {
+            this.disabledDiagnostics = config.get(
+                'disabledDiagnostics'
+            ) as string[];
+        }
+
+        if (config.has('cargoCheckOnSave')) {
+            this.cargoCheckOnSave = config.get('cargoCheckOnSave') as boolean;
+        }
+
+        if (config.has('cargoLoadOutDirsFromCheck')) {
+            this.cargoLoadOutDirsFromCheck = config.get(
+                'cargoLoadOutDirsFromCheck'
+            ) as boolean;
+        }
+
+        if (config.has('cargoCfgTest')) {
+            this.cargoCfgTest = config.get('cargoCfgTest') as boolean;
+        }
+
+        if (config.has('cargoAllFeatures')) {
+            this.cargoAllFeatures = config.get('cargoAllFeatures') as boolean;
+        }
+
+        if (config.has('cargoNoDefaultFeatures')) {
+            this.cargoNoDefaultFeatures = config.get(
+                'cargoNoDefaultFeatures'
+            ) as boolean;
+        }
+
+        if (config.has('cargoFeatures')) {
+            this.cargoFeatures = config.get('cargoFeatures') as string[];
+        }
+
+        if (config.has('completionAddCallParenthesis')) {
+            this.completionAddCallParenthesis = config.get(
+                'completionAddCallParenthesis'
+            ) as boolean;
+        }
+
+        if (config.has('completionEnablePostfix')) {
+            this.completionEnablePostfix = config.get(
+                'completionEnablePostfix'
+            ) as boolean;
+        }
+
+        if (config.has('rustfmtArgs')) {
+            this.rustfmtArgs = config.get('rustfmtArgs') as string[];
+        }
+
+        if (config.has('excludeDirs')) {
+            this.excludeDirs = config.get('excludeDirs') as string[];
+        }
+        // The directories which VS Code doesn't watch aren't watched by the
+        // server either, which means that they can't be analyzed.
+        const watcherExclude = vscode.workspace
+            .getConfiguration('files')
+            .get('watcherExclude', {}) as { [glob: string]: boolean };
+        this.excludeDirs = this.excludeDirs.concat(
+            Object.keys(watcherExclude).filter(glob => watcherExclude[glob])
+        );
+
+        if (config.has('symbolCacheDirectory')) {
+            this.symbolCacheDirectory = config.get('symbolCacheDirectory') as
+                | string
+                | null;
+        }
+
+        if (config.has('rainbowHighlightingOn')) {
+            this.rainbowHighlightingOn = config.get(
+                'rainbowHighlightingOn'
+            ) as boolean;
+        }
+
         if (!this.highlightingOn && Server) {
             Server.highlighter.removeHighlights();
         }
@@ -102,5 +189,14 @@ export class Config {
                 ''
             );
         }
+
+        if (Server.client) {
+            Server.client.onReady().then(() =>
+                Server.client.sendNotification(
+                    'workspace/didChangeConfiguration',
+                    { settings: Server.serverConfig() }
+                )
+            );
+        }
     }
 }
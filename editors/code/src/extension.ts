@@ -61,15 +61,44 @@ export function activate(context: vscode.ExtensionContext) {
         'rust-analyzer.matchingBrace',
         commands.matchingBrace.handle
     );
+    registerCommand(
+        'rust-analyzer.typeOfSelection',
+        commands.typeOfSelection.handle
+    );
+    registerCommand(
+        'rust-analyzer.typeOfSelectionQualified',
+        commands.typeOfSelection.handleQualified
+    );
     registerCommand('rust-analyzer.joinLines', commands.joinLines.handle);
+    registerCommand('rust-analyzer.moveItemUp', commands.moveItem.handleUp);
+    registerCommand('rust-analyzer.moveItemDown', commands.moveItem.handleDown);
+    registerCommand('rust-analyzer.ssr', commands.ssr.handle);
     registerCommand('rust-analyzer.parentModule', commands.parentModule.handle);
+    registerCommand(
+        'rust-analyzer.openCargoToml',
+        commands.openCargoToml.handle
+    );
     registerCommand('rust-analyzer.run', commands.runnables.handle);
+    registerCommand('rust-analyzer.profile', commands.profile.handle);
+    registerCommand('rust-analyzer.memoryUsage', commands.memoryUsage.handle);
     // Unlike the above this does not send requests to the language server
     registerCommand('rust-analyzer.runSingle', commands.runnables.handleSingle);
+    registerCommand(
+        'rust-analyzer.debugSingle',
+        commands.runnables.handleDebugSingle
+    );
     registerCommand(
         'rust-analyzer.applySourceChange',
         commands.applySourceChange.handle
     );
+    registerCommand(
+        'rust-analyzer.resolveAssist',
+        commands.resolveAssist.handle
+    );
+    registerCommand(
+        'rust-analyzer.selectAndResolveAssist',
+        commands.resolveAssist.handleSelect
+    );
     registerCommand(
         'rust-analyzer.showReferences',
         (uri: string, position: lc.Position, locations: lc.Location[]) => {
@@ -93,7 +122,8 @@ export function activate(context: vscode.ExtensionContext) {
         [
             'rust-analyzer/publishDecorations',
             notifications.publishDecorations.handle
-        ]
+        ],
+        ['$/progress', notifications.progress.handle]
     ];
     const syntaxTreeContentProvider = new SyntaxTreeContentProvider();
 
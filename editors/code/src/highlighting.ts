@@ -6,6 +6,16 @@ import { Server } from './server';
 export interface Decoration {
     range: lc.Range;
     tag: string;
+    bindingHash?: string;
+}
+
+// The color of a binding for rainbow highlighting, derived from its hash
+function bindingColor(bindingHash: string): string {
+    let hue = 0;
+    for (const c of bindingHash) {
+        hue = (hue * 31 + c.charCodeAt(0)) % 360;
+    }
+    return `hsl(${hue}, 60%, 70%)`;
 }
 
 export class Highlighter {
@@ -29,7 +39,31 @@ export class Highlighter {
             ['text', decor('#DCDCCC')],
             ['attribute', decor('#BFEBBF')],
             ['literal', decor('#DFAF8F')],
-            ['macro', decor('#DFAF8F')]
+            ['macro', decor('#DFAF8F')],
+            [
+                'unsafe',
+                vscode.window.createTextEditorDecorationType({
+                    textDecoration: 'underline'
+                })
+            ],
+            [
+                'mutable',
+                vscode.window.createTextEditorDecorationType({
+                    textDecoration: 'underline'
+                })
+            ],
+            [
+                'consuming',
+                vscode.window.createTextEditorDecorationType({
+                    fontStyle: 'italic'
+                })
+            ],
+            [
+                'deprecated',
+                vscode.window.createTextEditorDecorationType({
+                    textDecoration: 'line-through'
+                })
+            ]
         ];
 
         return new Map<string, vscode.TextEditorDecorationType>(decorations);
@@ -40,7 +74,18 @@ export class Highlighter {
         vscode.TextEditorDecorationType
     > | null = null;
 
+    // The decorations of the bindings for rainbow highlighting, by color
+    private bindingDecorations: Map<
+        string,
+        vscode.TextEditorDecorationType
+    > = new Map();
+
     public removeHighlights() {
+        for (const decoration of this.bindingDecorations.values()) {
+            decoration.dispose();
+        }
+        this.bindingDecorations = new Map();
+
         if (this.decorations == null) {
             return;
         }
@@ -67,13 +112,27 @@ export class Highlighter {
             byTag.set(tag, []);
         }
 
+        const byColor: Map<string, vscode.Range[]> = new Map();
+        for (const color of this.bindingDecorations.keys()) {
+            byColor.set(color, []);
+        }
+
         for (const d of highlights) {
+            const range = Server.client.protocol2CodeConverter.asRange(
+                d.range
+            );
+            if (Server.config.rainbowHighlightingOn && d.bindingHash) {
+                const color = bindingColor(d.bindingHash);
+                if (!byColor.has(color)) {
+                    byColor.set(color, []);
+                }
+                byColor.get(color)!.push(range);
+                continue;
+            }
             if (!byTag.get(d.tag)) {
                 continue;
             }
-            byTag
-                .get(d.tag)!
-                .push(Server.client.protocol2CodeConverter.asRange(d.range));
+            byTag.get(d.tag)!.push(range);
         }
 
         for (const tag of byTag.keys()) {
@@ -83,5 +142,15 @@ export class Highlighter {
             const ranges = byTag.get(tag)!;
             editor.setDecorations(dec, ranges);
         }
+
+        for (const [color, ranges] of byColor) {
+            if (!this.bindingDecorations.has(color)) {
+                this.bindingDecorations.set(
+                    color,
+                    vscode.window.createTextEditorDecorationType({ color })
+                );
+            }
+            editor.setDecorations(this.bindingDecorations.get(color)!, ranges);
+        }
     }
 }
@@ -0,0 +1,48 @@
+import * as vscode from 'vscode';
+
+export interface ProgressParams {
+    token: string;
+    value: WorkDoneProgress;
+}
+
+export interface WorkDoneProgress {
+    kind: 'begin' | 'report' | 'end';
+    title?: string;
+    message?: string;
+    percentage?: number;
+}
+
+interface PendingProgress {
+    progress: vscode.Progress<{ message?: string }>;
+    done: () => void;
+}
+
+const pending = new Map<string, PendingProgress>();
+
+export function handle(params: ProgressParams) {
+    const value = params.value;
+    if (value.kind === 'begin') {
+        vscode.window.withProgress(
+            {
+                location: vscode.ProgressLocation.Window,
+                title: `rust-analyzer: ${value.title}`
+            },
+            progress =>
+                new Promise<void>(done => {
+                    pending.set(params.token, { progress, done });
+                    progress.report({ message: value.message });
+                })
+        );
+        return;
+    }
+    const current = pending.get(params.token);
+    if (!current) {
+        return;
+    }
+    if (value.kind === 'report') {
+        current.progress.report({ message: value.message });
+    } else {
+        current.done();
+        pending.delete(params.token);
+    }
+}
@@ -9,6 +9,28 @@ export class Server {
     public static config = new Config();
     public static client: lc.LanguageClient;
 
+    // The configuration of the server, sent again when it changes.
+    public static serverConfig() {
+        return {
+            publishDecorations: true,
+            workDoneProgress: true,
+            showWorkspaceLoaded: Server.config.showWorkspaceLoadedNotification,
+            disabledDiagnostics: Server.config.disabledDiagnostics,
+            cargoCheckOnSave: Server.config.cargoCheckOnSave,
+            cargoLoadOutDirsFromCheck: Server.config.cargoLoadOutDirsFromCheck,
+            cargoCfgTest: Server.config.cargoCfgTest,
+            cargoAllFeatures: Server.config.cargoAllFeatures,
+            cargoNoDefaultFeatures: Server.config.cargoNoDefaultFeatures,
+            cargoFeatures: Server.config.cargoFeatures,
+            completionAddCallParenthesis:
+                Server.config.completionAddCallParenthesis,
+            completionEnablePostfix: Server.config.completionEnablePostfix,
+            rustfmtArgs: Server.config.rustfmtArgs,
+            excludeDirs: Server.config.excludeDirs,
+            symbolCacheDirectory: Server.config.symbolCacheDirectory
+        };
+    }
+
     public static start(
         notificationHandlers: Iterable<[string, lc.GenericNotificationHandler]>
     ) {
@@ -32,11 +54,7 @@ export class Server {
         );
         const clientOptions: lc.LanguageClientOptions = {
             documentSelector: [{ scheme: 'file', language: 'rust' }],
-            initializationOptions: {
-                publishDecorations: true,
-                showWorkspaceLoaded:
-                    Server.config.showWorkspaceLoadedNotification
-            },
+            initializationOptions: Server.serverConfig(),
             traceOutputChannel
         };
 
@@ -78,6 +96,11 @@ export class Server {
             for (const [type, handler] of notificationHandlers) {
                 Server.client.onNotification(type, handler);
             }
+            // The progress is shown by the `$/progress` handler.
+            Server.client.onRequest(
+                'window/workDoneProgress/create',
+                () => null
+            );
         });
         Server.client.start();
     }
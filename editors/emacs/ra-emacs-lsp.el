@@ -37,7 +37,11 @@
 
 (defconst rust-analyzer--action-handlers
   '(("rust-analyzer.applySourceChange" .
-     (lambda (p) (rust-analyzer--apply-source-change-command p)))))
+     (lambda (p) (rust-analyzer--apply-source-change-command p)))
+    ("rust-analyzer.resolveAssist" .
+     (lambda (p) (rust-analyzer--resolve-assist-command p)))
+    ("rust-analyzer.selectAndResolveAssist" .
+     (lambda (p) (rust-analyzer--select-and-resolve-assist-command p)))))
 
 (defun rust-analyzer--uri-filename (text-document)
   (lsp--uri-to-path (gethash "uri" text-document)))
@@ -70,6 +74,22 @@
   (let ((data (-> p (ht-get "arguments") (seq-first))))
     (rust-analyzer--apply-source-change data)))
 
+;; Code actions for assists only carry the assist to apply, its source change
+;; is requested once it's picked.
+(defun rust-analyzer--resolve-assist (params)
+  (-when-let (data (lsp-send-request (lsp-make-request "rust-analyzer/resolveAssist" params)))
+    (rust-analyzer--apply-source-change data)))
+
+(defun rust-analyzer--resolve-assist-command (p)
+  (rust-analyzer--resolve-assist (-> p (ht-get "arguments") (seq-first))))
+
+(defun rust-analyzer--select-and-resolve-assist-command (p)
+  (let* ((alternatives (ht-get p "arguments"))
+         (labels (seq-map (lambda (it) (ht-get it "label")) alternatives))
+         (picked (completing-read "Assist: " labels nil t)))
+    (rust-analyzer--resolve-assist
+     (seq-find (lambda (it) (equal (ht-get it "label") picked)) alternatives))))
+
 (lsp-register-client
  (make-lsp-client
   :new-connection (lsp-stdio-connection (lambda () rust-analyzer-command))